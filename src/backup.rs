@@ -0,0 +1,354 @@
+#![cfg(feature = "backup")]
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::{
+    error::PCloudError, file_ops::ChecksumMismatch, pcloud_client::PCloudClient,
+    pcloud_model::PCloudResult,
+};
+
+/// Folder (below the root folder of the store) containing all blobs
+const BLOBS_FOLDER: &str = "blobs";
+/// Folder (below the root folder of the store) containing all snapshot manifests
+const SNAPSHOTS_FOLDER: &str = "snapshots";
+/// Format of the snapshot ids, sortable by time
+const SNAPSHOT_ID_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+
+/// Single file of a snapshot
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SnapshotEntry {
+    /// Path of the file relative to the backed up directory, always separated by '/'
+    pub path: String,
+    /// SHA-256 checksum (hex) of the content, name of the blob
+    pub hash: String,
+    /// Size of the file in bytes
+    pub size: u64,
+    /// Modification time of the local file
+    pub modified: Option<DateTime<Utc>>,
+}
+
+/// Manifest of a snapshot, stored as JSON file in pCloud
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Snapshot {
+    /// Id of the snapshot (creation time, sortable)
+    pub id: String,
+    /// Creation time of the snapshot
+    pub created: DateTime<Utc>,
+    /// All files of the snapshot
+    pub entries: Vec<SnapshotEntry>,
+}
+
+/// Result of `BackupStore::backup_directory()`
+#[derive(Debug, Clone)]
+pub struct BackupSummary {
+    /// Snapshot created
+    pub snapshot: Snapshot,
+    /// Number of blobs uploaded (content not yet present in the store)
+    pub blobs_uploaded: usize,
+    /// Number of bytes uploaded
+    pub bytes_uploaded: u64,
+}
+
+/// Content-addressable backup store in a pCloud folder. File contents are stored once as blobs named by their SHA-256 checksum (`blobs/ab/ab12...`),
+/// each snapshot is a manifest (`snapshots/<id>.json`) mapping the paths of the backed up directory to blobs. Snapshots are incremental, only content not yet present is uploaded.
+pub struct BackupStore {
+    /// Client to actually perform the requests
+    client: PCloudClient,
+    /// Path of the root folder of the store
+    root: String,
+    /// Id of the folder containing the blobs
+    blobs_folder_id: u64,
+    /// Id of the folder containing the snapshot manifests
+    snapshots_folder_id: u64,
+}
+
+#[allow(dead_code)]
+impl BackupStore {
+    pub(crate) async fn open(
+        client: &PCloudClient,
+        root: &str,
+    ) -> Result<BackupStore, PCloudError> {
+        let root = format!("/{}", root.trim_matches('/'));
+        let root_folder_id = ensure_folder(client, &root).await?;
+        let blobs_folder_id = create_folder(client, root_folder_id, BLOBS_FOLDER).await?;
+        let snapshots_folder_id = create_folder(client, root_folder_id, SNAPSHOTS_FOLDER).await?;
+
+        Ok(BackupStore {
+            client: client.clone(),
+            root,
+            blobs_folder_id,
+            snapshots_folder_id,
+        })
+    }
+
+    /// Path of the root folder of the store
+    pub fn root(&self) -> &str {
+        &self.root
+    }
+
+    /// Creates a new snapshot of all files in the given local directory (recursively). Only contents not yet present in the store are uploaded.
+    pub async fn backup_directory<P: AsRef<Path>>(
+        &self,
+        directory: P,
+    ) -> Result<BackupSummary, PCloudError> {
+        let directory = directory.as_ref();
+        let created = Utc::now();
+
+        // Shard folder name -> folder id, and the names of all blobs present
+        let mut shards: HashMap<String, u64> = HashMap::new();
+        let mut blobs: HashSet<String> = HashSet::new();
+        let listing = self
+            .client
+            .list_folder(self.blobs_folder_id)?
+            .recursive(true)
+            .get()
+            .await?;
+        for shard in listing.metadata.map(|m| m.contents).unwrap_or_default() {
+            if let (true, Some(folder_id)) = (shard.isfolder, shard.folderid) {
+                blobs.extend(shard.contents.into_iter().map(|b| b.name));
+                shards.insert(shard.name, folder_id);
+            }
+        }
+
+        let mut summary = BackupSummary {
+            snapshot: Snapshot {
+                id: created.format(SNAPSHOT_ID_FORMAT).to_string(),
+                created,
+                entries: Vec::new(),
+            },
+            blobs_uploaded: 0,
+            bytes_uploaded: 0,
+        };
+
+        for (path, relative_path) in local_files(directory).await? {
+            let (hash, size) = sha256_of_file(&path).await?;
+            let modified = tokio::fs::metadata(&path)
+                .await?
+                .modified()
+                .ok()
+                .map(DateTime::<Utc>::from);
+
+            if blobs.insert(hash.clone()) {
+                let shard = hash[..2].to_string();
+                let shard_folder_id = match shards.get(&shard) {
+                    Some(folder_id) => *folder_id,
+                    None => {
+                        let folder_id =
+                            create_folder(&self.client, self.blobs_folder_id, &shard).await?;
+                        shards.insert(shard, folder_id);
+                        folder_id
+                    }
+                };
+
+                debug!("Uploading blob {} of {}", hash, relative_path);
+                let file = tokio::fs::File::open(&path).await?;
+                self.client
+                    .upload_file_into_folder(shard_folder_id)?
+                    .with_file(&hash, file)
+                    .upload()
+                    .await?;
+                summary.blobs_uploaded += 1;
+                summary.bytes_uploaded += size;
+            }
+
+            summary.snapshot.entries.push(SnapshotEntry {
+                path: relative_path,
+                hash,
+                size,
+                modified,
+            });
+        }
+
+        self.client
+            .upload_file_into_folder(self.snapshots_folder_id)?
+            .with_file(
+                &format!("{}.json", summary.snapshot.id),
+                serde_json::to_vec_pretty(&summary.snapshot)?,
+            )
+            .upload()
+            .await?;
+        debug!(
+            "Created snapshot {} with {} files, {} blobs uploaded",
+            summary.snapshot.id,
+            summary.snapshot.entries.len(),
+            summary.blobs_uploaded
+        );
+
+        Ok(summary)
+    }
+
+    /// Ids of all snapshots, oldest first
+    pub async fn snapshots(&self) -> Result<Vec<String>, PCloudError> {
+        let listing = self
+            .client
+            .list_folder(self.snapshots_folder_id)?
+            .get()
+            .await?;
+        let mut ids: Vec<String> = listing
+            .metadata
+            .map(|m| m.contents)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|m| m.name.strip_suffix(".json").map(|id| id.to_string()))
+            .collect();
+        ids.sort();
+        Ok(ids)
+    }
+
+    /// Loads the manifest of the given snapshot
+    pub async fn load_snapshot(&self, id: &str) -> Result<Snapshot, PCloudError> {
+        let path = format!(
+            "{}/{}/{}.json",
+            self.root.trim_end_matches('/'),
+            SNAPSHOTS_FOLDER,
+            id
+        );
+        let content = self
+            .client
+            .download_file(path)
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        Ok(serde_json::from_slice(&content)?)
+    }
+
+    /// Restores all files of the given snapshot into the given local directory. Existing files are overwritten, the content of each file is verified against its checksum.
+    pub async fn restore<P: AsRef<Path>>(
+        &self,
+        id: &str,
+        directory: P,
+    ) -> Result<Snapshot, PCloudError> {
+        let snapshot = self.load_snapshot(id).await?;
+
+        for entry in &snapshot.entries {
+            let target = entry
+                .path
+                .split('/')
+                .fold(directory.as_ref().to_path_buf(), |p, c| p.join(c));
+            if let Some(parent) = target.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+
+            debug!("Restoring {} from blob {}", entry.path, entry.hash);
+            let blob = format!(
+                "{}/{}/{}/{}",
+                self.root.trim_end_matches('/'),
+                BLOBS_FOLDER,
+                &entry.hash[..2],
+                entry.hash
+            );
+            let mut content = self
+                .client
+                .download_file(blob)
+                .await?
+                .error_for_status()?
+                .bytes_stream();
+
+            let mut file = tokio::fs::File::create(&target).await?;
+            let mut hasher = Sha256::new();
+            while let Some(chunk) = content.next().await {
+                let chunk = chunk?;
+                hasher.update(&chunk);
+                file.write_all(&chunk).await?;
+            }
+            file.flush().await?;
+
+            let actual = format!("{:x}", hasher.finalize());
+            if actual != entry.hash {
+                debug!("Restored file {} is corrupt", entry.path);
+                Err(ChecksumMismatch {
+                    algorithm: "SHA-256".to_string(),
+                    expected: entry.hash.clone(),
+                    actual,
+                })?;
+            }
+        }
+
+        Ok(snapshot)
+    }
+}
+
+/// Creates the given folder (if not existing) in the parent folder and returns its id
+async fn create_folder(
+    client: &PCloudClient,
+    parent_folder_id: u64,
+    name: &str,
+) -> Result<u64, PCloudError> {
+    let stat = client
+        .create_folder(parent_folder_id, name)?
+        .execute()
+        .await?;
+    Ok(stat
+        .metadata
+        .and_then(|m| m.folderid)
+        .ok_or(PCloudResult::DirectoryDoesNotExist)?)
+}
+
+/// Creates all folders of the given path (if not existing) and returns the id of the last one
+async fn ensure_folder(client: &PCloudClient, path: &str) -> Result<u64, PCloudError> {
+    let mut folder_id = 0;
+    for name in path.split('/').filter(|n| !n.is_empty()) {
+        folder_id = create_folder(client, folder_id, name).await?;
+    }
+    Ok(folder_id)
+}
+
+/// All files in the given directory (recursively) with their path relative to the directory (separated by '/'), sorted by relative path
+async fn local_files(directory: &Path) -> Result<Vec<(PathBuf, String)>, PCloudError> {
+    let mut result = Vec::new();
+    let mut pending = vec![(directory.to_path_buf(), String::new())];
+    while let Some((dir, prefix)) = pending.pop() {
+        let mut entries = tokio::fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let relative_path = if prefix.is_empty() {
+                name
+            } else {
+                format!("{}/{}", prefix, name)
+            };
+            let file_type = entry.file_type().await?;
+            if file_type.is_dir() {
+                pending.push((entry.path(), relative_path));
+            } else if file_type.is_file() {
+                result.push((entry.path(), relative_path));
+            }
+        }
+    }
+    result.sort_by(|a, b| a.1.cmp(&b.1));
+    Ok(result)
+}
+
+/// Calculates the SHA-256 checksum (hex) and the size of the given file
+async fn sha256_of_file(path: &Path) -> Result<(String, u64), PCloudError> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    let mut size = 0;
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    }
+    Ok((format!("{:x}", hasher.finalize()), size))
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Opens the content-addressable backup store in the given folder (path), creating the necessary folders if not present
+    pub async fn open_backup_store(&self, root: &str) -> Result<BackupStore, PCloudError> {
+        BackupStore::open(self, root).await
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use futures::StreamExt;
+use log::debug;
+
+use crate::{
+    concurrency::AdaptiveConcurrency, error::PCloudError, pcloud_client::PCloudClient,
+    pcloud_model::DownloadLink,
+};
+
+/// Default number of download links requested in parallel
+pub const DEFAULT_LINK_CONCURRENCY: usize = 8;
+
+/// Download link or error of a single file
+pub type DownloadLinkResult = Result<DownloadLink, PCloudError>;
+
+/// Fetches the download links of many files with bounded concurrency, e.g. to prepare a whole folder listing for streaming
+pub struct DownloadLinksRequestBuilder {
+    /// Client to actually perform the requests
+    client: PCloudClient,
+    /// Ids of the files, without duplicates
+    file_ids: BTreeSet<u64>,
+    /// Maximum number of links requested in parallel
+    concurrency: usize,
+    /// Adapts the number of links requested in parallel, if set
+    adaptive: Option<AdaptiveConcurrency>,
+}
+
+#[allow(dead_code)]
+impl DownloadLinksRequestBuilder {
+    pub(crate) fn for_files<I: IntoIterator<Item = u64>>(
+        client: &PCloudClient,
+        file_ids: I,
+    ) -> DownloadLinksRequestBuilder {
+        DownloadLinksRequestBuilder {
+            client: client.clone(),
+            file_ids: file_ids.into_iter().collect(),
+            concurrency: DEFAULT_LINK_CONCURRENCY,
+            adaptive: None,
+        }
+    }
+
+    /// Maximum number of links requested in parallel (defaults to 8)
+    pub fn concurrency(mut self, value: usize) -> DownloadLinksRequestBuilder {
+        self.concurrency = value.max(1);
+        self
+    }
+
+    /// Adapts the number of links requested in parallel to the load of the server, up to the maximum of the controller (replaces `concurrency()`)
+    pub fn adaptive_concurrency(
+        mut self,
+        value: AdaptiveConcurrency,
+    ) -> DownloadLinksRequestBuilder {
+        self.concurrency = value.max_concurrency();
+        self.adaptive = Some(value);
+        self
+    }
+
+    /// Requests all links. Failures of single files don't abort the others, they are returned in the map as well.
+    pub async fn get(self) -> BTreeMap<u64, DownloadLinkResult> {
+        debug!(
+            "Requesting download links of {} files with concurrency {}",
+            self.file_ids.len(),
+            self.concurrency
+        );
+
+        let client = &self.client;
+        let adaptive = &self.adaptive;
+        let mut requests = futures::stream::iter(self.file_ids.iter().copied())
+            .map(|file_id| async move {
+                let permit = match adaptive {
+                    Some(adaptive) => Some(adaptive.acquire().await),
+                    None => None,
+                };
+                let link = match client.get_download_link_for_file(file_id) {
+                    Ok(request) => request.get().await,
+                    Err(e) => Err(e),
+                };
+                if let Some(permit) = permit {
+                    permit.complete(link.as_ref().err().map(|e| e as _));
+                }
+                (file_id, link)
+            })
+            .buffer_unordered(self.concurrency);
+
+        let mut links = BTreeMap::new();
+        while let Some((file_id, link)) = requests.next().await {
+            links.insert(file_id, link);
+        }
+        links
+    }
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Fetches the download links (`getfilelink`) of many files concurrently and returns them by file id, e.g. `pcloud.get_download_links(listing.contents.iter().filter_map(|m| m.fileid))`
+    pub fn get_download_links<I: IntoIterator<Item = u64>>(
+        &self,
+        file_ids: I,
+    ) -> DownloadLinksRequestBuilder {
+        DownloadLinksRequestBuilder::for_files(self, file_ids)
+    }
+}
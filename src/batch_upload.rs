@@ -0,0 +1,278 @@
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use bytes::Bytes;
+use futures::StreamExt;
+use log::debug;
+
+use crate::{
+    concurrency::AdaptiveConcurrency,
+    error::PCloudError,
+    file_ops::{UploadOutcome, UploadReport},
+    folder_ops::{FolderDescriptor, PCloudFolder},
+    pcloud_client::PCloudClient,
+    pcloud_model::{self, Metadata, PCloudResult},
+    progress::{ItemsOnly, ProgressObserver},
+};
+
+/// Default number of files uploaded in parallel
+pub const DEFAULT_UPLOAD_CONCURRENCY: usize = 4;
+
+/// Single file to upload with `PCloudClient::upload_files()`
+#[derive(Debug, Clone)]
+pub enum UploadSource {
+    /// Local file, streamed from disk. The name of the file is used as name in pCloud.
+    Path(PathBuf),
+    /// In-memory content with the given file name
+    Content { file_name: String, content: Bytes },
+}
+
+impl UploadSource {
+    /// Name of the file in pCloud
+    pub fn file_name(&self) -> String {
+        match self {
+            UploadSource::Path(path) => path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.display().to_string()),
+            UploadSource::Content { file_name, .. } => file_name.clone(),
+        }
+    }
+}
+
+impl From<PathBuf> for UploadSource {
+    fn from(value: PathBuf) -> Self {
+        UploadSource::Path(value)
+    }
+}
+
+impl From<&Path> for UploadSource {
+    fn from(value: &Path) -> Self {
+        UploadSource::Path(value.to_path_buf())
+    }
+}
+
+impl<S: Into<String>, B: Into<Bytes>> From<(S, B)> for UploadSource {
+    fn from((file_name, content): (S, B)) -> Self {
+        UploadSource::Content {
+            file_name: file_name.into(),
+            content: content.into(),
+        }
+    }
+}
+
+/// Failed upload of a single file
+#[derive(Debug)]
+pub struct UploadFailure {
+    /// Name of the file not uploaded
+    pub file_name: String,
+    /// Original error
+    pub error: PCloudError,
+}
+
+impl Display for UploadFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Upload of {} failed: {}", self.file_name, self.error)
+    }
+}
+
+impl std::error::Error for UploadFailure {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+pub struct UploadFilesRequestBuilder {
+    /// Client to actually perform the request
+    client: PCloudClient,
+    /// Target folder
+    target: PCloudFolder,
+    /// Files to upload
+    sources: Vec<UploadSource>,
+    /// Maximum number of files uploaded in parallel
+    concurrency: usize,
+    /// Adapts the number of files uploaded in parallel, if set
+    adaptive: Option<AdaptiveConcurrency>,
+    /// if set, the first failed upload aborts all others
+    fail_fast: bool,
+    /// if set, uploaded files will be renamed, if file with the requested name exists in the folder.
+    rename_if_exists: bool,
+    /// Receives the progress of the uploads
+    progress: Option<Arc<dyn ProgressObserver>>,
+}
+
+#[allow(dead_code)]
+impl UploadFilesRequestBuilder {
+    pub(crate) fn into_folder<
+        T: FolderDescriptor,
+        I: IntoIterator<Item = S>,
+        S: Into<UploadSource>,
+    >(
+        client: &PCloudClient,
+        folder_like: T,
+        sources: I,
+    ) -> Result<UploadFilesRequestBuilder, PCloudError> {
+        let f = folder_like.to_folder()?;
+
+        if !f.is_empty() {
+            Ok(UploadFilesRequestBuilder {
+                client: client.clone(),
+                target: f,
+                sources: sources.into_iter().map(|s| s.into()).collect(),
+                concurrency: DEFAULT_UPLOAD_CONCURRENCY,
+                adaptive: None,
+                fail_fast: false,
+                rename_if_exists: false,
+                progress: None,
+            })
+        } else {
+            Err(pcloud_model::PCloudResult::NoFullPathOrFolderIdProvided)?
+        }
+    }
+
+    /// Maximum number of files uploaded in parallel (defaults to 4)
+    pub fn concurrency(mut self, value: usize) -> UploadFilesRequestBuilder {
+        self.concurrency = value.max(1);
+        self
+    }
+
+    /// Adapts the number of files uploaded in parallel to the load of the server, up to the maximum of the controller (replaces `concurrency()`)
+    pub fn adaptive_concurrency(mut self, value: AdaptiveConcurrency) -> UploadFilesRequestBuilder {
+        self.concurrency = value.max_concurrency();
+        self.adaptive = Some(value);
+        self
+    }
+
+    /// If set, the first failed upload aborts all other uploads and is returned as error (`UploadFailure`). Otherwise all failures are collected in the report (default).
+    pub fn fail_fast(mut self, value: bool) -> UploadFilesRequestBuilder {
+        self.fail_fast = value;
+        self
+    }
+
+    ///  if set, uploaded files will be renamed, if file with the requested name exists in the folder.
+    pub fn rename_if_exists(mut self, value: bool) -> UploadFilesRequestBuilder {
+        self.rename_if_exists = value;
+        self
+    }
+
+    /// Reports the progress of the uploads, each file being an item
+    pub fn progress_observer(
+        mut self,
+        value: Arc<dyn ProgressObserver>,
+    ) -> UploadFilesRequestBuilder {
+        self.progress = Some(value);
+        self
+    }
+
+    /// Uploads all files, each with a separate request. The report contains the outcome of each file in the order given.
+    pub async fn upload(self) -> Result<UploadReport, PCloudError> {
+        debug!(
+            "Uploading {} files with concurrency {}",
+            self.sources.len(),
+            self.concurrency
+        );
+
+        let client = &self.client;
+        let target = &self.target;
+        let rename_if_exists = self.rename_if_exists;
+        let adaptive = &self.adaptive;
+        let items: Option<Arc<dyn ProgressObserver>> = self.progress.as_ref().map(|observer| {
+            observer.operation_started(Some(self.sources.len()), None);
+            ItemsOnly::forward(observer.clone())
+        });
+        let items = &items;
+        // Up to `concurrency` uploads are running, results are sorted into the order of the sources afterwards
+        let mut uploads = futures::stream::iter(self.sources.into_iter().enumerate())
+            .map(|(index, source)| async move {
+                let file_name = source.file_name();
+                let permit = match adaptive {
+                    Some(adaptive) => Some(adaptive.acquire().await),
+                    None => None,
+                };
+                let result =
+                    upload_source(client, target, source, rename_if_exists, items.clone()).await;
+                if let Some(permit) = permit {
+                    permit.complete(result.as_ref().err().map(|e| e as _));
+                }
+                (index, file_name, result)
+            })
+            .buffer_unordered(self.concurrency);
+
+        let mut outcomes = Vec::new();
+        while let Some((index, file_name, result)) = uploads.next().await {
+            match result {
+                Err(error) if self.fail_fast => {
+                    if let Some(observer) = &self.progress {
+                        observer.operation_finished();
+                    }
+                    return Err(UploadFailure { file_name, error })?;
+                }
+                Err(error) => {
+                    debug!("Upload of {} failed: {}", file_name, error);
+                    outcomes.push((
+                        index,
+                        UploadOutcome {
+                            file_name,
+                            result: Err(error),
+                        },
+                    ));
+                }
+                Ok(metadata) => outcomes.push((
+                    index,
+                    UploadOutcome {
+                        file_name,
+                        result: Ok(metadata),
+                    },
+                )),
+            }
+        }
+        if let Some(observer) = &self.progress {
+            observer.operation_finished();
+        }
+        outcomes.sort_by_key(|(index, _)| *index);
+        Ok(UploadReport {
+            outcomes: outcomes.into_iter().map(|(_, outcome)| outcome).collect(),
+        })
+    }
+}
+
+/// Uploads a single file
+async fn upload_source(
+    client: &PCloudClient,
+    target: &PCloudFolder,
+    source: UploadSource,
+    rename_if_exists: bool,
+    progress: Option<Arc<dyn ProgressObserver>>,
+) -> Result<Metadata, PCloudError> {
+    let mut request = client
+        .upload_file_into_folder(target)?
+        .rename_if_exists(rename_if_exists);
+    if let Some(progress) = progress {
+        request = request.progress_observer(progress);
+    }
+    let request = match source {
+        UploadSource::Path(path) => request.with_file_from_path(path).await?,
+        UploadSource::Content { file_name, content } => request.with_file(&file_name, content),
+    };
+    let uploaded = request.upload().await?;
+    Ok(uploaded
+        .into_metadata()?
+        .into_iter()
+        .next()
+        .ok_or(PCloudResult::InternalUploadError)?)
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Uploads multiple files (local paths or in-memory content) into the given folder with bounded concurrency and aggregates the results into a single report. Accepts either a folder id (u64), a folder path (String) or any other pCloud object describing a folder (like Metadata)
+    pub fn upload_files<T: FolderDescriptor, I: IntoIterator<Item = S>, S: Into<UploadSource>>(
+        &self,
+        folder_like: T,
+        sources: I,
+    ) -> Result<UploadFilesRequestBuilder, PCloudError> {
+        UploadFilesRequestBuilder::into_folder(self, folder_like, sources)
+    }
+}
@@ -0,0 +1,177 @@
+#![cfg(feature = "blocking")]
+use std::{future::Future, sync::Arc};
+
+use tokio::runtime::Runtime;
+
+use crate::{
+    error::PCloudError,
+    file_ops::{FileDescriptor, UploadReport},
+    folder_ops::FolderDescriptor,
+    pcloud_client::{self, PCloudClientBuilder},
+    pcloud_model::{FileOrFolderStat, FolderRecursivlyDeleted, TokenValidation, UserInfo},
+};
+
+/// Reduced blocking facade of the `PCloudClient` for CLI tools and build scripts that don't want to adopt async. All requests are executed on an internal runtime.
+/// It deliberately doesn't mirror the whole async surface: only the basic account, folder and file operations are available as blocking methods, with default options. For all other operations (or to set optional parameters) use the request builders of the async client (`client()`) and execute them with `block_on()`.
+/// Must not be used from within an async context.
+#[derive(Clone)]
+pub struct PCloudClient {
+    /// Async client, always present until dropped
+    inner: Option<pcloud_client::PCloudClient>,
+    /// Runtime executing all requests. Common for all copies of this PCloudClient
+    runtime: Arc<Runtime>,
+}
+
+impl Drop for PCloudClient {
+    /// The async client (and its session) must be dropped within the runtime
+    fn drop(&mut self) {
+        let _guard = self.runtime.enter();
+        self.inner.take();
+    }
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Creates a new PCloudClient instance with an already present OAuth 2.0 authentication token. Automatically determines nearest API server for best performance
+    pub fn with_oauth(host: &str, oauth2: &str) -> Result<PCloudClient, PCloudError> {
+        PCloudClient::from_builder(pcloud_client::PCloudClient::builder(host).with_oauth(oauth2))
+    }
+
+    /// Creates a new PCloudClient instance using username and password to obtain a temporary auth token. Token is shared between all clones of this instance and revoked when the last instance is dropped. Automatically determines nearest API server for best performance.
+    pub fn with_username_and_password(
+        host: &str,
+        username: &str,
+        password: &str,
+    ) -> Result<PCloudClient, PCloudError> {
+        PCloudClient::from_builder(
+            pcloud_client::PCloudClient::builder(host)
+                .with_username_and_password(username, password),
+        )
+    }
+
+    /// Creates a new PCloudClient instance from a configured builder
+    pub fn from_builder(builder: PCloudClientBuilder) -> Result<PCloudClient, PCloudError> {
+        // Multiple worker threads are necessary to perform the logout while dropping the session
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .enable_all()
+            .build()?;
+        let inner = runtime.block_on(builder.build())?;
+
+        Ok(PCloudClient {
+            inner: Some(inner),
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// The underlying async client, e.g. to create request builders executed with `block_on()`
+    pub fn client(&self) -> &pcloud_client::PCloudClient {
+        self.inner.as_ref().expect("Client already dropped")
+    }
+
+    /// Runs the given future (e.g. a request of the async client) to completion on the internal runtime
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    /// Get user info
+    pub fn get_user_info(&self) -> Result<UserInfo, PCloudError> {
+        self.block_on(self.client().get_user_info())
+    }
+
+    /// Cheap API roundtrip to health-check the connectivity to pCloud. Returns the latency of the roundtrip.
+    pub fn ping(&self) -> Result<std::time::Duration, PCloudError> {
+        self.block_on(self.client().ping())
+    }
+
+    /// Checks if the token is still accepted by pCloud
+    pub fn validate_token(&self) -> Result<TokenValidation, PCloudError> {
+        self.block_on(self.client().validate_token())
+    }
+
+    /// Lists the content of a folder. Accepts either a folder id (u64), a folder path (String) or any other pCloud object describing a folder (like Metadata)
+    pub fn list_folder<T: FolderDescriptor>(
+        &self,
+        folder_like: T,
+    ) -> Result<FileOrFolderStat, PCloudError> {
+        let request = self.client().list_folder(folder_like)?;
+        self.block_on(request.get())
+    }
+
+    /// Creates a new folder in a parent folder. Accepts either a folder id (u64), a folder path (String) or any other pCloud object describing a folder (like Metadata)
+    pub fn create_folder<T: FolderDescriptor>(
+        &self,
+        parent_folder_like: T,
+        name: &str,
+    ) -> Result<FileOrFolderStat, PCloudError> {
+        let request = self.client().create_folder(parent_folder_like, name)?;
+        self.block_on(request.execute())
+    }
+
+    /// Deletes a folder recursively. Accepts either a folder id (u64), a folder path (String) or any other pCloud object describing a folder (like Metadata)
+    pub fn delete_folder_recursive<T: FolderDescriptor>(
+        &self,
+        folder_like: T,
+    ) -> Result<FolderRecursivlyDeleted, PCloudError> {
+        let request = self.client().delete_folder(folder_like)?;
+        self.block_on(request.delete_recursive())
+    }
+
+    /// Returns the metadata of a file. Accepts either a file id (u64), a file path (String) or any other pCloud object describing a file (like Metadata)
+    pub fn get_file_metadata<T: FileDescriptor>(
+        &self,
+        file_like: T,
+    ) -> Result<FileOrFolderStat, PCloudError> {
+        self.block_on(self.client().get_file_metadata(file_like))
+    }
+
+    /// Deletes a file. Accepts either a file id (u64), a file path (String) or any other pCloud object describing a file (like Metadata)
+    pub fn delete_file<T: FileDescriptor>(
+        &self,
+        file_like: T,
+    ) -> Result<FileOrFolderStat, PCloudError> {
+        self.block_on(self.client().delete_file(file_like))
+    }
+
+    /// Uploads a single file with the given name and content into the folder. Accepts either a folder id (u64), a folder path (String) or any other pCloud object describing a folder (like Metadata)
+    pub fn upload_file<T: FolderDescriptor, B: Into<reqwest::Body>>(
+        &self,
+        folder_like: T,
+        file_name: &str,
+        content: B,
+    ) -> Result<UploadReport, PCloudError> {
+        let request = self
+            .client()
+            .upload_file_into_folder(folder_like)?
+            .with_file(file_name, content);
+        self.block_on(request.upload())
+    }
+
+    /// Downloads the latest revision of a file into memory. Accepts either a file id (u64), a file path (String) or any other pCloud object describing a file (like Metadata)
+    pub fn download_file<T: FileDescriptor>(&self, file_like: T) -> Result<Vec<u8>, PCloudError> {
+        self.block_on(async {
+            let response = self.client().download_file(file_like).await?;
+            Ok(response.error_for_status()?.bytes().await?.to_vec())
+        })
+    }
+
+    /// Copies the given file to the given folder
+    pub fn copy_file<S: FileDescriptor, T: FolderDescriptor>(
+        &self,
+        file_like: S,
+        target_folder_like: T,
+    ) -> Result<FileOrFolderStat, PCloudError> {
+        let request = self.client().copy_file(file_like, target_folder_like)?;
+        self.block_on(request.execute())
+    }
+
+    /// Moves the given file to the given folder
+    pub fn move_file<S: FileDescriptor, T: FolderDescriptor>(
+        &self,
+        file_like: S,
+        target_folder_like: T,
+    ) -> Result<FileOrFolderStat, PCloudError> {
+        let request = self.client().move_file(file_like, target_folder_like)?;
+        self.block_on(request.execute())
+    }
+}
@@ -0,0 +1,137 @@
+use std::{
+    ops::{Deref, DerefMut},
+    sync::{Arc, Mutex},
+};
+
+use bytes::{BufMut, Bytes, BytesMut};
+use futures::{Stream, StreamExt};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Default size of the chunks read from local files
+pub(crate) const DEFAULT_TRANSFER_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Maximum number of idle buffers kept for reuse
+const MAX_IDLE_BUFFERS: usize = 32;
+
+/// Pool of buffers shared by the streaming adapters of a client, so concurrent transfers don't allocate new chunk buffers for every file. All clones share the same buffers.
+#[derive(Clone)]
+pub(crate) struct BufferPool {
+    /// Size of the chunks read into the buffers
+    chunk_size: usize,
+    /// Buffers currently not in use
+    idle: Arc<Mutex<Vec<BytesMut>>>,
+}
+
+impl BufferPool {
+    pub(crate) fn new(chunk_size: usize) -> BufferPool {
+        BufferPool {
+            chunk_size: chunk_size.max(1),
+            idle: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Size of the chunks read into the buffers
+    pub(crate) fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Takes an empty buffer from the pool (or allocates a new one). It returns into the pool when dropped.
+    pub(crate) fn take(&self) -> PooledBuffer {
+        let buffer = self
+            .idle
+            .lock()
+            .unwrap()
+            .pop()
+            .unwrap_or_else(|| BytesMut::with_capacity(self.chunk_size));
+        PooledBuffer {
+            buffer,
+            pool: self.clone(),
+        }
+    }
+
+    /// Returns the buffer into the pool, unless enough buffers are idle
+    fn give_back(&self, mut buffer: BytesMut) {
+        buffer.clear();
+        let mut idle = self.idle.lock().unwrap();
+        if idle.len() < MAX_IDLE_BUFFERS {
+            idle.push(buffer);
+        }
+    }
+
+    /// Reads the given file (or any other reader) as stream of chunks, using a buffer of the pool. The allocation is reclaimed for the next chunk once the previous ones are dropped.
+    pub(crate) fn file_stream<R: AsyncRead + Unpin>(
+        &self,
+        file: R,
+    ) -> impl Stream<Item = Result<Bytes, std::io::Error>> {
+        let chunk_size = self.chunk_size;
+        futures::stream::unfold(Some((file, self.take())), move |state| async move {
+            let (mut file, mut buffer) = state?;
+            buffer.reserve(chunk_size);
+            match file.read_buf(&mut (&mut *buffer).limit(chunk_size)).await {
+                Ok(0) => None,
+                Ok(_) => Some((Ok(buffer.split().freeze()), Some((file, buffer)))),
+                // Stop reading after the first error
+                Err(e) => Some((Err(e), None)),
+            }
+        })
+    }
+
+    /// Collects the given stream (like the body of a download) into chunks of the configured size, using a buffer of the pool. Small network frames are coalesced before they are hashed and written. The allocation is reclaimed for the next chunk once the previous ones are dropped.
+    pub(crate) fn rechunk<S, E>(&self, stream: S) -> impl Stream<Item = Result<Bytes, E>>
+    where
+        S: Stream<Item = Result<Bytes, E>>,
+    {
+        let chunk_size = self.chunk_size;
+        let state = (Box::pin(stream.fuse()), Bytes::new(), self.take());
+        futures::stream::unfold(Some(state), move |state| async move {
+            let (mut stream, mut pending, mut buffer) = state?;
+            buffer.reserve(chunk_size);
+            loop {
+                let size = (chunk_size - buffer.len()).min(pending.len());
+                buffer.extend_from_slice(&pending.split_to(size));
+                if buffer.len() == chunk_size {
+                    let chunk = buffer.split().freeze();
+                    return Some((Ok(chunk), Some((stream, pending, buffer))));
+                }
+                match stream.next().await {
+                    Some(Ok(chunk)) => pending = chunk,
+                    // Stop after the first error
+                    Some(Err(e)) => return Some((Err(e), None)),
+                    None if buffer.is_empty() => return None,
+                    None => {
+                        let chunk = buffer.split().freeze();
+                        return Some((Ok(chunk), Some((stream, pending, buffer))));
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Buffer taken from a BufferPool, returned into it when dropped
+pub(crate) struct PooledBuffer {
+    /// Buffer itself
+    buffer: BytesMut,
+    /// Pool to return the buffer to
+    pool: BufferPool,
+}
+
+impl Deref for PooledBuffer {
+    type Target = BytesMut;
+
+    fn deref(&self) -> &Self::Target {
+        &self.buffer
+    }
+}
+
+impl DerefMut for PooledBuffer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.buffer
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        self.pool.give_back(std::mem::take(&mut self.buffer));
+    }
+}
@@ -0,0 +1,432 @@
+use std::{
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use bytes::{Bytes, BytesMut};
+use futures::{future::BoxFuture, ready, FutureExt, Sink, SinkExt};
+use log::{debug, warn};
+use reqwest::Body;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::PCloudError,
+    folder_ops::FolderDescriptor,
+    pcloud_client::PCloudClient,
+    pcloud_model::{FileOrFolderStat, PCloudResult, UploadCreated, UploadInfo, UploadWritten},
+    rate_limit::limit,
+};
+
+/// Default size of the chunks written by the UploadSink (1 MiB)
+pub const DEFAULT_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Persisted progress of a chunked upload
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UploadState {
+    /// Id of the upload
+    pub upload_id: u64,
+    /// Number of bytes written so far
+    pub offset: u64,
+}
+
+/// Storage of the progress of chunked uploads, so uploads can be resumed after a process restart (see `PCloudClient::resume_upload()`)
+pub trait UploadStateStore: Send + Sync {
+    /// Loads the state stored for the given key
+    fn load(&self, key: &str) -> Result<Option<UploadState>, PCloudError>;
+
+    /// Stores the state for the given key
+    fn save(&self, key: &str, state: &UploadState) -> Result<(), PCloudError>;
+
+    /// Removes the state of the given key (once the upload is finished)
+    fn remove(&self, key: &str) -> Result<(), PCloudError>;
+}
+
+/// UploadStateStore keeping one small JSON file per upload in a directory
+#[derive(Debug, Clone)]
+pub struct FileUploadStateStore {
+    directory: PathBuf,
+}
+
+impl FileUploadStateStore {
+    /// Stores the upload states in the given directory (created if necessary)
+    pub fn new<P: AsRef<Path>>(directory: P) -> FileUploadStateStore {
+        FileUploadStateStore {
+            directory: directory.as_ref().to_path_buf(),
+        }
+    }
+
+    /// File containing the state of the given key
+    fn state_file(&self, key: &str) -> PathBuf {
+        let name: String = key
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.directory.join(format!("{}.json", name))
+    }
+}
+
+impl UploadStateStore for FileUploadStateStore {
+    fn load(&self, key: &str) -> Result<Option<UploadState>, PCloudError> {
+        let file = self.state_file(key);
+        if !file.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_slice(&std::fs::read(file)?)?))
+    }
+
+    fn save(&self, key: &str, state: &UploadState) -> Result<(), PCloudError> {
+        std::fs::create_dir_all(&self.directory)?;
+        std::fs::write(self.state_file(key), serde_json::to_vec(state)?)?;
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> Result<(), PCloudError> {
+        let file = self.state_file(key);
+        if file.exists() {
+            std::fs::remove_file(file)?;
+        }
+        Ok(())
+    }
+}
+
+/// In-progress chunked upload. Data is written with `upload_write` and finally stored as file with `upload_save`.
+/// Created with `PCloudClient::create_upload()`, or resumable across restarts with `PCloudClient::resume_upload()`.
+/// see https://docs.pcloud.com/methods/upload/
+pub struct ChunkedUpload {
+    /// Client to actually perform the requests
+    client: PCloudClient,
+    /// Id of the upload
+    upload_id: u64,
+    /// Number of bytes written so far
+    offset: u64,
+    /// Key and store to persist the progress, if resumable
+    state_store: Option<(String, Arc<dyn UploadStateStore>)>,
+}
+
+/// Chunked upload persisting its progress in an `UploadStateStore`, as returned by `PCloudClient::resume_upload()`
+pub type ResumableUpload = ChunkedUpload;
+
+#[allow(dead_code)]
+impl ChunkedUpload {
+    /// Id of the upload
+    pub fn upload_id(&self) -> u64 {
+        self.upload_id
+    }
+
+    /// Number of bytes written so far
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Appends the data to the upload
+    /// see https://docs.pcloud.com/methods/upload/upload_write.html
+    pub async fn write<T: Into<Bytes>>(&mut self, data: T) -> Result<(), PCloudError> {
+        self.write_at(self.offset, data).await
+    }
+
+    /// Writes the data at the given offset of the upload, e.g. to repeat a chunk which failed. The offset of the upload is moved to the end of the data, if beyond.
+    /// see https://docs.pcloud.com/methods/upload/upload_write.html
+    pub async fn write_at<T: Into<Bytes>>(
+        &mut self,
+        offset: u64,
+        data: T,
+    ) -> Result<(), PCloudError> {
+        let data: Bytes = data.into();
+        let len = data.len() as u64;
+
+        let mut r = self
+            .client
+            .client()
+            .put(format!("{}/upload_write", self.client.api_host()));
+
+        r = r.query(&[("uploadid", self.upload_id)]);
+        r = r.query(&[("uploadoffset", offset)]);
+        r = match self.client.rate_limiter().bandwidth() {
+            Some(limiter) => {
+                let chunk = futures::stream::once(async move { Ok::<_, std::io::Error>(data) });
+                r.body(Body::wrap_stream(limit(chunk, limiter)))
+            }
+            None => r.body(data),
+        };
+
+        r = self.client.add_token(r);
+
+        self.client.send_checked::<UploadWritten>(r).await?;
+
+        debug!(
+            "Wrote {} bytes at offset {} to upload {}",
+            len, offset, self.upload_id
+        );
+        self.offset = self.offset.max(offset.saturating_add(len));
+
+        if let Some((key, store)) = &self.state_store {
+            store.save(
+                key,
+                &UploadState {
+                    upload_id: self.upload_id,
+                    offset: self.offset,
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Removes the persisted progress of a finished upload
+    fn remove_state(&self) {
+        if let Some((key, store)) = &self.state_store {
+            if let Err(e) = store.remove(key) {
+                warn!("Failed to remove state of upload {}: {}", key, e);
+            }
+        }
+    }
+
+    /// Stores the uploaded data as file with the given name in the given folder
+    /// see https://docs.pcloud.com/methods/upload/upload_save.html
+    pub async fn save<T: FolderDescriptor>(
+        self,
+        folder_like: T,
+        name: &str,
+    ) -> Result<FileOrFolderStat, PCloudError> {
+        let folder = folder_like.to_folder()?;
+
+        let mut r = self
+            .client
+            .client()
+            .get(format!("{}/upload_save", self.client.api_host()));
+
+        r = r.query(&[("uploadid", self.upload_id)]);
+        r = r.query(&[("name", name)]);
+
+        if let Some(v) = folder.folder_id {
+            r = r.query(&[("folderid", v)]);
+        } else if let Some(v) = folder.path {
+            r = r.query(&[("path", v)]);
+        } else {
+            Err(PCloudResult::NoFullPathOrFolderIdProvided)?;
+        }
+
+        r = self.client.add_token(r);
+
+        let result = self.client.send_checked::<FileOrFolderStat>(r).await?;
+        self.remove_state();
+        Ok(result)
+    }
+
+    /// Discards the upload
+    /// see https://docs.pcloud.com/methods/upload/upload_delete.html
+    pub async fn delete(self) -> Result<(), PCloudError> {
+        let mut r = self
+            .client
+            .client()
+            .get(format!("{}/upload_delete", self.client.api_host()));
+
+        r = r.query(&[("uploadid", self.upload_id)]);
+
+        r = self.client.add_token(r);
+
+        self.client.send_checked::<UploadWritten>(r).await?;
+        self.remove_state();
+        Ok(())
+    }
+
+    /// Converts the upload into a `Sink<Bytes>`, e.g. to stream incrementally produced data with standard combinators
+    pub fn into_sink(self) -> UploadSink {
+        UploadSink {
+            upload: Some(self),
+            buffer: BytesMut::new(),
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            pending: None,
+        }
+    }
+}
+
+/// Pending write of the UploadSink. Hands back the upload once finished.
+type PendingWrite = BoxFuture<'static, Result<ChunkedUpload, PCloudError>>;
+
+/// `Sink<Bytes>` handle of a chunked upload. Incoming data is buffered and written in chunks of `chunk_size` bytes.
+/// Once all data is sent, finish the upload with `save()`.
+pub struct UploadSink {
+    /// Upload, if no write is pending
+    upload: Option<ChunkedUpload>,
+    /// Data not yet written
+    buffer: BytesMut,
+    /// Size of the chunks written
+    chunk_size: usize,
+    /// Currently running write
+    pending: Option<PendingWrite>,
+}
+
+#[allow(dead_code)]
+impl UploadSink {
+    /// Size of the chunks written (default 1 MiB)
+    pub fn chunk_size(mut self, value: usize) -> UploadSink {
+        self.chunk_size = value.max(1);
+        self
+    }
+
+    /// Flushes all buffered data and stores the upload as file with the given name in the given folder
+    pub async fn save<T: FolderDescriptor>(
+        mut self,
+        folder_like: T,
+        name: &str,
+    ) -> Result<FileOrFolderStat, PCloudError> {
+        self.close().await?;
+        let upload = self
+            .upload
+            .take()
+            .ok_or(PCloudResult::InternalUploadError)?;
+        upload.save(folder_like, name).await
+    }
+
+    /// Starts writing the given chunk
+    fn start_write(&mut self, chunk: Bytes) {
+        let mut upload = self.upload.take().expect("No write pending");
+        self.pending = Some(
+            async move {
+                upload.write(chunk).await?;
+                Ok(upload)
+            }
+            .boxed(),
+        );
+    }
+
+    /// Drives the pending write (if any) to completion
+    fn poll_pending(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), PCloudError>> {
+        if let Some(pending) = self.pending.as_mut() {
+            let result = ready!(pending.as_mut().poll(cx));
+            self.pending = None;
+            self.upload = Some(result?);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Sink<Bytes> for UploadSink {
+    type Error = PCloudError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        ready!(this.poll_pending(cx))?;
+
+        if this.buffer.len() >= this.chunk_size {
+            let chunk = this.buffer.split_to(this.chunk_size).freeze();
+            this.start_write(chunk);
+            ready!(this.poll_pending(cx))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Bytes) -> Result<(), Self::Error> {
+        self.get_mut().buffer.extend_from_slice(&item);
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        loop {
+            ready!(this.poll_pending(cx))?;
+
+            if this.buffer.is_empty() {
+                return Poll::Ready(Ok(()));
+            }
+            let len = this.buffer.len().min(this.chunk_size);
+            let chunk = this.buffer.split_to(len).freeze();
+            this.start_write(chunk);
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_flush(cx)
+    }
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Starts a new chunked upload. Write data with `ChunkedUpload::write()` (or as `Sink<Bytes>` using `ChunkedUpload::into_sink()`) and finally store it as file with `save()`.
+    /// see https://docs.pcloud.com/methods/upload/upload_create.html
+    pub async fn create_upload(&self) -> Result<ChunkedUpload, PCloudError> {
+        let mut r = self
+            .client()
+            .get(format!("{}/upload_create", self.api_host()));
+
+        r = self.add_token(r);
+
+        let created = self.send_checked::<UploadCreated>(r).await?;
+        let upload_id = created.uploadid.ok_or(PCloudResult::InternalUploadError)?;
+        debug!("Created upload {}", upload_id);
+
+        Ok(ChunkedUpload {
+            client: self.clone(),
+            upload_id,
+            offset: 0,
+            state_store: None,
+        })
+    }
+
+    /// Resumes the chunked upload stored with the given key (e.g. the path of the local source file), or starts a new one if none is stored or pCloud doesn't know the stored upload (anymore). Other failures (e.g. of the network) are returned, keeping the stored state. The progress is persisted in the store after each write and removed once the upload is saved or deleted.
+    /// Continue writing the source data from `ChunkedUpload::offset()`, which is the number of bytes actually received by pCloud.
+    pub async fn resume_upload(
+        &self,
+        key: &str,
+        store: Arc<dyn UploadStateStore>,
+    ) -> Result<ResumableUpload, PCloudError> {
+        if let Some(state) = store.load(key)? {
+            match self.upload_info(state.upload_id).await {
+                Ok(info) => {
+                    let offset = info.size.unwrap_or_default();
+                    debug!(
+                        "Resuming upload {} ({}) at offset {}",
+                        state.upload_id, key, offset
+                    );
+                    return Ok(ChunkedUpload {
+                        client: self.clone(),
+                        upload_id: state.upload_id,
+                        offset,
+                        state_store: Some((key.to_string(), store)),
+                    });
+                }
+                // Unknown to pCloud (never created or expired)
+                Err(e) if e.api_result() == Some(&PCloudResult::UploadNotFound) => {
+                    warn!(
+                        "Upload {} ({}) can not be resumed, starting a new one: {}",
+                        state.upload_id, key, e
+                    );
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut upload = self.create_upload().await?;
+        store.save(
+            key,
+            &UploadState {
+                upload_id: upload.upload_id,
+                offset: 0,
+            },
+        )?;
+        upload.state_store = Some((key.to_string(), store));
+        Ok(upload)
+    }
+
+    /// Requests the current size of a chunked upload
+    /// see https://docs.pcloud.com/methods/upload/upload_info.html
+    async fn upload_info(&self, upload_id: u64) -> Result<UploadInfo, PCloudError> {
+        let mut r = self
+            .client()
+            .get(format!("{}/upload_info", self.api_host()));
+
+        r = r.query(&[("uploadid", upload_id)]);
+        r = self.add_token(r);
+
+        let info = self.send_checked::<UploadInfo>(r).await?;
+        Ok(info)
+    }
+}
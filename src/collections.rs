@@ -0,0 +1,633 @@
+use log::debug;
+
+use crate::{
+    error::PCloudError,
+    file_ops::{FileDescriptor, PCloudFile},
+    pcloud_client::PCloudClient,
+    pcloud_model::{
+        CollectionDetails, CollectionLinkResult, CollectionList, CollectionType, PCloudResult,
+    },
+};
+
+/// Lists the collections (playlists) of the user
+pub struct CollectionListRequestBuilder {
+    /// Client to actually perform the request
+    client: PCloudClient,
+    /// Only collections of this type are listed
+    collection_type: Option<CollectionType>,
+    /// Lists the files of each collection as well
+    show_files: bool,
+    /// Maximum number of files listed per collection
+    page_size: Option<u64>,
+}
+
+#[allow(dead_code)]
+impl CollectionListRequestBuilder {
+    pub(crate) fn create(client: &PCloudClient) -> CollectionListRequestBuilder {
+        CollectionListRequestBuilder {
+            client: client.clone(),
+            collection_type: None,
+            show_files: false,
+            page_size: None,
+        }
+    }
+
+    /// Lists only collections of the given type
+    pub fn of_type(mut self, value: CollectionType) -> CollectionListRequestBuilder {
+        self.collection_type = Some(value);
+        self
+    }
+
+    /// If set, the files of each collection are listed as well
+    pub fn show_files(mut self, value: bool) -> CollectionListRequestBuilder {
+        self.show_files = value;
+        self
+    }
+
+    /// Maximum number of files listed per collection, if `show_files()` is set
+    pub fn page_size(mut self, value: u64) -> CollectionListRequestBuilder {
+        self.page_size = Some(value);
+        self
+    }
+
+    /// Executes the request
+    pub async fn get(self) -> Result<CollectionList, PCloudError> {
+        let mut r = self
+            .client
+            .client()
+            .get(format!("{}/collection_list", self.client.api_host()));
+
+        if let Some(collection_type) = self.collection_type {
+            r = r.query(&[("type", collection_type as u8)]);
+        }
+
+        if self.show_files {
+            r = r.query(&[("showfiles", "1")]);
+        }
+
+        if let Some(page_size) = self.page_size {
+            r = r.query(&[("pagesize", page_size)]);
+        }
+
+        debug!("Requesting collections");
+        r = self.client.add_token(r);
+
+        let result = self.client.send_checked::<CollectionList>(r).await?;
+        Ok(result)
+    }
+}
+
+/// Requests a single collection (playlist) including its files
+pub struct CollectionDetailsRequestBuilder {
+    /// Client to actually perform the request
+    client: PCloudClient,
+    /// Id of the collection
+    collection_id: u64,
+    /// Page of files to list
+    page: Option<u64>,
+    /// Number of files per page
+    page_size: Option<u64>,
+}
+
+#[allow(dead_code)]
+impl CollectionDetailsRequestBuilder {
+    pub(crate) fn for_collection(
+        client: &PCloudClient,
+        collection_id: u64,
+    ) -> CollectionDetailsRequestBuilder {
+        CollectionDetailsRequestBuilder {
+            client: client.clone(),
+            collection_id,
+            page: None,
+            page_size: None,
+        }
+    }
+
+    /// Lists only the given page of files, use together with `page_size()`
+    pub fn page(mut self, value: u64) -> CollectionDetailsRequestBuilder {
+        self.page = Some(value);
+        self
+    }
+
+    /// Number of files per page
+    pub fn page_size(mut self, value: u64) -> CollectionDetailsRequestBuilder {
+        self.page_size = Some(value);
+        self
+    }
+
+    /// Executes the request
+    pub async fn get(self) -> Result<CollectionDetails, PCloudError> {
+        let mut r = self
+            .client
+            .client()
+            .get(format!("{}/collection_details", self.client.api_host()));
+
+        debug!("Requesting collection {}", self.collection_id);
+        r = r.query(&[("collectionid", self.collection_id)]);
+
+        if let Some(page) = self.page {
+            r = r.query(&[("page", page)]);
+        }
+
+        if let Some(page_size) = self.page_size {
+            r = r.query(&[("pagesize", page_size)]);
+        }
+
+        r = self.client.add_token(r);
+
+        let result = self.client.send_checked::<CollectionDetails>(r).await?;
+        Ok(result)
+    }
+}
+
+/// Creates a new collection (playlist), optionally with an initial list of files
+pub struct CollectionCreateRequestBuilder {
+    /// Client to actually perform the request
+    client: PCloudClient,
+    /// Name of the collection
+    name: String,
+    /// Type of the collection
+    collection_type: Option<CollectionType>,
+    /// Files of the collection in their order
+    files: Vec<PCloudFile>,
+}
+
+#[allow(dead_code)]
+impl CollectionCreateRequestBuilder {
+    pub(crate) fn with_name(
+        client: &PCloudClient,
+        name: &str,
+    ) -> Result<CollectionCreateRequestBuilder, PCloudError> {
+        if name.trim().is_empty() {
+            Err(PCloudResult::InvalidFileOrFolderName)?
+        }
+
+        Ok(CollectionCreateRequestBuilder {
+            client: client.clone(),
+            name: name.to_string(),
+            collection_type: None,
+            files: Vec::new(),
+        })
+    }
+
+    /// Type of the collection, defaults to audio
+    pub fn of_type(mut self, value: CollectionType) -> CollectionCreateRequestBuilder {
+        self.collection_type = Some(value);
+        self
+    }
+
+    /// Adds a file to the end of the collection. Accepts either a file id (u64), a file path (String) or any other pCloud object describing a file (like Metadata)
+    pub fn with_file<T: FileDescriptor>(
+        mut self,
+        file_like: T,
+    ) -> Result<CollectionCreateRequestBuilder, PCloudError> {
+        let file = file_like.to_file()?;
+        if file.is_empty() {
+            Err(PCloudResult::NoFileIdOrPathProvided)?
+        }
+        self.files.push(file);
+        Ok(self)
+    }
+
+    /// Creates the collection
+    pub async fn execute(self) -> Result<CollectionDetails, PCloudError> {
+        let file_ids = resolve_file_ids(&self.client, self.files).await?;
+
+        let mut r = self
+            .client
+            .client()
+            .get(format!("{}/collection_create", self.client.api_host()));
+
+        debug!("Creating collection {}", self.name);
+        r = r.query(&[("name", &self.name)]);
+
+        if let Some(collection_type) = self.collection_type {
+            r = r.query(&[("type", collection_type as u8)]);
+        }
+
+        if !file_ids.is_empty() {
+            r = r.query(&[("fileids", join_ids(&file_ids))]);
+        }
+
+        r = self.client.add_token(r);
+
+        let result = self.client.send_checked::<CollectionDetails>(r).await?;
+        Ok(result)
+    }
+}
+
+/// Renames a collection (playlist)
+pub struct CollectionRenameRequestBuilder {
+    /// Client to actually perform the request
+    client: PCloudClient,
+    /// Id of the collection
+    collection_id: u64,
+    /// New name of the collection
+    name: String,
+}
+
+#[allow(dead_code)]
+impl CollectionRenameRequestBuilder {
+    pub(crate) fn for_collection(
+        client: &PCloudClient,
+        collection_id: u64,
+        name: &str,
+    ) -> Result<CollectionRenameRequestBuilder, PCloudError> {
+        if name.trim().is_empty() {
+            Err(PCloudResult::InvalidFileOrFolderName)?
+        }
+
+        Ok(CollectionRenameRequestBuilder {
+            client: client.clone(),
+            collection_id,
+            name: name.to_string(),
+        })
+    }
+
+    /// Renames the collection
+    pub async fn execute(self) -> Result<CollectionDetails, PCloudError> {
+        let mut r = self
+            .client
+            .client()
+            .get(format!("{}/collection_rename", self.client.api_host()));
+
+        debug!(
+            "Renaming collection {} to {}",
+            self.collection_id, self.name
+        );
+        r = r.query(&[("collectionid", self.collection_id)]);
+        r = r.query(&[("name", &self.name)]);
+
+        r = self.client.add_token(r);
+
+        let result = self.client.send_checked::<CollectionDetails>(r).await?;
+        Ok(result)
+    }
+}
+
+/// Deletes a collection (playlist). The files of the collection are not touched.
+pub struct CollectionDeleteRequestBuilder {
+    /// Client to actually perform the request
+    client: PCloudClient,
+    /// Id of the collection
+    collection_id: u64,
+}
+
+#[allow(dead_code)]
+impl CollectionDeleteRequestBuilder {
+    pub(crate) fn for_collection(
+        client: &PCloudClient,
+        collection_id: u64,
+    ) -> CollectionDeleteRequestBuilder {
+        CollectionDeleteRequestBuilder {
+            client: client.clone(),
+            collection_id,
+        }
+    }
+
+    /// Deletes the collection
+    pub async fn execute(self) -> Result<CollectionDetails, PCloudError> {
+        let mut r = self
+            .client
+            .client()
+            .get(format!("{}/collection_delete", self.client.api_host()));
+
+        debug!("Deleting collection {}", self.collection_id);
+        r = r.query(&[("collectionid", self.collection_id)]);
+
+        r = self.client.add_token(r);
+
+        let result = self.client.send_checked::<CollectionDetails>(r).await?;
+        Ok(result)
+    }
+}
+
+/// Appends files to a collection (playlist)
+pub struct CollectionLinkFilesRequestBuilder {
+    /// Client to actually perform the request
+    client: PCloudClient,
+    /// Id of the collection
+    collection_id: u64,
+    /// Files to append, in their order
+    files: Vec<PCloudFile>,
+    /// Omits the files of the collection in the result
+    no_items: bool,
+}
+
+#[allow(dead_code)]
+impl CollectionLinkFilesRequestBuilder {
+    pub(crate) fn for_collection(
+        client: &PCloudClient,
+        collection_id: u64,
+    ) -> CollectionLinkFilesRequestBuilder {
+        CollectionLinkFilesRequestBuilder {
+            client: client.clone(),
+            collection_id,
+            files: Vec::new(),
+            no_items: false,
+        }
+    }
+
+    /// Appends a file to the collection. Accepts either a file id (u64), a file path (String) or any other pCloud object describing a file (like Metadata)
+    pub fn with_file<T: FileDescriptor>(
+        mut self,
+        file_like: T,
+    ) -> Result<CollectionLinkFilesRequestBuilder, PCloudError> {
+        let file = file_like.to_file()?;
+        if file.is_empty() {
+            Err(PCloudResult::NoFileIdOrPathProvided)?
+        }
+        self.files.push(file);
+        Ok(self)
+    }
+
+    /// If set, the files of the collection are not returned
+    pub fn no_items(mut self, value: bool) -> CollectionLinkFilesRequestBuilder {
+        self.no_items = value;
+        self
+    }
+
+    /// Appends the files. The outcome of each file is reported separately, files that can't be added don't fail the request.
+    pub async fn execute(self) -> Result<CollectionLinkResult, PCloudError> {
+        if self.files.is_empty() {
+            Err(PCloudResult::NoFileIdOrPathProvided)?
+        }
+        let file_ids = resolve_file_ids(&self.client, self.files).await?;
+
+        let mut r = self
+            .client
+            .client()
+            .get(format!("{}/collection_linkfiles", self.client.api_host()));
+
+        debug!(
+            "Linking {} files to collection {}",
+            file_ids.len(),
+            self.collection_id
+        );
+        r = r.query(&[("collectionid", self.collection_id)]);
+        r = r.query(&[("fileids", join_ids(&file_ids))]);
+
+        if self.no_items {
+            r = r.query(&[("noitems", "1")]);
+        }
+
+        r = self.client.add_token(r);
+
+        let result = self.client.send_checked::<CollectionLinkResult>(r).await?;
+        Ok(result)
+    }
+}
+
+/// Removes files from a collection (playlist), either by file or by position. The files themselves are not touched.
+pub struct CollectionUnlinkFilesRequestBuilder {
+    /// Client to actually perform the request
+    client: PCloudClient,
+    /// Id of the collection
+    collection_id: u64,
+    /// Files to remove
+    files: Vec<PCloudFile>,
+    /// Positions (starting with 1) of the items to remove
+    positions: Vec<u64>,
+    /// Removes all files
+    all: bool,
+}
+
+#[allow(dead_code)]
+impl CollectionUnlinkFilesRequestBuilder {
+    pub(crate) fn for_collection(
+        client: &PCloudClient,
+        collection_id: u64,
+    ) -> CollectionUnlinkFilesRequestBuilder {
+        CollectionUnlinkFilesRequestBuilder {
+            client: client.clone(),
+            collection_id,
+            files: Vec::new(),
+            positions: Vec::new(),
+            all: false,
+        }
+    }
+
+    /// Removes all items of the given file. Accepts either a file id (u64), a file path (String) or any other pCloud object describing a file (like Metadata)
+    pub fn with_file<T: FileDescriptor>(
+        mut self,
+        file_like: T,
+    ) -> Result<CollectionUnlinkFilesRequestBuilder, PCloudError> {
+        let file = file_like.to_file()?;
+        if file.is_empty() {
+            Err(PCloudResult::NoFileIdOrPathProvided)?
+        }
+        self.files.push(file);
+        Ok(self)
+    }
+
+    /// Removes the item at the given position (starting with 1)
+    pub fn at_position(
+        mut self,
+        position: u64,
+    ) -> Result<CollectionUnlinkFilesRequestBuilder, PCloudError> {
+        if position == 0 {
+            Err(InvalidCollectionPosition(position))?
+        }
+        self.positions.push(position);
+        Ok(self)
+    }
+
+    /// If set, all files are removed from the collection
+    pub fn all(mut self, value: bool) -> CollectionUnlinkFilesRequestBuilder {
+        self.all = value;
+        self
+    }
+
+    /// Removes the files
+    pub async fn execute(self) -> Result<CollectionDetails, PCloudError> {
+        if !self.all && self.files.is_empty() && self.positions.is_empty() {
+            Err(PCloudResult::NoFileIdOrPathProvided)?
+        }
+        let file_ids = resolve_file_ids(&self.client, self.files).await?;
+
+        let mut r = self
+            .client
+            .client()
+            .get(format!("{}/collection_unlinkfiles", self.client.api_host()));
+
+        debug!("Unlinking files from collection {}", self.collection_id);
+        r = r.query(&[("collectionid", self.collection_id)]);
+
+        if self.all {
+            r = r.query(&[("all", "1")]);
+        }
+
+        if !file_ids.is_empty() {
+            r = r.query(&[("fileids", join_ids(&file_ids))]);
+        }
+
+        if !self.positions.is_empty() {
+            r = r.query(&[("positions", join_ids(&self.positions))]);
+        }
+
+        r = self.client.add_token(r);
+
+        let result = self.client.send_checked::<CollectionDetails>(r).await?;
+        Ok(result)
+    }
+}
+
+/// Moves an item of a collection (playlist) to another position
+pub struct CollectionMoveRequestBuilder {
+    /// Client to actually perform the request
+    client: PCloudClient,
+    /// Id of the collection
+    collection_id: u64,
+    /// Current position (starting with 1) of the item
+    item: u64,
+    /// New position (starting with 1) of the item
+    position: u64,
+}
+
+#[allow(dead_code)]
+impl CollectionMoveRequestBuilder {
+    pub(crate) fn for_collection(
+        client: &PCloudClient,
+        collection_id: u64,
+        item: u64,
+        position: u64,
+    ) -> Result<CollectionMoveRequestBuilder, PCloudError> {
+        if item == 0 {
+            Err(InvalidCollectionPosition(item))?
+        }
+        if position == 0 {
+            Err(InvalidCollectionPosition(position))?
+        }
+
+        Ok(CollectionMoveRequestBuilder {
+            client: client.clone(),
+            collection_id,
+            item,
+            position,
+        })
+    }
+
+    /// Moves the item
+    pub async fn execute(self) -> Result<CollectionDetails, PCloudError> {
+        let mut r = self
+            .client
+            .client()
+            .get(format!("{}/collection_move", self.client.api_host()));
+
+        debug!(
+            "Moving item {} of collection {} to position {}",
+            self.item, self.collection_id, self.position
+        );
+        r = r.query(&[("collectionid", self.collection_id)]);
+        r = r.query(&[("item", self.item)]);
+        r = r.query(&[("position", self.position)]);
+
+        r = self.client.add_token(r);
+
+        let result = self.client.send_checked::<CollectionDetails>(r).await?;
+        Ok(result)
+    }
+}
+
+/// Error of a collection position outside the valid range, positions start with 1
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidCollectionPosition(pub u64);
+
+impl std::fmt::Display for InvalidCollectionPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Invalid collection position {}, positions start with 1",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for InvalidCollectionPosition {}
+
+/// Resolves the ids of the given files, in their order
+async fn resolve_file_ids(
+    client: &PCloudClient,
+    files: Vec<PCloudFile>,
+) -> Result<Vec<u64>, PCloudError> {
+    let mut file_ids = Vec::with_capacity(files.len());
+    for file in files {
+        let (file_id, _) = client.get_file_id(file).await?;
+        file_ids.push(file_id);
+    }
+    Ok(file_ids)
+}
+
+/// Joins ids to the comma separated list expected by pCloud
+fn join_ids(ids: &[u64]) -> String {
+    ids.iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Lists the collections (playlists) of the user
+    /// see https://docs.pcloud.com/methods/collection/collection_list.html
+    pub fn collection_list(&self) -> CollectionListRequestBuilder {
+        CollectionListRequestBuilder::create(self)
+    }
+
+    /// Returns a collection (playlist) with its files in their order
+    /// see https://docs.pcloud.com/methods/collection/collection_details.html
+    pub fn collection_details(&self, collection_id: u64) -> CollectionDetailsRequestBuilder {
+        CollectionDetailsRequestBuilder::for_collection(self, collection_id)
+    }
+
+    /// Creates a new collection (playlist)
+    /// see https://docs.pcloud.com/methods/collection/collection_create.html
+    pub fn collection_create(
+        &self,
+        name: &str,
+    ) -> Result<CollectionCreateRequestBuilder, PCloudError> {
+        CollectionCreateRequestBuilder::with_name(self, name)
+    }
+
+    /// Renames a collection (playlist)
+    /// see https://docs.pcloud.com/methods/collection/collection_rename.html
+    pub fn collection_rename(
+        &self,
+        collection_id: u64,
+        name: &str,
+    ) -> Result<CollectionRenameRequestBuilder, PCloudError> {
+        CollectionRenameRequestBuilder::for_collection(self, collection_id, name)
+    }
+
+    /// Deletes a collection (playlist), its files are not touched
+    /// see https://docs.pcloud.com/methods/collection/collection_delete.html
+    pub fn collection_delete(&self, collection_id: u64) -> CollectionDeleteRequestBuilder {
+        CollectionDeleteRequestBuilder::for_collection(self, collection_id)
+    }
+
+    /// Appends files to a collection (playlist)
+    /// see https://docs.pcloud.com/methods/collection/collection_linkfiles.html
+    pub fn collection_link_files(&self, collection_id: u64) -> CollectionLinkFilesRequestBuilder {
+        CollectionLinkFilesRequestBuilder::for_collection(self, collection_id)
+    }
+
+    /// Removes files from a collection (playlist), by file or by position
+    /// see https://docs.pcloud.com/methods/collection/collection_unlinkfiles.html
+    pub fn collection_unlink_files(
+        &self,
+        collection_id: u64,
+    ) -> CollectionUnlinkFilesRequestBuilder {
+        CollectionUnlinkFilesRequestBuilder::for_collection(self, collection_id)
+    }
+
+    /// Moves the item at position `item` of a collection (playlist) to `position`, both starting with 1
+    /// see https://docs.pcloud.com/methods/collection/collection_move.html
+    pub fn collection_move(
+        &self,
+        collection_id: u64,
+        item: u64,
+        position: u64,
+    ) -> Result<CollectionMoveRequestBuilder, PCloudError> {
+        CollectionMoveRequestBuilder::for_collection(self, collection_id, item, position)
+    }
+}
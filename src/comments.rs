@@ -0,0 +1,147 @@
+use log::debug;
+
+use crate::{
+    error::PCloudError,
+    file_ops::{FileDescriptor, PCloudFile},
+    pcloud_client::PCloudClient,
+    pcloud_model::{CommentList, CommentResult, PCloudResult},
+};
+
+/// Lists the comments of a file
+pub struct CommentListRequestBuilder {
+    /// Client to actually perform the request
+    client: PCloudClient,
+    /// File commented
+    file: PCloudFile,
+}
+
+impl CommentListRequestBuilder {
+    pub(crate) fn for_file<T: FileDescriptor>(
+        client: &PCloudClient,
+        file_like: T,
+    ) -> Result<CommentListRequestBuilder, PCloudError> {
+        let file = file_like.to_file()?;
+
+        if file.is_empty() {
+            Err(PCloudResult::NoFileIdOrPathProvided)?
+        }
+
+        Ok(CommentListRequestBuilder {
+            client: client.clone(),
+            file,
+        })
+    }
+
+    /// Executes the request
+    pub async fn get(self) -> Result<CommentList, PCloudError> {
+        let mut r = self
+            .client
+            .client()
+            .get(format!("{}/commentlist", self.client.api_host()));
+
+        debug!("Requesting comments of file {}", self.file);
+        r = add_file(r, self.file);
+        r = self.client.add_token(r);
+
+        let result = self.client.send_checked::<CommentList>(r).await?;
+        Ok(result)
+    }
+}
+
+/// Posts a comment on a file
+pub struct CommentPostRequestBuilder {
+    /// Client to actually perform the request
+    client: PCloudClient,
+    /// File to comment
+    file: PCloudFile,
+    /// Text of the comment
+    text: String,
+}
+
+impl CommentPostRequestBuilder {
+    pub(crate) fn for_file<T: FileDescriptor>(
+        client: &PCloudClient,
+        file_like: T,
+        text: &str,
+    ) -> Result<CommentPostRequestBuilder, PCloudError> {
+        let file = file_like.to_file()?;
+
+        if file.is_empty() {
+            Err(PCloudResult::NoFileIdOrPathProvided)?
+        }
+
+        if text.trim().is_empty() {
+            Err(PCloudError::EmptyComment)?
+        }
+
+        Ok(CommentPostRequestBuilder {
+            client: client.clone(),
+            file,
+            text: text.to_string(),
+        })
+    }
+
+    /// Posts the comment
+    pub async fn execute(self) -> Result<CommentResult, PCloudError> {
+        let mut r = self
+            .client
+            .client()
+            .post(format!("{}/commentpost", self.client.api_host()));
+
+        debug!("Posting comment on file {}", self.file);
+        r = add_file(r, self.file);
+        r = r.query(&[("comment", &self.text)]);
+        r = self.client.add_token(r);
+
+        let result = self.client.send_checked::<CommentResult>(r).await?;
+        Ok(result)
+    }
+}
+
+/// Adds the file (by id or path) to the request
+fn add_file(mut r: reqwest::RequestBuilder, file: PCloudFile) -> reqwest::RequestBuilder {
+    if let Some(id) = file.file_id {
+        r = r.query(&[("fileid", id)]);
+    }
+
+    if let Some(path) = file.path {
+        r = r.query(&[("path", path)]);
+    }
+    r
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Lists the comments of a file, oldest first. Accepts either a file id (u64), a file path (String) or any other pCloud object describing a file (like Metadata)
+    pub async fn list_file_comments<T: FileDescriptor>(
+        &self,
+        file_like: T,
+    ) -> Result<CommentList, PCloudError> {
+        CommentListRequestBuilder::for_file(self, file_like)?
+            .get()
+            .await
+    }
+
+    /// Posts a comment on a file and returns it. Accepts either a file id (u64), a file path (String) or any other pCloud object describing a file (like Metadata)
+    pub async fn post_file_comment<T: FileDescriptor>(
+        &self,
+        file_like: T,
+        text: &str,
+    ) -> Result<CommentResult, PCloudError> {
+        CommentPostRequestBuilder::for_file(self, file_like, text)?
+            .execute()
+            .await
+    }
+
+    /// Deletes a comment by its id and returns it
+    pub async fn delete_file_comment(&self, comment_id: u64) -> Result<CommentResult, PCloudError> {
+        let mut r = self.client().get(format!("{}/commentdel", self.api_host()));
+
+        debug!("Deleting comment {}", comment_id);
+        r = r.query(&[("commentid", comment_id)]);
+        r = self.add_token(r);
+
+        let result = self.send_checked::<CommentResult>(r).await?;
+        Ok(result)
+    }
+}
@@ -0,0 +1,248 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use log::debug;
+use tokio::sync::Notify;
+
+use crate::pcloud_model::PCloudResult;
+
+/// Default factor the limit is multiplied with on overload
+pub const DEFAULT_DECREASE_FACTOR: f64 = 0.5;
+
+/// Default factor of the usual latency above which a request counts as latency spike
+pub const DEFAULT_LATENCY_THRESHOLD: f64 = 3.0;
+
+/// Weight of a new latency sample in the smoothed latency
+const LATENCY_SMOOTHING: f64 = 0.2;
+
+/// Share of the difference the baseline latency rises towards the smoothed latency per sample
+const BASELINE_DRIFT: f64 = 0.05;
+
+/// Number of successful requests needed before latency spikes are detected
+const MIN_LATENCY_SAMPLES: u32 = 5;
+
+/// Adaptive concurrency limit (AIMD) for bulk operations like `upload_files()` or `get_download_links()`. The limit grows by one after each round of successful requests and is cut down (multiplied with the decrease factor) on rate limits (4xxx), server errors (5xxx), broken connections or latency spikes, so bulk jobs settle at the highest sustainable parallelism.
+/// All clones share the same limit, so one controller can throttle several jobs together.
+#[derive(Clone)]
+pub struct AdaptiveConcurrency {
+    /// Lowest limit
+    min: usize,
+    /// Highest limit
+    max: usize,
+    /// Factor the limit is multiplied with on overload
+    decrease_factor: f64,
+    /// Factor of the usual latency above which a request counts as latency spike, if enabled
+    latency_threshold: Option<f64>,
+    /// Current limit and statistics
+    state: Arc<Mutex<State>>,
+    /// Wakes tasks waiting for a permit
+    released: Arc<Notify>,
+}
+
+/// Shared state of an AdaptiveConcurrency
+struct State {
+    /// Number of requests allowed in parallel
+    limit: usize,
+    /// Number of requests running
+    in_flight: usize,
+    /// Successful requests since the limit was last raised
+    successes: usize,
+    /// Last time the limit was decreased
+    last_decrease: Option<Instant>,
+    /// Smoothed latency of successful requests
+    smoothed_latency: Option<Duration>,
+    /// Usual latency, following drops of the smoothed latency immediately and rises only slowly
+    baseline_latency: Option<Duration>,
+    /// Number of latency samples taken
+    samples: u32,
+}
+
+impl AdaptiveConcurrency {
+    /// Creates a controller with limits between 1 and `max`, starting at 1
+    pub fn new(max: usize) -> AdaptiveConcurrency {
+        AdaptiveConcurrency {
+            min: 1,
+            max: max.max(1),
+            decrease_factor: DEFAULT_DECREASE_FACTOR,
+            latency_threshold: Some(DEFAULT_LATENCY_THRESHOLD),
+            state: Arc::new(Mutex::new(State {
+                limit: 1,
+                in_flight: 0,
+                successes: 0,
+                last_decrease: None,
+                smoothed_latency: None,
+                baseline_latency: None,
+                samples: 0,
+            })),
+            released: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Lowest limit, never undercut on overload (defaults to 1)
+    pub fn min_concurrency(mut self, value: usize) -> AdaptiveConcurrency {
+        self.min = value.clamp(1, self.max);
+        let mut state = self.state.lock().unwrap();
+        state.limit = state.limit.max(self.min);
+        drop(state);
+        self
+    }
+
+    /// Limit to start with (defaults to the lowest limit)
+    pub fn initial_concurrency(self, value: usize) -> AdaptiveConcurrency {
+        self.state.lock().unwrap().limit = value.clamp(self.min, self.max);
+        self
+    }
+
+    /// Factor the limit is multiplied with on overload, between 0 and 1 (defaults to 0.5)
+    pub fn decrease_factor(mut self, value: f64) -> AdaptiveConcurrency {
+        self.decrease_factor = value.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Factor of the usual latency above which a request counts as overload (defaults to 3), or None to only react on errors. Disable it for jobs with very different request sizes, like uploads of small and large files.
+    pub fn latency_threshold(mut self, value: Option<f64>) -> AdaptiveConcurrency {
+        self.latency_threshold = value;
+        self
+    }
+
+    /// Highest limit
+    pub fn max_concurrency(&self) -> usize {
+        self.max
+    }
+
+    /// Number of requests currently allowed in parallel
+    pub fn current_limit(&self) -> usize {
+        self.state.lock().unwrap().limit
+    }
+
+    /// Waits until another request may start
+    pub(crate) async fn acquire(&self) -> AdaptivePermit {
+        loop {
+            let released = self.released.notified();
+            {
+                let mut state = self.state.lock().unwrap();
+                if state.in_flight < state.limit {
+                    state.in_flight += 1;
+                    return AdaptivePermit {
+                        controller: self.clone(),
+                        started: Instant::now(),
+                        overloaded: None,
+                    };
+                }
+            }
+            released.await;
+        }
+    }
+
+    /// Releases a permit and adjusts the limit to the outcome (if known) of its request started at `started`
+    fn release(&self, started: Instant, overloaded: Option<bool>) {
+        let latency = started.elapsed();
+        let mut state = self.state.lock().unwrap();
+        state.in_flight -= 1;
+        let overloaded = match overloaded {
+            Some(overloaded) => overloaded,
+            None => {
+                drop(state);
+                self.released.notify_waiters();
+                return;
+            }
+        };
+
+        let spike = !overloaded && self.is_latency_spike(&mut state, latency);
+        if overloaded || spike {
+            // Requests started before the last decrease saw the old limit, don't punish it twice
+            if state.last_decrease.is_none_or(|last| started > last) {
+                let limit = ((state.limit as f64 * self.decrease_factor) as usize).max(self.min);
+                debug!(
+                    "Decreasing concurrency from {} to {} after {}",
+                    state.limit,
+                    limit,
+                    if spike { "latency spike" } else { "overload" }
+                );
+                state.limit = limit;
+                state.successes = 0;
+                state.last_decrease = Some(Instant::now());
+            }
+        } else {
+            state.successes += 1;
+            if state.successes >= state.limit && state.limit < self.max {
+                state.limit += 1;
+                state.successes = 0;
+                debug!("Increasing concurrency to {}", state.limit);
+            }
+        }
+        drop(state);
+        self.released.notify_waiters();
+    }
+
+    /// Records the latency of a successful request and checks it against the usual latency. Lasting slowdowns become the usual latency over time.
+    fn is_latency_spike(&self, state: &mut State, latency: Duration) -> bool {
+        let threshold = match self.latency_threshold {
+            Some(threshold) => threshold,
+            None => return false,
+        };
+        let spike = state.samples >= MIN_LATENCY_SAMPLES
+            && state
+                .baseline_latency
+                .is_some_and(|baseline| latency > baseline.mul_f64(threshold));
+
+        let smoothed = match state.smoothed_latency {
+            Some(smoothed) => {
+                smoothed.mul_f64(1.0 - LATENCY_SMOOTHING) + latency.mul_f64(LATENCY_SMOOTHING)
+            }
+            None => latency,
+        };
+        state.smoothed_latency = Some(smoothed);
+        state.baseline_latency = Some(match state.baseline_latency {
+            Some(baseline) if baseline < smoothed => {
+                baseline + (smoothed - baseline).mul_f64(BASELINE_DRIFT)
+            }
+            _ => smoothed,
+        });
+        state.samples += 1;
+        spike
+    }
+}
+
+/// Permission to run a request, taken from an AdaptiveConcurrency. Dropping it without reporting the outcome releases it without changing the limit.
+pub(crate) struct AdaptivePermit {
+    /// Controller the permit was taken from
+    controller: AdaptiveConcurrency,
+    /// Start of the request
+    started: Instant,
+    /// Whether the request showed an overload, once completed
+    overloaded: Option<bool>,
+}
+
+impl AdaptivePermit {
+    /// Releases the permit and adapts the limit to the outcome of the request, given its error if it failed
+    pub(crate) fn complete(mut self, error: Option<&(dyn std::error::Error + 'static)>) {
+        self.overloaded = Some(error.is_some_and(is_overload));
+    }
+}
+
+impl Drop for AdaptivePermit {
+    fn drop(&mut self) {
+        self.controller.release(self.started, self.overloaded);
+    }
+}
+
+/// True for errors showing the server or connection is overloaded: rate limits (4xxx), server errors (5xxx), timeouts and broken connections
+fn is_overload(error: &(dyn std::error::Error + 'static)) -> bool {
+    if let Some(result) = PCloudResult::from_error(error) {
+        return matches!(result.error_class(), 4 | 5) || *result == PCloudResult::ConnectionBroken;
+    }
+    let mut current = Some(error);
+    while let Some(error) = current {
+        if let Some(e) = error.downcast_ref::<reqwest::Error>() {
+            return e.is_timeout()
+                || e.is_connect()
+                || e.status()
+                    .is_some_and(|s| s.is_server_error() || s.as_u16() == 429);
+        }
+        current = error.source();
+    }
+    false
+}
@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use log::{debug, warn};
+use reqwest::Body;
+
+use crate::{
+    error::PCloudError,
+    file_ops::ChecksumMismatch,
+    folder_ops::{FolderDescriptor, PCloudFolder},
+    pcloud_client::PCloudClient,
+    pcloud_model::{Metadata, PCloudResult},
+    transfer::SizeMismatch,
+};
+
+/// Default number of files transferred in parallel between two accounts
+pub const DEFAULT_CROSS_ACCOUNT_CONCURRENCY: usize = 4;
+
+/// Single file copied into the target account
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrossAccountFile {
+    /// Path relative to the target folder, separated by '/'
+    pub path: String,
+    /// Id of the file in the source account
+    pub source_fileid: u64,
+    /// Id of the copy in the target account
+    pub target_fileid: u64,
+    /// Size in bytes
+    pub size: u64,
+}
+
+/// File that couldn't be copied into the target account
+#[derive(Debug)]
+pub struct CrossAccountFailure {
+    /// Path relative to the target folder, separated by '/'
+    pub path: String,
+    /// Error of the transfer or the verification
+    pub error: PCloudError,
+}
+
+/// Result of a transfer between two accounts
+#[derive(Debug, Default)]
+pub struct CrossAccountTransferReport {
+    /// All files copied (and verified, if enabled)
+    pub transferred: Vec<CrossAccountFile>,
+    /// All files failed
+    pub failed: Vec<CrossAccountFailure>,
+    /// Number of folders created (or already present) in the target account
+    pub folders: usize,
+}
+
+impl CrossAccountTransferReport {
+    /// Checks if all files were copied
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    /// Number of bytes of all files copied
+    pub fn bytes_transferred(&self) -> u64 {
+        self.transferred.iter().map(|f| f.size).sum()
+    }
+}
+
+/// File or folder of the source, with its path relative to the target folder
+struct SourceEntry {
+    path: String,
+    isfolder: bool,
+    fileid: Option<u64>,
+    size: Option<u64>,
+    modified: DateTime<Utc>,
+}
+
+/// Copies a file or a folder (recursively) from one pCloud account into a folder of another one, streaming each download directly into the upload without local staging
+pub struct CrossAccountTransferBuilder {
+    /// Client of the account to copy from
+    source: PCloudClient,
+    /// Path of the file or folder to copy
+    source_path: String,
+    /// Client of the account to copy into
+    target: PCloudClient,
+    /// Folder to copy into
+    target_folder: PCloudFolder,
+    /// Maximum number of files transferred in parallel
+    concurrency: usize,
+    /// if set, the checksums of each copy are compared with the ones of the source
+    verify: bool,
+    /// if set, the modification time of the source files is kept
+    keep_mtime: bool,
+}
+
+#[allow(dead_code)]
+impl CrossAccountTransferBuilder {
+    pub(crate) fn between<T: FolderDescriptor>(
+        source: &PCloudClient,
+        source_path: &str,
+        target: &PCloudClient,
+        target_folder_like: T,
+    ) -> Result<CrossAccountTransferBuilder, PCloudError> {
+        let target_folder = target_folder_like.to_folder()?;
+
+        if source_path.is_empty() || target_folder.is_empty() {
+            Err(PCloudResult::NoFullPathOrFolderIdProvided)?
+        }
+
+        Ok(CrossAccountTransferBuilder {
+            source: source.clone(),
+            source_path: source_path.to_string(),
+            target: target.clone(),
+            target_folder,
+            concurrency: DEFAULT_CROSS_ACCOUNT_CONCURRENCY,
+            verify: true,
+            keep_mtime: true,
+        })
+    }
+
+    /// Maximum number of files transferred in parallel (defaults to 4)
+    pub fn concurrency(mut self, value: usize) -> CrossAccountTransferBuilder {
+        self.concurrency = value.max(1);
+        self
+    }
+
+    /// if set, the size and checksums of each copy are compared with the ones of the source, a mismatch fails the file (defaults to true)
+    pub fn verify(mut self, value: bool) -> CrossAccountTransferBuilder {
+        self.verify = value;
+        self
+    }
+
+    /// if set, the copies get the modification time of the source files (defaults to true)
+    pub fn keep_mtime(mut self, value: bool) -> CrossAccountTransferBuilder {
+        self.keep_mtime = value;
+        self
+    }
+
+    /// Copies all files. Folders are created first, a folder is copied into the target folder with its name. Failed files are reported, they don't stop the transfer.
+    pub async fn execute(self) -> Result<CrossAccountTransferReport, PCloudError> {
+        let entries = self.collect().await?;
+        let mut report = CrossAccountTransferReport::default();
+
+        // Parents are always listed before their contents
+        let mut folders: HashMap<String, PCloudFolder> = HashMap::new();
+        folders.insert(String::new(), self.target_folder.clone());
+        let mut files = Vec::new();
+        for entry in entries {
+            let (parent, name) = split(&entry.path);
+            let parent = folders
+                .get(parent)
+                .cloned()
+                .ok_or(PCloudResult::DirectoryDoesNotExist)?;
+            if !entry.isfolder {
+                files.push((entry, parent));
+                continue;
+            }
+            let created = self
+                .target
+                .create_folder(&parent, name)?
+                .if_not_exists(true)
+                .execute()
+                .await?
+                .metadata
+                .and_then(|m| m.folderid)
+                .ok_or(PCloudResult::DirectoryDoesNotExist)?;
+            folders.insert(entry.path, PCloudFolder::from(created));
+            report.folders += 1;
+        }
+
+        debug!(
+            "Transferring {} files from {} into {}",
+            files.len(),
+            self.source_path,
+            self.target_folder
+        );
+        let this = &self;
+        let mut transfers = futures::stream::iter(files)
+            .map(|(entry, folder)| async move {
+                let result = this.transfer_file(&entry, &folder).await;
+                (entry, result)
+            })
+            .buffer_unordered(self.concurrency);
+
+        while let Some((entry, result)) = transfers.next().await {
+            match result {
+                Ok(file) => report.transferred.push(file),
+                Err(error) => {
+                    warn!("Failed to transfer {}: {}", entry.path, error);
+                    report.failed.push(CrossAccountFailure {
+                        path: entry.path,
+                        error,
+                    });
+                }
+            }
+        }
+        report.transferred.sort_by(|a, b| a.path.cmp(&b.path));
+        report.failed.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(report)
+    }
+
+    /// Lists the source, which is either a folder (listed recursively) or a single file
+    async fn collect(&self) -> Result<Vec<SourceEntry>, PCloudError> {
+        let listing = self
+            .source
+            .list_folder(self.source_path.as_str())?
+            .recursive(true)
+            .get()
+            .await;
+        let metadata = match listing {
+            Ok(listing) => listing
+                .metadata
+                .ok_or(PCloudResult::DirectoryDoesNotExist)?,
+            Err(e) if e.api_result() == Some(&PCloudResult::DirectoryDoesNotExist) => self
+                .source
+                .get_file_metadata(self.source_path.as_str())
+                .await?
+                .metadata
+                .ok_or(PCloudResult::FileNotFound)?,
+            Err(e) => Err(e)?,
+        };
+
+        let mut entries = Vec::new();
+        collect_entries(&metadata, "", &mut entries);
+        Ok(entries)
+    }
+
+    /// Streams a single file from the source into the target folder and verifies the copy
+    async fn transfer_file(
+        &self,
+        entry: &SourceEntry,
+        folder: &PCloudFolder,
+    ) -> Result<CrossAccountFile, PCloudError> {
+        let fileid = entry.fileid.ok_or(PCloudResult::FileNotFound)?;
+        let (info, stream) = self.source.download_stream(fileid).await?;
+
+        let (_, name) = split(&entry.path);
+        let mut upload = self
+            .target
+            .upload_file_into_folder(folder)?
+            .with_file(name, Body::wrap_stream(stream));
+        if self.keep_mtime {
+            upload = upload.mtime(&entry.modified);
+        }
+        let metadata = upload
+            .upload()
+            .await?
+            .into_metadata()?
+            .into_iter()
+            .next()
+            .ok_or_else(|| PCloudError::MissingMetadata(entry.path.clone()))?;
+        let target_fileid = metadata.fileid.ok_or(PCloudResult::FileNotFound)?;
+        let size = metadata.size.unwrap_or_default();
+
+        if self.verify {
+            if let Some(expected) = entry.size.or(info.content_length) {
+                if expected != size {
+                    Err(SizeMismatch {
+                        expected,
+                        actual: size,
+                    })?
+                }
+            }
+            let copy = self.target.checksum_file(target_fileid)?.get().await?;
+            let mut verified = false;
+            for (algorithm, expected, actual) in [
+                ("SHA-1", info.sha1, copy.sha1),
+                ("SHA-256", info.sha256, copy.sha256),
+            ] {
+                if let (Some(expected), Some(actual)) = (expected, actual) {
+                    if !expected.eq_ignore_ascii_case(&actual) {
+                        Err(ChecksumMismatch {
+                            algorithm: algorithm.to_string(),
+                            expected,
+                            actual,
+                        })?
+                    }
+                    verified = true;
+                }
+            }
+            if !verified {
+                Err(PCloudError::MissingChecksum)?
+            }
+        }
+
+        debug!("Transferred {} ({} bytes)", entry.path, size);
+        Ok(CrossAccountFile {
+            path: entry.path.clone(),
+            source_fileid: fileid,
+            target_fileid,
+            size,
+        })
+    }
+}
+
+/// Collects the file or folder with all its (recursive) contents, parents before their contents
+fn collect_entries(metadata: &Metadata, prefix: &str, entries: &mut Vec<SourceEntry>) {
+    let path = if prefix.is_empty() {
+        metadata.name.clone()
+    } else {
+        format!("{}/{}", prefix, metadata.name)
+    };
+    entries.push(SourceEntry {
+        path: path.clone(),
+        isfolder: metadata.isfolder,
+        fileid: metadata.fileid,
+        size: metadata.size,
+        modified: metadata.modified,
+    });
+    for child in &metadata.contents {
+        collect_entries(child, &path, entries);
+    }
+}
+
+/// Splits a relative path into the path of the parent and the name
+fn split(path: &str) -> (&str, &str) {
+    path.rsplit_once('/').unwrap_or(("", path))
+}
+
+/// Copies a file or folder (recursively) at `source_path` of the source account into `target_folder` of the target account, e.g. to migrate between accounts. Each file is downloaded and uploaded as a stream, nothing is stored locally. Accepts either a folder id (u64), a folder path (String) or any other pCloud object describing a folder (like Metadata) as target folder
+pub fn transfer_between<T: FolderDescriptor>(
+    source: &PCloudClient,
+    source_path: &str,
+    target: &PCloudClient,
+    target_folder: T,
+) -> Result<CrossAccountTransferBuilder, PCloudError> {
+    CrossAccountTransferBuilder::between(source, source_path, target, target_folder)
+}
@@ -0,0 +1,218 @@
+#![cfg(feature = "decompress")]
+use std::{fmt::Display, io::Write};
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt, TryStreamExt};
+use log::debug;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::{
+    error::PCloudError,
+    file_ops::{FileDescriptor, PCloudFile},
+    pcloud_client::PCloudClient,
+    pcloud_model::PCloudResult,
+};
+
+/// Compression formats decompressed on the fly
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// gzip (`.gz`, `.tgz`)
+    Gzip,
+    /// Zstandard (`.zst`, `.zstd`)
+    Zstd,
+}
+
+impl Compression {
+    /// Detects the compression from the file name extension or the content type. None if the content is not compressed (or with an unsupported format).
+    pub fn detect(file_name: &str, content_type: Option<&str>) -> Option<Compression> {
+        let extension = file_name
+            .rsplit_once('.')
+            .map(|(_, e)| e.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("gz") | Some("tgz") => return Some(Compression::Gzip),
+            Some("zst") | Some("zstd") => return Some(Compression::Zstd),
+            _ => {}
+        }
+
+        match content_type.map(|c| c.to_ascii_lowercase()).as_deref() {
+            Some("application/gzip") | Some("application/x-gzip") => Some(Compression::Gzip),
+            Some("application/zstd") => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Compression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Compression::Gzip => write!(f, "gzip"),
+            Compression::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+/// Synchronous decoder writing the decompressed data into a buffer, drained after each chunk
+enum Decoder {
+    Gzip(flate2::write::GzDecoder<Vec<u8>>),
+    Zstd(zstd::stream::write::Decoder<'static, Vec<u8>>),
+    /// Content passed on as is
+    Identity(Vec<u8>),
+}
+
+impl Decoder {
+    fn new(compression: Option<Compression>) -> std::io::Result<Decoder> {
+        Ok(match compression {
+            Some(Compression::Gzip) => Decoder::Gzip(flate2::write::GzDecoder::new(Vec::new())),
+            Some(Compression::Zstd) => {
+                Decoder::Zstd(zstd::stream::write::Decoder::new(Vec::new())?)
+            }
+            None => Decoder::Identity(Vec::new()),
+        })
+    }
+
+    /// Decodes the chunk and returns the data decompressed so far
+    fn decode(&mut self, chunk: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Decoder::Gzip(decoder) => {
+                decoder.write_all(chunk)?;
+                Ok(std::mem::take(decoder.get_mut()))
+            }
+            Decoder::Zstd(decoder) => {
+                decoder.write_all(chunk)?;
+                Ok(std::mem::take(decoder.get_mut()))
+            }
+            Decoder::Identity(_) => Ok(chunk.to_vec()),
+        }
+    }
+
+    /// Finishes decoding and returns the remaining data
+    fn finish(self) -> std::io::Result<Vec<u8>> {
+        match self {
+            Decoder::Gzip(decoder) => decoder.finish(),
+            Decoder::Zstd(mut decoder) => {
+                decoder.flush()?;
+                Ok(decoder.into_inner())
+            }
+            Decoder::Identity(buffer) => Ok(buffer),
+        }
+    }
+}
+
+/// Bytes transferred by a decompressing download
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecompressedDownload {
+    /// Compression of the content, None if passed on as is
+    pub compression: Option<Compression>,
+    /// Number of (compressed) bytes downloaded
+    pub compressed_bytes: u64,
+    /// Number of (decompressed) bytes written to the writer
+    pub decompressed_bytes: u64,
+}
+
+/// Decompresses the stream on the fly and writes the decompressed data to the writer. Without compression the content is written as is.
+pub async fn decompress_into<S, W>(
+    stream: S,
+    compression: Option<Compression>,
+    writer: &mut W,
+) -> Result<DecompressedDownload, PCloudError>
+where
+    S: Stream<Item = Result<Bytes, Box<dyn std::error::Error + Send + Sync>>>,
+    W: AsyncWrite + Unpin,
+{
+    let mut result = DecompressedDownload {
+        compression,
+        compressed_bytes: 0,
+        decompressed_bytes: 0,
+    };
+    let mut decoder = Decoder::new(compression)?;
+
+    futures::pin_mut!(stream);
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        result.compressed_bytes += chunk.len() as u64;
+        let decompressed = decoder.decode(&chunk)?;
+        result.decompressed_bytes += decompressed.len() as u64;
+        writer.write_all(&decompressed).await?;
+    }
+    let decompressed = decoder.finish()?;
+    result.decompressed_bytes += decompressed.len() as u64;
+    writer.write_all(&decompressed).await?;
+    writer.flush().await?;
+
+    Ok(result)
+}
+
+/// Downloads a file and decompresses it on the fly
+pub struct DecompressDownloadRequestBuilder {
+    /// Client to actually perform the request
+    client: PCloudClient,
+    /// File to download
+    file: PCloudFile,
+    /// Compression forced, otherwise detected from file name and content type
+    compression: Option<Compression>,
+}
+
+#[allow(dead_code)]
+impl DecompressDownloadRequestBuilder {
+    pub(crate) fn for_file<T: FileDescriptor>(
+        client: &PCloudClient,
+        file_like: T,
+    ) -> Result<DecompressDownloadRequestBuilder, PCloudError> {
+        let file = file_like.to_file()?;
+
+        if file.is_empty() {
+            Err(PCloudResult::NoFileIdOrPathProvided)?
+        }
+
+        Ok(DecompressDownloadRequestBuilder {
+            client: client.clone(),
+            file,
+            compression: None,
+        })
+    }
+
+    /// Forces the given compression format instead of detecting it from the file name extension or the content type
+    pub fn compression(mut self, value: Compression) -> DecompressDownloadRequestBuilder {
+        self.compression = Some(value);
+        self
+    }
+
+    /// Downloads the file and writes the decompressed content to the given writer. Files not compressed (in a supported format) are written as is.
+    pub async fn write_to<W: AsyncWrite + Unpin>(
+        self,
+        writer: &mut W,
+    ) -> Result<DecompressedDownload, PCloudError> {
+        let (info, stream) = self.client.download_stream(&self.file).await?;
+
+        let compression = self.compression.or_else(|| {
+            let name = info
+                .metadata
+                .as_ref()
+                .map(|m| m.name.as_str())
+                .unwrap_or_default();
+            let content_type = info
+                .metadata
+                .as_ref()
+                .and_then(|m| m.contenttype.as_deref())
+                .or(info.content_type.as_deref());
+            Compression::detect(name, content_type)
+        });
+        debug!(
+            "Downloading {} with decompression {:?}",
+            self.file, compression
+        );
+
+        decompress_into(stream.map_err(|e| e.into()), compression, writer).await
+    }
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Downloads the latest revision of a file (or the revision given) and decompresses gzip or zstd content on the fly. Accepts either a file id (u64), a file path (String) or any other pCloud object describing a file (like Metadata)
+    pub fn download_decompressed<T: FileDescriptor>(
+        &self,
+        file_like: T,
+    ) -> Result<DecompressDownloadRequestBuilder, PCloudError> {
+        DecompressDownloadRequestBuilder::for_file(self, file_like)
+    }
+}
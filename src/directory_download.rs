@@ -0,0 +1,408 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use futures::StreamExt;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::PCloudError,
+    filter::SyncFilter,
+    folder_ops::{FolderDescriptor, PCloudFolder},
+    pcloud_client::PCloudClient,
+    pcloud_model::{FileInfo, Metadata, PCloudResult},
+    progress::ProgressObserver,
+};
+
+/// Name of the state file, stored in the local directory if no other location is given
+pub const DEFAULT_DOWNLOAD_STATE_FILE: &str = ".pcloud-download.json";
+
+/// Default number of files downloaded in parallel
+pub const DEFAULT_DIRECTORY_DOWNLOAD_CONCURRENCY: usize = 4;
+
+/// Handling of local files already present at the target of a directory download (and not downloaded by an interrupted run)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// Local files are always replaced
+    #[default]
+    Always,
+    /// Local files are kept as they are, so a stale local file (differing from the remote one) is never replaced
+    Never,
+    /// Local files are only replaced if their size differs from the remote file
+    IfSizeDiffers,
+}
+
+impl OverwritePolicy {
+    /// Checks if the existing local file with the given size is replaced by the remote file
+    fn replaces(&self, local_size: u64, remote_size: Option<u64>) -> bool {
+        match self {
+            OverwritePolicy::Always => true,
+            OverwritePolicy::Never => false,
+            OverwritePolicy::IfSizeDiffers => remote_size != Some(local_size),
+        }
+    }
+}
+
+/// Single file of a directory download
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DownloadEntry {
+    /// Path relative to the downloaded folder, separated by '/'
+    pub path: String,
+    /// Id of the remote file
+    pub fileid: u64,
+    /// pCloud content hash of the remote file
+    pub hash: Option<u64>,
+    /// Size of the remote file in bytes
+    pub size: Option<u64>,
+}
+
+/// Persisted progress of a directory download. Contains the listing of the remote folder taken at the start, so an interrupted download is resumed without walking the folder again.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct DownloadState {
+    /// Remote folder downloaded (path or id)
+    pub folder: String,
+    /// All files to download
+    pub files: Vec<DownloadEntry>,
+    /// Content hashes of all files completely downloaded, by file id
+    pub completed: BTreeMap<u64, Option<u64>>,
+}
+
+impl DownloadState {
+    /// Loads the state from the given JSON file, None if there is no state
+    pub async fn load(path: &Path) -> Result<Option<DownloadState>, PCloudError> {
+        match tokio::fs::read(path).await {
+            Ok(content) => Ok(Some(serde_json::from_slice(&content)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e)?,
+        }
+    }
+
+    /// Writes the state as JSON to the given file. The file is replaced atomically, so an interruption never leaves a corrupt state behind.
+    pub async fn save(&self, path: &Path) -> Result<(), PCloudError> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let temp = path.with_extension("tmp");
+        tokio::fs::write(&temp, serde_json::to_vec(self)?).await?;
+        tokio::fs::rename(&temp, path).await?;
+        Ok(())
+    }
+
+    /// Checks if the given file was already downloaded completely
+    pub fn is_completed(&self, entry: &DownloadEntry) -> bool {
+        self.completed.get(&entry.fileid) == Some(&entry.hash)
+    }
+}
+
+/// File of a directory download that couldn't be downloaded
+#[derive(Debug)]
+pub struct DirectoryDownloadFailure {
+    /// Path relative to the downloaded folder, separated by '/'
+    pub path: String,
+    /// Original error
+    pub error: PCloudError,
+}
+
+/// Result of a directory download
+#[derive(Debug, Default)]
+pub struct DirectoryDownloadReport {
+    /// Number of files downloaded
+    pub downloaded: usize,
+    /// Number of files skipped, because they were already downloaded by an interrupted run
+    pub skipped: usize,
+    /// Number of files excluded by the filter
+    pub excluded: usize,
+    /// Number of files not downloaded, because they were already present locally and kept by the overwrite policy
+    pub kept: usize,
+    /// Number of bytes downloaded
+    pub bytes_downloaded: u64,
+    /// If set, the download continued an interrupted run
+    pub resumed: bool,
+    /// All files not downloaded, sorted by path
+    pub failed: Vec<DirectoryDownloadFailure>,
+}
+
+impl DirectoryDownloadReport {
+    /// True if all files were downloaded (or skipped)
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Downloads a remote folder (recursively) into a local directory. Progress is persisted in a state file, so an interrupted download can be resumed without walking the folder again and without downloading completed files again.
+pub struct DownloadDirectoryRequestBuilder {
+    /// Client to actually perform the requests
+    client: PCloudClient,
+    /// Remote folder to download
+    folder: PCloudFolder,
+    /// Local target directory
+    directory: PathBuf,
+    /// Location of the state file
+    state_file: Option<PathBuf>,
+    /// Resume an interrupted download, if a state is present
+    resume: bool,
+    /// Files not downloaded
+    filter: Option<SyncFilter>,
+    /// Receives the progress of the download
+    progress: Option<Arc<dyn ProgressObserver>>,
+    /// Maximum number of files downloaded in parallel
+    concurrency: usize,
+    /// Handling of local files already present
+    overwrite: OverwritePolicy,
+}
+
+#[allow(dead_code)]
+impl DownloadDirectoryRequestBuilder {
+    pub(crate) fn for_folder<T: FolderDescriptor, P: AsRef<Path>>(
+        client: &PCloudClient,
+        folder_like: T,
+        directory: P,
+    ) -> Result<DownloadDirectoryRequestBuilder, PCloudError> {
+        let folder = folder_like.to_folder()?;
+
+        if folder.is_empty() {
+            Err(PCloudResult::NoFileIdOrPathProvided)?
+        }
+
+        Ok(DownloadDirectoryRequestBuilder {
+            client: client.clone(),
+            folder,
+            directory: directory.as_ref().to_path_buf(),
+            state_file: None,
+            resume: true,
+            filter: None,
+            progress: None,
+            concurrency: DEFAULT_DIRECTORY_DOWNLOAD_CONCURRENCY,
+            overwrite: OverwritePolicy::default(),
+        })
+    }
+
+    /// Location of the state file. Defaults to `.pcloud-download.json` in the local directory.
+    pub fn state_file<P: AsRef<Path>>(mut self, path: P) -> DownloadDirectoryRequestBuilder {
+        self.state_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// If set (default), an interrupted download of the same folder is resumed. Otherwise the folder is listed and downloaded again completely.
+    pub fn resume(mut self, value: bool) -> DownloadDirectoryRequestBuilder {
+        self.resume = value;
+        self
+    }
+
+    /// Files not downloaded. Defaults to the rules of the `.pcloudignore` file in the local directory, if present.
+    pub fn filter(mut self, value: SyncFilter) -> DownloadDirectoryRequestBuilder {
+        self.filter = Some(value);
+        self
+    }
+
+    /// Reports the progress of the download, each file being an item named by its relative path. Files skipped or excluded aren't reported.
+    pub fn progress_observer(
+        mut self,
+        value: Arc<dyn ProgressObserver>,
+    ) -> DownloadDirectoryRequestBuilder {
+        self.progress = Some(value);
+        self
+    }
+
+    /// Maximum number of files downloaded in parallel (defaults to 4)
+    pub fn concurrency(mut self, value: usize) -> DownloadDirectoryRequestBuilder {
+        self.concurrency = value.max(1);
+        self
+    }
+
+    /// Handling of local files already present, which weren't downloaded by an interrupted run (defaults to `OverwritePolicy::Always`). With `OverwritePolicy::Never`, a stale local file is never replaced.
+    pub fn overwrite(mut self, value: OverwritePolicy) -> DownloadDirectoryRequestBuilder {
+        self.overwrite = value;
+        self
+    }
+
+    /// Downloads all files. Failed files don't abort the download, but are collected in the report. The state file is updated after each file and removed once all files are downloaded, otherwise it is kept so another run retries the failed files.
+    pub async fn execute(self) -> Result<DirectoryDownloadReport, PCloudError> {
+        let state_file = self
+            .state_file
+            .clone()
+            .unwrap_or_else(|| self.directory.join(DEFAULT_DOWNLOAD_STATE_FILE));
+        let folder = self.folder.to_string();
+        let mut report = DirectoryDownloadReport::default();
+        let filter = match &self.filter {
+            Some(filter) => filter.clone(),
+            None => SyncFilter::for_directory(&self.directory).await?,
+        };
+
+        let previous = if self.resume {
+            DownloadState::load(&state_file)
+                .await?
+                .filter(|state| state.folder == folder)
+        } else {
+            None
+        };
+        let mut state = match previous {
+            Some(state) => {
+                debug!(
+                    "Resuming download of {}, {} of {} files completed",
+                    folder,
+                    state.completed.len(),
+                    state.files.len()
+                );
+                report.resumed = true;
+                state
+            }
+            None => {
+                let listing = self
+                    .client
+                    .list_folder(&self.folder)?
+                    .recursive(true)
+                    .get()
+                    .await?;
+                let mut files = Vec::new();
+                if let Some(metadata) = listing.metadata {
+                    collect_files(&metadata.contents, "", &mut files);
+                }
+                let state = DownloadState {
+                    folder,
+                    files,
+                    completed: BTreeMap::new(),
+                };
+                state.save(&state_file).await?;
+                state
+            }
+        };
+
+        let mut pending = Vec::new();
+        for entry in state.files.clone() {
+            if filter.is_excluded(&entry.path, entry.size) {
+                report.excluded += 1;
+                continue;
+            }
+            let target = entry
+                .path
+                .split('/')
+                .fold(self.directory.clone(), |p, c| p.join(c));
+
+            if state.is_completed(&entry) && is_present(&target, entry.size).await {
+                report.skipped += 1;
+                continue;
+            }
+            if let Ok(metadata) = tokio::fs::metadata(&target).await {
+                if !self.overwrite.replaces(metadata.len(), entry.size) {
+                    report.kept += 1;
+                    continue;
+                }
+            }
+            pending.push((entry, target));
+        }
+        if let Some(observer) = &self.progress {
+            let size: Option<u64> = pending.iter().map(|(entry, _)| entry.size).sum();
+            observer.operation_started(Some(pending.len()), size);
+        }
+
+        let this = &self;
+        let mut downloads = futures::stream::iter(pending)
+            .map(|(entry, target)| async move {
+                let result = this.download_file(&entry, &target).await;
+                (entry, result)
+            })
+            .buffer_unordered(self.concurrency);
+
+        while let Some((entry, result)) = downloads.next().await {
+            if let Some(observer) = &self.progress {
+                observer.item_finished(&entry.path, result.as_ref().err().map(|e| e as _));
+            }
+            match result {
+                Ok(info) => {
+                    report.downloaded += 1;
+                    report.bytes_downloaded +=
+                        info.content_length.or(entry.size).unwrap_or_default();
+
+                    state.completed.insert(entry.fileid, entry.hash);
+                    state.save(&state_file).await?;
+                }
+                Err(error) => {
+                    warn!("Failed to download {}: {}", entry.path, error);
+                    report.failed.push(DirectoryDownloadFailure {
+                        path: entry.path,
+                        error,
+                    });
+                }
+            }
+        }
+
+        if let Some(observer) = &self.progress {
+            observer.operation_finished();
+        }
+        if report.is_success() {
+            tokio::fs::remove_file(&state_file).await?;
+        }
+        report.failed.sort_by(|a, b| a.path.cmp(&b.path));
+        debug!(
+            "Download finished: {} files downloaded, {} skipped, {} failed",
+            report.downloaded,
+            report.skipped,
+            report.failed.len()
+        );
+        Ok(report)
+    }
+
+    /// Downloads a single file to the given target, creating its parent directories
+    async fn download_file(
+        &self,
+        entry: &DownloadEntry,
+        target: &Path,
+    ) -> Result<FileInfo, PCloudError> {
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        debug!("Downloading {}", entry.path);
+        let progress = self.progress.as_ref().map(|observer| {
+            observer.item_started(&entry.path, entry.size);
+            (observer.clone(), entry.path.clone())
+        });
+        self.client
+            .download_and_verify_observed(entry.fileid, target, progress)
+            .await
+    }
+}
+
+/// Collects all files of the given (recursive) folder contents with their path relative to the folder
+fn collect_files(contents: &[Metadata], prefix: &str, files: &mut Vec<DownloadEntry>) {
+    for metadata in contents {
+        let path = if prefix.is_empty() {
+            metadata.name.clone()
+        } else {
+            format!("{}/{}", prefix, metadata.name)
+        };
+        if metadata.isfolder {
+            collect_files(&metadata.contents, &path, files);
+        } else if let Some(fileid) = metadata.fileid {
+            files.push(DownloadEntry {
+                path,
+                fileid,
+                hash: metadata.hash,
+                size: metadata.size,
+            });
+        }
+    }
+}
+
+/// Checks if the local file exists (with the expected size, if known)
+async fn is_present(path: &Path, size: Option<u64>) -> bool {
+    match tokio::fs::metadata(path).await {
+        Ok(metadata) => size.map(|s| s == metadata.len()).unwrap_or(true),
+        Err(_) => false,
+    }
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Downloads a remote folder (recursively) into a local directory, resuming an interrupted download. Accepts either a folder id (u64), a folder path (String) or any other pCloud object describing a folder (like Metadata)
+    pub fn download_directory<T: FolderDescriptor, P: AsRef<Path>>(
+        &self,
+        folder_like: T,
+        directory: P,
+    ) -> Result<DownloadDirectoryRequestBuilder, PCloudError> {
+        DownloadDirectoryRequestBuilder::for_folder(self, folder_like, directory)
+    }
+}
@@ -0,0 +1,233 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use futures::StreamExt;
+use log::{debug, warn};
+
+use crate::{
+    error::PCloudError,
+    folder_ops::{FolderDescriptor, PCloudFolder},
+    pcloud_client::PCloudClient,
+    pcloud_model::{Metadata, PCloudResult},
+};
+
+/// Default number of files uploaded in parallel
+pub const DEFAULT_DIRECTORY_UPLOAD_CONCURRENCY: usize = 4;
+
+/// File uploaded by a directory upload
+#[derive(Debug)]
+pub struct DirectoryUploadFile {
+    /// Path relative to the local directory, separated by '/'
+    pub path: String,
+    /// Metadata of the file in pCloud
+    pub metadata: Metadata,
+}
+
+/// File of a directory upload that couldn't be uploaded
+#[derive(Debug)]
+pub struct DirectoryUploadFailure {
+    /// Path relative to the local directory, separated by '/'
+    pub path: String,
+    /// Original error
+    pub error: PCloudError,
+}
+
+/// Result of a directory upload
+#[derive(Debug, Default)]
+pub struct DirectoryUploadReport {
+    /// Number of remote folders created (or already existing)
+    pub folders: usize,
+    /// All files uploaded, sorted by path
+    pub uploaded: Vec<DirectoryUploadFile>,
+    /// All files not uploaded, sorted by path
+    pub failed: Vec<DirectoryUploadFailure>,
+}
+
+impl DirectoryUploadReport {
+    /// True if all files were uploaded
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    /// Total size of all files uploaded in bytes
+    pub fn bytes_uploaded(&self) -> u64 {
+        self.uploaded
+            .iter()
+            .map(|f| f.metadata.size.unwrap_or_default())
+            .sum()
+    }
+}
+
+/// Uploads a local directory (recursively) into a remote folder. The folder structure is recreated remotely, missing folders are created and the files are uploaded with bounded concurrency. Failed files don't abort the upload, but are collected in the report.
+pub struct UploadDirectoryRequestBuilder {
+    /// Client to actually perform the requests
+    client: PCloudClient,
+    /// Local directory to upload
+    directory: PathBuf,
+    /// Remote target folder
+    folder: PCloudFolder,
+    /// Maximum number of files uploaded in parallel
+    concurrency: usize,
+}
+
+#[allow(dead_code)]
+impl UploadDirectoryRequestBuilder {
+    pub(crate) fn for_directory<P: AsRef<Path>, T: FolderDescriptor>(
+        client: &PCloudClient,
+        directory: P,
+        folder_like: T,
+    ) -> Result<UploadDirectoryRequestBuilder, PCloudError> {
+        let folder = folder_like.to_folder()?;
+
+        if folder.is_empty() {
+            Err(PCloudResult::NoFullPathOrFolderIdProvided)?
+        }
+
+        Ok(UploadDirectoryRequestBuilder {
+            client: client.clone(),
+            directory: directory.as_ref().to_path_buf(),
+            folder,
+            concurrency: DEFAULT_DIRECTORY_UPLOAD_CONCURRENCY,
+        })
+    }
+
+    /// Maximum number of files uploaded in parallel (defaults to 4)
+    pub fn concurrency(mut self, value: usize) -> UploadDirectoryRequestBuilder {
+        self.concurrency = value.max(1);
+        self
+    }
+
+    /// Creates all folders and uploads all files. Fails if the local directory can't be read or a folder can't be created.
+    pub async fn execute(self) -> Result<DirectoryUploadReport, PCloudError> {
+        let (directories, files) = local_entries(&self.directory).await?;
+        let mut report = DirectoryUploadReport::default();
+
+        // Parents are always created before their contents
+        let mut folders: HashMap<String, PCloudFolder> = HashMap::new();
+        folders.insert(String::new(), self.folder.clone());
+        for path in directories {
+            let (parent, name) = split(&path);
+            let parent = folders
+                .get(parent)
+                .cloned()
+                .ok_or(PCloudResult::DirectoryDoesNotExist)?;
+            let created = self
+                .client
+                .create_folder(&parent, name)?
+                .if_not_exists(true)
+                .execute()
+                .await?
+                .metadata
+                .and_then(|m| m.folderid)
+                .ok_or(PCloudResult::DirectoryDoesNotExist)?;
+            folders.insert(path, PCloudFolder::from(created));
+            report.folders += 1;
+        }
+
+        debug!(
+            "Uploading {} files of {} into {}",
+            files.len(),
+            self.directory.display(),
+            self.folder
+        );
+        let client = &self.client;
+        let folders = &folders;
+        let mut uploads = futures::stream::iter(files)
+            .map(|(local, path)| async move {
+                let result = upload_file(client, folders, &local, &path).await;
+                (path, result)
+            })
+            .buffer_unordered(self.concurrency);
+
+        while let Some((path, result)) = uploads.next().await {
+            match result {
+                Ok(metadata) => report.uploaded.push(DirectoryUploadFile { path, metadata }),
+                Err(error) => {
+                    warn!("Failed to upload {}: {}", path, error);
+                    report.failed.push(DirectoryUploadFailure { path, error });
+                }
+            }
+        }
+        report.uploaded.sort_by(|a, b| a.path.cmp(&b.path));
+        report.failed.sort_by(|a, b| a.path.cmp(&b.path));
+        debug!(
+            "Upload finished: {} files uploaded, {} failed",
+            report.uploaded.len(),
+            report.failed.len()
+        );
+        Ok(report)
+    }
+}
+
+/// Uploads a single file into the remote folder of its parent
+async fn upload_file(
+    client: &PCloudClient,
+    folders: &HashMap<String, PCloudFolder>,
+    local: &Path,
+    path: &str,
+) -> Result<Metadata, PCloudError> {
+    let (parent, _) = split(path);
+    let folder = folders
+        .get(parent)
+        .ok_or(PCloudResult::DirectoryDoesNotExist)?;
+    let uploaded = client
+        .upload_file_into_folder(folder)?
+        .with_file_from_path(local)
+        .await?
+        .upload()
+        .await?;
+    Ok(uploaded
+        .into_metadata()?
+        .into_iter()
+        .next()
+        .ok_or(PCloudResult::InternalUploadError)?)
+}
+
+/// All directories and files in the given directory (recursively) with their path relative to the directory (separated by '/'). Directories are sorted by path, so parents come before their contents.
+async fn local_entries(
+    directory: &Path,
+) -> Result<(Vec<String>, Vec<(PathBuf, String)>), PCloudError> {
+    let mut directories = Vec::new();
+    let mut files = Vec::new();
+    let mut pending = vec![(directory.to_path_buf(), String::new())];
+    while let Some((dir, prefix)) = pending.pop() {
+        let mut entries = tokio::fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let relative_path = if prefix.is_empty() {
+                name
+            } else {
+                format!("{}/{}", prefix, name)
+            };
+            let file_type = entry.file_type().await?;
+            if file_type.is_dir() {
+                directories.push(relative_path.clone());
+                pending.push((entry.path(), relative_path));
+            } else if file_type.is_file() {
+                files.push((entry.path(), relative_path));
+            }
+        }
+    }
+    directories.sort();
+    files.sort_by(|a, b| a.1.cmp(&b.1));
+    Ok((directories, files))
+}
+
+/// Splits a relative path into the path of the parent and the name
+fn split(path: &str) -> (&str, &str) {
+    path.rsplit_once('/').unwrap_or(("", path))
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Uploads a local directory (recursively) into a remote folder, creating missing folders. Accepts either a folder id (u64), a folder path (String) or any other pCloud object describing a folder (like Metadata)
+    pub fn upload_directory<P: AsRef<Path>, T: FolderDescriptor>(
+        &self,
+        directory: P,
+        folder_like: T,
+    ) -> Result<UploadDirectoryRequestBuilder, PCloudError> {
+        UploadDirectoryRequestBuilder::for_directory(self, directory, folder_like)
+    }
+}
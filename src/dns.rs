@@ -0,0 +1,115 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use hyper::client::connect::dns::Name;
+use log::debug;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+
+use crate::error::PCloudError;
+
+/// Resolved addresses and the time of resolution by host name
+type AddressCache = HashMap<String, (Instant, Vec<SocketAddr>)>;
+
+/// IP address family used to connect to the pCloud API and content hosts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IpPreference {
+    /// Addresses in the order returned by the system resolver (default)
+    #[default]
+    Any,
+    /// IPv4 addresses first, falling back to IPv6
+    PreferIpv4,
+    /// IPv6 addresses first, falling back to IPv4
+    PreferIpv6,
+    /// Only IPv4 addresses
+    Ipv4Only,
+    /// Only IPv6 addresses
+    Ipv6Only,
+}
+
+impl IpPreference {
+    /// Filters and orders the resolved addresses
+    fn apply(&self, mut addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+        match self {
+            IpPreference::Any => {}
+            IpPreference::PreferIpv4 => addrs.sort_by_key(|a| a.is_ipv6()),
+            IpPreference::PreferIpv6 => addrs.sort_by_key(|a| a.is_ipv4()),
+            IpPreference::Ipv4Only => addrs.retain(|a| a.is_ipv4()),
+            IpPreference::Ipv6Only => addrs.retain(|a| a.is_ipv6()),
+        }
+        addrs
+    }
+}
+
+/// DNS resolver caching the addresses of each host for a fixed time and applying an `IpPreference`. The system resolver doesn't expose the TTL of its records, so the cache duration is configured.
+/// Used by the `PCloudClient` if enabled with `dns_cache_ttl()` or `ip_preference()` of the builder, but works for any reqwest client.
+#[derive(Clone, Default)]
+pub struct CachingResolver {
+    /// Time to keep resolved addresses, no caching if None
+    ttl: Option<Duration>,
+    /// Filter and order of the resolved addresses
+    preference: IpPreference,
+    /// Resolved addresses by host name, common for all clones
+    cache: Arc<Mutex<AddressCache>>,
+}
+
+impl CachingResolver {
+    /// Creates a resolver caching the addresses for the given time (no caching if None) and applying the IP preference
+    pub fn new(ttl: Option<Duration>, preference: IpPreference) -> CachingResolver {
+        CachingResolver {
+            ttl,
+            preference,
+            cache: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the cached addresses of the host, if resolved within the TTL
+    pub fn cached_addrs(&self, host: &str) -> Option<Vec<SocketAddr>> {
+        let ttl = self.ttl?;
+        let cache = self.cache.lock().unwrap();
+        cache
+            .get(host)
+            .filter(|(resolved, _)| resolved.elapsed() < ttl)
+            .map(|(_, addrs)| addrs.clone())
+    }
+
+    /// Forgets all cached addresses, e.g. after a network change
+    pub fn clear(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    /// Resolves the host using the system resolver, applies the IP preference and caches the addresses
+    pub async fn lookup(&self, host: &str) -> Result<Vec<SocketAddr>, PCloudError> {
+        if let Some(addrs) = self.cached_addrs(host) {
+            return Ok(addrs);
+        }
+
+        let resolved = tokio::net::lookup_host((host, 0)).await?.collect();
+        let addrs = self.preference.apply(resolved);
+        if addrs.is_empty() {
+            Err(PCloudError::NoMatchingAddress(host.to_string()))?
+        }
+        debug!("Resolved {} to {:?}", host, addrs);
+
+        if self.ttl.is_some() {
+            self.cache
+                .lock()
+                .unwrap()
+                .insert(host.to_string(), (Instant::now(), addrs.clone()));
+        }
+        Ok(addrs)
+    }
+}
+
+impl Resolve for CachingResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.clone();
+        Box::pin(async move {
+            let addrs = resolver.lookup(name.as_str()).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
@@ -0,0 +1,317 @@
+use std::{fmt::Display, path::PathBuf};
+
+use reqwest::header::{InvalidHeaderName, InvalidHeaderValue};
+
+use crate::{
+    batch_upload::UploadFailure,
+    collections::InvalidCollectionPosition,
+    events::ResyncRequired,
+    file_ops::{ChecksumMismatch, InsufficientQuota},
+    filter::InvalidFilterRule,
+    guard::GuardViolation,
+    pcloud_client::CorrelatedError,
+    pcloud_model::PCloudResult,
+    permissions::PermissionDenied,
+    raw_capture::DeserializationError,
+    shutdown::ShutDown,
+    transfer::SizeMismatch,
+};
+
+/// Error of the pCloud client, distinguishing failures of the API, the network, (de)serialization, local I/O and the checks of the client itself
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PCloudError {
+    /// pCloud rejected the request with the given result
+    ApiError(PCloudResult),
+    /// Request failed on the network or HTTP level (connection, timeout, status)
+    Http(reqwest::Error),
+    /// Response or content couldn't be (de)serialized
+    Json(serde_json::Error),
+    /// Local file or stream failed
+    Io(std::io::Error),
+    /// File or folder descriptor doesn't describe a file or folder (like an empty path)
+    InvalidDescriptor(PCloudResult),
+    /// Request couldn't be built, e.g. because of an invalid header name or value
+    Request(hyper::http::Error),
+    /// Request sent with a correlation id failed, see `PCloudClientBuilder::correlation_id()`
+    Correlated(Box<CorrelatedError>),
+    /// Response couldn't be deserialized, keeping the raw response (see `PCloudClientBuilder::capture_raw_responses()`)
+    Deserialization(Box<DeserializationError>),
+    /// Checksum of the data received or uploaded differs from the one announced by pCloud
+    ChecksumMismatch(ChecksumMismatch),
+    /// pCloud provided no checksum to verify the data with
+    MissingChecksum,
+    /// pCloud returned no (matching) metadata for the file of the given name or id
+    MissingMetadata(String),
+    /// Size of a transferred file or range differs from the expected one
+    SizeMismatch(SizeMismatch),
+    /// Upload exceeds the remaining quota of the user
+    InsufficientQuota(InsufficientQuota),
+    /// Upload of a single file of a batch failed
+    UploadFailure(Box<UploadFailure>),
+    /// Operation was refused by a guard rail of the client
+    GuardViolation(Box<GuardViolation>),
+    /// Operation was refused as the user lacks the permissions on a shared file or folder
+    PermissionDenied(Box<PermissionDenied>),
+    /// Events can't be replayed, a full resync is required
+    ResyncRequired(ResyncRequired),
+    /// Position in a collection is invalid
+    InvalidCollectionPosition(InvalidCollectionPosition),
+    /// Line of a filter file can't be parsed
+    InvalidFilterRule(InvalidFilterRule),
+    /// Local path doesn't name a file
+    NotAFile(PathBuf),
+    /// Id is neither a file id (`f` + number) nor a folder id (`d` + number)
+    InvalidId(String),
+    /// Text of a comment is empty
+    EmptyComment,
+    /// Download host doesn't support the requested range, responding with the given status
+    RangeNotSupported(reqwest::StatusCode),
+    /// No address of the host matches the IP preference
+    NoMatchingAddress(String),
+    /// Client was shut down before the operation started
+    ShutDown(ShutDown),
+    /// Operation didn't complete in time
+    Timeout(tokio::time::error::Elapsed),
+    /// Receiving side of a channel was dropped
+    ChannelClosed,
+    /// Request of a replayed cassette (method and url) wasn't recorded
+    #[cfg(feature = "vcr")]
+    NotRecorded(String),
+    /// Watching local files failed
+    #[cfg(feature = "notify")]
+    Watch(notify::Error),
+    /// SQLite database of a state store failed
+    #[cfg(feature = "sqlite")]
+    Sqlite(rusqlite::Error),
+    /// Failure of an external component, like a custom transport or tower layer. The original error can be accessed with `PCloudError::downcast_ref()`.
+    Other(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl PCloudError {
+    /// Result returned by pCloud, if the request was rejected by the API (or the descriptor was invalid)
+    pub fn api_result(&self) -> Option<&PCloudResult> {
+        match self {
+            PCloudError::ApiError(result) | PCloudError::InvalidDescriptor(result) => Some(result),
+            PCloudError::Correlated(e) => e.source.api_result(),
+            PCloudError::UploadFailure(e) => e.error.api_result(),
+            PCloudError::Other(error) => PCloudResult::from_error(error.as_ref()),
+            _ => None,
+        }
+    }
+
+    /// Checks if the request failed on the network level or timed out
+    pub fn is_network(&self) -> bool {
+        match self {
+            PCloudError::Http(e) => e.is_connect() || e.is_timeout() || e.is_request(),
+            PCloudError::ApiError(result) => *result == PCloudResult::ConnectionBroken,
+            PCloudError::Correlated(e) => e.source.is_network(),
+            _ => false,
+        }
+    }
+
+    /// Correlation id sent with the failed request, see `PCloudClientBuilder::correlation_id()`
+    pub fn correlation_id(&self) -> Option<&str> {
+        match self {
+            PCloudError::Correlated(e) => Some(&e.correlation_id),
+            _ => None,
+        }
+    }
+
+    /// Original error of the given type, e.g. `ChecksumMismatch` or a `PCloudResult`. Errors of a request with a correlation id are searched as well.
+    pub fn downcast_ref<T: std::error::Error + 'static>(&self) -> Option<&T> {
+        if let PCloudError::Correlated(e) = self {
+            if let Some(error) = (e.as_ref() as &dyn std::error::Error).downcast_ref::<T>() {
+                return Some(error);
+            }
+            return e.source.downcast_ref::<T>();
+        }
+        self.inner().downcast_ref::<T>()
+    }
+
+    /// Wrapped error, or the error itself if it wraps none
+    fn inner(&self) -> &(dyn std::error::Error + 'static) {
+        match self {
+            PCloudError::ApiError(result) | PCloudError::InvalidDescriptor(result) => result,
+            PCloudError::Http(e) => e,
+            PCloudError::Json(e) => e,
+            PCloudError::Io(e) => e,
+            PCloudError::Request(e) => e,
+            PCloudError::Correlated(e) => e.as_ref(),
+            PCloudError::Deserialization(e) => e.as_ref(),
+            PCloudError::ChecksumMismatch(e) => e,
+            PCloudError::SizeMismatch(e) => e,
+            PCloudError::InsufficientQuota(e) => e,
+            PCloudError::UploadFailure(e) => e.as_ref(),
+            PCloudError::GuardViolation(e) => e.as_ref(),
+            PCloudError::PermissionDenied(e) => e.as_ref(),
+            PCloudError::ResyncRequired(e) => e,
+            PCloudError::InvalidCollectionPosition(e) => e,
+            PCloudError::InvalidFilterRule(e) => e,
+            PCloudError::ShutDown(e) => e,
+            PCloudError::Timeout(e) => e,
+            #[cfg(feature = "notify")]
+            PCloudError::Watch(e) => e,
+            #[cfg(feature = "sqlite")]
+            PCloudError::Sqlite(e) => e,
+            PCloudError::Other(e) => e.as_ref(),
+            _ => self,
+        }
+    }
+}
+
+impl Display for PCloudError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PCloudError::ApiError(result) => write!(f, "{}", result),
+            PCloudError::Http(e) => write!(f, "HTTP request failed: {}", e),
+            PCloudError::Json(e) => write!(f, "Invalid JSON: {}", e),
+            PCloudError::Io(e) => write!(f, "I/O failed: {}", e),
+            PCloudError::InvalidDescriptor(result) => {
+                write!(f, "Invalid file or folder: {}", result)
+            }
+            PCloudError::Request(e) => write!(f, "Invalid request: {}", e),
+            PCloudError::MissingChecksum => write!(f, "No checksum provided by pCloud"),
+            PCloudError::MissingMetadata(file) => write!(f, "No metadata returned for {}", file),
+            PCloudError::NotAFile(path) => write!(f, "{} is not a file", path.display()),
+            PCloudError::InvalidId(id) => write!(f, "Invalid id {}", id),
+            PCloudError::EmptyComment => write!(f, "Comments must not be empty"),
+            PCloudError::RangeNotSupported(status) => write!(
+                f,
+                "Range not supported by the download host (status {})",
+                status
+            ),
+            PCloudError::NoMatchingAddress(host) => {
+                write!(f, "No address of {} matches the IP preference", host)
+            }
+            PCloudError::ChannelClosed => write!(f, "Channel closed"),
+            #[cfg(feature = "vcr")]
+            PCloudError::NotRecorded(request) => {
+                write!(f, "No recorded interaction for {}", request)
+            }
+            e => write!(f, "{}", e.inner()),
+        }
+    }
+}
+
+impl std::error::Error for PCloudError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PCloudError::MissingChecksum
+            | PCloudError::MissingMetadata(_)
+            | PCloudError::NotAFile(_)
+            | PCloudError::InvalidId(_)
+            | PCloudError::EmptyComment
+            | PCloudError::RangeNotSupported(_)
+            | PCloudError::NoMatchingAddress(_)
+            | PCloudError::ChannelClosed => None,
+            #[cfg(feature = "vcr")]
+            PCloudError::NotRecorded(_) => None,
+            e => Some(e.inner()),
+        }
+    }
+}
+
+impl From<PCloudResult> for PCloudError {
+    fn from(value: PCloudResult) -> Self {
+        PCloudError::ApiError(value)
+    }
+}
+
+impl From<reqwest::Error> for PCloudError {
+    fn from(value: reqwest::Error) -> Self {
+        PCloudError::Http(value)
+    }
+}
+
+impl From<serde_json::Error> for PCloudError {
+    fn from(value: serde_json::Error) -> Self {
+        PCloudError::Json(value)
+    }
+}
+
+impl From<std::io::Error> for PCloudError {
+    fn from(value: std::io::Error) -> Self {
+        PCloudError::Io(value)
+    }
+}
+
+impl From<Box<dyn std::error::Error + Send + Sync>> for PCloudError {
+    /// Unwraps errors of the known kinds (e.g. returned by other parts of the crate), keeps all others as they are
+    fn from(value: Box<dyn std::error::Error + Send + Sync>) -> Self {
+        let value = match value.downcast::<PCloudError>() {
+            Ok(e) => return *e,
+            Err(value) => value,
+        };
+        let value = match value.downcast::<PCloudResult>() {
+            Ok(result) => return PCloudError::ApiError(*result),
+            Err(value) => value,
+        };
+        let value = match value.downcast::<reqwest::Error>() {
+            Ok(e) => return PCloudError::Http(*e),
+            Err(value) => value,
+        };
+        let value = match value.downcast::<serde_json::Error>() {
+            Ok(e) => return PCloudError::Json(*e),
+            Err(value) => value,
+        };
+        match value.downcast::<std::io::Error>() {
+            Ok(e) => PCloudError::Io(*e),
+            Err(value) => PCloudError::Other(value),
+        }
+    }
+}
+
+impl From<PCloudError> for std::io::Error {
+    /// Keeps local I/O errors as they are, wraps all others (e.g. for async IO adapters)
+    fn from(value: PCloudError) -> Self {
+        match value {
+            PCloudError::Io(e) => e,
+            e => std::io::Error::other(e),
+        }
+    }
+}
+
+/// Errors of the client wrapped by their own variant
+macro_rules! variant_error {
+    ($($error:ty => $variant:ident),*) => {
+        $(
+            impl From<$error> for PCloudError {
+                fn from(value: $error) -> Self {
+                    PCloudError::$variant(value.into())
+                }
+            }
+        )*
+    };
+}
+
+variant_error!(
+    hyper::http::Error => Request,
+    InvalidHeaderName => Request,
+    InvalidHeaderValue => Request,
+    CorrelatedError => Correlated,
+    DeserializationError => Deserialization,
+    ChecksumMismatch => ChecksumMismatch,
+    SizeMismatch => SizeMismatch,
+    InsufficientQuota => InsufficientQuota,
+    UploadFailure => UploadFailure,
+    GuardViolation => GuardViolation,
+    PermissionDenied => PermissionDenied,
+    ResyncRequired => ResyncRequired,
+    InvalidCollectionPosition => InvalidCollectionPosition,
+    InvalidFilterRule => InvalidFilterRule,
+    ShutDown => ShutDown,
+    tokio::time::error::Elapsed => Timeout
+);
+
+#[cfg(feature = "notify")]
+variant_error!(notify::Error => Watch);
+
+#[cfg(feature = "sqlite")]
+variant_error!(rusqlite::Error => Sqlite);
+
+impl<T> From<tokio::sync::mpsc::error::SendError<T>> for PCloudError {
+    fn from(_: tokio::sync::mpsc::error::SendError<T>) -> Self {
+        PCloudError::ChannelClosed
+    }
+}
@@ -0,0 +1,209 @@
+use std::{fmt::Display, time::Duration};
+
+use log::{debug, warn};
+use reqwest::{header::CONTENT_TYPE, StatusCode};
+use tokio::{
+    sync::mpsc::{Receiver, Sender},
+    task::JoinHandle,
+};
+
+use crate::{error::PCloudError, pcloud_model::DiffEntry};
+
+/// Default number of retries of a failed delivery to an HTTP endpoint
+pub const DEFAULT_BRIDGE_MAX_RETRIES: u32 = 5;
+
+/// Default delay before the first retry of a failed delivery, doubled for each further retry
+pub const DEFAULT_BRIDGE_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Upper bound of the delay between two retries
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Destination of the forwarded events
+enum EventSink {
+    /// Channel receiving the events as JSON
+    Channel(Sender<String>),
+    /// HTTP endpoint receiving each event as JSON in a POST request
+    Http {
+        url: String,
+        client: reqwest::Client,
+        headers: Vec<(String, String)>,
+    },
+}
+
+/// Error ending an event bridge, carrying the id of the last event delivered to resume from
+#[derive(Debug)]
+pub struct EventBridgeError {
+    /// Diff id of the last event delivered, None if no event was delivered
+    pub last_delivered: Option<u64>,
+    /// Diff id of the event that couldn't be delivered
+    pub diffid: u64,
+    /// Error of the last delivery attempt
+    pub source: PCloudError,
+}
+
+impl Display for EventBridgeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Failed to deliver event {}: {}",
+            self.diffid, self.source
+        )
+    }
+}
+
+impl std::error::Error for EventBridgeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Forwards the events of a diff stream (see `DiffRequestBuilder::stream()`) serialized as JSON to a channel or an HTTP endpoint (webhook), so external systems can react to changes without running the polling loop themselves
+pub struct EventBridge {
+    /// Destination of the events
+    sink: EventSink,
+    /// Number of retries of a failed HTTP delivery
+    max_retries: u32,
+    /// Delay before the first retry
+    retry_delay: Duration,
+}
+
+impl EventBridge {
+    /// Forwards the events as JSON into the given channel
+    pub fn channel(sender: Sender<String>) -> EventBridge {
+        EventBridge::with_sink(EventSink::Channel(sender))
+    }
+
+    /// Forwards each event as JSON in a POST request to the given URL. Requests failing with a connection error, a timeout, 408, 429 or a server error are retried.
+    pub fn http(url: &str) -> EventBridge {
+        EventBridge::with_sink(EventSink::Http {
+            url: url.to_string(),
+            client: reqwest::Client::new(),
+            headers: Vec::new(),
+        })
+    }
+
+    fn with_sink(sink: EventSink) -> EventBridge {
+        EventBridge {
+            sink,
+            max_retries: DEFAULT_BRIDGE_MAX_RETRIES,
+            retry_delay: DEFAULT_BRIDGE_RETRY_DELAY,
+        }
+    }
+
+    /// HTTP client for the requests to the endpoint, e.g. with timeouts or client certificates. Ignored for channels.
+    pub fn http_client(mut self, value: reqwest::Client) -> EventBridge {
+        if let EventSink::Http { client, .. } = &mut self.sink {
+            *client = value;
+        }
+        self
+    }
+
+    /// Adds a header to each request to the endpoint, e.g. for authentication. Ignored for channels.
+    pub fn header(mut self, name: &str, value: &str) -> EventBridge {
+        if let EventSink::Http { headers, .. } = &mut self.sink {
+            headers.push((name.to_string(), value.to_string()));
+        }
+        self
+    }
+
+    /// Number of retries of a failed delivery to the endpoint before the bridge ends (defaults to 5)
+    pub fn max_retries(mut self, value: u32) -> EventBridge {
+        self.max_retries = value;
+        self
+    }
+
+    /// Delay before the first retry, doubled for each further retry (defaults to 1 second)
+    pub fn retry_delay(mut self, value: Duration) -> EventBridge {
+        self.retry_delay = value;
+        self
+    }
+
+    /// Forwards all events in order until the stream ends or the channel is closed. Returns the diff id of the last event delivered, or an error once a delivery failed all retries.
+    pub async fn run(
+        self,
+        mut events: Receiver<DiffEntry>,
+    ) -> Result<Option<u64>, EventBridgeError> {
+        let mut last_delivered = None;
+        while let Some(entry) = events.recv().await {
+            let delivered = match serde_json::to_string(&entry) {
+                Ok(json) => self.deliver(entry.diffid, json).await,
+                Err(e) => Err(e.into()),
+            };
+            match delivered {
+                Ok(true) => last_delivered = Some(entry.diffid),
+                Ok(false) => {
+                    debug!("Event channel closed, stopping bridge");
+                    break;
+                }
+                Err(source) => {
+                    return Err(EventBridgeError {
+                        last_delivered,
+                        diffid: entry.diffid,
+                        source,
+                    })
+                }
+            }
+        }
+        Ok(last_delivered)
+    }
+
+    /// Runs the bridge in the background, see `run()`
+    pub fn spawn(
+        self,
+        events: Receiver<DiffEntry>,
+    ) -> JoinHandle<Result<Option<u64>, EventBridgeError>> {
+        tokio::spawn(self.run(events))
+    }
+
+    /// Delivers a single event, retrying failed HTTP requests. Returns false if the channel is closed.
+    async fn deliver(&self, diffid: u64, json: String) -> Result<bool, PCloudError> {
+        let (url, client, headers) = match &self.sink {
+            EventSink::Channel(sender) => return Ok(sender.send(json).await.is_ok()),
+            EventSink::Http {
+                url,
+                client,
+                headers,
+            } => (url, client, headers),
+        };
+
+        let mut retries = 0;
+        loop {
+            let mut r = client
+                .post(url)
+                .header(CONTENT_TYPE, "application/json")
+                .body(json.clone());
+            for (name, value) in headers {
+                r = r.header(name, value);
+            }
+            let error = match r.send().await.and_then(|r| r.error_for_status()) {
+                Ok(_) => {
+                    debug!("Delivered event {} to {}", diffid, url);
+                    return Ok(true);
+                }
+                Err(e) if e.status().is_some_and(|status| !is_retryable(status)) => Err(e)?,
+                Err(e) => e,
+            };
+
+            if retries >= self.max_retries {
+                return Err(error.into());
+            }
+            let delay = self
+                .retry_delay
+                .saturating_mul(2u32.saturating_pow(retries))
+                .min(MAX_RETRY_DELAY);
+            retries += 1;
+            warn!(
+                "Failed to deliver event {}: {}, retrying in {:?}",
+                diffid, error, delay
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// True for responses worth retrying: timeouts, rate limits and server errors
+fn is_retryable(status: StatusCode) -> bool {
+    status.is_server_error()
+        || status == StatusCode::REQUEST_TIMEOUT
+        || status == StatusCode::TOO_MANY_REQUESTS
+}
@@ -1,16 +1,35 @@
+use crate::error::PCloudError;
+use std::collections::HashMap;
 use std::fmt::Display;
 use std::time::Duration;
 
 use crate::file_ops::{FileDescriptor, PCloudFile};
+use crate::folder_ops::FolderDescriptor;
 use crate::pcloud_client::PCloudClient;
-use crate::pcloud_model::{self, Diff, WithPCloudResult};
-use crate::pcloud_model::{DiffEntry, FileHistory};
+use crate::pcloud_model::{self, Diff};
+use crate::pcloud_model::{DiffEntry, DiffEvent, FileHistory, Metadata};
 use chrono::{DateTime, TimeZone};
+use futures::Stream;
 use log::{debug, warn};
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::Receiver;
 use tokio::sync::mpsc::Sender;
 
+/// Timeout of each blocking diff call of `DiffRequestBuilder::stream()`, if no `block_timeout()` is set
+pub const DEFAULT_STREAM_BLOCK_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Default number of consecutive failed diff calls tolerated by `DiffRequestBuilder::stream()` before it ends
+pub const DEFAULT_MAX_RECONNECTS: u32 = 10;
+
+/// Default delay before the first reconnect of `DiffRequestBuilder::stream()`, doubled for each further failure
+pub const DEFAULT_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Upper bound of the delay between two reconnects
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// Maximum time granted to a blocking diff call on top of its timeout (at most the timeout itself), before it is considered stalled (e.g. if the transport doesn't enforce the timeout)
+const STALL_GRACE: Duration = Duration::from_secs(10);
+
 /// Consumes a Receiver of DiffEntries, applies the given predicate on each entry and passes all accepted entries to the returned Receiver
 pub fn filter_stream<P>(mut source: Receiver<DiffEntry>, filter: P) -> Receiver<DiffEntry>
 where
@@ -33,6 +52,328 @@ where
     rx
 }
 
+/// Reason a client has to rebuild its state instead of replaying events
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResyncReason {
+    /// pCloud sent a `reset` event
+    Reset,
+    /// Events following the stored diff id are no longer available
+    Gap {
+        /// Diff id following the stored one
+        expected: u64,
+        /// Diff id of the first event received
+        received: u64,
+    },
+    /// The latest diff id is older than the stored one
+    Behind {
+        /// Diff id stored by the client
+        stored: u64,
+        /// Latest diff id of the account
+        latest: u64,
+    },
+}
+
+/// Error of `DiffRequestBuilder::replay()`, signaling that the stored state can't be updated by replaying events and has to be rebuilt (see `PCloudClient::resync_baseline()`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResyncRequired {
+    /// Reason the events can't be replayed
+    pub reason: ResyncReason,
+    /// Latest diff id received
+    pub diffid: u64,
+}
+
+impl Display for ResyncRequired {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.reason {
+            ResyncReason::Reset => write!(f, "Resync required: reset event {}", self.diffid),
+            ResyncReason::Gap { expected, received } => write!(
+                f,
+                "Resync required: expected event {}, but got {}",
+                expected, received
+            ),
+            ResyncReason::Behind { stored, latest } => write!(
+                f,
+                "Resync required: stored diff id {} is newer than the latest one {}",
+                stored, latest
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ResyncRequired {}
+
+/// Baseline state of a folder to replay events on, see `PCloudClient::resync_baseline()`
+#[derive(Debug)]
+pub struct ResyncBaseline {
+    /// Diff id taken before listing, replaying the events after it brings the listing up to date
+    pub diffid: u64,
+    /// Recursive listing of the folder
+    pub metadata: Metadata,
+}
+
+/// Kind of a coalesced change
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChangeKind {
+    /// Created (and possibly modified afterwards)
+    Created,
+    /// Content modified
+    Modified,
+    /// Deleted
+    Deleted,
+    /// Moved or renamed. The content might have been modified as well.
+    Moved {
+        /// Id of the previous parent folder
+        from_parent: Option<u64>,
+        /// Previous name
+        from_name: String,
+        /// If set, the content was modified as well
+        modified: bool,
+    },
+}
+
+/// Coalesced change of a single file or folder, with its latest metadata
+#[derive(Debug)]
+pub struct Change {
+    /// Kind of the change
+    pub kind: ChangeKind,
+    /// Latest metadata of the file or folder
+    pub metadata: Metadata,
+}
+
+/// Compact set of changes, coalesced from all events of a window
+#[derive(Debug, Default)]
+pub struct ChangeSet {
+    /// All changes, in the order of the first event of each file or folder
+    pub changes: Vec<Change>,
+    /// If set, a reset event was received. All local state should be dropped and rebuilt.
+    pub reset: bool,
+    /// Diff id of the last event contained
+    pub diffid: Option<u64>,
+}
+
+impl ChangeSet {
+    /// Checks if there is nothing to process
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && !self.reset
+    }
+
+    /// All changes of the given kind
+    fn of_kind(&self, f: fn(&ChangeKind) -> bool) -> impl Iterator<Item = &Metadata> {
+        self.changes
+            .iter()
+            .filter(move |c| f(&c.kind))
+            .map(|c| &c.metadata)
+    }
+
+    /// Metadata of all created files and folders
+    pub fn created(&self) -> impl Iterator<Item = &Metadata> {
+        self.of_kind(|k| *k == ChangeKind::Created)
+    }
+
+    /// Metadata of all modified files and folders
+    pub fn modified(&self) -> impl Iterator<Item = &Metadata> {
+        self.of_kind(|k| *k == ChangeKind::Modified)
+    }
+
+    /// Metadata of all deleted files and folders
+    pub fn deleted(&self) -> impl Iterator<Item = &Metadata> {
+        self.of_kind(|k| *k == ChangeKind::Deleted)
+    }
+
+    /// Metadata of all moved or renamed files and folders
+    pub fn moved(&self) -> impl Iterator<Item = &Metadata> {
+        self.of_kind(|k| matches!(k, ChangeKind::Moved { .. }))
+    }
+}
+
+/// Last known location and content of a file or folder
+struct KnownObject {
+    parent: Option<u64>,
+    name: String,
+    hash: Option<u64>,
+    size: Option<u64>,
+}
+
+/// Events of a single file or folder within the current window
+struct PendingChange {
+    created: bool,
+    deleted: bool,
+    modified: bool,
+    /// Location before the window, if known
+    from: Option<(Option<u64>, String)>,
+    metadata: Metadata,
+}
+
+/// Coalesces diff events per file and folder into compact change sets. Multiple events of the same file (e.g. several `modifyfile`) result in a single change, a file created and deleted within the same window results in none. Moves are detected by comparing with the last known location, so the aggregator should see all events from the start.
+#[derive(Default)]
+pub struct ChangeAggregator {
+    /// Last known state of all files and folders seen, by their id (like `f123` or `d45`)
+    known: HashMap<String, KnownObject>,
+    /// Changes of the current window, in order of their first event
+    pending: Vec<PendingChange>,
+    /// Index of the pending change of each id
+    index: HashMap<String, usize>,
+    reset: bool,
+    diffid: Option<u64>,
+}
+
+impl ChangeAggregator {
+    /// Creates an empty aggregator
+    pub fn new() -> ChangeAggregator {
+        ChangeAggregator::default()
+    }
+
+    /// Adds an event to the current window. Events without file or folder metadata (like shares) are ignored.
+    pub fn push(&mut self, entry: DiffEntry) {
+        self.diffid = Some(entry.diffid);
+        if entry.event == DiffEvent::Reset {
+            self.reset = true;
+            self.known.clear();
+            self.pending.clear();
+            self.index.clear();
+            return;
+        }
+        let metadata = match (&entry.event, entry.metadata) {
+            (
+                DiffEvent::CreateFile
+                | DiffEvent::CreateFolder
+                | DiffEvent::ModifyFile
+                | DiffEvent::ModifyFolder
+                | DiffEvent::DeleteFile
+                | DiffEvent::DeleteFolder,
+                Some(metadata),
+            ) => metadata,
+            _ => return,
+        };
+
+        let id = metadata.id.clone();
+        let previous = self.known.get(&id);
+        let content_changed = match previous {
+            Some(previous) => previous.hash != metadata.hash || previous.size != metadata.size,
+            None => true,
+        };
+        let from = previous.map(|p| (p.parent, p.name.clone()));
+        match entry.event {
+            DiffEvent::DeleteFile | DiffEvent::DeleteFolder => {
+                self.known.remove(&id);
+            }
+            _ => {
+                self.known.insert(
+                    id.clone(),
+                    KnownObject {
+                        parent: metadata.parentfolderid,
+                        name: metadata.name.clone(),
+                        hash: metadata.hash,
+                        size: metadata.size,
+                    },
+                );
+            }
+        }
+
+        let pending = match self.index.get(&id) {
+            Some(index) => {
+                let pending = &mut self.pending[*index];
+                pending.metadata = metadata;
+                pending
+            }
+            None => {
+                self.index.insert(id, self.pending.len());
+                self.pending.push(PendingChange {
+                    created: false,
+                    deleted: false,
+                    modified: false,
+                    from,
+                    metadata,
+                });
+                self.pending.last_mut().unwrap()
+            }
+        };
+        match entry.event {
+            DiffEvent::CreateFile | DiffEvent::CreateFolder if pending.deleted => {
+                pending.deleted = false;
+                pending.modified = true;
+            }
+            DiffEvent::CreateFile | DiffEvent::CreateFolder => pending.created = true,
+            DiffEvent::DeleteFile | DiffEvent::DeleteFolder => pending.deleted = true,
+            _ => pending.modified |= content_changed,
+        }
+    }
+
+    /// Checks if the current window contains nothing to process
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty() && !self.reset
+    }
+
+    /// Closes the current window and returns its coalesced changes
+    pub fn take(&mut self) -> ChangeSet {
+        self.index.clear();
+        let changes = std::mem::take(&mut self.pending)
+            .into_iter()
+            .filter_map(|pending| {
+                let location = (
+                    pending.metadata.parentfolderid,
+                    pending.metadata.name.clone(),
+                );
+                let kind = match pending.from {
+                    _ if pending.created && pending.deleted => return None,
+                    _ if pending.created => ChangeKind::Created,
+                    _ if pending.deleted => ChangeKind::Deleted,
+                    Some(from) if from != location => ChangeKind::Moved {
+                        from_parent: from.0,
+                        from_name: from.1,
+                        modified: pending.modified,
+                    },
+                    _ if pending.modified => ChangeKind::Modified,
+                    _ => return None,
+                };
+                Some(Change {
+                    kind,
+                    metadata: pending.metadata,
+                })
+            })
+            .collect();
+
+        ChangeSet {
+            changes,
+            reset: std::mem::take(&mut self.reset),
+            diffid: self.diffid,
+        }
+    }
+}
+
+/// Consumes a Receiver of DiffEntries and passes the coalesced changes to the returned Receiver. A window starts with the first event received and lasts for the given duration.
+pub fn aggregate_stream(mut source: Receiver<DiffEntry>, window: Duration) -> Receiver<ChangeSet> {
+    let channel_size = 16;
+    let (tx, rx) = mpsc::channel::<ChangeSet>(channel_size);
+
+    tokio::spawn(async move {
+        let mut aggregator = ChangeAggregator::new();
+        while let Some(entry) = source.recv().await {
+            aggregator.push(entry);
+            let deadline = tokio::time::Instant::now() + window;
+            let mut closed = false;
+            loop {
+                match tokio::time::timeout_at(deadline, source.recv()).await {
+                    Ok(Some(entry)) => aggregator.push(entry),
+                    Ok(None) => {
+                        closed = true;
+                        break;
+                    }
+                    Err(_) => break,
+                }
+            }
+            if !aggregator.is_empty() && tx.send(aggregator.take()).await.is_err() {
+                break;
+            }
+            if closed {
+                break;
+            }
+        }
+    });
+
+    rx
+}
+
 pub struct GetFileHistoryRequestBuilder {
     /// Client to actually perform the request
     client: PCloudClient,
@@ -42,10 +383,10 @@ pub struct GetFileHistoryRequestBuilder {
 
 impl GetFileHistoryRequestBuilder {
     /// Creates a GetFileHistoryRequestBuilder instance
-    pub fn create<'a, T: FileDescriptor>(
+    pub fn create<T: FileDescriptor>(
         client: &PCloudClient,
         file_like: T,
-    ) -> Result<GetFileHistoryRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<GetFileHistoryRequestBuilder, PCloudError> {
         let file = file_like.to_file()?;
 
         let result = GetFileHistoryRequestBuilder {
@@ -57,9 +398,9 @@ impl GetFileHistoryRequestBuilder {
     }
 
     /// returns event history of a file identified by fileid. File might be a deleted one. The output format is the same as of diff method.
-    pub async fn get(self) -> Result<FileHistory, Box<dyn std::error::Error + Send + Sync>> {
-        let url = format!("{}/getfilehistory", self.client.api_host);
-        let mut r = self.client.client.get(url);
+    pub async fn get(self) -> Result<FileHistory, PCloudError> {
+        let url = format!("{}/getfilehistory", self.client.api_host());
+        let mut r = self.client.client().get(url);
 
         // Requires a file_id not a file name
         let (file_id, _) = self.client.get_file_id(self.file).await?;
@@ -68,12 +409,10 @@ impl GetFileHistoryRequestBuilder {
 
         r = self.client.add_token(r);
 
-        let result = r
-            .send()
-            .await?
-            .json::<pcloud_model::FileHistory>()
-            .await?
-            .assert_ok()?;
+        let result = self
+            .client
+            .send_checked::<pcloud_model::FileHistory>(r)
+            .await?;
 
         Ok(result)
     }
@@ -94,6 +433,10 @@ pub struct DiffRequestBuilder {
     timeout: Option<Duration>,
     /// if provided, no more than limit entries will be returned
     limit: Option<u64>,
+    /// Number of consecutive failed calls tolerated while streaming
+    max_reconnects: u32,
+    /// Delay before the first reconnect while streaming
+    reconnect_delay: Duration,
 }
 
 #[allow(dead_code)]
@@ -107,6 +450,8 @@ impl DiffRequestBuilder {
             limit: None,
             timeout: None,
             client: client.clone(),
+            max_reconnects: DEFAULT_MAX_RECONNECTS,
+            reconnect_delay: DEFAULT_RECONNECT_DELAY,
         }
     }
 
@@ -137,25 +482,38 @@ impl DiffRequestBuilder {
         self
     }
 
-    /// block is set, one should provide a connection time out duration. This is especially necessary during event streaming, or the endless blocks could happen.
+    /// block is set, one should provide a connection time out duration. This is especially necessary during event streaming, or the endless blocks could happen. `stream()` uses `DEFAULT_STREAM_BLOCK_TIMEOUT` if not set.
     pub fn block_timeout(mut self, value: Duration) -> DiffRequestBuilder {
         self.timeout = Some(value);
         self
     }
 
+    /// Number of consecutive failed calls (connection errors or invalid responses) `stream()` tolerates before it ends (default 10). Timeouts of the blocking calls are expected and never count as failure, stalled calls are abandoned and repeated right away.
+    pub fn max_reconnects(mut self, value: u32) -> DiffRequestBuilder {
+        self.max_reconnects = value;
+        self
+    }
+
+    /// Delay before `stream()` reconnects after a failed call (default 1s), doubled for each further consecutive failure up to one minute
+    pub fn reconnect_delay(mut self, value: Duration) -> DiffRequestBuilder {
+        self.reconnect_delay = value;
+        self
+    }
+
     /// if provided, no more than limit entries will be returned. If not provided ~100 entries are returned.
     pub fn limit(mut self, value: u64) -> DiffRequestBuilder {
         self.limit = Some(value);
         self
     }
 
-    /// Streams a single batch of DiffEntries to the given Sender and returns the last diff id received
+    /// Streams a single batch of DiffEntries to the given Sender and returns the last diff id received. The call is abandoned if it doesn't return within the given time.
     async fn stream_once(
         self,
         tx: &Sender<DiffEntry>,
-    ) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
+        stall_timeout: Duration,
+    ) -> Result<Option<u64>, PCloudError> {
         let diff_id = self.diff_id.clone();
-        let diffs = self.get().await?;
+        let diffs = tokio::time::timeout(stall_timeout, self.get()).await??;
 
         if diffs.entries.len() > 0 {
             if !tx.is_closed() {
@@ -179,6 +537,7 @@ impl DiffRequestBuilder {
     }
 
     /// Streams the events using the given configuration. Calls the /diff endpoint repeatedly until the channel is closed.
+    /// Each blocking call is bounded by the block timeout (even if the transport doesn't enforce it), so connections silently dropped by NATs or proxies don't stall the stream. Failed calls are repeated with increasing delay, the stream ends after `max_reconnects()` consecutive failures.
     pub fn stream(self) -> Receiver<DiffEntry> {
         // Configure size of the channel. If a batch size is set, channel size is batch size to avoid unnecessary blocking
         let channel_size = if let Some(limit) = self.limit {
@@ -191,7 +550,9 @@ impl DiffRequestBuilder {
         let (tx, rx) = mpsc::channel::<DiffEntry>(channel_size);
 
         tokio::spawn(async move {
+            let block_timeout = self.timeout.unwrap_or(DEFAULT_STREAM_BLOCK_TIMEOUT);
             let mut next_diff_id = self.diff_id;
+            let mut failures = 0;
             while !tx.is_closed() {
                 let next = DiffRequestBuilder {
                     /// There seem to be collisions when setting both after and diff_id
@@ -205,23 +566,42 @@ impl DiffRequestBuilder {
                     block: true,
                     last: self.last.clone(),
                     limit: self.limit.clone(),
-                    timeout: self.timeout.clone(),
+                    timeout: Some(block_timeout),
+                    max_reconnects: self.max_reconnects,
+                    reconnect_delay: self.reconnect_delay,
                 };
 
-                match next.stream_once(&tx).await {
+                let stall_timeout = block_timeout + STALL_GRACE.min(block_timeout);
+                match next.stream_once(&tx, stall_timeout).await {
                     Ok(diff_id) => {
                         next_diff_id = diff_id;
+                        failures = 0;
+                    }
+                    // Ignore timeout errors and try next time
+                    Err(e) if is_timeout(&e) => {
+                        debug!("No events within {:?}, calling again", block_timeout);
+                    }
+                    Err(e) if e.downcast_ref::<tokio::time::error::Elapsed>().is_some() => {
+                        warn!("Receiving events stalled, reconnecting");
+                    }
+                    Err(e) if failures >= self.max_reconnects => {
+                        warn!(
+                            "Giving up receiving events after {} reconnects: {}",
+                            failures, e
+                        );
+                        break;
                     }
                     Err(e) => {
-                        if let Some(err) = e.downcast_ref::<reqwest::Error>() {
-                            // Ignore timeout errors and try next time
-                            if !err.is_timeout() {
-                                warn!("Connection errors during receiving events: {}", err);
-                                break;
-                            }
-                        } else {
-                            break;
-                        }
+                        let delay = self
+                            .reconnect_delay
+                            .saturating_mul(2u32.saturating_pow(failures))
+                            .min(MAX_RECONNECT_DELAY);
+                        failures += 1;
+                        warn!(
+                            "Connection errors during receiving events: {}, reconnecting in {:?}",
+                            e, delay
+                        );
+                        tokio::time::sleep(delay).await;
                     }
                 }
             }
@@ -230,10 +610,85 @@ impl DiffRequestBuilder {
         rx
     }
 
+    /// Streams the events like `stream()`, but as `futures::Stream`, e.g. to use stream combinators. Dropping the stream stops calling the /diff endpoint.
+    pub fn into_stream(self) -> impl Stream<Item = DiffEntry> + Send + Unpin {
+        let rx = self.stream();
+        Box::pin(futures::stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|entry| (entry, rx))
+        }))
+    }
+
+    /// Streams the events like `stream()`, but coalesced into change sets per window
+    pub fn stream_changes(self, window: Duration) -> Receiver<ChangeSet> {
+        aggregate_stream(self.stream(), window)
+    }
+
+    /// Fetches all events since the diff id set with `after_diff_id()` (in batches of `limit()`), checking that they continue the stored state. Fails with `ResyncRequired` if a `reset` event was received or events between the stored diff id and the first one received are missing.
+    pub async fn replay(self) -> Result<Diff, PCloudError> {
+        let stored = self.diff_id.unwrap_or_default();
+        let mut diff = Diff {
+            diffid: stored,
+            entries: Vec::new(),
+        };
+        loop {
+            let mut batch = DiffRequestBuilder {
+                client: self.client.clone(),
+                diff_id: Some(diff.diffid),
+                after: None,
+                last: None,
+                block: false,
+                timeout: self.timeout,
+                limit: self.limit,
+                max_reconnects: self.max_reconnects,
+                reconnect_delay: self.reconnect_delay,
+            }
+            .get()
+            .await?;
+            // Like in `stream()`, the event of the diff id itself may be returned again
+            batch.entries.retain(|e| e.diffid > diff.diffid);
+
+            let first = batch.entries.first().map(|e| e.diffid);
+            match first {
+                None if batch.diffid < stored => Err(ResyncRequired {
+                    reason: ResyncReason::Behind {
+                        stored,
+                        latest: batch.diffid,
+                    },
+                    diffid: batch.diffid,
+                })?,
+                None => return Ok(diff),
+                Some(received) if diff.entries.is_empty() && received != stored + 1 => {
+                    Err(ResyncRequired {
+                        reason: ResyncReason::Gap {
+                            expected: stored + 1,
+                            received,
+                        },
+                        diffid: batch.diffid,
+                    })?
+                }
+                Some(_) => {}
+            }
+            if let Some(reset) = batch.entries.iter().find(|e| e.event == DiffEvent::Reset) {
+                Err(ResyncRequired {
+                    reason: ResyncReason::Reset,
+                    diffid: reset.diffid,
+                })?
+            }
+
+            debug!(
+                "Replaying {} events after {}",
+                batch.entries.len(),
+                diff.diffid
+            );
+            diff.diffid = batch.diffid;
+            diff.entries.extend(batch.entries);
+        }
+    }
+
     /// Fetches the events. No matter you configure the limit, not all events could be fetched at once. Therefore one has to call repeatedly with the diffid of the last result set in the next call.
-    pub async fn get(self) -> Result<Diff, Box<dyn std::error::Error + Send + Sync>> {
-        let url = format!("{}/diff", self.client.api_host);
-        let mut r = self.client.client.get(url);
+    pub async fn get(self) -> Result<Diff, PCloudError> {
+        let url = format!("{}/diff", self.client.api_host());
+        let mut r = self.client.client().get(url);
 
         if let Some(v) = self.diff_id {
             r = r.query(&[("diffid", v)]);
@@ -263,12 +718,26 @@ impl DiffRequestBuilder {
 
         r = self.client.add_token(r);
 
-        let diff = r.send().await?.json::<pcloud_model::Diff>().await?;
+        let diff = self.client.send_json::<pcloud_model::Diff>(r).await?;
 
         Ok(diff)
     }
 }
 
+/// Checks if the error (or any of its sources, like the one of a `CorrelatedError`) is a timeout of the request
+fn is_timeout(error: &(dyn std::error::Error + 'static)) -> bool {
+    let mut current = Some(error);
+    while let Some(e) = current {
+        if e.downcast_ref::<reqwest::Error>()
+            .is_some_and(|e| e.is_timeout())
+        {
+            return true;
+        }
+        current = e.source();
+    }
+    false
+}
+
 impl PCloudClient {
     /// List events on the users pCloud account.
     /// see https://docs.pcloud.com/methods/general/diff.html for details
@@ -276,11 +745,33 @@ impl PCloudClient {
         DiffRequestBuilder::create(self)
     }
 
+    /// Long-polls the /diff endpoint and yields all events of the account as `futures::Stream`. The latest diff id is tracked internally and timed out or failed calls are repeated (see `DiffRequestBuilder::stream()`). To continue after a stored diff id, use `get_events().after_diff_id(id).into_stream()`.
+    pub fn diff_stream(&self) -> impl Stream<Item = DiffEntry> + Send + Unpin {
+        self.get_events().into_stream()
+    }
+
+    /// Rebuilds the baseline state of a folder after `ResyncRequired`: takes the latest diff id and lists the folder recursively afterwards, so no event is missed when replaying from the returned diff id. Accepts either a folder id (u64), a folder path (String) or any other pCloud object describing a folder (like Metadata)
+    pub async fn resync_baseline<T: FolderDescriptor>(
+        &self,
+        folder_like: T,
+    ) -> Result<ResyncBaseline, PCloudError> {
+        let diffid = self.get_events().only_last(0).get().await?.diffid;
+        let metadata = self
+            .list_folder(folder_like)?
+            .recursive(true)
+            .get()
+            .await?
+            .metadata
+            .ok_or(pcloud_model::PCloudResult::DirectoryDoesNotExist)?;
+        debug!("Rebuilt baseline at diff id {}", diffid);
+        Ok(ResyncBaseline { diffid, metadata })
+    }
+
     /// returns event history of a file. File might be a deleted one.
-    pub async fn get_file_history<'a, T: FileDescriptor>(
+    pub async fn get_file_history<T: FileDescriptor>(
         &self,
         file_like: T,
-    ) -> Result<FileHistory, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<FileHistory, PCloudError> {
         let result = GetFileHistoryRequestBuilder::create(self, file_like)?
             .get()
             .await?;
@@ -0,0 +1,1937 @@
+#![cfg(feature = "fake")]
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap},
+    convert::Infallible,
+    error::Error,
+    hash::{Hash, Hasher},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, TimeZone, Utc};
+use futures::future::BoxFuture;
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Server,
+};
+use log::debug;
+use reqwest::{Request, Response, Url};
+use serde_json::{json, Value};
+use sha1::{Digest, Sha1};
+use tokio::sync::oneshot;
+
+use crate::{
+    error::PCloudError,
+    pcloud_client::{PCloudClient, PCloudClientBuilder},
+    pcloud_model::PCloudResult,
+    permissions::Permissions,
+    transport::HttpTransport,
+};
+
+/// Date format used by pCloud
+const DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S %z";
+/// Host returned for download links
+const DOWNLOAD_HOST: &str = "fake.pcloud.local";
+/// Id of the root folder
+const ROOT_FOLDER_ID: u64 = 0;
+
+struct FakeFolder {
+    parent: u64,
+    name: String,
+    created: DateTime<Utc>,
+    modified: DateTime<Utc>,
+}
+
+struct FakeFile {
+    parent: u64,
+    name: String,
+    content: Vec<u8>,
+    created: DateTime<Utc>,
+    modified: DateTime<Utc>,
+    /// Previous contents, oldest first
+    revisions: Vec<FakeRevision>,
+}
+
+/// Previous content of a file, as listed by `listrevisions`
+struct FakeRevision {
+    id: u64,
+    content: Vec<u8>,
+    created: DateTime<Utc>,
+}
+
+/// Collection (playlist) of files
+struct FakeCollection {
+    name: String,
+    files: Vec<u64>,
+    created: DateTime<Utc>,
+    modified: DateTime<Utc>,
+}
+
+/// Comment on a file
+struct FakeComment {
+    fileid: u64,
+    text: String,
+    created: DateTime<Utc>,
+}
+
+/// Folder shared by another user with the permissions granted
+struct FakeShare {
+    owner: u64,
+    permissions: Permissions,
+}
+
+/// Upload link into a folder
+struct FakeUploadLink {
+    folder: u64,
+    comment: String,
+    created: DateTime<Utc>,
+}
+
+/// File opened with `file_open`
+struct FakeOpenFile {
+    file: u64,
+    /// Current offset of the descriptor
+    offset: u64,
+    /// Whether writes always append to the end
+    append: bool,
+}
+
+/// How to handle existing files when copying folders
+#[derive(Clone, Copy, PartialEq)]
+enum Conflict {
+    Overwrite,
+    Fail,
+    Skip,
+}
+
+/// In-memory file system of the fake
+struct FakeState {
+    folders: BTreeMap<u64, FakeFolder>,
+    files: BTreeMap<u64, FakeFile>,
+    /// Next id for files and folders. Unique across both.
+    next_id: u64,
+    /// Public link codes and their files
+    links: HashMap<String, u64>,
+    /// Public link codes and their folders
+    folder_links: HashMap<String, u64>,
+    /// Events returned by `diff`, the diff id of each event is its position + 1
+    events: Vec<Value>,
+    /// Number of oldest events no longer returned by `diff`, like pCloud expiring its event history
+    expired_events: usize,
+    /// Collections by id
+    collections: BTreeMap<u64, FakeCollection>,
+    /// File comments by id
+    comments: BTreeMap<u64, FakeComment>,
+    /// Folders shared by other users by id, their contents share the permissions
+    shares: HashMap<u64, FakeShare>,
+    /// Upload links by id
+    upload_links: BTreeMap<u64, FakeUploadLink>,
+    /// Metadata of the deleted files and folders (with their contents at deletion) in the trash
+    trash: Vec<Value>,
+    /// Files opened with `file_open` by descriptor
+    open_files: HashMap<u64, FakeOpenFile>,
+    /// Next file descriptor
+    next_fd: u64,
+}
+
+type FakeResult = Result<Value, PCloudResult>;
+
+impl FakeState {
+    fn new() -> FakeState {
+        let now = Utc::now();
+        let mut folders = BTreeMap::new();
+        folders.insert(
+            ROOT_FOLDER_ID,
+            FakeFolder {
+                parent: ROOT_FOLDER_ID,
+                name: "/".to_string(),
+                created: now,
+                modified: now,
+            },
+        );
+        FakeState {
+            folders,
+            files: BTreeMap::new(),
+            next_id: 1,
+            links: HashMap::new(),
+            folder_links: HashMap::new(),
+            events: Vec::new(),
+            expired_events: 0,
+            collections: BTreeMap::new(),
+            comments: BTreeMap::new(),
+            shares: HashMap::new(),
+            upload_links: BTreeMap::new(),
+            trash: Vec::new(),
+            open_files: HashMap::new(),
+            next_fd: 1,
+        }
+    }
+
+    /// Records an event returned by `diff`
+    fn record(&mut self, event: &str, metadata: Value) {
+        let diffid = self.events.len() as u64 + 1;
+        self.events.push(json!({
+            "event": event,
+            "diffid": diffid,
+            "time": Utc::now().format(DATE_FORMAT).to_string(),
+            "metadata": metadata,
+        }));
+    }
+
+    /// Handles the methods of the file descriptor API, writes take the raw request body
+    fn file_op(
+        &mut self,
+        method: &str,
+        params: &HashMap<String, String>,
+        data: &[u8],
+    ) -> FakeResult {
+        if method == "file_open" {
+            return self.open_file(params);
+        }
+        let fd = params
+            .get("fd")
+            .and_then(|fd| fd.parse::<u64>().ok())
+            .filter(|fd| self.open_files.contains_key(fd))
+            .ok_or(PCloudResult::InvalidFileDescriptor)?;
+        let open = &self.open_files[&fd];
+        let size = self
+            .files
+            .get(&open.file)
+            .map(|f| f.content.len() as u64)
+            .ok_or(PCloudResult::InvalidFileDescriptor)?;
+        match method {
+            "file_write" | "file_pwrite" => {
+                let offset = if method == "file_pwrite" {
+                    offset_param(params)?
+                } else if open.append {
+                    size
+                } else {
+                    open.offset
+                };
+                let file = self.files.get_mut(&open.file).unwrap();
+                let end = offset as usize + data.len();
+                if file.content.len() < end {
+                    file.content.resize(end, 0);
+                }
+                file.content[offset as usize..end].copy_from_slice(data);
+                file.modified = Utc::now();
+                if method == "file_write" {
+                    self.open_files.get_mut(&fd).unwrap().offset = end as u64;
+                }
+                Ok(json!({ "result": 0, "bytes": data.len() }))
+            }
+            "file_seek" => {
+                let offset: i64 = params
+                    .get("offset")
+                    .and_then(|v| v.parse().ok())
+                    .ok_or(PCloudResult::ProvideOffset)?;
+                let base = match params.get("whence").map(|v| v.as_str()) {
+                    None | Some("0") => 0,
+                    Some("1") => open.offset as i64,
+                    Some("2") => size as i64,
+                    Some(_) => return Err(PCloudResult::InvalidWhence),
+                };
+                let offset =
+                    u64::try_from(base + offset).map_err(|_| PCloudResult::ProvideOffset)?;
+                self.open_files.get_mut(&fd).unwrap().offset = offset;
+                Ok(json!({ "result": 0, "offset": offset }))
+            }
+            "file_truncate" => {
+                let length: usize = params
+                    .get("length")
+                    .and_then(|v| v.parse().ok())
+                    .ok_or(PCloudResult::ProvideLength)?;
+                let file = self.files.get_mut(&open.file).unwrap();
+                file.content.resize(length, 0);
+                file.modified = Utc::now();
+                Ok(json!({ "result": 0 }))
+            }
+            "file_size" => Ok(json!({ "result": 0, "size": size, "offset": open.offset })),
+            "file_close" => {
+                self.open_files.remove(&fd);
+                Ok(json!({ "result": 0 }))
+            }
+            _ => Err(PCloudResult::InternalError),
+        }
+    }
+
+    /// Opens (or creates) the file given by `fileid`, `path` or `folderid` and `name`
+    fn open_file(&mut self, params: &HashMap<String, String>) -> FakeResult {
+        let flags: u64 = params
+            .get("flags")
+            .and_then(|v| v.parse().ok())
+            .ok_or(PCloudResult::ProvideFlags)?;
+        let (create, exclusive, truncate, append) = (
+            flags & 0x0040 != 0,
+            flags & 0x0080 != 0,
+            flags & 0x0200 != 0,
+            flags & 0x0400 != 0,
+        );
+        let (parent, name) = if params.contains_key("fileid") {
+            let id = self.file_param(params)?;
+            (self.files[&id].parent, self.files[&id].name.clone())
+        } else if let Some(path) = params.get("path") {
+            let (parent, name) = FakeState::split_path(path);
+            let parent = self
+                .folder_by_path(&parent)
+                .map_err(|_| PCloudResult::ComponentOfTheParentDirectoryDoesNotExist)?;
+            (parent, name)
+        } else {
+            let parent = self.folder_param(params)?;
+            let name = params
+                .get("name")
+                .ok_or(PCloudResult::NoFullPathOrNameOrFolderIdProvided)?;
+            (parent, name.clone())
+        };
+        let id = match self.child_file(parent, &name) {
+            Some(_) if create && exclusive => return Err(PCloudResult::FileOrFolderAlreadyExists),
+            Some(id) => {
+                if truncate {
+                    let file = self.files.get_mut(&id).unwrap();
+                    file.content.clear();
+                    file.modified = Utc::now();
+                }
+                id
+            }
+            None if create => self.write_file(parent, &name, Vec::new()),
+            None => return Err(PCloudResult::FileNotFound),
+        };
+        let fd = self.next_fd;
+        self.next_fd += 1;
+        self.open_files.insert(
+            fd,
+            FakeOpenFile {
+                file: id,
+                offset: 0,
+                append,
+            },
+        );
+        Ok(json!({ "result": 0, "fd": fd, "fileid": id }))
+    }
+
+    /// Reads `count` bytes of an open file at its offset (or at `offset`, if positional)
+    fn read_fd(
+        &mut self,
+        params: &HashMap<String, String>,
+        positional: bool,
+    ) -> Result<Vec<u8>, PCloudResult> {
+        let fd = params
+            .get("fd")
+            .and_then(|fd| fd.parse::<u64>().ok())
+            .ok_or(PCloudResult::InvalidFileDescriptor)?;
+        let open = self
+            .open_files
+            .get_mut(&fd)
+            .ok_or(PCloudResult::InvalidFileDescriptor)?;
+        let count: usize = params
+            .get("count")
+            .and_then(|v| v.parse().ok())
+            .ok_or(PCloudResult::ProvideCount)?;
+        let offset = if positional {
+            offset_param(params)?
+        } else {
+            open.offset
+        };
+        let content = &self
+            .files
+            .get(&open.file)
+            .ok_or(PCloudResult::InvalidFileDescriptor)?
+            .content;
+        let start = (offset as usize).min(content.len());
+        let end = (start + count).min(content.len());
+        if !positional {
+            open.offset = end as u64;
+        }
+        Ok(content[start..end].to_vec())
+    }
+
+    /// Metadata of a deleted file or folder, as reported by `diff` and the delete methods
+    fn deleted_metadata(&self, id: u64, isfolder: bool) -> Value {
+        let mut metadata = if isfolder {
+            self.folder_metadata(id, false, false, false)
+        } else {
+            self.file_metadata(id)
+        };
+        metadata["isdeleted"] = json!(true);
+        metadata
+    }
+
+    /// Metadata of the trash root folder, optionally with the (recursive) trashed items
+    fn trash_metadata(&self, recursive: bool) -> Value {
+        let contents: Vec<Value> = self
+            .trash
+            .iter()
+            .map(|item| {
+                let mut item = item.clone();
+                if !recursive {
+                    if let Some(item) = item.as_object_mut() {
+                        item.remove("contents");
+                    }
+                }
+                item
+            })
+            .collect();
+        json!({
+            "name": "Trash",
+            "created": Utc::now().format(DATE_FORMAT).to_string(),
+            "modified": Utc::now().format(DATE_FORMAT).to_string(),
+            "ismine": true,
+            "thumb": false,
+            "isshared": false,
+            "icon": "folder",
+            "isfolder": true,
+            "id": "d0",
+            "folderid": 0,
+            "contents": contents,
+        })
+    }
+
+    /// Size of all files in the trash
+    fn trash_size(&self) -> u64 {
+        fn size(item: &Value) -> u64 {
+            item["size"].as_u64().unwrap_or_default()
+                + item["contents"]
+                    .as_array()
+                    .map(|c| c.iter().map(size).sum::<u64>())
+                    .unwrap_or_default()
+        }
+        self.trash.iter().map(size).sum()
+    }
+
+    fn next_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    fn child_folder(&self, parent: u64, name: &str) -> Option<u64> {
+        self.folders
+            .iter()
+            .find(|(id, f)| **id != ROOT_FOLDER_ID && f.parent == parent && f.name == name)
+            .map(|(id, _)| *id)
+    }
+
+    /// Checks if the file is (directly or indirectly) contained in the folder
+    fn is_below(&self, file: u64, folder: u64) -> bool {
+        let mut current = self.files[&file].parent;
+        loop {
+            if current == folder {
+                return true;
+            }
+            if current == ROOT_FOLDER_ID {
+                return false;
+            }
+            current = self.folders[&current].parent;
+        }
+    }
+
+    fn child_file(&self, parent: u64, name: &str) -> Option<u64> {
+        self.files
+            .iter()
+            .find(|(_, f)| f.parent == parent && f.name == name)
+            .map(|(id, _)| *id)
+    }
+
+    /// Fake thumbnail of a file, made of the size and the checksum of the content
+    fn thumbnail(&self, id: u64, size: &str) -> Option<String> {
+        let file = self.files.get(&id)?;
+        Some(format!("thumb {} {:x}", size, Sha1::digest(&file.content)))
+    }
+
+    /// Splits a path into the path of the parent folder and the name
+    fn split_path(path: &str) -> (String, String) {
+        let trimmed = path.trim_end_matches('/');
+        match trimmed.rfind('/') {
+            Some(index) => (
+                trimmed[..index].to_string(),
+                trimmed[index + 1..].to_string(),
+            ),
+            None => (String::new(), trimmed.to_string()),
+        }
+    }
+
+    fn folder_by_path(&self, path: &str) -> Result<u64, PCloudResult> {
+        let mut current = ROOT_FOLDER_ID;
+        for name in path.split('/').filter(|n| !n.is_empty()) {
+            current = self
+                .child_folder(current, name)
+                .ok_or(PCloudResult::DirectoryDoesNotExist)?;
+        }
+        Ok(current)
+    }
+
+    fn file_by_path(&self, path: &str) -> Result<u64, PCloudResult> {
+        let (parent, name) = FakeState::split_path(path);
+        let parent = self
+            .folder_by_path(&parent)
+            .map_err(|_| PCloudResult::ComponentOfTheParentDirectoryDoesNotExist)?;
+        self.child_file(parent, &name)
+            .ok_or(PCloudResult::FileNotFound)
+    }
+
+    /// Determines the folder given by either `folderid` or `path`
+    fn folder_param(&self, params: &HashMap<String, String>) -> Result<u64, PCloudResult> {
+        if let Some(id) = params.get("folderid") {
+            let id = id.parse().map_err(|_| PCloudResult::InvalidFolderId)?;
+            if self.folders.contains_key(&id) {
+                Ok(id)
+            } else {
+                Err(PCloudResult::DirectoryDoesNotExist)
+            }
+        } else if let Some(path) = params.get("path") {
+            self.folder_by_path(path)
+        } else {
+            Err(PCloudResult::NoFullPathOrFolderIdProvided)
+        }
+    }
+
+    /// Determines the file given by either `fileid` or `path`
+    fn file_param(&self, params: &HashMap<String, String>) -> Result<u64, PCloudResult> {
+        if let Some(id) = params.get("fileid") {
+            let id = id.parse().map_err(|_| PCloudResult::InvalidFileId)?;
+            if self.files.contains_key(&id) {
+                Ok(id)
+            } else {
+                Err(PCloudResult::FileNotFound)
+            }
+        } else if let Some(path) = params.get("path") {
+            self.file_by_path(path)
+        } else {
+            Err(PCloudResult::NoFileIdOrPathProvided)
+        }
+    }
+
+    fn collection_param(&self, params: &HashMap<String, String>) -> Result<u64, PCloudResult> {
+        params
+            .get("collectionid")
+            .and_then(|id| id.parse::<u64>().ok())
+            .filter(|id| self.collections.contains_key(id))
+            .ok_or(PCloudResult::AccessDenied)
+    }
+
+    /// Ids of the existing files given by the comma separated `fileids`
+    fn file_ids_param(&self, params: &HashMap<String, String>) -> Result<Vec<u64>, PCloudResult> {
+        params
+            .get("fileids")
+            .map(|ids| ids.split(',').collect::<Vec<_>>())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|id| {
+                id.parse::<u64>()
+                    .ok()
+                    .filter(|id| self.files.contains_key(id))
+                    .ok_or(PCloudResult::FileNotFound)
+            })
+            .collect()
+    }
+
+    /// Determines the target folder and name given by `topath`, `tofolderid` and `toname`
+    fn target_param(
+        &self,
+        params: &HashMap<String, String>,
+        source_parent: u64,
+        source_name: &str,
+    ) -> Result<(u64, String), PCloudResult> {
+        if let Some(path) = params.get("topath") {
+            if path.ends_with('/') {
+                let name = params
+                    .get("toname")
+                    .cloned()
+                    .unwrap_or_else(|| source_name.to_string());
+                Ok((self.folder_by_path(path)?, name))
+            } else {
+                let (parent, name) = FakeState::split_path(path);
+                let parent = self
+                    .folder_by_path(&parent)
+                    .map_err(|_| PCloudResult::ComponentOfTheParentDirectoryDoesNotExist)?;
+                Ok((parent, name))
+            }
+        } else if let Some(id) = params.get("tofolderid") {
+            let id = id.parse().map_err(|_| PCloudResult::InvalidFolderId)?;
+            if !self.folders.contains_key(&id) {
+                return Err(PCloudResult::DirectoryDoesNotExist);
+            }
+            let name = params
+                .get("toname")
+                .cloned()
+                .unwrap_or_else(|| source_name.to_string());
+            Ok((id, name))
+        } else if let Some(name) = params.get("toname") {
+            Ok((source_parent, name.clone()))
+        } else {
+            Err(PCloudResult::ProvidedAtLeastToPathOrToFolderIdOrToName)
+        }
+    }
+
+    fn folder_path(&self, id: u64) -> String {
+        if id == ROOT_FOLDER_ID {
+            return "/".to_string();
+        }
+        let folder = &self.folders[&id];
+        let parent = self.folder_path(folder.parent);
+        format!("{}/{}", parent.trim_end_matches('/'), folder.name)
+    }
+
+    fn is_descendant(&self, folder: u64, ancestor: u64) -> bool {
+        let mut current = folder;
+        while current != ROOT_FOLDER_ID {
+            if current == ancestor {
+                return true;
+            }
+            current = self.folders[&current].parent;
+        }
+        ancestor == ROOT_FOLDER_ID
+    }
+
+    fn file_metadata(&self, id: u64) -> Value {
+        let file = &self.files[&id];
+        let mut hasher = DefaultHasher::new();
+        file.content.hash(&mut hasher);
+        let mut metadata = json!({
+            "name": file.name,
+            "created": file.created.format(DATE_FORMAT).to_string(),
+            "modified": file.modified.format(DATE_FORMAT).to_string(),
+            "ismine": true,
+            "thumb": false,
+            "isshared": false,
+            "icon": "file",
+            "isfolder": false,
+            "id": format!("f{}", id),
+            "fileid": id,
+            "parentfolderid": file.parent,
+            "size": file.content.len(),
+            "contenttype": "application/octet-stream",
+            "hash": hasher.finish(),
+        });
+        self.add_permissions(&mut metadata, file.parent, false);
+        metadata
+    }
+
+    /// Share of the folder or of the nearest parent folder shared by another user
+    fn share_of(&self, mut folder: u64) -> Option<&FakeShare> {
+        loop {
+            if let Some(share) = self.shares.get(&folder) {
+                return Some(share);
+            }
+            if folder == ROOT_FOLDER_ID {
+                return None;
+            }
+            folder = self.folders[&folder].parent;
+        }
+    }
+
+    /// Marks the metadata as owned by another user, if in a shared folder
+    fn add_permissions(&self, metadata: &mut Value, folder: u64, isfolder: bool) {
+        if let Some(share) = self.share_of(folder) {
+            metadata["ismine"] = json!(false);
+            metadata["userid"] = json!(share.owner);
+            metadata["canread"] = json!(share.permissions.can_read());
+            metadata["canmodify"] = json!(share.permissions.can_modify());
+            metadata["candelete"] = json!(share.permissions.can_delete());
+            if isfolder {
+                metadata["cancreate"] = json!(share.permissions.can_create());
+            }
+        }
+    }
+
+    /// Metadata of a collection, optionally with (at most `limit`) files of the collection
+    fn collection_metadata(&self, id: u64, files: bool, limit: Option<usize>) -> Value {
+        let collection = &self.collections[&id];
+        let mut metadata = json!({
+            "id": id,
+            "name": collection.name,
+            "ismine": true,
+            "system": false,
+            "type": 1,
+            "items": collection.files.iter().filter(|f| self.files.contains_key(f)).count(),
+            "created": collection.created.format(DATE_FORMAT).to_string(),
+            "modified": collection.modified.format(DATE_FORMAT).to_string(),
+        });
+        if files {
+            let contents: Vec<Value> = collection
+                .files
+                .iter()
+                .filter(|f| self.files.contains_key(f))
+                .take(limit.unwrap_or(usize::MAX))
+                .map(|f| self.file_metadata(*f))
+                .collect();
+            metadata["contents"] = json!(contents);
+        }
+        metadata
+    }
+
+    /// Upload link as returned by `listuploadlinks`. All files of the folder count as uploaded through the link.
+    fn upload_link_metadata(&self, id: u64) -> Value {
+        let link = &self.upload_links[&id];
+        let files: Vec<&FakeFile> = self
+            .files
+            .values()
+            .filter(|f| f.parent == link.folder)
+            .collect();
+        let modified = files
+            .iter()
+            .map(|f| f.modified)
+            .chain([link.created])
+            .max()
+            .unwrap_or(link.created);
+        let code = format!("fakeupload{}", id);
+        json!({
+            "id": id,
+            "comment": link.comment,
+            "link": format!("https://{}/#page=puplink&code={}", DOWNLOAD_HOST, code),
+            "code": code,
+            "files": files.len(),
+            "space": files.iter().map(|f| f.content.len()).sum::<usize>(),
+            "created": link.created.format(DATE_FORMAT).to_string(),
+            "modified": modified.format(DATE_FORMAT).to_string(),
+            "metadata": self.folder_metadata(link.folder, false, false, false),
+        })
+    }
+
+    /// Comment as returned by the comment methods, all comments are posted by the fake user
+    fn comment_metadata(&self, id: u64) -> Value {
+        let comment = &self.comments[&id];
+        json!({
+            "id": id,
+            "fileid": comment.fileid,
+            "comment": comment.text,
+            "author": { "userid": 1, "email": "fake@pcloud.local" },
+            "created": comment.created.format(DATE_FORMAT).to_string(),
+        })
+    }
+
+    fn folder_metadata(&self, id: u64, contents: bool, recursive: bool, nofiles: bool) -> Value {
+        let folder = &self.folders[&id];
+        let mut result = json!({
+            "name": folder.name,
+            "path": self.folder_path(id),
+            "created": folder.created.format(DATE_FORMAT).to_string(),
+            "modified": folder.modified.format(DATE_FORMAT).to_string(),
+            "ismine": true,
+            "thumb": false,
+            "isshared": false,
+            "icon": "folder",
+            "isfolder": true,
+            "id": format!("d{}", id),
+            "folderid": id,
+        });
+        if id != ROOT_FOLDER_ID {
+            result["parentfolderid"] = json!(folder.parent);
+        }
+        self.add_permissions(&mut result, id, true);
+        if contents {
+            let mut children: Vec<Value> = self
+                .folders
+                .iter()
+                .filter(|(child, f)| **child != ROOT_FOLDER_ID && f.parent == id)
+                .map(|(child, _)| self.folder_metadata(*child, recursive, recursive, nofiles))
+                .collect();
+            if !nofiles {
+                children.extend(
+                    self.files
+                        .iter()
+                        .filter(|(_, f)| f.parent == id)
+                        .map(|(child, _)| self.file_metadata(*child)),
+                );
+            }
+            result["contents"] = Value::Array(children);
+        }
+        result
+    }
+
+    /// Creates a new folder, fails if it already exists
+    fn create_folder(&mut self, parent: u64, name: &str) -> Result<u64, PCloudResult> {
+        if name.is_empty() || name.contains('/') {
+            return Err(PCloudResult::InvalidFileOrFolderName);
+        }
+        if self.child_folder(parent, name).is_some() || self.child_file(parent, name).is_some() {
+            return Err(PCloudResult::FileOrFolderAlreadyExists);
+        }
+        let now = Utc::now();
+        let id = self.next_id();
+        self.folders.insert(
+            id,
+            FakeFolder {
+                parent,
+                name: name.to_string(),
+                created: now,
+                modified: now,
+            },
+        );
+        self.record(
+            "createfolder",
+            self.folder_metadata(id, false, false, false),
+        );
+        Ok(id)
+    }
+
+    /// Writes a file, replacing the content of an existing file with the same name
+    fn write_file(&mut self, parent: u64, name: &str, content: Vec<u8>) -> u64 {
+        let now = Utc::now();
+        if let Some(id) = self.child_file(parent, name) {
+            let revision = self.next_id();
+            let file = self.files.get_mut(&id).unwrap();
+            let previous = std::mem::replace(&mut file.content, content);
+            file.revisions.push(FakeRevision {
+                id: revision,
+                content: previous,
+                created: file.modified,
+            });
+            file.modified = now;
+            self.record("modifyfile", self.file_metadata(id));
+            return id;
+        }
+        let id = self.next_id();
+        self.files.insert(
+            id,
+            FakeFile {
+                parent,
+                name: name.to_string(),
+                content,
+                created: now,
+                modified: now,
+                revisions: Vec::new(),
+            },
+        );
+        self.record("createfile", self.file_metadata(id));
+        id
+    }
+
+    /// Creates all missing folders of the path
+    fn create_folders(&mut self, path: &str) -> Result<u64, PCloudResult> {
+        let mut current = ROOT_FOLDER_ID;
+        for name in path.split('/').filter(|n| !n.is_empty()) {
+            current = match self.child_folder(current, name) {
+                Some(id) => id,
+                None => self.create_folder(current, name)?,
+            };
+        }
+        Ok(current)
+    }
+
+    /// Returns all folders and files below the given folder
+    fn descendants(&self, id: u64) -> (Vec<u64>, Vec<u64>) {
+        let mut folders = vec![];
+        let mut files: Vec<u64> = self
+            .files
+            .iter()
+            .filter(|(_, f)| f.parent == id)
+            .map(|(file, _)| *file)
+            .collect();
+        for (child, _) in self
+            .folders
+            .iter()
+            .filter(|(child, f)| **child != ROOT_FOLDER_ID && f.parent == id)
+        {
+            folders.push(*child);
+            let (sub_folders, sub_files) = self.descendants(*child);
+            folders.extend(sub_folders);
+            files.extend(sub_files);
+        }
+        (folders, files)
+    }
+
+    fn copy_folder_contents(
+        &mut self,
+        source: u64,
+        target: u64,
+        conflict: Conflict,
+    ) -> Result<(), PCloudResult> {
+        let files: Vec<(String, Vec<u8>)> = self
+            .files
+            .values()
+            .filter(|f| f.parent == source)
+            .map(|f| (f.name.clone(), f.content.clone()))
+            .collect();
+        for (name, content) in files {
+            if self.child_file(target, &name).is_some() {
+                match conflict {
+                    Conflict::Fail => return Err(PCloudResult::FileOrFolderAlreadyExists),
+                    Conflict::Skip => continue,
+                    Conflict::Overwrite => {}
+                }
+            }
+            self.write_file(target, &name, content);
+        }
+
+        let folders: Vec<(u64, String)> = self
+            .folders
+            .iter()
+            .filter(|(id, f)| **id != ROOT_FOLDER_ID && f.parent == source)
+            .map(|(id, f)| (*id, f.name.clone()))
+            .collect();
+        for (id, name) in folders {
+            let child = match self.child_folder(target, &name) {
+                Some(child) => child,
+                None => self.create_folder(target, &name)?,
+            };
+            self.copy_folder_contents(id, child, conflict)?;
+        }
+        Ok(())
+    }
+
+    /// Handles a pCloud API call
+    fn call(
+        &mut self,
+        method: &str,
+        params: &HashMap<String, String>,
+        uploads: Vec<(String, Vec<u8>)>,
+    ) -> FakeResult {
+        match method {
+            "userinfo" => {
+                let mut result = json!({
+                    "result": 0,
+                    "userid": 1,
+                    "email": "fake@pcloud.local",
+                    "emailverified": true,
+                    "registered": Utc::now().format(DATE_FORMAT).to_string(),
+                    "premium": false,
+                    "quota": 10_000_000_000u64,
+                    "usedquota": self.files.values().map(|f| f.content.len() as u64).sum::<u64>() + self.trash_size(),
+                });
+                if flag(params, "getauth") {
+                    result["auth"] = json!("fake-auth-token");
+                }
+                Ok(result)
+            }
+            "listtokens" => {
+                let now = Utc::now();
+                Ok(json!({
+                    "result": 0,
+                    "tokens": [{
+                        "tokenid": 1,
+                        "device": "FakePCloud",
+                        "current": true,
+                        "created": now.format(DATE_FORMAT).to_string(),
+                        "expires": (now + chrono::Duration::days(365)).format(DATE_FORMAT).to_string(),
+                    }],
+                }))
+            }
+            "logout" => Ok(json!({ "result": 0, "auth_deleted": true })),
+            "getapiserver" => Ok(json!({ "result": 0, "api": [DOWNLOAD_HOST], "binapi": [] })),
+            "currentserver" => {
+                Ok(json!({ "result": 0, "ip": "127.0.0.1", "hostname": DOWNLOAD_HOST }))
+            }
+            "listfolder" => {
+                let id = self.folder_param(params)?;
+                let recursive = flag(params, "recursive");
+                let nofiles = flag(params, "nofiles");
+                Ok(json!({
+                    "result": 0,
+                    "metadata": self.folder_metadata(id, true, recursive, nofiles),
+                }))
+            }
+            "createfolder" | "createfolderifnotexists" => {
+                let parent = self.folder_param(params)?;
+                let name = params
+                    .get("name")
+                    .ok_or(PCloudResult::InvalidFileOrFolderName)?;
+                let id = match self.create_folder(parent, name) {
+                    Ok(id) => id,
+                    Err(PCloudResult::FileOrFolderAlreadyExists)
+                        if method == "createfolderifnotexists" =>
+                    {
+                        self.child_folder(parent, name)
+                            .ok_or(PCloudResult::FileOrFolderAlreadyExists)?
+                    }
+                    Err(e) => return Err(e),
+                };
+                Ok(
+                    json!({ "result": 0, "metadata": self.folder_metadata(id, false, false, false) }),
+                )
+            }
+            "deletefolder" => {
+                let id = self.folder_param(params)?;
+                if id == ROOT_FOLDER_ID {
+                    return Err(PCloudResult::CanNotDeleteRootFolder);
+                }
+                let (folders, files) = self.descendants(id);
+                if !folders.is_empty() || !files.is_empty() {
+                    return Err(PCloudResult::FolderIsNotEmpty);
+                }
+                let metadata = self.deleted_metadata(id, true);
+                self.folders.remove(&id);
+                self.trash.push(metadata.clone());
+                self.record("deletefolder", metadata.clone());
+                Ok(json!({ "result": 0, "metadata": metadata }))
+            }
+            "deletefolderrecursive" => {
+                let id = self.folder_param(params)?;
+                if id == ROOT_FOLDER_ID {
+                    return Err(PCloudResult::CanNotDeleteRootFolder);
+                }
+                let (folders, files) = self.descendants(id);
+                let mut trashed = self.folder_metadata(id, true, true, false);
+                trashed["isdeleted"] = json!(true);
+                self.trash.push(trashed);
+                for file in &files {
+                    let metadata = self.deleted_metadata(*file, false);
+                    self.files.remove(file);
+                    self.record("deletefile", metadata);
+                }
+                // Deepest folders first
+                for folder in folders.iter().rev().chain([&id]) {
+                    let metadata = self.deleted_metadata(*folder, true);
+                    self.folders.remove(folder);
+                    self.record("deletefolder", metadata);
+                }
+                Ok(json!({
+                    "result": 0,
+                    "deletedfiles": files.len(),
+                    "deletedfolders": folders.len() + 1,
+                }))
+            }
+            "renamefolder" => {
+                let id = self.folder_param(params)?;
+                if id == ROOT_FOLDER_ID {
+                    return Err(PCloudResult::CannotRenameTheRootFolder);
+                }
+                let (parent, name) = {
+                    let folder = &self.folders[&id];
+                    self.target_param(params, folder.parent, &folder.name)?
+                };
+                if self.is_descendant(parent, id) {
+                    return Err(PCloudResult::CannotMoveAFolderToASubfolderOfItself);
+                }
+                if self
+                    .child_folder(parent, &name)
+                    .filter(|f| *f != id)
+                    .is_some()
+                    || self.child_file(parent, &name).is_some()
+                {
+                    return Err(PCloudResult::FileOrFolderAlreadyExists);
+                }
+                let folder = self.folders.get_mut(&id).unwrap();
+                folder.parent = parent;
+                folder.name = name;
+                folder.modified = Utc::now();
+                let metadata = self.folder_metadata(id, false, false, false);
+                self.record("modifyfolder", metadata.clone());
+                Ok(json!({ "result": 0, "metadata": metadata }))
+            }
+            "copyfolder" => {
+                let id = self.folder_param(params)?;
+                let name = self.folders[&id].name.clone();
+                let (parent, name) = self.target_param(params, ROOT_FOLDER_ID, &name)?;
+                if self.is_descendant(parent, id) {
+                    return Err(PCloudResult::CannotMoveAFolderToASubfolderOfItself);
+                }
+                let conflict = if flag(params, "noover") {
+                    Conflict::Fail
+                } else if flag(params, "skipexisting") {
+                    Conflict::Skip
+                } else {
+                    Conflict::Overwrite
+                };
+                let target = if flag(params, "copycontentonly") {
+                    parent
+                } else {
+                    match self.child_folder(parent, &name) {
+                        Some(existing) => existing,
+                        None => self.create_folder(parent, &name)?,
+                    }
+                };
+                self.copy_folder_contents(id, target, conflict)?;
+                Ok(
+                    json!({ "result": 0, "metadata": self.folder_metadata(target, false, false, false) }),
+                )
+            }
+            "stat" => {
+                let id = self.file_param(params)?;
+                Ok(json!({ "result": 0, "metadata": self.file_metadata(id) }))
+            }
+            "checksumfile" => {
+                let id = self.file_param(params)?;
+                let sha1 = format!("{:x}", Sha1::digest(&self.files[&id].content));
+                Ok(json!({ "result": 0, "sha1": sha1, "metadata": self.file_metadata(id) }))
+            }
+            "uploadfile" => {
+                let parent = self.folder_param(params)?;
+                let mut ids = vec![];
+                for (name, content) in uploads {
+                    let mut name = name;
+                    if flag(params, "renameifexists") {
+                        let mut counter = 1;
+                        let original = name.clone();
+                        while self.child_file(parent, &name).is_some() {
+                            name = match original.rsplit_once('.') {
+                                Some((base, ext)) => format!("{} ({}).{}", base, counter, ext),
+                                None => format!("{} ({})", original, counter),
+                            };
+                            counter += 1;
+                        }
+                    }
+                    let id = self.write_file(parent, &name, content);
+                    if let Some(mtime) = params.get("mtime").and_then(|v| v.parse().ok()) {
+                        if let Some(modified) = Utc.timestamp_opt(mtime, 0).single() {
+                            self.files.get_mut(&id).unwrap().modified = modified;
+                        }
+                    }
+                    ids.push(id);
+                }
+                let metadata: Vec<Value> = ids.iter().map(|id| self.file_metadata(*id)).collect();
+                Ok(json!({ "result": 0, "fileids": ids, "metadata": metadata }))
+            }
+            "deletefile" => {
+                let id = self.file_param(params)?;
+                let metadata = self.deleted_metadata(id, false);
+                self.files.remove(&id);
+                self.links.retain(|_, file| *file != id);
+                self.trash.push(metadata.clone());
+                self.record("deletefile", metadata.clone());
+                Ok(json!({ "result": 0, "metadata": metadata }))
+            }
+            "renamefile" | "copyfile" => {
+                let id = self.file_param(params)?;
+                let (parent, name) = {
+                    let file = &self.files[&id];
+                    self.target_param(params, file.parent, &file.name)?
+                };
+                if self.child_folder(parent, &name).is_some() {
+                    return Err(PCloudResult::FileOrFolderAlreadyExists);
+                }
+                let existing = self.child_file(parent, &name).filter(|f| *f != id);
+                if existing.is_some() && flag(params, "noover") {
+                    return Err(PCloudResult::FileOrFolderAlreadyExists);
+                }
+                let target = if method == "copyfile" {
+                    let file = &self.files[&id];
+                    let content = match params.get("revisionid") {
+                        Some(revision) => file
+                            .revisions
+                            .iter()
+                            .find(|r| r.id.to_string() == *revision)
+                            .ok_or(PCloudResult::FileNotFound)?
+                            .content
+                            .clone(),
+                        None => file.content.clone(),
+                    };
+                    let target = self.write_file(parent, &name, content);
+                    let time_param = |name: &str| {
+                        params
+                            .get(name)
+                            .and_then(|v| v.parse().ok())
+                            .and_then(|v| Utc.timestamp_opt(v, 0).single())
+                    };
+                    let file = self.files.get_mut(&target).unwrap();
+                    if let Some(mtime) = time_param("mtime") {
+                        file.modified = mtime;
+                    }
+                    if let Some(ctime) = time_param("ctime") {
+                        file.created = ctime;
+                    }
+                    target
+                } else {
+                    if let Some(existing) = existing {
+                        let metadata = self.deleted_metadata(existing, false);
+                        self.files.remove(&existing);
+                        self.record("deletefile", metadata);
+                    }
+                    // Like pCloud, moving a file keeps its timestamps
+                    let file = self.files.get_mut(&id).unwrap();
+                    file.parent = parent;
+                    file.name = name;
+                    self.record("modifyfile", self.file_metadata(id));
+                    id
+                };
+                Ok(json!({ "result": 0, "metadata": self.file_metadata(target) }))
+            }
+            "listrevisions" => {
+                let id = self.file_param(params)?;
+                let revisions: Vec<Value> = self.files[&id]
+                    .revisions
+                    .iter()
+                    .rev()
+                    .map(|r| {
+                        let mut hasher = DefaultHasher::new();
+                        r.content.hash(&mut hasher);
+                        json!({
+                            "revisionid": r.id,
+                            "size": r.content.len(),
+                            "hash": hasher.finish(),
+                            "created": r.created.format(DATE_FORMAT).to_string(),
+                        })
+                    })
+                    .collect();
+                Ok(
+                    json!({ "result": 0, "metadata": self.file_metadata(id), "revisions": revisions }),
+                )
+            }
+            "getfilehistory" => {
+                let id = params
+                    .get("fileid")
+                    .and_then(|id| id.parse::<u64>().ok())
+                    .ok_or(PCloudResult::NoFileIdOrPathProvided)?;
+                let entries: Vec<Value> = self
+                    .events
+                    .iter()
+                    .filter(|e| e["metadata"]["fileid"] == json!(id))
+                    .cloned()
+                    .collect();
+                Ok(json!({ "result": 0, "entries": entries }))
+            }
+            "collection_list" => {
+                let limit = params.get("pagesize").and_then(|v| v.parse().ok());
+                let collections: Vec<Value> = self
+                    .collections
+                    .keys()
+                    .map(|id| self.collection_metadata(*id, flag(params, "showfiles"), limit))
+                    .collect();
+                Ok(json!({ "result": 0, "collections": collections }))
+            }
+            "listuploadlinks" => {
+                let links: Vec<Value> = self
+                    .upload_links
+                    .keys()
+                    .map(|id| self.upload_link_metadata(*id))
+                    .collect();
+                Ok(json!({ "result": 0, "uploadlinks": links }))
+            }
+            "collection_details" => {
+                let id = self.collection_param(params)?;
+                Ok(json!({ "result": 0, "collection": self.collection_metadata(id, true, None) }))
+            }
+            "collection_create" => {
+                let name = params
+                    .get("name")
+                    .filter(|n| !n.is_empty())
+                    .ok_or(PCloudResult::InvalidFileOrFolderName)?;
+                let files = self.file_ids_param(params)?;
+                let id = self.next_id();
+                let now = Utc::now();
+                self.collections.insert(
+                    id,
+                    FakeCollection {
+                        name: name.clone(),
+                        files,
+                        created: now,
+                        modified: now,
+                    },
+                );
+                Ok(json!({ "result": 0, "collection": self.collection_metadata(id, false, None) }))
+            }
+            "collection_rename" => {
+                let id = self.collection_param(params)?;
+                let name = params
+                    .get("name")
+                    .filter(|n| !n.is_empty())
+                    .ok_or(PCloudResult::InvalidFileOrFolderName)?;
+                let collection = self.collections.get_mut(&id).unwrap();
+                collection.name = name.clone();
+                collection.modified = Utc::now();
+                Ok(json!({ "result": 0, "collection": self.collection_metadata(id, false, None) }))
+            }
+            "collection_linkfiles" => {
+                let id = self.collection_param(params)?;
+                let mut results = vec![];
+                for fileid in params
+                    .get("fileids")
+                    .map(|ids| ids.split(','))
+                    .into_iter()
+                    .flatten()
+                {
+                    match fileid
+                        .parse::<u64>()
+                        .ok()
+                        .filter(|f| self.files.contains_key(f))
+                    {
+                        Some(fileid) => {
+                            self.collections.get_mut(&id).unwrap().files.push(fileid);
+                            results.push(json!({ "fileid": fileid, "result": 0 }));
+                        }
+                        None => results.push(json!({
+                            "fileid": fileid.parse::<u64>().unwrap_or_default(),
+                            "result": PCloudResult::FileNotFound.code(),
+                            "message": PCloudResult::FileNotFound.to_string(),
+                        })),
+                    }
+                }
+                self.collections.get_mut(&id).unwrap().modified = Utc::now();
+                let collection = self.collection_metadata(id, !flag(params, "noitems"), None);
+                Ok(json!({ "result": 0, "linkresult": results, "collection": collection }))
+            }
+            "collection_unlinkfiles" => {
+                let id = self.collection_param(params)?;
+                let fileids = self.file_ids_param(params)?;
+                let positions: Vec<usize> = params
+                    .get("positions")
+                    .map(|p| p.split(',').filter_map(|p| p.parse().ok()).collect())
+                    .unwrap_or_default();
+                let all = flag(params, "all");
+                let collection = self.collections.get_mut(&id).unwrap();
+                let mut position = 0;
+                collection.files.retain(|fileid| {
+                    position += 1;
+                    !all && !fileids.contains(fileid) && !positions.contains(&position)
+                });
+                collection.modified = Utc::now();
+                Ok(json!({ "result": 0, "collection": self.collection_metadata(id, true, None) }))
+            }
+            "collection_move" => {
+                let id = self.collection_param(params)?;
+                let number = |name: &str| params.get(name).and_then(|v| v.parse::<usize>().ok());
+                let collection = self.collections.get_mut(&id).unwrap();
+                let len = collection.files.len();
+                match (number("item"), number("position")) {
+                    (Some(item), Some(position))
+                        if (1..=len).contains(&item) && (1..=len).contains(&position) =>
+                    {
+                        let fileid = collection.files.remove(item - 1);
+                        collection.files.insert(position - 1, fileid);
+                        collection.modified = Utc::now();
+                    }
+                    _ => return Err(PCloudResult::InvalidFileDescriptor),
+                }
+                Ok(json!({ "result": 0, "collection": self.collection_metadata(id, true, None) }))
+            }
+            "collection_delete" => {
+                let id = self.collection_param(params)?;
+                self.collections.remove(&id);
+                Ok(json!({ "result": 0 }))
+            }
+            "commentlist" => {
+                let fileid = self.file_param(params)?;
+                let comments: Vec<Value> = self
+                    .comments
+                    .iter()
+                    .filter(|(_, c)| c.fileid == fileid)
+                    .map(|(id, _)| self.comment_metadata(*id))
+                    .collect();
+                Ok(json!({ "result": 0, "comments": comments }))
+            }
+            "commentpost" => {
+                let fileid = self.file_param(params)?;
+                let text = params
+                    .get("comment")
+                    .filter(|c| !c.is_empty())
+                    .ok_or(PCloudResult::InternalError)?;
+                let id = self.next_id();
+                self.comments.insert(
+                    id,
+                    FakeComment {
+                        fileid,
+                        text: text.clone(),
+                        created: Utc::now(),
+                    },
+                );
+                Ok(json!({ "result": 0, "comment": self.comment_metadata(id) }))
+            }
+            "commentdel" => {
+                let id = params
+                    .get("commentid")
+                    .and_then(|id| id.parse::<u64>().ok())
+                    .filter(|id| self.comments.contains_key(id))
+                    .ok_or(PCloudResult::AccessDenied)?;
+                let comment = self.comment_metadata(id);
+                self.comments.remove(&id);
+                Ok(json!({ "result": 0, "comment": comment }))
+            }
+            "getfilelink" => {
+                let id = self.file_param(params)?;
+                // Revisions are downloaded from /dl/{fileid}/{revisionid}
+                let path = match params.get("revisionid") {
+                    Some(revision) => {
+                        let revision = revision
+                            .parse::<u64>()
+                            .map_err(|_| PCloudResult::InvalidFileId)?;
+                        if !self.files[&id].revisions.iter().any(|r| r.id == revision) {
+                            return Err(PCloudResult::FileNotFound);
+                        }
+                        format!("/dl/{}/{}", id, revision)
+                    }
+                    None => format!("/dl/{}", id),
+                };
+                Ok(json!({
+                    "result": 0,
+                    "path": path,
+                    "hosts": [DOWNLOAD_HOST],
+                    "expires": (Utc::now() + chrono::Duration::hours(6)).format(DATE_FORMAT).to_string(),
+                }))
+            }
+            "getthumblink" => {
+                let id = self.file_param(params)?;
+                let size = thumbnail_size(params)?;
+                Ok(json!({
+                    "result": 0,
+                    "path": format!("/thumb/{}/{}", id, size),
+                    "hosts": [DOWNLOAD_HOST],
+                    "size": size,
+                    "expires": (Utc::now() + chrono::Duration::hours(6)).format(DATE_FORMAT).to_string(),
+                }))
+            }
+            "getaudiolink" => {
+                let id = self.file_param(params)?;
+                let bitrate = params
+                    .get("abitrate")
+                    .map(|b| b.parse::<u32>())
+                    .unwrap_or(Ok(192))
+                    .map_err(|_| PCloudResult::InvalidFileOrFolderName)?;
+                Ok(json!({
+                    "result": 0,
+                    "path": format!("/audio/{}/{}", id, bitrate),
+                    "hosts": [DOWNLOAD_HOST],
+                    "expires": (Utc::now() + chrono::Duration::hours(6)).format(DATE_FORMAT).to_string(),
+                }))
+            }
+            "getthumbslinks" => {
+                let size = thumbnail_size(params)?;
+                let thumbs: Vec<Value> = params
+                    .get("fileids")
+                    .ok_or(PCloudResult::NoFileIdOrPathProvided)?
+                    .split(',')
+                    .filter_map(|id| id.parse::<u64>().ok())
+                    .map(|id| {
+                        if self.files.contains_key(&id) {
+                            json!({
+                                "fileid": id,
+                                "result": 0,
+                                "path": format!("/thumb/{}/{}", id, size),
+                                "hosts": [DOWNLOAD_HOST],
+                                "size": size,
+                                "expires": (Utc::now() + chrono::Duration::hours(6)).format(DATE_FORMAT).to_string(),
+                            })
+                        } else {
+                            json!({ "fileid": id, "result": PCloudResult::FileNotFound })
+                        }
+                    })
+                    .collect();
+                Ok(json!({ "result": 0, "thumbs": thumbs }))
+            }
+            "savethumb" => {
+                let id = self.file_param(params)?;
+                let size = thumbnail_size(params)?;
+                let file = &self.files[&id];
+                let (parent, name) = self.target_param(params, file.parent, &file.name)?;
+                if flag(params, "noover") && self.child_file(parent, &name).is_some() {
+                    return Err(PCloudResult::FileOrFolderAlreadyExists);
+                }
+                let thumbnail = self.thumbnail(id, size).ok_or(PCloudResult::FileNotFound)?;
+                let saved = self.write_file(parent, &name, thumbnail.into_bytes());
+                let (width, height) = size.split_once('x').unwrap_or_default();
+                Ok(json!({
+                    "result": 0,
+                    "metadata": self.file_metadata(saved),
+                    "width": width.parse::<u32>().ok(),
+                    "height": height.parse::<u32>().ok(),
+                }))
+            }
+            "getfilepublink" => {
+                let id = self.file_param(params)?;
+                let code = format!("fake{}", self.next_id());
+                self.links.insert(code.clone(), id);
+                let now = Utc::now().format(DATE_FORMAT).to_string();
+                Ok(json!({
+                    "result": 0,
+                    "code": code,
+                    "link": format!("https://{}/publink/show?code={}", DOWNLOAD_HOST, code),
+                    "metadata": self.file_metadata(id),
+                    "created": now,
+                    "modified": now,
+                    "downloadenabled": true,
+                    "downloads": 0,
+                }))
+            }
+            "getfolderpublink" => {
+                let id = self.folder_param(params)?;
+                let code = format!("fake{}", self.next_id());
+                self.folder_links.insert(code.clone(), id);
+                let now = Utc::now().format(DATE_FORMAT).to_string();
+                Ok(json!({
+                    "result": 0,
+                    "code": code,
+                    "link": format!("https://{}/publink/show?code={}", DOWNLOAD_HOST, code),
+                    "metadata": self.folder_metadata(id, false, false, false),
+                    "created": now,
+                    "modified": now,
+                    "downloadenabled": true,
+                    "downloads": 0,
+                }))
+            }
+            "showpublink" => {
+                let code = params.get("code").ok_or(PCloudResult::AccessDenied)?;
+                let metadata = match (self.links.get(code), self.folder_links.get(code)) {
+                    (Some(file), _) => self.file_metadata(*file),
+                    (None, Some(folder)) => self.folder_metadata(*folder, true, true, false),
+                    (None, None) => return Err(PCloudResult::InvalidLinkCode),
+                };
+                Ok(json!({ "result": 0, "metadata": metadata }))
+            }
+            "getpublinkdownload" => {
+                let code = params.get("code").ok_or(PCloudResult::AccessDenied)?;
+                let id = match (self.links.get(code), self.folder_links.get(code)) {
+                    (Some(file), _) => *file,
+                    (None, Some(folder)) => {
+                        let id = params
+                            .get("fileid")
+                            .and_then(|id| id.parse::<u64>().ok())
+                            .ok_or(PCloudResult::FileNotFound)?;
+                        if !self.files.contains_key(&id) || !self.is_below(id, *folder) {
+                            return Err(PCloudResult::FileNotFound);
+                        }
+                        id
+                    }
+                    (None, None) => return Err(PCloudResult::InvalidLinkCode),
+                };
+                Ok(json!({
+                    "result": 0,
+                    "path": format!("/dl/{}", id),
+                    "hosts": [DOWNLOAD_HOST],
+                    "expires": (Utc::now() + chrono::Duration::hours(6)).format(DATE_FORMAT).to_string(),
+                }))
+            }
+            "trash_list" => {
+                let recursive = flag(params, "recursive");
+                let metadata = match params.get("folderid") {
+                    Some(id) if id != "0" => find_trashed(&self.trash, &format!("d{}", id))
+                        .cloned()
+                        .ok_or(PCloudResult::DirectoryDoesNotExist)?,
+                    _ => self.trash_metadata(recursive),
+                };
+                Ok(json!({ "result": 0, "metadata": metadata }))
+            }
+            "trash_clear" => {
+                let id = match (params.get("fileid"), params.get("folderid")) {
+                    (Some(id), _) => format!("f{}", id),
+                    (None, Some(id)) if id != "0" => format!("d{}", id),
+                    _ => {
+                        self.trash.clear();
+                        return Ok(json!({ "result": 0 }));
+                    }
+                };
+                if !remove_trashed(&mut self.trash, &id) {
+                    return Err(if id.starts_with('f') {
+                        PCloudResult::FileNotFound
+                    } else {
+                        PCloudResult::DirectoryDoesNotExist
+                    });
+                }
+                Ok(json!({ "result": 0 }))
+            }
+            "diff" => {
+                let number = |name: &str| params.get(name).and_then(|v| v.parse::<usize>().ok());
+                let start = match (number("last"), number("diffid")) {
+                    (Some(last), _) => self.events.len().saturating_sub(last),
+                    (None, Some(diffid)) => diffid.min(self.events.len()),
+                    (None, None) => 0,
+                }
+                .max(self.expired_events);
+                let end = match number("limit") {
+                    Some(limit) => (start + limit).min(self.events.len()),
+                    None => self.events.len(),
+                };
+                // Like pCloud, an empty result returns the latest diff id, the fake never blocks
+                Ok(json!({
+                    "result": 0,
+                    "diffid": if start < end { end } else { self.events.len() },
+                    "entries": self.events[start..end].to_vec(),
+                }))
+            }
+            _ => Err(PCloudResult::InternalError),
+        }
+    }
+}
+
+/// Checks if a boolean parameter is set. pCloud treats any value but `0` as set.
+fn flag(params: &HashMap<String, String>, name: &str) -> bool {
+    params.get(name).map(|v| v != "0").unwrap_or(false)
+}
+
+/// Parses the `size` of thumbnails, like "120x90"
+fn thumbnail_size(params: &HashMap<String, String>) -> Result<&str, PCloudResult> {
+    params
+        .get("size")
+        .map(|s| s.as_str())
+        .filter(|s| s.split_once('x').is_some())
+        .ok_or(PCloudResult::InvalidFileOrFolderName)
+}
+
+/// Parses the `offset` of positional reads and writes
+fn offset_param(params: &HashMap<String, String>) -> Result<u64, PCloudResult> {
+    params
+        .get("offset")
+        .and_then(|v| v.parse().ok())
+        .ok_or(PCloudResult::ProvideOffset)
+}
+
+/// Finds a trashed file or folder by its id (like `f123` or `d45`), also within trashed folders
+fn find_trashed<'a>(items: &'a [Value], id: &str) -> Option<&'a Value> {
+    items.iter().find_map(|item| {
+        if item["id"] == id {
+            Some(item)
+        } else {
+            find_trashed(item["contents"].as_array()?, id)
+        }
+    })
+}
+
+/// Removes a trashed file or folder by its id, also from within trashed folders. Returns false if not found.
+fn remove_trashed(items: &mut Vec<Value>, id: &str) -> bool {
+    let count = items.len();
+    items.retain(|item| item["id"] != id);
+    count != items.len()
+        || items
+            .iter_mut()
+            .any(|item| match item["contents"].as_array_mut() {
+                Some(contents) => remove_trashed(contents, id),
+                None => false,
+            })
+}
+
+/// Extracts all uploaded files (name, content) from a multipart/form-data body
+fn parse_multipart(content_type: &str, body: &[u8]) -> Vec<(String, Vec<u8>)> {
+    let boundary = match content_type.split("boundary=").nth(1) {
+        Some(boundary) => format!("--{}", boundary.trim_matches('"')),
+        None => return vec![],
+    };
+
+    let mut result = vec![];
+    let mut rest = body;
+    while let Some(start) = find(rest, boundary.as_bytes()) {
+        rest = &rest[start + boundary.len()..];
+        let end = match find(rest, boundary.as_bytes()) {
+            Some(end) => end,
+            None => break,
+        };
+        let part = &rest[..end];
+        if let Some(header_end) = find(part, b"\r\n\r\n") {
+            let headers = String::from_utf8_lossy(&part[..header_end]);
+            let content = &part[header_end + 4..];
+            let content = content.strip_suffix(b"\r\n").unwrap_or(content);
+            if let Some(name) = headers
+                .split("filename=\"")
+                .nth(1)
+                .and_then(|n| n.split('"').next())
+            {
+                result.push((name.to_string(), content.to_vec()));
+            }
+        }
+    }
+    result
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Parses a single range header (`bytes=start-end`, `bytes=start-` or `bytes=-suffix`) into the first and last byte (inclusive). None if not satisfiable.
+fn parse_range(range: &str, len: usize) -> Option<(usize, usize)> {
+    let (start, end) = range.strip_prefix("bytes=")?.split_once('-')?;
+    let (start, end) = match (start.parse::<usize>().ok(), end.parse::<usize>().ok()) {
+        (Some(start), Some(end)) => (start, end.min(len.checked_sub(1)?)),
+        (Some(start), None) => (start, len.checked_sub(1)?),
+        (None, Some(suffix)) => (len.checked_sub(suffix.min(len))?, len.checked_sub(1)?),
+        (None, None) => return None,
+    };
+    if start <= end {
+        Some((start, end))
+    } else {
+        None
+    }
+}
+
+/// Handles a single HTTP request to the fake server
+async fn handle(
+    state: Arc<Mutex<FakeState>>,
+    request: hyper::Request<hyper::Body>,
+) -> Result<hyper::Response<hyper::Body>, Infallible> {
+    let url = Url::parse(&format!("http://{}{}", DOWNLOAD_HOST, request.uri()))
+        .unwrap_or_else(|_| Url::parse(&format!("http://{}/", DOWNLOAD_HOST)).unwrap());
+    let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+    let content_type = request
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let range = request
+        .headers()
+        .get("range")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+    let body = hyper::body::to_bytes(request.into_body())
+        .await
+        .unwrap_or_default();
+
+    // Downloads
+    if let Some(path) = url.path().strip_prefix("/dl/") {
+        let state = state.lock().unwrap();
+        let (id, revision) = match path.split_once('/') {
+            Some((id, revision)) => (id, revision.parse::<u64>().ok()),
+            None => (path, None),
+        };
+        let file = id.parse::<u64>().ok().and_then(|id| state.files.get(&id));
+        let content = match revision {
+            Some(revision) => file
+                .and_then(|f| f.revisions.iter().find(|r| r.id == revision))
+                .map(|r| &r.content),
+            None => file.map(|f| &f.content),
+        };
+        let len = content.map(|c| c.len()).unwrap_or_default();
+        let response = match (content, range.map(|r| parse_range(&r, len))) {
+            (Some(_), Some(None)) => hyper::Response::builder()
+                .status(416)
+                .header("content-range", format!("bytes */{}", len))
+                .body(hyper::Body::empty()),
+            (Some(content), Some(Some((start, end)))) => hyper::Response::builder()
+                .status(206)
+                .header("content-type", "application/octet-stream")
+                .header("content-range", format!("bytes {}-{}/{}", start, end, len))
+                .body(hyper::Body::from(content[start..=end].to_vec())),
+            (Some(content), None) => hyper::Response::builder()
+                .header("content-type", "application/octet-stream")
+                .body(hyper::Body::from(content.clone())),
+            (None, _) => hyper::Response::builder()
+                .status(404)
+                .body(hyper::Body::empty()),
+        };
+        return Ok(response.unwrap());
+    }
+
+    // Thumbnails, made of the size and the checksum of the content
+    if let Some(path) = url.path().strip_prefix("/thumb/") {
+        let state = state.lock().unwrap();
+        let thumbnail = path
+            .split_once('/')
+            .and_then(|(id, size)| state.thumbnail(id.parse().ok()?, size));
+        let response = match thumbnail {
+            Some(thumbnail) => hyper::Response::builder()
+                .header("content-type", "image/jpeg")
+                .body(hyper::Body::from(thumbnail)),
+            None => hyper::Response::builder()
+                .status(404)
+                .body(hyper::Body::empty()),
+        };
+        return Ok(response.unwrap());
+    }
+
+    // Transcoded audio, made of the bitrate and the checksum of the content
+    if let Some(path) = url.path().strip_prefix("/audio/") {
+        let state = state.lock().unwrap();
+        let audio = path.split_once('/').and_then(|(id, bitrate)| {
+            let file = state.files.get(&id.parse::<u64>().ok()?)?;
+            Some(format!(
+                "audio {} {:x}",
+                bitrate,
+                Sha1::digest(&file.content)
+            ))
+        });
+        let response = match audio {
+            Some(audio) => hyper::Response::builder()
+                .header("content-type", "audio/mpeg")
+                .body(hyper::Body::from(audio)),
+            None => hyper::Response::builder()
+                .status(404)
+                .body(hyper::Body::empty()),
+        };
+        return Ok(response.unwrap());
+    }
+
+    let method = url.path().trim_start_matches('/');
+    debug!("FakePCloud call {} {:?}", method, params);
+    let result = if method == "getthumb" {
+        // Thumbnail is returned as is, only failures as JSON
+        let state = state.lock().unwrap();
+        let thumbnail = state.file_param(&params).and_then(|id| {
+            let size = thumbnail_size(&params)?;
+            state.thumbnail(id, size).ok_or(PCloudResult::FileNotFound)
+        });
+        match thumbnail {
+            Ok(thumbnail) => {
+                return Ok(hyper::Response::builder()
+                    .header("content-type", "image/jpeg")
+                    .body(hyper::Body::from(thumbnail))
+                    .unwrap())
+            }
+            Err(result) => Err(result),
+        }
+    } else if method == "file_read" || method == "file_pread" {
+        // Content is returned as is, only failures as JSON
+        match state
+            .lock()
+            .unwrap()
+            .read_fd(&params, method == "file_pread")
+        {
+            Ok(content) => {
+                return Ok(hyper::Response::builder()
+                    .header("content-type", "application/octet-stream")
+                    .body(hyper::Body::from(content))
+                    .unwrap())
+            }
+            Err(result) => Err(result),
+        }
+    } else if method.starts_with("file_") {
+        state.lock().unwrap().file_op(method, &params, &body)
+    } else {
+        let uploads = parse_multipart(&content_type, &body);
+        state.lock().unwrap().call(method, &params, uploads)
+    };
+    let json = match result {
+        Ok(json) => json,
+        Err(result) => json!({ "result": result, "error": result.to_string() }),
+    };
+
+    Ok(hyper::Response::builder()
+        .header("content-type", "application/json")
+        .body(hyper::Body::from(json.to_string()))
+        .unwrap())
+}
+
+/// In-process fake of the pCloud backend implementing the folder / file / link semantics in memory. Plugged into a PCloudClient as HttpTransport, it allows full end-to-end tests (e.g. of sync logic) without any pCloud account.
+/// All requests are answered by a local HTTP server (necessary to receive the streamed upload bodies), regardless of the API host configured. All clones share the same file system, the server stops once the last clone is dropped.
+#[derive(Clone)]
+pub struct FakePCloud {
+    state: Arc<Mutex<FakeState>>,
+    addr: SocketAddr,
+    client: reqwest::Client,
+    _shutdown: Arc<oneshot::Sender<()>>,
+}
+
+impl FakePCloud {
+    /// Starts a new fake with an empty file system
+    pub async fn start() -> Result<FakePCloud, Box<dyn Error + Send + Sync>> {
+        let state = Arc::new(Mutex::new(FakeState::new()));
+
+        let service_state = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = service_state.clone();
+            async move { Ok::<_, Infallible>(service_fn(move |r| handle(state.clone(), r))) }
+        });
+
+        let server = Server::try_bind(&SocketAddr::from(([127, 0, 0, 1], 0)))?.serve(make_service);
+        let addr = server.local_addr();
+        let (shutdown, shutdown_received) = oneshot::channel::<()>();
+        tokio::spawn(server.with_graceful_shutdown(async {
+            shutdown_received.await.ok();
+        }));
+        debug!("FakePCloud listening on {}", addr);
+
+        Ok(FakePCloud {
+            state,
+            addr,
+            client: reqwest::Client::builder().no_proxy().build()?,
+            _shutdown: Arc::new(shutdown),
+        })
+    }
+
+    /// Creates a builder for a PCloudClient using this fake as transport
+    pub fn client_builder(&self) -> PCloudClientBuilder {
+        PCloudClient::builder("https://api.pcloud.com")
+            .with_oauth("fake-token")
+            .select_best_api_server(false)
+            .with_transport(self.clone())
+    }
+
+    /// Creates a PCloudClient using this fake as transport
+    pub async fn client(&self) -> Result<PCloudClient, PCloudError> {
+        self.client_builder().build().await
+    }
+
+    /// Creates the folder (and all missing parent folders) and returns its id
+    pub fn create_folder(&self, path: &str) -> Result<u64, PCloudResult> {
+        self.state.lock().unwrap().create_folders(path)
+    }
+
+    /// Writes a file (and creates all missing parent folders) and returns its id
+    pub fn put_file<T: Into<Vec<u8>>>(&self, path: &str, content: T) -> Result<u64, PCloudResult> {
+        let (parent, name) = FakeState::split_path(path);
+        let mut state = self.state.lock().unwrap();
+        let parent = state.create_folders(&parent)?;
+        Ok(state.write_file(parent, &name, content.into()))
+    }
+
+    /// Returns the content of the file, if present
+    pub fn read_file(&self, path: &str) -> Option<Vec<u8>> {
+        let state = self.state.lock().unwrap();
+        let id = state.file_by_path(path).ok()?;
+        Some(state.files[&id].content.clone())
+    }
+
+    /// Checks if a file or folder with the given path exists
+    pub fn exists(&self, path: &str) -> bool {
+        let state = self.state.lock().unwrap();
+        state.folder_by_path(path).is_ok() || state.file_by_path(path).is_ok()
+    }
+
+    /// Creates a collection (playlist) of the given files, like one created by another pCloud app, and returns its id
+    pub fn put_collection(&self, name: &str, files: &[&str]) -> Result<u64, PCloudResult> {
+        let mut state = self.state.lock().unwrap();
+        let files = files
+            .iter()
+            .map(|path| state.file_by_path(path))
+            .collect::<Result<Vec<_>, _>>()?;
+        let id = state.next_id();
+        let now = Utc::now();
+        state.collections.insert(
+            id,
+            FakeCollection {
+                name: name.to_string(),
+                files,
+                created: now,
+                modified: now,
+            },
+        );
+        Ok(id)
+    }
+
+    /// Creates an upload link into the folder (and all missing parent folders), like one created in the pCloud web interface, and returns its id
+    pub fn put_upload_link(&self, path: &str, comment: &str) -> Result<u64, PCloudResult> {
+        let mut state = self.state.lock().unwrap();
+        let folder = state.create_folders(path)?;
+        let id = state.next_id();
+        state.upload_links.insert(
+            id,
+            FakeUploadLink {
+                folder,
+                comment: comment.to_string(),
+                created: Utc::now(),
+            },
+        );
+        Ok(id)
+    }
+
+    /// Creates the folder (and all missing parent folders) as shared by another user with the given permissions, applying to all its contents as well, and returns its id
+    pub fn put_shared_folder(
+        &self,
+        path: &str,
+        owner: u64,
+        permissions: Permissions,
+    ) -> Result<u64, PCloudResult> {
+        let mut state = self.state.lock().unwrap();
+        let id = state.create_folders(path)?;
+        state.shares.insert(id, FakeShare { owner, permissions });
+        Ok(id)
+    }
+
+    /// Drops all events recorded so far from the history returned by `diff`, like pCloud does with old events
+    pub fn expire_events(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.expired_events = state.events.len();
+    }
+
+    /// Records a `reset` event, telling clients to discard their state
+    pub fn reset_events(&self) {
+        self.state.lock().unwrap().record("reset", Value::Null);
+    }
+}
+
+impl HttpTransport for FakePCloud {
+    fn execute<'a>(
+        &'a self,
+        mut request: Request,
+    ) -> BoxFuture<'a, Result<Response, Box<dyn Error + Send + Sync>>> {
+        Box::pin(async move {
+            // Redirect all requests (API and downloads) to the local server
+            let url = request.url_mut();
+            url.set_scheme("http")
+                .map_err(|_| format!("Unable to redirect {} to FakePCloud", url))?;
+            url.set_host(Some(&self.addr.ip().to_string()))?;
+            url.set_port(Some(self.addr.port()))
+                .map_err(|_| "Unable to redirect request to FakePCloud")?;
+
+            let response = self.client.execute(request).await?;
+            Ok(response)
+        })
+    }
+}
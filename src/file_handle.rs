@@ -0,0 +1,243 @@
+#![cfg(feature = "low_level_file_ops")]
+use std::{
+    io::SeekFrom,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use bytes::{Bytes, BytesMut};
+use futures::{future::BoxFuture, ready, FutureExt};
+use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, ReadBuf};
+
+use crate::{error::PCloudError, low_level_file::OpenPCloudFile, pcloud_model::PCloudResult};
+
+/// Default size of the reads ahead and of the buffered writes (1 MiB)
+pub const DEFAULT_HANDLE_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Request of the handle in flight
+enum Pending {
+    Read(BoxFuture<'static, Result<Bytes, PCloudError>>),
+    Write(BoxFuture<'static, Result<(), PCloudError>>),
+    Size(BoxFuture<'static, Result<u64, PCloudError>>),
+}
+
+/// Remote file opened with `file_open` as `AsyncRead + AsyncWrite + AsyncSeek`, e.g. for `tokio::io::copy()` or a `BufReader`. Create it with `OpenPCloudFile::into_handle()`.
+/// The handle keeps its own position and reads and writes at it with positional requests. Reads fetch at least `buffer_size` bytes ahead, writes are buffered up to `buffer_size` bytes and sent once full, on `flush()`, `shutdown()` and before seeking or reading. Errors of buffered writes are returned by the next write or flush, writes not flushed are lost when the handle is dropped.
+pub struct PCloudFileHandle {
+    /// Opened file, shared with the requests in flight
+    file: Arc<OpenPCloudFile>,
+    /// Offset of the next read or write, including the buffered writes
+    position: u64,
+    /// Data read ahead of the position
+    read_buffer: Bytes,
+    /// Data written before the position, not sent yet
+    write_buffer: BytesMut,
+    /// Minimum size of reads and maximum size of buffered writes
+    buffer_size: usize,
+    /// Request in flight
+    pending: Option<Pending>,
+    /// Seek started, but not completed yet
+    seek: Option<SeekFrom>,
+}
+
+#[allow(dead_code)]
+impl OpenPCloudFile {
+    /// Converts the file into a handle implementing the tokio IO traits, positioned at the start of the file
+    pub fn into_handle(self) -> PCloudFileHandle {
+        PCloudFileHandle {
+            file: Arc::new(self),
+            position: 0,
+            read_buffer: Bytes::new(),
+            write_buffer: BytesMut::new(),
+            buffer_size: DEFAULT_HANDLE_BUFFER_SIZE,
+            pending: None,
+            seek: None,
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl PCloudFileHandle {
+    /// Minimum size of reads and maximum size of buffered writes (default 1 MiB)
+    pub fn buffer_size(mut self, value: usize) -> PCloudFileHandle {
+        self.buffer_size = value.max(1);
+        self
+    }
+
+    /// Current position of the handle
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Flushes all buffered writes and closes the file
+    pub async fn close(mut self) -> Result<(), PCloudError> {
+        futures::future::poll_fn(|cx| self.poll_flush_writes(cx)).await?;
+        // Requests in flight were completed by the flush, so the handle holds the only reference
+        self.pending = None;
+        if let Ok(file) = Arc::try_unwrap(self.file) {
+            file.close().await?;
+        }
+        Ok(())
+    }
+
+    /// Completes the pending write (if any) and sends the buffered writes
+    fn poll_flush_writes(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), PCloudError>> {
+        loop {
+            if let Some(Pending::Write(write)) = self.pending.as_mut() {
+                let result = ready!(write.poll_unpin(cx));
+                self.pending = None;
+                result?;
+            }
+
+            if self.write_buffer.is_empty() {
+                return Poll::Ready(Ok(()));
+            }
+            // Reads and size requests in flight are replaced, they are outdated once written
+            let data = self.write_buffer.split().freeze();
+            let offset = self.position - data.len() as u64;
+            let file = self.file.clone();
+            self.pending = Some(Pending::Write(
+                async move { file.write_at(offset, data).await.map(|_| ()) }.boxed(),
+            ));
+        }
+    }
+
+    /// Resolves the target of the seek to an absolute position
+    fn poll_seek_target(
+        &mut self,
+        cx: &mut Context<'_>,
+        target: SeekFrom,
+    ) -> Poll<Result<u64, PCloudError>> {
+        let (base, offset) = match target {
+            SeekFrom::Start(offset) => return Poll::Ready(Ok(offset)),
+            SeekFrom::Current(offset) => (self.position, offset),
+            SeekFrom::End(offset) => {
+                if !matches!(self.pending, Some(Pending::Size(_))) {
+                    let file = self.file.clone();
+                    self.pending = Some(Pending::Size(
+                        async move {
+                            let size = file.size().await?;
+                            Ok(size.size.ok_or(PCloudResult::InternalError)?)
+                        }
+                        .boxed(),
+                    ));
+                }
+                let size = match self.pending.as_mut() {
+                    Some(Pending::Size(size)) => ready!(size.poll_unpin(cx)),
+                    _ => unreachable!("Size requested"),
+                };
+                self.pending = None;
+                (size?, offset)
+            }
+        };
+        match base.checked_add_signed(offset) {
+            Some(position) => Poll::Ready(Ok(position)),
+            None => Poll::Ready(Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Seek before the start of the file",
+            )
+            .into())),
+        }
+    }
+}
+
+impl AsyncRead for PCloudFileHandle {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        if this.read_buffer.is_empty() {
+            ready!(this.poll_flush_writes(cx))?;
+
+            if !matches!(this.pending, Some(Pending::Read(_))) {
+                let file = this.file.clone();
+                let offset = this.position;
+                let count = buf.remaining().max(this.buffer_size) as u64;
+                this.pending = Some(Pending::Read(
+                    async move { file.read_at(offset, count).await }.boxed(),
+                ));
+            }
+            let data = match this.pending.as_mut() {
+                Some(Pending::Read(read)) => ready!(read.poll_unpin(cx)),
+                _ => unreachable!("Read requested"),
+            };
+            this.pending = None;
+            // Empty at the end of the file
+            this.read_buffer = data?;
+        }
+
+        let len = this.read_buffer.len().min(buf.remaining());
+        buf.put_slice(&this.read_buffer.split_to(len));
+        this.position += len as u64;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for PCloudFileHandle {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        if this.write_buffer.len() >= this.buffer_size {
+            ready!(this.poll_flush_writes(cx))?;
+        }
+        if let Some(Pending::Write(write)) = this.pending.as_mut() {
+            // Surfaces errors of the write in flight without waiting for it
+            if let Poll::Ready(result) = write.poll_unpin(cx) {
+                this.pending = None;
+                result?;
+            }
+        } else {
+            this.pending = None;
+        }
+
+        // Data read ahead is outdated once written
+        this.read_buffer.clear();
+        let len = buf.len().min(this.buffer_size - this.write_buffer.len());
+        this.write_buffer.extend_from_slice(&buf[..len]);
+        this.position += len as u64;
+        Poll::Ready(Ok(len))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(ready!(self.get_mut().poll_flush_writes(cx))?))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+impl AsyncSeek for PCloudFileHandle {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> std::io::Result<()> {
+        let this = self.get_mut();
+        if this.seek.is_some() {
+            return Err(std::io::Error::other("Seek already in progress"));
+        }
+        this.seek = Some(position);
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<u64>> {
+        let this = self.get_mut();
+        let target = match this.seek {
+            Some(target) => target,
+            None => return Poll::Ready(Ok(this.position)),
+        };
+        ready!(this.poll_flush_writes(cx))?;
+        let result = ready!(this.poll_seek_target(cx, target));
+        this.seek = None;
+        let position = result?;
+
+        if position != this.position {
+            this.read_buffer.clear();
+            this.position = position;
+        }
+        Poll::Ready(Ok(position))
+    }
+}
@@ -1,16 +1,35 @@
-use std::fmt::Display;
+use std::{
+    fmt::Display,
+    future::Future,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
 
 use crate::{
-    folder_ops::FolderDescriptor,
+    buffer_pool::BufferPool,
+    error::PCloudError,
+    folder_ops::{FolderDescriptor, PCloudFolder},
     pcloud_client::PCloudClient,
     pcloud_model::{
-        self, FileOrFolderStat, Metadata, PCloudResult, PublicFileLink, RevisionList, UploadedFile,
-        WithPCloudResult,
+        self, FileChecksums, FileInfo, FileOrFolderStat, Metadata, PCloudResult, PublicFileLink,
+        RevisionList, UploadedFile,
     },
+    permissions::{self, Permissions},
+    progress::{self, ProgressObserver},
+    rate_limit, throttle,
+};
+use bytes::{Bytes, BytesMut};
+use chrono::{DateTime, TimeZone, Utc};
+use futures::{
+    future::{BoxFuture, Either},
+    stream::BoxStream,
+    FutureExt, Stream, StreamExt, TryStreamExt,
 };
-use chrono::{DateTime, TimeZone};
-use log::debug;
-use reqwest::{Body, RequestBuilder, Response};
+use log::{debug, warn};
+use reqwest::{Body, RequestBuilder, Response, StatusCode};
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
 
 /// Generic description of a pCloud File. Either by its file id (preferred) or by its path. Optionally give tuple with id / path and file revision
 pub trait FileDescriptor {
@@ -323,11 +342,38 @@ impl Tree {
         return r;
     }
 
+    /// Ids of all files of the tree, including the files of its folders (recursively), without the excluded ones
+    pub(crate) async fn resolve_file_ids(&self) -> Result<Vec<u64>, PCloudError> {
+        let mut ids = self.file_ids.clone();
+        for folder_id in self.folder_id.iter().chain(self.folder_ids.iter()) {
+            let listing = self
+                .client
+                .list_folder(*folder_id)?
+                .recursive(true)
+                .get()
+                .await?;
+            let mut pending: Vec<Metadata> = listing.metadata.into_iter().collect();
+            while let Some(entry) = pending.pop() {
+                if entry.isfolder {
+                    let excluded = entry
+                        .folderid
+                        .is_some_and(|id| self.exclude_folder_ids.contains(&id));
+                    if !excluded {
+                        pending.extend(entry.contents);
+                    }
+                } else if let Some(id) = entry.fileid {
+                    ids.push(id);
+                }
+            }
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        ids.retain(|id| !self.exclude_file_ids.contains(id) && seen.insert(*id));
+        Ok(ids)
+    }
+
     /// Adds a file or folder from a metadata object
-    pub async fn with(
-        self,
-        source: &Metadata,
-    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+    pub async fn with(self, source: &Metadata) -> Result<Self, PCloudError> {
         if source.isfolder {
             self.with_folder(source).await
         } else {
@@ -336,10 +382,7 @@ impl Tree {
     }
 
     /// Excludes a file or folder
-    pub async fn without(
-        self,
-        source: &Metadata,
-    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+    pub async fn without(self, source: &Metadata) -> Result<Self, PCloudError> {
         if source.isfolder {
             self.without_folder(source).await
         } else {
@@ -348,20 +391,17 @@ impl Tree {
     }
 
     /// If set, files with corresponding ids will appear in the root folder of the tree structure.
-    pub async fn with_file<'a, T: FileDescriptor>(
-        mut self,
-        file_like: T,
-    ) -> Result<Self, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    pub async fn with_file<T: FileDescriptor>(mut self, file_like: T) -> Result<Self, PCloudError> {
         let (file_id, _) = self.client.get_file_id(file_like).await?;
         self.file_ids.push(file_id);
         Ok(self)
     }
 
     /// If set, defines fileids that are not to be included in the tree structure.
-    pub async fn without_file<'a, T: FileDescriptor>(
+    pub async fn without_file<T: FileDescriptor>(
         mut self,
         file_like: T,
-    ) -> Result<Self, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<Self, PCloudError> {
         let (file_id, _) = self.client.get_file_id(file_like).await?;
 
         self.exclude_file_ids.push(file_id);
@@ -369,10 +409,10 @@ impl Tree {
     }
 
     /// If set, defines one or more folders that will appear as folders in the root folder.
-    pub async fn with_folder<'a, T: FolderDescriptor>(
+    pub async fn with_folder<T: FolderDescriptor>(
         mut self,
         folder_like: T,
-    ) -> Result<Self, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<Self, PCloudError> {
         let folder_id = self.client.get_folder_id(folder_like).await?;
 
         self.folder_ids.push(folder_id);
@@ -380,10 +420,10 @@ impl Tree {
     }
 
     /// If set, folders with the given id will be removed from the tree structure. This is useful when you want to include a folder in the tree structure with some of it's subfolders excluded.
-    pub async fn without_folder<'a, T: FolderDescriptor>(
+    pub async fn without_folder<T: FolderDescriptor>(
         mut self,
         folder_like: T,
-    ) -> Result<Self, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<Self, PCloudError> {
         let folder_id = self.client.get_folder_id(folder_like).await?;
 
         self.exclude_folder_ids.push(folder_id);
@@ -391,10 +431,10 @@ impl Tree {
     }
 
     /// If set, contents of the folder with the given id will appear as root elements of the tree. The folder itself does not appear as a part of the structure.
-    pub async fn with_content_of_folder<'a, T: FolderDescriptor>(
+    pub async fn with_content_of_folder<T: FolderDescriptor>(
         mut self,
         folder_like: T,
-    ) -> Result<Self, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<Self, PCloudError> {
         let folder_id = self.client.get_folder_id(folder_like).await?;
 
         self.folder_id = Some(folder_id);
@@ -423,17 +463,23 @@ pub struct CopyFileRequestBuilder {
     ctime: Option<i64>,
     /// File revision to fetch
     revision_id: Option<u64>,
+    /// if set, the permissions on the target folder are checked before copying
+    check_permissions: bool,
 }
 
 #[allow(dead_code)]
 impl CopyFileRequestBuilder {
-    pub(crate) fn copy_file<'a, S: FileDescriptor, T: FolderDescriptor>(
+    pub(crate) fn copy_file<S: FileDescriptor, T: FolderDescriptor>(
         client: &PCloudClient,
         file_like: S,
         target_folder_like: T,
-    ) -> Result<CopyFileRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
-        let source = file_like.to_file()?;
-        let target = target_folder_like.to_folder()?;
+    ) -> Result<CopyFileRequestBuilder, PCloudError> {
+        let source = file_like
+            .to_file()
+            .map_err(PCloudError::InvalidDescriptor)?;
+        let target = target_folder_like
+            .to_folder()
+            .map_err(PCloudError::InvalidDescriptor)?;
 
         if !source.is_empty() && !target.is_empty() {
             Ok(CopyFileRequestBuilder {
@@ -447,9 +493,12 @@ impl CopyFileRequestBuilder {
                 mtime: None,
                 ctime: None,
                 revision_id: source.revision,
+                check_permissions: false,
             })
         } else {
-            Err(pcloud_model::PCloudResult::NoFileIdOrPathProvided)?
+            Err(PCloudError::InvalidDescriptor(
+                PCloudResult::NoFileIdOrPathProvided,
+            ))?
         }
     }
 
@@ -491,14 +540,29 @@ impl CopyFileRequestBuilder {
         self
     }
 
+    /// If set, the create permission on a target folder shared by another user is checked before copying. Fails with `PermissionDenied` instead of a server error.
+    pub fn check_permissions(mut self, value: bool) -> CopyFileRequestBuilder {
+        self.check_permissions = value;
+        self
+    }
+
     // Execute the copy operation
-    pub async fn execute(
-        self,
-    ) -> Result<pcloud_model::FileOrFolderStat, Box<dyn std::error::Error + Send + Sync>> {
+    pub async fn execute(self) -> Result<pcloud_model::FileOrFolderStat, PCloudError> {
+        if self.check_permissions {
+            let target = permissions::receiving_folder(self.to_folder_id, self.to_path.as_ref());
+            permissions::require_folder_permission(
+                &self.client,
+                &target,
+                Permissions::CREATE,
+                "copy",
+            )
+            .await?;
+        }
+
         let mut r = self
             .client
-            .client
-            .post(format!("{}/copyfile", self.client.api_host));
+            .client()
+            .post(format!("{}/copyfile", self.client.api_host()));
 
         if let Some(v) = self.from_path {
             r = r.query(&[("path", v)]);
@@ -538,12 +602,10 @@ impl CopyFileRequestBuilder {
 
         r = self.client.add_token(r);
 
-        let result = r
-            .send()
-            .await?
-            .json::<pcloud_model::FileOrFolderStat>()
-            .await?
-            .assert_ok()?;
+        let result = self
+            .client
+            .send_checked::<pcloud_model::FileOrFolderStat>(r)
+            .await?;
         Ok(result)
     }
 }
@@ -563,17 +625,23 @@ pub struct MoveFileRequestBuilder {
     to_name: Option<String>,
     /// File revision to fetch
     revision_id: Option<u64>,
+    /// if set, the permissions on the file and the target folder are checked before moving
+    check_permissions: bool,
 }
 
 #[allow(dead_code)]
 impl MoveFileRequestBuilder {
-    pub(crate) fn move_file<'a, S: FileDescriptor, T: FolderDescriptor>(
+    pub(crate) fn move_file<S: FileDescriptor, T: FolderDescriptor>(
         client: &PCloudClient,
         file_like: S,
         target_folder_like: T,
-    ) -> Result<MoveFileRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
-        let source = file_like.to_file()?;
-        let target = target_folder_like.to_folder()?;
+    ) -> Result<MoveFileRequestBuilder, PCloudError> {
+        let source = file_like
+            .to_file()
+            .map_err(PCloudError::InvalidDescriptor)?;
+        let target = target_folder_like
+            .to_folder()
+            .map_err(PCloudError::InvalidDescriptor)?;
 
         if !source.is_empty() && !target.is_empty() {
             Ok(MoveFileRequestBuilder {
@@ -584,9 +652,12 @@ impl MoveFileRequestBuilder {
                 client: client.clone(),
                 to_name: None,
                 revision_id: source.revision,
+                check_permissions: false,
             })
         } else {
-            Err(pcloud_model::PCloudResult::NoFileIdOrPathProvided)?
+            Err(PCloudError::InvalidDescriptor(
+                PCloudResult::NoFileIdOrPathProvided,
+            ))?
         }
     }
 
@@ -602,14 +673,44 @@ impl MoveFileRequestBuilder {
         self
     }
 
+    /// If set, the delete permission on a file and the create permission on a target folder shared by another user are checked before moving. Fails with `PermissionDenied` instead of a server error.
+    pub fn check_permissions(mut self, value: bool) -> MoveFileRequestBuilder {
+        self.check_permissions = value;
+        self
+    }
+
     // Execute the move operation
-    pub async fn execute(
-        self,
-    ) -> Result<pcloud_model::FileOrFolderStat, Box<dyn std::error::Error + Send + Sync>> {
+    pub async fn execute(self) -> Result<pcloud_model::FileOrFolderStat, PCloudError> {
+        let source = PCloudFile {
+            file_id: self.from_file_id,
+            path: self.from_path.clone(),
+            revision: None,
+        };
+        let target = permissions::receiving_folder(self.to_folder_id, self.to_path.as_ref());
+        self.client.guard_file(&source, "move").await?;
+        self.client.guard_target(&target, "move").await?;
+
+        if self.check_permissions {
+            permissions::require_file_permission(
+                &self.client,
+                &source,
+                Permissions::DELETE,
+                "move",
+            )
+            .await?;
+            permissions::require_folder_permission(
+                &self.client,
+                &target,
+                Permissions::CREATE,
+                "move",
+            )
+            .await?;
+        }
+
         let mut r = self
             .client
-            .client
-            .post(format!("{}/renamefile", self.client.api_host));
+            .client()
+            .post(format!("{}/renamefile", self.client.api_host()));
 
         if let Some(v) = self.from_path {
             r = r.query(&[("path", v)]);
@@ -637,16 +738,299 @@ impl MoveFileRequestBuilder {
 
         r = self.client.add_token(r);
 
-        let result = r
-            .send()
-            .await?
-            .json::<pcloud_model::FileOrFolderStat>()
-            .await?
-            .assert_ok()?;
+        let result = self
+            .client
+            .send_checked::<pcloud_model::FileOrFolderStat>(r)
+            .await?;
         Ok(result)
     }
 }
 
+/// Content of a file to upload
+enum UploadContent {
+    /// Content given as reqwest body (in-memory or opaque stream)
+    Body(Body),
+    /// Source (like a local file) only opened once its part is streamed
+    Lazy(SourceOpener),
+    /// Shared buffer (e.g. a memory-mapped file), passed on without copying
+    Bytes(Bytes),
+}
+
+/// Content of a file to upload as stream of chunks
+type ContentStream = BoxStream<'static, Result<Bytes, std::io::Error>>;
+
+/// Reader of the content of a lazily opened upload source
+type SourceReader = Box<dyn AsyncRead + Send + Unpin>;
+
+/// Opens an upload source, can be called again (e.g. to calculate the checksum before the upload)
+type SourceOpener =
+    Arc<dyn Fn() -> BoxFuture<'static, Result<SourceReader, std::io::Error>> + Send + Sync>;
+
+/// Reads the source only once the stream is polled, so sources of parts not yet sent are not held open
+fn lazy_stream(open: SourceOpener, buffers: BufferPool) -> ContentStream {
+    futures::stream::once(async move { open().await })
+        .map(move |reader| match reader {
+            Ok(reader) => buffers.file_stream(reader).boxed(),
+            Err(e) => futures::stream::once(async move { Err(e) }).boxed(),
+        })
+        .flatten()
+        .boxed()
+}
+
+/// Makes a stream `Sync` as required for request bodies. The stream is only ever polled through an exclusive reference, so the mutex is never contended.
+struct SyncStream(Mutex<ContentStream>);
+
+impl Stream for SyncStream {
+    type Item = Result<Bytes, std::io::Error>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        self.get_mut().0.get_mut().unwrap().poll_next_unpin(cx)
+    }
+}
+
+/// Converts the stream into a request body
+fn stream_body(stream: ContentStream) -> Body {
+    Body::wrap_stream(SyncStream(Mutex::new(stream)))
+}
+
+impl UploadContent {
+    /// Converts the content into a request body, limiting the throughput if requested (per request and by the bandwidth budget of the client) and reporting the bytes sent to the observer of the item. Opaque streams can not be limited or observed.
+    fn into_body(
+        self,
+        max_bytes_per_second: Option<u64>,
+        client: &PCloudClient,
+        progress: Option<(Arc<dyn ProgressObserver>, String)>,
+    ) -> Body {
+        let bandwidth = client.rate_limiter().bandwidth();
+        if max_bytes_per_second.is_none() && bandwidth.is_none() && progress.is_none() {
+            return match self {
+                UploadContent::Body(body) => body,
+                UploadContent::Lazy(open) => {
+                    stream_body(lazy_stream(open, client.buffer_pool().clone()))
+                }
+                UploadContent::Bytes(bytes) => Body::from(bytes),
+            };
+        }
+
+        let stream: ContentStream = match self {
+            UploadContent::Body(body) => match body.as_bytes() {
+                Some(bytes) => {
+                    let content = Bytes::copy_from_slice(bytes);
+                    Box::pin(futures::stream::once(async move { Ok(content) }))
+                }
+                None => {
+                    if max_bytes_per_second.is_some() {
+                        warn!("Upload speed limit not applicable to streamed body");
+                    } else {
+                        debug!("Bandwidth limit or progress not applicable to streamed body");
+                    }
+                    return body;
+                }
+            },
+            UploadContent::Lazy(open) => lazy_stream(open, client.buffer_pool().clone()),
+            UploadContent::Bytes(bytes) => {
+                Box::pin(futures::stream::once(async move { Ok(bytes) }))
+            }
+        };
+        let stream = match max_bytes_per_second {
+            Some(limit) => throttle::throttle(stream, limit).boxed(),
+            None => stream,
+        };
+        let stream = match progress {
+            Some((observer, item)) => progress::observe(stream, observer, item).boxed(),
+            None => stream,
+        };
+        match bandwidth {
+            Some(limiter) => stream_body(rate_limit::limit(stream, limiter).boxed()),
+            None => stream_body(stream),
+        }
+    }
+}
+
+/// Error of an upload request exceeding the remaining quota of the user (see `UploadRequestBuilder::check_quota()`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct InsufficientQuota {
+    /// Bytes to upload
+    pub needed: u64,
+    /// Bytes remaining in the quota of the user
+    pub available: u64,
+}
+
+impl Display for InsufficientQuota {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Insufficient quota: {} bytes needed, {} bytes available",
+            self.needed, self.available
+        )
+    }
+}
+
+impl std::error::Error for InsufficientQuota {}
+
+/// Error of a download not matching the checksum announced by pCloud
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChecksumMismatch {
+    /// Checksum algorithm (`SHA-1` or `SHA-256`)
+    pub algorithm: String,
+    /// Checksum (hex) announced by pCloud
+    pub expected: String,
+    /// Checksum (hex) of the data received
+    pub actual: String,
+}
+
+impl Display for ChecksumMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} checksum mismatch: expected {}, got {}",
+            self.algorithm, self.expected, self.actual
+        )
+    }
+}
+
+impl std::error::Error for ChecksumMismatch {}
+
+/// Outcome of the upload of a single file
+#[derive(Debug)]
+pub struct UploadOutcome {
+    /// Name of the file as requested
+    pub file_name: String,
+    /// Metadata of the file in pCloud or the error
+    pub result: Result<Metadata, PCloudError>,
+}
+
+/// Result of an upload, pairing each requested file (in the order requested) with its metadata or error
+#[derive(Debug, Default)]
+pub struct UploadReport {
+    /// Outcome of each requested file
+    pub outcomes: Vec<UploadOutcome>,
+}
+
+impl UploadReport {
+    /// True if all files were uploaded
+    pub fn is_success(&self) -> bool {
+        self.outcomes.iter().all(|o| o.result.is_ok())
+    }
+
+    /// Requested file names and metadata of all files uploaded
+    pub fn uploaded(&self) -> impl Iterator<Item = (&str, &Metadata)> {
+        self.outcomes.iter().filter_map(|o| match &o.result {
+            Ok(metadata) => Some((o.file_name.as_str(), metadata)),
+            Err(_) => None,
+        })
+    }
+
+    /// Requested file names and errors of all files not uploaded
+    pub fn failures(&self) -> impl Iterator<Item = (&str, &PCloudError)> {
+        self.outcomes.iter().filter_map(|o| match &o.result {
+            Ok(_) => None,
+            Err(e) => Some((o.file_name.as_str(), e)),
+        })
+    }
+
+    /// Ids of all files uploaded
+    pub fn fileids(&self) -> Vec<u64> {
+        self.files().map(|(id, _)| id).collect()
+    }
+
+    /// Pairs the id of each file uploaded with its metadata (in the order requested)
+    pub fn files(&self) -> impl Iterator<Item = (u64, &Metadata)> {
+        self.uploaded()
+            .filter_map(|(_, m)| m.fileid.map(|id| (id, m)))
+    }
+
+    /// Metadata of the uploaded file with the given name, either as requested or as renamed by `rename_if_exists()`
+    pub fn find_by_name(&self, name: &str) -> Option<&Metadata> {
+        self.uploaded()
+            .find(|(requested, m)| *requested == name || m.name == name)
+            .map(|(_, m)| m)
+    }
+
+    /// Metadata of all files (in the order requested). Fails with the first error, if any file wasn't uploaded.
+    pub fn into_metadata(self) -> Result<Vec<Metadata>, PCloudError> {
+        self.outcomes.into_iter().map(|o| o.result).collect()
+    }
+}
+
+/// Single file of an upload request
+struct UploadPart {
+    /// Name of the file in pCloud
+    file_name: String,
+    /// Explicit MIME type of the file
+    content_type: Option<String>,
+    /// Content of the file
+    content: UploadContent,
+    /// Content length, if known in advance
+    length: Option<u64>,
+}
+
+impl UploadPart {
+    /// Calculates the SHA-1 checksum (hex) of the content. Not available for bodies wrapping a stream. Lazy sources are opened separately for it.
+    async fn sha1(&mut self, buffers: &BufferPool) -> Result<Option<String>, std::io::Error> {
+        match &mut self.content {
+            UploadContent::Body(body) => {
+                Ok(body.as_bytes().map(|b| format!("{:x}", Sha1::digest(b))))
+            }
+            UploadContent::Lazy(open) => {
+                let mut reader = open().await?;
+                let mut hasher = Sha1::new();
+                let mut buffer = buffers.take();
+                buffer.reserve(buffers.chunk_size());
+                while reader.read_buf(&mut *buffer).await? > 0 {
+                    hasher.update(&buffer[..]);
+                    buffer.clear();
+                }
+                Ok(Some(format!("{:x}", hasher.finalize())))
+            }
+            UploadContent::Bytes(bytes) => Ok(Some(format!("{:x}", Sha1::digest(bytes)))),
+        }
+    }
+
+    /// Size of the file, if known in advance
+    fn size(&self) -> Option<u64> {
+        match &self.content {
+            UploadContent::Body(body) => body.as_bytes().map(|b| b.len() as u64).or(self.length),
+            UploadContent::Lazy(_) => self.length,
+            UploadContent::Bytes(bytes) => Some(bytes.len() as u64),
+        }
+    }
+}
+
+/// Checks if the given name is usable as pCloud file name: Not empty, no path separators or control characters
+pub fn validate_file_name(file_name: &str) -> Result<(), PCloudResult> {
+    if file_name.is_empty()
+        || file_name == "."
+        || file_name == ".."
+        || file_name.contains(['/', '\\'])
+        || file_name.chars().any(char::is_control)
+    {
+        debug!("Invalid file name {:?}", file_name);
+        Err(PCloudResult::InvalidFileOrFolderName)
+    } else {
+        Ok(())
+    }
+}
+
+/// Percent-encodes all non-ASCII characters (and '%' itself) of the file name as UTF-8 bytes
+fn percent_encode_file_name(file_name: &str) -> String {
+    let mut result = String::with_capacity(file_name.len());
+    for c in file_name.chars() {
+        if c.is_ascii() && c != '%' {
+            result.push(c);
+        } else {
+            let mut buffer = [0; 4];
+            for b in c.encode_utf8(&mut buffer).bytes() {
+                result.push_str(&format!("%{:02X}", b));
+            }
+        }
+    }
+    result
+}
+
 pub struct UploadRequestBuilder {
     /// Client to actually perform the request
     client: PCloudClient,
@@ -663,16 +1047,34 @@ pub struct UploadRequestBuilder {
     /// if set, file created time is set. It's required to provide mtime to set ctime. Have to be unix time seconds.
     ctime: Option<i64>,
     /// files to upload
-    files: Vec<reqwest::multipart::Part>,
+    files: Vec<UploadPart>,
+    /// if set, non-ASCII characters (and '%') of file names are percent-encoded
+    percent_encode_file_names: bool,
+    /// if set, the upload speed of the request is limited to the given bytes per second
+    max_bytes_per_second: Option<u64>,
+    /// if set, the remaining quota is checked before the upload starts
+    check_quota: bool,
+    /// if set, files already present in the target folder with the same checksum are not uploaded again
+    skip_identical: bool,
+    /// Folder to copy files with the same name and checksum from (server-side) instead of uploading them
+    copy_identical_from: Option<PCloudFolder>,
+    /// Modification time (unix time seconds) of the last file added from a local path
+    local_mtime: Option<i64>,
+    /// if set, the modification time of the local file is propagated
+    keep_local_mtime: bool,
+    /// Receives the progress of the upload
+    progress: Option<Arc<dyn ProgressObserver>>,
 }
 
 #[allow(dead_code)]
 impl UploadRequestBuilder {
-    pub(crate) fn into_folder<'a, T: FolderDescriptor>(
+    pub(crate) fn into_folder<T: FolderDescriptor>(
         client: &PCloudClient,
         folder_like: T,
-    ) -> Result<UploadRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
-        let f = folder_like.to_folder()?;
+    ) -> Result<UploadRequestBuilder, PCloudError> {
+        let f = folder_like
+            .to_folder()
+            .map_err(PCloudError::InvalidDescriptor)?;
 
         if !f.is_empty() {
             Ok(UploadRequestBuilder {
@@ -684,9 +1086,19 @@ impl UploadRequestBuilder {
                 mtime: None,
                 ctime: None,
                 files: Vec::new(),
+                percent_encode_file_names: false,
+                max_bytes_per_second: None,
+                check_quota: false,
+                skip_identical: false,
+                copy_identical_from: None,
+                local_mtime: None,
+                keep_local_mtime: false,
+                progress: None,
             })
         } else {
-            Err(pcloud_model::PCloudResult::NoFileIdOrPathProvided)?
+            Err(PCloudError::InvalidDescriptor(
+                PCloudResult::NoFileIdOrPathProvided,
+            ))?
         }
     }
 
@@ -724,28 +1136,260 @@ impl UploadRequestBuilder {
 
     /// Adds a file to the upload request. Multiple files can be added!
     pub fn with_file<T: Into<Body>>(mut self, file_name: &str, body: T) -> UploadRequestBuilder {
-        let file_part = reqwest::multipart::Part::stream(body).file_name(file_name.to_string());
-        self.files.push(file_part);
+        self.files.push(UploadPart {
+            file_name: file_name.to_string(),
+            content_type: None,
+            content: UploadContent::Body(body.into()),
+            length: None,
+        });
         self
     }
 
-    // Finally uploads the files
-    pub async fn upload(self) -> Result<UploadedFile, Box<dyn std::error::Error + Send + Sync>> {
-        if self.files.is_empty() {
+    /// Adds a file with an explicit MIME type (like `image/png`) to the upload request
+    pub fn with_file_and_content_type<T: Into<Body>>(
+        mut self,
+        file_name: &str,
+        body: T,
+        content_type: &str,
+    ) -> UploadRequestBuilder {
+        self.files.push(UploadPart {
+            file_name: file_name.to_string(),
+            content_type: Some(content_type.to_string()),
+            content: UploadContent::Body(body.into()),
+            length: None,
+        });
+        self
+    }
+
+    /// Adds a file from a shared buffer to the upload request. The content is neither copied nor re-buffered, its length is announced in the multipart form.
+    pub fn with_file_from_bytes(mut self, file_name: &str, content: Bytes) -> UploadRequestBuilder {
+        self.files.push(UploadPart {
+            file_name: file_name.to_string(),
+            content_type: None,
+            length: Some(content.len() as u64),
+            content: UploadContent::Bytes(content),
+        });
+        self
+    }
+
+    /// Adds a local file to the upload request by mapping it into memory, its name is used as file name. The mapped pages are sent without copying them into buffers. The file must not be modified until the upload is finished.
+    #[cfg(feature = "mmap")]
+    pub async fn with_file_mmapped<P: AsRef<Path>>(
+        mut self,
+        path: P,
+    ) -> Result<UploadRequestBuilder, PCloudError> {
+        let path = path.as_ref();
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| PCloudError::NotAFile(path.to_path_buf()))?
+            .to_string_lossy()
+            .to_string();
+
+        let file = std::fs::File::open(path)?;
+        let file_metadata = file.metadata()?;
+        if let Ok(modified) = file_metadata.modified() {
+            self.local_mtime = Some(DateTime::<Utc>::from(modified).timestamp());
+        }
+
+        debug!("Mapping local file {} for upload request", path.display());
+        // Empty files can't be mapped
+        let content = if file_metadata.len() == 0 {
+            Bytes::new()
+        } else {
+            // Safety: The documentation requires the file not to be modified during the upload
+            let map = unsafe { memmap2::Mmap::map(&file)? };
+            Bytes::from_owner(map)
+        };
+        Ok(self.with_file_from_bytes(&file_name, content))
+    }
+
+    /// if set, non-ASCII characters (and '%') of all file names are percent-encoded (UTF-8), so names are transferred the same way regardless of the server side handling of the multipart form.
+    pub fn percent_encode_file_names(mut self, value: bool) -> UploadRequestBuilder {
+        self.percent_encode_file_names = value;
+        self
+    }
+
+    /// Adds a file to the upload request whose content is only opened once its part is streamed (and again, if its checksum is needed for `skip_identical()`). Sources of parts not yet sent are not held open, so memory stays bounded regardless of the number of files.
+    pub fn with_file_from_source<F, Fut, R>(
+        mut self,
+        file_name: &str,
+        length: Option<u64>,
+        open: F,
+    ) -> UploadRequestBuilder
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<R, std::io::Error>> + Send + 'static,
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        let open: SourceOpener = Arc::new(move || {
+            let reader = open();
+            async move { Ok(Box::new(reader.await?) as SourceReader) }.boxed()
+        });
+        self.files.push(UploadPart {
+            file_name: file_name.to_string(),
+            content_type: None,
+            content: UploadContent::Lazy(open),
+            length,
+        });
+        self
+    }
+
+    /// Adds a local file to the upload request, its name is used as file name. The file is only opened once its part is streamed and is read without buffering.
+    pub async fn with_file_from_path<P: AsRef<Path>>(
+        mut self,
+        path: P,
+    ) -> Result<UploadRequestBuilder, PCloudError> {
+        let path = path.as_ref();
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| PCloudError::NotAFile(path.to_path_buf()))?
+            .to_string_lossy()
+            .to_string();
+
+        let file_metadata = tokio::fs::metadata(path).await?;
+        if !file_metadata.is_file() {
+            return Err(PCloudError::NotAFile(path.to_path_buf()));
+        }
+        if let Ok(modified) = file_metadata.modified() {
+            self.local_mtime = Some(DateTime::<Utc>::from(modified).timestamp());
+        }
+
+        debug!("Adding local file {} to upload request", path.display());
+        let path = path.to_path_buf();
+        Ok(
+            self.with_file_from_source(&file_name, Some(file_metadata.len()), move || {
+                tokio::fs::File::open(path.clone())
+            }),
+        )
+    }
+
+    /// Limits the upload speed of this request to the given bytes per second, e.g. to deprioritize a large backup upload compared to interactive traffic. Applies to in-memory content and local files, not to bodies wrapping a stream.
+    pub fn max_bytes_per_second(mut self, value: u64) -> UploadRequestBuilder {
+        self.max_bytes_per_second = Some(value);
+        self
+    }
+
+    /// if set, the remaining quota of the user is checked (using `userinfo`) before the upload starts. Fails fast with `InsufficientQuota` if the size of all files (as far as known in advance) exceeds it.
+    pub fn check_quota(mut self, value: bool) -> UploadRequestBuilder {
+        self.check_quota = value;
+        self
+    }
+
+    /// if set, files already present in the target folder with the same name and SHA-1 checksum are not uploaded again (their metadata is still part of the result). Applies to in-memory content and local files, not to bodies wrapping a stream.
+    pub fn skip_identical(mut self, value: bool) -> UploadRequestBuilder {
+        self.skip_identical = value;
+        self
+    }
+
+    /// Files present in the given folder with the same name and SHA-1 checksum (e.g. in the previous nightly backup) are copied server-side instead of being uploaded. Applies to in-memory content and local files, not to bodies wrapping a stream.
+    pub fn copy_identical_from<T: FolderDescriptor>(
+        mut self,
+        folder_like: T,
+    ) -> Result<UploadRequestBuilder, PCloudError> {
+        let f = folder_like
+            .to_folder()
+            .map_err(PCloudError::InvalidDescriptor)?;
+        if f.is_empty() {
+            return Err(PCloudError::InvalidDescriptor(
+                PCloudResult::NoFullPathOrFolderIdProvided,
+            ))?;
+        }
+        self.copy_identical_from = Some(f);
+        Ok(self)
+    }
+
+    /// if set, the modification time of the local file (last one added with `with_file_from_path()` or `with_file_mmapped()`) is propagated to pCloud. An explicitly set mtime takes precedence.
+    pub fn keep_local_mtime(mut self, value: bool) -> UploadRequestBuilder {
+        self.keep_local_mtime = value;
+        self
+    }
+
+    /// Reports the progress of the upload, each file sent being an item. Files skipped as identical aren't reported, streamed bodies (`with_file()` with a stream) only report start and end.
+    pub fn progress_observer(mut self, value: Arc<dyn ProgressObserver>) -> UploadRequestBuilder {
+        self.progress = Some(value);
+        self
+    }
+
+    /// Finally uploads the files and returns a report pairing each requested file (in the order added) with its metadata. Fails without sending anything if a file name is invalid.
+    pub async fn upload(self) -> Result<UploadReport, PCloudError> {
+        let (requested, uploaded, deduplicated) = self.execute().await?;
+
+        let mut results: Vec<(String, Metadata)> =
+            uploaded.into_iter().chain(deduplicated).collect();
+        let outcomes = requested
+            .into_iter()
+            .map(|file_name| {
+                let result = match results.iter().position(|(name, _)| *name == file_name) {
+                    Some(index) => Ok(results.remove(index).1),
+                    None => Err(PCloudError::MissingMetadata(file_name.clone())),
+                };
+                UploadOutcome { file_name, result }
+            })
+            .collect();
+        Ok(UploadReport { outcomes })
+    }
+
+    /// Uploads the files. Returns the requested file names and the metadata of all files uploaded and of all files deduplicated, each paired with the requested file name.
+    async fn execute(
+        self,
+    ) -> Result<
+        (
+            Vec<String>,
+            Vec<(String, Metadata)>,
+            Vec<(String, Metadata)>,
+        ),
+        PCloudError,
+    > {
+        let mut files = self.files;
+        for file in files.iter_mut() {
+            validate_file_name(&file.file_name)?;
+            if self.percent_encode_file_names {
+                file.file_name = percent_encode_file_name(&file.file_name);
+            }
+        }
+        let requested: Vec<String> = files.iter().map(|f| f.file_name.clone()).collect();
+
+        let mut deduplicated = Vec::new();
+        if !files.is_empty() && (self.skip_identical || self.copy_identical_from.is_some()) {
+            let target = PCloudFolder {
+                folder_id: self.folder_id,
+                path: self.path.clone(),
+            };
+            (files, deduplicated) = deduplicate(
+                &self.client,
+                target,
+                self.skip_identical,
+                self.copy_identical_from,
+                files,
+            )
+            .await?;
+        }
+
+        if self.check_quota {
+            let needed: u64 = files.iter().filter_map(|f| f.size()).sum();
+            let user_info = self.client.get_user_info().await?;
+            if let (Some(quota), Some(used_quota)) = (user_info.quota, user_info.usedquota) {
+                let available = quota.saturating_sub(used_quota);
+                if needed > available {
+                    debug!(
+                        "Upload of {} bytes rejected, only {} bytes of quota available",
+                        needed, available
+                    );
+                    return Err(InsufficientQuota { needed, available })?;
+                }
+            }
+        }
+
+        if files.is_empty() {
             // Short cut operation if no files are configured to upload
             debug!("Requested file upload, but no files are added to the request.");
-            let result = UploadedFile {
-                result: PCloudResult::Ok,
-                fileids: Vec::default(),
-                metadata: Vec::default(),
-            };
-            return Ok(result);
+            return Ok((requested, Vec::new(), deduplicated));
         }
 
         let mut r = self
             .client
-            .client
-            .post(format!("{}/uploadfile", self.client.api_host));
+            .client()
+            .post(format!("{}/uploadfile", self.client.api_host()));
 
         if let Some(v) = self.path {
             r = r.query(&[("path", v)]);
@@ -763,7 +1407,13 @@ impl UploadRequestBuilder {
             r = r.query(&[("renameifexists", "1")]);
         }
 
-        if let Some(v) = self.mtime {
+        let mtime = match self.mtime {
+            Some(v) => Some(v),
+            None if self.keep_local_mtime => self.local_mtime,
+            None => None,
+        };
+
+        if let Some(v) = mtime {
             r = r.query(&[("mtime", v)]);
         }
 
@@ -773,18 +1423,300 @@ impl UploadRequestBuilder {
 
         r = self.client.add_token(r);
 
+        // pCloud returns the metadata in the order of the parts
+        let uploaded_names: Vec<String> = files.iter().map(|f| f.file_name.clone()).collect();
+        if let Some(observer) = &self.progress {
+            let sizes: Option<u64> = files.iter().map(|f| f.size()).sum();
+            observer.operation_started(Some(files.len()), sizes);
+        }
         let mut form = reqwest::multipart::Form::new();
-        for part in self.files {
+        for file in files {
+            let progress = self.progress.as_ref().map(|observer| {
+                observer.item_started(&file.file_name, file.size());
+                (observer.clone(), file.file_name.clone())
+            });
+            let body = file
+                .content
+                .into_body(self.max_bytes_per_second, &self.client, progress);
+            let mut part = match file.length {
+                Some(length) => reqwest::multipart::Part::stream_with_length(body, length),
+                None => reqwest::multipart::Part::stream(body),
+            }
+            .file_name(file.file_name);
+            if let Some(content_type) = file.content_type {
+                part = part.mime_str(&content_type)?;
+            }
             form = form.part("part", part);
         }
 
         r = r.multipart(form);
 
-        let result = r.send().await?.json::<UploadedFile>().await?.assert_ok()?;
-        Ok(result)
+        let result = self.client.send_checked::<UploadedFile>(r).await;
+        if let Some(observer) = &self.progress {
+            for file_name in &uploaded_names {
+                observer.item_finished(file_name, result.as_ref().err().map(|e| e as _));
+            }
+            observer.operation_finished();
+        }
+        let result = result?;
+        // Fails if the ids and metadata returned by pCloud don't line up
+        let fileids: Vec<u64> = result.files()?.map(|(id, _)| id).collect();
+        debug!("Uploaded file ids {:?}", fileids);
+        let uploaded = uploaded_names.into_iter().zip(result.metadata).collect();
+        Ok((requested, uploaded, deduplicated))
     }
 }
 
+/// Computes the checksums announced by pCloud on the fly and compares them. pCloud always returns SHA-1, additionally MD5 on US and SHA-256 on EU API servers.
+struct ChecksumVerifier<'c> {
+    sha1: Option<(&'c String, Sha1)>,
+    md5: Option<(&'c String, md5::Md5)>,
+    sha256: Option<(&'c String, Sha256)>,
+}
+
+impl<'c> ChecksumVerifier<'c> {
+    /// Creates a verifier for the given expected checksums (hex). Fails if none is given.
+    fn new(
+        sha1: &'c Option<String>,
+        md5: &'c Option<String>,
+        sha256: &'c Option<String>,
+    ) -> Result<ChecksumVerifier<'c>, PCloudError> {
+        if sha1.is_none() && md5.is_none() && sha256.is_none() {
+            return Err(PCloudError::MissingChecksum);
+        }
+        Ok(ChecksumVerifier {
+            sha1: sha1.as_ref().map(|e| (e, Sha1::new())),
+            md5: md5.as_ref().map(|e| (e, md5::Md5::new())),
+            sha256: sha256.as_ref().map(|e| (e, Sha256::new())),
+        })
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        if let Some((_, hasher)) = &mut self.sha1 {
+            hasher.update(data);
+        }
+        if let Some((_, hasher)) = &mut self.md5 {
+            hasher.update(data);
+        }
+        if let Some((_, hasher)) = &mut self.sha256 {
+            hasher.update(data);
+        }
+    }
+
+    /// Compares all computed checksums with the expected ones
+    fn verify(self) -> Result<(), ChecksumMismatch> {
+        let actual = [
+            (
+                "SHA-1",
+                self.sha1.map(|(e, h)| (e, format!("{:x}", h.finalize()))),
+            ),
+            (
+                "MD5",
+                self.md5.map(|(e, h)| (e, format!("{:x}", h.finalize()))),
+            ),
+            (
+                "SHA-256",
+                self.sha256.map(|(e, h)| (e, format!("{:x}", h.finalize()))),
+            ),
+        ];
+        for (algorithm, checksums) in actual {
+            if let Some((expected, actual)) = checksums {
+                if !expected.eq_ignore_ascii_case(&actual) {
+                    return Err(ChecksumMismatch {
+                        algorithm: algorithm.to_string(),
+                        expected: expected.clone(),
+                        actual,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FileChecksums {
+    /// Verifies the given content against all checksums returned by pCloud (SHA-1 and, depending on the region, MD5 or SHA-256). Fails with `ChecksumMismatch` on the first difference.
+    pub fn verify_bytes(&self, data: &[u8]) -> Result<(), PCloudError> {
+        let mut verifier = ChecksumVerifier::new(&self.sha1, &self.md5, &self.sha256)?;
+        verifier.update(data);
+        Ok(verifier.verify()?)
+    }
+
+    /// Verifies the content of the local file (streamed, not loaded into memory) against all checksums returned by pCloud (SHA-1 and, depending on the region, MD5 or SHA-256). Fails with `ChecksumMismatch` on the first difference.
+    pub async fn verify_file<P: AsRef<Path>>(&self, path: P) -> Result<(), PCloudError> {
+        let mut verifier = ChecksumVerifier::new(&self.sha1, &self.md5, &self.sha256)?;
+        let mut file = tokio::fs::File::open(path).await?;
+        let mut buffer = vec![0u8; 64 * 1024];
+        loop {
+            let read = file.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            verifier.update(&buffer[..read]);
+        }
+        Ok(verifier.verify()?)
+    }
+}
+
+/// Writes the stream to the given file. If `verify` is set, the checksums are computed on the fly and compared with the ones of the FileInfo.
+async fn write_and_verify<S: Stream<Item = Result<Bytes, PCloudError>>>(
+    info: &FileInfo,
+    stream: S,
+    path: &Path,
+    verify: bool,
+) -> Result<(), PCloudError> {
+    let mut verifier = match verify {
+        true => Some(ChecksumVerifier::new(&info.sha1, &info.md5, &info.sha256)?),
+        false => None,
+    };
+    let mut file = tokio::fs::File::create(path).await?;
+
+    futures::pin_mut!(stream);
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        if let Some(verifier) = &mut verifier {
+            verifier.update(&chunk);
+        }
+        file.write_all(&chunk).await?;
+    }
+    file.flush().await?;
+
+    if let Some(verifier) = verifier {
+        verifier.verify()?;
+    }
+    Ok(())
+}
+
+/// Lets `write` fill a temporary `.part` file next to the given path and renames it to the path once complete (and verified). The `.part` file is removed on any failure, so no partial download is left behind.
+pub(crate) async fn save_download<F, Fut>(path: &Path, write: F) -> Result<(), PCloudError>
+where
+    F: FnOnce(PathBuf) -> Fut,
+    Fut: Future<Output = Result<(), PCloudError>>,
+{
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| PCloudError::NotAFile(path.to_path_buf()))?;
+    let partial = path.with_file_name(format!("{}.part", file_name.to_string_lossy()));
+
+    let result = match write(partial.clone()).await {
+        Ok(()) => tokio::fs::rename(&partial, path)
+            .await
+            .map_err(PCloudError::from),
+        Err(e) => Err(e),
+    };
+    if result.is_err() {
+        if let Err(e) = tokio::fs::remove_file(&partial).await {
+            debug!("Failed to remove {}: {}", partial.display(), e);
+        }
+    }
+    result
+}
+
+/// Lists the files (not folders) of the given folder
+async fn list_files(
+    client: &PCloudClient,
+    folder: PCloudFolder,
+) -> Result<Vec<Metadata>, PCloudError> {
+    let listing = client.list_folder(folder)?.get().await?;
+    Ok(listing
+        .metadata
+        .map(|m| m.contents)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|m| !m.isfolder)
+        .collect())
+}
+
+/// Returns the index of the candidate with the same name and SHA-1 checksum as the given file
+async fn find_identical(
+    client: &PCloudClient,
+    file: &mut UploadPart,
+    candidates: &[Metadata],
+) -> Result<Option<usize>, PCloudError> {
+    let index = match candidates.iter().position(|c| c.name == file.file_name) {
+        Some(index) => index,
+        None => return Ok(None),
+    };
+    let candidate = &candidates[index];
+    // Cheap check first, different sizes can't be identical
+    if let (Some(size), Some(candidate_size)) = (file.size(), candidate.size) {
+        if size != candidate_size {
+            return Ok(None);
+        }
+    }
+    let (sha1, file_id) = match (file.sha1(client.buffer_pool()).await?, candidate.fileid) {
+        (Some(sha1), Some(file_id)) => (sha1, file_id),
+        _ => return Ok(None),
+    };
+
+    let checksums = client.checksum_file(file_id)?.get().await?;
+    if checksums.sha1.as_deref() == Some(sha1.as_str()) {
+        Ok(Some(index))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Removes all files already present in the target folder (if skip_identical is set) or copies identical files server-side from the source folder (if given) instead of uploading them.
+/// Returns the files still to upload and the metadata of all files skipped or copied.
+async fn deduplicate(
+    client: &PCloudClient,
+    target: PCloudFolder,
+    skip_identical: bool,
+    source: Option<PCloudFolder>,
+    files: Vec<UploadPart>,
+) -> Result<(Vec<UploadPart>, Vec<(String, Metadata)>), PCloudError> {
+    let mut target_files = if skip_identical {
+        list_files(client, target.clone()).await?
+    } else {
+        Vec::new()
+    };
+    let mut source_files = match source {
+        Some(source) => list_files(client, source).await?,
+        None => Vec::new(),
+    };
+
+    // Without trailing slash pCloud treats the target path as path of the new file
+    let copy_target = PCloudFolder {
+        folder_id: target.folder_id,
+        path: target.path.map(|p| {
+            if p.ends_with('/') {
+                p
+            } else {
+                format!("{}/", p)
+            }
+        }),
+    };
+
+    let mut remaining = Vec::new();
+    let mut deduplicated = Vec::new();
+    for mut file in files {
+        if let Some(index) = find_identical(client, &mut file, &target_files).await? {
+            debug!(
+                "Skipping upload of {}, identical file present",
+                file.file_name
+            );
+            deduplicated.push((file.file_name, target_files.swap_remove(index)));
+        } else if let Some(index) = find_identical(client, &mut file, &source_files).await? {
+            debug!(
+                "Copying identical file {} instead of uploading it",
+                file.file_name
+            );
+            let source_file = source_files.swap_remove(index);
+            let copied = client
+                .copy_file(&source_file, &copy_target)?
+                .execute()
+                .await?;
+            if let Some(metadata) = copied.metadata {
+                deduplicated.push((file.file_name, metadata));
+            }
+        } else {
+            remaining.push(file);
+        }
+    }
+    Ok((remaining, deduplicated))
+}
+
 pub struct PublicFileLinkRequestBuilder {
     /// Client to actually perform the request
     client: PCloudClient,
@@ -804,11 +1736,13 @@ pub struct PublicFileLinkRequestBuilder {
 
 #[allow(dead_code)]
 impl PublicFileLinkRequestBuilder {
-    pub(crate) fn for_file<'a, T: FileDescriptor>(
+    pub(crate) fn for_file<T: FileDescriptor>(
         client: &PCloudClient,
         file_like: T,
-    ) -> Result<PublicFileLinkRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
-        let f: PCloudFile = file_like.to_file()?;
+    ) -> Result<PublicFileLinkRequestBuilder, PCloudError> {
+        let f: PCloudFile = file_like
+            .to_file()
+            .map_err(PCloudError::InvalidDescriptor)?;
 
         if !f.is_empty() {
             Ok(PublicFileLinkRequestBuilder {
@@ -823,7 +1757,9 @@ impl PublicFileLinkRequestBuilder {
                 revision_id: f.revision,
             })
         } else {
-            Err(pcloud_model::PCloudResult::NoFileIdOrPathProvided)?
+            Err(PCloudError::InvalidDescriptor(
+                PCloudResult::NoFileIdOrPathProvided,
+            ))?
         }
     }
 
@@ -867,11 +1803,11 @@ impl PublicFileLinkRequestBuilder {
         self
     }
 
-    pub async fn get(self) -> Result<PublicFileLink, Box<dyn std::error::Error + Send + Sync>> {
+    pub async fn get(self) -> Result<PublicFileLink, PCloudError> {
         let mut r = self
             .client
-            .client
-            .get(format!("{}/getfilepublink", self.client.api_host));
+            .client()
+            .get(format!("{}/getfilepublink", self.client.api_host()));
 
         if let Some(id) = self.file_id {
             debug!("Requesting public link for file {}", id);
@@ -909,12 +1845,10 @@ impl PublicFileLinkRequestBuilder {
 
         r = self.client.add_token(r);
 
-        let diff = r
-            .send()
-            .await?
-            .json::<pcloud_model::PublicFileLink>()
-            .await?
-            .assert_ok()?;
+        let diff = self
+            .client
+            .send_checked::<pcloud_model::PublicFileLink>(r)
+            .await?;
         Ok(diff)
     }
 }
@@ -956,13 +1890,11 @@ impl PublicFileDownloadRequestBuilder {
     }
 
     /// Create file download link
-    pub async fn get(
-        self,
-    ) -> Result<pcloud_model::DownloadLink, Box<dyn std::error::Error + Send + Sync>> {
+    pub async fn get(self) -> Result<pcloud_model::DownloadLink, PCloudError> {
         let mut r = self
             .client
-            .client
-            .get(format!("{}/getpublinkdownload", self.client.api_host));
+            .client()
+            .get(format!("{}/getpublinkdownload", self.client.api_host()));
 
         r = r.query(&[("code", self.code)]);
 
@@ -972,12 +1904,10 @@ impl PublicFileDownloadRequestBuilder {
 
         r = self.client.add_token(r);
 
-        let diff = r
-            .send()
-            .await?
-            .json::<pcloud_model::DownloadLink>()
-            .await?
-            .assert_ok()?;
+        let diff = self
+            .client
+            .send_checked::<pcloud_model::DownloadLink>(r)
+            .await?;
         Ok(diff)
     }
 }
@@ -992,11 +1922,13 @@ pub struct ListRevisionsRequestBuilder {
 }
 
 impl ListRevisionsRequestBuilder {
-    pub(crate) fn for_file<'a, T: FileDescriptor>(
+    pub(crate) fn for_file<T: FileDescriptor>(
         client: &PCloudClient,
         file_like: T,
-    ) -> Result<ListRevisionsRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
-        let f = file_like.to_file()?;
+    ) -> Result<ListRevisionsRequestBuilder, PCloudError> {
+        let f = file_like
+            .to_file()
+            .map_err(PCloudError::InvalidDescriptor)?;
 
         if !f.is_empty() {
             Ok(ListRevisionsRequestBuilder {
@@ -1005,16 +1937,18 @@ impl ListRevisionsRequestBuilder {
                 client: client.clone(),
             })
         } else {
-            Err(pcloud_model::PCloudResult::NoFileIdOrPathProvided)?
+            Err(PCloudError::InvalidDescriptor(
+                PCloudResult::NoFileIdOrPathProvided,
+            ))?
         }
     }
 
     /// Executes the request
-    pub async fn get(self) -> Result<RevisionList, Box<dyn std::error::Error + Send + Sync>> {
+    pub async fn get(self) -> Result<RevisionList, PCloudError> {
         let mut r = self
             .client
-            .client
-            .get(format!("{}/listrevisions", self.client.api_host));
+            .client()
+            .get(format!("{}/listrevisions", self.client.api_host()));
 
         if let Some(id) = self.file_id {
             debug!("Requesting file revisions for file {}", id);
@@ -1028,7 +1962,7 @@ impl ListRevisionsRequestBuilder {
 
         r = self.client.add_token(r);
 
-        let result = r.send().await?.json::<RevisionList>().await?.assert_ok()?;
+        let result = self.client.send_checked::<RevisionList>(r).await?;
         Ok(result)
     }
 }
@@ -1046,11 +1980,13 @@ pub struct ChecksumFileRequestBuilder {
 
 #[allow(dead_code)]
 impl ChecksumFileRequestBuilder {
-    pub(crate) fn for_file<'a, T: FileDescriptor>(
+    pub(crate) fn for_file<T: FileDescriptor>(
         client: &PCloudClient,
         file_like: T,
-    ) -> Result<ChecksumFileRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
-        let f = file_like.to_file()?;
+    ) -> Result<ChecksumFileRequestBuilder, PCloudError> {
+        let f = file_like
+            .to_file()
+            .map_err(PCloudError::InvalidDescriptor)?;
 
         if !f.is_empty() {
             Ok(ChecksumFileRequestBuilder {
@@ -1060,7 +1996,9 @@ impl ChecksumFileRequestBuilder {
                 revision_id: f.revision,
             })
         } else {
-            Err(pcloud_model::PCloudResult::NoFileIdOrPathProvided)?
+            Err(PCloudError::InvalidDescriptor(
+                PCloudResult::NoFileIdOrPathProvided,
+            ))?
         }
     }
 
@@ -1071,13 +2009,11 @@ impl ChecksumFileRequestBuilder {
     }
 
     /// Executes the request
-    pub async fn get(
-        self,
-    ) -> Result<pcloud_model::FileChecksums, Box<dyn std::error::Error + Send + Sync>> {
+    pub async fn get(self) -> Result<pcloud_model::FileChecksums, PCloudError> {
         let mut r = self
             .client
-            .client
-            .get(format!("{}/checksumfile", self.client.api_host));
+            .client()
+            .get(format!("{}/checksumfile", self.client.api_host()));
 
         if let Some(id) = self.file_id {
             debug!("Requesting file checksums for file {}", id);
@@ -1095,12 +2031,10 @@ impl ChecksumFileRequestBuilder {
 
         r = self.client.add_token(r);
 
-        let diff = r
-            .send()
-            .await?
-            .json::<pcloud_model::FileChecksums>()
-            .await?
-            .assert_ok()?;
+        let diff = self
+            .client
+            .send_checked::<pcloud_model::FileChecksums>(r)
+            .await?;
         Ok(diff)
     }
 }
@@ -1116,11 +2050,13 @@ pub struct FileDeleteRequestBuilder {
 
 #[allow(dead_code)]
 impl FileDeleteRequestBuilder {
-    pub(crate) fn for_file<'a, T: FileDescriptor>(
+    pub(crate) fn for_file<T: FileDescriptor>(
         client: &PCloudClient,
         file_like: T,
-    ) -> Result<FileDeleteRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
-        let f = file_like.to_file()?;
+    ) -> Result<FileDeleteRequestBuilder, PCloudError> {
+        let f = file_like
+            .to_file()
+            .map_err(PCloudError::InvalidDescriptor)?;
 
         if !f.is_empty() {
             Ok(FileDeleteRequestBuilder {
@@ -1129,17 +2065,24 @@ impl FileDeleteRequestBuilder {
                 client: client.clone(),
             })
         } else {
-            Err(pcloud_model::PCloudResult::NoFileIdOrPathProvided)?
+            Err(PCloudError::InvalidDescriptor(
+                PCloudResult::NoFileIdOrPathProvided,
+            ))?
         }
     }
 
-    pub async fn execute(
-        self,
-    ) -> Result<pcloud_model::FileOrFolderStat, Box<dyn std::error::Error + Send + Sync>> {
+    pub async fn execute(self) -> Result<pcloud_model::FileOrFolderStat, PCloudError> {
+        let file = PCloudFile {
+            file_id: self.file_id,
+            path: self.path.clone(),
+            revision: None,
+        };
+        self.client.guard_file(&file, "delete").await?;
+
         let mut r = self
             .client
-            .client
-            .get(format!("{}/deletefile", self.client.api_host));
+            .client()
+            .get(format!("{}/deletefile", self.client.api_host()));
 
         if let Some(id) = self.file_id {
             debug!("Requesting delete for file {}", id);
@@ -1153,12 +2096,10 @@ impl FileDeleteRequestBuilder {
 
         r = self.client.add_token(r);
 
-        let diff = r
-            .send()
-            .await?
-            .json::<pcloud_model::FileOrFolderStat>()
-            .await?
-            .assert_ok()?;
+        let diff = self
+            .client
+            .send_checked::<pcloud_model::FileOrFolderStat>(r)
+            .await?;
         Ok(diff)
     }
 }
@@ -1172,15 +2113,19 @@ pub struct FileDownloadRequestBuilder {
     path: Option<String>,
     /// File revision to fetch
     revision_id: Option<u64>,
+    /// Verify the checksums of the downloaded content
+    verify_checksum: bool,
 }
 
 #[allow(dead_code)]
 impl FileDownloadRequestBuilder {
-    pub(crate) fn for_file<'a, T: FileDescriptor>(
+    pub(crate) fn for_file<T: FileDescriptor>(
         client: &PCloudClient,
         file_like: T,
-    ) -> Result<FileDownloadRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
-        let f = file_like.to_file()?;
+    ) -> Result<FileDownloadRequestBuilder, PCloudError> {
+        let f = file_like
+            .to_file()
+            .map_err(PCloudError::InvalidDescriptor)?;
 
         if !f.is_empty() {
             Ok(FileDownloadRequestBuilder {
@@ -1188,9 +2133,12 @@ impl FileDownloadRequestBuilder {
                 path: f.path,
                 client: client.clone(),
                 revision_id: f.revision,
+                verify_checksum: false,
             })
         } else {
-            Err(pcloud_model::PCloudResult::NoFileIdOrPathProvided)?
+            Err(PCloudError::InvalidDescriptor(
+                PCloudResult::NoFileIdOrPathProvided,
+            ))?
         }
     }
 
@@ -1200,14 +2148,30 @@ impl FileDownloadRequestBuilder {
         self
     }
 
+    /// If set, `save_to()` hashes the content on the fly (SHA-1 and, depending on the region, MD5 or SHA-256) and compares it with the checksums of `checksumfile` for the same revision. Fails with `ChecksumMismatch` on corruption.
+    pub fn verify_checksum(mut self, value: bool) -> FileDownloadRequestBuilder {
+        self.verify_checksum = value;
+        self
+    }
+
+    /// Downloads the file to the given local path. The data is written to a temporary `.part` file next to the target, which is only renamed to the target once complete (and verified, if requested).
+    pub async fn save_to<P: AsRef<Path>>(self, path: P) -> Result<FileInfo, PCloudError> {
+        let file = PCloudFile {
+            file_id: self.file_id,
+            path: self.path,
+            revision: self.revision_id,
+        };
+        self.client
+            .download_to_path(file, path.as_ref(), self.verify_checksum, None)
+            .await
+    }
+
     /// Fetch the download link for the file
-    pub async fn get(
-        self,
-    ) -> Result<pcloud_model::DownloadLink, Box<dyn std::error::Error + Send + Sync>> {
+    pub async fn get(self) -> Result<pcloud_model::DownloadLink, PCloudError> {
         let mut r = self
             .client
-            .client
-            .get(format!("{}/getfilelink", self.client.api_host));
+            .client()
+            .get(format!("{}/getfilelink", self.client.api_host()));
 
         if let Some(id) = self.file_id {
             debug!("Requesting download for file {}", id);
@@ -1225,16 +2189,100 @@ impl FileDownloadRequestBuilder {
 
         r = self.client.add_token(r);
 
-        let diff = r
-            .send()
-            .await?
-            .json::<pcloud_model::DownloadLink>()
-            .await?
-            .assert_ok()?;
+        let diff = self
+            .client
+            .send_checked::<pcloud_model::DownloadLink>(r)
+            .await?;
         Ok(diff)
     }
 }
 
+/// Default bitrate of transcoded audio streams in kilobits
+pub const DEFAULT_AUDIO_BITRATE: u32 = 192;
+
+pub struct AudioLinkRequestBuilder {
+    /// Client to actually perform the request
+    client: PCloudClient,
+    ///  ID of the  file
+    file_id: Option<u64>,
+    /// Path to the  file
+    path: Option<String>,
+    /// Bitrate of the audio stream in kilobits
+    bitrate: u32,
+    /// if set, the content-type of the stream is application/octet-stream
+    force_download: bool,
+}
+
+#[allow(dead_code)]
+impl AudioLinkRequestBuilder {
+    pub(crate) fn for_file<T: FileDescriptor>(
+        client: &PCloudClient,
+        file_like: T,
+    ) -> Result<AudioLinkRequestBuilder, PCloudError> {
+        let f = file_like
+            .to_file()
+            .map_err(PCloudError::InvalidDescriptor)?;
+
+        if !f.is_empty() {
+            Ok(AudioLinkRequestBuilder {
+                file_id: f.file_id,
+                path: f.path,
+                client: client.clone(),
+                bitrate: DEFAULT_AUDIO_BITRATE,
+                force_download: false,
+            })
+        } else {
+            Err(PCloudError::InvalidDescriptor(
+                PCloudResult::NoFileIdOrPathProvided,
+            ))?
+        }
+    }
+
+    /// Bitrate of the transcoded audio in kilobits (16 to 320, default 192)
+    pub fn bitrate(mut self, value: u32) -> AudioLinkRequestBuilder {
+        self.bitrate = value.clamp(16, 320);
+        self
+    }
+
+    /// if set, the stream is served as application/octet-stream, so browsers download instead of playing it
+    pub fn force_download(mut self, value: bool) -> AudioLinkRequestBuilder {
+        self.force_download = value;
+        self
+    }
+
+    /// Fetch the link to stream the transcoded audio (MP3) from
+    pub async fn get(self) -> Result<pcloud_model::DownloadLink, PCloudError> {
+        let mut r = self
+            .client
+            .client()
+            .get(format!("{}/getaudiolink", self.client.api_host()));
+
+        if let Some(id) = self.file_id {
+            debug!("Requesting audio link for file {}", id);
+            r = r.query(&[("fileid", id)]);
+        }
+
+        if let Some(p) = self.path {
+            debug!("Requesting audio link for file {}", p);
+            r = r.query(&[("path", p)]);
+        }
+
+        r = r.query(&[("abitrate", self.bitrate)]);
+
+        if self.force_download {
+            r = r.query(&[("forcedownload", "1")]);
+        }
+
+        r = self.client.add_token(r);
+
+        let link = self
+            .client
+            .send_checked::<pcloud_model::DownloadLink>(r)
+            .await?;
+        Ok(link)
+    }
+}
+
 pub struct FileStatRequestBuilder {
     /// Client to actually perform the request
     client: PCloudClient,
@@ -1248,11 +2296,13 @@ pub struct FileStatRequestBuilder {
 
 #[allow(dead_code)]
 impl FileStatRequestBuilder {
-    pub(crate) fn for_file<'a, T: FileDescriptor>(
+    pub(crate) fn for_file<T: FileDescriptor>(
         client: &PCloudClient,
         file_like: T,
-    ) -> Result<FileStatRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
-        let f = file_like.to_file()?;
+    ) -> Result<FileStatRequestBuilder, PCloudError> {
+        let f = file_like
+            .to_file()
+            .map_err(PCloudError::InvalidDescriptor)?;
 
         if !f.is_empty() {
             Ok(FileStatRequestBuilder {
@@ -1262,7 +2312,9 @@ impl FileStatRequestBuilder {
                 revision_id: f.revision,
             })
         } else {
-            Err(pcloud_model::PCloudResult::NoFileIdOrPathProvided)?
+            Err(PCloudError::InvalidDescriptor(
+                PCloudResult::NoFileIdOrPathProvided,
+            ))?
         }
     }
 
@@ -1273,13 +2325,11 @@ impl FileStatRequestBuilder {
     }
 
     /// Fetch the file metadata
-    pub async fn get(
-        self,
-    ) -> Result<pcloud_model::FileOrFolderStat, Box<dyn std::error::Error + Send + Sync>> {
+    pub async fn get(self) -> Result<pcloud_model::FileOrFolderStat, PCloudError> {
         let mut r = self
             .client
-            .client
-            .get(format!("{}/stat", self.client.api_host));
+            .client()
+            .get(format!("{}/stat", self.client.api_host()));
 
         if let Some(id) = self.file_id {
             debug!("Requesting file metadata for file {}", id);
@@ -1297,12 +2347,10 @@ impl FileStatRequestBuilder {
 
         r = self.client.add_token(r);
 
-        let diff = r
-            .send()
-            .await?
-            .json::<pcloud_model::FileOrFolderStat>()
-            .await?
-            .assert_ok()?;
+        let diff = self
+            .client
+            .send_checked::<pcloud_model::FileOrFolderStat>(r)
+            .await?;
         Ok(diff)
     }
 }
@@ -1313,26 +2361,18 @@ impl PCloudClient {
     pub async fn download_link(
         &self,
         link: &pcloud_model::DownloadLink,
-    ) -> Result<Response, Box<dyn std::error::Error + Send + Sync>> {
-        if let Some(url) = link.into_url() {
-            debug!("Downloading file link {}", url);
-
-            // No authentication necessary!
-            // r = self.add_token(r);
-            let resp = self.client.get(url).send().await?;
-
-            Ok(resp)
-        } else {
-            Err(PCloudResult::ProvideURL)?
-        }
+    ) -> Result<Response, PCloudError> {
+        self.send_download(link, |r| r).await
     }
 
     /// Returns the file id (and the revision if given) of a PCloudFile. If the file_id is given, just return it. If a path is given, fetch the metadata with the file id.
     pub(crate) async fn get_file_id<T: FileDescriptor>(
         &self,
         file_like: T,
-    ) -> Result<(u64, Option<u64>), Box<dyn std::error::Error + Send + Sync>> {
-        let file = file_like.to_file()?;
+    ) -> Result<(u64, Option<u64>), PCloudError> {
+        let file = file_like
+            .to_file()
+            .map_err(PCloudError::InvalidDescriptor)?;
         let rev = file.revision;
 
         if let Some(file_id) = file.file_id {
@@ -1341,87 +2381,340 @@ impl PCloudClient {
             let metadata = self.get_file_metadata(file).await?.metadata.unwrap();
 
             if metadata.isfolder {
-                Err(PCloudResult::NoFileIdOrPathProvided)?
+                Err(PCloudError::InvalidDescriptor(
+                    PCloudResult::NoFileIdOrPathProvided,
+                ))?
             }
 
             if let Some(file_id) = metadata.fileid {
                 Ok((file_id, rev))
             } else {
-                Err(PCloudResult::NoFileIdOrPathProvided)?
+                Err(PCloudError::InvalidDescriptor(
+                    PCloudResult::NoFileIdOrPathProvided,
+                ))?
             }
         }
     }
 
     /// Fetches the download link for the latest file revision and directly downloads the file.  Accepts either a file id (u64), a file path (String) or any other pCloud object describing a file (like Metadata)
-    pub async fn download_file<'a, T: FileDescriptor>(
+    pub async fn download_file<T: FileDescriptor>(
         &self,
         file_like: T,
-    ) -> Result<Response, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<Response, PCloudError> {
         let link = self.get_download_link_for_file(file_like)?.get().await?;
         self.download_link(&link).await
     }
 
+    /// Fetches the download link for the given revision of a file and directly downloads it. Accepts either a file id (u64), a file path (String) or any other pCloud object describing a file (like Metadata)
+    pub async fn download_revision<T: FileDescriptor>(
+        &self,
+        file_like: T,
+        revision: &pcloud_model::FileRevision,
+    ) -> Result<Response, PCloudError> {
+        let mut file = file_like
+            .to_file()
+            .map_err(PCloudError::InvalidDescriptor)?;
+        file.revision = Some(revision.revisionid);
+        self.download_file(file).await
+    }
+
+    /// Resolves the download link of the latest file revision (or the revision given) and returns information about the file (including content length and checksums) together with the body as stream of chunks of the configured transfer chunk size (in pooled buffers). Fails if the download is not successful. Accepts either a file id (u64), a file path (String) or any other pCloud object describing a file (like Metadata)
+    pub async fn download_stream<T: FileDescriptor>(
+        &self,
+        file_like: T,
+    ) -> Result<(FileInfo, impl Stream<Item = Result<Bytes, PCloudError>>), PCloudError> {
+        let file = file_like
+            .to_file()
+            .map_err(PCloudError::InvalidDescriptor)?;
+        self.open_download(file, true).await
+    }
+
+    /// Fetches the checksums of the latest file revision (or the revision given) and the download link of exactly the file they were computed for. The link is requested by the file id of the checksummed metadata, so a file replaced at the same path in between isn't downloaded instead.
+    pub(crate) async fn checksummed_link(
+        &self,
+        file: &PCloudFile,
+    ) -> Result<(FileChecksums, pcloud_model::DownloadLink), PCloudError> {
+        let checksums = self.checksum_file(file)?.get().await?;
+        let pinned = match checksums.metadata.as_ref().and_then(|m| m.fileid) {
+            Some(file_id) => PCloudFile {
+                file_id: Some(file_id),
+                path: None,
+                revision: file.revision,
+            },
+            None => file.clone(),
+        };
+        let link = self.get_download_link_for_file(pinned)?.get().await?;
+        Ok((checksums, link))
+    }
+
+    /// Starts the download of the file. The checksums are only fetched (along with a link pinned to the checksummed file) if the download is verified.
+    async fn open_download(
+        &self,
+        file: PCloudFile,
+        verify: bool,
+    ) -> Result<(FileInfo, impl Stream<Item = Result<Bytes, PCloudError>>), PCloudError> {
+        let (checksums, link) = match verify {
+            true => {
+                let (checksums, link) = self.checksummed_link(&file).await?;
+                (Some(checksums), link)
+            }
+            false => (None, self.get_download_link_for_file(file)?.get().await?),
+        };
+        let response = self.download_link(&link).await?.error_for_status()?;
+
+        let checksums = checksums.unwrap_or(FileChecksums {
+            result: PCloudResult::Ok,
+            metadata: None,
+            sha1: None,
+            md5: None,
+            sha256: None,
+        });
+        let info = FileInfo {
+            content_length: response
+                .content_length()
+                .or_else(|| checksums.metadata.as_ref().and_then(|m| m.size)),
+            content_type: response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string()),
+            metadata: checksums.metadata,
+            sha1: checksums.sha1,
+            md5: checksums.md5,
+            sha256: checksums.sha256,
+        };
+
+        let stream = response.bytes_stream().map_err(PCloudError::Http);
+        Ok((info, self.buffer_pool().rechunk(stream)))
+    }
+
+    /// Downloads the file to the given local path through a temporary `.part` file, verifying the content on the fly against the checksums of `checksumfile` if requested. Shared by all downloads into local files.
+    pub(crate) async fn download_to_path(
+        &self,
+        file: PCloudFile,
+        path: &Path,
+        verify: bool,
+        progress: Option<(Arc<dyn ProgressObserver>, String)>,
+    ) -> Result<FileInfo, PCloudError> {
+        let (info, stream) = self.open_download(file, verify).await?;
+        let stream = match progress {
+            Some((observer, item)) => Either::Left(progress::observe(stream, observer, item)),
+            None => Either::Right(stream),
+        };
+
+        let written = &info;
+        save_download(path, |partial| async move {
+            write_and_verify(written, stream, &partial, verify).await
+        })
+        .await?;
+        Ok(info)
+    }
+
+    /// Reads `len` bytes of the latest file revision (or the revision given) starting at `offset` using a HTTP range request, e.g. to read file headers or sparse sections of huge files without fetching them entirely.
+    /// Returns less bytes if the range exceeds the end of the file (none if the offset is beyond the end). Accepts either a file id (u64), a file path (String) or any other pCloud object describing a file (like Metadata)
+    pub async fn read_range<T: FileDescriptor>(
+        &self,
+        file_like: T,
+        offset: u64,
+        len: u64,
+    ) -> Result<Bytes, PCloudError> {
+        if len == 0 {
+            return Ok(Bytes::new());
+        }
+
+        let link = self.get_download_link_for_file(file_like)?.get().await?;
+        // A length exceeding the largest offset reads to the end of the file
+        let range = format!("bytes={}-{}", offset, offset.saturating_add(len - 1));
+        debug!("Reading range {} of {:?}", range, link.path);
+        let response = self
+            .send_download(&link, |r| r.header(reqwest::header::RANGE, &range))
+            .await?;
+
+        match response.status() {
+            StatusCode::PARTIAL_CONTENT => {
+                let mut content = response.bytes().await?;
+                content.truncate(usize::try_from(len).unwrap_or(usize::MAX));
+                Ok(content)
+            }
+            StatusCode::RANGE_NOT_SATISFIABLE => Ok(Bytes::new()),
+            _ => {
+                // Range not supported, skip everything before the range and stop reading after it
+                let mut stream = response.error_for_status()?.bytes_stream();
+                let mut skip = offset;
+                let mut content = BytesMut::new();
+                while let Some(chunk) = stream.next().await {
+                    let mut chunk = chunk?;
+                    if skip >= chunk.len() as u64 {
+                        skip -= chunk.len() as u64;
+                        continue;
+                    }
+                    let _ = chunk.split_to(skip as usize);
+                    skip = 0;
+                    content.extend_from_slice(&chunk);
+                    if content.len() as u64 >= len {
+                        break;
+                    }
+                }
+                content.truncate(usize::try_from(len).unwrap_or(usize::MAX));
+                Ok(content.freeze())
+            }
+        }
+    }
+
+    /// Downloads the latest revision of a file (or the revision given) to the given local path. SHA-1 (and SHA-256, if provided by pCloud) checksums are computed while streaming and compared with the ones of `checksumfile`.
+    /// The data is written to a temporary `.part` file next to the target, which is only renamed to the target if the checksums match. Returns `ChecksumMismatch` on corruption. Accepts either a file id (u64), a file path (String) or any other pCloud object describing a file (like Metadata)
+    pub async fn download_and_verify<T: FileDescriptor, P: AsRef<Path>>(
+        &self,
+        file_like: T,
+        path: P,
+    ) -> Result<FileInfo, PCloudError> {
+        self.download_and_verify_observed(file_like, path, None)
+            .await
+    }
+
+    /// Downloads and verifies the file like `download_and_verify()`, reporting the bytes received to the observer of the item
+    pub(crate) async fn download_and_verify_observed<T: FileDescriptor, P: AsRef<Path>>(
+        &self,
+        file_like: T,
+        path: P,
+        progress: Option<(Arc<dyn ProgressObserver>, String)>,
+    ) -> Result<FileInfo, PCloudError> {
+        let file = file_like
+            .to_file()
+            .map_err(PCloudError::InvalidDescriptor)?;
+        self.download_to_path(file, path.as_ref(), true, progress)
+            .await
+    }
+
+    /// Streams the latest revision of a file (or the revision given) to the given local path chunk by chunk, without buffering the whole file in memory. The download is written through a temporary `.part` file like with `download_and_verify()`, but not verified (use `download_and_verify()` for that).
+    /// If `keep_mtime` is set, the modification time of the local file is set to the one of the remote file. Accepts either a file id (u64), a file path (String) or any other pCloud object describing a file (like Metadata)
+    pub async fn download_file_to_path<T: FileDescriptor, P: AsRef<Path>>(
+        &self,
+        file_like: T,
+        local_path: P,
+        keep_mtime: bool,
+    ) -> Result<FileInfo, PCloudError> {
+        let path = local_path.as_ref();
+        let file = file_like
+            .to_file()
+            .map_err(PCloudError::InvalidDescriptor)?;
+        let mut info = self
+            .download_to_path(file.clone(), path, false, None)
+            .await?;
+
+        if keep_mtime {
+            info.metadata = self.get_file_metadata(file).await?.metadata;
+        }
+        if let Some(modified) = info.metadata.as_ref().map(|m| m.modified) {
+            let path = path.to_path_buf();
+            tokio::task::spawn_blocking(move || {
+                std::fs::OpenOptions::new()
+                    .write(true)
+                    .open(path)?
+                    .set_modified(modified.into())
+            })
+            .await
+            .map_err(std::io::Error::other)??;
+        }
+        Ok(info)
+    }
+
     /// Copies the given file to the given folder. Either set a target folder id and then the target with with_new_name or give a full new file path as target path
-    pub fn copy_file<'a, S: FileDescriptor, T: FolderDescriptor>(
+    pub fn copy_file<S: FileDescriptor, T: FolderDescriptor>(
         &self,
         file_like: S,
         target_folder_like: T,
-    ) -> Result<CopyFileRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<CopyFileRequestBuilder, PCloudError> {
         CopyFileRequestBuilder::copy_file(self, file_like, target_folder_like)
     }
 
+    /// Sets the modification time (and optionally the creation time, otherwise the current one is kept) of a file, e.g. for files uploaded without them.
+    /// pCloud has no method to change timestamps, so the file is copied with the given times onto itself. The file keeps its file id, the content before becomes a revision. Accepts either a file id (u64), a file path (String) or any other pCloud object describing a file (like Metadata)
+    pub async fn set_file_times<T: FileDescriptor, Tz>(
+        &self,
+        file_like: T,
+        mtime: &DateTime<Tz>,
+        ctime: Option<&DateTime<Tz>>,
+    ) -> Result<FileOrFolderStat, PCloudError>
+    where
+        Tz: TimeZone,
+        Tz::Offset: Display,
+    {
+        let metadata = self
+            .get_file_metadata(file_like)
+            .await?
+            .metadata
+            .ok_or(PCloudResult::FileNotFound)?;
+        let file_id = metadata.fileid.ok_or(PCloudResult::FileNotFound)?;
+        let parent_folder_id = metadata
+            .parentfolderid
+            .ok_or(PCloudResult::DirectoryDoesNotExist)?;
+
+        debug!("Setting timestamps of file {}", file_id);
+        let copy = self
+            .copy_file(file_id, parent_folder_id)?
+            .with_new_name(&metadata.name)
+            .overwrite(true)
+            .mtime(mtime);
+        let copy = match ctime {
+            Some(ctime) => copy.ctime(ctime),
+            None => copy.ctime(&metadata.created),
+        };
+        copy.execute().await
+    }
+
     /// Moves the given file to the given folder. Either set a target folder id and then the target with with_new_name or give a full new file path as target path
-    pub fn move_file<'a, S: FileDescriptor, T: FolderDescriptor>(
+    pub fn move_file<S: FileDescriptor, T: FolderDescriptor>(
         &self,
         file_like: S,
         target_folder_like: T,
-    ) -> Result<MoveFileRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<MoveFileRequestBuilder, PCloudError> {
         MoveFileRequestBuilder::move_file(self, file_like, target_folder_like)
     }
 
     /// Lists revisions for a given fileid / path
-    pub async fn list_file_revisions<'a, S: FileDescriptor>(
+    pub async fn list_file_revisions<S: FileDescriptor>(
         &self,
         file_like: S,
-    ) -> Result<RevisionList, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<RevisionList, PCloudError> {
         ListRevisionsRequestBuilder::for_file(self, file_like)?
             .get()
             .await
     }
 
     /// Returns the metadata of a file. Accepts either a file id (u64), a file path (String) or any other pCloud object describing a file (like Metadata)
-    pub async fn get_file_metadata<'a, T: FileDescriptor>(
+    pub async fn get_file_metadata<T: FileDescriptor>(
         &self,
         file_like: T,
-    ) -> Result<FileOrFolderStat, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<FileOrFolderStat, PCloudError> {
         FileStatRequestBuilder::for_file(self, file_like)?
             .get()
             .await
     }
 
     /// Requests deleting a file. Accepts either a file id (u64), a file path (String) or any other pCloud object describing a file (like Metadata)
-    pub async fn delete_file<'a, T: FileDescriptor>(
+    pub async fn delete_file<T: FileDescriptor>(
         &self,
         file_like: T,
-    ) -> Result<FileOrFolderStat, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<FileOrFolderStat, PCloudError> {
         FileDeleteRequestBuilder::for_file(self, file_like)?
             .execute()
             .await
     }
 
     /// Requests the checksums of a file. Accepts either a file id (u64), a file path (String) or any other pCloud object describing a file (like Metadata)
-    pub fn checksum_file<'a, T: FileDescriptor>(
+    pub fn checksum_file<T: FileDescriptor>(
         &self,
         file_like: T,
-    ) -> Result<ChecksumFileRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<ChecksumFileRequestBuilder, PCloudError> {
         ChecksumFileRequestBuilder::for_file(self, file_like)
     }
 
     /// Returns the public link for a pCloud file. Accepts either a file id (u64), a file path (String) or any other pCloud object describing a file (like Metadata)
-    pub fn get_public_link_for_file<'a, T: FileDescriptor>(
+    pub fn get_public_link_for_file<T: FileDescriptor>(
         &self,
         file_like: T,
-    ) -> Result<PublicFileLinkRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<PublicFileLinkRequestBuilder, PCloudError> {
         PublicFileLinkRequestBuilder::for_file(&self, file_like)
     }
 
@@ -1429,25 +2722,33 @@ impl PCloudClient {
     pub async fn get_public_download_link_for_file(
         &self,
         link: &pcloud_model::PublicFileLink,
-    ) -> Result<pcloud_model::DownloadLink, Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<pcloud_model::DownloadLink, PCloudError> {
         PublicFileDownloadRequestBuilder::for_public_file(self, link.code.clone().unwrap().as_str())
             .get()
             .await
     }
 
     /// Returns the download link for a file. Accepts either a file id (u64), a file path (String) or any other pCloud object describing a file (like Metadata)
-    pub fn get_download_link_for_file<'a, T: FileDescriptor>(
+    pub fn get_download_link_for_file<T: FileDescriptor>(
         &self,
         file_like: T,
-    ) -> Result<FileDownloadRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<FileDownloadRequestBuilder, PCloudError> {
         FileDownloadRequestBuilder::for_file(self, file_like)
     }
 
+    /// Returns the link to stream the audio of an audio or video file transcoded to MP3. Accepts either a file id (u64), a file path (String) or any other pCloud object describing a file (like Metadata)
+    pub fn get_audio_link_for_file<T: FileDescriptor>(
+        &self,
+        file_like: T,
+    ) -> Result<AudioLinkRequestBuilder, PCloudError> {
+        AudioLinkRequestBuilder::for_file(self, file_like)
+    }
+
     /// Uploads files into a folder. Accepts either a folder id (u64), a folder path (String) or any other pCloud object describing a folder (like Metadata)
-    pub fn upload_file_into_folder<'a, T: FolderDescriptor>(
+    pub fn upload_file_into_folder<T: FolderDescriptor>(
         &self,
         folder_like: T,
-    ) -> Result<UploadRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<UploadRequestBuilder, PCloudError> {
         UploadRequestBuilder::into_folder(self, folder_like)
     }
 
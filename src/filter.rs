@@ -0,0 +1,341 @@
+use crate::error::PCloudError;
+use std::{fmt::Display, path::Path};
+
+/// Name of the ignore file, read from the local directory if no other filter is given
+pub const DEFAULT_IGNORE_FILE: &str = ".pcloudignore";
+
+/// Error of an ignore file line that is no valid rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidFilterRule {
+    /// Line number (starting with 1)
+    pub line: usize,
+    /// Content of the line
+    pub rule: String,
+}
+
+impl Display for InvalidFilterRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Invalid filter rule in line {}: {}",
+            self.line, self.rule
+        )
+    }
+}
+
+impl std::error::Error for InvalidFilterRule {}
+
+/// Single gitignore-style pattern
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    /// Glob pattern, without leading '!' and trailing '/'
+    pattern: Vec<char>,
+    /// Re-includes matching paths ('!')
+    negated: bool,
+    /// Matches folders only (trailing '/')
+    folders_only: bool,
+    /// Matches the full relative path instead of the name (pattern contains '/')
+    anchored: bool,
+}
+
+impl Rule {
+    fn parse(line: &str) -> Rule {
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let (folders_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        Rule {
+            pattern: line.trim_start_matches('/').chars().collect(),
+            negated,
+            folders_only,
+            anchored: line.contains('/'),
+        }
+    }
+
+    fn matches(&self, path: &str, is_folder: bool) -> bool {
+        if self.folders_only && !is_folder {
+            return false;
+        }
+        let text = if self.anchored {
+            path
+        } else {
+            path.rsplit('/').next().unwrap_or(path)
+        };
+        glob_match(&self.pattern, &text.chars().collect::<Vec<_>>())
+    }
+}
+
+/// Include and exclude rules for the files of a transfer (sync, mirror, directory download or watch), as known from `.gitignore` files and extended by size and extension limits. Paths are relative to the synced folder and separated by '/'.
+///
+/// An ignore file contains one gitignore-style pattern per line (`*.tmp`, `/build/`, `**/cache`, `!keep.tmp`), lines starting with '#' are comments. The directives `@min-size <size>`, `@max-size <size>` (in bytes or with the suffix K, M or G), `@extensions <ext> ...` (only files with these extensions) and `@exclude-extensions <ext> ...` add the other limits.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SyncFilter {
+    rules: Vec<Rule>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    extensions: Vec<String>,
+    excluded_extensions: Vec<String>,
+}
+
+impl SyncFilter {
+    /// Creates a filter excluding nothing
+    pub fn new() -> SyncFilter {
+        SyncFilter::default()
+    }
+
+    /// Parses the content of an ignore file
+    pub fn parse(content: &str) -> Result<SyncFilter, InvalidFilterRule> {
+        let mut filter = SyncFilter::new();
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || InvalidFilterRule {
+                line: index + 1,
+                rule: line.to_string(),
+            };
+            match line.strip_prefix('@') {
+                Some(directive) => {
+                    let (name, value) = directive.split_once(' ').ok_or_else(invalid)?;
+                    let value = value.trim();
+                    filter = match name {
+                        "min-size" => filter.min_size(parse_size(value).ok_or_else(invalid)?),
+                        "max-size" => filter.max_size(parse_size(value).ok_or_else(invalid)?),
+                        "extensions" => filter.extensions(value.split_whitespace()),
+                        "exclude-extensions" => filter.exclude_extensions(value.split_whitespace()),
+                        _ => Err(invalid())?,
+                    };
+                }
+                None if line == "!" || line == "/" => Err(invalid())?,
+                None => filter.rules.push(Rule::parse(line)),
+            }
+        }
+        Ok(filter)
+    }
+
+    /// Loads the given ignore file
+    pub async fn load<P: AsRef<Path>>(path: P) -> Result<SyncFilter, PCloudError> {
+        let content = tokio::fs::read_to_string(path).await?;
+        Ok(SyncFilter::parse(&content)?)
+    }
+
+    /// Loads the ignore file `.pcloudignore` of the local directory, a filter excluding nothing if there is none
+    pub async fn for_directory<P: AsRef<Path>>(directory: P) -> Result<SyncFilter, PCloudError> {
+        match tokio::fs::read_to_string(directory.as_ref().join(DEFAULT_IGNORE_FILE)).await {
+            Ok(content) => Ok(SyncFilter::parse(&content)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(SyncFilter::new()),
+            Err(e) => Err(e)?,
+        }
+    }
+
+    /// Excludes all files and folders matching the gitignore-style pattern
+    pub fn exclude(mut self, pattern: &str) -> SyncFilter {
+        self.rules.push(Rule::parse(pattern));
+        self
+    }
+
+    /// Includes the files and folders matching the gitignore-style pattern again, even if an earlier pattern excluded them. Files of an excluded folder can't be included again.
+    pub fn include(mut self, pattern: &str) -> SyncFilter {
+        let mut rule = Rule::parse(pattern);
+        rule.negated = true;
+        self.rules.push(rule);
+        self
+    }
+
+    /// Excludes files smaller than the given size in bytes
+    pub fn min_size(mut self, value: u64) -> SyncFilter {
+        self.min_size = Some(value);
+        self
+    }
+
+    /// Excludes files larger than the given size in bytes
+    pub fn max_size(mut self, value: u64) -> SyncFilter {
+        self.max_size = Some(value);
+        self
+    }
+
+    /// Excludes all files not having one of the given extensions (case insensitive, without '.')
+    pub fn extensions<I: IntoIterator<Item = S>, S: AsRef<str>>(mut self, values: I) -> SyncFilter {
+        self.extensions
+            .extend(values.into_iter().map(|e| normalize_extension(e.as_ref())));
+        self
+    }
+
+    /// Excludes all files having one of the given extensions (case insensitive, without '.')
+    pub fn exclude_extensions<I: IntoIterator<Item = S>, S: AsRef<str>>(
+        mut self,
+        values: I,
+    ) -> SyncFilter {
+        self.excluded_extensions
+            .extend(values.into_iter().map(|e| normalize_extension(e.as_ref())));
+        self
+    }
+
+    /// Checks if the filter excludes nothing
+    pub fn is_empty(&self) -> bool {
+        self == &SyncFilter::default()
+    }
+
+    /// Checks if the folder (and therefore all its content) is excluded
+    pub fn is_folder_excluded(&self, path: &str) -> bool {
+        let mut prefix = String::new();
+        for name in path.split('/').filter(|n| !n.is_empty()) {
+            if !prefix.is_empty() {
+                prefix.push('/');
+            }
+            prefix.push_str(name);
+            if self.matches_rules(&prefix, true) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Checks if the file is excluded, by its path, its extension or its size (if known)
+    pub fn is_excluded(&self, path: &str, size: Option<u64>) -> bool {
+        if let Some((parent, _)) = path.rsplit_once('/') {
+            if self.is_folder_excluded(parent) {
+                return true;
+            }
+        }
+        if self.matches_rules(path, false) {
+            return true;
+        }
+
+        let name = path.rsplit('/').next().unwrap_or(path);
+        let extension = name
+            .rsplit_once('.')
+            .map(|(_, e)| e.to_lowercase())
+            .unwrap_or_default();
+        if !self.extensions.is_empty() && !self.extensions.contains(&extension) {
+            return true;
+        }
+        if self.excluded_extensions.contains(&extension) {
+            return true;
+        }
+
+        match size {
+            Some(size) => {
+                self.min_size.map(|min| size < min).unwrap_or(false)
+                    || self.max_size.map(|max| size > max).unwrap_or(false)
+            }
+            None => false,
+        }
+    }
+
+    /// Applies the patterns to a single path, the last matching pattern wins
+    fn matches_rules(&self, path: &str, is_folder: bool) -> bool {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(path, is_folder))
+            .map(|rule| !rule.negated)
+            .unwrap_or(false)
+    }
+}
+
+fn normalize_extension(value: &str) -> String {
+    value.trim_start_matches('.').to_lowercase()
+}
+
+/// Parses a size in bytes, optionally with the suffix K, M or G (powers of 1024)
+fn parse_size(value: &str) -> Option<u64> {
+    let value = value.trim().to_uppercase();
+    let value = value.strip_suffix('B').unwrap_or(&value);
+    let (number, factor) = match value.chars().last()? {
+        'K' => (&value[..value.len() - 1], 1024),
+        'M' => (&value[..value.len() - 1], 1024 * 1024),
+        'G' => (&value[..value.len() - 1], 1024 * 1024 * 1024),
+        _ => (value, 1),
+    };
+    number.trim().parse::<u64>().ok()?.checked_mul(factor)
+}
+
+/// Matches a text against a glob pattern: '*' and '?' don't match '/', '**' matches across folders and '[...]' matches character classes
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') if pattern.get(1) == Some(&'*') => {
+            let rest = &pattern[2..];
+            match rest.first() {
+                // "**/" matches zero or more folders
+                Some('/') => {
+                    let rest = &rest[1..];
+                    glob_match(rest, text)
+                        || text
+                            .iter()
+                            .enumerate()
+                            .any(|(i, c)| *c == '/' && glob_match(rest, &text[i + 1..]))
+                }
+                _ => (0..=text.len()).any(|i| glob_match(rest, &text[i..])),
+            }
+        }
+        Some('*') => {
+            for i in 0..=text.len() {
+                if glob_match(&pattern[1..], &text[i..]) {
+                    return true;
+                }
+                if text.get(i) == Some(&'/') {
+                    return false;
+                }
+            }
+            false
+        }
+        Some('?') => match text.first() {
+            Some(c) if *c != '/' => glob_match(&pattern[1..], &text[1..]),
+            _ => false,
+        },
+        Some('[') => match class_end(pattern) {
+            Some(end) => match text.first() {
+                Some(c) if *c != '/' && class_matches(&pattern[1..end], *c) => {
+                    glob_match(&pattern[end + 1..], &text[1..])
+                }
+                _ => false,
+            },
+            None => text.first() == Some(&'[') && glob_match(&pattern[1..], &text[1..]),
+        },
+        Some('\\') if pattern.len() > 1 => {
+            text.first() == Some(&pattern[1]) && glob_match(&pattern[2..], &text[1..])
+        }
+        Some(c) => text.first() == Some(c) && glob_match(&pattern[1..], &text[1..]),
+    }
+}
+
+/// Index of the ']' closing the character class starting at the beginning of the pattern
+fn class_end(pattern: &[char]) -> Option<usize> {
+    let mut start = 1;
+    if pattern.get(start) == Some(&'!') {
+        start += 1;
+    }
+    // A ']' right at the start is part of the class
+    pattern
+        .iter()
+        .skip(start + 1)
+        .position(|c| *c == ']')
+        .map(|i| i + start + 1)
+}
+
+/// Checks a character against the content of a class like "a-z0-9" or "!abc"
+fn class_matches(class: &[char], c: char) -> bool {
+    let (negated, class) = match class.first() {
+        Some('!') => (true, &class[1..]),
+        _ => (false, class),
+    };
+    let mut matched = false;
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            matched |= class[i] <= c && c <= class[i + 2];
+            i += 3;
+        } else {
+            matched |= class[i] == c;
+            i += 1;
+        }
+    }
+    matched != negated
+}
@@ -1,8 +1,10 @@
-use std::fmt::Display;
+use std::{fmt::Display, sync::Arc, time::Duration};
 
 use crate::{
+    error::PCloudError,
     pcloud_client::PCloudClient,
-    pcloud_model::{self, FileOrFolderStat, Metadata, PCloudResult, WithPCloudResult},
+    pcloud_model::{self, FileOrFolderStat, Metadata, PCloudResult},
+    permissions::{self, Permissions},
 };
 use log::debug;
 
@@ -189,14 +191,16 @@ pub struct DeleteFolderRequestBuilder {
     path: Option<String>,
     ///  id of the folder
     folder_id: Option<u64>,
+    /// if set, the delete permission on the folder is checked before deleting
+    check_permissions: bool,
 }
 
 #[allow(dead_code)]
 impl DeleteFolderRequestBuilder {
-    pub(crate) fn for_folder<'a, T: FolderDescriptor>(
+    pub(crate) fn for_folder<T: FolderDescriptor>(
         client: &PCloudClient,
         folder_like: T,
-    ) -> Result<DeleteFolderRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<DeleteFolderRequestBuilder, PCloudError> {
         let f = folder_like.to_folder()?;
 
         if !f.is_empty() {
@@ -204,20 +208,47 @@ impl DeleteFolderRequestBuilder {
                 folder_id: f.folder_id,
                 path: f.path,
                 client: client.clone(),
+                check_permissions: false,
             })
         } else {
             Err(pcloud_model::PCloudResult::NoFileIdOrPathProvided)?
         }
     }
 
+    /// If set, the delete permission on a folder shared by another user is checked before deleting. Fails with `PermissionDenied` instead of a server error.
+    pub fn check_permissions(mut self, value: bool) -> DeleteFolderRequestBuilder {
+        self.check_permissions = value;
+        self
+    }
+
+    /// Checks the guard rails of the client and, if requested, the delete permission on the folder
+    async fn verify(&self) -> Result<(), PCloudError> {
+        let folder = PCloudFolder {
+            folder_id: self.folder_id,
+            path: self.path.clone(),
+        };
+        self.client.guard_folder(&folder, "delete").await?;
+
+        if self.check_permissions {
+            permissions::require_folder_permission(
+                &self.client,
+                &folder,
+                Permissions::DELETE,
+                "delete",
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
     /// Deletes the folder and all its content recursively
     pub async fn delete_recursive(
         self,
-    ) -> Result<pcloud_model::FolderRecursivlyDeleted, Box<dyn std::error::Error + Send + Sync>>
-    {
-        let url = format!("{}/deletefolderrecursive", self.client.api_host);
+    ) -> Result<pcloud_model::FolderRecursivlyDeleted, PCloudError> {
+        self.verify().await?;
+        let url = format!("{}/deletefolderrecursive", self.client.api_host());
 
-        let mut r = self.client.client.get(url);
+        let mut r = self.client.client().get(url);
 
         if let Some(p) = self.path {
             debug!("Deleting folder {} recursively", p);
@@ -231,22 +262,61 @@ impl DeleteFolderRequestBuilder {
 
         r = self.client.add_token(r);
 
-        let stat = r
-            .send()
-            .await?
-            .json::<pcloud_model::FolderRecursivlyDeleted>()
-            .await?
-            .assert_ok()?;
+        let stat = self
+            .client
+            .send_checked::<pcloud_model::FolderRecursivlyDeleted>(r)
+            .await?;
         Ok(stat)
     }
 
+    /// Deletes all files and subfolders (recursively) of the folder, but keeps the folder itself. Returns the total number of files and folders deleted.
+    pub async fn delete_contents(
+        self,
+    ) -> Result<pcloud_model::FolderRecursivlyDeleted, PCloudError> {
+        self.verify().await?;
+        let folder = PCloudFolder {
+            folder_id: self.folder_id,
+            path: self.path,
+        };
+        let listing = self.client.list_folder(&folder)?.listing().await?;
+        debug!(
+            "Deleting {} files and {} folders of folder {}",
+            listing.file_count(),
+            listing.folder_count(),
+            folder
+        );
+
+        let mut deleted_files = 0;
+        let mut deleted_folders = 0;
+        for folder_id in listing.folders().iter().filter_map(|m| m.folderid) {
+            let deleted = self
+                .client
+                .delete_folder(folder_id)?
+                .delete_recursive()
+                .await?;
+            deleted_files += deleted.deletedfiles.unwrap_or_default();
+            deleted_folders += deleted.deletedfolders.unwrap_or_default();
+        }
+        for file_id in listing.files().iter().filter_map(|m| m.fileid) {
+            self.client.delete_file(file_id).await?;
+            deleted_files += 1;
+        }
+
+        Ok(pcloud_model::FolderRecursivlyDeleted {
+            result: PCloudResult::Ok,
+            deletedfiles: Some(deleted_files),
+            deletedfolders: Some(deleted_folders),
+        })
+    }
+
     /// Deletes the folder, only if  it is empty
     pub async fn delete_folder_if_empty(
         self,
-    ) -> Result<pcloud_model::FileOrFolderStat, Box<dyn std::error::Error + Send + Sync>> {
-        let url = format!("{}/deletefolder", self.client.api_host);
+    ) -> Result<pcloud_model::FileOrFolderStat, PCloudError> {
+        self.verify().await?;
+        let url = format!("{}/deletefolder", self.client.api_host());
 
-        let mut r = self.client.client.get(url);
+        let mut r = self.client.client().get(url);
 
         if let Some(p) = self.path {
             debug!("Deleting folder {} if empty", p);
@@ -260,12 +330,10 @@ impl DeleteFolderRequestBuilder {
 
         r = self.client.add_token(r);
 
-        let stat = r
-            .send()
-            .await?
-            .json::<pcloud_model::FileOrFolderStat>()
-            .await?
-            .assert_ok()?;
+        let stat = self
+            .client
+            .send_checked::<pcloud_model::FileOrFolderStat>(r)
+            .await?;
         Ok(stat)
     }
 }
@@ -285,11 +353,11 @@ pub struct CreateFolderRequestBuilder {
 
 #[allow(dead_code)]
 impl CreateFolderRequestBuilder {
-    pub(crate) fn for_folder<'a, T: FolderDescriptor>(
+    pub(crate) fn for_folder<T: FolderDescriptor>(
         client: &PCloudClient,
         folder_like_parent: T,
         name: &str,
-    ) -> Result<CreateFolderRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<CreateFolderRequestBuilder, PCloudError> {
         let f = folder_like_parent.to_folder()?;
 
         if !f.is_empty() {
@@ -312,16 +380,14 @@ impl CreateFolderRequestBuilder {
     }
 
     /// Creates the folder
-    pub async fn execute(
-        self,
-    ) -> Result<pcloud_model::FileOrFolderStat, Box<dyn std::error::Error + Send + Sync>> {
+    pub async fn execute(self) -> Result<pcloud_model::FileOrFolderStat, PCloudError> {
         let url = if self.if_not_exists {
-            format!("{}/createfolderifnotexists", self.client.api_host)
+            format!("{}/createfolderifnotexists", self.client.api_host())
         } else {
-            format!("{}/createfolder", self.client.api_host)
+            format!("{}/createfolder", self.client.api_host())
         };
 
-        let mut r = self.client.client.get(url);
+        let mut r = self.client.client().get(url);
 
         if let Some(p) = self.path {
             debug!("Creating folder {} in folder {}", self.name, p);
@@ -337,16 +403,33 @@ impl CreateFolderRequestBuilder {
 
         r = self.client.add_token(r);
 
-        let stat = r
-            .send()
-            .await?
-            .json::<pcloud_model::FileOrFolderStat>()
-            .await?
-            .assert_ok()?;
+        let stat = self
+            .client
+            .send_checked::<pcloud_model::FileOrFolderStat>(r)
+            .await?;
         Ok(stat)
     }
 }
 
+/// Default interval between two polls of the destination while copying a folder with progress reporting
+pub const DEFAULT_COPY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Callback invoked with the estimated progress of a folder copy
+type CopyProgressCallback = Arc<dyn Fn(&CopyFolderProgress) + Send + Sync>;
+
+/// Approximate progress of a server-side folder copy, estimated from the contents of the destination
+#[derive(Debug, Clone, PartialEq)]
+pub struct CopyFolderProgress {
+    /// Number of files found in the destination so far
+    pub files_copied: usize,
+    /// Number of files in the source folder
+    pub files_total: usize,
+    /// Number of bytes found in the destination so far
+    pub bytes_copied: u64,
+    /// Number of bytes in the source folder
+    pub bytes_total: u64,
+}
+
 pub struct CopyFolderRequestBuilder {
     /// Client to actually perform the request
     client: PCloudClient,
@@ -366,16 +449,22 @@ pub struct CopyFolderRequestBuilder {
     skip_existing: bool,
     ///  If it is set only the content of source folder will be copied otherwise the folder itself is copied
     copy_content_only: bool,
+    /// Invoked with the estimated progress while copying
+    on_progress: Option<CopyProgressCallback>,
+    /// Interval between two polls of the destination
+    poll_interval: Duration,
+    /// if set, the permissions on the target folder are checked before copying
+    check_permissions: bool,
 }
 
 #[allow(dead_code)]
 impl CopyFolderRequestBuilder {
     /// Copies a folder identified by folderid or path to either topath or tofolderid.
-    pub(crate) fn copy_folder<'a, S: FolderDescriptor, T: FolderDescriptor>(
+    pub(crate) fn copy_folder<S: FolderDescriptor, T: FolderDescriptor>(
         client: &PCloudClient,
         folder_like: S,
         target_folder_like: T,
-    ) -> Result<CopyFolderRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<CopyFolderRequestBuilder, PCloudError> {
         let source: PCloudFolder = folder_like.to_folder()?;
         let target: PCloudFolder = target_folder_like.to_folder()?;
 
@@ -390,6 +479,9 @@ impl CopyFolderRequestBuilder {
                 overwrite: true,
                 skip_existing: false,
                 copy_content_only: false,
+                on_progress: None,
+                poll_interval: DEFAULT_COPY_POLL_INTERVAL,
+                check_permissions: false,
             })
         } else {
             Err(pcloud_model::PCloudResult::NoFileIdOrPathProvided)?
@@ -414,16 +506,142 @@ impl CopyFolderRequestBuilder {
         self
     }
 
+    /// Callback invoked with the estimated progress while copying. pCloud copies folders in a single request without reporting progress, so the source folder is counted first and the destination is polled while the copy runs. The numbers are approximate: files overwritten or skipped in an existing destination don't show up.
+    pub fn on_progress<F: Fn(&CopyFolderProgress) + Send + Sync + 'static>(
+        mut self,
+        callback: F,
+    ) -> CopyFolderRequestBuilder {
+        self.on_progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Interval between two polls of the destination when reporting progress (defaults to 1 second)
+    pub fn poll_interval(mut self, value: Duration) -> CopyFolderRequestBuilder {
+        self.poll_interval = value;
+        self
+    }
+
+    /// If set, the create permission on a target folder shared by another user is checked before copying. Fails with `PermissionDenied` instead of a server error.
+    pub fn check_permissions(mut self, value: bool) -> CopyFolderRequestBuilder {
+        self.check_permissions = value;
+        self
+    }
+
     /// Execute the copy operation
-    pub async fn execute(
-        self,
-    ) -> Result<pcloud_model::FileOrFolderStat, Box<dyn std::error::Error + Send + Sync>> {
-        let mut r = self
+    pub async fn execute(self) -> Result<pcloud_model::FileOrFolderStat, PCloudError> {
+        if self.check_permissions {
+            let target = permissions::receiving_folder(self.to_folder_id, self.to_path.as_ref());
+            permissions::require_folder_permission(
+                &self.client,
+                &target,
+                Permissions::CREATE,
+                "copy",
+            )
+            .await?;
+        }
+
+        match &self.on_progress {
+            Some(on_progress) => self.copy_with_progress(on_progress).await,
+            None => self.copy().await,
+        }
+    }
+
+    /// Copies the folder while polling the destination for the estimated progress
+    async fn copy_with_progress(
+        &self,
+        on_progress: &CopyProgressCallback,
+    ) -> Result<pcloud_model::FileOrFolderStat, PCloudError> {
+        let source = PCloudFolder {
+            folder_id: self.from_folder_id,
+            path: self.from_path.clone(),
+        };
+        let metadata = self
             .client
+            .list_folder(&source)?
+            .recursive(true)
+            .get()
+            .await?
+            .metadata
+            .ok_or(PCloudResult::DirectoryDoesNotExist)?;
+        let (files_total, bytes_total) = count_files(&metadata.contents);
+        let name = self.to_name.clone().unwrap_or(metadata.name);
+        debug!(
+            "Copying {} files ({} bytes) of folder {}",
+            files_total, bytes_total, source
+        );
+
+        // Files already present in the destination are not part of the progress
+        let (files_before, bytes_before) = self.count_destination(&name).await;
+        let progress = |files: usize, bytes: u64| CopyFolderProgress {
+            files_copied: files.saturating_sub(files_before).min(files_total),
+            files_total,
+            bytes_copied: bytes.saturating_sub(bytes_before).min(bytes_total),
+            bytes_total,
+        };
+        on_progress(&progress(files_before, bytes_before));
+
+        let copy = self.copy();
+        tokio::pin!(copy);
+        loop {
+            tokio::select! {
+                result = &mut copy => {
+                    let result = result?;
+                    on_progress(&progress(files_before + files_total, bytes_before + bytes_total));
+                    return Ok(result);
+                }
+                _ = tokio::time::sleep(self.poll_interval) => {
+                    let (files, bytes) = self.count_destination(&name).await;
+                    on_progress(&progress(files, bytes));
+                }
+            }
+        }
+    }
+
+    /// Counts the files and bytes in the destination of the copy. A destination not yet created counts as empty.
+    async fn count_destination(&self, name: &str) -> (usize, u64) {
+        let target = PCloudFolder {
+            folder_id: self.to_folder_id,
+            path: self.to_path.clone(),
+        };
+        let listing = match self.client.list_folder(&target) {
+            Ok(request) => request.recursive(true).get().await,
+            Err(e) => Err(e),
+        };
+        let metadata = match listing.map(|l| l.metadata) {
+            Ok(Some(metadata)) => metadata,
+            Ok(None) => return (0, 0),
+            Err(e) => {
+                debug!("Failed to poll copy destination {}: {}", target, e);
+                return (0, 0);
+            }
+        };
+
+        // A target path without trailing '/' names the copy itself instead of the folder to copy into
+        let into_target = self
+            .to_path
+            .as_ref()
+            .map(|p| p.ends_with('/'))
+            .unwrap_or(true);
+        if self.copy_content_only || !into_target {
+            count_files(&metadata.contents)
+        } else {
+            metadata
+                .contents
+                .iter()
+                .find(|m| m.isfolder && m.name == name)
+                .map(|m| count_files(&m.contents))
+                .unwrap_or((0, 0))
+        }
+    }
+
+    /// Sends the copy request
+    async fn copy(&self) -> Result<pcloud_model::FileOrFolderStat, PCloudError> {
+        let mut r = self
             .client
-            .post(format!("{}/copyfolder", self.client.api_host));
+            .client()
+            .post(format!("{}/copyfolder", self.client.api_host()));
 
-        if let Some(v) = self.from_path {
+        if let Some(v) = &self.from_path {
             r = r.query(&[("path", v)]);
         }
 
@@ -431,7 +649,7 @@ impl CopyFolderRequestBuilder {
             r = r.query(&[("folderid", v)]);
         }
 
-        if let Some(v) = self.to_path {
+        if let Some(v) = &self.to_path {
             r = r.query(&[("topath", v)]);
         }
 
@@ -439,7 +657,7 @@ impl CopyFolderRequestBuilder {
             r = r.query(&[("tofolderid", v)]);
         }
 
-        if let Some(v) = self.to_name {
+        if let Some(v) = &self.to_name {
             r = r.query(&[("toname", v)]);
         }
 
@@ -447,26 +665,36 @@ impl CopyFolderRequestBuilder {
             r = r.query(&[("noover", "1")]);
         }
 
-        if !self.skip_existing {
+        if self.skip_existing {
             r = r.query(&[("skipexisting", "1")]);
         }
 
-        if !self.copy_content_only {
+        if self.copy_content_only {
             r = r.query(&[("copycontentonly", "1")]);
         }
 
         r = self.client.add_token(r);
 
-        let result = r
-            .send()
-            .await?
-            .json::<pcloud_model::FileOrFolderStat>()
-            .await?
-            .assert_ok()?;
+        let result = self
+            .client
+            .send_checked::<pcloud_model::FileOrFolderStat>(r)
+            .await?;
         Ok(result)
     }
 }
 
+/// Counts the files and their total size in the given (recursive) folder contents
+fn count_files(contents: &[Metadata]) -> (usize, u64) {
+    contents.iter().fold((0, 0), |(files, bytes), m| {
+        if m.isfolder {
+            let (f, b) = count_files(&m.contents);
+            (files + f, bytes + b)
+        } else {
+            (files + 1, bytes + m.size.unwrap_or_default())
+        }
+    })
+}
+
 pub struct MoveFolderRequestBuilder {
     /// Client to actually perform the request
     client: PCloudClient,
@@ -480,16 +708,18 @@ pub struct MoveFolderRequestBuilder {
     to_folder_id: Option<u64>,
     /// New file name
     to_name: Option<String>,
+    /// if set, the permissions on the folder and the target folder are checked before moving
+    check_permissions: bool,
 }
 
 #[allow(dead_code)]
 impl MoveFolderRequestBuilder {
     /// Renames (and/or moves) a folder identified by folderid or path to either topath (if topath is a existing folder to place source folder without new name for the folder it MUST end with slash - /newpath/) or tofolderid/toname (one or both can be provided).
-    pub(crate) fn move_folder<'a, S: FolderDescriptor, T: FolderDescriptor>(
+    pub(crate) fn move_folder<S: FolderDescriptor, T: FolderDescriptor>(
         client: &PCloudClient,
         folder_like: S,
         target_folder_like: T,
-    ) -> Result<MoveFolderRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<MoveFolderRequestBuilder, PCloudError> {
         let source: PCloudFolder = folder_like.to_folder()?;
         let target: PCloudFolder = target_folder_like.to_folder()?;
 
@@ -501,6 +731,7 @@ impl MoveFolderRequestBuilder {
                 to_folder_id: target.folder_id,
                 client: client.clone(),
                 to_name: None,
+                check_permissions: false,
             })
         } else {
             Err(pcloud_model::PCloudResult::NoFileIdOrPathProvided)?
@@ -513,14 +744,43 @@ impl MoveFolderRequestBuilder {
         self
     }
 
+    /// If set, the delete permission on a folder and the create permission on a target folder shared by another user are checked before moving. Fails with `PermissionDenied` instead of a server error.
+    pub fn check_permissions(mut self, value: bool) -> MoveFolderRequestBuilder {
+        self.check_permissions = value;
+        self
+    }
+
     // Execute the move operation
-    pub async fn execute(
-        self,
-    ) -> Result<pcloud_model::FileOrFolderStat, Box<dyn std::error::Error + Send + Sync>> {
+    pub async fn execute(self) -> Result<pcloud_model::FileOrFolderStat, PCloudError> {
+        let source = PCloudFolder {
+            folder_id: self.from_folder_id,
+            path: self.from_path.clone(),
+        };
+        let target = permissions::receiving_folder(self.to_folder_id, self.to_path.as_ref());
+        self.client.guard_folder(&source, "move").await?;
+        self.client.guard_target(&target, "move").await?;
+
+        if self.check_permissions {
+            permissions::require_folder_permission(
+                &self.client,
+                &source,
+                Permissions::DELETE,
+                "move",
+            )
+            .await?;
+            permissions::require_folder_permission(
+                &self.client,
+                &target,
+                Permissions::CREATE,
+                "move",
+            )
+            .await?;
+        }
+
         let mut r = self
             .client
-            .client
-            .post(format!("{}/renamefolder", self.client.api_host));
+            .client()
+            .post(format!("{}/renamefolder", self.client.api_host()));
 
         if let Some(v) = self.from_path {
             r = r.query(&[("path", v)]);
@@ -544,12 +804,10 @@ impl MoveFolderRequestBuilder {
 
         r = self.client.add_token(r);
 
-        let result = r
-            .send()
-            .await?
-            .json::<pcloud_model::FileOrFolderStat>()
-            .await?
-            .assert_ok()?;
+        let result = self
+            .client
+            .send_checked::<pcloud_model::FileOrFolderStat>(r)
+            .await?;
         Ok(result)
     }
 }
@@ -573,10 +831,10 @@ pub struct ListFolderRequestBuilder {
 
 #[allow(dead_code)]
 impl ListFolderRequestBuilder {
-    pub(crate) fn for_folder<'a, T: FolderDescriptor>(
+    pub(crate) fn for_folder<T: FolderDescriptor>(
         client: &PCloudClient,
         folder_like: T,
-    ) -> Result<ListFolderRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<ListFolderRequestBuilder, PCloudError> {
         let f = folder_like.to_folder()?;
 
         if !f.is_empty() {
@@ -619,13 +877,39 @@ impl ListFolderRequestBuilder {
     }
 
     /// Execute list operation
-    pub async fn get(
-        self,
-    ) -> Result<pcloud_model::FileOrFolderStat, Box<dyn std::error::Error + Send + Sync>> {
+    pub async fn get(self) -> Result<pcloud_model::FileOrFolderStat, PCloudError> {
+        let client = self.client.clone();
+        let stat = client
+            .send_checked::<pcloud_model::FileOrFolderStat>(self.request())
+            .await?;
+        Ok(stat)
+    }
+
+    /// Execute list operation, but only decode the metadata of the folder itself. Its contents are kept as raw JSON and decoded on demand with `LazyFolder::contents()`, saving CPU if only the folder is of interest.
+    pub async fn get_lazy(self) -> Result<pcloud_model::LazyFolderStat, PCloudError> {
+        let client = self.client.clone();
+        let stat = client
+            .send_checked::<pcloud_model::LazyFolderStat>(self.request())
+            .await?;
+        Ok(stat)
+    }
+
+    /// Execute list operation and return the metadata of the folder itself (without contents), e.g. for existence checks
+    pub async fn folder_metadata(self) -> Result<Metadata, PCloudError> {
+        let folder = self
+            .get_lazy()
+            .await?
+            .metadata
+            .ok_or(PCloudResult::DirectoryDoesNotExist)?;
+        Ok(folder.metadata)
+    }
+
+    /// Builds the listfolder request
+    fn request(self) -> reqwest::RequestBuilder {
         let mut r = self
             .client
-            .client
-            .get(format!("{}/listfolder", self.client.api_host));
+            .client()
+            .get(format!("{}/listfolder", self.client.api_host()));
 
         if let Some(v) = self.path {
             debug!("List folder {}", v);
@@ -653,68 +937,160 @@ impl ListFolderRequestBuilder {
             r = r.query(&[("noshares", "1")]);
         }
 
-        r = self.client.add_token(r);
+        self.client.add_token(r)
+    }
 
-        let stat = r
-            .send()
-            .await?
-            .json::<pcloud_model::FileOrFolderStat>()
-            .await?
-            .assert_ok()?;
-        Ok(stat)
+    /// Execute list operation and split the contents of the folder into files and folders
+    pub async fn listing(self) -> Result<FolderListing, PCloudError> {
+        Ok(FolderListing::try_from(self.get().await?)?)
+    }
+}
+
+/// Result of a folder listing with the contents split into files and folders
+#[derive(Debug)]
+pub struct FolderListing {
+    /// Metadata of the listed folder, without contents
+    folder: Metadata,
+    /// Files directly in the folder
+    files: Vec<Metadata>,
+    /// Folders directly in the folder. For recursive listings with their contents.
+    folders: Vec<Metadata>,
+}
+
+impl FolderListing {
+    /// Metadata of the listed folder. Its contents are available through `files()` and `folders()`.
+    pub fn folder(&self) -> &Metadata {
+        &self.folder
+    }
+
+    /// Files directly in the folder
+    pub fn files(&self) -> &[Metadata] {
+        &self.files
+    }
+
+    /// Folders directly in the folder. For recursive listings with their contents.
+    pub fn folders(&self) -> &[Metadata] {
+        &self.folders
+    }
+
+    /// Number of files directly in the folder
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Number of folders directly in the folder
+    pub fn folder_count(&self) -> usize {
+        self.folders.len()
+    }
+
+    /// True if the folder contains neither files nor folders
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.folders.is_empty()
+    }
+
+    /// Splits the listing into the folder metadata, the files and the folders
+    pub fn into_parts(self) -> (Metadata, Vec<Metadata>, Vec<Metadata>) {
+        (self.folder, self.files, self.folders)
+    }
+}
+
+impl TryFrom<FileOrFolderStat> for FolderListing {
+    type Error = PCloudResult;
+
+    fn try_from(value: FileOrFolderStat) -> Result<Self, Self::Error> {
+        let mut folder = value.metadata.ok_or(PCloudResult::DirectoryDoesNotExist)?;
+        if !folder.isfolder {
+            return Err(PCloudResult::InvalidFolderId);
+        }
+        let (folders, files) = std::mem::take(&mut folder.contents)
+            .into_iter()
+            .partition(|m| m.isfolder);
+        Ok(FolderListing {
+            folder,
+            files,
+            folders,
+        })
     }
 }
 
 #[allow(dead_code)]
 impl PCloudClient {
     /// Lists the content of a folder. Accepts either a folder id (u64), a folder path (String) or any other pCloud object describing a folder (like Metadata)
-    pub fn list_folder<'a, T: FolderDescriptor>(
+    pub fn list_folder<T: FolderDescriptor>(
         &self,
         folder_like: T,
-    ) -> Result<ListFolderRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<ListFolderRequestBuilder, PCloudError> {
         ListFolderRequestBuilder::for_folder(self, folder_like)
     }
 
     /// Creates a new folder in a parent folder. Accepts either a folder id (u64), a folder path (String) or any other pCloud object describing a folder (like Metadata)
-    pub fn create_folder<'a, T: FolderDescriptor>(
+    pub fn create_folder<T: FolderDescriptor>(
         &self,
         parent_folder_like: T,
         name: &str,
-    ) -> Result<CreateFolderRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<CreateFolderRequestBuilder, PCloudError> {
         CreateFolderRequestBuilder::for_folder(self, parent_folder_like, name)
     }
 
     /// Deletes a folder. Either only if empty or recursively. Accepts either a folder id (u64), a folder path (String) or any other pCloud object describing a folder (like Metadata)
-    pub fn delete_folder<'a, T: FolderDescriptor>(
+    pub fn delete_folder<T: FolderDescriptor>(
         &self,
         folder_like: T,
-    ) -> Result<DeleteFolderRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<DeleteFolderRequestBuilder, PCloudError> {
         DeleteFolderRequestBuilder::for_folder(self, folder_like)
     }
 
+    /// Empties a folder: deletes all its files and subfolders (recursively), but keeps the folder itself. Accepts either a folder id (u64), a folder path (String) or any other pCloud object describing a folder (like Metadata)
+    pub async fn delete_folder_contents<T: FolderDescriptor>(
+        &self,
+        folder_like: T,
+    ) -> Result<pcloud_model::FolderRecursivlyDeleted, PCloudError> {
+        DeleteFolderRequestBuilder::for_folder(self, folder_like)?
+            .delete_contents()
+            .await
+    }
+
     /// Copies a folder identified by folderid or path to either topath or tofolderid.
-    pub fn copy_folder<'a, S: FolderDescriptor, T: FolderDescriptor>(
+    pub fn copy_folder<S: FolderDescriptor, T: FolderDescriptor>(
         &self,
         folder_like: S,
         target_folder_like: T,
-    ) -> Result<CopyFolderRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<CopyFolderRequestBuilder, PCloudError> {
         CopyFolderRequestBuilder::copy_folder(self, folder_like, target_folder_like)
     }
 
     /// Renames (and/or moves) a folder identified by folderid or path to either topath (if topath is a existing folder to place source folder without new name for the folder it MUST end with slash - /newpath/) or tofolderid/toname (one or both can be provided).
-    pub fn move_folder<'a, S: FolderDescriptor, T: FolderDescriptor>(
+    pub fn move_folder<S: FolderDescriptor, T: FolderDescriptor>(
         &self,
         folder_like: S,
         target_folder_like: T,
-    ) -> Result<MoveFolderRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<MoveFolderRequestBuilder, PCloudError> {
         MoveFolderRequestBuilder::move_folder(self, folder_like, target_folder_like)
     }
 
+    /// Fetches the metadata of the parent folder (including its subfolders) of a file or folder using its `parentfolderid`. Returns None for the root folder.
+    pub async fn parent_of(&self, metadata: &Metadata) -> Result<Option<Metadata>, PCloudError> {
+        let parent_id = match metadata.parentfolderid {
+            Some(parent_id) if metadata.folderid != Some(0) => parent_id,
+            _ => return Ok(None),
+        };
+
+        debug!("Requesting parent folder {} of {}", parent_id, metadata.id);
+        let parent = self
+            .list_folder(parent_id)?
+            .nofiles(true)
+            .get()
+            .await?
+            .metadata
+            .ok_or(PCloudResult::DirectoryDoesNotExist)?;
+        Ok(Some(parent))
+    }
+
     /// Returns the folder id of a PCloudFolder. If the folder_id is given, just return it. If a path is given, fetch the metadata with the folder id.
     pub(crate) async fn get_folder_id<T: FolderDescriptor>(
         &self,
         folder_like: T,
-    ) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<u64, PCloudError> {
         let folder = folder_like.to_folder()?;
 
         if let Some(folder_id) = folder.folder_id {
@@ -724,10 +1100,8 @@ impl PCloudClient {
                 .list_folder(folder)?
                 .recursive(false)
                 .nofiles(true)
-                .get()
-                .await?
-                .metadata
-                .unwrap();
+                .folder_metadata()
+                .await?;
 
             if !metadata.isfolder {
                 Err(PCloudResult::InvalidFolderId)?
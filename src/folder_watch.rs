@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+
+use futures::Stream;
+use log::{debug, warn};
+use tokio::sync::mpsc::Receiver;
+
+use crate::{
+    error::PCloudError,
+    folder_ops::FolderDescriptor,
+    pcloud_client::PCloudClient,
+    pcloud_model::{DiffEntry, DiffEvent, Metadata, PCloudResult},
+};
+
+/// Change of a file or folder within a watched folder, see `PCloudClient::watch_folder()`. All paths are relative to the watched folder and separated by '/'.
+#[derive(Debug)]
+pub enum FolderEvent {
+    /// File or folder created, or moved into the watched folder
+    Created { path: String, metadata: Metadata },
+    /// Content of a file modified
+    Modified { path: String, metadata: Metadata },
+    /// File or folder deleted, or moved out of the watched folder
+    Deleted { path: String, metadata: Metadata },
+    /// File or folder moved or renamed within the watched folder
+    Moved {
+        from: String,
+        path: String,
+        metadata: Metadata,
+    },
+}
+
+impl FolderEvent {
+    /// Current path of the file or folder (the last one, if deleted)
+    pub fn path(&self) -> &str {
+        match self {
+            FolderEvent::Created { path, .. }
+            | FolderEvent::Modified { path, .. }
+            | FolderEvent::Deleted { path, .. }
+            | FolderEvent::Moved { path, .. } => path,
+        }
+    }
+
+    /// Latest metadata of the file or folder
+    pub fn metadata(&self) -> &Metadata {
+        match self {
+            FolderEvent::Created { metadata, .. }
+            | FolderEvent::Modified { metadata, .. }
+            | FolderEvent::Deleted { metadata, .. }
+            | FolderEvent::Moved { metadata, .. } => metadata,
+        }
+    }
+}
+
+/// Tracks the paths of all files and folders below the watched folder and translates diff events into folder events
+struct FolderWatcher {
+    /// Client to list folders moved in
+    client: PCloudClient,
+    /// Id of the watched folder
+    folder_id: u64,
+    /// Relative paths of all known files and folders by their id (like `f123` or `d45`). The watched folder has the empty path.
+    known: HashMap<String, String>,
+    /// Events of the account
+    events: Receiver<DiffEntry>,
+    /// If set, the watched folder was deleted (or can't be listed anymore)
+    gone: bool,
+}
+
+impl FolderWatcher {
+    /// Remembers the paths of the folder and all its (recursive) contents
+    fn remember(&mut self, metadata: &Metadata, path: &str) {
+        self.known.insert(metadata.id.clone(), path.to_string());
+        for entry in &metadata.contents {
+            self.remember(entry, &join(path, &entry.name));
+        }
+    }
+
+    /// Forgets the file or folder and everything below it
+    fn forget(&mut self, id: &str, path: &str) {
+        self.known.remove(id);
+        let prefix = format!("{}/", path);
+        self.known.retain(|_, p| !p.starts_with(&prefix));
+    }
+
+    /// Moves the paths of everything below the folder along with it
+    fn rename_below(&mut self, from: &str, to: &str) {
+        let prefix = format!("{}/", from);
+        for path in self.known.values_mut() {
+            if let Some(rest) = path.strip_prefix(&prefix) {
+                *path = join(to, rest);
+            }
+        }
+    }
+
+    /// Relative path the metadata points to, if its parent is within the watched folder
+    fn location(&self, metadata: &Metadata) -> Option<String> {
+        let parent = metadata.parentfolderid?;
+        self.known
+            .get(&format!("d{}", parent))
+            .map(|p| join(p, &metadata.name))
+    }
+
+    /// Rebuilds the known paths from a fresh listing
+    async fn relist(&mut self) -> Result<(), PCloudError> {
+        let metadata = self
+            .client
+            .list_folder(self.folder_id)?
+            .recursive(true)
+            .get()
+            .await?
+            .metadata
+            .ok_or(PCloudResult::DirectoryDoesNotExist)?;
+        self.known.clear();
+        self.remember(&metadata, "");
+        Ok(())
+    }
+
+    /// Translates a single diff event. Returns None if it doesn't affect the watched folder.
+    async fn translate(&mut self, entry: DiffEntry) -> Option<FolderEvent> {
+        if entry.event == DiffEvent::Reset {
+            warn!(
+                "Reset received, listing watched folder {} again",
+                self.folder_id
+            );
+            if let Err(e) = self.relist().await {
+                warn!("Failed to list watched folder {}: {}", self.folder_id, e);
+                self.gone = true;
+            }
+            return None;
+        }
+        let metadata = entry.metadata?;
+        if metadata.isfolder && metadata.folderid == Some(self.folder_id) {
+            self.gone = entry.event == DiffEvent::DeleteFolder;
+            return None;
+        }
+
+        let previous = self.known.get(&metadata.id).cloned();
+        match entry.event {
+            DiffEvent::CreateFile | DiffEvent::CreateFolder => match self.location(&metadata) {
+                Some(path) => {
+                    self.known.insert(metadata.id.clone(), path.clone());
+                    Some(FolderEvent::Created { path, metadata })
+                }
+                None => None,
+            },
+            DiffEvent::DeleteFile | DiffEvent::DeleteFolder => match previous {
+                Some(path) => {
+                    self.forget(&metadata.id, &path);
+                    Some(FolderEvent::Deleted { path, metadata })
+                }
+                None => None,
+            },
+            DiffEvent::ModifyFile | DiffEvent::ModifyFolder => {
+                match (previous, self.location(&metadata)) {
+                    (Some(from), Some(path)) if from == path => {
+                        // Folders are modified by changes of their contents, which are reported separately
+                        (!metadata.isfolder).then_some(FolderEvent::Modified { path, metadata })
+                    }
+                    (Some(from), Some(path)) => {
+                        self.known.insert(metadata.id.clone(), path.clone());
+                        self.rename_below(&from, &path);
+                        Some(FolderEvent::Moved {
+                            from,
+                            path,
+                            metadata,
+                        })
+                    }
+                    (Some(path), None) => {
+                        self.forget(&metadata.id, &path);
+                        Some(FolderEvent::Deleted { path, metadata })
+                    }
+                    (None, Some(path)) => {
+                        self.known.insert(metadata.id.clone(), path.clone());
+                        if let Some(folder_id) = metadata.folderid.filter(|_| metadata.isfolder) {
+                            self.remember_moved_in(folder_id, &path).await;
+                        }
+                        Some(FolderEvent::Created { path, metadata })
+                    }
+                    (None, None) => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Remembers the contents of a folder moved into the watched folder, which don't get events of their own
+    async fn remember_moved_in(&mut self, folder_id: u64, path: &str) {
+        let listing = match self.client.list_folder(folder_id) {
+            Ok(request) => request.recursive(true).get().await,
+            Err(e) => Err(e),
+        };
+        match listing.map(|l| l.metadata) {
+            Ok(Some(metadata)) => self.remember(&metadata, path),
+            Ok(None) => {}
+            Err(e) => debug!("Failed to list folder {} moved in: {}", path, e),
+        }
+    }
+}
+
+/// Appends the name to the relative folder path
+fn join(folder: &str, name: &str) -> String {
+    if folder.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", folder, name)
+    }
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Watches a folder (recursively) and streams its changes as simple created, modified, deleted and moved events with paths relative to the folder. Runs the blocking diff stream of the account in the background and ends if the folder is deleted. Accepts either a folder id (u64), a folder path (String) or any other pCloud object describing a folder (like Metadata)
+    pub async fn watch_folder<T: FolderDescriptor>(
+        &self,
+        folder_like: T,
+    ) -> Result<impl Stream<Item = FolderEvent>, PCloudError> {
+        let baseline = self.resync_baseline(folder_like).await?;
+        let folder_id = baseline
+            .metadata
+            .folderid
+            .ok_or(PCloudResult::DirectoryDoesNotExist)?;
+        debug!(
+            "Watching folder {} from diff id {}",
+            folder_id, baseline.diffid
+        );
+
+        let mut watcher = FolderWatcher {
+            client: self.clone(),
+            folder_id,
+            known: HashMap::new(),
+            events: self.get_events().after_diff_id(baseline.diffid).stream(),
+            gone: false,
+        };
+        watcher.remember(&baseline.metadata, "");
+
+        Ok(futures::stream::unfold(watcher, |mut watcher| async move {
+            while let Some(entry) = watcher.events.recv().await {
+                if let Some(event) = watcher.translate(entry).await {
+                    return Some((event, watcher));
+                }
+                if watcher.gone {
+                    debug!("Watched folder {} is gone", watcher.folder_id);
+                    return None;
+                }
+            }
+            None
+        }))
+    }
+}
@@ -0,0 +1,247 @@
+use std::fmt::Display;
+
+use log::debug;
+
+use crate::{
+    error::PCloudError,
+    file_ops::PCloudFile,
+    folder_ops::PCloudFolder,
+    pcloud_client::PCloudClient,
+    pcloud_model::{Metadata, PCloudResult},
+};
+
+/// Safety limits for deletes and moves (see `PCloudClientBuilder::guard_rails()`), protecting the account from bugs in automated tools running with full-account tokens. The root folder can never be deleted, emptied or moved.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GuardRails {
+    /// Folders with more bytes (recursively) are never deleted or moved
+    max_folder_bytes: Option<u64>,
+    /// Folders with more entries (files and folders, recursively) are never deleted or moved
+    max_folder_entries: Option<usize>,
+    /// Only files and folders below this path are deleted or moved
+    allowed_base: Option<String>,
+}
+
+impl GuardRails {
+    /// Creates guard rails only protecting the root folder
+    pub fn new() -> GuardRails {
+        GuardRails::default()
+    }
+
+    /// Refuses deleting or moving folders containing more than the given number of bytes (recursively)
+    pub fn max_folder_size(mut self, bytes: u64) -> GuardRails {
+        self.max_folder_bytes = Some(bytes);
+        self
+    }
+
+    /// Refuses deleting or moving folders containing more than the given number of files and folders (recursively)
+    pub fn max_folder_entries(mut self, count: usize) -> GuardRails {
+        self.max_folder_entries = Some(count);
+        self
+    }
+
+    /// Refuses deleting or moving anything not below the given path. Moves are only allowed into the path or its subfolders.
+    pub fn allowed_base(mut self, path: &str) -> GuardRails {
+        self.allowed_base = Some(normalize(path));
+        self
+    }
+
+    /// Checks if the path is below the allowed base (or the base itself, if `inclusive`)
+    fn allows_path(&self, path: &str, inclusive: bool) -> bool {
+        match &self.allowed_base {
+            Some(base) => {
+                let path = normalize(path);
+                (inclusive && path == *base)
+                    || base == "/"
+                    || path.starts_with(&format!("{}/", base))
+            }
+            None => true,
+        }
+    }
+}
+
+/// Rule of the guard rails refusing an operation
+#[derive(Debug, Clone, PartialEq)]
+pub enum GuardReason {
+    /// The root folder is affected
+    RootFolder,
+    /// The folder contains more bytes than allowed
+    FolderTooLarge { bytes: u64, limit: u64 },
+    /// The folder contains more entries than allowed
+    TooManyEntries { entries: usize, limit: usize },
+    /// The path is not below the allowed base path
+    OutsideAllowedBase { base: String },
+}
+
+/// Error of a delete or move refused by the guard rails of the client
+#[derive(Debug, Clone, PartialEq)]
+pub struct GuardViolation {
+    /// Operation refused (`delete` or `move`)
+    pub operation: String,
+    /// Path of the file or folder affected
+    pub path: String,
+    /// Rule refusing the operation
+    pub reason: GuardReason,
+}
+
+impl Display for GuardViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Refused to {} {}: ", self.operation, self.path)?;
+        match &self.reason {
+            GuardReason::RootFolder => write!(f, "root folder"),
+            GuardReason::FolderTooLarge { bytes, limit } => {
+                write!(f, "folder contains {} bytes, limit is {}", bytes, limit)
+            }
+            GuardReason::TooManyEntries { entries, limit } => {
+                write!(f, "folder contains {} entries, limit is {}", entries, limit)
+            }
+            GuardReason::OutsideAllowedBase { base } => write!(f, "not below {}", base),
+        }
+    }
+}
+
+impl std::error::Error for GuardViolation {}
+
+/// Normalizes a path to start with '/' and not end with '/'
+fn normalize(path: &str) -> String {
+    let path = path.trim_matches('/');
+    format!("/{}", path)
+}
+
+/// Counts all files and folders and their total size in the given (recursive) folder contents
+fn count_entries(contents: &[Metadata]) -> (usize, u64) {
+    contents.iter().fold((0, 0), |(entries, bytes), m| {
+        let (e, b) = count_entries(&m.contents);
+        (entries + 1 + e, bytes + m.size.unwrap_or_default() + b)
+    })
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Checks a delete or move of the folder against the guard rails, if enabled
+    pub(crate) async fn guard_folder(
+        &self,
+        folder: &PCloudFolder,
+        operation: &str,
+    ) -> Result<(), PCloudError> {
+        let guard = match self.guard_rails() {
+            Some(guard) => guard,
+            None => return Ok(()),
+        };
+
+        let recursive = guard.max_folder_bytes.is_some() || guard.max_folder_entries.is_some();
+        let metadata = self
+            .list_folder(folder)?
+            .recursive(recursive)
+            .nofiles(!recursive)
+            .get()
+            .await?
+            .metadata
+            .ok_or(PCloudResult::DirectoryDoesNotExist)?;
+        let path = match &folder.path {
+            Some(path) => normalize(path),
+            None => normalize(&self.path_resolver().resolve_metadata(&metadata).await?),
+        };
+        let violation = |reason| GuardViolation {
+            operation: operation.to_string(),
+            path: path.clone(),
+            reason,
+        };
+        debug!(
+            "Checking {} of folder {} against guard rails",
+            operation, path
+        );
+
+        if metadata.folderid == Some(0) || path == "/" {
+            Err(violation(GuardReason::RootFolder))?
+        }
+        if !guard.allows_path(&path, false) {
+            Err(violation(GuardReason::OutsideAllowedBase {
+                base: guard.allowed_base.clone().unwrap_or_default(),
+            }))?
+        }
+        let (entries, bytes) = count_entries(&metadata.contents);
+        if let Some(limit) = guard.max_folder_entries.filter(|l| entries > *l) {
+            Err(violation(GuardReason::TooManyEntries { entries, limit }))?
+        }
+        if let Some(limit) = guard.max_folder_bytes.filter(|l| bytes > *l) {
+            Err(violation(GuardReason::FolderTooLarge { bytes, limit }))?
+        }
+        Ok(())
+    }
+
+    /// Checks a delete or move of the file against the guard rails, if enabled
+    pub(crate) async fn guard_file(
+        &self,
+        file: &PCloudFile,
+        operation: &str,
+    ) -> Result<(), PCloudError> {
+        let guard = match self.guard_rails() {
+            Some(guard) => guard,
+            None => return Ok(()),
+        };
+        if guard.allowed_base.is_none() {
+            return Ok(());
+        }
+
+        let path = match &file.path {
+            Some(path) => normalize(path),
+            None => {
+                let metadata = self
+                    .get_file_metadata(file)
+                    .await?
+                    .metadata
+                    .ok_or(PCloudResult::FileNotFound)?;
+                normalize(&self.path_resolver().resolve_metadata(&metadata).await?)
+            }
+        };
+
+        if !guard.allows_path(&path, false) {
+            Err(GuardViolation {
+                operation: operation.to_string(),
+                path,
+                reason: GuardReason::OutsideAllowedBase {
+                    base: guard.allowed_base.clone().unwrap_or_default(),
+                },
+            })?
+        }
+        Ok(())
+    }
+
+    /// Checks the folder receiving a move against the allowed base path of the guard rails, if enabled
+    pub(crate) async fn guard_target(
+        &self,
+        folder: &PCloudFolder,
+        operation: &str,
+    ) -> Result<(), PCloudError> {
+        let guard = match self.guard_rails() {
+            Some(guard) => guard,
+            None => return Ok(()),
+        };
+        if guard.allowed_base.is_none() {
+            return Ok(());
+        }
+
+        let path = match &folder.path {
+            Some(path) => normalize(path),
+            None => {
+                let metadata = self
+                    .list_folder(folder)?
+                    .nofiles(true)
+                    .folder_metadata()
+                    .await?;
+                normalize(&self.path_resolver().resolve_metadata(&metadata).await?)
+            }
+        };
+
+        if !guard.allows_path(&path, true) {
+            Err(GuardViolation {
+                operation: operation.to_string(),
+                path,
+                reason: GuardReason::OutsideAllowedBase {
+                    base: guard.allowed_base.clone().unwrap_or_default(),
+                },
+            })?
+        }
+        Ok(())
+    }
+}
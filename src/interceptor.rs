@@ -0,0 +1,11 @@
+use reqwest::{Request, Response};
+
+/// Hook into every request sent and every response received by the PCloudClient (and all its request builders), e.g. to add custom headers, for auditing or to mutate requests.
+/// Interceptors are registered using `PCloudClientBuilder::with_interceptor()` and invoked in the order of registration.
+pub trait Interceptor: Send + Sync {
+    /// Called with the fully built request right before it is passed to the transport
+    fn on_request(&self, _request: &mut Request) {}
+
+    /// Called with every response received from the transport, before its body is consumed
+    fn on_response(&self, _response: &Response) {}
+}
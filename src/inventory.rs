@@ -0,0 +1,177 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::{
+    error::PCloudError,
+    folder_ops::{FolderDescriptor, PCloudFolder},
+    pcloud_client::PCloudClient,
+    pcloud_model::{Metadata, PCloudResult},
+};
+
+/// Output formats of an inventory export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InventoryFormat {
+    /// One JSON object per line
+    #[default]
+    JsonLines,
+    /// Comma separated values with a header line (RFC 4180)
+    Csv,
+}
+
+/// Single file of an inventory
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct InventoryEntry {
+    /// Path relative to the exported folder, separated by '/'
+    pub path: String,
+    /// Size in bytes
+    pub size: Option<u64>,
+    /// pCloud content hash
+    pub hash: Option<u64>,
+    /// Modification time
+    pub modified: DateTime<Utc>,
+    /// Id of the file
+    pub fileid: u64,
+}
+
+impl InventoryEntry {
+    /// Header line of the CSV format
+    pub const CSV_HEADER: &'static str = "path,size,hash,modified,fileid";
+
+    /// Formats the entry as CSV line (without line break)
+    pub fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{},{}",
+            csv_field(&self.path),
+            self.size.map(|s| s.to_string()).unwrap_or_default(),
+            self.hash.map(|h| h.to_string()).unwrap_or_default(),
+            self.modified.to_rfc3339_opts(SecondsFormat::Secs, true),
+            self.fileid
+        )
+    }
+}
+
+/// Quotes a CSV field if it contains separators, quotes or line breaks
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Walks a remote folder (recursively) and writes a flat inventory of all files
+pub struct InventoryExportRequestBuilder {
+    /// Client to actually perform the request
+    client: PCloudClient,
+    /// Remote folder to export
+    folder: PCloudFolder,
+    /// Output format
+    format: InventoryFormat,
+}
+
+#[allow(dead_code)]
+impl InventoryExportRequestBuilder {
+    pub(crate) fn for_folder<T: FolderDescriptor>(
+        client: &PCloudClient,
+        folder_like: T,
+    ) -> Result<InventoryExportRequestBuilder, PCloudError> {
+        let folder = folder_like.to_folder()?;
+
+        if folder.is_empty() {
+            Err(PCloudResult::NoFileIdOrPathProvided)?
+        }
+
+        Ok(InventoryExportRequestBuilder {
+            client: client.clone(),
+            folder,
+            format: InventoryFormat::default(),
+        })
+    }
+
+    /// Output format, defaults to JSON Lines
+    pub fn format(mut self, value: InventoryFormat) -> InventoryExportRequestBuilder {
+        self.format = value;
+        self
+    }
+
+    /// Lists all files of the folder, sorted by path
+    pub async fn entries(&self) -> Result<Vec<InventoryEntry>, PCloudError> {
+        let listing = self
+            .client
+            .list_folder(&self.folder)?
+            .recursive(true)
+            .get()
+            .await?;
+        let mut entries = Vec::new();
+        if let Some(metadata) = listing.metadata {
+            collect_files(&metadata.contents, "", &mut entries);
+        }
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(entries)
+    }
+
+    /// Writes the inventory to the given writer and returns the number of files exported
+    pub async fn write_to<W: AsyncWrite + Unpin>(
+        self,
+        writer: &mut W,
+    ) -> Result<usize, PCloudError> {
+        let entries = self.entries().await?;
+        debug!(
+            "Exporting {} files of {} as {:?}",
+            entries.len(),
+            self.folder,
+            self.format
+        );
+
+        if self.format == InventoryFormat::Csv {
+            writer
+                .write_all(format!("{}\r\n", InventoryEntry::CSV_HEADER).as_bytes())
+                .await?;
+        }
+        for entry in &entries {
+            let line = match self.format {
+                InventoryFormat::JsonLines => format!("{}\n", serde_json::to_string(entry)?),
+                InventoryFormat::Csv => format!("{}\r\n", entry.to_csv()),
+            };
+            writer.write_all(line.as_bytes()).await?;
+        }
+        writer.flush().await?;
+
+        Ok(entries.len())
+    }
+}
+
+/// Collects all files of the given (recursive) folder contents with their path relative to the folder
+fn collect_files(contents: &[Metadata], prefix: &str, entries: &mut Vec<InventoryEntry>) {
+    for metadata in contents {
+        let path = if prefix.is_empty() {
+            metadata.name.clone()
+        } else {
+            format!("{}/{}", prefix, metadata.name)
+        };
+        if metadata.isfolder {
+            collect_files(&metadata.contents, &path, entries);
+        } else if let Some(fileid) = metadata.fileid {
+            entries.push(InventoryEntry {
+                path,
+                size: metadata.size,
+                hash: metadata.hash,
+                modified: metadata.modified,
+                fileid,
+            });
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Exports a flat inventory (path, size, hash, modification time, file id) of all files below a folder as JSON Lines or CSV. Accepts either a folder id (u64), a folder path (String) or any other pCloud object describing a folder (like Metadata)
+    pub fn export_inventory<T: FolderDescriptor>(
+        &self,
+        folder_like: T,
+    ) -> Result<InventoryExportRequestBuilder, PCloudError> {
+        InventoryExportRequestBuilder::for_folder(self, folder_like)
+    }
+}
@@ -1,7 +1,60 @@
+// Without a TLS backend the client builds, but can't connect to pCloud at all
+#[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+compile_error!("Either the feature \"native-tls\" or \"rustls\" must be enabled");
+
+pub mod backup;
+pub mod batch_links;
+pub mod batch_upload;
+pub mod blocking;
+mod buffer_pool;
+pub mod chunked_upload;
+pub mod collections;
+pub mod comments;
+pub mod concurrency;
+pub mod cross_account;
+pub mod decompress;
+pub mod directory_download;
+pub mod directory_upload;
+pub mod dns;
+pub mod error;
+pub mod event_bridge;
 pub mod events;
+pub mod fake_pcloud;
+pub mod file_handle;
 pub mod file_ops;
+pub mod filter;
 pub mod folder_ops;
+pub mod folder_watch;
+pub mod guard;
+pub mod interceptor;
+pub mod inventory;
 pub mod low_level_file;
+pub mod manifest;
+pub mod path_resolver;
 pub mod pcloud_client;
 pub mod pcloud_model;
+pub mod permissions;
+pub mod progress;
+pub mod proxy;
+pub mod public_download;
+pub mod rate_limit;
+pub mod raw_capture;
+mod redact;
 pub mod remote_zip;
+pub mod restore;
+pub mod retention;
+pub mod segmented_download;
+pub mod service;
+pub mod shutdown;
+pub mod sqlite_store;
+pub mod sync;
+pub mod sync_session;
+pub mod testing;
+mod throttle;
+pub mod thumbnails;
+pub mod transfer;
+pub mod transport;
+pub mod trash;
+pub mod upload_links;
+pub mod vcr;
+pub mod watch;
@@ -1,14 +1,19 @@
 #![cfg(feature = "low_level_file_ops")]
-use std::collections::HashSet;
+use std::{collections::HashSet, io::SeekFrom};
 
+use bytes::Bytes;
 use log::{debug, warn};
-use reqwest::Body;
+use reqwest::{header::CONTENT_TYPE, Body};
 
 use crate::{
-    file_ops::{FileDescriptor, PCloudFile},
+    error::PCloudError,
+    file_ops::FileDescriptor,
     folder_ops::FolderDescriptor,
     pcloud_client::PCloudClient,
-    pcloud_model::{FileCloseResponse, FileOpenResponse, FileWriteResponse, WithPCloudResult},
+    pcloud_model::{
+        FileCloseResponse, FileOpResult, FileOpenResponse, FileSeekResponse, FileSizeResponse,
+        FileWriteResponse, PCloudResult, WithPCloudResult,
+    },
 };
 
 impl PCloudClient {
@@ -57,12 +62,14 @@ impl InitiatePCloudFileOpenRequest {
     }
 
     /// Opens the file by its file id
-    pub async fn by_file_id<'a, T: FileDescriptor>(
+    pub async fn by_file_id<T: FileDescriptor>(
         self,
         file_like: T,
-    ) -> Result<PCloudFileOpenRequest, Box<dyn 'a + std::error::Error + Send + Sync>> {
-        let file = file_like.to_file()?;
-        let file_id = self.client.get_file_id(file).await?;
+    ) -> Result<PCloudFileOpenRequest, PCloudError> {
+        let file = file_like
+            .to_file()
+            .map_err(PCloudError::InvalidDescriptor)?;
+        let (file_id, _) = self.client.get_file_id(file).await?;
 
         Ok(PCloudFileOpenRequest {
             client: self.client,
@@ -87,12 +94,14 @@ impl InitiatePCloudFileOpenRequest {
     }
 
     /// Target folder and file name of the target  file
-    pub async fn by_file_in_folder<'a, T: FolderDescriptor>(
+    pub async fn by_file_in_folder<T: FolderDescriptor>(
         self,
         folder_like: T,
         file_name: &str,
-    ) -> Result<PCloudFileOpenRequest, Box<dyn 'a + std::error::Error + Send + Sync>> {
-        let folder = folder_like.to_folder()?;
+    ) -> Result<PCloudFileOpenRequest, PCloudError> {
+        let folder = folder_like
+            .to_folder()
+            .map_err(PCloudError::InvalidDescriptor)?;
         let folder_id = self.client.get_folder_id(folder).await?;
 
         Ok(PCloudFileOpenRequest {
@@ -129,11 +138,12 @@ impl PCloudFileOpenRequest {
     }
 
     /// Performs the request to open the file
-    pub async fn open(self) -> Result<OpenPCloudFile, Box<dyn std::error::Error + Send + Sync>> {
+    /// see https://docs.pcloud.com/methods/fileops/file_open.html
+    pub async fn open(self) -> Result<OpenPCloudFile, PCloudError> {
         let mut r = self
             .client
-            .client
-            .get(format!("{}/file_open", self.client.api_host));
+            .client()
+            .get(format!("{}/file_open", self.client.api_host()));
 
         let flags: u16 = self.flags.iter().map(|f| f.to_number()).sum();
 
@@ -157,12 +167,8 @@ impl PCloudFileOpenRequest {
 
         r = self.client.add_token(r);
 
-        let response = r
-            .send()
-            .await?
-            .json::<FileOpenResponse>()
-            .await?
-            .assert_ok()?;
+        let response = self.client.send_checked::<FileOpenResponse>(r).await?;
+        debug!("Opened file {} as {}", response.fileid, response.fd);
 
         let result = OpenPCloudFile {
             client: self.client,
@@ -175,7 +181,8 @@ impl PCloudFileOpenRequest {
     }
 }
 
-/// Currently does not work. Most probably because of connection pooling and: A descriptor is only valid for the same connection. If a connection closes, all the files are also closed. You can open the same file in multiple connections.
+/// File opened with `file_open`, read and written at the current offset of its descriptor or at given offsets. Closed on drop, if not closed explicitly.
+/// pCloud binds a descriptor to the connection it was opened on, all other connections fail with `InvalidFileDescriptor`. So build the client with `pool_max_idle_per_host(1)` and don't run other requests concurrently while files are open, then all requests reuse the same connection.
 pub struct OpenPCloudFile {
     /// Client to actually perform the request
     client: PCloudClient,
@@ -189,59 +196,178 @@ pub struct OpenPCloudFile {
 
 #[allow(dead_code)]
 impl OpenPCloudFile {
+    /// File descriptor
+    pub fn fd(&self) -> u64 {
+        self.fd
+    }
+
+    /// Id of the opened file
+    pub fn file_id(&self) -> u64 {
+        self.file_id
+    }
+
     /// Close the given file
-    async fn close_file(
-        client: &PCloudClient,
-        fd: u64,
-    ) -> Result<FileCloseResponse, Box<dyn std::error::Error + Send + Sync>> {
-        let mut r = client.client.get(format!("{}/file_close", client.api_host));
+    async fn close_file(client: &PCloudClient, fd: u64) -> Result<FileCloseResponse, PCloudError> {
+        let mut r = client
+            .client()
+            .get(format!("{}/file_close", client.api_host()));
 
         r = r.query(&[("fd", fd)]);
 
         r = client.add_token(r);
 
-        let result = r
-            .send()
-            .await?
-            .json::<FileCloseResponse>()
-            .await?
-            .assert_ok()?;
+        let result = client.send_checked::<FileCloseResponse>(r).await?;
 
         Ok(result)
     }
 
-    /// Close this file (Called by drop)
-    async fn close(
-        mut self,
-    ) -> Result<FileCloseResponse, Box<dyn std::error::Error + Send + Sync>> {
-        let result = Self::close_file(&self.client, self.fd).await?;
+    /// Closes this file (otherwise called by drop)
+    /// see https://docs.pcloud.com/methods/fileops/file_close.html
+    pub async fn close(mut self) -> Result<FileCloseResponse, PCloudError> {
         self.open = false;
+        Self::close_file(&self.client, self.fd).await
+    }
+
+    /// Writes the content at the current offset and moves the offset past it (or to the end of the file, if opened with `APPEND`)
+    /// see https://docs.pcloud.com/methods/fileops/file_write.html
+    pub async fn write<T: Into<Body>>(&self, body: T) -> Result<FileWriteResponse, PCloudError> {
+        let mut r = self
+            .client
+            .client()
+            .put(format!("{}/file_write", self.client.api_host()));
+        r = r.query(&[("fd", self.fd)]);
+
+        r = self.client.add_token(r);
+
+        let result = self
+            .client
+            .send_checked::<FileWriteResponse>(r.body(body))
+            .await?;
+
         Ok(result)
     }
 
-    /// Write content to file
-    pub async fn write<T: Into<Body>>(
+    /// Writes the content at the given offset, without changing the current offset
+    /// see https://docs.pcloud.com/methods/fileops/file_pwrite.html
+    pub async fn write_at<T: Into<Body>>(
         &self,
+        offset: u64,
         body: T,
-    ) -> Result<FileWriteResponse, Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<FileWriteResponse, PCloudError> {
+        let mut r = self
+            .client
+            .client()
+            .put(format!("{}/file_pwrite", self.client.api_host()));
+        r = r.query(&[("fd", self.fd)]);
+        r = r.query(&[("offset", offset)]);
+
+        r = self.client.add_token(r);
+
+        let result = self
+            .client
+            .send_checked::<FileWriteResponse>(r.body(body))
+            .await?;
+
+        Ok(result)
+    }
+
+    /// Reads up to the given number of bytes at the current offset and moves the offset past them. Returns fewer bytes at the end of the file.
+    /// see https://docs.pcloud.com/methods/fileops/file_read.html
+    pub async fn read(&self, count: u64) -> Result<Bytes, PCloudError> {
+        let mut r = self
+            .client
+            .client()
+            .get(format!("{}/file_read", self.client.api_host()));
+        r = r.query(&[("fd", self.fd)]);
+        r = r.query(&[("count", count)]);
+
+        self.read_content(r).await
+    }
+
+    /// Reads up to the given number of bytes at the given offset, without changing the current offset
+    /// see https://docs.pcloud.com/methods/fileops/file_pread.html
+    pub async fn read_at(&self, offset: u64, count: u64) -> Result<Bytes, PCloudError> {
         let mut r = self
             .client
+            .client()
+            .get(format!("{}/file_pread", self.client.api_host()));
+        r = r.query(&[("fd", self.fd)]);
+        r = r.query(&[("count", count)]);
+        r = r.query(&[("offset", offset)]);
+
+        self.read_content(r).await
+    }
+
+    /// Performs a read request. The content is returned as is, failures as JSON result.
+    async fn read_content(&self, mut r: reqwest::RequestBuilder) -> Result<Bytes, PCloudError> {
+        r = self.client.add_token(r);
+
+        let response = self.client.send(r).await?.error_for_status()?;
+        let is_json = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("application/json"));
+        let content = response.bytes().await?;
+        if is_json {
+            serde_json::from_slice::<FileOpResult>(&content)?.assert_ok()?;
+        }
+        Ok(content)
+    }
+
+    /// Moves the current offset, returns the new offset
+    /// see https://docs.pcloud.com/methods/fileops/file_seek.html
+    pub async fn seek(&self, position: SeekFrom) -> Result<u64, PCloudError> {
+        let (offset, whence) = match position {
+            SeekFrom::Start(offset) => (offset as i64, 0),
+            SeekFrom::Current(offset) => (offset, 1),
+            SeekFrom::End(offset) => (offset, 2),
+        };
+
+        let mut r = self
             .client
-            .post(format!("{}/file_write", self.client.api_host));
+            .client()
+            .get(format!("{}/file_seek", self.client.api_host()));
         r = r.query(&[("fd", self.fd)]);
+        r = r.query(&[("offset", offset)]);
+        r = r.query(&[("whence", whence)]);
 
         r = self.client.add_token(r);
 
-        let part = reqwest::multipart::Part::stream(body);
-        let form = reqwest::multipart::Form::new().part("files", part);
+        let result = self.client.send_checked::<FileSeekResponse>(r).await?;
+
+        Ok(result.offset.ok_or(PCloudResult::InternalError)?)
+    }
+
+    /// Truncates (or extends) the file to the given length
+    /// see https://docs.pcloud.com/methods/fileops/file_truncate.html
+    pub async fn truncate(&self, length: u64) -> Result<(), PCloudError> {
+        let mut r = self
+            .client
+            .client()
+            .get(format!("{}/file_truncate", self.client.api_host()));
+        r = r.query(&[("fd", self.fd)]);
+        r = r.query(&[("length", length)]);
+
+        r = self.client.add_token(r);
+
+        self.client.send_checked::<FileOpResult>(r).await?;
+
+        Ok(())
+    }
+
+    /// Requests the size of the file and the current offset
+    /// see https://docs.pcloud.com/methods/fileops/file_size.html
+    pub async fn size(&self) -> Result<FileSizeResponse, PCloudError> {
+        let mut r = self
+            .client
+            .client()
+            .get(format!("{}/file_size", self.client.api_host()));
+        r = r.query(&[("fd", self.fd)]);
+
+        r = self.client.add_token(r);
 
-        let result = r
-            .multipart(form)
-            .send()
-            .await?
-            .json::<FileWriteResponse>()
-            .await?
-            .assert_ok()?;
+        let result = self.client.send_checked::<FileSizeResponse>(r).await?;
 
         Ok(result)
     }
@@ -251,8 +377,8 @@ impl Drop for OpenPCloudFile {
     fn drop(&mut self) {
         if self.open {
             let client = self.client.clone();
-            let fd = self.fd.clone();
-            let file_id = self.file_id.clone();
+            let fd = self.fd;
+            let file_id = self.file_id;
 
             let op = tokio::spawn(async move {
                 match Self::close_file(&client, fd).await {
@@ -0,0 +1,308 @@
+use futures::StreamExt;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::PCloudError,
+    folder_ops::{FolderDescriptor, PCloudFolder},
+    pcloud_client::PCloudClient,
+    pcloud_model::{Metadata, PCloudResult},
+};
+
+/// Default name of the manifest stored in the folder it describes
+pub const DEFAULT_MANIFEST_NAME: &str = ".pcloud-manifest.json";
+
+/// Default number of checksums requested in parallel
+pub const DEFAULT_MANIFEST_CONCURRENCY: usize = 8;
+
+/// Checksums of a single file of a manifest
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ManifestEntry {
+    /// Path relative to the folder, separated by '/'
+    pub path: String,
+    /// Size in bytes
+    pub size: u64,
+    /// SHA-1 checksum (hex)
+    pub sha1: String,
+    /// SHA-256 checksum (hex), only provided by EU API servers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+/// Deterministic manifest of all files below a folder: the checksums of each file, sorted by path, and an aggregate digest over all of them. The same content always results in the same manifest.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct IntegrityManifest {
+    /// SHA-256 (hex) over all entries, see `IntegrityManifest::compute_digest()`
+    pub digest: String,
+    /// All files, sorted by path
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl IntegrityManifest {
+    /// Creates a manifest of the given entries
+    pub fn new(mut entries: Vec<ManifestEntry>) -> IntegrityManifest {
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        IntegrityManifest {
+            digest: IntegrityManifest::compute_digest(&entries),
+            entries,
+        }
+    }
+
+    /// Computes the aggregate digest: SHA-256 over one line `path \t size \t sha1 \t sha256 \n` per entry, in the given order
+    pub fn compute_digest(entries: &[ManifestEntry]) -> String {
+        let mut hasher = Sha256::new();
+        for entry in entries {
+            hasher.update(
+                format!(
+                    "{}\t{}\t{}\t{}\n",
+                    entry.path,
+                    entry.size,
+                    entry.sha1.to_lowercase(),
+                    entry.sha256.as_deref().unwrap_or_default().to_lowercase()
+                )
+                .as_bytes(),
+            );
+        }
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// Checks if the digest matches the entries, i.e. the manifest itself wasn't modified
+    pub fn is_consistent(&self) -> bool {
+        self.digest == IntegrityManifest::compute_digest(&self.entries)
+    }
+
+    /// Compares the current state of a folder (`current`) against this manifest
+    pub fn compare(&self, current: &IntegrityManifest) -> ManifestVerification {
+        let mut verification = ManifestVerification {
+            manifest_consistent: self.is_consistent(),
+            ..Default::default()
+        };
+        let mut expected = self.entries.iter().peekable();
+        let mut actual = current.entries.iter().peekable();
+        loop {
+            match (expected.peek(), actual.peek()) {
+                (Some(e), Some(a)) if e.path == a.path => {
+                    if !same_content(e, a) {
+                        verification.mismatched.push(e.path.clone());
+                    }
+                    expected.next();
+                    actual.next();
+                }
+                (Some(e), Some(a)) if e.path < a.path => {
+                    verification.missing.push(e.path.clone());
+                    expected.next();
+                }
+                (Some(_), Some(a)) | (None, Some(a)) => {
+                    verification.added.push(a.path.clone());
+                    actual.next();
+                }
+                (Some(e), None) => {
+                    verification.missing.push(e.path.clone());
+                    expected.next();
+                }
+                (None, None) => break,
+            }
+        }
+        verification
+    }
+}
+
+/// Compares the checksums of two entries. SHA-256 is only compared if both have it.
+fn same_content(expected: &ManifestEntry, actual: &ManifestEntry) -> bool {
+    expected.size == actual.size
+        && expected.sha1.eq_ignore_ascii_case(&actual.sha1)
+        && match (&expected.sha256, &actual.sha256) {
+            (Some(e), Some(a)) => e.eq_ignore_ascii_case(a),
+            _ => true,
+        }
+}
+
+/// Outcome of verifying a folder against its manifest
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ManifestVerification {
+    /// If not set, the digest of the manifest doesn't match its entries
+    pub manifest_consistent: bool,
+    /// Files of the manifest not present anymore
+    pub missing: Vec<String>,
+    /// Files present but not in the manifest
+    pub added: Vec<String>,
+    /// Files whose size or checksums differ from the manifest
+    pub mismatched: Vec<String>,
+}
+
+impl ManifestVerification {
+    /// Checks if the folder matches the manifest exactly
+    pub fn is_ok(&self) -> bool {
+        self.manifest_consistent
+            && self.missing.is_empty()
+            && self.added.is_empty()
+            && self.mismatched.is_empty()
+    }
+}
+
+/// Creates, stores and verifies integrity manifests of a remote folder
+pub struct IntegrityManifestRequestBuilder {
+    /// Client to actually perform the requests
+    client: PCloudClient,
+    /// Folder described by the manifest
+    folder: PCloudFolder,
+    /// Name of the manifest stored in the folder
+    manifest_name: String,
+    /// Maximum number of checksums requested in parallel
+    concurrency: usize,
+}
+
+#[allow(dead_code)]
+impl IntegrityManifestRequestBuilder {
+    pub(crate) fn for_folder<T: FolderDescriptor>(
+        client: &PCloudClient,
+        folder_like: T,
+    ) -> Result<IntegrityManifestRequestBuilder, PCloudError> {
+        let folder = folder_like.to_folder()?;
+
+        if folder.is_empty() {
+            Err(PCloudResult::NoFullPathOrFolderIdProvided)?
+        }
+
+        Ok(IntegrityManifestRequestBuilder {
+            client: client.clone(),
+            folder,
+            manifest_name: DEFAULT_MANIFEST_NAME.to_string(),
+            concurrency: DEFAULT_MANIFEST_CONCURRENCY,
+        })
+    }
+
+    /// Name of the manifest stored in the folder (defaults to `.pcloud-manifest.json`). It is never part of the manifest itself.
+    pub fn manifest_name(mut self, value: &str) -> IntegrityManifestRequestBuilder {
+        self.manifest_name = value.to_string();
+        self
+    }
+
+    /// Maximum number of checksums requested in parallel (defaults to 8)
+    pub fn concurrency(mut self, value: usize) -> IntegrityManifestRequestBuilder {
+        self.concurrency = value.max(1);
+        self
+    }
+
+    /// Walks the folder and requests the checksums of all files. The contents aren't downloaded, pCloud provides the checksums.
+    pub async fn create(&self) -> Result<IntegrityManifest, PCloudError> {
+        let metadata = self.listing().await?;
+        self.manifest_of(&metadata).await
+    }
+
+    /// Creates the manifest and stores it in the folder, replacing a previous one
+    pub async fn store(&self) -> Result<IntegrityManifest, PCloudError> {
+        let manifest = self.create().await?;
+        self.client
+            .upload_file_into_folder(&self.folder)?
+            .with_file(&self.manifest_name, serde_json::to_vec_pretty(&manifest)?)
+            .upload()
+            .await?;
+        debug!(
+            "Stored manifest of {} files in {} with digest {}",
+            manifest.entries.len(),
+            self.folder,
+            manifest.digest
+        );
+        Ok(manifest)
+    }
+
+    /// Verifies the folder against the manifest stored in it
+    pub async fn verify(&self) -> Result<ManifestVerification, PCloudError> {
+        let metadata = self.listing().await?;
+        let stored = metadata
+            .contents
+            .iter()
+            .find(|m| !m.isfolder && m.name == self.manifest_name)
+            .and_then(|m| m.fileid)
+            .ok_or(PCloudResult::FileNotFound)?;
+        let content = self
+            .client
+            .download_file(stored)
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        let expected: IntegrityManifest = serde_json::from_slice(&content)?;
+        Ok(expected.compare(&self.manifest_of(&metadata).await?))
+    }
+
+    /// Verifies the folder against the given manifest, e.g. one kept outside of pCloud
+    pub async fn verify_against(
+        &self,
+        manifest: &IntegrityManifest,
+    ) -> Result<ManifestVerification, PCloudError> {
+        Ok(manifest.compare(&self.create().await?))
+    }
+
+    /// Lists the folder recursively
+    async fn listing(&self) -> Result<Metadata, PCloudError> {
+        Ok(self
+            .client
+            .list_folder(&self.folder)?
+            .recursive(true)
+            .get()
+            .await?
+            .metadata
+            .ok_or(PCloudResult::DirectoryDoesNotExist)?)
+    }
+
+    /// Requests the checksums of all files of the listing, except the stored manifest
+    async fn manifest_of(&self, metadata: &Metadata) -> Result<IntegrityManifest, PCloudError> {
+        let mut files = Vec::new();
+        collect_files(&metadata.contents, "", &mut files);
+        files.retain(|(path, _, _)| *path != self.manifest_name);
+        debug!(
+            "Requesting checksums of {} files in {}",
+            files.len(),
+            self.folder
+        );
+
+        let client = &self.client;
+        let mut checksums = futures::stream::iter(files)
+            .map(|(path, fileid, size)| async move {
+                let checksums = client.checksum_file(fileid)?.get().await?;
+                Ok::<_, PCloudError>(ManifestEntry {
+                    path,
+                    size,
+                    sha1: checksums.sha1.ok_or(PCloudError::MissingChecksum)?,
+                    sha256: checksums.sha256,
+                })
+            })
+            .buffer_unordered(self.concurrency);
+
+        let mut entries = Vec::new();
+        while let Some(entry) = checksums.next().await {
+            entries.push(entry?);
+        }
+        Ok(IntegrityManifest::new(entries))
+    }
+}
+
+/// Collects all files (path, file id, size) of the given (recursive) folder contents with their path relative to the folder
+fn collect_files(contents: &[Metadata], prefix: &str, files: &mut Vec<(String, u64, u64)>) {
+    for metadata in contents {
+        let path = if prefix.is_empty() {
+            metadata.name.clone()
+        } else {
+            format!("{}/{}", prefix, metadata.name)
+        };
+        if metadata.isfolder {
+            collect_files(&metadata.contents, &path, files);
+        } else if let Some(fileid) = metadata.fileid {
+            files.push((path, fileid, metadata.size.unwrap_or_default()));
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Creates, stores or verifies a deterministic integrity manifest (per-file checksums and an aggregate digest) of all files below a folder, to detect silent corruption or tampering. Accepts either a folder id (u64), a folder path (String) or any other pCloud object describing a folder (like Metadata)
+    pub fn integrity_manifest<T: FolderDescriptor>(
+        &self,
+        folder_like: T,
+    ) -> Result<IntegrityManifestRequestBuilder, PCloudError> {
+        IntegrityManifestRequestBuilder::for_folder(self, folder_like)
+    }
+}
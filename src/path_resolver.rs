@@ -0,0 +1,170 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use log::debug;
+
+use crate::{
+    error::PCloudError,
+    pcloud_client::PCloudClient,
+    pcloud_model::{Metadata, PCloudResult},
+};
+
+/// Id of the root folder
+const ROOT_FOLDER_ID: u64 = 0;
+
+/// Reconstructs the absolute paths of files and folders by walking up their `parentfolderid` chains. Recursive listings and diff events often omit the `path`, which pCloud only provides for objects requested by path.
+/// The paths of all folders passed on the way are cached, so resolving many entries of the same tree only requests each folder once. All clones share the same cache.
+#[derive(Clone)]
+pub struct PathResolver {
+    /// Client to actually perform the requests
+    client: PCloudClient,
+    /// Absolute paths of the folders resolved so far by folder id
+    folders: Arc<Mutex<HashMap<u64, String>>>,
+}
+
+impl PathResolver {
+    pub(crate) fn new(client: &PCloudClient) -> PathResolver {
+        let mut folders = HashMap::new();
+        folders.insert(ROOT_FOLDER_ID, "/".to_string());
+        PathResolver {
+            client: client.clone(),
+            folders: Arc::new(Mutex::new(folders)),
+        }
+    }
+
+    /// Resolves the path of a file or folder by its unique string id ('f' + file id or 'd' + folder id, see `Metadata::id`)
+    pub async fn resolve(&self, id: &str) -> Result<String, PCloudError> {
+        let number = |value: &str| {
+            value
+                .parse::<u64>()
+                .map_err(|_| PCloudError::InvalidId(id.to_string()))
+        };
+        if let Some(folder_id) = id.strip_prefix('d') {
+            self.resolve_folder(number(folder_id)?).await
+        } else if let Some(file_id) = id.strip_prefix('f') {
+            self.resolve_file(number(file_id)?).await
+        } else {
+            Err(PCloudError::InvalidId(id.to_string()))?
+        }
+    }
+
+    /// Resolves the path of a folder by its id
+    pub async fn resolve_folder(&self, folder_id: u64) -> Result<String, PCloudError> {
+        // Walk up until a folder with known path is reached
+        let mut chain = Vec::new();
+        let mut current = folder_id;
+        let mut base = loop {
+            if let Some(path) = self.cached(current) {
+                break path;
+            }
+
+            let metadata = self
+                .client
+                .list_folder(current)?
+                .nofiles(true)
+                .folder_metadata()
+                .await?;
+            if let Some(path) = &metadata.path {
+                self.remember(current, path);
+                break path.clone();
+            }
+            chain.push((current, metadata.name));
+            current = metadata
+                .parentfolderid
+                .ok_or(PCloudResult::DirectoryDoesNotExist)?;
+        };
+
+        debug!(
+            "Resolved {} parent folders of folder {}",
+            chain.len(),
+            folder_id
+        );
+        for (id, name) in chain.into_iter().rev() {
+            base = join(&base, &name);
+            self.remember(id, &base);
+        }
+        Ok(base)
+    }
+
+    /// Resolves the path of a file by its id
+    pub async fn resolve_file(&self, file_id: u64) -> Result<String, PCloudError> {
+        let metadata = self
+            .client
+            .get_file_metadata(file_id)
+            .await?
+            .metadata
+            .ok_or(PCloudResult::FileNotFound)?;
+        self.resolve_metadata(&metadata).await
+    }
+
+    /// Resolves the path of a file or folder from its metadata. Uses the `path` if present, otherwise only the parent folders are requested.
+    pub async fn resolve_metadata(&self, metadata: &Metadata) -> Result<String, PCloudError> {
+        if let Some(path) = &metadata.path {
+            if let Some(folder_id) = metadata.folderid.filter(|_| metadata.isfolder) {
+                self.remember(folder_id, path);
+            }
+            return Ok(path.clone());
+        }
+        if metadata.isfolder && metadata.folderid == Some(ROOT_FOLDER_ID) {
+            return Ok("/".to_string());
+        }
+
+        let parent_id = metadata
+            .parentfolderid
+            .ok_or(PCloudResult::DirectoryDoesNotExist)?;
+        let path = join(&self.resolve_folder(parent_id).await?, &metadata.name);
+        if let Some(folder_id) = metadata.folderid.filter(|_| metadata.isfolder) {
+            self.remember(folder_id, &path);
+        }
+        Ok(path)
+    }
+
+    /// Caches the paths of the folder and all subfolders of a (recursive) listing, like one of `list_folder()` by path. Later lookups of its entries don't need any request.
+    pub fn remember_listing(&self, metadata: &Metadata, path: &str) {
+        if let Some(folder_id) = metadata.folderid.filter(|_| metadata.isfolder) {
+            self.remember(folder_id, path);
+        }
+        for entry in metadata.contents.iter().filter(|m| m.isfolder) {
+            self.remember_listing(entry, &join(path, &entry.name));
+        }
+    }
+
+    /// Forgets all cached paths, e.g. after folders were moved or renamed
+    pub fn clear(&self) {
+        let mut folders = self.folders.lock().unwrap();
+        folders.retain(|id, _| *id == ROOT_FOLDER_ID);
+    }
+
+    /// Cached path of the folder, if already resolved
+    fn cached(&self, folder_id: u64) -> Option<String> {
+        self.folders.lock().unwrap().get(&folder_id).cloned()
+    }
+
+    /// Caches the path of the folder
+    fn remember(&self, folder_id: u64, path: &str) {
+        self.folders
+            .lock()
+            .unwrap()
+            .insert(folder_id, path.to_string());
+    }
+}
+
+/// Appends the name to the folder path
+fn join(folder: &str, name: &str) -> String {
+    format!("{}/{}", folder.trim_end_matches('/'), name)
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Creates a PathResolver, caching the paths of all folders resolved with it
+    pub fn path_resolver(&self) -> PathResolver {
+        PathResolver::new(self)
+    }
+
+    /// Reconstructs the absolute path of a file or folder by its unique string id ('f' + file id or 'd' + folder id, see `Metadata::id`) by walking up its parent folders. Use a `path_resolver()` to resolve many paths.
+    pub async fn resolve_path(&self, id: &str) -> Result<String, PCloudError> {
+        self.path_resolver().resolve(id).await
+    }
+}
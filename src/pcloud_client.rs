@@ -1,25 +1,241 @@
-use crate::pcloud_model::{self, PCloudResult, UserInfo, WithPCloudResult};
+use std::{
+    fmt::Display,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    buffer_pool::{BufferPool, DEFAULT_TRANSFER_CHUNK_SIZE},
+    dns::{CachingResolver, IpPreference},
+    error::PCloudError,
+    guard::GuardRails,
+    interceptor::Interceptor,
+    pcloud_model::{
+        self, CurrentServer, PCloudResult, TokenList, TokenValidation, UserInfo, WithPCloudResult,
+    },
+    rate_limit::{limit, RateLimiter},
+    raw_capture::{DeserializationError, RawResponse, RawResponseLog},
+    redact::{redact_body, redact_url},
+    shutdown::{ShutDown, ShutdownSignal},
+    thumbnails::ThumbnailCache,
+    transport::HttpTransport,
+};
+use chrono::{DateTime, Utc};
 use log::{debug, warn};
-use reqwest::{Client, RequestBuilder};
+use reqwest::{header::HeaderName, Client, Request, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+
+/// Default header to propagate the correlation id
+pub const DEFAULT_CORRELATION_HEADER: &str = "X-Request-Id";
+
+/// Callback generating a new correlation id for each request
+type CorrelationIdGenerator = Arc<dyn Fn() -> String + Send + Sync>;
+
+/// Wraps a transport, e.g. into a tower service stack
+#[cfg(feature = "tower")]
+type TransportWrapper = Box<dyn FnOnce(Arc<dyn HttpTransport>) -> Arc<dyn HttpTransport> + Send>;
+
+/// Error of a request sent with a correlation id. Wraps the original error.
+#[derive(Debug)]
+pub struct CorrelatedError {
+    /// Correlation id sent with the failed request
+    pub correlation_id: String,
+    /// Original error
+    pub source: PCloudError,
+}
+
+impl Display for CorrelatedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Request {} failed: {}", self.correlation_id, self.source)
+    }
+}
+
+impl std::error::Error for CorrelatedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Correlation id of a request, attached to the extensions of its response
+#[derive(Debug, Clone)]
+struct CorrelationId(String);
+
+/// Wraps the error of a request into a `CorrelatedError`, if the request was sent with a correlation id
+fn correlate(correlation_id: Option<String>, source: PCloudError) -> PCloudError {
+    match correlation_id {
+        Some(correlation_id) => {
+            warn!("Request {} failed: {}", correlation_id, source);
+            CorrelatedError {
+                correlation_id,
+                source,
+            }
+            .into()
+        }
+        None => source,
+    }
+}
+
+/// Preference of the download hosts returned with each download link (see `PCloudClientBuilder::download_hosts()`), e.g. to only download from EU data centers for compliance
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownloadHostPolicy {
+    /// Parts of the preferred host names (like `lux` or `ams`)
+    patterns: Vec<String>,
+    /// If set, other hosts are never used
+    strict: bool,
+}
+
+impl DownloadHostPolicy {
+    /// Hosts containing one of the given patterns are tried first (in the order returned by pCloud), all other hosts only if the preferred ones fail
+    pub fn prefer<I: IntoIterator<Item = S>, S: Into<String>>(patterns: I) -> DownloadHostPolicy {
+        DownloadHostPolicy {
+            patterns: patterns.into_iter().map(|p| p.into()).collect(),
+            strict: false,
+        }
+    }
+
+    /// Only hosts containing one of the given patterns are used. If pCloud returns no such host, the download fails with `PCloudResult::ProvideURL`.
+    pub fn pin<I: IntoIterator<Item = S>, S: Into<String>>(patterns: I) -> DownloadHostPolicy {
+        DownloadHostPolicy {
+            patterns: patterns.into_iter().map(|p| p.into()).collect(),
+            strict: true,
+        }
+    }
+
+    /// Checks if the given host is one of the preferred hosts
+    pub fn matches(&self, host: &str) -> bool {
+        self.patterns.iter().any(|p| host.contains(p.as_str()))
+    }
+
+    /// Orders the given hosts by preference. Preferred hosts keep their order, for pinned hosts all others are removed.
+    pub fn order<'h>(&self, hosts: &'h [String]) -> Vec<&'h String> {
+        let (mut preferred, others): (Vec<&String>, Vec<&String>) =
+            hosts.iter().partition(|h| self.matches(h));
+        if !self.strict {
+            preferred.extend(others);
+        }
+        preferred
+    }
+}
+
+/// Data region of a pCloud account. Each account lives in exactly one region and is only accepted by its API hosts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Region {
+    /// United States, served by `api.pcloud.com`
+    Us,
+    /// Europe (Luxembourg), served by `eapi.pcloud.com`
+    Eu,
+}
+
+impl Region {
+    /// Default API host of the region
+    pub fn api_host(&self) -> &'static str {
+        match self {
+            Region::Us => "https://api.pcloud.com",
+            Region::Eu => "https://eapi.pcloud.com",
+        }
+    }
 
+    /// Determines the region of a pCloud API host name or url, like the `hostname` returned by the OAuth 2.0 authorization or a host selected by `getapiserver` (`eapi-ams1.pcloud.com`). Returns None for other hosts.
+    pub fn from_hostname(hostname: &str) -> Option<Region> {
+        let host = hostname
+            .trim_start_matches("https://")
+            .trim_start_matches("http://");
+        let host = host.split(['/', ':']).next().unwrap_or_default();
+        let name = host.strip_suffix(".pcloud.com")?;
+        if name.starts_with("eapi") || name.starts_with("bineapi") {
+            Some(Region::Eu)
+        } else if name.starts_with("api") || name.starts_with("binapi") {
+            Some(Region::Us)
+        } else {
+            None
+        }
+    }
+
+    /// Determines the region of the `locationid` returned by the OAuth 2.0 authorization (1 for US, 2 for EU)
+    pub fn from_location_id(location_id: u64) -> Option<Region> {
+        match location_id {
+            1 => Some(Region::Us),
+            2 => Some(Region::Eu),
+            _ => None,
+        }
+    }
+
+    /// The respective other region
+    fn other(&self) -> Region {
+        match self {
+            Region::Us => Region::Eu,
+            Region::Eu => Region::Us,
+        }
+    }
+}
+
+impl Display for Region {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Region::Us => write!(f, "US"),
+            Region::Eu => write!(f, "EU"),
+        }
+    }
+}
+
+/// Client for the pCloud API. Clones are cheap and share the configuration, the session and all other state.
 #[derive(Clone)]
 pub struct PCloudClient {
-    pub(crate) api_host: String,
-    pub(crate) client: reqwest::Client,
-    /// Session auth token (not the OAuth2 token, which is set as default header). Common for all copies of this PCloudClient
-    session_token: std::sync::Arc<Option<PCloudClientSession>>,
+    /// Configuration and state common for all copies of this PCloudClient
+    inner: Arc<ClientInner>,
+}
+
+/// Configuration and state of a PCloudClient, shared by all its clones
+struct ClientInner {
+    /// Host to connect to pCloud API
+    api_host: String,
+    /// Client to build the requests
+    client: reqwest::Client,
+    /// Transport to finally execute the requests
+    transport: Arc<dyn HttpTransport>,
+    /// Interceptors invoked for every request and response
+    interceptors: Vec<Arc<dyn Interceptor>>,
+    /// Header and generator of the correlation id added to each request
+    correlation_id: Option<(HeaderName, CorrelationIdGenerator)>,
+    /// Raw JSON of the last responses, if enabled
+    raw_responses: Option<Mutex<RawResponseLog>>,
+    /// OAuth2 token, added as bearer authorization header
+    oauth2_token: Option<String>,
+    /// Session auth token (not the OAuth2 token), logged out when the last copy of the PCloudClient is dropped
+    session: Option<PCloudClientSession>,
+    /// Preference of the download hosts, if any
+    download_hosts: Option<DownloadHostPolicy>,
+    /// Safety limits for deletes and moves, if enabled
+    guard_rails: Option<GuardRails>,
+    /// Buffers reused by the streaming transfers
+    buffer_pool: BufferPool,
+    /// Request rate and bandwidth limits shared by all clones
+    rate_limiter: RateLimiter,
+    /// On-disk cache of thumbnails, if enabled
+    thumbnail_cache: Option<ThumbnailCache>,
+    /// Shutdown of the client and everything created from it
+    shutdown: ShutdownSignal,
+    /// If set, the client was shut down and rejects all requests
+    closed: AtomicBool,
 }
 
 /// Contains the client session opened on login (not necessary for oauth2 sessions)
 /// Due to drop implementation, logout automatically happens once the sessions drops
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 struct PCloudClientSession {
-    /// Auth token (not the OAuth2 token, which is set as default header)
+    /// Auth token (not the OAuth2 token, which is set as header)
     token: String,
     /// Host to connect to pCloud API
     api_host: String,
-    /// Client to connect
+    /// Client to build the requests
     client: reqwest::Client,
+    /// Transport to execute the requests
+    transport: Arc<dyn HttpTransport>,
+    /// If set, the token was already logged out by a shutdown
+    logged_out: Arc<AtomicBool>,
 }
 
 impl PCloudClientSession {
@@ -34,12 +250,16 @@ impl PCloudClientSession {
 impl Drop for PCloudClientSession {
     /// Drop the aquired session token
     fn drop(&mut self) {
+        if self.logged_out.load(Ordering::SeqCst) {
+            return;
+        }
         let client = self.client.clone();
+        let transport = self.transport.clone();
         let api_host = self.api_host.clone();
         let token = self.token.clone();
 
         let op = tokio::spawn(async move {
-            let result = PCloudClient::logout(&client, &api_host, &token).await;
+            let result = PCloudClient::logout(&client, transport.as_ref(), &api_host, &token).await;
 
             match result {
                 Ok(v) => {
@@ -61,76 +281,498 @@ impl Drop for PCloudClientSession {
     }
 }
 
-#[allow(dead_code)]
-impl PCloudClient {
-    /// Creates a new PCloudClient instance with an already present OAuth 2.0 authentication token. Automatically determines nearest API server for best performance
-    pub async fn with_oauth(
-        host: &str,
-        oauth2: &str,
-    ) -> Result<PCloudClient, Box<dyn std::error::Error + Send + Sync>> {
-        let builder = reqwest::ClientBuilder::new();
+/// Builder to configure and create a PCloudClient
+pub struct PCloudClientBuilder {
+    /// Host to connect to pCloud API. Either one of the default api servers or any custom API base url
+    host: String,
+    /// OAuth 2.0 token, set as bearer authorization header
+    oauth2: Option<String>,
+    /// Username and password to obtain a temporary session auth token
+    credentials: Option<(String, String)>,
+    /// If set (default), the nearest API server is determined using `getapiserver`. Otherwise the host is used as is.
+    select_best_api_server: bool,
+    /// If set, the region of the account is determined by probing the default API hosts of both regions
+    detect_region: bool,
+    /// Custom transport to execute the requests
+    transport: Option<Arc<dyn HttpTransport>>,
+    /// Interceptors invoked for every request and response
+    interceptors: Vec<Arc<dyn Interceptor>>,
+    /// Header and generator of the correlation id added to each request
+    correlation_id: Option<(String, CorrelationIdGenerator)>,
+    /// Number of raw responses to retain for debugging
+    capture_raw_responses: usize,
+    /// Preference of the download hosts, if any
+    download_hosts: Option<DownloadHostPolicy>,
+    /// Safety limits for deletes and moves, if enabled
+    guard_rails: Option<GuardRails>,
+    /// If set, a connection to the API host is established while building
+    preconnect: bool,
+    /// Download hosts to establish a connection to while building
+    preconnect_download_hosts: Vec<String>,
+    /// Time to cache resolved host addresses, if enabled
+    dns_cache_ttl: Option<Duration>,
+    /// IP address family used to connect to pCloud
+    ip_preference: IpPreference,
+    /// Size of the chunks local files are read in for streaming transfers
+    transfer_chunk_size: usize,
+    /// Request rate and bandwidth limits
+    rate_limiter: RateLimiter,
+    /// On-disk cache of thumbnails, if enabled
+    thumbnail_cache: Option<ThumbnailCache>,
+    /// Interval of TCP keepalive probes on idle connections, if enabled
+    tcp_keepalive: Option<Duration>,
+    /// Timeout of whole requests, if any
+    timeout: Option<Duration>,
+    /// Timeout of establishing connections, if any
+    connect_timeout: Option<Duration>,
+    /// User agent sent with each request, if set
+    user_agent: Option<String>,
+    /// Proxy all requests are sent through, if set
+    proxy: Option<reqwest::Proxy>,
+    /// Maximum number of idle connections kept per host, if limited
+    pool_max_idle_per_host: Option<usize>,
+    /// Tower layers wrapping the transport, innermost first
+    #[cfg(feature = "tower")]
+    layers: Vec<TransportWrapper>,
+}
 
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert(
-            "Authorization",
-            reqwest::header::HeaderValue::from_str(format!("Bearer {}", oauth2).as_str()).unwrap(),
-        );
+impl PCloudClientBuilder {
+    /// Creates a new PCloudClientBuilder for the given host
+    pub(crate) fn for_host(host: &str) -> PCloudClientBuilder {
+        PCloudClientBuilder {
+            // All request urls are built as {host}/{method}
+            host: host.trim_end_matches('/').to_string(),
+            oauth2: None,
+            credentials: None,
+            select_best_api_server: true,
+            detect_region: false,
+            transport: None,
+            interceptors: Vec::new(),
+            correlation_id: None,
+            capture_raw_responses: 0,
+            download_hosts: None,
+            guard_rails: None,
+            preconnect: false,
+            preconnect_download_hosts: Vec::new(),
+            dns_cache_ttl: None,
+            ip_preference: IpPreference::Any,
+            transfer_chunk_size: DEFAULT_TRANSFER_CHUNK_SIZE,
+            rate_limiter: RateLimiter::new(),
+            thumbnail_cache: None,
+            tcp_keepalive: None,
+            timeout: None,
+            connect_timeout: None,
+            user_agent: None,
+            proxy: None,
+            pool_max_idle_per_host: None,
+            #[cfg(feature = "tower")]
+            layers: Vec::new(),
+        }
+    }
 
-        let client = builder.default_headers(headers).build().unwrap();
+    /// Uses an already present OAuth 2.0 authentication token
+    pub fn with_oauth(mut self, oauth2: &str) -> PCloudClientBuilder {
+        self.oauth2 = Some(oauth2.to_string());
+        self.credentials = None;
+        self
+    }
 
-        let best_host = PCloudClient::get_best_api_server(&client, host, None).await?;
+    /// Uses username and password to obtain a temporary auth token. Token is shared between all clones of the client and revoked when the last instance is dropped.
+    pub fn with_username_and_password(
+        mut self,
+        username: &str,
+        password: &str,
+    ) -> PCloudClientBuilder {
+        self.credentials = Some((username.to_string(), password.to_string()));
+        self.oauth2 = None;
+        self
+    }
 
-        Ok(PCloudClient {
-            api_host: best_host,
-            client: client,
-            session_token: std::sync::Arc::new(None),
+    /// If set (default true), the nearest API server is determined using `getapiserver`. Disable it to use the given host as is, e.g. for a local mock server or a corporate gateway.
+    pub fn select_best_api_server(mut self, value: bool) -> PCloudClientBuilder {
+        self.select_best_api_server = value;
+        self
+    }
+
+    /// If set, the region (US or EU) of the account is detected before connecting: the credentials are probed with `userinfo` on the default API host of the given host's region first and on the one of the other region if rejected. Avoids the `AccessDenied` / `Invalid 'access_token'` errors of accounts used with the wrong region. Custom hosts (like a local mock server) are used as is.
+    pub fn detect_region(mut self, value: bool) -> PCloudClientBuilder {
+        self.detect_region = value;
+        self
+    }
+
+    /// Uses a custom transport to execute all requests instead of directly sending them with reqwest (e.g. a `MockTransport` for unit tests)
+    pub fn with_transport<T: HttpTransport + 'static>(
+        mut self,
+        transport: T,
+    ) -> PCloudClientBuilder {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Registers an interceptor invoked for every request and response. Interceptors are invoked in the order of registration.
+    pub fn with_interceptor<I: Interceptor + 'static>(
+        mut self,
+        interceptor: I,
+    ) -> PCloudClientBuilder {
+        self.interceptors.push(Arc::new(interceptor));
+        self
+    }
+
+    /// Adds a correlation id generated by the given callback as header (e.g. `X-Request-Id`) to each request. The id is logged with each request and attached to all its errors, including results of the API and responses that can't be deserialized (see `PCloudError::correlation_id()`), so client-side logs can be correlated with gateway or proxy logs.
+    pub fn with_correlation_id<F: Fn() -> String + Send + Sync + 'static>(
+        mut self,
+        header: &str,
+        generator: F,
+    ) -> PCloudClientBuilder {
+        self.correlation_id = Some((header.to_string(), Arc::new(generator)));
+        self
+    }
+
+    /// Adds a random UUID as `X-Request-Id` header to each request
+    pub fn with_uuid_correlation_id(self) -> PCloudClientBuilder {
+        self.with_correlation_id(DEFAULT_CORRELATION_HEADER, || {
+            uuid::Uuid::new_v4().to_string()
         })
     }
 
+    /// Debug option: Retains the raw JSON of the last `count` responses (secrets redacted), see `PCloudClient::last_raw_responses()`. Additionally deserialization errors contain the offending payload (see `DeserializationError`).
+    pub fn capture_raw_responses(mut self, count: usize) -> PCloudClientBuilder {
+        self.capture_raw_responses = count;
+        self
+    }
+
+    /// Prefers or pins the download hosts of all downloads, e.g. `DownloadHostPolicy::pin(["lux"])` to only download from data centers in Luxembourg. By default the first host returned by pCloud is used.
+    pub fn download_hosts(mut self, policy: DownloadHostPolicy) -> PCloudClientBuilder {
+        self.download_hosts = Some(policy);
+        self
+    }
+
+    /// Enables guard rails refusing deletes and moves of the root folder, of folders above a size or entry count, or outside an allowed base path, with a `GuardViolation` error. Recommended for automated tools running with full-account tokens.
+    pub fn guard_rails(mut self, guard_rails: GuardRails) -> PCloudClientBuilder {
+        self.guard_rails = Some(guard_rails);
+        self
+    }
+
+    /// If set, the connection to the API host (DNS lookup, TCP and TLS handshake) is established while building with a cheap `currentserver` request, so the first real request doesn't pay for it. Useful for interactive apps. Failures are only logged.
+    pub fn preconnect(mut self, value: bool) -> PCloudClientBuilder {
+        self.preconnect = value;
+        self
+    }
+
+    /// Additionally establishes a connection to the given download host (like `c123.pcloud.com`) while building, e.g. a host of a previous download. Failures are only logged.
+    pub fn preconnect_download_host(mut self, host: &str) -> PCloudClientBuilder {
+        self.preconnect_download_hosts.push(host.to_string());
+        self
+    }
+
+    /// Caches the resolved addresses of the API and content hosts for the given time instead of resolving them for each new connection. Not applied to custom transports.
+    pub fn dns_cache_ttl(mut self, ttl: Duration) -> PCloudClientBuilder {
+        self.dns_cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Prefers (or restricts to) IPv4 or IPv6 addresses to connect to the API and content hosts, e.g. if IPv6 is routed poorly. Not applied to custom transports.
+    pub fn ip_preference(mut self, preference: IpPreference) -> PCloudClientBuilder {
+        self.ip_preference = preference;
+        self
+    }
+
+    /// Size of the chunks local files are read in for streaming uploads (default 64 KiB). The chunk buffers are pooled and reused across all transfers of the client, so larger chunks for high-throughput transfers don't cause repeated allocations.
+    pub fn transfer_chunk_size(mut self, bytes: usize) -> PCloudClientBuilder {
+        self.transfer_chunk_size = bytes.max(1);
+        self
+    }
+
+    /// Sends TCP keepalive probes in the given interval on idle connections, so NATs and proxies don't drop long blocking requests (like the event stream of `get_events()`). Not applied to custom transports.
+    pub fn tcp_keepalive(mut self, interval: Duration) -> PCloudClientBuilder {
+        self.tcp_keepalive = Some(interval);
+        self
+    }
+
+    /// Aborts requests not completed within the given time, including the transfer of the response body, so choose it generously for large downloads. Requests with their own timeout (like the blocking calls of event streams) keep theirs. Not applied to custom transports.
+    pub fn timeout(mut self, timeout: Duration) -> PCloudClientBuilder {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Aborts establishing a connection (DNS lookup, TCP and TLS handshake) not completed within the given time. Not applied to custom transports.
+    pub fn connect_timeout(mut self, timeout: Duration) -> PCloudClientBuilder {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Sends the given user agent (like `my-app/1.0`) with each request. Not applied to custom transports.
+    pub fn user_agent(mut self, user_agent: &str) -> PCloudClientBuilder {
+        self.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    /// Sends all requests through the given proxy (e.g. `reqwest::Proxy::all("http://proxy:3128")?`) instead of the ones configured by environment variables. Not applied to custom transports.
+    pub fn proxy(mut self, proxy: reqwest::Proxy) -> PCloudClientBuilder {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Keeps at most the given number of idle connections per host (the API host and each content host) for reuse. Not applied to custom transports.
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> PCloudClientBuilder {
+        self.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    /// Limits the request rate and the bandwidth of uploads and downloads. The budget is shared by all clones of the client, pass clones of the same RateLimiter to several builders to bound them together.
+    pub fn rate_limiter(mut self, limiter: RateLimiter) -> PCloudClientBuilder {
+        self.rate_limiter = limiter;
+        self
+    }
+
+    /// Caches thumbnails on disk, so apps don't fetch the same thumbnails again across sessions. Thumbnails are keyed by file id, content hash and size, so changed files get new thumbnails.
+    pub fn thumbnail_cache(mut self, cache: ThumbnailCache) -> PCloudClientBuilder {
+        self.thumbnail_cache = Some(cache);
+        self
+    }
+
+    /// Wraps the HTTP layer into the given tower layer (e.g. rate limit, retry, concurrency limit or timeout). Layers added later wrap the ones added before. The resulting service is cloned for each request, so wrap non-clonable middleware into a `Buffer`.
+    #[cfg(feature = "tower")]
+    pub fn with_layer<L>(mut self, layer: L) -> PCloudClientBuilder
+    where
+        L: tower::Layer<crate::service::TransportService> + Send + 'static,
+        L::Service: tower::Service<crate::service::PCloudRequest, Response = Response>
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        <L::Service as tower::Service<crate::service::PCloudRequest>>::Error:
+            Into<Box<dyn std::error::Error + Send + Sync>>,
+        <L::Service as tower::Service<crate::service::PCloudRequest>>::Future: Send,
+    {
+        self.layers.push(Box::new(move |transport| {
+            let service = layer.layer(crate::service::TransportService::new(transport));
+            Arc::new(crate::service::TowerTransport::new(service))
+        }));
+        self
+    }
+
+    /// Creates the PCloudClient. Performs the login, if username and password are given.
+    pub async fn build(self) -> Result<PCloudClient, PCloudError> {
+        let mut http_client = PCloudClient::http_client_builder();
+        if self.dns_cache_ttl.is_some() || self.ip_preference != IpPreference::Any {
+            let resolver = CachingResolver::new(self.dns_cache_ttl, self.ip_preference);
+            http_client = http_client.dns_resolver(Arc::new(resolver));
+        }
+        if let Some(interval) = self.tcp_keepalive {
+            http_client = http_client.tcp_keepalive(interval);
+        }
+        if let Some(timeout) = self.timeout {
+            http_client = http_client.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            http_client = http_client.connect_timeout(timeout);
+        }
+        if let Some(user_agent) = &self.user_agent {
+            http_client = http_client.user_agent(user_agent);
+        }
+        if let Some(proxy) = self.proxy {
+            http_client = http_client.proxy(proxy);
+        }
+        if let Some(max) = self.pool_max_idle_per_host {
+            http_client = http_client.pool_max_idle_per_host(max);
+        }
+        let client = http_client.build()?;
+
+        let transport: Arc<dyn HttpTransport> = match self.transport {
+            Some(transport) => transport,
+            None => Arc::new(client.clone()),
+        };
+
+        #[cfg(feature = "tower")]
+        let transport = self
+            .layers
+            .into_iter()
+            .fold(transport, |transport, wrap| wrap(transport));
+
+        let correlation_id = match self.correlation_id {
+            Some((header, generator)) => Some((HeaderName::try_from(header)?, generator)),
+            None => None,
+        };
+
+        // Client without any authentication, to perform the login and determine the api server
+        let mut anonymous = PCloudClient {
+            inner: Arc::new(ClientInner {
+                api_host: self.host,
+                client,
+                transport,
+                interceptors: self.interceptors,
+                correlation_id,
+                raw_responses: if self.capture_raw_responses > 0 {
+                    Some(Mutex::new(RawResponseLog::new(self.capture_raw_responses)))
+                } else {
+                    None
+                },
+                oauth2_token: None,
+                session: None,
+                download_hosts: self.download_hosts,
+                guard_rails: self.guard_rails,
+                buffer_pool: BufferPool::new(self.transfer_chunk_size),
+                rate_limiter: self.rate_limiter,
+                thumbnail_cache: self.thumbnail_cache,
+                shutdown: ShutdownSignal::new(),
+                closed: AtomicBool::new(false),
+            }),
+        };
+
+        if self.detect_region {
+            if let Some(region) = Region::from_hostname(anonymous.api_host()) {
+                let region = anonymous
+                    .detect_region(region, self.oauth2.as_deref(), self.credentials.as_ref())
+                    .await?;
+                anonymous.inner_mut().api_host = region.api_host().to_string();
+            }
+        }
+
+        let token = if let Some((username, password)) = &self.credentials {
+            Some(anonymous.login(username, password).await?)
+        } else {
+            None
+        };
+
+        let api_host = if self.select_best_api_server {
+            anonymous.get_best_api_server(token.clone()).await?
+        } else {
+            debug!("Using pCloud API endpoint {} as is", anonymous.api_host());
+            anonymous.api_host().to_string()
+        };
+
+        let inner = anonymous.inner_mut();
+        inner.session = token.map(|token| PCloudClientSession {
+            api_host: api_host.clone(),
+            client: inner.client.clone(),
+            transport: inner.transport.clone(),
+            token,
+            logged_out: Arc::new(AtomicBool::new(false)),
+        });
+        inner.api_host = api_host;
+        inner.oauth2_token = self.oauth2;
+
+        if self.preconnect || !self.preconnect_download_hosts.is_empty() {
+            anonymous
+                .warm_up(self.preconnect, &self.preconnect_download_hosts)
+                .await;
+        }
+        Ok(anonymous)
+    }
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Creates a builder to configure a new PCloudClient instance for the given host (either `https://api.pcloud.com`, `https://eapi.pcloud.com` or any custom API base url like a local mock server or a gateway)
+    pub fn builder(host: &str) -> PCloudClientBuilder {
+        PCloudClientBuilder::for_host(host)
+    }
+
+    /// Creates a new PCloudClient instance with an already present OAuth 2.0 authentication token. Automatically determines nearest API server for best performance
+    pub async fn with_oauth(host: &str, oauth2: &str) -> Result<PCloudClient, PCloudError> {
+        PCloudClient::builder(host).with_oauth(oauth2).build().await
+    }
+
     /// Creates a new PCloudClient instance using username and password to obtain a temporary auth token. Token is shared between all clones of this instance and revoked when the last instance is dropped. Automatically determines nearest API server for best performance.
     pub async fn with_username_and_password(
         host: &str,
         username: &str,
         password: &str,
-    ) -> Result<PCloudClient, Box<dyn std::error::Error + Send + Sync>> {
-        let token = PCloudClient::login(host, username, password).await?;
+    ) -> Result<PCloudClient, PCloudError> {
+        PCloudClient::builder(host)
+            .with_username_and_password(username, password)
+            .build()
+            .await
+    }
 
-        let builder = reqwest::ClientBuilder::new();
+    /// Host to connect to pCloud API
+    pub(crate) fn api_host(&self) -> &str {
+        &self.inner.api_host
+    }
 
-        let client = builder.build().unwrap();
+    /// Client to build the requests
+    pub(crate) fn client(&self) -> &reqwest::Client {
+        &self.inner.client
+    }
 
-        let best_host =
-            PCloudClient::get_best_api_server(&client, host, Some(token.clone())).await?;
+    /// Safety limits for deletes and moves, if enabled
+    pub(crate) fn guard_rails(&self) -> Option<&GuardRails> {
+        self.inner.guard_rails.as_ref()
+    }
 
-        let session = PCloudClientSession {
-            api_host: best_host.clone(),
-            client: client.clone(),
-            token: token,
-        };
+    /// Buffers reused by the streaming transfers
+    pub(crate) fn buffer_pool(&self) -> &BufferPool {
+        &self.inner.buffer_pool
+    }
 
-        Ok(PCloudClient {
-            api_host: best_host,
-            client: client,
-            session_token: std::sync::Arc::new(Some(session)),
-        })
+    /// Request rate and bandwidth limits shared by all clones
+    pub(crate) fn rate_limiter(&self) -> &RateLimiter {
+        &self.inner.rate_limiter
     }
 
-    /// Performs the login to pCloud using username and password.
-    async fn login(
-        host: &str,
-        username: &str,
-        password: &str,
-    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-        let url = format!("{}/userinfo?getauth=1", host);
+    /// On-disk cache of thumbnails, if enabled
+    pub(crate) fn thumbnail_cache(&self) -> Option<&ThumbnailCache> {
+        self.inner.thumbnail_cache.as_ref()
+    }
+
+    /// Shutdown signal of the client, watched by the transfer managers and syncs created from it
+    pub fn shutdown_signal(&self) -> &ShutdownSignal {
+        &self.inner.shutdown
+    }
+
+    /// Shuts the client down gracefully, e.g. on SIGTERM: transfer managers and syncs created from the client stop starting new work, in-flight requests and transfers get until the deadline to complete (syncs and transfer managers persist their progress), then all further requests are rejected with `ShutDown` and the session token (if logged in with username and password) is logged out. Returns false if work was still running when the deadline passed.
+    pub async fn shutdown(&self, deadline: Duration) -> Result<bool, PCloudError> {
+        self.inner.shutdown.trigger(deadline);
+        let drained = self.inner.shutdown.drain().await;
+        if !drained {
+            warn!(
+                "Shutting down with {} operations or requests still running",
+                self.inner.shutdown.active()
+            );
+        }
+        self.inner.closed.store(true, Ordering::SeqCst);
+
+        if let Some(session) = &self.inner.session {
+            if !session.logged_out.swap(true, Ordering::SeqCst) {
+                let logged_out = PCloudClient::logout(
+                    &session.client,
+                    session.transport.as_ref(),
+                    &session.api_host,
+                    &session.token,
+                )
+                .await?;
+                debug!("Logout on shutdown: {}", logged_out);
+            }
+        }
+        Ok(drained)
+    }
 
-        let client = reqwest::ClientBuilder::new().build()?;
+    /// Mutable access to the configuration while building, before the client is shared
+    fn inner_mut(&mut self) -> &mut ClientInner {
+        Arc::get_mut(&mut self.inner).expect("PCloudClient is not shared while building")
+    }
+
+    /// Creates a reqwest ClientBuilder using the TLS backend selected by the crate features
+    fn http_client_builder() -> reqwest::ClientBuilder {
+        let builder = reqwest::ClientBuilder::new();
 
-        let mut r = client.get(url);
+        #[cfg(feature = "rustls")]
+        let builder = builder.use_rustls_tls();
+
+        builder
+    }
+
+    /// Performs the login to pCloud using username and password.
+    async fn login(&self, username: &str, password: &str) -> Result<String, PCloudError> {
+        let url = format!("{}/userinfo?getauth=1", self.inner.api_host);
+
+        let mut r = self.inner.client.get(url);
 
         r = r.query(&[("username", username)]);
         r = r.query(&[("password", password)]);
 
-        let user_info = r.send().await?.json::<pcloud_model::UserInfo>().await?;
+        let user_info = self.send_json::<pcloud_model::UserInfo>(r).await?;
 
         if user_info.result == PCloudResult::Ok && user_info.auth.is_some() {
             debug!("Successful login for user {}", username);
@@ -140,18 +782,64 @@ impl PCloudClient {
         }
     }
 
+    /// Probes the credentials with `userinfo` on the default API hosts of the given region and the other one. Returns the region accepting them, or the given region if none does (so the login fails as usual).
+    async fn detect_region(
+        &self,
+        region: Region,
+        oauth2: Option<&str>,
+        credentials: Option<&(String, String)>,
+    ) -> Result<Region, PCloudError> {
+        if oauth2.is_none() && credentials.is_none() {
+            return Ok(region);
+        }
+
+        for candidate in [region, region.other()] {
+            let mut r = self
+                .inner
+                .client
+                .get(format!("{}/userinfo", candidate.api_host()));
+            if let Some(oauth2) = oauth2 {
+                r = r.bearer_auth(oauth2);
+            } else if let Some((username, password)) = credentials {
+                r = r.query(&[("username", username), ("password", password)]);
+            }
+
+            let user_info = self.send_json::<UserInfo>(r).await?;
+            match user_info.result {
+                PCloudResult::Ok => {
+                    debug!("pCloud account accepted by {} region", candidate);
+                    return Ok(candidate);
+                }
+                PCloudResult::LogInRequired
+                | PCloudResult::LoginFailed
+                | PCloudResult::AccessDenied
+                | PCloudResult::InvalidAccessToken => {
+                    debug!(
+                        "pCloud account rejected by {} region: {}",
+                        candidate, user_info.result
+                    );
+                }
+                result => Err(result)?,
+            }
+        }
+
+        warn!("pCloud account rejected by all regions, using {}", region);
+        Ok(region)
+    }
+
     /// Performs the logout for the token aquired with login
     async fn logout(
         client: &Client,
+        transport: &dyn HttpTransport,
         api_host: &str,
         token: &str,
-    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<bool, PCloudError> {
         let mut r = client.get(format!("{}/logout", api_host));
 
         r = r.query(&[("auth", token)]);
 
-        let response = r
-            .send()
+        let response = transport
+            .execute(r.build()?)
             .await?
             .json::<pcloud_model::LogoutResponse>()
             .await?;
@@ -161,32 +849,209 @@ impl PCloudClient {
             && response.auth_deleted.unwrap())
     }
 
-    /// If theres is a session token present, add it to the given request.
+    /// If theres is an OAuth2 token or a session token present, add it to the given request.
     pub(crate) fn add_token(&self, r: RequestBuilder) -> RequestBuilder {
-        let arc = self.session_token.clone();
+        if let Some(ref oauth2) = self.inner.oauth2_token {
+            return r.bearer_auth(oauth2);
+        }
 
-        if let Some(ref session) = *arc {
+        if let Some(session) = &self.inner.session {
             return session.add_token(r);
         }
 
         return r;
     }
 
+    /// Sends the request using the configured transport
+    pub(crate) async fn send(&self, r: RequestBuilder) -> Result<Response, PCloudError> {
+        self.execute(r.build()?).await
+    }
+
+    /// Downloads the given link. The hosts are tried in the order of the download host policy, falling back to the next one on connection errors or server errors. Without policy only the first host is used.
+    /// The request to each host can be customized (e.g. to add a range header).
+    pub(crate) async fn send_download<F: Fn(RequestBuilder) -> RequestBuilder>(
+        &self,
+        link: &pcloud_model::DownloadLink,
+        customize: F,
+    ) -> Result<Response, PCloudError> {
+        let hosts = match &self.inner.download_hosts {
+            Some(policy) => policy.order(&link.hosts),
+            None => link.hosts.iter().take(1).collect(),
+        };
+        let path = match &link.path {
+            Some(path) if link.result == PCloudResult::Ok && !hosts.is_empty() => path,
+            _ => Err(PCloudResult::ProvideURL)?,
+        };
+
+        let last = hosts.len() - 1;
+        for (i, host) in hosts.into_iter().enumerate() {
+            let url = format!("https://{}{}", host, path);
+            debug!("Downloading file link {}", url);
+
+            // No authentication necessary!
+            match self.send(customize(self.inner.client.get(&url))).await {
+                Ok(response) if i == last || !response.status().is_server_error() => {
+                    return self.limit_download(response);
+                }
+                Ok(response) => warn!(
+                    "Download from {} failed with status {}, trying next host",
+                    host,
+                    response.status()
+                ),
+                Err(e) if i == last => return Err(e),
+                Err(e) => warn!("Download from {} failed: {}, trying next host", host, e),
+            }
+        }
+        unreachable!("At least one download host is tried")
+    }
+
+    /// Executes the request using the configured transport. All interceptors are invoked for the request and the response.
+    async fn execute(&self, mut request: Request) -> Result<Response, PCloudError> {
+        if self.inner.closed.load(Ordering::SeqCst) {
+            return Err(ShutDown)?;
+        }
+        let _request = self.inner.shutdown.begin();
+        let correlation_id = if let Some((header, generator)) = &self.inner.correlation_id {
+            let id = generator();
+            request.headers_mut().insert(header, id.parse()?);
+            debug!(
+                "Sending request {} {} {}",
+                id,
+                request.method(),
+                request.url().path()
+            );
+            Some(id)
+        } else {
+            None
+        };
+
+        for interceptor in self.inner.interceptors.iter() {
+            interceptor.on_request(&mut request);
+        }
+
+        self.inner.rate_limiter.acquire_request().await;
+
+        let mut response = match self.inner.transport.execute(request).await {
+            Ok(response) => response,
+            Err(source) => return Err(correlate(correlation_id, source.into())),
+        };
+        if let Some(correlation_id) = correlation_id {
+            response
+                .extensions_mut()
+                .insert(CorrelationId(correlation_id));
+        }
+        for interceptor in self.inner.interceptors.iter() {
+            interceptor.on_response(&response);
+        }
+        Ok(response)
+    }
+
+    /// Passes the body of a download on within the bandwidth budget of the client, if limited. The status and headers are kept, the content length is only available as header.
+    fn limit_download(&self, response: Response) -> Result<Response, PCloudError> {
+        let limiter = match self.inner.rate_limiter.bandwidth() {
+            Some(limiter) => limiter,
+            None => return Ok(response),
+        };
+        let mut builder = hyper::Response::builder()
+            .status(response.status())
+            .version(response.version());
+        if let Some(headers) = builder.headers_mut() {
+            *headers = response.headers().clone();
+        }
+        let body = reqwest::Body::wrap_stream(limit(response.bytes_stream(), limiter));
+        Ok(Response::from(builder.body(body)?))
+    }
+
+    /// Sends the request using the configured transport and deserializes the JSON response. If raw response capture is enabled, the response is retained and deserialization errors contain the payload.
+    pub(crate) async fn send_json<T: DeserializeOwned>(
+        &self,
+        r: RequestBuilder,
+    ) -> Result<T, PCloudError> {
+        Ok(self.receive_json(r).await?.0)
+    }
+
+    /// Sends the request like `send_json()` and fails with the result if pCloud rejected the request. Errors carry the correlation id of the request, if any.
+    pub(crate) async fn send_checked<T: DeserializeOwned + WithPCloudResult>(
+        &self,
+        r: RequestBuilder,
+    ) -> Result<T, PCloudError> {
+        let (response, correlation_id) = self.receive_json::<T>(r).await?;
+        response
+            .assert_ok()
+            .map_err(|result| correlate(correlation_id, result.into()))
+    }
+
+    /// Sends the request and deserializes the JSON response, returning the correlation id of the request (if any) along with it. Failures already carry the id.
+    async fn receive_json<T: DeserializeOwned>(
+        &self,
+        r: RequestBuilder,
+    ) -> Result<(T, Option<String>), PCloudError> {
+        let request = r.build()?;
+        let url = redact_url(request.url());
+        let response = self.execute(request).await?;
+        let correlation_id = response
+            .extensions()
+            .get::<CorrelationId>()
+            .map(|id| id.0.clone());
+
+        let result = match &self.inner.raw_responses {
+            Some(raw_responses) => Self::capture_json(raw_responses, url, response).await,
+            None => response.json::<T>().await.map_err(PCloudError::from),
+        };
+        match result {
+            Ok(value) => Ok((value, correlation_id)),
+            Err(e) => Err(correlate(correlation_id, e)),
+        }
+    }
+
+    /// Deserializes the JSON response and retains it in the log of raw responses. Deserialization errors contain the payload.
+    async fn capture_json<T: DeserializeOwned>(
+        raw_responses: &Mutex<RawResponseLog>,
+        url: String,
+        response: Response,
+    ) -> Result<T, PCloudError> {
+        let status = response.status().as_u16();
+        let bytes = response.bytes().await?;
+        let raw = RawResponse {
+            url,
+            status,
+            body: redact_body(String::from_utf8_lossy(&bytes).to_string()),
+        };
+        raw_responses.lock().unwrap().push(raw.clone());
+
+        match serde_json::from_slice::<T>(&bytes) {
+            Ok(result) => Ok(result),
+            Err(source) => Err(DeserializationError {
+                response: raw,
+                source,
+            }
+            .into()),
+        }
+    }
+
+    /// Returns the raw JSON of the last responses (oldest first, secrets redacted). Empty unless enabled with `PCloudClientBuilder::capture_raw_responses()`.
+    pub fn last_raw_responses(&self) -> Vec<RawResponse> {
+        match &self.inner.raw_responses {
+            Some(raw_responses) => raw_responses.lock().unwrap().responses(),
+            None => Vec::new(),
+        }
+    }
+
     // Determine fastest api server for the given default api server (either api.pcloud.com or eapi.pcloud.com)
     async fn get_best_api_server(
-        client: &reqwest::Client,
-        host: &str,
+        &self,
         session_token: Option<String>,
-    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<String, PCloudError> {
+        let host = &self.inner.api_host;
         let url = format!("{}/getapiserver", host);
 
-        let mut r = client.get(url);
+        let mut r = self.inner.client.get(url);
 
         if let Some(v) = session_token {
             r = r.query(&[("auth", v)]);
         }
 
-        let api_servers = r.send().await?.json::<pcloud_model::ApiServers>().await?;
+        let api_servers = self.send_json::<pcloud_model::ApiServers>(r).await?;
 
         let best_host = match api_servers.result {
             pcloud_model::PCloudResult::Ok => {
@@ -203,18 +1068,168 @@ impl PCloudClient {
         Ok(best_host)
     }
 
+    /// Region of the API host in use, None for custom hosts
+    pub fn region(&self) -> Option<Region> {
+        Region::from_hostname(&self.inner.api_host)
+    }
+
+    /// Establishes the connections to the API host and the given download hosts concurrently, so they are reused by the following requests. Failures are only logged.
+    async fn warm_up(&self, api_host: bool, download_hosts: &[String]) {
+        let start = Instant::now();
+        let api = async {
+            if api_host {
+                if let Err(e) = self.ping().await {
+                    warn!("Failed to preconnect to {}: {}", self.api_host(), e);
+                }
+            }
+        };
+        let downloads = futures::future::join_all(download_hosts.iter().map(|host| async move {
+            let url = format!("https://{}/", host.trim_end_matches('/'));
+            if let Err(e) = self.send(self.client().head(&url)).await {
+                warn!("Failed to preconnect to download host {}: {}", host, e);
+            }
+        }));
+        futures::join!(api, downloads);
+        debug!(
+            "Preconnected to pCloud hosts in {} ms",
+            start.elapsed().as_millis()
+        );
+    }
+
     /// Get user info
-    pub async fn get_user_info(
-        &self,
-    ) -> Result<UserInfo, Box<dyn std::error::Error + Send + Sync>> {
-        let url = format!("{}/userinfo", self.api_host);
-        let mut r = self.client.get(url);
+    pub async fn get_user_info(&self) -> Result<UserInfo, PCloudError> {
+        let url = format!("{}/userinfo", self.inner.api_host);
+        let mut r = self.inner.client.get(url);
 
         r = self.add_token(r);
 
         debug!("Requesting user info");
-        let user_info = r.send().await?.json::<UserInfo>().await?.assert_ok()?;
+        let user_info = self.send_checked::<UserInfo>(r).await?;
 
         Ok(user_info)
     }
+
+    /// Cheap API roundtrip (`currentserver`) to health-check the connectivity to pCloud. Returns the latency of the roundtrip.
+    /// see https://docs.pcloud.com/methods/general/currentserver.html
+    pub async fn ping(&self) -> Result<Duration, PCloudError> {
+        let url = format!("{}/currentserver", self.inner.api_host);
+        let r = self.inner.client.get(url);
+
+        let start = Instant::now();
+        let server = self.send_checked::<CurrentServer>(r).await?;
+        let latency = start.elapsed();
+
+        debug!(
+            "Ping to pCloud API server {:?} took {} ms",
+            server.hostname,
+            latency.as_millis()
+        );
+        Ok(latency)
+    }
+
+    /// Checks if the token (OAuth2 or session token) is still accepted by pCloud using `userinfo`. Rejected tokens result in an invalid TokenValidation, not an error.
+    pub async fn validate_token(&self) -> Result<TokenValidation, PCloudError> {
+        let url = format!("{}/userinfo", self.inner.api_host);
+        let mut r = self.inner.client.get(url);
+
+        r = self.add_token(r);
+
+        let user_info = self.send_json::<UserInfo>(r).await?;
+
+        match user_info.result {
+            PCloudResult::Ok => Ok(TokenValidation {
+                valid: true,
+                result: user_info.result,
+                userid: user_info.userid,
+                email: user_info.email,
+                expires: self.token_expiry().await,
+            }),
+            PCloudResult::LogInRequired
+            | PCloudResult::LoginFailed
+            | PCloudResult::AccessDenied
+            | PCloudResult::InvalidAccessToken => {
+                warn!("pCloud token rejected: {}", user_info.result);
+                Ok(TokenValidation {
+                    valid: false,
+                    result: user_info.result,
+                    userid: None,
+                    email: None,
+                    expires: None,
+                })
+            }
+            result => Err(result)?,
+        }
+    }
+
+    /// Expiry of the current token from `listtokens`. Failures are only logged, as not all tokens (e.g. OAuth2 tokens) are listed.
+    async fn token_expiry(&self) -> Option<DateTime<Utc>> {
+        let url = format!("{}/listtokens", self.inner.api_host);
+        let r = self.add_token(self.inner.client.get(url));
+
+        let list = match self.send_json::<TokenList>(r).await {
+            Ok(list) => list,
+            Err(e) => {
+                debug!("Failed to determine the expiry of the token: {}", e);
+                return None;
+            }
+        };
+        if list.result != PCloudResult::Ok {
+            debug!(
+                "Failed to determine the expiry of the token: {}",
+                list.result
+            );
+            return None;
+        }
+        list.tokens
+            .into_iter()
+            .find(|t| t.current)
+            .and_then(|t| t.expires)
+    }
+
+    /// Calls an arbitrary pCloud API method not (yet) wrapped by this crate with the given query parameters. Authentication, host selection and all configured transport layers are applied.
+    /// The JSON response is deserialized into the given type, a non-zero `result` is returned as error.
+    pub async fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: &[(&str, &str)],
+    ) -> Result<T, PCloudError> {
+        let (value, correlation_id) = self.call_correlated(method, params).await?;
+        serde_json::from_value(value).map_err(|e| correlate(correlation_id, e.into()))
+    }
+
+    /// Calls an arbitrary pCloud API method not (yet) wrapped by this crate with the given query parameters and returns the raw JSON response. A non-zero `result` is returned as error.
+    pub async fn call_json(
+        &self,
+        method: &str,
+        params: &[(&str, &str)],
+    ) -> Result<serde_json::Value, PCloudError> {
+        Ok(self.call_correlated(method, params).await?.0)
+    }
+
+    /// Calls the pCloud API method, returning the raw JSON response along with the correlation id of the request (if any). Failures already carry the id.
+    async fn call_correlated(
+        &self,
+        method: &str,
+        params: &[(&str, &str)],
+    ) -> Result<(serde_json::Value, Option<String>), PCloudError> {
+        let url = format!("{}/{}", self.inner.api_host, method.trim_start_matches('/'));
+        let mut r = self.inner.client.get(url);
+
+        r = r.query(params);
+        r = self.add_token(r);
+
+        debug!("Calling pCloud API method {}", method);
+        let (value, correlation_id) = self.receive_json::<serde_json::Value>(r).await?;
+
+        let error: PCloudError = match value
+            .get("result")
+            .and_then(|result| result.as_u64())
+            .and_then(|code| u16::try_from(code).ok())
+        {
+            Some(0) => return Ok((value, correlation_id)),
+            Some(code) => PCloudResult::from_code(code).into(),
+            None => <serde_json::Error as serde::de::Error>::missing_field("result").into(),
+        };
+        Err(correlate(correlation_id, error))
+    }
 }
@@ -1,131 +1,213 @@
-use std::fmt::Display;
+use std::{fmt::Display, time::Duration};
 
 use chrono::{DateTime, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
 use serde_repr::*;
 
-/// # ERRORS
-/// There are number of cases when you request can't be processed as is and an error will be returned. Error codes are always 4 digit. They can be grouped into few categories depending on the type of error occurred.
-/// @see https://docs.pcloud.com/errors/
-///
-/// | Code  |	Description |
-/// | ----  | ------------- |
-/// | 1xxx	| These errors are reserved for cases when the API client misbehaved. Most of the time it means that required parameters were not provided, text was provided when a number was expected, or one of several valid values was expected, but the input was something else. Also, trying to call a method that requires login without providing any login credentials is a 1xxx error, while providing bad credentials is not. Well behaved applications should never receive this type of error, regardless of user actions. It is advisable to find a way to send the error and the error message to the application developer. |
-/// | 19xx	| This is sub-type of 1xxx errors. It may be the case that the application is misbehaving or it could be a synchronization error - e.g. you are trying to monitor the progress of an upload that the server knows nothing about. It might be the case that the application has passed a wrong or not existing hash or it could be that the upload request is still in transit and the API server is yet to start processing it. If you are sure that you have passed the correct parameters, it is safe to retry the request later. |
-/// | 2xxx	| The user is trying to preform invalid operation or is providing bad data. Example errors are bad filename supplied, file not found or folder already exists. While a part of these can be prevented in the application (notably can not delete root folder), given the multi-user and multi client environment, files that were here just a moment ago may disappear. Generally these types of errors can be displayed directly to the user. However, it is preferable for the applications to actually understand the error codes instead of blindly displaying them. Of course, in some cases this errors can be the application's fault - e.g. the user wanted to open a file, but the application provided incorrect folderid. Keep in mind that user here is a quite abstract concept. If your applications is a filesystem, your users are not the end users, but end users' applications. |
-/// | 3xxx	| These are rare errors when something can not be done and is unlikely that retrying will give any better results. One example of error of this type is trying to create a thumbnail from text file renamed to mypicture.jpg. It can't be classified as 1xxx error as the application did nothing wrong - it received thumb: true and decided to create thumbnail. The user probably didn't do anything wrong either (apart from renaming text file to mypicture.jpg, but it was probably the application that decided to display the thumbnail). These errors should be ignored if the unsuccessful action is not explicitly requested by user (fall back for failing to display a thumbnail would be to simply display an icon instead) and if the action was indeed requested by user, it should be reported that the file is bad. |
-/// | 4xxx	| Should generally be very rare. They are reserved for cases when server is not willing to process you request. This generally means that the API server is rate limiting you because of too many requests or login tries. It should be possible to retry the request at a later stage. |
-/// | 5xxx	| Errors of this type are the ones that we work very hard to never happen. Nevertheless they are still possible. These type of errors generally mean that we can not satisfy the request at this time (e.g. a server is unavailable) but it is very likely that the API server will be able to satisfy the request at a later stage. |
-/// | 6xxx	| These are not real errors, but legitimate non-error answers. They are used by conditional methods mostly to signal some action not required state |
-/// | 7xxx	| These errors generally represent error condition for which neither the implementation that accesses the API nor it's user are responsible. These errors should be expected when a method is indicated to return one of those and should be presented to the user more like a normal condition, rather than you got an error, the sky is falling down. Typical 7xxx error is for example when somebody has deleted his public link and the user is trying to access it. |
-#[derive(Serialize_repr, Deserialize_repr, PartialEq, Debug, Clone)]
-#[repr(u16)]
-pub enum PCloudResult {
-    /// No Error    
-    Ok = 0,
-    LogInRequired = 1000,
-    NoFullPathOrNameOrFolderIdProvided = 1001,
-    NoFullPathOrFolderIdProvided = 1002,
-    NoFileIdOrPathProvided = 1004,
-    InvalidFileDescriptor = 1007,
-    DateTimeFormatNotUnderstood = 1013,
-    NoFullToPathOrToNameAndToFolderIdProvided = 1016,
-    InvalidFolderId = 1017,
-    InvalidFileId = 1018,
-    ProvidedAtLeastToPathOrToFolderIdOrToName = 1037,
-    ProvideURL = 1040,
-    LoginFailed = 2000,
-    InvalidFileOrFolderName = 2001,
-    ComponentOfTheParentDirectoryDoesNotExist = 2002,
-    AccessDenied = 2003,
-    DirectoryDoesNotExist = 2005,
-    FolderIsNotEmpty = 2006,
-    CanNotDeleteRootFolder = 2007,
-    UserOverQuota = 2008,
-    FileNotFound = 2009,
-    InvalidPath = 2010,
-    PleaseVerifyYourMailAddressToPerformThisAction = 2014,
-    CannotPlaceASharedFolderIntoAnotherSharedFolder = 2023,
-    YouCanOnlyShareYourOwnFilesOrFolders = 2026,
-    ActiveSharesOrShareRequestsForThisFolder = 2028,
-    ConnectionBroken = 2041,
-    CannotRenameTheRootFolder = 2042,
-    CannotMoveAFolderToASubfolderOfItself = 2043,
-    TooManyLogins = 4000,
-    InternalError = 5000,
-    InternalUploadError = 5001,
-    WriteError = 5003,
-}
-
-/// Necessary to implement Error trait
-impl Display for PCloudResult {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            PCloudResult::Ok => write!(f, "Everything ok - no error"),
-            PCloudResult::NoFullPathOrNameOrFolderIdProvided => {
-                write!(f, "No full path or name/folderid provided.")
-            }
-            PCloudResult::LogInRequired => write!(f, "Log in required"),
-            PCloudResult::NoFullPathOrFolderIdProvided => {
-                write!(f, "No full path or folder id provided.")
-            }
-            PCloudResult::NoFileIdOrPathProvided => write!(f, "No file id or file path provided"),
-            PCloudResult::DateTimeFormatNotUnderstood => {
-                write!(f, "Date time format not understood")
-            }
+use crate::{error::PCloudError, pcloud_client::PCloudClient, permissions::Permissions};
 
-            PCloudResult::ProvideURL => write!(f, "Provide url"),
-            PCloudResult::LoginFailed => write!(f, "Log in failed"),
-            PCloudResult::InvalidFileOrFolderName => write!(f, "Invalid file or folder name"),
-            PCloudResult::ComponentOfTheParentDirectoryDoesNotExist => {
-                write!(f, "A component of the parent directory does not exist")
-            }
-            PCloudResult::AccessDenied => write!(f, "Access denied"),
-            PCloudResult::DirectoryDoesNotExist => write!(f, "Directory does not exist"),
-            PCloudResult::UserOverQuota => write!(f, "User over quota"),
-            PCloudResult::FileNotFound => write!(f, "File not found"),
-            PCloudResult::InvalidPath => write!(f, "Invalid path"),
-            PCloudResult::PleaseVerifyYourMailAddressToPerformThisAction => {
-                write!(f, "Please verify your mail address to perform this action")
-            }
-            PCloudResult::YouCanOnlyShareYourOwnFilesOrFolders => {
-                write!(f, "You can only share your own files or folders")
+/// Defines the PCloudResult enum with the numeric code and message of each variant
+macro_rules! pcloud_results {
+    ($(#[$meta:meta])* pub enum $name:ident { $($(#[$doc:meta])* $variant:ident = $code:literal => $message:literal,)* }) => {
+        $(#[$meta])*
+        #[derive(PartialEq, Debug, Clone)]
+        #[non_exhaustive]
+        pub enum $name {
+            $($(#[$doc])* $variant,)*
+            /// Error code not known to this crate
+            Unknown(u16),
+        }
+
+        impl $name {
+            /// Numeric pCloud error code (0 for Ok)
+            pub fn code(&self) -> u16 {
+                match self {
+                    $($name::$variant => $code,)*
+                    $name::Unknown(code) => *code,
+                }
             }
-            PCloudResult::ConnectionBroken => write!(f, "Connection broken"),
-            PCloudResult::TooManyLogins => write!(f, "Too many logins"),
-            PCloudResult::InternalError => write!(f, "Internal error"),
-            PCloudResult::InternalUploadError => write!(f, "Internal upload error"),
-            PCloudResult::FolderIsNotEmpty => write!(f, "Folder is not empty"),
-            PCloudResult::CanNotDeleteRootFolder => write!(f, "Cannot delete the root folder."),
-            PCloudResult::ActiveSharesOrShareRequestsForThisFolder => write!(
-                f,
-                "There are active shares or sharerequests for this folder."
-            ),
-            PCloudResult::CannotRenameTheRootFolder => write!(f, "Cannot rename the root folder."),
-            PCloudResult::CannotMoveAFolderToASubfolderOfItself => {
-                write!(f, "Cannot move a folder to a subfolder of itself.")
+
+            /// Result of the numeric pCloud error code, `Unknown` for codes not known to this crate
+            pub fn from_code(code: u16) -> $name {
+                match code {
+                    $($code => $name::$variant,)*
+                    code => $name::Unknown(code),
+                }
             }
-            PCloudResult::CannotPlaceASharedFolderIntoAnotherSharedFolder => write!(
-                f,
-                "You are trying to place shared folder into another shared folder."
-            ),
-            PCloudResult::ProvidedAtLeastToPathOrToFolderIdOrToName => write!(
-                f,
-                "Please provide at least one of 'topath', 'tofolderid' or 'toname'."
-            ),
-            PCloudResult::InvalidFileId => write!(f, "Invalid 'fileid' provided."),
-            PCloudResult::InvalidFolderId => write!(f, "Invalid 'folderid' provided."),
-            PCloudResult::NoFullToPathOrToNameAndToFolderIdProvided => {
-                write!(f, "No full topath or toname/tofolderid provided.")
+        }
+
+        /// Necessary to implement Error trait
+        impl Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    $($name::$variant => write!(f, $message),)*
+                    $name::Unknown(code) => write!(f, "Unknown error {}", code),
+                }
             }
-            PCloudResult::WriteError => write!(f, "Write error. Try reopening the file."),
-            PCloudResult::InvalidFileDescriptor => write!(f, "Invalid or closed file descriptor."),
         }
+    };
+}
+
+pcloud_results! {
+    /// # ERRORS
+    /// There are number of cases when you request can't be processed as is and an error will be returned. Error codes are always 4 digit. They can be grouped into few categories depending on the type of error occurred.
+    /// @see https://docs.pcloud.com/errors/
+    ///
+    /// | Code  |	Description |
+    /// | ----  | ------------- |
+    /// | 1xxx	| These errors are reserved for cases when the API client misbehaved. Most of the time it means that required parameters were not provided, text was provided when a number was expected, or one of several valid values was expected, but the input was something else. Also, trying to call a method that requires login without providing any login credentials is a 1xxx error, while providing bad credentials is not. Well behaved applications should never receive this type of error, regardless of user actions. It is advisable to find a way to send the error and the error message to the application developer. |
+    /// | 19xx	| This is sub-type of 1xxx errors. It may be the case that the application is misbehaving or it could be a synchronization error - e.g. you are trying to monitor the progress of an upload that the server knows nothing about. It might be the case that the application has passed a wrong or not existing hash or it could be that the upload request is still in transit and the API server is yet to start processing it. If you are sure that you have passed the correct parameters, it is safe to retry the request later. |
+    /// | 2xxx	| The user is trying to preform invalid operation or is providing bad data. Example errors are bad filename supplied, file not found or folder already exists. While a part of these can be prevented in the application (notably can not delete root folder), given the multi-user and multi client environment, files that were here just a moment ago may disappear. Generally these types of errors can be displayed directly to the user. However, it is preferable for the applications to actually understand the error codes instead of blindly displaying them. Of course, in some cases this errors can be the application's fault - e.g. the user wanted to open a file, but the application provided incorrect folderid. Keep in mind that user here is a quite abstract concept. If your applications is a filesystem, your users are not the end users, but end users' applications. |
+    /// | 3xxx	| These are rare errors when something can not be done and is unlikely that retrying will give any better results. One example of error of this type is trying to create a thumbnail from text file renamed to mypicture.jpg. It can't be classified as 1xxx error as the application did nothing wrong - it received thumb: true and decided to create thumbnail. The user probably didn't do anything wrong either (apart from renaming text file to mypicture.jpg, but it was probably the application that decided to display the thumbnail). These errors should be ignored if the unsuccessful action is not explicitly requested by user (fall back for failing to display a thumbnail would be to simply display an icon instead) and if the action was indeed requested by user, it should be reported that the file is bad. |
+    /// | 4xxx	| Should generally be very rare. They are reserved for cases when server is not willing to process you request. This generally means that the API server is rate limiting you because of too many requests or login tries. It should be possible to retry the request at a later stage. |
+    /// | 5xxx	| Errors of this type are the ones that we work very hard to never happen. Nevertheless they are still possible. These type of errors generally mean that we can not satisfy the request at this time (e.g. a server is unavailable) but it is very likely that the API server will be able to satisfy the request at a later stage. |
+    /// | 6xxx	| These are not real errors, but legitimate non-error answers. They are used by conditional methods mostly to signal some action not required state |
+    /// | 7xxx	| These errors generally represent error condition for which neither the implementation that accesses the API nor it's user are responsible. These errors should be expected when a method is indicated to return one of those and should be presented to the user more like a normal condition, rather than you got an error, the sky is falling down. Typical 7xxx error is for example when somebody has deleted his public link and the user is trying to access it. |
+    pub enum PCloudResult {
+        /// No Error
+        Ok = 0 => "Everything ok - no error",
+        LogInRequired = 1000 => "Log in required",
+        NoFullPathOrNameOrFolderIdProvided = 1001 => "No full path or name/folderid provided.",
+        NoFullPathOrFolderIdProvided = 1002 => "No full path or folder id provided.",
+        NoFileIdOrPathProvided = 1004 => "No file id or file path provided",
+        UnknownContentTypeRequested = 1005 => "Unknown content-type requested.",
+        ProvideFlags = 1006 => "Please provide flags.",
+        InvalidFileDescriptor = 1007 => "Invalid or closed file descriptor.",
+        ProvideOffset = 1008 => "Please provide 'offset'.",
+        ProvideLength = 1009 => "Please provide 'length'.",
+        ProvideCount = 1010 => "Please provide 'count'.",
+        InvalidWhence = 1011 => "Invalid 'whence' provided.",
+        DateTimeFormatNotUnderstood = 1013 => "Date time format not understood",
+        NoFullToPathOrToNameAndToFolderIdProvided = 1016 => "No full topath or toname/tofolderid provided.",
+        InvalidFolderId = 1017 => "Invalid 'folderid' provided.",
+        InvalidFileId = 1018 => "Invalid 'fileid' provided.",
+        InvalidToFolderId = 1021 => "Invalid 'tofolderid' provided.",
+        ProvideCode = 1022 => "Please provide 'code'.",
+        ProvideLinkId = 1023 => "Please provide 'linkid'.",
+        ProvideMail = 1024 => "Please provide 'mail'.",
+        ProvidePermissions = 1025 => "Please provide 'permissions'.",
+        ProvideShareRequestId = 1027 => "Please provide 'sharerequestid'.",
+        ProvideShareId = 1028 => "Please provide 'shareid'.",
+        ProvideUsername = 1033 => "Please provide 'username'.",
+        ProvidePassword = 1034 => "Please provide 'password'.",
+        ProvidedAtLeastToPathOrToFolderIdOrToName = 1037 => "Please provide at least one of 'topath', 'tofolderid' or 'toname'.",
+        ProvideURL = 1040 => "Provide url",
+        ProvideTokenId = 1076 => "Please provide 'tokenid'.",
+        UploadNotFound = 1900 => "Upload not found.",
+        TransferNotFound = 1901 => "Transfer not found.",
+        LoginFailed = 2000 => "Log in failed",
+        InvalidFileOrFolderName = 2001 => "Invalid file or folder name",
+        ComponentOfTheParentDirectoryDoesNotExist = 2002 => "A component of the parent directory does not exist",
+        AccessDenied = 2003 => "Access denied",
+        FileOrFolderAlreadyExists = 2004 => "File or folder already exists",
+        DirectoryDoesNotExist = 2005 => "Directory does not exist",
+        FolderIsNotEmpty = 2006 => "Folder is not empty",
+        CanNotDeleteRootFolder = 2007 => "Cannot delete the root folder.",
+        UserOverQuota = 2008 => "User over quota",
+        FileNotFound = 2009 => "File not found",
+        InvalidPath = 2010 => "Invalid path",
+        SpeedLimitTooLow = 2011 => "Requested speed limit too low, see minspeed for minimum.",
+        InvalidCode = 2012 => "Invalid 'code' provided.",
+        PleaseVerifyYourMailAddressToPerformThisAction = 2014 => "Please verify your mail address to perform this action",
+        CannotShareWithYourself = 2019 => "You can not share with yourself.",
+        CannotPlaceASharedFolderIntoAnotherSharedFolder = 2023 => "You are trying to place shared folder into another shared folder.",
+        FolderAlreadyShared = 2024 => "You already share this folder or a subfolder of it with this user.",
+        ShareRequestNotFound = 2025 => "Share request not found.",
+        YouCanOnlyShareYourOwnFilesOrFolders = 2026 => "You can only share your own files or folders",
+        ShareNotFound = 2027 => "Share not found.",
+        ActiveSharesOrShareRequestsForThisFolder = 2028 => "There are active shares or sharerequests for this folder.",
+        ConnectionBroken = 2041 => "Connection broken",
+        CannotRenameTheRootFolder = 2042 => "Cannot rename the root folder.",
+        CannotMoveAFolderToASubfolderOfItself = 2043 => "Cannot move a folder to a subfolder of itself.",
+        InvalidAccessToken = 2094 => "Invalid 'access_token' provided.",
+        TooManyPublicLinks = 2201 => "Maximum number of public links reached.",
+        TooManyLogins = 4000 => "Too many logins",
+        RateLimitExceeded = 4001 => "Too many requests. Try again later.",
+        InternalError = 5000 => "Internal error",
+        InternalUploadError = 5001 => "Internal upload error",
+        NoServersAvailable = 5002 => "Internal error, no servers available. Try again later.",
+        WriteError = 5003 => "Write error. Try reopening the file.",
+        ReadError = 5004 => "Read error. Try reopening the file.",
+        /// Returned by conditional methods when the requested action is not required
+        NotModified = 6000 => "Not modified",
+        InvalidLinkCode = 7001 => "Invalid link 'code'.",
+        LinkDeletedByOwner = 7002 => "This link is deleted by the owner.",
+        LinkTemporarilyUnavailable = 7003 => "This link is temporarily unavailable. Try again later.",
+        LinkExpired = 7004 => "This link has expired.",
+        LinkTrafficLimitReached = 7005 => "This link has reached its traffic limit.",
+        LinkDownloadLimitReached = 7006 => "This link has reached its maximum number of downloads.",
+    }
+}
+
+impl Serialize for PCloudResult {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u16(self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for PCloudResult {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(PCloudResult::from_code(u16::deserialize(deserializer)?))
     }
 }
+
 /// PCloudResult implements the Error trait
 impl std::error::Error for PCloudResult {}
 
+impl PCloudResult {
+    /// Error class according to the pCloud error taxonomy, the first digit of the code (1 to 7, 0 for Ok). See the table above.
+    pub fn error_class(&self) -> u8 {
+        (self.code() / 1000) as u8
+    }
+
+    /// True for errors which may succeed if retried later: synchronization errors (19xx), rate limits (4xxx), temporary server errors (5xxx), broken connections and temporarily unavailable links
+    pub fn is_retryable(&self) -> bool {
+        let code = self.code();
+        (1900..2000).contains(&code)
+            || matches!(self.error_class(), 4 | 5)
+            || *self == PCloudResult::ConnectionBroken
+            || *self == PCloudResult::LinkTemporarilyUnavailable
+    }
+
+    /// True if the request was rejected because of missing, wrong or expired credentials
+    pub fn is_auth_error(&self) -> bool {
+        matches!(
+            self,
+            PCloudResult::LogInRequired
+                | PCloudResult::LoginFailed
+                | PCloudResult::InvalidAccessToken
+        )
+    }
+
+    /// True if the file, folder or link requested doesn't exist (anymore)
+    pub fn is_not_found(&self) -> bool {
+        matches!(
+            self,
+            PCloudResult::ComponentOfTheParentDirectoryDoesNotExist
+                | PCloudResult::DirectoryDoesNotExist
+                | PCloudResult::FileNotFound
+                | PCloudResult::ShareRequestNotFound
+                | PCloudResult::ShareNotFound
+                | PCloudResult::InvalidLinkCode
+                | PCloudResult::LinkDeletedByOwner
+        )
+    }
+
+    /// Finds the pCloud error in an error returned by the client, also if wrapped into another error (following `source()`)
+    pub fn from_error<'e>(
+        error: &'e (dyn std::error::Error + 'static),
+    ) -> Option<&'e PCloudResult> {
+        let mut current = Some(error);
+        while let Some(error) = current {
+            if let Some(result) = error.downcast_ref::<PCloudResult>() {
+                return Some(result);
+            }
+            current = error.source();
+        }
+        None
+    }
+}
+
 /// Category of the file
 #[derive(Serialize_repr, Deserialize_repr, PartialEq, Debug)]
 #[repr(u8)]
@@ -177,16 +259,17 @@ pub trait WithPCloudResult {
     }
 }
 
-/// Result of the `getpublinkdownload` or `getfilelink` calls
+/// Result of the `getpublinkdownload`, `getfilelink` or `getaudiolink` calls
 /// see https://docs.pcloud.com/methods/public_links/getpublinkdownload.html
 /// see https://docs.pcloud.com/methods/streaming/getfilelink.html
+/// see https://docs.pcloud.com/methods/streaming/getaudiolink.html
 #[derive(Serialize, Deserialize, Debug)]
 pub struct DownloadLink {
     pub result: PCloudResult,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub path: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    #[serde(with = "pcloud_option_date_format")]
+    #[serde(with = "pcloud_option_date_format", default)]
     pub expires: Option<DateTime<Utc>>,
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub hosts: Vec<String>,
@@ -347,18 +430,9 @@ pub struct Share {
     /// date/time when the share request expires, not available in removeshare* and modifiedshare
     #[serde(with = "pcloud_option_date_format")]
     pub expires: Option<DateTime<Utc>>,
-    /// flag that you are granded read permissions, not available in removeshare
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub canread: Option<bool>,
-    /// flag that you are granded modify permissions, not available in removeshare
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub canmodify: Option<bool>,
-    /// flag that you are granded delete permissions, not available in removeshare
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub candelete: Option<bool>,
-    /// flag that you are granded create permissions, not available in removeshare
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub cancreate: Option<bool>,
+    /// permissions you are granted (canread, canmodify, candelete and cancreate), not available in removeshare
+    #[serde(flatten, with = "pcloud_permission_flags")]
+    pub permissions: Option<Permissions>,
     /// optional message provided by the user offering share (may not be provided), not available in removeshare* and modifiedshare*
     pub message: Option<String>,
 }
@@ -366,6 +440,7 @@ pub struct Share {
 /// The metadata for a file or folder normally consists of:
 /// see https://docs.pcloud.com/structures/metadata.html
 #[derive(Serialize, Deserialize, Debug)]
+#[serde(from = "MetadataFields")]
 pub struct Metadata {
     // is the folderid of the folder the object resides in
     pub parentfolderid: Option<u64>,
@@ -373,18 +448,9 @@ pub struct Metadata {
     pub isfolder: bool,
     /// is the object owned by the user if ismine is false than four other bool fields are provided: canread, canmodify, candelete, cancreate (cancreate - only for folders). These are user's permissions for this object Also, when ismine is false, userid is provided with the id of the owner of the file/folder.
     pub ismine: bool,
-    /// flag that you are granded read permissions
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub canread: Option<bool>,
-    ///  flag that you are granded modify permissions
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub canmodify: Option<bool>,
-    /// flag that you are granded delete permissions
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub candelete: Option<bool>,
-    /// flag that you are granded create permissions
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub cancreate: Option<bool>,
+    /// permissions you are granted (canread, canmodify, candelete and cancreate), only provided if ismine is false
+    #[serde(flatten, with = "pcloud_permission_flags")]
+    pub permissions: Option<Permissions>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub userid: Option<u64>,
     ///  is the object shared with other users
@@ -433,51 +499,177 @@ pub struct Metadata {
     /// Full path might be provided in some cases. If you work with paths and request folders by path, it will be provided. Recursive listings do not have path provided.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub path: Option<String>,
-    /// Optional for images / videos: width of the image in pixels
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub width: Option<u64>,
-    /// Optional for images / videos: height of the image in pixels
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub height: Option<u64>,
-    /// Optional for audio files
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Typed information of image, audio and video files (sent by pCloud as flat optional fields), see `media_info()`
+    #[serde(flatten, serialize_with = "pcloud_media_fields::serialize")]
+    pub media: Option<MediaInfo>,
+}
+
+/// Metadata as sent by pCloud. The media fields are only classified once the category and content type are known.
+#[derive(Deserialize)]
+struct MetadataFields {
+    parentfolderid: Option<u64>,
+    isfolder: bool,
+    ismine: bool,
+    #[serde(flatten, with = "pcloud_permission_flags")]
+    permissions: Option<Permissions>,
+    userid: Option<u64>,
+    isshared: bool,
+    name: String,
+    id: String,
+    folderid: Option<u64>,
+    fileid: Option<u64>,
+    deletefileid: Option<String>,
+    #[serde(with = "pcloud_date_format")]
+    created: DateTime<Utc>,
+    #[serde(with = "pcloud_date_format")]
+    modified: DateTime<Utc>,
+    icon: Option<FileIcon>,
+    category: Option<FileCategory>,
+    thumb: bool,
+    size: Option<u64>,
+    contenttype: Option<String>,
+    hash: Option<u64>,
+    #[serde(default)]
+    contents: Vec<Metadata>,
+    isdeleted: Option<bool>,
+    path: Option<String>,
+    #[serde(flatten)]
+    media: pcloud_media_fields::Fields,
+}
+
+impl From<MetadataFields> for Metadata {
+    fn from(value: MetadataFields) -> Self {
+        let media = value
+            .media
+            .classify(value.category.as_ref(), value.contenttype.as_deref());
+        Metadata {
+            parentfolderid: value.parentfolderid,
+            isfolder: value.isfolder,
+            ismine: value.ismine,
+            permissions: value.permissions,
+            userid: value.userid,
+            isshared: value.isshared,
+            name: value.name,
+            id: value.id,
+            folderid: value.folderid,
+            fileid: value.fileid,
+            deletefileid: value.deletefileid,
+            created: value.created,
+            modified: value.modified,
+            icon: value.icon,
+            category: value.category,
+            thumb: value.thumb,
+            size: value.size,
+            contenttype: value.contenttype,
+            hash: value.hash,
+            contents: value.contents,
+            isdeleted: value.isdeleted,
+            path: value.path,
+            media,
+        }
+    }
+}
+
+impl Metadata {
+    /// Fetches the metadata of the parent folder, see `PCloudClient::parent_of()`. Returns None for the root folder.
+    pub async fn parent(&self, client: &PCloudClient) -> Result<Option<Metadata>, PCloudError> {
+        client.parent_of(self).await
+    }
+
+    /// Typed information of image, audio and video files, if provided by pCloud
+    pub fn media_info(&self) -> Option<&MediaInfo> {
+        self.media.as_ref()
+    }
+
+    /// Permissions of the user on this file or folder. Objects owned by the user allow everything.
+    pub fn effective_permissions(&self) -> Permissions {
+        if self.ismine {
+            Permissions::all()
+        } else {
+            self.permissions.unwrap_or_default()
+        }
+    }
+}
+
+/// Media information of a file, depending on its kind
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaInfo {
+    /// Image file (category image)
+    Image(ImageInfo),
+    /// Audio file (category audio)
+    Audio(AudioInfo),
+    /// Video file (category video), including the tags of its audio track
+    Video(VideoInfo),
+}
+
+impl MediaInfo {
+    /// Width and height in pixels of images and videos
+    pub fn dimensions(&self) -> Option<(u64, u64)> {
+        match self {
+            MediaInfo::Image(image) => Some((image.width, image.height)),
+            MediaInfo::Video(video) => video.width.zip(video.height),
+            MediaInfo::Audio(_) => None,
+        }
+    }
+}
+
+/// Information of an image file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageInfo {
+    /// Width in pixels
+    pub width: u64,
+    /// Height in pixels
+    pub height: u64,
+}
+
+/// Tags of an audio file
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AudioInfo {
+    /// Performing artist
     pub artist: Option<String>,
-    /// Optional for audio files
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Album the track belongs to
     pub album: Option<String>,
-    /// Optional for audio files
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Title of the track
     pub title: Option<String>,
-    /// Optional for audio files
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Genre of the track
     pub genre: Option<String>,
-    /// Optional for audio files
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub trackno: Option<String>,
-    /// Optional for video files: duration of the video in seconds (floating point number sent as string)
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub duration: Option<String>,
-    /// Optional for video files: frames per second rate of the video (floating point number sent as string)
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub fps: Option<String>,
-    /// Optional for video files: codec used for encoding of the video
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub videocodec: Option<String>,
-    /// Optional for video files: codec used for encoding of the audio
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub audiocodec: Option<String>,
-    /// Optional for video files: bitrate of the video in kilobits
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub videobitrate: Option<u32>,
-    /// Optional for video files: bitrate of the audio in kilobits
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub audiobitrate: Option<u32>,
-    /// Optional for video files: sampling rate of the audio in Hz
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub audiosamplerate: Option<u32>,
-    /// Optional for video files:  indicates that video should be rotated (0, 90, 180 or 270) degrees when playing
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Track number as tagged, like `3` or `3/12`
+    pub track: Option<String>,
+}
+
+impl AudioInfo {
+    /// Number of the track, without the total number of tracks
+    pub fn track_number(&self) -> Option<u32> {
+        let track = self.track.as_deref()?;
+        track.split('/').next()?.trim().parse().ok()
+    }
+}
+
+/// Information of a video file
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct VideoInfo {
+    /// Width in pixels
+    pub width: Option<u64>,
+    /// Height in pixels
+    pub height: Option<u64>,
+    /// Playing time
+    pub duration: Option<Duration>,
+    /// Frames per second
+    pub fps: Option<f64>,
+    /// Codec used for encoding the video
+    pub video_codec: Option<String>,
+    /// Codec used for encoding the audio
+    pub audio_codec: Option<String>,
+    /// Bitrate of the video in kilobits
+    pub video_bitrate: Option<u32>,
+    /// Bitrate of the audio in kilobits
+    pub audio_bitrate: Option<u32>,
+    /// Sampling rate of the audio in Hz
+    pub audio_sample_rate: Option<u32>,
+    /// Rotation (0, 90, 180 or 270 degrees) to apply when playing
     pub rotate: Option<u16>,
+    /// Tags (artist, title, ...) of the video, if present
+    pub tags: Option<AudioInfo>,
 }
 
 /// Result of the `getapiserver`request
@@ -517,6 +709,74 @@ impl WithPCloudResult for FileOrFolderStat {
     }
 }
 
+/// Result of a folder listing with the contents of the folder kept as raw JSON until requested (see `ListFolderRequestBuilder::get_lazy()`)
+#[derive(Deserialize, Debug)]
+pub struct LazyFolderStat {
+    /// Result of the operation, must be Ok for further values to be present
+    pub result: PCloudResult,
+    /// Metadata of the targeted folder
+    pub metadata: Option<LazyFolder>,
+}
+
+impl WithPCloudResult for LazyFolderStat {
+    fn get_result(&self) -> &PCloudResult {
+        &self.result
+    }
+}
+
+/// Metadata of a folder whose contents are only decoded on demand
+#[derive(Debug)]
+pub struct LazyFolder {
+    /// Metadata of the folder itself, without contents
+    pub metadata: Metadata,
+    /// Raw JSON of the contents, if present
+    contents: Option<Box<serde_json::value::RawValue>>,
+}
+
+impl LazyFolder {
+    /// True if the listing contains any files or folders
+    pub fn has_contents(&self) -> bool {
+        self.contents
+            .as_ref()
+            .is_some_and(|raw| raw.get().trim() != "[]")
+    }
+
+    /// Decodes the contents of the folder
+    pub fn contents(&self) -> Result<Vec<Metadata>, serde_json::Error> {
+        match &self.contents {
+            Some(raw) => serde_json::from_str(raw.get()),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Decodes the contents and returns the complete metadata, as returned by `ListFolderRequestBuilder::get()`
+    pub fn into_metadata(self) -> Result<Metadata, serde_json::Error> {
+        let contents = self.contents()?;
+        Ok(Metadata {
+            contents,
+            ..self.metadata
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for LazyFolder {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        /// Fields of the folder itself decoded as metadata, the contents captured as is
+        #[derive(Deserialize)]
+        struct LazyFolderFields {
+            #[serde(flatten)]
+            metadata: Metadata,
+            contents: Option<Box<serde_json::value::RawValue>>,
+        }
+
+        let fields = LazyFolderFields::deserialize(deserializer)?;
+        Ok(LazyFolder {
+            metadata: fields.metadata,
+            contents: fields.contents,
+        })
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct FileRevision {
     /// id of the revision
@@ -544,6 +804,31 @@ pub struct RevisionList {
     pub revisions: Vec<FileRevision>,
 }
 
+impl RevisionList {
+    /// The most recently created revision
+    pub fn latest(&self) -> Option<&FileRevision> {
+        self.revisions.iter().max_by_key(|r| r.created)
+    }
+
+    /// The earliest created revision still kept by pCloud
+    pub fn oldest(&self) -> Option<&FileRevision> {
+        self.revisions.iter().min_by_key(|r| r.created)
+    }
+
+    /// Sorts the revisions by creation date, oldest first
+    pub fn sort_by_date(&mut self) {
+        self.revisions.sort_by_key(|r| r.created);
+    }
+
+    /// The most recently created revision with the given content hash (as in `Metadata::hash`)
+    pub fn find_by_hash(&self, hash: u64) -> Option<&FileRevision> {
+        self.revisions
+            .iter()
+            .filter(|r| r.hash == hash)
+            .max_by_key(|r| r.created)
+    }
+}
+
 impl WithPCloudResult for RevisionList {
     fn get_result(&self) -> &PCloudResult {
         &self.result
@@ -612,7 +897,7 @@ pub struct UserInfo {
     /// true if the user had verified it's email
     pub emailverified: Option<bool>,
     /// when the user was registered
-    #[serde(with = "pcloud_option_date_format")]
+    #[serde(with = "pcloud_option_date_format", default)]
     pub registered: Option<DateTime<Utc>>,
     /// 2-3 characters lowercase languageid
     pub language: Option<String>,
@@ -642,6 +927,30 @@ pub struct UploadedFile {
     pub metadata: Vec<Metadata>,
 }
 
+impl UploadedFile {
+    /// Pairs each file id with the metadata of the file. Fails if the ids and metadata returned by pCloud don't line up.
+    pub fn files(&self) -> Result<impl Iterator<Item = (u64, &Metadata)>, PCloudError> {
+        for index in 0..self.fileids.len().max(self.metadata.len()) {
+            match (self.fileids.get(index), self.metadata.get(index)) {
+                (Some(fileid), Some(metadata)) if metadata.fileid == Some(*fileid) => {}
+                (Some(fileid), _) => Err(PCloudError::MissingMetadata(format!("file {}", fileid)))?,
+                (None, metadata) => Err(PCloudError::MissingMetadata(
+                    metadata.map(|m| m.name.clone()).unwrap_or_default(),
+                ))?,
+            }
+        }
+        Ok(self.fileids.iter().copied().zip(&self.metadata))
+    }
+
+    /// Id and metadata of the uploaded file with the given name. Files renamed by `rename_if_exists()` are only found by their new name, `UploadReport::find_by_name()` also finds them by the requested name.
+    pub fn find_by_name(&self, name: &str) -> Option<(u64, &Metadata)> {
+        self.metadata
+            .iter()
+            .find(|m| m.name == name)
+            .and_then(|m| m.fileid.map(|id| (id, m)))
+    }
+}
+
 impl WithPCloudResult for UploadedFile {
     fn get_result(&self) -> &PCloudResult {
         &self.result
@@ -664,6 +973,150 @@ impl WithPCloudResult for LogoutResponse {
     }
 }
 
+/// Result of the `currentserver` request
+/// see https://docs.pcloud.com/methods/general/currentserver.html
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CurrentServer {
+    /// Result of the operation, must be Ok for further values to be present
+    pub result: PCloudResult,
+    /// IP address of the client as seen by the server
+    pub ip: Option<String>,
+    /// IPv6 address of the client as seen by the server
+    pub ipv6: Option<String>,
+    /// Hostname of the API server answering the request
+    pub hostname: Option<String>,
+}
+
+impl WithPCloudResult for CurrentServer {
+    fn get_result(&self) -> &PCloudResult {
+        &self.result
+    }
+}
+
+/// Result of the token validation
+#[derive(Debug)]
+pub struct TokenValidation {
+    /// True if the token (OAuth2 or session token) is accepted by pCloud
+    pub valid: bool,
+    /// Result returned by pCloud
+    pub result: PCloudResult,
+    /// Unique id of the user the token belongs to (only present for valid tokens)
+    pub userid: Option<u64>,
+    /// email address of the user the token belongs to (only present for valid tokens)
+    pub email: Option<String>,
+    /// Expiry of the token, as reported by `listtokens` (not present if the token isn't listed, e.g. for OAuth2 tokens)
+    pub expires: Option<DateTime<Utc>>,
+}
+
+/// Authentication token of the user
+/// see https://docs.pcloud.com/methods/auth/listtokens.html
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AuthToken {
+    /// Id of the token, e.g. to delete it
+    pub tokenid: u64,
+    /// Name of the device the token was created for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    /// True for the token used by this request
+    #[serde(default)]
+    pub current: bool,
+    /// creation date of the token
+    #[serde(with = "pcloud_option_date_format", default)]
+    pub created: Option<DateTime<Utc>>,
+    /// expiry date of the token
+    #[serde(with = "pcloud_option_date_format", default)]
+    pub expires: Option<DateTime<Utc>>,
+}
+
+/// Result of the `listtokens` call
+/// see https://docs.pcloud.com/methods/auth/listtokens.html
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TokenList {
+    /// Result of the operation, must be Ok for further values to be present
+    pub result: PCloudResult,
+    /// All tokens of the user
+    #[serde(default)]
+    pub tokens: Vec<AuthToken>,
+}
+
+impl WithPCloudResult for TokenList {
+    fn get_result(&self) -> &PCloudResult {
+        &self.result
+    }
+}
+
+/// Information about a file downloaded as stream
+#[derive(Debug)]
+pub struct FileInfo {
+    /// Metadata of the file
+    pub metadata: Option<Metadata>,
+    /// Size of the download in bytes, if announced by the server
+    pub content_length: Option<u64>,
+    /// Content type of the download
+    pub content_type: Option<String>,
+    /// SHA-1 checksum of the file. Always present
+    pub sha1: Option<String>,
+    /// MD5 checksum of the file, only returned from US API servers
+    pub md5: Option<String>,
+    /// SHA-256 checksum of the file, only returned in Europe
+    pub sha256: Option<String>,
+}
+
+/// Result of creating a chunked upload
+/// see https://docs.pcloud.com/methods/upload/upload_create.html
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UploadCreated {
+    /// Result of the operation, must be Ok for further values to be present
+    pub result: PCloudResult,
+    /// Id of the upload, used for all further upload_* calls
+    pub uploadid: Option<u64>,
+}
+
+impl WithPCloudResult for UploadCreated {
+    fn get_result(&self) -> &PCloudResult {
+        &self.result
+    }
+}
+
+/// Result of writing to or deleting a chunked upload
+/// see https://docs.pcloud.com/methods/upload/upload_write.html
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UploadWritten {
+    /// Result of the operation
+    pub result: PCloudResult,
+}
+
+impl WithPCloudResult for UploadWritten {
+    fn get_result(&self) -> &PCloudResult {
+        &self.result
+    }
+}
+
+/// Current state of a chunked upload
+/// see https://docs.pcloud.com/methods/upload/upload_info.html
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UploadInfo {
+    /// Result of the operation, must be Ok for further values to be present
+    pub result: PCloudResult,
+    /// Number of bytes received so far
+    pub size: Option<u64>,
+    /// MD5 checksum of the data received, is returned only from US API servers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub md5: Option<String>,
+    /// SHA-1 checksum of the data received
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<String>,
+    /// SHA-256 checksum of the data received, is returned in Europe only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+impl WithPCloudResult for UploadInfo {
+    fn get_result(&self) -> &PCloudResult {
+        &self.result
+    }
+}
+
 /// Result of opening a file
 /// see https://docs.pcloud.com/methods/fileops/file_open.html
 #[derive(Serialize, Deserialize, Debug)]
@@ -671,8 +1124,10 @@ pub struct FileOpenResponse {
     /// Result of the operation, must be Ok for further values to be present
     pub result: PCloudResult,
     /// File descriptor
+    #[serde(default)]
     pub fd: u64,
     /// File id
+    #[serde(default)]
     pub fileid: u64,
 }
 
@@ -712,6 +1167,122 @@ impl WithPCloudResult for FileWriteResponse {
     }
 }
 
+/// Result of a file operation without further values, like truncating a file or a failed read
+/// see https://docs.pcloud.com/methods/fileops/file_truncate.html
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FileOpResult {
+    /// Result of the operation
+    pub result: PCloudResult,
+}
+
+impl WithPCloudResult for FileOpResult {
+    fn get_result(&self) -> &PCloudResult {
+        &self.result
+    }
+}
+
+/// Result of seeking in a file
+/// see https://docs.pcloud.com/methods/fileops/file_seek.html
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FileSeekResponse {
+    /// Result of the operation, must be Ok for further values to be present
+    pub result: PCloudResult,
+    /// new offset of the file descriptor
+    pub offset: Option<u64>,
+}
+
+impl WithPCloudResult for FileSeekResponse {
+    fn get_result(&self) -> &PCloudResult {
+        &self.result
+    }
+}
+
+/// Size and current offset of an open file
+/// see https://docs.pcloud.com/methods/fileops/file_size.html
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FileSizeResponse {
+    /// Result of the operation, must be Ok for further values to be present
+    pub result: PCloudResult,
+    /// size of the file in bytes
+    pub size: Option<u64>,
+    /// current offset of the file descriptor
+    pub offset: Option<u64>,
+}
+
+impl WithPCloudResult for FileSizeResponse {
+    fn get_result(&self) -> &PCloudResult {
+        &self.result
+    }
+}
+
+/// Thumbnail link of a single file, as returned by the `getthumbslinks` call
+/// see https://docs.pcloud.com/methods/thumbnails/getthumbslinks.html
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ThumbnailLink {
+    /// Id of the file
+    pub fileid: u64,
+    /// Result for this file, the other values are only present if Ok
+    pub result: PCloudResult,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(with = "pcloud_option_date_format", default)]
+    pub expires: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub hosts: Vec<String>,
+    /// Actual size of the thumbnail, like "120x90"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<String>,
+}
+
+impl ThumbnailLink {
+    /// Converts the link into a DownloadLink, e.g. to download it with `PCloudClient::download_link()`
+    pub fn into_download_link(self) -> DownloadLink {
+        DownloadLink {
+            result: self.result,
+            path: self.path,
+            expires: self.expires,
+            hosts: self.hosts,
+        }
+    }
+}
+
+/// Result of the `getthumbslinks` call
+/// see https://docs.pcloud.com/methods/thumbnails/getthumbslinks.html
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ThumbnailLinks {
+    pub result: PCloudResult,
+    /// Links of all files, in the order requested
+    #[serde(default)]
+    pub thumbs: Vec<ThumbnailLink>,
+}
+
+impl WithPCloudResult for ThumbnailLinks {
+    fn get_result(&self) -> &PCloudResult {
+        &self.result
+    }
+}
+
+/// Result of the `savethumb` call
+/// see https://docs.pcloud.com/methods/thumbnails/savethumb.html
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SavedThumbnail {
+    pub result: PCloudResult,
+    /// Metadata of the created file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Metadata>,
+    /// Actual width of the thumbnail
+    pub width: Option<u32>,
+    /// Actual height of the thumbnail
+    pub height: Option<u32>,
+}
+
+impl WithPCloudResult for SavedThumbnail {
+    fn get_result(&self) -> &PCloudResult {
+        &self.result
+    }
+}
+
 /// Get the progress in process of zipping file in the user's filesystem.
 /// see https://docs.pcloud.com/methods/archiving/savezipprogress.html
 #[derive(Serialize, Deserialize, Debug)]
@@ -726,6 +1297,235 @@ pub struct SaveZipProgressResponse {
     pub totalbytes: u64,
 }
 
+/// Type of a collection
+#[derive(Serialize_repr, Deserialize_repr, PartialEq, Debug, Clone, Copy)]
+#[repr(u8)]
+pub enum CollectionType {
+    /// Audio playlist, the only type currently supported by pCloud
+    Audio = 1,
+}
+
+/// A collection (playlist) of files
+/// see https://docs.pcloud.com/structures/collection.html
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Collection {
+    /// Id of the collection
+    pub id: u64,
+    /// Name of the collection
+    pub name: String,
+    /// Is the collection owned by the user
+    pub ismine: bool,
+    /// Collections created by pCloud itself (like the most played songs) can't be modified
+    #[serde(default)]
+    pub system: bool,
+    /// Type of the collection
+    #[serde(rename = "type")]
+    pub collection_type: CollectionType,
+    /// Number of files in the collection
+    pub items: u64,
+    /// creation date of the collection
+    #[serde(with = "pcloud_date_format")]
+    pub created: DateTime<Utc>,
+    /// modification date of the collection
+    #[serde(with = "pcloud_date_format")]
+    pub modified: DateTime<Utc>,
+    /// Files of the collection in their order, only present if requested
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub contents: Vec<CollectionItem>,
+}
+
+/// Single file of a collection
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CollectionItem {
+    /// Metadata of the file, including artist, album and title of audio files
+    #[serde(flatten)]
+    pub metadata: Metadata,
+}
+
+/// Result of the collection_list call
+/// see https://docs.pcloud.com/methods/collection/collection_list.html
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CollectionList {
+    /// Result of the operation, must be Ok for further values to be present
+    pub result: PCloudResult,
+    /// All collections of the user
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub collections: Vec<Collection>,
+}
+
+impl WithPCloudResult for CollectionList {
+    fn get_result(&self) -> &PCloudResult {
+        &self.result
+    }
+}
+
+/// Result of the collection_details call
+/// see https://docs.pcloud.com/methods/collection/collection_details.html
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CollectionDetails {
+    /// Result of the operation, must be Ok for further values to be present
+    pub result: PCloudResult,
+    /// The collection including its files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collection: Option<Collection>,
+}
+
+impl WithPCloudResult for CollectionDetails {
+    fn get_result(&self) -> &PCloudResult {
+        &self.result
+    }
+}
+
+/// Outcome of linking a single file to a collection
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CollectionLinkEntry {
+    /// Id of the file
+    pub fileid: u64,
+    /// Result of linking the file, Ok if it was added
+    pub result: PCloudResult,
+    /// Error message, if the file wasn't added
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Result of the collection_linkfiles call
+/// see https://docs.pcloud.com/methods/collection/collection_linkfiles.html
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CollectionLinkResult {
+    /// Result of the operation, must be Ok for further values to be present
+    pub result: PCloudResult,
+    /// Outcome for each file, in the order given
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub linkresult: Vec<CollectionLinkEntry>,
+    /// The collection including its files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collection: Option<Collection>,
+}
+
+impl WithPCloudResult for CollectionLinkResult {
+    fn get_result(&self) -> &PCloudResult {
+        &self.result
+    }
+}
+
+/// Author of a file comment
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CommentAuthor {
+    /// Id of the user
+    pub userid: u64,
+    /// Mail address of the user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+}
+
+/// Comment on a file
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Comment {
+    /// Id of the comment
+    pub id: u64,
+    /// Id of the file commented
+    pub fileid: u64,
+    /// Text of the comment
+    #[serde(rename = "comment")]
+    pub text: String,
+    /// User who posted the comment
+    pub author: CommentAuthor,
+    /// Date/time the comment was posted
+    #[serde(with = "pcloud_date_format")]
+    pub created: DateTime<Utc>,
+    /// Date/time the comment was edited last, if ever
+    #[serde(
+        with = "pcloud_option_date_format",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub modified: Option<DateTime<Utc>>,
+}
+
+/// Result of the commentlist call
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CommentList {
+    /// Result of the operation, must be Ok for further values to be present
+    pub result: PCloudResult,
+    /// All comments of the file, oldest first
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub comments: Vec<Comment>,
+}
+
+impl WithPCloudResult for CommentList {
+    fn get_result(&self) -> &PCloudResult {
+        &self.result
+    }
+}
+
+/// Result of the commentpost and commentdel calls
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CommentResult {
+    /// Result of the operation, must be Ok for further values to be present
+    pub result: PCloudResult,
+    /// The comment posted or deleted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<Comment>,
+}
+
+impl WithPCloudResult for CommentResult {
+    fn get_result(&self) -> &PCloudResult {
+        &self.result
+    }
+}
+
+/// Upload link, allowing anyone with the link to upload files into a folder
+/// see https://docs.pcloud.com/methods/upload_links/listuploadlinks.html
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UploadLink {
+    /// Id of the upload link
+    pub id: u64,
+    /// Comment shown to the uploaders
+    #[serde(default)]
+    pub comment: String,
+    /// Full link to share
+    pub link: String,
+    /// Code of the link
+    pub code: String,
+    /// Number of files uploaded through the link
+    #[serde(default)]
+    pub files: u64,
+    /// Number of bytes uploaded through the link
+    #[serde(default)]
+    pub space: u64,
+    /// Maximum number of files accepted, if limited
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maxfiles: Option<u64>,
+    /// Maximum number of bytes accepted, if limited
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maxspace: Option<u64>,
+    /// creation date of the link
+    #[serde(with = "pcloud_date_format")]
+    pub created: DateTime<Utc>,
+    /// modification date of the link, changes with each upload
+    #[serde(with = "pcloud_date_format")]
+    pub modified: DateTime<Utc>,
+    /// Metadata of the folder receiving the uploads
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Metadata>,
+}
+
+/// Result of the listuploadlinks call
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UploadLinkList {
+    /// Result of the operation, must be Ok for further values to be present
+    pub result: PCloudResult,
+    /// All upload links of the user
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub uploadlinks: Vec<UploadLink>,
+}
+
+impl WithPCloudResult for UploadLinkList {
+    fn get_result(&self) -> &PCloudResult {
+        &self.result
+    }
+}
+
 /// Converts a DateTime for pCloud URLs
 pub fn format_date_time_for_pcloud<Tz>(datetime: &DateTime<Tz>) -> String
 where
@@ -774,6 +1574,251 @@ mod pcloud_date_format {
     }
 }
 
+/// pCloud media fields (width, height, artist, duration, videocodec, ...) for serializing / deserializing typed media information
+mod pcloud_media_fields {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Serialize, Serializer};
+
+    use super::{AudioInfo, FileCategory, ImageInfo, MediaInfo, VideoInfo};
+
+    /// Floating point number, which pCloud usually sends as string
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum Number {
+        Text(String),
+        Value(f64),
+    }
+
+    impl Number {
+        fn value(&self) -> Option<f64> {
+            match self {
+                Number::Text(text) => text.trim().parse().ok(),
+                Number::Value(value) => Some(*value),
+            }
+        }
+    }
+
+    /// The separate optional fields as sent by pCloud
+    #[derive(Serialize, Deserialize, Default)]
+    pub(super) struct Fields {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        width: Option<u64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        height: Option<u64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        artist: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        album: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        genre: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        trackno: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        duration: Option<Number>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        fps: Option<Number>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        videocodec: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        audiocodec: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        videobitrate: Option<u32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        audiobitrate: Option<u32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        audiosamplerate: Option<u32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        rotate: Option<u16>,
+    }
+
+    impl Fields {
+        /// Picks the kind of media by the category (or content type) of the file. Without either, the kind is guessed from the fields present.
+        pub(super) fn classify(
+            self,
+            category: Option<&FileCategory>,
+            contenttype: Option<&str>,
+        ) -> Option<MediaInfo> {
+            let kind = match (category, contenttype) {
+                (Some(FileCategory::Image), _) => Some(FileCategory::Image),
+                (Some(FileCategory::Video), _) => Some(FileCategory::Video),
+                (Some(FileCategory::Audio), _) => Some(FileCategory::Audio),
+                (Some(_), _) => None,
+                (None, Some(t)) if t.starts_with("image/") => Some(FileCategory::Image),
+                (None, Some(t)) if t.starts_with("video/") => Some(FileCategory::Video),
+                (None, Some(t)) if t.starts_with("audio/") => Some(FileCategory::Audio),
+                (None, _) => self.guess(),
+            };
+
+            match kind? {
+                FileCategory::Image => self
+                    .width
+                    .zip(self.height)
+                    .map(|(width, height)| MediaInfo::Image(ImageInfo { width, height })),
+                FileCategory::Audio => self.audio().map(MediaInfo::Audio),
+                FileCategory::Video => {
+                    let tags = self.audio();
+                    let video = VideoInfo {
+                        width: self.width,
+                        height: self.height,
+                        duration: self
+                            .duration
+                            .and_then(|d| d.value())
+                            .and_then(|d| Duration::try_from_secs_f64(d).ok()),
+                        fps: self.fps.and_then(|f| f.value()),
+                        video_codec: self.videocodec,
+                        audio_codec: self.audiocodec,
+                        video_bitrate: self.videobitrate,
+                        audio_bitrate: self.audiobitrate,
+                        audio_sample_rate: self.audiosamplerate,
+                        rotate: self.rotate,
+                        tags,
+                    };
+                    (video != VideoInfo::default()).then_some(MediaInfo::Video(video))
+                }
+                _ => None,
+            }
+        }
+
+        /// Kind of media guessed from the fields present, if neither category nor content type are known
+        fn guess(&self) -> Option<FileCategory> {
+            let is_video = self.duration.is_some()
+                || self.fps.is_some()
+                || self.videocodec.is_some()
+                || self.audiocodec.is_some()
+                || self.videobitrate.is_some()
+                || self.audiobitrate.is_some()
+                || self.audiosamplerate.is_some()
+                || self.rotate.is_some();
+            if is_video {
+                Some(FileCategory::Video)
+            } else if self.audio().is_some() {
+                Some(FileCategory::Audio)
+            } else if self.width.is_some() && self.height.is_some() {
+                Some(FileCategory::Image)
+            } else {
+                None
+            }
+        }
+
+        /// Audio tags, if any are present
+        fn audio(&self) -> Option<AudioInfo> {
+            let audio = AudioInfo {
+                artist: self.artist.clone(),
+                album: self.album.clone(),
+                title: self.title.clone(),
+                genre: self.genre.clone(),
+                track: self.trackno.clone(),
+            };
+            (audio != AudioInfo::default()).then_some(audio)
+        }
+    }
+
+    pub fn serialize<S>(inp: &Option<MediaInfo>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let audio_fields = |audio: &AudioInfo| Fields {
+            artist: audio.artist.clone(),
+            album: audio.album.clone(),
+            title: audio.title.clone(),
+            genre: audio.genre.clone(),
+            trackno: audio.track.clone(),
+            ..Default::default()
+        };
+        let fields = match inp {
+            Some(MediaInfo::Image(image)) => Fields {
+                width: Some(image.width),
+                height: Some(image.height),
+                ..Default::default()
+            },
+            Some(MediaInfo::Audio(audio)) => audio_fields(audio),
+            Some(MediaInfo::Video(video)) => Fields {
+                width: video.width,
+                height: video.height,
+                duration: video
+                    .duration
+                    .map(|d| Number::Text(d.as_secs_f64().to_string())),
+                fps: video.fps.map(|f| Number::Text(f.to_string())),
+                videocodec: video.video_codec.clone(),
+                audiocodec: video.audio_codec.clone(),
+                videobitrate: video.video_bitrate,
+                audiobitrate: video.audio_bitrate,
+                audiosamplerate: video.audio_sample_rate,
+                rotate: video.rotate,
+                ..video.tags.as_ref().map(audio_fields).unwrap_or_default()
+            },
+            None => Fields::default(),
+        };
+        fields.serialize(serializer)
+    }
+}
+
+/// pCloud permission flags (canread, canmodify, candelete, cancreate) for serializing / deserializing optional permissions
+mod pcloud_permission_flags {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::permissions::Permissions;
+
+    /// The four separate flags as sent by pCloud
+    #[derive(Serialize, Deserialize, Default)]
+    struct Flags {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        canread: Option<bool>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        canmodify: Option<bool>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        candelete: Option<bool>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cancreate: Option<bool>,
+    }
+
+    pub fn serialize<S>(inp: &Option<Permissions>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let flags = match inp {
+            Some(p) => Flags {
+                canread: Some(p.can_read()),
+                canmodify: Some(p.can_modify()),
+                candelete: Some(p.can_delete()),
+                // Only folders have the create permission, pCloud omits it for files
+                cancreate: p.can_create().then_some(true),
+            },
+            None => Flags::default(),
+        };
+        flags.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Permissions>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let flags = Flags::deserialize(deserializer)?;
+        let all = [
+            (flags.canread, Permissions::READ),
+            (flags.canmodify, Permissions::MODIFY),
+            (flags.candelete, Permissions::DELETE),
+            (flags.cancreate, Permissions::CREATE),
+        ];
+        if all.iter().all(|(flag, _)| flag.is_none()) {
+            return Ok(None);
+        }
+        Ok(Some(all.iter().fold(
+            Permissions::empty(),
+            |p, (flag, f)| {
+                if flag.unwrap_or(false) {
+                    p | *f
+                } else {
+                    p
+                }
+            },
+        )))
+    }
+}
+
 /// pCloud Date format for serializing / deserializing optional values
 mod pcloud_option_date_format {
     use chrono::{DateTime, TimeZone, Utc};
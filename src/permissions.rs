@@ -0,0 +1,167 @@
+use std::fmt::Display;
+
+use bitflags::bitflags;
+use log::debug;
+
+use crate::{
+    error::PCloudError,
+    file_ops::PCloudFile,
+    folder_ops::PCloudFolder,
+    pcloud_client::PCloudClient,
+    pcloud_model::{Metadata, PCloudResult},
+};
+
+/// Error of an operation on a file or folder shared by another user without the necessary permission (see `check_permissions()` of the copy, move and delete requests)
+#[derive(Debug, Clone, PartialEq)]
+pub struct PermissionDenied {
+    /// Operation refused (`copy`, `move` or `delete`)
+    pub operation: String,
+    /// Permissions missing
+    pub permission: Permissions,
+    /// Name of the file or folder
+    pub name: String,
+    /// Unique id of the file or folder ('f' + file id or 'd' + folder id)
+    pub id: String,
+    /// Id of the user owning the file or folder
+    pub owner: Option<u64>,
+}
+
+impl Display for PermissionDenied {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Permission denied: {} requires {} permission on '{}' ({})",
+            self.operation, self.permission, self.name, self.id
+        )?;
+        if let Some(owner) = self.owner {
+            write!(f, " shared by user {}", owner)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for PermissionDenied {}
+
+bitflags! {
+    /// Set of permissions granted on a file or folder shared by another user (see `Metadata::permissions` and `Share::permissions`)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct Permissions: u8 {
+        /// Files can be read
+        const READ = 1;
+        /// Files can be modified
+        const MODIFY = 1 << 1;
+        /// Files and folders can be deleted
+        const DELETE = 1 << 2;
+        /// Files and folders can be created (folders only)
+        const CREATE = 1 << 3;
+    }
+}
+
+impl Permissions {
+    /// True if files can be read
+    pub fn can_read(&self) -> bool {
+        self.contains(Permissions::READ)
+    }
+
+    /// True if files can be modified, created and deleted
+    pub fn can_write(&self) -> bool {
+        self.contains(Permissions::MODIFY | Permissions::CREATE | Permissions::DELETE)
+    }
+
+    /// True if files can be modified
+    pub fn can_modify(&self) -> bool {
+        self.contains(Permissions::MODIFY)
+    }
+
+    /// True if files and folders can be deleted
+    pub fn can_delete(&self) -> bool {
+        self.contains(Permissions::DELETE)
+    }
+
+    /// True if files and folders can be created
+    pub fn can_create(&self) -> bool {
+        self.contains(Permissions::CREATE)
+    }
+}
+
+impl Display for Permissions {
+    /// Lower case names of the permissions, like `read, create`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<String> = self
+            .iter_names()
+            .map(|(name, _)| name.to_lowercase())
+            .collect();
+        write!(f, "{}", names.join(", "))
+    }
+}
+
+/// Checks the permission in the metadata of a file or folder. Objects owned by the user allow everything.
+pub(crate) fn check_permission(
+    metadata: &Metadata,
+    permission: Permissions,
+    operation: &str,
+) -> Result<(), PermissionDenied> {
+    if metadata.effective_permissions().contains(permission) {
+        Ok(())
+    } else {
+        Err(PermissionDenied {
+            operation: operation.to_string(),
+            permission,
+            name: metadata.name.clone(),
+            id: metadata.id.clone(),
+            owner: metadata.userid,
+        })
+    }
+}
+
+/// Fetches the metadata of the folder and checks the permission
+pub(crate) async fn require_folder_permission(
+    client: &PCloudClient,
+    folder: &PCloudFolder,
+    permission: Permissions,
+    operation: &str,
+) -> Result<(), PCloudError> {
+    debug!("Checking {} permission on folder {}", permission, folder);
+    let metadata = client
+        .list_folder(folder)?
+        .nofiles(true)
+        .folder_metadata()
+        .await?;
+    check_permission(&metadata, permission, operation)?;
+    Ok(())
+}
+
+/// Fetches the metadata of the file and checks the permission
+pub(crate) async fn require_file_permission(
+    client: &PCloudClient,
+    file: &PCloudFile,
+    permission: Permissions,
+    operation: &str,
+) -> Result<(), PCloudError> {
+    debug!("Checking {} permission on file {}", permission, file);
+    let metadata = client
+        .get_file_metadata(file)
+        .await?
+        .metadata
+        .ok_or(PCloudResult::FileNotFound)?;
+    check_permission(&metadata, permission, operation)?;
+    Ok(())
+}
+
+/// Folder receiving the result of a copy or move. A target path without trailing '/' names the result itself, so its parent folder receives it.
+pub(crate) fn receiving_folder(folder_id: Option<u64>, path: Option<&String>) -> PCloudFolder {
+    match (folder_id, path) {
+        (Some(folder_id), _) => PCloudFolder::from(folder_id),
+        (None, Some(path)) if !path.ends_with('/') => {
+            let parent = path.rsplit_once('/').map(|(p, _)| p).unwrap_or("");
+            PCloudFolder {
+                folder_id: None,
+                path: Some(if parent.is_empty() { "/" } else { parent }.to_string()),
+            }
+        }
+        (None, path) => PCloudFolder {
+            folder_id: None,
+            path: path.cloned(),
+        },
+    }
+}
@@ -0,0 +1,191 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+
+/// Receives the progress of uploads, downloads, syncs and bulk operations. An operation runs one or more items (like files), each item is started, reports the bytes transferred so far and is finished. Items may be started again (e.g. when a transfer is retried or interrupted), their progress then starts over.
+/// All methods default to doing nothing. They are called from the tasks running the operation and should return quickly.
+pub trait ProgressObserver: Send + Sync {
+    /// The operation started with the given number of items and total bytes, if known in advance
+    fn operation_started(&self, _items: Option<usize>, _total_bytes: Option<u64>) {}
+
+    /// An item (like a file) started with the given size, if known
+    fn item_started(&self, _item: &str, _size: Option<u64>) {}
+
+    /// Bytes of the item transferred so far
+    fn item_progress(&self, _item: &str, _transferred: u64) {}
+
+    /// The item completed successfully or with the given error
+    fn item_finished(&self, _item: &str, _error: Option<&(dyn std::error::Error + Send + Sync)>) {}
+
+    /// The operation completed
+    fn operation_finished(&self) {}
+}
+
+/// Reports the bytes passing through the stream as progress of the item
+pub(crate) fn observe<S, E>(
+    stream: S,
+    observer: Arc<dyn ProgressObserver>,
+    item: String,
+) -> impl Stream<Item = Result<Bytes, E>>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+{
+    let mut transferred = 0;
+    stream.map(move |chunk| {
+        if let Ok(chunk) = &chunk {
+            transferred += chunk.len() as u64;
+            observer.item_progress(&item, transferred);
+        }
+        chunk
+    })
+}
+
+/// Forwards only the item events, for operations made of other operations reporting their items. Optionally reports them under another name.
+pub(crate) struct ItemsOnly {
+    observer: Arc<dyn ProgressObserver>,
+    name: Option<String>,
+}
+
+impl ItemsOnly {
+    /// Forwards the item events unchanged
+    pub(crate) fn forward(observer: Arc<dyn ProgressObserver>) -> Arc<dyn ProgressObserver> {
+        Arc::new(ItemsOnly {
+            observer,
+            name: None,
+        })
+    }
+
+    /// Forwards the item events as events of the given item
+    pub(crate) fn renamed(
+        observer: Arc<dyn ProgressObserver>,
+        name: &str,
+    ) -> Arc<dyn ProgressObserver> {
+        Arc::new(ItemsOnly {
+            observer,
+            name: Some(name.to_string()),
+        })
+    }
+
+    fn item<'a>(&'a self, item: &'a str) -> &'a str {
+        self.name.as_deref().unwrap_or(item)
+    }
+}
+
+impl ProgressObserver for ItemsOnly {
+    fn item_started(&self, item: &str, size: Option<u64>) {
+        self.observer.item_started(self.item(item), size)
+    }
+
+    fn item_progress(&self, item: &str, transferred: u64) {
+        self.observer.item_progress(self.item(item), transferred)
+    }
+
+    fn item_finished(&self, item: &str, error: Option<&(dyn std::error::Error + Send + Sync)>) {
+        self.observer.item_finished(self.item(item), error)
+    }
+}
+
+#[cfg(feature = "indicatif")]
+pub use self::indicatif_progress::IndicatifProgress;
+
+#[cfg(feature = "indicatif")]
+mod indicatif_progress {
+    use std::{collections::HashMap, sync::Mutex};
+
+    use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+
+    use super::ProgressObserver;
+
+    /// Progress bars for CLI tools: one bar counting the finished items of the operation and one bar per running item showing its bytes and speed. Failed items are printed above the bars.
+    pub struct IndicatifProgress {
+        /// Container of all bars
+        multi: MultiProgress,
+        /// Items finished
+        overall: ProgressBar,
+        /// Bars of the running items
+        items: Mutex<HashMap<String, ProgressBar>>,
+    }
+
+    impl IndicatifProgress {
+        /// Creates the progress bars, drawn to stderr
+        pub fn new() -> IndicatifProgress {
+            IndicatifProgress::with_multi_progress(MultiProgress::new())
+        }
+
+        /// Adds the progress bars to the given container, e.g. to show them along with other bars of the application
+        pub fn with_multi_progress(multi: MultiProgress) -> IndicatifProgress {
+            let overall = multi.add(ProgressBar::new(0));
+            overall.set_style(
+                ProgressStyle::with_template("{prefix:>10} [{bar:30}] {pos}/{len} files {elapsed}")
+                    .expect("Valid template")
+                    .progress_chars("=> "),
+            );
+            overall.set_prefix("Total");
+            IndicatifProgress {
+                multi,
+                overall,
+                items: Mutex::new(HashMap::new()),
+            }
+        }
+    }
+
+    impl Default for IndicatifProgress {
+        fn default() -> Self {
+            IndicatifProgress::new()
+        }
+    }
+
+    impl ProgressObserver for IndicatifProgress {
+        fn operation_started(&self, items: Option<usize>, _total_bytes: Option<u64>) {
+            if let Some(items) = items {
+                self.overall.set_length(items as u64);
+            }
+        }
+
+        fn item_started(&self, item: &str, size: Option<u64>) {
+            let mut items = self.items.lock().unwrap();
+            let bar = items.entry(item.to_string()).or_insert_with(|| {
+                let bar = self.multi.add(ProgressBar::new(0));
+                bar.set_style(
+                    ProgressStyle::with_template(
+                        "{msg:.dim} {bytes}/{total_bytes} {binary_bytes_per_sec} {eta}",
+                    )
+                    .expect("Valid template"),
+                );
+                bar.set_message(item.to_string());
+                bar
+            });
+            bar.set_length(size.unwrap_or_default());
+            bar.set_position(0);
+            if self.overall.length() <= Some(self.overall.position()) {
+                // Items not announced by the operation
+                self.overall.inc_length(1);
+            }
+        }
+
+        fn item_progress(&self, item: &str, transferred: u64) {
+            if let Some(bar) = self.items.lock().unwrap().get(item) {
+                if bar.length() < Some(transferred) {
+                    bar.set_length(transferred);
+                }
+                bar.set_position(transferred);
+            }
+        }
+
+        fn item_finished(&self, item: &str, error: Option<&(dyn std::error::Error + Send + Sync)>) {
+            if let Some(bar) = self.items.lock().unwrap().remove(item) {
+                bar.finish_and_clear();
+                self.multi.remove(&bar);
+            }
+            if let Some(error) = error {
+                let _ = self.multi.println(format!("Failed {}: {}", item, error));
+            }
+            self.overall.inc(1);
+        }
+
+        fn operation_finished(&self) {
+            self.overall.finish();
+        }
+    }
+}
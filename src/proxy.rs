@@ -0,0 +1,64 @@
+#![cfg(feature = "proxy")]
+use http::{header, HeaderValue};
+use log::debug;
+
+use crate::{
+    error::PCloudError, file_ops::FileDescriptor, pcloud_client::PCloudClient,
+    pcloud_model::DownloadLink,
+};
+
+/// Response headers of the download forwarded to the client
+const FORWARDED_HEADERS: [header::HeaderName; 7] = [
+    header::CONTENT_LENGTH,
+    header::CONTENT_TYPE,
+    header::CONTENT_RANGE,
+    header::ACCEPT_RANGES,
+    header::LAST_MODIFIED,
+    header::ETAG,
+    header::CONTENT_DISPOSITION,
+];
+
+/// Converts a pCloud download into a streaming http response (usable as hyper or axum response) without buffering the body. Status code and content headers (length, type, range) are propagated.
+pub fn into_http_response(
+    response: reqwest::Response,
+) -> Result<http::Response<hyper::Body>, PCloudError> {
+    let mut builder = http::Response::builder().status(response.status().as_u16());
+
+    for name in FORWARDED_HEADERS {
+        if let Some(value) = response.headers().get(name.as_str()) {
+            builder = builder.header(name, HeaderValue::from_bytes(value.as_bytes())?);
+        }
+    }
+
+    let body = hyper::Body::wrap_stream(response.bytes_stream());
+    Ok(builder.body(body)?)
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Downloads a DownloadLink as streaming http response, e.g. to proxy it from a web service to browsers. The given range header (if any) is forwarded to pCloud.
+    pub async fn proxy_download_link(
+        &self,
+        link: &DownloadLink,
+        range: Option<&HeaderValue>,
+    ) -> Result<http::Response<hyper::Body>, PCloudError> {
+        debug!("Proxying file link {:?} (range {:?})", link.path, range);
+        let response = self
+            .send_download(link, |r| match range {
+                Some(range) => r.header(header::RANGE.as_str(), range.as_bytes()),
+                None => r,
+            })
+            .await?;
+        into_http_response(response)
+    }
+
+    /// Downloads the latest revision of a file as streaming http response, e.g. to proxy it from a web service to browsers. The given range header (if any) is forwarded to pCloud. Accepts either a file id (u64), a file path (String) or any other pCloud object describing a file (like Metadata)
+    pub async fn proxy_download<T: FileDescriptor>(
+        &self,
+        file_like: T,
+        range: Option<&HeaderValue>,
+    ) -> Result<http::Response<hyper::Body>, PCloudError> {
+        let link = self.get_download_link_for_file(file_like)?.get().await?;
+        self.proxy_download_link(&link, range).await
+    }
+}
@@ -0,0 +1,234 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use futures::StreamExt;
+use log::debug;
+use tokio::io::AsyncWriteExt;
+
+use crate::{
+    error::PCloudError,
+    pcloud_client::PCloudClient,
+    pcloud_model::{self, FileOrFolderStat, Metadata},
+};
+
+/// Default number of files downloaded in parallel
+pub const DEFAULT_PUBLIC_DOWNLOAD_CONCURRENCY: usize = 4;
+
+/// Callback invoked after each downloaded file
+type ProgressCallback = Arc<dyn Fn(&PublicDownloadProgress) + Send + Sync>;
+
+/// Progress of a public link download, reported after each file
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublicDownloadProgress {
+    /// Path (relative to the shared folder, separated by '/') of the file just downloaded
+    pub path: String,
+    /// Number of files downloaded so far
+    pub files_completed: usize,
+    /// Number of files to download
+    pub files_total: usize,
+    /// Number of bytes downloaded so far
+    pub bytes_completed: u64,
+    /// Number of bytes to download (as reported by pCloud)
+    pub bytes_total: u64,
+}
+
+/// Result of a public link download
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PublicDownloadReport {
+    /// Paths (relative to the shared folder, separated by '/') of all files downloaded
+    pub files: Vec<String>,
+    /// Number of bytes downloaded
+    pub bytes_downloaded: u64,
+}
+
+/// Downloads the content of a public link (a shared folder recursively or a single file) into a local directory without authentication
+pub struct PublicLinkDownloadRequestBuilder {
+    /// Client to actually perform the requests
+    client: PCloudClient,
+    /// Code of the public link
+    code: String,
+    /// Local target directory
+    directory: PathBuf,
+    /// Maximum number of files downloaded in parallel
+    concurrency: usize,
+    /// Invoked after each downloaded file
+    on_progress: Option<ProgressCallback>,
+}
+
+#[allow(dead_code)]
+impl PublicLinkDownloadRequestBuilder {
+    pub(crate) fn for_code<P: AsRef<Path>>(
+        client: &PCloudClient,
+        code: &str,
+        directory: P,
+    ) -> PublicLinkDownloadRequestBuilder {
+        PublicLinkDownloadRequestBuilder {
+            client: client.clone(),
+            code: code.to_string(),
+            directory: directory.as_ref().to_path_buf(),
+            concurrency: DEFAULT_PUBLIC_DOWNLOAD_CONCURRENCY,
+            on_progress: None,
+        }
+    }
+
+    /// Maximum number of files downloaded in parallel (defaults to 4)
+    pub fn concurrency(mut self, value: usize) -> PublicLinkDownloadRequestBuilder {
+        self.concurrency = value.max(1);
+        self
+    }
+
+    /// Callback invoked after each downloaded file with the overall progress
+    pub fn on_progress<F: Fn(&PublicDownloadProgress) + Send + Sync + 'static>(
+        mut self,
+        callback: F,
+    ) -> PublicLinkDownloadRequestBuilder {
+        self.on_progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Downloads all files of the link. A shared folder is recreated below the local directory (without the shared folder itself), a shared file is stored directly in it.
+    pub async fn execute(self) -> Result<PublicDownloadReport, PCloudError> {
+        let link = self.client.show_public_link(&self.code).await?;
+        let metadata = link
+            .metadata
+            .ok_or(pcloud_model::PCloudResult::InvalidLinkCode)?;
+
+        let mut files = Vec::new();
+        if metadata.isfolder {
+            collect_files(&metadata.contents, "", &mut files);
+        } else if let Some(fileid) = metadata.fileid {
+            files.push((
+                metadata.name.clone(),
+                fileid,
+                metadata.size.unwrap_or_default(),
+            ));
+        }
+
+        let files_total = files.len();
+        let bytes_total = files.iter().map(|(_, _, size)| size).sum();
+        debug!(
+            "Downloading {} files ({} bytes) of public link {}",
+            files_total, bytes_total, self.code
+        );
+
+        let files_completed = AtomicUsize::new(0);
+        let bytes_completed = AtomicU64::new(0);
+        let this = &self;
+        let (files_completed, bytes_completed) = (&files_completed, &bytes_completed);
+        let mut downloads = futures::stream::iter(files)
+            .map(|(path, fileid, _)| async move {
+                let size = this.download_file(&path, fileid).await?;
+                let progress = PublicDownloadProgress {
+                    path: path.clone(),
+                    files_completed: files_completed.fetch_add(1, Ordering::SeqCst) + 1,
+                    files_total,
+                    bytes_completed: bytes_completed.fetch_add(size, Ordering::SeqCst) + size,
+                    bytes_total,
+                };
+                if let Some(on_progress) = &this.on_progress {
+                    on_progress(&progress);
+                }
+                Ok::<_, PCloudError>((path, size))
+            })
+            .buffered(self.concurrency);
+
+        let mut report = PublicDownloadReport::default();
+        while let Some(result) = downloads.next().await {
+            let (path, size) = result?;
+            report.files.push(path);
+            report.bytes_downloaded += size;
+        }
+        Ok(report)
+    }
+
+    /// Downloads a single file of the link to its local path and returns its size
+    async fn download_file(&self, path: &str, fileid: u64) -> Result<u64, PCloudError> {
+        let target = path
+            .split('/')
+            .fold(self.directory.clone(), |p, c| p.join(c));
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let mut r = self
+            .client
+            .client()
+            .get(format!("{}/getpublinkdownload", self.client.api_host()));
+        r = r.query(&[("code", &self.code)]);
+        r = r.query(&[("fileid", fileid)]);
+        // No authentication, public links are accessible to everyone
+        let link = self
+            .client
+            .send_checked::<pcloud_model::DownloadLink>(r)
+            .await?;
+
+        debug!("Downloading {} of public link {}", path, self.code);
+        let mut stream = self
+            .client
+            .send_download(&link, |r| r)
+            .await?
+            .error_for_status()?
+            .bytes_stream();
+
+        // Only complete files get the final name
+        let partial = target.with_file_name(format!(
+            "{}.part",
+            target.file_name().unwrap_or_default().to_string_lossy()
+        ));
+        let mut file = tokio::fs::File::create(&partial).await?;
+        let mut size = 0;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            size += chunk.len() as u64;
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+        tokio::fs::rename(&partial, &target).await?;
+        Ok(size)
+    }
+}
+
+/// Collects all files of the given (recursive) folder contents with their path relative to the folder, file id and size
+fn collect_files(contents: &[Metadata], prefix: &str, files: &mut Vec<(String, u64, u64)>) {
+    for metadata in contents {
+        let path = if prefix.is_empty() {
+            metadata.name.clone()
+        } else {
+            format!("{}/{}", prefix, metadata.name)
+        };
+        if metadata.isfolder {
+            collect_files(&metadata.contents, &path, files);
+        } else if let Some(fileid) = metadata.fileid {
+            files.push((path, fileid, metadata.size.unwrap_or_default()));
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Returns the metadata of a public link (for shared folders including all contents recursively) without authentication
+    /// see https://docs.pcloud.com/methods/public_links/showpublink.html
+    pub async fn show_public_link(&self, code: &str) -> Result<FileOrFolderStat, PCloudError> {
+        let mut r = self
+            .client()
+            .get(format!("{}/showpublink", self.api_host()));
+        r = r.query(&[("code", code)]);
+
+        let stat = self.send_checked::<FileOrFolderStat>(r).await?;
+        Ok(stat)
+    }
+
+    /// Downloads the content of a public link (a shared folder recursively or a single file) into a local directory without authentication, with bounded concurrency and progress reporting
+    pub fn download_public_link<P: AsRef<Path>>(
+        &self,
+        code: &str,
+        directory: P,
+    ) -> PublicLinkDownloadRequestBuilder {
+        PublicLinkDownloadRequestBuilder::for_code(self, code, directory)
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use chrono::Local;
+use futures::Stream;
+use log::debug;
+
+use crate::{
+    throttle::{paced, SliceBudget},
+    transfer::BandwidthSchedule,
+};
+
+/// Request rate and bandwidth limits of a client (see `PCloudClientBuilder::rate_limiter()`). The budget is shared by all clones of the client and of the RateLimiter itself, so several separately built clients can be bound by one global budget as well.
+#[derive(Clone, Default)]
+pub struct RateLimiter {
+    /// Spacing of the requests, if limited
+    requests: Option<Arc<RequestLimiter>>,
+    /// Bandwidth budget of uploads and downloads, if limited
+    bandwidth: Option<Arc<BandwidthLimiter>>,
+}
+
+impl RateLimiter {
+    /// Creates a limiter without any limits
+    pub fn new() -> RateLimiter {
+        RateLimiter::default()
+    }
+
+    /// Limits the number of requests (API calls and downloads) started per second
+    pub fn max_requests_per_second(mut self, requests: u32) -> RateLimiter {
+        self.requests = Some(Arc::new(RequestLimiter::new(requests)));
+        self
+    }
+
+    /// Limits the total bandwidth of all uploads and downloads to the given bytes per second
+    pub fn max_bytes_per_second(self, bytes_per_second: u64) -> RateLimiter {
+        self.bandwidth_schedule(BandwidthSchedule::new(Some(bytes_per_second)))
+    }
+
+    /// Limits the total bandwidth of all uploads and downloads according to the schedule, e.g. to throttle only during office hours
+    pub fn bandwidth_schedule(mut self, schedule: BandwidthSchedule) -> RateLimiter {
+        self.bandwidth = Some(Arc::new(BandwidthLimiter::new(schedule)));
+        self
+    }
+
+    /// Waits until the next request may be started
+    pub(crate) async fn acquire_request(&self) {
+        if let Some(requests) = &self.requests {
+            requests.acquire().await;
+        }
+    }
+
+    /// Bandwidth budget, if limited
+    pub(crate) fn bandwidth(&self) -> Option<Arc<BandwidthLimiter>> {
+        self.bandwidth.clone()
+    }
+}
+
+/// Spaces the requests evenly to stay within the limit
+struct RequestLimiter {
+    /// Minimum time between two requests
+    interval: Duration,
+    /// Earliest start of the next request
+    next: Mutex<Instant>,
+}
+
+impl RequestLimiter {
+    fn new(requests_per_second: u32) -> RequestLimiter {
+        RequestLimiter {
+            interval: Duration::from_secs(1) / requests_per_second.max(1),
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Reserves the next slot and waits for it
+    async fn acquire(&self) {
+        let due = {
+            let mut next = self.next.lock().unwrap();
+            let due = (*next).max(Instant::now());
+            *next = due + self.interval;
+            due
+        };
+        if due > Instant::now() {
+            debug!("Request delayed by rate limit");
+            tokio::time::sleep_until(due.into()).await;
+        }
+    }
+}
+
+/// Bandwidth budget shared by all transfers of a TransferManager or all clones of a client. The schedule is evaluated for each chunk, so a changing limit applies to running transfers.
+pub(crate) struct BandwidthLimiter {
+    schedule: BandwidthSchedule,
+    budget: Arc<SliceBudget>,
+}
+
+impl BandwidthLimiter {
+    pub(crate) fn new(schedule: BandwidthSchedule) -> BandwidthLimiter {
+        BandwidthLimiter {
+            schedule,
+            budget: Arc::new(SliceBudget::new()),
+        }
+    }
+
+    /// Bytes per second allowed right now, None for full speed
+    fn current_limit(&self) -> Option<u64> {
+        self.schedule.limit_at(Local::now().time())
+    }
+}
+
+/// Passes the stream on within the budget of the limiter, splitting chunks larger than a slice
+pub(crate) fn limit<S, E>(
+    stream: S,
+    limiter: Arc<BandwidthLimiter>,
+) -> impl Stream<Item = Result<Bytes, E>>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+{
+    let budget = limiter.budget.clone();
+    paced(stream, budget, move || limiter.current_limit())
+}
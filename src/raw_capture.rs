@@ -0,0 +1,70 @@
+use std::{collections::VecDeque, fmt::Display};
+
+/// Raw response retained for debugging, secrets redacted
+#[derive(Debug, Clone)]
+pub struct RawResponse {
+    /// Url of the request, secrets redacted
+    pub url: String,
+    /// HTTP status code
+    pub status: u16,
+    /// Raw response body, secrets redacted
+    pub body: String,
+}
+
+/// Ring buffer of the last raw responses of a PCloudClient
+#[derive(Debug)]
+pub(crate) struct RawResponseLog {
+    /// Maximum number of responses retained
+    capacity: usize,
+    responses: VecDeque<RawResponse>,
+}
+
+impl RawResponseLog {
+    pub(crate) fn new(capacity: usize) -> RawResponseLog {
+        RawResponseLog {
+            capacity,
+            responses: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Adds a response, dropping the oldest one if the capacity is exceeded
+    pub(crate) fn push(&mut self, response: RawResponse) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.responses.len() == self.capacity {
+            self.responses.pop_front();
+        }
+        self.responses.push_back(response);
+    }
+
+    /// Returns all retained responses, oldest first
+    pub(crate) fn responses(&self) -> Vec<RawResponse> {
+        self.responses.iter().cloned().collect()
+    }
+}
+
+/// Error on deserializing a pCloud response into the expected model. Contains the offending (redacted) payload, to report model mismatches upstream.
+#[derive(Debug)]
+pub struct DeserializationError {
+    /// Response which could not be deserialized
+    pub response: RawResponse,
+    /// Original error
+    pub source: serde_json::Error,
+}
+
+impl Display for DeserializationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Failed to deserialize response of {}: {}. Payload: {}",
+            self.response.url, self.source, self.response.body
+        )
+    }
+}
+
+impl std::error::Error for DeserializationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
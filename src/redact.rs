@@ -0,0 +1,50 @@
+use reqwest::Url;
+
+/// Replacement for all redacted values
+pub const REDACTED: &str = "REDACTED";
+
+/// Query parameters (and top-level JSON response fields) never written to fixtures or debug logs
+const SECRET_PARAMS: [&str; 5] = ["auth", "access_token", "username", "password", "token"];
+
+/// Replaces the values of all secret query parameters
+pub(crate) fn redact_url(url: &Url) -> String {
+    let mut result = url.clone();
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(key, value)| {
+            if SECRET_PARAMS.contains(&key.as_ref()) {
+                (key.to_string(), REDACTED.to_string())
+            } else {
+                (key.to_string(), value.to_string())
+            }
+        })
+        .collect();
+
+    if pairs.is_empty() {
+        result.set_query(None);
+    } else {
+        result.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    result.to_string()
+}
+
+/// Replaces the values of all secret top-level fields of JSON responses (like the `auth` token returned on login)
+pub(crate) fn redact_body(body: String) -> String {
+    match serde_json::from_str::<serde_json::Value>(&body) {
+        Ok(serde_json::Value::Object(mut map)) => {
+            let mut redacted = false;
+            for key in SECRET_PARAMS {
+                if let Some(value) = map.get_mut(key) {
+                    *value = serde_json::Value::String(REDACTED.to_string());
+                    redacted = true;
+                }
+            }
+            if redacted {
+                serde_json::Value::Object(map).to_string()
+            } else {
+                body
+            }
+        }
+        _ => body,
+    }
+}
@@ -9,10 +9,11 @@ use tokio::{
 use uuid::Uuid;
 
 use crate::{
+    error::PCloudError,
     file_ops::Tree,
     folder_ops::FolderDescriptor,
     pcloud_client::PCloudClient,
-    pcloud_model::{FileOrFolderStat, SaveZipProgressResponse, WithPCloudResult},
+    pcloud_model::{FileOrFolderStat, SaveZipProgressResponse},
 };
 
 pub struct GetZipRequestBuilder {
@@ -32,17 +33,17 @@ impl GetZipRequestBuilder {
     }
 
     /// Starts creating a zip file from the given files and download it directly
-    pub async fn download(self) -> Result<Response, Box<dyn std::error::Error + Send + Sync>> {
+    pub async fn download(self) -> Result<Response, PCloudError> {
         let mut r = self
             .client
-            .client
-            .get(format!("{}/getzip", self.client.api_host));
+            .client()
+            .get(format!("{}/getzip", self.client.api_host()));
 
         r = self.tree.add_to_request(r);
 
         r = self.client.add_token(r);
 
-        let resp = r.send().await?;
+        let resp = self.client.send(r).await?;
         Ok(resp)
     }
 }
@@ -92,11 +93,11 @@ impl InitiateSavezipRequestBuilder {
     }
 
     /// Target folder and file name of the target zip file
-    pub fn to_folder<'a, T: FolderDescriptor>(
+    pub fn to_folder<T: FolderDescriptor>(
         self,
         folder_like: T,
         file_name: &str,
-    ) -> Result<SaveZipRequestBuilder, Box<dyn 'a + std::error::Error + Send + Sync>> {
+    ) -> Result<SaveZipRequestBuilder, PCloudError> {
         let f = folder_like.to_folder()?;
 
         Ok(SaveZipRequestBuilder {
@@ -115,16 +116,16 @@ impl SaveZipRequestBuilder {
     async fn fetch_progress(
         client: &PCloudClient,
         progress_hash: &str,
-    ) -> Result<SaveZipProgressResponse, Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<SaveZipProgressResponse, PCloudError> {
         let mut r = client
-            .client
-            .get(format!("{}/savezipprogress", client.api_host));
+            .client()
+            .get(format!("{}/savezipprogress", client.api_host()));
 
         r = r.query(&[("progresshash", progress_hash)]);
 
         r = client.add_token(r);
 
-        let result = r.send().await?.json::<SaveZipProgressResponse>().await?;
+        let result = client.send_json::<SaveZipProgressResponse>(r).await?;
         Ok(result)
     }
 
@@ -133,7 +134,7 @@ impl SaveZipRequestBuilder {
         client: &PCloudClient,
         progress_hash: &str,
         tx: &Sender<SaveZipProgressResponse>,
-    ) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<u64, PCloudError> {
         let progress = SaveZipRequestBuilder::fetch_progress(client, progress_hash).await?;
         let remaining = progress.totalfiles - progress.files;
         tx.send(progress).await?;
@@ -145,10 +146,7 @@ impl SaveZipRequestBuilder {
     pub async fn execute_with_progress_notification(
         self,
         polling_interval: Duration,
-    ) -> Result<
-        (FileOrFolderStat, Receiver<SaveZipProgressResponse>),
-        Box<dyn std::error::Error + Send + Sync>,
-    > {
+    ) -> Result<(FileOrFolderStat, Receiver<SaveZipProgressResponse>), PCloudError> {
         let progress_hash = Uuid::new_v4().to_string();
         let progress_client = self.client.clone();
 
@@ -190,13 +188,11 @@ impl SaveZipRequestBuilder {
     }
 
     /// Starts creating a zip file in the user's filesystem.
-    pub async fn execute(
-        self,
-    ) -> Result<FileOrFolderStat, Box<dyn std::error::Error + Send + Sync>> {
+    pub async fn execute(self) -> Result<FileOrFolderStat, PCloudError> {
         let mut r = self
             .client
-            .client
-            .get(format!("{}/savezip", self.client.api_host));
+            .client()
+            .get(format!("{}/savezip", self.client.api_host()));
 
         if let Some(v) = self.to_path {
             r = r.query(&[("topath", v)]);
@@ -218,12 +214,7 @@ impl SaveZipRequestBuilder {
 
         r = self.client.add_token(r);
 
-        let result = r
-            .send()
-            .await?
-            .json::<FileOrFolderStat>()
-            .await?
-            .assert_ok()?;
+        let result = self.client.send_checked::<FileOrFolderStat>(r).await?;
         Ok(result)
     }
 }
@@ -238,10 +229,7 @@ impl PCloudClient {
     /// Downloads a zip file of the files requested in the given tree.
     ///  When successful it returns a zip archive over the current API connection with all the files and directories in the requested tree.
     /// If the size of the resulting file is going to be over 4Gb or if it contains more than 65535 entries, the zip64 format is used, otherwise the file is plain zip. This is the fastest way to generate a zip file as the API server will construct the archive on-the-fly for you. Therefore the download will start instantly even with multi-gigabyte files.
-    pub async fn download_zip_of_files(
-        &self,
-        tree: Tree,
-    ) -> Result<Response, Box<dyn std::error::Error + Send + Sync>> {
+    pub async fn download_zip_of_files(&self, tree: Tree) -> Result<Response, PCloudError> {
         GetZipRequestBuilder::zip(self, tree).download().await
     }
 }
@@ -0,0 +1,288 @@
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use log::debug;
+
+use crate::{
+    error::PCloudError,
+    folder_ops::{FolderDescriptor, PCloudFolder},
+    pcloud_client::PCloudClient,
+    pcloud_model::{Metadata, PCloudResult},
+};
+
+/// Default number of files copied in parallel
+pub const DEFAULT_RESTORE_CONCURRENCY: usize = 4;
+
+/// Single file copied into the restore target
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestoredFile {
+    /// Path relative to the restored folder, separated by '/'
+    pub path: String,
+    /// Id of the original file
+    pub fileid: u64,
+    /// Revision copied, None if the current content was already the one at the given time
+    pub revisionid: Option<u64>,
+    /// Id of the copy in the restore target
+    pub restored_fileid: u64,
+}
+
+/// Result of a point-in-time restore
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FolderRestoreReport {
+    /// Id of the folder the files were restored into
+    pub target_folder_id: u64,
+    /// All files restored
+    pub restored: Vec<RestoredFile>,
+    /// Paths of the files created after the given time
+    pub skipped: Vec<String>,
+    /// Paths of the files existing at the given time, whose revision of that time is no longer kept by pCloud
+    pub unavailable: Vec<String>,
+}
+
+/// Content of a file at the restore time
+enum Version {
+    /// The current content
+    Current,
+    /// An older revision
+    Revision(u64, DateTime<Utc>),
+    /// The file didn't exist yet
+    Missing,
+    /// The file existed, but its revision expired
+    Expired,
+}
+
+/// Outcome of restoring a single file
+enum Outcome {
+    Restored(RestoredFile),
+    Skipped(String),
+    Unavailable(String),
+}
+
+/// Copies the revision of each file of a folder (recursively) that was current at a given time into a restore target folder, leaving the folder itself untouched
+pub struct FolderRestoreRequestBuilder {
+    /// Client to actually perform the requests
+    client: PCloudClient,
+    /// Remote folder to restore
+    folder: PCloudFolder,
+    /// Point in time to restore
+    timestamp: DateTime<Utc>,
+    /// Folder to restore into
+    target: Option<PCloudFolder>,
+    /// Maximum number of files copied in parallel
+    concurrency: usize,
+}
+
+#[allow(dead_code)]
+impl FolderRestoreRequestBuilder {
+    pub(crate) fn for_folder<T: FolderDescriptor>(
+        client: &PCloudClient,
+        folder_like: T,
+        timestamp: DateTime<Utc>,
+    ) -> Result<FolderRestoreRequestBuilder, PCloudError> {
+        let folder = folder_like.to_folder()?;
+
+        if folder.is_empty() {
+            Err(PCloudResult::NoFileIdOrPathProvided)?
+        }
+
+        Ok(FolderRestoreRequestBuilder {
+            client: client.clone(),
+            folder,
+            timestamp,
+            target: None,
+            concurrency: DEFAULT_RESTORE_CONCURRENCY,
+        })
+    }
+
+    /// Folder to restore into (created if missing, existing files are overwritten). Defaults to a new folder next to the restored one, named after it and the restore time.
+    pub fn target<T: FolderDescriptor>(
+        mut self,
+        folder_like: T,
+    ) -> Result<FolderRestoreRequestBuilder, PCloudError> {
+        let target = folder_like.to_folder()?;
+        if target.is_empty() {
+            Err(PCloudResult::NoFileIdOrPathProvided)?
+        }
+        self.target = Some(target);
+        Ok(self)
+    }
+
+    /// Maximum number of files copied in parallel (defaults to 4)
+    pub fn concurrency(mut self, value: usize) -> FolderRestoreRequestBuilder {
+        self.concurrency = value.max(1);
+        self
+    }
+
+    /// Restores all files of the folder. Files deleted since the given time are not restored, they can be recovered from the trash.
+    pub async fn execute(self) -> Result<FolderRestoreReport, PCloudError> {
+        let metadata = self
+            .client
+            .list_folder(&self.folder)?
+            .recursive(true)
+            .get()
+            .await?
+            .metadata
+            .ok_or(PCloudResult::DirectoryDoesNotExist)?;
+
+        let target_folder_id = self.create_target(&metadata).await?;
+        debug!(
+            "Restoring {} as of {} into folder {}",
+            self.folder, self.timestamp, target_folder_id
+        );
+
+        let mut files = Vec::new();
+        self.create_folders(&metadata.contents, "", target_folder_id, &mut files)
+            .await?;
+
+        let this = &self;
+        let mut copies = futures::stream::iter(files)
+            .map(|(path, fileid, modified, folder_id)| async move {
+                let (revisionid, modified) = match this.version_at(fileid, modified).await? {
+                    Version::Current => (None, modified),
+                    Version::Revision(revisionid, created) => (Some(revisionid), created),
+                    Version::Missing => return Ok(Outcome::Skipped(path)),
+                    Version::Expired => return Ok(Outcome::Unavailable(path)),
+                };
+
+                let mut copy = this.client.copy_file(fileid, folder_id)?.mtime(&modified);
+                if let Some(revisionid) = revisionid {
+                    copy = copy.with_revision(revisionid);
+                }
+                let restored_fileid = copy
+                    .execute()
+                    .await?
+                    .metadata
+                    .and_then(|m| m.fileid)
+                    .ok_or(PCloudResult::FileNotFound)?;
+                Ok::<_, PCloudError>(Outcome::Restored(RestoredFile {
+                    path,
+                    fileid,
+                    revisionid,
+                    restored_fileid,
+                }))
+            })
+            .buffered(self.concurrency);
+
+        let mut report = FolderRestoreReport {
+            target_folder_id,
+            ..Default::default()
+        };
+        while let Some(result) = copies.next().await {
+            match result? {
+                Outcome::Restored(file) => report.restored.push(file),
+                Outcome::Skipped(path) => report.skipped.push(path),
+                Outcome::Unavailable(path) => report.unavailable.push(path),
+            }
+        }
+        Ok(report)
+    }
+
+    /// Creates the restore target and returns its id
+    async fn create_target(&self, metadata: &Metadata) -> Result<u64, PCloudError> {
+        let stat = match &self.target {
+            Some(target) if target.folder_id.is_some() => {
+                return self.client.get_folder_id(target).await;
+            }
+            Some(target) => {
+                let path = target.path.clone().unwrap_or_default();
+                let (parent, name) = match path.trim_end_matches('/').rsplit_once('/') {
+                    Some((parent, name)) if !name.is_empty() => (parent.to_string(), name),
+                    _ => Err(PCloudResult::NoFullPathOrFolderIdProvided)?,
+                };
+                let parent = if parent.is_empty() {
+                    "/".to_string()
+                } else {
+                    parent
+                };
+                self.client.create_folder(parent, name)?.execute().await?
+            }
+            None => {
+                // The root folder has no parent to create the target in
+                let parent = metadata
+                    .parentfolderid
+                    .filter(|_| metadata.folderid != Some(0))
+                    .ok_or(PCloudResult::NoFullPathOrFolderIdProvided)?;
+                let name = format!(
+                    "{} (restored {})",
+                    metadata.name,
+                    self.timestamp.format("%Y-%m-%d %H.%M.%S")
+                );
+                self.client.create_folder(parent, &name)?.execute().await?
+            }
+        };
+        Ok(stat
+            .metadata
+            .and_then(|m| m.folderid)
+            .ok_or(PCloudResult::DirectoryDoesNotExist)?)
+    }
+
+    /// Recreates the folders of the (recursive) folder contents in the target folder and collects all files with their relative path, id, modification time and target folder id
+    async fn create_folders(
+        &self,
+        contents: &[Metadata],
+        prefix: &str,
+        target_folder_id: u64,
+        files: &mut Vec<(String, u64, DateTime<Utc>, u64)>,
+    ) -> Result<(), PCloudError> {
+        for metadata in contents {
+            let path = if prefix.is_empty() {
+                metadata.name.clone()
+            } else {
+                format!("{}/{}", prefix, metadata.name)
+            };
+            if metadata.isfolder {
+                let folder_id = self
+                    .client
+                    .create_folder(target_folder_id, &metadata.name)?
+                    .execute()
+                    .await?
+                    .metadata
+                    .and_then(|m| m.folderid)
+                    .ok_or(PCloudResult::DirectoryDoesNotExist)?;
+                Box::pin(self.create_folders(&metadata.contents, &path, folder_id, files)).await?;
+            } else if let Some(fileid) = metadata.fileid {
+                files.push((path, fileid, metadata.modified, target_folder_id));
+            }
+        }
+        Ok(())
+    }
+
+    /// Determines the content of the file at the restore time: the current content if not modified since, otherwise the latest revision created before. Without such a revision, the event history of the file tells if it existed at all.
+    async fn version_at(
+        &self,
+        fileid: u64,
+        modified: DateTime<Utc>,
+    ) -> Result<Version, PCloudError> {
+        if modified <= self.timestamp {
+            return Ok(Version::Current);
+        }
+
+        let revisions = self.client.list_file_revisions(fileid).await?;
+        if let Some(revision) = revisions
+            .revisions
+            .iter()
+            .filter(|r| r.created <= self.timestamp)
+            .max_by_key(|r| r.created)
+        {
+            return Ok(Version::Revision(revision.revisionid, revision.created));
+        }
+
+        let history = self.client.get_file_history(fileid).await?;
+        if history.entries.iter().any(|e| e.time <= self.timestamp) {
+            Ok(Version::Expired)
+        } else {
+            Ok(Version::Missing)
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Restores a folder to its state at the given time: the revision of each file current at that time is copied into a restore target folder, recreating the folder structure. Accepts either a folder id (u64), a folder path (String) or any other pCloud object describing a folder (like Metadata)
+    pub fn restore_folder_to<T: FolderDescriptor>(
+        &self,
+        folder_like: T,
+        timestamp: DateTime<Utc>,
+    ) -> Result<FolderRestoreRequestBuilder, PCloudError> {
+        FolderRestoreRequestBuilder::for_folder(self, folder_like, timestamp)
+    }
+}
@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use log::debug;
+
+use crate::{
+    error::PCloudError,
+    filter::SyncFilter,
+    folder_ops::{FolderDescriptor, PCloudFolder},
+    pcloud_client::PCloudClient,
+    pcloud_model::{Metadata, PCloudResult},
+};
+
+/// Rule of a retention policy that expired a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetentionReason {
+    /// Older than the maximum age
+    Age,
+    /// Beyond the number of files kept per folder
+    Count,
+}
+
+/// Single file expired by a retention policy
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpiredFile {
+    /// Path relative to the cleaned up folder, separated by '/'
+    pub path: String,
+    /// Id of the file
+    pub fileid: u64,
+    /// Modification time
+    pub modified: DateTime<Utc>,
+    /// Size in bytes
+    pub size: Option<u64>,
+    /// Rule expiring the file
+    pub reason: RetentionReason,
+}
+
+/// Result of a retention cleanup
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RetentionReport {
+    /// True if nothing was deleted or moved
+    pub dry_run: bool,
+    /// Files deleted or moved to the archive folder (or which would have been, on a dry run)
+    pub expired: Vec<ExpiredFile>,
+    /// Number of files kept
+    pub kept: usize,
+    /// Paths of the files left out by the exclusion patterns
+    pub excluded: Vec<String>,
+}
+
+impl RetentionReport {
+    /// Total size of all expired files in bytes
+    pub fn expired_bytes(&self) -> u64 {
+        self.expired.iter().filter_map(|f| f.size).sum()
+    }
+}
+
+/// Cleans up a remote folder (recursively) by deleting files older than a maximum age or beyond a number of files per folder, or by moving them to an archive folder
+pub struct RetentionRequestBuilder {
+    /// Client to actually perform the requests
+    client: PCloudClient,
+    /// Remote folder to clean up
+    folder: PCloudFolder,
+    /// Files modified before are expired
+    max_age: Option<chrono::Duration>,
+    /// Number of newest files kept in each folder
+    keep_per_folder: Option<usize>,
+    /// Files never expired
+    filter: SyncFilter,
+    /// Expired files are moved here instead of being deleted
+    archive: Option<PCloudFolder>,
+    /// Only reports the expired files
+    dry_run: bool,
+}
+
+#[allow(dead_code)]
+impl RetentionRequestBuilder {
+    pub(crate) fn for_folder<T: FolderDescriptor>(
+        client: &PCloudClient,
+        folder_like: T,
+    ) -> Result<RetentionRequestBuilder, PCloudError> {
+        let folder = folder_like.to_folder()?;
+
+        if folder.is_empty() {
+            Err(PCloudResult::NoFileIdOrPathProvided)?
+        }
+
+        Ok(RetentionRequestBuilder {
+            client: client.clone(),
+            folder,
+            max_age: None,
+            keep_per_folder: None,
+            filter: SyncFilter::new(),
+            archive: None,
+            dry_run: false,
+        })
+    }
+
+    /// Expires all files not modified within the given number of days
+    pub fn older_than_days(mut self, days: u32) -> RetentionRequestBuilder {
+        self.max_age = Some(chrono::Duration::days(days as i64));
+        self
+    }
+
+    /// Keeps only the given number of newest files in each folder, expiring all older ones
+    pub fn keep_per_folder(mut self, value: usize) -> RetentionRequestBuilder {
+        self.keep_per_folder = Some(value);
+        self
+    }
+
+    /// Never expires files and folders matching the gitignore-style pattern. Excluded files don't count for `keep_per_folder()`.
+    pub fn exclude(mut self, pattern: &str) -> RetentionRequestBuilder {
+        self.filter = self.filter.exclude(pattern);
+        self
+    }
+
+    /// Moves expired files to the given folder (created if missing, keeping their relative path) instead of deleting them. Accepts either a folder id (u64), a folder path (String) or any other pCloud object describing a folder (like Metadata)
+    pub fn archive_to<T: FolderDescriptor>(
+        mut self,
+        folder_like: T,
+    ) -> Result<RetentionRequestBuilder, PCloudError> {
+        let archive = folder_like.to_folder()?;
+        if archive.is_empty() {
+            Err(PCloudResult::NoFileIdOrPathProvided)?
+        }
+        self.archive = Some(archive);
+        Ok(self)
+    }
+
+    /// If set, only reports the files which would be expired without deleting or moving anything
+    pub fn dry_run(mut self, value: bool) -> RetentionRequestBuilder {
+        self.dry_run = value;
+        self
+    }
+
+    /// Applies the policy
+    pub async fn execute(self) -> Result<RetentionReport, PCloudError> {
+        let metadata = self
+            .client
+            .list_folder(&self.folder)?
+            .recursive(true)
+            .get()
+            .await?
+            .metadata
+            .ok_or(PCloudResult::DirectoryDoesNotExist)?;
+
+        // A dry run doesn't create the archive, but still leaves it out if present
+        let archive_id = match &self.archive {
+            Some(archive) if !self.dry_run => Some(self.ensure_archive(archive).await?),
+            Some(archive) => self.client.get_folder_id(archive).await.ok(),
+            None => None,
+        };
+
+        let mut report = RetentionReport {
+            dry_run: self.dry_run,
+            ..Default::default()
+        };
+        let cutoff = self.max_age.map(|age| Utc::now() - age);
+        self.collect(&metadata.contents, "", cutoff, archive_id, &mut report);
+        debug!(
+            "Retention policy on {} expires {} files, keeps {}",
+            self.folder,
+            report.expired.len(),
+            report.kept
+        );
+
+        if !self.dry_run {
+            let mut folders = HashMap::new();
+            for file in &report.expired {
+                match archive_id {
+                    Some(archive_id) => {
+                        let parent = file.path.rsplit_once('/').map(|(p, _)| p).unwrap_or("");
+                        let folder_id = self
+                            .archive_folder(archive_id, parent, &mut folders)
+                            .await?;
+                        self.client
+                            .move_file(file.fileid, folder_id)?
+                            .execute()
+                            .await?;
+                    }
+                    None => {
+                        self.client.delete_file(file.fileid).await?;
+                    }
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Applies the rules to the files of each folder of the (recursive) folder contents
+    fn collect(
+        &self,
+        contents: &[Metadata],
+        prefix: &str,
+        cutoff: Option<DateTime<Utc>>,
+        archive_id: Option<u64>,
+        report: &mut RetentionReport,
+    ) {
+        let path_of = |metadata: &Metadata| {
+            if prefix.is_empty() {
+                metadata.name.clone()
+            } else {
+                format!("{}/{}", prefix, metadata.name)
+            }
+        };
+
+        let mut files = Vec::new();
+        for metadata in contents {
+            let path = path_of(metadata);
+            if metadata.isfolder {
+                // Never clean up the archive itself
+                if metadata.folderid.is_some() && metadata.folderid == archive_id {
+                    continue;
+                }
+                if !self.filter.is_folder_excluded(&path) {
+                    self.collect(&metadata.contents, &path, cutoff, archive_id, report);
+                }
+            } else if let Some(fileid) = metadata.fileid {
+                if self.filter.is_excluded(&path, metadata.size) {
+                    report.excluded.push(path);
+                } else {
+                    files.push(ExpiredFile {
+                        path,
+                        fileid,
+                        modified: metadata.modified,
+                        size: metadata.size,
+                        reason: RetentionReason::Age,
+                    });
+                }
+            }
+        }
+
+        // Newest first, so the files beyond the limit are the oldest ones
+        files.sort_by_key(|f| std::cmp::Reverse(f.modified));
+        for (index, mut file) in files.into_iter().enumerate() {
+            if cutoff.map(|c| file.modified < c).unwrap_or(false) {
+                report.expired.push(file);
+            } else if self.keep_per_folder.map(|n| index >= n).unwrap_or(false) {
+                file.reason = RetentionReason::Count;
+                report.expired.push(file);
+            } else {
+                report.kept += 1;
+            }
+        }
+    }
+
+    /// Creates the archive folder (if not existing) and returns its id
+    async fn ensure_archive(&self, archive: &PCloudFolder) -> Result<u64, PCloudError> {
+        match (&archive.folder_id, &archive.path) {
+            (Some(folder_id), _) => Ok(*folder_id),
+            (None, Some(path)) => {
+                let mut folder_id = 0;
+                for name in path.split('/').filter(|n| !n.is_empty()) {
+                    folder_id = self.create_folder(folder_id, name).await?;
+                }
+                Ok(folder_id)
+            }
+            (None, None) => Err(PCloudResult::NoFileIdOrPathProvided)?,
+        }
+    }
+
+    /// Creates all folders of the relative path below the archive (if not existing) and returns the id of the last one
+    async fn archive_folder(
+        &self,
+        archive_id: u64,
+        path: &str,
+        folders: &mut HashMap<String, u64>,
+    ) -> Result<u64, PCloudError> {
+        let mut folder_id = archive_id;
+        let mut current = String::new();
+        for name in path.split('/').filter(|n| !n.is_empty()) {
+            current = format!("{}/{}", current, name);
+            folder_id = match folders.get(&current) {
+                Some(id) => *id,
+                None => {
+                    let id = self.create_folder(folder_id, name).await?;
+                    folders.insert(current.clone(), id);
+                    id
+                }
+            };
+        }
+        Ok(folder_id)
+    }
+
+    /// Creates the given folder (if not existing) in the parent folder and returns its id
+    async fn create_folder(&self, parent_folder_id: u64, name: &str) -> Result<u64, PCloudError> {
+        let stat = self
+            .client
+            .create_folder(parent_folder_id, name)?
+            .execute()
+            .await?;
+        Ok(stat
+            .metadata
+            .and_then(|m| m.folderid)
+            .ok_or(PCloudResult::DirectoryDoesNotExist)?)
+    }
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Applies a retention policy to a folder (recursively): files older than a maximum age or beyond a number of files per folder are deleted or moved to an archive folder. Accepts either a folder id (u64), a folder path (String) or any other pCloud object describing a folder (like Metadata)
+    pub fn apply_retention<T: FolderDescriptor>(
+        &self,
+        folder_like: T,
+    ) -> Result<RetentionRequestBuilder, PCloudError> {
+        RetentionRequestBuilder::for_folder(self, folder_like)
+    }
+}
@@ -0,0 +1,200 @@
+use std::{
+    io::SeekFrom,
+    path::{Path, PathBuf},
+};
+
+use futures::{StreamExt, TryStreamExt};
+use log::debug;
+use reqwest::StatusCode;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+
+use crate::{
+    error::PCloudError,
+    file_ops::{save_download, FileDescriptor, PCloudFile},
+    pcloud_client::PCloudClient,
+    pcloud_model::{DownloadLink, FileInfo, PCloudResult},
+    transfer::SizeMismatch,
+};
+
+/// Default number of segments downloaded at the same time
+pub const DEFAULT_SEGMENT_PARALLELISM: usize = 4;
+
+/// Default size of a segment (32 MiB)
+pub const DEFAULT_SEGMENT_SIZE: u64 = 32 * 1024 * 1024;
+
+/// Downloads a file in segments fetched concurrently with HTTP range requests, e.g. to saturate the bandwidth with multi-GB files. Each segment is written to its offset of the target file.
+/// The data is written to a temporary `.part` file next to the target, which is only renamed to the target once all segments are complete (and verified, if requested).
+pub struct SegmentedDownloadBuilder {
+    /// Client to actually perform the requests
+    client: PCloudClient,
+    /// File to download
+    file: PCloudFile,
+    /// Local target file
+    path: PathBuf,
+    /// Number of segments downloaded at the same time
+    parallelism: usize,
+    /// Size of a segment in bytes
+    segment_size: u64,
+    /// Verify the checksums of the complete file
+    verify_checksum: bool,
+}
+
+#[allow(dead_code)]
+impl SegmentedDownloadBuilder {
+    pub(crate) fn for_file<T: FileDescriptor, P: AsRef<Path>>(
+        client: &PCloudClient,
+        file_like: T,
+        path: P,
+    ) -> Result<SegmentedDownloadBuilder, PCloudError> {
+        let file = file_like
+            .to_file()
+            .map_err(PCloudError::InvalidDescriptor)?;
+
+        if file.is_empty() {
+            Err(PCloudError::InvalidDescriptor(
+                PCloudResult::NoFileIdOrPathProvided,
+            ))?
+        }
+
+        Ok(SegmentedDownloadBuilder {
+            client: client.clone(),
+            file,
+            path: path.as_ref().to_path_buf(),
+            parallelism: DEFAULT_SEGMENT_PARALLELISM,
+            segment_size: DEFAULT_SEGMENT_SIZE,
+            verify_checksum: false,
+        })
+    }
+
+    /// Number of segments downloaded at the same time (default 4)
+    pub fn parallelism(mut self, value: usize) -> SegmentedDownloadBuilder {
+        self.parallelism = value.max(1);
+        self
+    }
+
+    /// Size of a segment in bytes (default 32 MiB)
+    pub fn segment_size(mut self, value: u64) -> SegmentedDownloadBuilder {
+        self.segment_size = value.max(1);
+        self
+    }
+
+    /// If set, the complete file is hashed (SHA-1 and, depending on the region, MD5 or SHA-256) and compared with the checksums of `checksumfile` for the revision downloaded before it is renamed to the target. Fails with `ChecksumMismatch` on corruption. As segments arrive out of order, the file is read once more after the download.
+    pub fn verify_checksum(mut self, value: bool) -> SegmentedDownloadBuilder {
+        self.verify_checksum = value;
+        self
+    }
+
+    /// Downloads all segments. Returns information about the file, fails if any segment fails.
+    pub async fn execute(self) -> Result<FileInfo, PCloudError> {
+        // The link is pinned to the file the checksums and size were taken from
+        let (checksums, link) = self.client.checksummed_link(&self.file).await?;
+        let size = checksums
+            .metadata
+            .as_ref()
+            .and_then(|m| m.size)
+            .ok_or(PCloudResult::InternalError)?;
+
+        let (this, expected, link) = (&self, &checksums, &link);
+        save_download(&self.path, |partial| async move {
+            this.download_segments(link, size, &partial).await?;
+            if this.verify_checksum {
+                expected.verify_file(&partial).await?;
+            }
+            Ok(())
+        })
+        .await?;
+
+        Ok(FileInfo {
+            content_length: Some(size),
+            content_type: checksums
+                .metadata
+                .as_ref()
+                .and_then(|m| m.contenttype.clone()),
+            metadata: checksums.metadata,
+            sha1: checksums.sha1,
+            md5: checksums.md5,
+            sha256: checksums.sha256,
+        })
+    }
+
+    /// Creates the file with its final size and downloads the segments into it
+    async fn download_segments(
+        &self,
+        link: &DownloadLink,
+        size: u64,
+        path: &Path,
+    ) -> Result<(), PCloudError> {
+        tokio::fs::File::create(path).await?.set_len(size).await?;
+
+        let segments = (0..size)
+            .step_by(self.segment_size as usize)
+            .map(|start| (start, (start + self.segment_size).min(size)));
+        debug!(
+            "Downloading {} bytes of {:?} in {} segments",
+            size,
+            link.path,
+            size.div_ceil(self.segment_size)
+        );
+        futures::stream::iter(segments)
+            .map(|(start, end)| self.download_segment(link, start, end, size, path))
+            .buffer_unordered(self.parallelism)
+            .try_collect::<Vec<_>>()
+            .await?;
+        Ok(())
+    }
+
+    /// Downloads the bytes from `start` to `end` (exclusive) and writes them to the same offset of the file
+    async fn download_segment(
+        &self,
+        link: &DownloadLink,
+        start: u64,
+        end: u64,
+        size: u64,
+        path: &Path,
+    ) -> Result<(), PCloudError> {
+        let range = format!("bytes={}-{}", start, end - 1);
+        let response = self
+            .client
+            .send_download(link, |r| r.header(reqwest::header::RANGE, &range))
+            .await?
+            .error_for_status()?;
+        // The complete file is only acceptable if it is the only segment
+        let whole_file = start == 0 && end == size;
+        match response.status() {
+            StatusCode::PARTIAL_CONTENT => {}
+            StatusCode::OK if whole_file => {}
+            status => Err(PCloudError::RangeNotSupported(status))?,
+        }
+
+        let mut file = tokio::fs::OpenOptions::new().write(true).open(path).await?;
+        file.seek(SeekFrom::Start(start)).await?;
+        let mut stream = response.bytes_stream();
+        let mut written = 0;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            file.write_all(&chunk).await?;
+            written += chunk.len() as u64;
+        }
+        file.flush().await?;
+
+        if written != end - start {
+            Err(SizeMismatch {
+                expected: end - start,
+                actual: written,
+            })?
+        }
+        Ok(())
+    }
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Downloads the latest revision of a file (or the revision given) to the given local path in segments fetched concurrently. Accepts either a file id (u64), a file path (String) or any other pCloud object describing a file (like Metadata)
+    pub fn segmented_download<T: FileDescriptor, P: AsRef<Path>>(
+        &self,
+        file_like: T,
+        path: P,
+    ) -> Result<SegmentedDownloadBuilder, PCloudError> {
+        SegmentedDownloadBuilder::for_file(self, file_like, path)
+    }
+}
@@ -0,0 +1,95 @@
+#![cfg(feature = "tower")]
+use std::{
+    error::Error,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::future::BoxFuture;
+use reqwest::{Request, Response};
+use tower::{Service, ServiceExt};
+
+use crate::transport::HttpTransport;
+
+/// Request passed through the tower service stack
+#[derive(Debug)]
+pub struct PCloudRequest {
+    /// The HTTP request
+    pub request: Request,
+}
+
+impl PCloudRequest {
+    /// Name of the pCloud method called (last path segment of the url, like `listfolder`)
+    pub fn method(&self) -> &str {
+        self.request
+            .url()
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .unwrap_or_default()
+    }
+
+    /// Tries to clone the request (e.g. for retry middleware). Fails for streamed bodies like uploads.
+    pub fn try_clone(&self) -> Option<PCloudRequest> {
+        self.request
+            .try_clone()
+            .map(|request| PCloudRequest { request })
+    }
+}
+
+/// Innermost tower service: Executes the request with the configured transport
+#[derive(Clone)]
+pub struct TransportService {
+    transport: Arc<dyn HttpTransport>,
+}
+
+impl TransportService {
+    pub(crate) fn new(transport: Arc<dyn HttpTransport>) -> TransportService {
+        TransportService { transport }
+    }
+}
+
+impl Service<PCloudRequest> for TransportService {
+    type Response = Response;
+    type Error = Box<dyn Error + Send + Sync>;
+    type Future = BoxFuture<'static, Result<Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: PCloudRequest) -> Self::Future {
+        let transport = self.transport.clone();
+        Box::pin(async move { transport.execute(request.request).await })
+    }
+}
+
+/// HttpTransport executing all requests with a tower service stack. The service is cloned for each request, wrap non-clonable middleware (like `RateLimit`) into a `Buffer`.
+pub(crate) struct TowerTransport<S> {
+    service: S,
+}
+
+impl<S> TowerTransport<S> {
+    pub(crate) fn new(service: S) -> TowerTransport<S> {
+        TowerTransport { service }
+    }
+}
+
+impl<S> HttpTransport for TowerTransport<S>
+where
+    S: Service<PCloudRequest, Response = Response> + Clone + Send + Sync + 'static,
+    S::Error: Into<Box<dyn Error + Send + Sync>>,
+    S::Future: Send,
+{
+    fn execute<'a>(
+        &'a self,
+        request: Request,
+    ) -> BoxFuture<'a, Result<Response, Box<dyn Error + Send + Sync>>> {
+        let service = self.service.clone();
+        Box::pin(async move {
+            service
+                .oneshot(PCloudRequest { request })
+                .await
+                .map_err(Into::into)
+        })
+    }
+}
@@ -0,0 +1,143 @@
+use std::{
+    fmt::Display,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tokio::{
+    sync::{watch, Notify},
+    time::Instant,
+};
+
+/// Default time to wait for in-flight work on shutdown
+pub const DEFAULT_SHUTDOWN_DEADLINE: Duration = Duration::from_secs(30);
+
+/// Error of requests sent after the client was shut down (see `PCloudClient::shutdown()`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShutDown;
+
+impl Display for ShutDown {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Client was shut down")
+    }
+}
+
+impl std::error::Error for ShutDown {}
+
+/// Signal to shut down gracefully: once triggered, transfer managers and syncs watching it don't start new work and running work gets until the deadline to complete. Clones share the same signal. Each client has its own signal, used by everything created from it unless another one is set.
+#[derive(Clone)]
+pub struct ShutdownSignal {
+    state: Arc<SignalState>,
+}
+
+struct SignalState {
+    /// Deadline for running work, set once triggered
+    deadline: watch::Sender<Option<Instant>>,
+    /// Number of operations and requests running
+    active: AtomicUsize,
+    /// Notified when the last running operation or request completed
+    idle: Notify,
+}
+
+impl Default for ShutdownSignal {
+    fn default() -> Self {
+        ShutdownSignal::new()
+    }
+}
+
+impl ShutdownSignal {
+    /// Creates a signal, not triggered yet
+    pub fn new() -> ShutdownSignal {
+        ShutdownSignal {
+            state: Arc::new(SignalState {
+                deadline: watch::channel(None).0,
+                active: AtomicUsize::new(0),
+                idle: Notify::new(),
+            }),
+        }
+    }
+
+    /// Triggers the shutdown, giving running work the given time to complete. Triggering it again doesn't move the deadline.
+    pub fn trigger(&self, deadline: Duration) {
+        let deadline = Instant::now() + deadline;
+        self.state.deadline.send_if_modified(|current| {
+            if current.is_some() {
+                return false;
+            }
+            *current = Some(deadline);
+            true
+        });
+    }
+
+    /// Checks if the shutdown was triggered
+    pub fn is_triggered(&self) -> bool {
+        self.state.deadline.borrow().is_some()
+    }
+
+    /// Number of operations and requests still running
+    pub fn active(&self) -> usize {
+        self.state.active.load(Ordering::SeqCst)
+    }
+
+    /// Waits until all running operations and requests completed, at most until the deadline once triggered. Returns false if the deadline passed first.
+    pub async fn drain(&self) -> bool {
+        tokio::select! {
+            _ = self.idle() => true,
+            _ = self.deadline_passed() => self.active() == 0,
+        }
+    }
+
+    /// Waits until the shutdown is triggered
+    pub(crate) async fn triggered(&self) {
+        let mut deadline = self.state.deadline.subscribe();
+        while deadline.borrow_and_update().is_none() {
+            if deadline.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Waits until the shutdown is triggered and its deadline passed
+    pub(crate) async fn deadline_passed(&self) {
+        self.triggered().await;
+        let deadline = *self.state.deadline.borrow();
+        if let Some(deadline) = deadline {
+            tokio::time::sleep_until(deadline).await;
+        }
+    }
+
+    /// Waits until no operation or request is running
+    async fn idle(&self) {
+        loop {
+            let idle = self.state.idle.notified();
+            if self.active() == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+
+    /// Registers a running operation or request until the guard is dropped
+    pub(crate) fn begin(&self) -> ActivityGuard {
+        self.state.active.fetch_add(1, Ordering::SeqCst);
+        ActivityGuard {
+            state: self.state.clone(),
+        }
+    }
+}
+
+/// Running operation or request of a `ShutdownSignal`, completed on drop
+pub(crate) struct ActivityGuard {
+    state: Arc<SignalState>,
+}
+
+impl Drop for ActivityGuard {
+    fn drop(&mut self) {
+        if self.state.active.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.state.idle.notify_waiters();
+        }
+    }
+}
@@ -0,0 +1,231 @@
+#![cfg(feature = "sqlite")]
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::{
+    error::PCloudError,
+    sync::{MirrorEntry, SyncCheckpoint, SyncEntry, SyncOperation, SyncStateStore},
+};
+
+/// Schema of the database, created on open
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS checkpoint (
+        key TEXT PRIMARY KEY,
+        value TEXT
+    );
+    CREATE TABLE IF NOT EXISTS files (
+        path TEXT PRIMARY KEY,
+        fileid INTEGER NOT NULL,
+        hash INTEGER,
+        size INTEGER,
+        modified TEXT NOT NULL,
+        local_size INTEGER NOT NULL,
+        local_modified TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS operations (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        time TEXT NOT NULL,
+        action TEXT NOT NULL,
+        path TEXT NOT NULL,
+        moved_from TEXT,
+        conflict TEXT
+    );
+";
+
+/// SyncStateStore keeping the state of a two-way sync in an embedded SQLite database. Every file is updated in place, so the sync scales to millions of files and an interrupted run loses nothing. All operations applied are kept as history.
+pub struct SqliteSyncStateStore {
+    path: PathBuf,
+    connection: Mutex<Connection>,
+}
+
+impl SqliteSyncStateStore {
+    /// Opens (or creates) the database at the given location
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<SqliteSyncStateStore, PCloudError> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let connection = Connection::open(&path)?;
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.pragma_update(None, "synchronous", "NORMAL")?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(SqliteSyncStateStore {
+            path,
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Returns all operations recorded, oldest first
+    pub fn operations(&self) -> Result<Vec<SyncOperation>, PCloudError> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare(
+            "SELECT time, action, path, moved_from, conflict FROM operations ORDER BY id",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, Option<String>>(4)?,
+            ))
+        })?;
+
+        let mut operations = Vec::new();
+        for row in rows {
+            let (time, action, path, from, conflict) = row?;
+            operations.push(SyncOperation {
+                time: parse_time(&time, 0)?,
+                action: serde_json::from_value(serde_json::Value::String(action))?,
+                path,
+                from,
+                conflict: conflict
+                    .map(|c| serde_json::from_value(serde_json::Value::String(c)))
+                    .transpose()?,
+            });
+        }
+        Ok(operations)
+    }
+}
+
+impl SyncStateStore for SqliteSyncStateStore {
+    fn load_checkpoint(&self) -> Result<SyncCheckpoint, PCloudError> {
+        let connection = self.connection.lock().unwrap();
+        let value: Option<String> = connection
+            .query_row(
+                "SELECT value FROM checkpoint WHERE key = 'checkpoint'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+        match value {
+            Some(value) => Ok(serde_json::from_str(&value)?),
+            None => Ok(SyncCheckpoint::default()),
+        }
+    }
+
+    fn save_checkpoint(&self, checkpoint: &SyncCheckpoint) -> Result<(), PCloudError> {
+        let connection = self.connection.lock().unwrap();
+        connection.execute(
+            "INSERT OR REPLACE INTO checkpoint (key, value) VALUES ('checkpoint', ?1)",
+            params![serde_json::to_string(checkpoint)?],
+        )?;
+        Ok(())
+    }
+
+    fn entries(&self) -> Result<Vec<(String, SyncEntry)>, PCloudError> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare(
+            "SELECT path, fileid, hash, size, modified, local_size, local_modified FROM files",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, Option<i64>>(2)?,
+                row.get::<_, Option<i64>>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, i64>(5)?,
+                row.get::<_, String>(6)?,
+            ))
+        })?;
+
+        let mut entries = Vec::new();
+        for row in rows {
+            let (path, fileid, hash, size, modified, local_size, local_modified) = row?;
+            entries.push((
+                path,
+                SyncEntry {
+                    remote: MirrorEntry {
+                        fileid: fileid as u64,
+                        hash: hash.map(|h| h as u64),
+                        size: size.map(|s| s as u64),
+                        modified: parse_time(&modified, 4)?,
+                    },
+                    local_size: local_size as u64,
+                    local_modified: parse_time(&local_modified, 6)?,
+                },
+            ));
+        }
+        Ok(entries)
+    }
+
+    fn put(&self, path: &str, entry: &SyncEntry) -> Result<(), PCloudError> {
+        let connection = self.connection.lock().unwrap();
+        // SQLite only knows signed integers, content hashes use the full 64 bits
+        connection.execute(
+            "INSERT OR REPLACE INTO files (path, fileid, hash, size, modified, local_size, local_modified) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                path,
+                entry.remote.fileid as i64,
+                entry.remote.hash.map(|h| h as i64),
+                entry.remote.size.map(|s| s as i64),
+                entry.remote.modified.to_rfc3339(),
+                entry.local_size as i64,
+                entry.local_modified.to_rfc3339(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn remove(&self, path: &str) -> Result<(), PCloudError> {
+        let connection = self.connection.lock().unwrap();
+        connection.execute("DELETE FROM files WHERE path = ?1", params![path])?;
+        Ok(())
+    }
+
+    fn record_operation(&self, operation: &SyncOperation) -> Result<(), PCloudError> {
+        let action = serde_json::to_value(operation.action)?;
+        let conflict = operation
+            .conflict
+            .as_ref()
+            .map(serde_json::to_value)
+            .transpose()?;
+        let connection = self.connection.lock().unwrap();
+        connection.execute(
+            "INSERT INTO operations (time, action, path, moved_from, conflict) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                operation.time.to_rfc3339(),
+                action.as_str(),
+                operation.path,
+                operation.from,
+                conflict.as_ref().and_then(|c| c.as_str()),
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<(), PCloudError> {
+        let connection = self.connection.lock().unwrap();
+        connection.pragma_update(None, "wal_checkpoint", "PASSIVE")?;
+        Ok(())
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        let name = self.path.file_name().unwrap_or_default().to_string_lossy();
+        vec![
+            self.path.clone(),
+            self.path.with_file_name(format!("{}-wal", name)),
+            self.path.with_file_name(format!("{}-shm", name)),
+            self.path.with_file_name(format!("{}-journal", name)),
+        ]
+    }
+}
+
+/// Parses a time stored as RFC 3339 text in the given column
+fn parse_time(value: &str, column: usize) -> Result<DateTime<Utc>, rusqlite::Error> {
+    match DateTime::parse_from_rfc3339(value) {
+        Ok(time) => Ok(time.with_timezone(&Utc)),
+        Err(e) => Err(rusqlite::Error::FromSqlConversionFailure(
+            column,
+            rusqlite::types::Type::Text,
+            Box::new(e),
+        )),
+    }
+}
@@ -0,0 +1,1484 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::{
+    error::PCloudError,
+    filter::SyncFilter,
+    folder_ops::{FolderDescriptor, PCloudFolder},
+    pcloud_client::PCloudClient,
+    pcloud_model::{Metadata, PCloudResult},
+    progress::{ItemsOnly, ProgressObserver},
+    shutdown::ShutdownSignal,
+    transfer::TransferPriority,
+};
+
+/// Name of the state manifest, stored in the local directory if no other location is given
+pub const DEFAULT_MIRROR_STATE_FILE: &str = ".pcloud-mirror.json";
+
+/// Name of the state of a two-way sync, stored in the local directory if no other location is given
+pub const DEFAULT_SYNC_STATE_FILE: &str = ".pcloud-sync.json";
+
+/// State of a single mirrored file at the time of the last run
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MirrorEntry {
+    /// Id of the remote file
+    pub fileid: u64,
+    /// pCloud content hash of the remote file
+    pub hash: Option<u64>,
+    /// Size of the remote file in bytes
+    pub size: Option<u64>,
+    /// Modification time of the remote file
+    pub modified: DateTime<Utc>,
+}
+
+impl MirrorEntry {
+    fn from_metadata(metadata: &Metadata) -> Option<MirrorEntry> {
+        Some(MirrorEntry {
+            fileid: metadata.fileid?,
+            hash: metadata.hash,
+            size: metadata.size,
+            modified: metadata.modified,
+        })
+    }
+
+    /// Checks if the remote file is unchanged (same hash, size and modification time). The file id is not compared, since it changes on server-side copies of unchanged content.
+    fn is_unchanged(&self, other: &MirrorEntry) -> bool {
+        self.hash == other.hash && self.size == other.size && self.modified == other.modified
+    }
+}
+
+/// Persisted state of a mirror, mapping the relative paths (separated by '/') of all mirrored files to their remote state
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct MirrorState {
+    /// Time of the last successful run
+    pub last_run: Option<DateTime<Utc>>,
+    /// All mirrored files
+    pub files: BTreeMap<String, MirrorEntry>,
+}
+
+impl MirrorState {
+    /// Loads the state from the given JSON file. A missing file results in an empty state.
+    pub async fn load(path: &Path) -> Result<MirrorState, PCloudError> {
+        match tokio::fs::read(path).await {
+            Ok(content) => Ok(serde_json::from_slice(&content)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(MirrorState::default()),
+            Err(e) => Err(e)?,
+        }
+    }
+
+    /// Writes the state as JSON to the given file
+    pub async fn save(&self, path: &Path) -> Result<(), PCloudError> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(path, serde_json::to_vec_pretty(self)?).await?;
+        Ok(())
+    }
+}
+
+/// Summary of the changes applied by a mirror run. All paths are relative to the mirrored folder and separated by '/'.
+#[derive(Debug, Clone, Default)]
+pub struct MirrorReport {
+    /// Files downloaded for the first time
+    pub added: Vec<String>,
+    /// Files downloaded again, because they changed remotely (or were missing locally)
+    pub updated: Vec<String>,
+    /// Local files deleted, because they were removed remotely
+    pub deleted: Vec<String>,
+    /// Number of files left untouched
+    pub unchanged: usize,
+    /// Number of remote files excluded by the filter
+    pub excluded: usize,
+    /// Number of bytes downloaded
+    pub bytes_downloaded: u64,
+}
+
+impl MirrorReport {
+    /// Checks if the run changed anything locally
+    pub fn has_changes(&self) -> bool {
+        !self.added.is_empty() || !self.updated.is_empty() || !self.deleted.is_empty()
+    }
+}
+
+/// Mirrors a remote folder (recursively) into a local directory. Only files whose hash, size or modification time changed since the last run are downloaded, based on a persisted state manifest.
+pub struct MirrorRequestBuilder {
+    /// Client to actually perform the requests
+    client: PCloudClient,
+    /// Remote folder to mirror
+    folder: PCloudFolder,
+    /// Local target directory
+    directory: PathBuf,
+    /// Location of the state manifest
+    state_file: Option<PathBuf>,
+    /// Delete local files removed remotely
+    delete_removed: bool,
+    /// Files neither downloaded nor deleted
+    filter: Option<SyncFilter>,
+}
+
+#[allow(dead_code)]
+impl MirrorRequestBuilder {
+    pub(crate) fn for_folder<T: FolderDescriptor, P: AsRef<Path>>(
+        client: &PCloudClient,
+        folder_like: T,
+        directory: P,
+    ) -> Result<MirrorRequestBuilder, PCloudError> {
+        let folder = folder_like.to_folder()?;
+
+        if folder.is_empty() {
+            Err(PCloudResult::NoFileIdOrPathProvided)?
+        }
+
+        Ok(MirrorRequestBuilder {
+            client: client.clone(),
+            folder,
+            directory: directory.as_ref().to_path_buf(),
+            state_file: None,
+            delete_removed: false,
+            filter: None,
+        })
+    }
+
+    /// Location of the state manifest. Defaults to `.pcloud-mirror.json` in the local directory.
+    pub fn state_file<P: AsRef<Path>>(mut self, path: P) -> MirrorRequestBuilder {
+        self.state_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// If set, local files mirrored by a previous run are deleted once they are removed remotely. Local files never mirrored are not touched. Defaults to false.
+    pub fn delete_removed(mut self, value: bool) -> MirrorRequestBuilder {
+        self.delete_removed = value;
+        self
+    }
+
+    /// Files neither downloaded nor deleted. Defaults to the rules of the `.pcloudignore` file in the local directory, if present.
+    pub fn filter(mut self, value: SyncFilter) -> MirrorRequestBuilder {
+        self.filter = Some(value);
+        self
+    }
+
+    /// Executes the mirror run and persists the new state. The state is also persisted if a download fails, so the next run continues with the files still missing.
+    pub async fn execute(self) -> Result<MirrorReport, PCloudError> {
+        let state_file = self
+            .state_file
+            .clone()
+            .unwrap_or_else(|| self.directory.join(DEFAULT_MIRROR_STATE_FILE));
+        let mut state = MirrorState::load(&state_file).await?;
+        let filter = match &self.filter {
+            Some(filter) => filter.clone(),
+            None => SyncFilter::for_directory(&self.directory).await?,
+        };
+
+        let listing = self
+            .client
+            .list_folder(&self.folder)?
+            .recursive(true)
+            .get()
+            .await?;
+        let mut remote = BTreeMap::new();
+        if let Some(metadata) = listing.metadata {
+            collect_files(&metadata.contents, "", &mut remote);
+        }
+        debug!(
+            "Mirroring {} remote files into {}",
+            remote.len(),
+            self.directory.display()
+        );
+
+        let mut report = MirrorReport::default();
+        let result = self.apply(&remote, &filter, &mut state, &mut report).await;
+        if result.is_ok() {
+            state.last_run = Some(Utc::now());
+        }
+        state.save(&state_file).await?;
+        result?;
+
+        debug!(
+            "Mirror finished: {} added, {} updated, {} deleted, {} unchanged",
+            report.added.len(),
+            report.updated.len(),
+            report.deleted.len(),
+            report.unchanged
+        );
+        Ok(report)
+    }
+
+    /// Downloads all new and changed files and deletes the removed ones, updating the state after each file
+    async fn apply(
+        &self,
+        remote: &BTreeMap<String, MirrorEntry>,
+        filter: &SyncFilter,
+        state: &mut MirrorState,
+        report: &mut MirrorReport,
+    ) -> Result<(), PCloudError> {
+        for (path, entry) in remote {
+            if filter.is_excluded(path, entry.size) {
+                report.excluded += 1;
+                continue;
+            }
+
+            let target = local_path(&self.directory, path);
+            let known = state.files.get(path);
+
+            if let Some(known) = known {
+                if known.is_unchanged(entry) && tokio::fs::metadata(&target).await.is_ok() {
+                    report.unchanged += 1;
+                    continue;
+                }
+            }
+
+            if let Some(parent) = target.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            debug!("Downloading {}", path);
+            let info = self
+                .client
+                .download_and_verify(entry.fileid, &target)
+                .await?;
+            report.bytes_downloaded += info.content_length.or(entry.size).unwrap_or_default();
+
+            if known.is_some() {
+                report.updated.push(path.clone());
+            } else {
+                report.added.push(path.clone());
+            }
+            state.files.insert(path.clone(), entry.clone());
+        }
+
+        let removed: Vec<String> = state
+            .files
+            .keys()
+            // Excluded files are kept, they may just not be mirrored anymore
+            .filter(|path| !remote.contains_key(*path) && !filter.is_excluded(path, None))
+            .cloned()
+            .collect();
+        for path in removed {
+            if self.delete_removed {
+                let target = local_path(&self.directory, &path);
+                debug!("Deleting {}, removed remotely", path);
+                match tokio::fs::remove_file(&target).await {
+                    Ok(_) => report.deleted.push(path.clone()),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => {
+                        warn!("Failed to delete {}: {}", target.display(), e);
+                        Err(e)?
+                    }
+                }
+            }
+            state.files.remove(&path);
+        }
+
+        Ok(())
+    }
+}
+
+/// Collects all files of the given (recursive) folder contents with their path relative to the folder
+fn collect_files(contents: &[Metadata], prefix: &str, files: &mut BTreeMap<String, MirrorEntry>) {
+    for metadata in contents {
+        let path = if prefix.is_empty() {
+            metadata.name.clone()
+        } else {
+            format!("{}/{}", prefix, metadata.name)
+        };
+        if metadata.isfolder {
+            collect_files(&metadata.contents, &path, files);
+        } else if let Some(entry) = MirrorEntry::from_metadata(metadata) {
+            files.insert(path, entry);
+        }
+    }
+}
+
+/// Local path of the given relative path (separated by '/')
+fn local_path(directory: &Path, path: &str) -> PathBuf {
+    path.split('/')
+        .fold(directory.to_path_buf(), |p, c| p.join(c))
+}
+
+/// Local file found while scanning the local directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalFile {
+    /// Size in bytes
+    pub size: u64,
+    /// Modification time
+    pub modified: DateTime<Utc>,
+}
+
+impl LocalFile {
+    /// Reads size and modification time of the given local file
+    async fn read(path: &Path) -> Result<LocalFile, PCloudError> {
+        let metadata = tokio::fs::metadata(path).await?;
+        Ok(LocalFile {
+            size: metadata.len(),
+            modified: metadata.modified()?.into(),
+        })
+    }
+}
+
+/// State of a single file at the time of the last two-way sync, on both sides
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SyncEntry {
+    /// State of the remote file
+    pub remote: MirrorEntry,
+    /// Size of the local file in bytes
+    pub local_size: u64,
+    /// Modification time of the local file
+    pub local_modified: DateTime<Utc>,
+}
+
+impl SyncEntry {
+    /// Checks if the local file is unchanged (same size and modification time)
+    fn is_local_unchanged(&self, local: &LocalFile) -> bool {
+        self.local_size == local.size && self.local_modified == local.modified
+    }
+}
+
+/// Persisted state of a two-way sync, mapping the relative paths (separated by '/') of all synced files to their state on both sides
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SyncState {
+    /// Diff id of the account at the start of the last successful run. Without newer events the remote folder isn't listed again.
+    pub diffid: Option<u64>,
+    /// Time of the last successful run
+    pub last_run: Option<DateTime<Utc>>,
+    /// All synced files
+    pub files: BTreeMap<String, SyncEntry>,
+}
+
+impl SyncState {
+    /// Loads the state from the given JSON file. A missing file results in an empty state.
+    pub async fn load(path: &Path) -> Result<SyncState, PCloudError> {
+        match tokio::fs::read(path).await {
+            Ok(content) => Ok(serde_json::from_slice(&content)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(SyncState::default()),
+            Err(e) => Err(e)?,
+        }
+    }
+
+    /// Writes the state as JSON to the given file
+    pub async fn save(&self, path: &Path) -> Result<(), PCloudError> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(path, serde_json::to_vec_pretty(self)?).await?;
+        Ok(())
+    }
+}
+
+/// Diff id and time of the last successful two-way sync run
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SyncCheckpoint {
+    /// Diff id of the account at the start of the last successful run
+    pub diffid: Option<u64>,
+    /// Time of the last successful run
+    pub last_run: Option<DateTime<Utc>>,
+}
+
+/// Storage of the state of a two-way sync (see `SyncRequestBuilder::state_store()`). Entries are updated after each file, so the storage should handle single updates efficiently.
+pub trait SyncStateStore: Send + Sync {
+    /// Loads the checkpoint of the last successful run
+    fn load_checkpoint(&self) -> Result<SyncCheckpoint, PCloudError>;
+
+    /// Stores the checkpoint
+    fn save_checkpoint(&self, checkpoint: &SyncCheckpoint) -> Result<(), PCloudError>;
+
+    /// Returns the state of all synced files by their relative path
+    fn entries(&self) -> Result<Vec<(String, SyncEntry)>, PCloudError>;
+
+    /// Stores the state of the file with the given relative path
+    fn put(&self, path: &str, entry: &SyncEntry) -> Result<(), PCloudError>;
+
+    /// Removes the state of the file with the given relative path
+    fn remove(&self, path: &str) -> Result<(), PCloudError>;
+
+    /// Records an operation applied (the sync decision), stores not keeping a history ignore it
+    fn record_operation(&self, _operation: &SyncOperation) -> Result<(), PCloudError> {
+        Ok(())
+    }
+
+    /// Persists all pending changes, called at the end of each run
+    fn flush(&self) -> Result<(), PCloudError> {
+        Ok(())
+    }
+
+    /// Local files used by the store, never synced if they are located in the local directory
+    fn files(&self) -> Vec<PathBuf> {
+        vec![]
+    }
+}
+
+/// SyncStateStore keeping the whole state in memory and writing it as a single JSON file (see `SyncState`) at the end of each run. Suited for folders up to some ten thousand files.
+pub struct JsonSyncStateStore {
+    path: PathBuf,
+    state: Mutex<SyncState>,
+}
+
+impl JsonSyncStateStore {
+    /// Opens the state stored in the given JSON file. A missing file results in an empty state.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<JsonSyncStateStore, PCloudError> {
+        let path = path.as_ref().to_path_buf();
+        let state = match std::fs::read(&path) {
+            Ok(content) => serde_json::from_slice(&content)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => SyncState::default(),
+            Err(e) => Err(e)?,
+        };
+        Ok(JsonSyncStateStore {
+            path,
+            state: Mutex::new(state),
+        })
+    }
+}
+
+impl SyncStateStore for JsonSyncStateStore {
+    fn load_checkpoint(&self) -> Result<SyncCheckpoint, PCloudError> {
+        let state = self.state.lock().unwrap();
+        Ok(SyncCheckpoint {
+            diffid: state.diffid,
+            last_run: state.last_run,
+        })
+    }
+
+    fn save_checkpoint(&self, checkpoint: &SyncCheckpoint) -> Result<(), PCloudError> {
+        let mut state = self.state.lock().unwrap();
+        state.diffid = checkpoint.diffid;
+        state.last_run = checkpoint.last_run;
+        Ok(())
+    }
+
+    fn entries(&self) -> Result<Vec<(String, SyncEntry)>, PCloudError> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .files
+            .iter()
+            .map(|(path, entry)| (path.clone(), entry.clone()))
+            .collect())
+    }
+
+    fn put(&self, path: &str, entry: &SyncEntry) -> Result<(), PCloudError> {
+        let mut state = self.state.lock().unwrap();
+        state.files.insert(path.to_string(), entry.clone());
+        Ok(())
+    }
+
+    fn remove(&self, path: &str) -> Result<(), PCloudError> {
+        self.state.lock().unwrap().files.remove(path);
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<(), PCloudError> {
+        let content = serde_json::to_vec_pretty(&*self.state.lock().unwrap())?;
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, content)?;
+        Ok(())
+    }
+
+    fn files(&self) -> Vec<PathBuf> {
+        vec![self.path.clone()]
+    }
+}
+
+/// Resolution of a conflict
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+    /// The local version replaces the remote one
+    KeepLocal,
+    /// The remote version replaces the local one
+    KeepRemote,
+    /// The local version is renamed to a conflict copy, both versions end up on both sides
+    KeepBoth,
+}
+
+/// File changed both locally and remotely since the last sync (or present on both sides with different content at the first sync)
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncConflict {
+    /// Path relative to the synced folder, separated by '/'
+    pub path: String,
+    /// State of the local file
+    pub local: LocalFile,
+    /// State of the remote file
+    pub remote: MirrorEntry,
+}
+
+/// Callback deciding how to resolve a conflict
+pub type ConflictCallback = Arc<dyn Fn(&SyncConflict) -> ConflictResolution + Send + Sync>;
+
+/// Strategy to resolve conflicts
+#[derive(Clone, Default)]
+pub enum ConflictStrategy {
+    /// The version modified last wins
+    #[default]
+    NewestWins,
+    /// Both versions are kept, the local version is renamed to a conflict copy
+    KeepBoth,
+    /// The callback decides for each conflict, e.g. by asking the user
+    Prompt(ConflictCallback),
+}
+
+impl ConflictStrategy {
+    fn resolve(&self, conflict: &SyncConflict) -> ConflictResolution {
+        match self {
+            ConflictStrategy::NewestWins if conflict.local.modified >= conflict.remote.modified => {
+                ConflictResolution::KeepLocal
+            }
+            ConflictStrategy::NewestWins => ConflictResolution::KeepRemote,
+            ConflictStrategy::KeepBoth => ConflictResolution::KeepBoth,
+            ConflictStrategy::Prompt(callback) => callback(conflict),
+        }
+    }
+}
+
+/// Actions applied by a two-way sync
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncAction {
+    /// Local file uploaded
+    Upload,
+    /// Remote file downloaded
+    Download,
+    /// Local file deleted, because it was deleted remotely
+    DeleteLocal,
+    /// Remote file deleted, because it was deleted locally
+    DeleteRemote,
+    /// Local file moved, because it was moved remotely
+    MoveLocal,
+    /// Remote file moved, because it was moved locally
+    MoveRemote,
+}
+
+/// Single operation applied by a two-way sync, as written to the operation log
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SyncOperation {
+    /// Time the operation was completed
+    pub time: DateTime<Utc>,
+    /// Action applied
+    pub action: SyncAction,
+    /// Path relative to the synced folder, separated by '/'
+    pub path: String,
+    /// Previous path, if the operation moved the file
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub from: Option<String>,
+    /// Resolution applied, if the operation resolves a conflict
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub conflict: Option<ConflictResolution>,
+}
+
+/// Summary of a two-way sync run
+#[derive(Debug, Clone, Default)]
+pub struct SyncReport {
+    /// All operations applied, in order
+    pub operations: Vec<SyncOperation>,
+    /// Number of conflicts resolved
+    pub conflicts: usize,
+    /// Number of files unchanged on both sides
+    pub unchanged: usize,
+    /// Number of files excluded by the filter
+    pub excluded: usize,
+    /// Number of bytes uploaded
+    pub bytes_uploaded: u64,
+    /// Number of bytes downloaded
+    pub bytes_downloaded: u64,
+    /// If false, there were no remote events since the last run and the remote folder wasn't listed
+    pub remote_listed: bool,
+    /// If set, the run was stopped by a shutdown before all changes were applied. The next run continues.
+    pub interrupted: bool,
+    /// Number of local changes not applied to the remote folder, because `push_local_changes()` is off
+    pub local_changes_pending: usize,
+}
+
+impl SyncReport {
+    /// Checks if the run changed anything on either side
+    pub fn has_changes(&self) -> bool {
+        !self.operations.is_empty()
+    }
+}
+
+/// Synchronizes a remote folder and a local directory in both directions. Remote changes are detected by diff events (the folder is only listed if events occurred since the last run), local changes by scanning the directory. Changes on both sides are resolved by a conflict strategy, all operations can be written to an operation log.
+pub struct SyncRequestBuilder {
+    /// Client to actually perform the requests
+    client: PCloudClient,
+    /// Remote folder to sync
+    folder: PCloudFolder,
+    /// Local directory to sync
+    directory: PathBuf,
+    /// Location of the state
+    state_file: Option<PathBuf>,
+    /// Storage of the state, replacing the state file
+    state_store: Option<Arc<dyn SyncStateStore>>,
+    /// JSON Lines file all operations are appended to
+    operation_log: Option<PathBuf>,
+    /// Resolves files changed on both sides
+    conflict_strategy: ConflictStrategy,
+    /// Files left alone on both sides
+    filter: Option<SyncFilter>,
+    /// Files up to this size (in bytes) are synced first
+    small_files: Option<u64>,
+    /// Priorities of relative folders, the first match wins
+    folder_priorities: Vec<(String, TransferPriority)>,
+    /// Receives the progress of the sync run
+    progress: Option<Arc<dyn ProgressObserver>>,
+    /// Stops the sync run gracefully once triggered
+    shutdown: ShutdownSignal,
+    /// Apply local changes to the remote folder
+    push_local_changes: bool,
+}
+
+#[allow(dead_code)]
+impl SyncRequestBuilder {
+    pub(crate) fn for_folder<T: FolderDescriptor, P: AsRef<Path>>(
+        client: &PCloudClient,
+        folder_like: T,
+        directory: P,
+    ) -> Result<SyncRequestBuilder, PCloudError> {
+        let folder = folder_like.to_folder()?;
+
+        if folder.is_empty() {
+            Err(PCloudResult::NoFileIdOrPathProvided)?
+        }
+
+        Ok(SyncRequestBuilder {
+            client: client.clone(),
+            folder,
+            directory: directory.as_ref().to_path_buf(),
+            state_file: None,
+            state_store: None,
+            operation_log: None,
+            conflict_strategy: ConflictStrategy::default(),
+            filter: None,
+            small_files: None,
+            folder_priorities: Vec::new(),
+            progress: None,
+            shutdown: client.shutdown_signal().clone(),
+            push_local_changes: true,
+        })
+    }
+
+    /// Location of the state. Defaults to `.pcloud-sync.json` in the local directory.
+    pub fn state_file<P: AsRef<Path>>(mut self, path: P) -> SyncRequestBuilder {
+        self.state_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Storage of the state (like the `SqliteSyncStateStore` of the feature `sqlite`), replacing the JSON state file
+    pub fn state_store(mut self, store: Arc<dyn SyncStateStore>) -> SyncRequestBuilder {
+        self.state_store = Some(store);
+        self
+    }
+
+    /// Appends every operation applied as JSON line to the given file, for auditing
+    pub fn operation_log<P: AsRef<Path>>(mut self, path: P) -> SyncRequestBuilder {
+        self.operation_log = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Strategy to resolve files changed on both sides. Defaults to `NewestWins`.
+    pub fn conflict_strategy(mut self, value: ConflictStrategy) -> SyncRequestBuilder {
+        self.conflict_strategy = value;
+        self
+    }
+
+    /// Lets the callback decide how to resolve each conflict
+    pub fn on_conflict<F: Fn(&SyncConflict) -> ConflictResolution + Send + Sync + 'static>(
+        self,
+        callback: F,
+    ) -> SyncRequestBuilder {
+        self.conflict_strategy(ConflictStrategy::Prompt(Arc::new(callback)))
+    }
+
+    /// Files excluded from the sync on both sides. Defaults to the rules of the `.pcloudignore` file in the local directory, if present.
+    pub fn filter(mut self, value: SyncFilter) -> SyncRequestBuilder {
+        self.filter = Some(value);
+        self
+    }
+
+    /// Files up to the given size (in bytes) get a high priority and are synced before bulk data
+    pub fn small_files_first(mut self, max_size: u64) -> SyncRequestBuilder {
+        self.small_files = Some(max_size);
+        self
+    }
+
+    /// Files below the given folder (relative to the synced folder, separated by '/') are synced with the given priority. Takes precedence over `small_files_first()`, the first matching folder wins.
+    pub fn prioritize_folder(
+        mut self,
+        path: &str,
+        priority: TransferPriority,
+    ) -> SyncRequestBuilder {
+        self.folder_priorities
+            .push((path.trim_matches('/').to_string(), priority));
+        self
+    }
+
+    /// Reports the progress of the sync run, each file downloaded or uploaded being an item named by its relative path. The number of files is not known in advance.
+    pub fn progress_observer(mut self, value: Arc<dyn ProgressObserver>) -> SyncRequestBuilder {
+        self.progress = Some(value);
+        self
+    }
+
+    /// Signal to stop the sync run gracefully, defaults to the one of the client (see `PCloudClient::shutdown()`). Once triggered no further files are synced, the running operation gets until the deadline to complete. The state of all files synced so far is persisted, the report is marked as interrupted.
+    pub fn shutdown_signal(mut self, value: ShutdownSignal) -> SyncRequestBuilder {
+        self.shutdown = value;
+        self
+    }
+
+    /// If set (default), local changes are applied to the remote folder. Otherwise only remote changes are applied to the local directory, local changes are left alone (and counted in the report) and conflicts are resolved in favor of the remote version.
+    pub fn push_local_changes(mut self, value: bool) -> SyncRequestBuilder {
+        self.push_local_changes = value;
+        self
+    }
+
+    /// Executes the sync run and persists the new state. The state is also persisted if an operation fails, the next run then lists the remote folder again and continues.
+    pub async fn execute(mut self) -> Result<SyncReport, PCloudError> {
+        let _running = self.shutdown.begin();
+        let store = match &self.state_store {
+            Some(store) => store.clone(),
+            None => {
+                let state_file = self
+                    .state_file
+                    .clone()
+                    .unwrap_or_else(|| self.directory.join(DEFAULT_SYNC_STATE_FILE));
+                let store: Arc<dyn SyncStateStore> =
+                    Arc::new(JsonSyncStateStore::open(state_file)?);
+                self.state_store = Some(store.clone());
+                store
+            }
+        };
+        let filter = match &self.filter {
+            Some(filter) => filter.clone(),
+            None => SyncFilter::for_directory(&self.directory).await?,
+        };
+        let checkpoint = store.load_checkpoint()?;
+        let mut previous: BTreeMap<String, SyncEntry> = store.entries()?.into_iter().collect();
+        // Invalidated until the run succeeds, so an interrupted run lists the remote folder again
+        store.save_checkpoint(&SyncCheckpoint {
+            diffid: None,
+            last_run: checkpoint.last_run,
+        })?;
+        let mut report = SyncReport::default();
+        let mut folders = HashMap::new();
+
+        // Taken before listing, so changes during the run are detected by the next one
+        let diffid = self.client.get_events().only_last(0).get().await?.diffid;
+        let mut remote = if checkpoint.diffid == Some(diffid) {
+            debug!("No remote events since the last run");
+            previous
+                .iter()
+                .map(|(path, entry)| (path.clone(), entry.remote.clone()))
+                .collect()
+        } else {
+            let listing = self
+                .client
+                .list_folder(&self.folder)?
+                .recursive(true)
+                .get()
+                .await?;
+            let mut remote = BTreeMap::new();
+            if let Some(metadata) = listing.metadata {
+                if let Some(folderid) = metadata.folderid {
+                    folders.insert(String::new(), folderid);
+                }
+                collect_files(&metadata.contents, "", &mut remote);
+            }
+            report.remote_listed = true;
+            remote
+        };
+
+        let mut excluded = store.files();
+        excluded.extend(self.operation_log.clone());
+        let mut local = scan_directory(&self.directory, &excluded, &filter).await?;
+
+        // Excluded on one side means left alone on both, including the state of the last run
+        let excluded: BTreeSet<String> = remote
+            .iter()
+            .filter(|(path, entry)| filter.is_excluded(path, entry.size))
+            .map(|(path, _)| path)
+            .chain(
+                local
+                    .iter()
+                    .filter(|(path, file)| filter.is_excluded(path, Some(file.size)))
+                    .map(|(path, _)| path),
+            )
+            .chain(
+                previous
+                    .keys()
+                    .filter(|path| filter.is_excluded(path, None)),
+            )
+            .cloned()
+            .collect();
+        remote.retain(|path, _| !excluded.contains(path));
+        local.retain(|path, _| !excluded.contains(path));
+        previous.retain(|path, _| !excluded.contains(path));
+        report.excluded = excluded.len();
+
+        debug!(
+            "Syncing {} remote and {} local files",
+            remote.len(),
+            local.len()
+        );
+
+        if let Some(observer) = &self.progress {
+            observer.operation_started(None, None);
+        }
+        let result = tokio::select! {
+            result = self.apply(
+                &remote,
+                &local,
+                &previous,
+                store.as_ref(),
+                &mut folders,
+                &mut report,
+            ) => result,
+            _ = self.shutdown.deadline_passed() => {
+                warn!("Shutdown deadline passed, interrupting sync of {}", self.folder);
+                Ok(())
+            }
+        };
+        if self.shutdown.is_triggered() && result.is_ok() {
+            report.interrupted = true;
+        }
+        if let Some(observer) = &self.progress {
+            observer.operation_finished();
+        }
+        if result.is_ok() && !report.interrupted {
+            store.save_checkpoint(&SyncCheckpoint {
+                diffid: Some(diffid),
+                last_run: Some(Utc::now()),
+            })?;
+        }
+        store.flush()?;
+        result?;
+
+        debug!(
+            "Sync finished: {} operations, {} conflicts, {} unchanged",
+            report.operations.len(),
+            report.conflicts,
+            report.unchanged
+        );
+        Ok(report)
+    }
+
+    /// Compares both sides with the state of the last run and applies all changes, updating the state after each file
+    async fn apply(
+        &self,
+        remote: &BTreeMap<String, MirrorEntry>,
+        local: &BTreeMap<String, LocalFile>,
+        previous: &BTreeMap<String, SyncEntry>,
+        store: &dyn SyncStateStore,
+        folders: &mut HashMap<String, u64>,
+        report: &mut SyncReport,
+    ) -> Result<(), PCloudError> {
+        let moved = self
+            .apply_moves(remote, local, previous, store, folders, report)
+            .await?;
+        let paths: BTreeSet<&String> = remote
+            .keys()
+            .chain(local.keys())
+            .chain(previous.keys())
+            .filter(|path| !moved.contains(*path))
+            .collect();
+        let mut paths: Vec<String> = paths.into_iter().cloned().collect();
+        // Stable, so files of the same priority are still synced in order of their path
+        paths.sort_by_key(|path| {
+            std::cmp::Reverse(
+                self.priority(
+                    path,
+                    local
+                        .get(path)
+                        .map(|file| file.size)
+                        .or_else(|| remote.get(path).and_then(|entry| entry.size)),
+                ),
+            )
+        });
+
+        for path in paths {
+            if self.shutdown.is_triggered() {
+                debug!("Shutting down, stopping sync before {}", path);
+                return Ok(());
+            }
+            let known = previous.get(&path).cloned();
+            let remote_entry = remote.get(&path);
+            let local_file = local.get(&path);
+
+            let remote_changed = match (&known, remote_entry) {
+                (Some(known), Some(entry)) => !known.remote.is_unchanged(entry),
+                (None, None) => false,
+                _ => true,
+            };
+            let local_changed = match (&known, local_file) {
+                (Some(known), Some(file)) => !known.is_local_unchanged(file),
+                (None, None) => false,
+                _ => true,
+            };
+
+            match (remote_changed, local_changed, remote_entry, local_file) {
+                (false, false, _, _) => report.unchanged += 1,
+                (true, false, None, _) => {
+                    self.delete_local(&path, report).await?;
+                    store.remove(&path)?;
+                }
+                (false, true, _, _) | (_, true, None, Some(_)) if !self.push_local_changes => {
+                    report.local_changes_pending += 1;
+                }
+                (false, true, _, None) => {
+                    if let Some(known) = known {
+                        self.delete_remote(&path, known.remote.fileid, report)
+                            .await?;
+                    }
+                    store.remove(&path)?;
+                }
+                (_, _, None, None) => {
+                    store.remove(&path)?;
+                }
+                // Changed remotely (and not locally or deleted locally)
+                (true, _, Some(entry), None) | (true, false, Some(entry), _) => {
+                    let synced = self.download(&path, entry, None, report).await?;
+                    store.put(&path, &synced)?;
+                }
+                // Changed locally (and not remotely or deleted remotely)
+                (_, true, None, Some(_)) | (false, true, _, Some(_)) => {
+                    let synced = self.upload(&path, folders, None, report).await?;
+                    store.put(&path, &synced)?;
+                }
+                (true, true, Some(entry), Some(file)) => {
+                    if known.is_none() && self.is_identical(&path, entry, file).await? {
+                        report.unchanged += 1;
+                        let synced = SyncEntry {
+                            remote: entry.clone(),
+                            local_size: file.size,
+                            local_modified: file.modified,
+                        };
+                        store.put(&path, &synced)?;
+                        continue;
+                    }
+
+                    let conflict = SyncConflict {
+                        path: path.clone(),
+                        local: file.clone(),
+                        remote: entry.clone(),
+                    };
+                    let resolution = if self.push_local_changes {
+                        self.conflict_strategy.resolve(&conflict)
+                    } else {
+                        ConflictResolution::KeepRemote
+                    };
+                    debug!("Conflict on {} resolved by {:?}", path, resolution);
+                    report.conflicts += 1;
+                    match resolution {
+                        ConflictResolution::KeepLocal => {
+                            let synced = self
+                                .upload(&path, folders, Some(resolution), report)
+                                .await?;
+                            store.put(&path, &synced)?;
+                        }
+                        ConflictResolution::KeepRemote => {
+                            let synced = self
+                                .download(&path, entry, Some(resolution), report)
+                                .await?;
+                            store.put(&path, &synced)?;
+                        }
+                        ConflictResolution::KeepBoth => {
+                            let copy = conflict_copy_path(&path, Utc::now());
+                            tokio::fs::rename(
+                                local_path(&self.directory, &path),
+                                local_path(&self.directory, &copy),
+                            )
+                            .await?;
+                            let synced = self
+                                .upload(&copy, folders, Some(resolution), report)
+                                .await?;
+                            store.put(&copy, &synced)?;
+                            let synced = self
+                                .download(&path, entry, Some(resolution), report)
+                                .await?;
+                            store.put(&path, &synced)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Priority of the file with the given relative path and size
+    fn priority(&self, path: &str, size: Option<u64>) -> TransferPriority {
+        let folder = self
+            .folder_priorities
+            .iter()
+            .find(|(folder, _)| folder.is_empty() || path.starts_with(&format!("{}/", folder)));
+        match (folder, self.small_files, size) {
+            (Some((_, priority)), _, _) => *priority,
+            (None, Some(max_size), Some(size)) if size <= max_size => TransferPriority::High,
+            _ => TransferPriority::Normal,
+        }
+    }
+
+    /// Detects files moved (or renamed) on one side since the last run by their content and moves them on the other side as well, instead of deleting and transferring them again. Returns the old and new paths of all files moved.
+    async fn apply_moves(
+        &self,
+        remote: &BTreeMap<String, MirrorEntry>,
+        local: &BTreeMap<String, LocalFile>,
+        previous: &BTreeMap<String, SyncEntry>,
+        store: &dyn SyncStateStore,
+        folders: &mut HashMap<String, u64>,
+        report: &mut SyncReport,
+    ) -> Result<BTreeSet<String>, PCloudError> {
+        let mut moved = BTreeSet::new();
+
+        // Moved locally: gone locally but unchanged remotely, and a new local file with the same SHA-1 checksum
+        let mut vanished: Vec<(&String, &SyncEntry)> = previous
+            .iter()
+            .filter(|(path, known)| {
+                self.push_local_changes
+                    && !local.contains_key(*path)
+                    && remote
+                        .get(*path)
+                        .map(|entry| known.remote.is_unchanged(entry))
+                        .unwrap_or(false)
+            })
+            .collect();
+        for (path, file) in local {
+            if vanished.is_empty() {
+                break;
+            }
+            if previous.contains_key(path) || remote.contains_key(path) {
+                continue;
+            }
+            let candidates: Vec<usize> = (0..vanished.len())
+                .filter(|i| vanished[*i].1.remote.size == Some(file.size))
+                .collect();
+            let mut source = None;
+            for index in candidates {
+                if self
+                    .is_identical(path, &vanished[index].1.remote, file)
+                    .await?
+                {
+                    source = Some(index);
+                    break;
+                }
+            }
+            if let Some(index) = source {
+                let (from, known) = vanished.remove(index);
+                let synced = self
+                    .move_remote(from, path, known, file, folders, report)
+                    .await?;
+                store.remove(from)?;
+                store.put(path, &synced)?;
+                moved.insert(from.clone());
+                moved.insert(path.clone());
+            }
+        }
+
+        // Moved remotely: gone remotely but unchanged locally, and a new remote file with the same id or content hash
+        let mut vanished: Vec<(&String, &SyncEntry)> = previous
+            .iter()
+            .filter(|(path, known)| {
+                !moved.contains(*path)
+                    && !remote.contains_key(*path)
+                    && local
+                        .get(*path)
+                        .map(|file| known.is_local_unchanged(file))
+                        .unwrap_or(false)
+            })
+            .collect();
+        for (path, entry) in remote {
+            if previous.contains_key(path) || local.contains_key(path) {
+                continue;
+            }
+            let source = vanished.iter().position(|(_, known)| {
+                known.remote.fileid == entry.fileid
+                    || (entry.hash.is_some() && known.remote.hash == entry.hash)
+            });
+            if let Some(index) = source {
+                let (from, _) = vanished.remove(index);
+                let synced = self.move_local(from, path, entry, report).await?;
+                store.remove(from)?;
+                store.put(path, &synced)?;
+                moved.insert(from.clone());
+                moved.insert(path.clone());
+            }
+        }
+
+        Ok(moved)
+    }
+
+    /// Moves the remote file to the new path of the local one (creating missing remote folders) and returns its new state
+    async fn move_remote(
+        &self,
+        from: &str,
+        path: &str,
+        known: &SyncEntry,
+        file: &LocalFile,
+        folders: &mut HashMap<String, u64>,
+        report: &mut SyncReport,
+    ) -> Result<SyncEntry, PCloudError> {
+        let (parent, name) = path.rsplit_once('/').unwrap_or(("", path));
+        let folderid = self.remote_folder(parent, folders).await?;
+
+        debug!("Moving remote {} to {}, moved locally", from, path);
+        let stat = self
+            .client
+            .move_file(known.remote.fileid, folderid)?
+            .with_new_name(name)
+            .execute()
+            .await?;
+        let remote = stat
+            .metadata
+            .as_ref()
+            .and_then(MirrorEntry::from_metadata)
+            .ok_or(PCloudResult::FileNotFound)?;
+        self.record(
+            report,
+            SyncOperation {
+                time: Utc::now(),
+                action: SyncAction::MoveRemote,
+                path: path.to_string(),
+                from: Some(from.to_string()),
+                conflict: None,
+            },
+        )
+        .await?;
+
+        Ok(SyncEntry {
+            remote,
+            local_size: file.size,
+            local_modified: file.modified,
+        })
+    }
+
+    /// Moves the local file to the new path of the remote one and returns its new state
+    async fn move_local(
+        &self,
+        from: &str,
+        path: &str,
+        entry: &MirrorEntry,
+        report: &mut SyncReport,
+    ) -> Result<SyncEntry, PCloudError> {
+        let target = local_path(&self.directory, path);
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        debug!("Moving local {} to {}, moved remotely", from, path);
+        tokio::fs::rename(local_path(&self.directory, from), &target).await?;
+        self.record(
+            report,
+            SyncOperation {
+                time: Utc::now(),
+                action: SyncAction::MoveLocal,
+                path: path.to_string(),
+                from: Some(from.to_string()),
+                conflict: None,
+            },
+        )
+        .await?;
+
+        let local = LocalFile::read(&target).await?;
+        Ok(SyncEntry {
+            remote: entry.clone(),
+            local_size: local.size,
+            local_modified: local.modified,
+        })
+    }
+
+    /// Checks if the local file has the same content as the remote one (comparing size and SHA-1 checksum)
+    async fn is_identical(
+        &self,
+        path: &str,
+        remote: &MirrorEntry,
+        local: &LocalFile,
+    ) -> Result<bool, PCloudError> {
+        if remote.size != Some(local.size) {
+            return Ok(false);
+        }
+        let checksums = self.client.checksum_file(remote.fileid)?.get().await?;
+        let local_sha1 = sha1_of_file(&local_path(&self.directory, path)).await?;
+        Ok(checksums.sha1.as_deref() == Some(local_sha1.as_str()))
+    }
+
+    /// Downloads the remote file and returns its new state
+    async fn download(
+        &self,
+        path: &str,
+        entry: &MirrorEntry,
+        conflict: Option<ConflictResolution>,
+        report: &mut SyncReport,
+    ) -> Result<SyncEntry, PCloudError> {
+        let target = local_path(&self.directory, path);
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        debug!("Downloading {}", path);
+        let progress = self.progress.as_ref().map(|observer| {
+            observer.item_started(path, entry.size);
+            (observer.clone(), path.to_string())
+        });
+        let result = self
+            .client
+            .download_and_verify_observed(entry.fileid, &target, progress)
+            .await;
+        if let Some(observer) = &self.progress {
+            observer.item_finished(path, result.as_ref().err().map(|e| e as _));
+        }
+        let info = result?;
+        report.bytes_downloaded += info.content_length.or(entry.size).unwrap_or_default();
+        self.log(report, SyncAction::Download, path, conflict)
+            .await?;
+
+        let local = LocalFile::read(&target).await?;
+        Ok(SyncEntry {
+            remote: entry.clone(),
+            local_size: local.size,
+            local_modified: local.modified,
+        })
+    }
+
+    /// Uploads the local file (creating missing remote folders) and returns its new state
+    async fn upload(
+        &self,
+        path: &str,
+        folders: &mut HashMap<String, u64>,
+        conflict: Option<ConflictResolution>,
+        report: &mut SyncReport,
+    ) -> Result<SyncEntry, PCloudError> {
+        let source = local_path(&self.directory, path);
+        let local = LocalFile::read(&source).await?;
+        let parent = path.rsplit_once('/').map(|(p, _)| p).unwrap_or_default();
+        let folderid = self.remote_folder(parent, folders).await?;
+
+        debug!("Uploading {}", path);
+        let mut request = self
+            .client
+            .upload_file_into_folder(folderid)?
+            .with_file_from_path(&source)
+            .await?
+            .keep_local_mtime(true);
+        if let Some(observer) = &self.progress {
+            request = request.progress_observer(ItemsOnly::renamed(observer.clone(), path));
+        }
+        let uploaded = request.upload().await?;
+        let remote = uploaded
+            .into_metadata()?
+            .first()
+            .and_then(MirrorEntry::from_metadata)
+            .ok_or(PCloudResult::InternalUploadError)?;
+        report.bytes_uploaded += local.size;
+        self.log(report, SyncAction::Upload, path, conflict).await?;
+
+        Ok(SyncEntry {
+            remote,
+            local_size: local.size,
+            local_modified: local.modified,
+        })
+    }
+
+    /// Deletes the local file
+    async fn delete_local(&self, path: &str, report: &mut SyncReport) -> Result<(), PCloudError> {
+        debug!("Deleting local {}, deleted remotely", path);
+        match tokio::fs::remove_file(local_path(&self.directory, path)).await {
+            Ok(_) => self.log(report, SyncAction::DeleteLocal, path, None).await,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e)?,
+        }
+    }
+
+    /// Deletes the remote file
+    async fn delete_remote(
+        &self,
+        path: &str,
+        fileid: u64,
+        report: &mut SyncReport,
+    ) -> Result<(), PCloudError> {
+        debug!("Deleting remote {}, deleted locally", path);
+        self.client.delete_file(fileid).await?;
+        self.log(report, SyncAction::DeleteRemote, path, None).await
+    }
+
+    /// Returns the id of the remote folder with the given relative path, creating all missing folders
+    async fn remote_folder(
+        &self,
+        path: &str,
+        folders: &mut HashMap<String, u64>,
+    ) -> Result<u64, PCloudError> {
+        let mut current = match folders.get("") {
+            Some(id) => *id,
+            None => {
+                let stat = self.client.list_folder(&self.folder)?.get().await?;
+                let id = stat
+                    .metadata
+                    .and_then(|m| m.folderid)
+                    .ok_or(PCloudResult::DirectoryDoesNotExist)?;
+                folders.insert(String::new(), id);
+                id
+            }
+        };
+
+        let mut current_path = String::new();
+        for name in path.split('/').filter(|n| !n.is_empty()) {
+            current_path = if current_path.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}/{}", current_path, name)
+            };
+            current = match folders.get(&current_path) {
+                Some(id) => *id,
+                None => {
+                    let stat = self.client.create_folder(current, name)?.execute().await?;
+                    let id = stat
+                        .metadata
+                        .and_then(|m| m.folderid)
+                        .ok_or(PCloudResult::DirectoryDoesNotExist)?;
+                    folders.insert(current_path.clone(), id);
+                    id
+                }
+            };
+        }
+        Ok(current)
+    }
+
+    /// Records an operation (other than a move) in the report, the state store and the operation log
+    async fn log(
+        &self,
+        report: &mut SyncReport,
+        action: SyncAction,
+        path: &str,
+        conflict: Option<ConflictResolution>,
+    ) -> Result<(), PCloudError> {
+        let operation = SyncOperation {
+            time: Utc::now(),
+            action,
+            path: path.to_string(),
+            from: None,
+            conflict,
+        };
+        self.record(report, operation).await
+    }
+
+    /// Records an operation in the report, the state store and the operation log
+    async fn record(
+        &self,
+        report: &mut SyncReport,
+        operation: SyncOperation,
+    ) -> Result<(), PCloudError> {
+        if let Some(log) = &self.operation_log {
+            let mut line = serde_json::to_vec(&operation)?;
+            line.push(b'\n');
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(log)
+                .await?;
+            file.write_all(&line).await?;
+            file.flush().await?;
+        }
+        if let Some(store) = &self.state_store {
+            store.record_operation(&operation)?;
+        }
+        report.operations.push(operation);
+        Ok(())
+    }
+}
+
+/// Scans the local directory recursively and returns all files by their relative path (separated by '/'). Excluded paths and folders excluded by the filter are skipped.
+async fn scan_directory(
+    directory: &Path,
+    excluded: &[PathBuf],
+    filter: &SyncFilter,
+) -> Result<BTreeMap<String, LocalFile>, PCloudError> {
+    let mut files = BTreeMap::new();
+    let mut pending = vec![(directory.to_path_buf(), String::new())];
+    while let Some((dir, prefix)) = pending.pop() {
+        let mut entries = match tokio::fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            // Nothing to upload yet
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && prefix.is_empty() => continue,
+            Err(e) => Err(e)?,
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if excluded.contains(&path) {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            let relative = if prefix.is_empty() {
+                name
+            } else {
+                format!("{}/{}", prefix, name)
+            };
+            let metadata = entry.metadata().await?;
+            if metadata.is_dir() {
+                if !filter.is_folder_excluded(&relative) {
+                    pending.push((path, relative));
+                }
+            } else if metadata.is_file() {
+                files.insert(
+                    relative,
+                    LocalFile {
+                        size: metadata.len(),
+                        modified: metadata.modified()?.into(),
+                    },
+                );
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Computes the SHA-1 checksum (hex encoded) of a local file
+async fn sha1_of_file(path: &Path) -> Result<String, PCloudError> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut sha1 = Sha1::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        sha1.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", sha1.finalize()))
+}
+
+/// Relative path of the conflict copy of the given file, like `notes (conflict 2023-05-01 103000).txt`
+fn conflict_copy_path(path: &str, time: DateTime<Utc>) -> String {
+    let (parent, name) = match path.rsplit_once('/') {
+        Some((parent, name)) => (format!("{}/", parent), name),
+        None => (String::new(), path),
+    };
+    let suffix = format!("conflict {}", time.format("%Y-%m-%d %H%M%S"));
+    match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => {
+            format!("{}{} ({}).{}", parent, stem, suffix, extension)
+        }
+        _ => format!("{}{} ({})", parent, name, suffix),
+    }
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Mirrors a remote folder (recursively) into a local directory, only downloading files changed since the last run. Accepts either a folder id (u64), a folder path (String) or any other pCloud object describing a folder (like Metadata)
+    pub fn mirror_folder<T: FolderDescriptor, P: AsRef<Path>>(
+        &self,
+        folder_like: T,
+        directory: P,
+    ) -> Result<MirrorRequestBuilder, PCloudError> {
+        MirrorRequestBuilder::for_folder(self, folder_like, directory)
+    }
+
+    /// Synchronizes a remote folder (recursively) and a local directory in both directions, resolving conflicts by the configured strategy. Accepts either a folder id (u64), a folder path (String) or any other pCloud object describing a folder (like Metadata)
+    pub fn sync_folder<T: FolderDescriptor, P: AsRef<Path>>(
+        &self,
+        folder_like: T,
+        directory: P,
+    ) -> Result<SyncRequestBuilder, PCloudError> {
+        SyncRequestBuilder::for_folder(self, folder_like, directory)
+    }
+}
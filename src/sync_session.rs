@@ -0,0 +1,257 @@
+use std::{
+    path::{Path, PathBuf},
+    pin::Pin,
+    time::Duration,
+};
+
+use futures::{Stream, StreamExt};
+use log::{debug, warn};
+use tokio::{
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+};
+
+use crate::{
+    error::PCloudError,
+    filter::SyncFilter,
+    folder_ops::{FolderDescriptor, PCloudFolder},
+    folder_watch::FolderEvent,
+    pcloud_client::PCloudClient,
+    pcloud_model::PCloudResult,
+    sync::{ConflictStrategy, SyncReport, SyncRequestBuilder},
+};
+
+/// Default window remote events are coalesced over before a sync run is started
+pub const DEFAULT_SESSION_WINDOW: Duration = Duration::from_secs(5);
+
+/// Default interval the local directory is scanned for changes, if local changes are pushed
+pub const DEFAULT_RESCAN_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Outcome of a single sync run of a session
+pub type SessionRun = Result<SyncReport, PCloudError>;
+
+/// Events of the synced folder and its contents
+type FolderEvents = Pin<Box<dyn Stream<Item = FolderEvent> + Send>>;
+
+/// Running sync session. Dropping it stops the session immediately, abandoning a running sync run.
+pub struct SyncSession {
+    /// Outcomes of the sync runs
+    runs: mpsc::UnboundedReceiver<SessionRun>,
+    /// Signals the task to stop after the current sync run
+    stop: Option<oneshot::Sender<()>>,
+    /// Task consuming the events and starting the runs
+    task: Option<JoinHandle<()>>,
+}
+
+impl SyncSession {
+    /// Waits for the outcome of the next sync run. Failed runs don't stop the session, the next run continues. None once the session stopped (e.g. the event stream ended or the client was shut down).
+    pub async fn next_run(&mut self) -> Option<SessionRun> {
+        self.runs.recv().await
+    }
+
+    /// Stops the session gracefully, waiting for a running sync run to finish
+    pub async fn stop(mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+    }
+}
+
+impl Drop for SyncSession {
+    fn drop(&mut self) {
+        if let Some(task) = &self.task {
+            task.abort();
+        }
+    }
+}
+
+/// Keeps a remote folder and a local directory in sync continuously. A sync run (see `PCloudClient::sync_folder()`) is started at the beginning, whenever remote diff events touch the folder (see `PCloudClient::watch_folder()`) and periodically to pick up local changes. The state (folder tree and hashes of both sides) is persisted between runs and sessions.
+pub struct SyncSessionBuilder {
+    /// Client to actually perform the requests
+    client: PCloudClient,
+    /// Remote folder to sync
+    folder: PCloudFolder,
+    /// Local directory to sync
+    directory: PathBuf,
+    /// Location of the state
+    state_file: Option<PathBuf>,
+    /// Resolves files changed on both sides
+    conflict_strategy: ConflictStrategy,
+    /// Files left alone on both sides
+    filter: Option<SyncFilter>,
+    /// Apply local changes to the remote folder
+    push_local_changes: bool,
+    /// Window remote events are coalesced over
+    window: Duration,
+    /// Interval the local directory is scanned for changes
+    rescan_interval: Duration,
+}
+
+#[allow(dead_code)]
+impl SyncSessionBuilder {
+    pub(crate) fn for_folder<T: FolderDescriptor, P: AsRef<Path>>(
+        client: &PCloudClient,
+        folder_like: T,
+        directory: P,
+    ) -> Result<SyncSessionBuilder, PCloudError> {
+        let folder = folder_like.to_folder()?;
+
+        if folder.is_empty() {
+            Err(PCloudResult::NoFileIdOrPathProvided)?
+        }
+
+        Ok(SyncSessionBuilder {
+            client: client.clone(),
+            folder,
+            directory: directory.as_ref().to_path_buf(),
+            state_file: None,
+            conflict_strategy: ConflictStrategy::default(),
+            filter: None,
+            push_local_changes: true,
+            window: DEFAULT_SESSION_WINDOW,
+            rescan_interval: DEFAULT_RESCAN_INTERVAL,
+        })
+    }
+
+    /// Location of the state. Defaults to `.pcloud-sync.json` in the local directory.
+    pub fn state_file<P: AsRef<Path>>(mut self, path: P) -> SyncSessionBuilder {
+        self.state_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Strategy to resolve files changed on both sides. Defaults to `NewestWins`.
+    pub fn conflict_strategy(mut self, value: ConflictStrategy) -> SyncSessionBuilder {
+        self.conflict_strategy = value;
+        self
+    }
+
+    /// Files excluded from the sync on both sides. Defaults to the rules of the `.pcloudignore` file in the local directory, if present.
+    pub fn filter(mut self, value: SyncFilter) -> SyncSessionBuilder {
+        self.filter = Some(value);
+        self
+    }
+
+    /// If set (default), local changes are applied to the remote folder. Otherwise the session only applies remote changes to the local directory.
+    pub fn push_local_changes(mut self, value: bool) -> SyncSessionBuilder {
+        self.push_local_changes = value;
+        self
+    }
+
+    /// Window remote events are coalesced over before a sync run is started (defaults to 5 seconds)
+    pub fn window(mut self, value: Duration) -> SyncSessionBuilder {
+        self.window = value;
+        self
+    }
+
+    /// Interval the local directory is scanned for changes, if local changes are pushed (defaults to 60 seconds)
+    pub fn rescan_interval(mut self, value: Duration) -> SyncSessionBuilder {
+        self.rescan_interval = value;
+        self
+    }
+
+    /// Starts the session with an initial sync run. Runs continue in the background until the session is stopped, the folder is deleted or the client is shut down.
+    pub async fn start(self) -> Result<SyncSession, PCloudError> {
+        // Watched before the first run, so no event during the run is missed
+        let events = self.client.watch_folder(self.folder.clone()).await?;
+        debug!(
+            "Starting sync session of {} and {}",
+            self.folder,
+            self.directory.display()
+        );
+
+        let (runs_tx, runs) = mpsc::unbounded_channel();
+        let (stop, stop_rx) = oneshot::channel();
+        let task = tokio::spawn(self.run(Box::pin(events.fuse()), runs_tx, stop_rx));
+        Ok(SyncSession {
+            runs,
+            stop: Some(stop),
+            task: Some(task),
+        })
+    }
+
+    /// Starts a sync run for each window of events touching the folder and each rescan, until the events end, the session is stopped or the receiver is dropped
+    async fn run(
+        self,
+        mut events: FolderEvents,
+        runs: mpsc::UnboundedSender<SessionRun>,
+        mut stop: oneshot::Receiver<()>,
+    ) {
+        let shutdown = self.client.shutdown_signal().clone();
+        let mut rescan = tokio::time::interval_at(
+            tokio::time::Instant::now() + self.rescan_interval,
+            self.rescan_interval,
+        );
+        rescan.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            let result = match self.sync_request() {
+                Ok(request) => request.execute().await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = &result {
+                warn!("Sync run of {} failed: {}", self.folder, e);
+            }
+            if runs.send(result).is_err() {
+                return;
+            }
+            rescan.reset();
+
+            tokio::select! {
+                _ = shutdown.triggered() => {
+                    debug!("Client shut down, stopping sync session of {}", self.folder);
+                    return;
+                }
+                _ = &mut stop => {
+                    debug!("Stopping sync session of {}", self.folder);
+                    return;
+                }
+                event = events.next() => match event {
+                    Some(event) => {
+                        // Coalesce the events of the window into a single run
+                        let deadline = tokio::time::Instant::now() + self.window;
+                        let mut changes = vec![event.path().to_string()];
+                        while let Ok(Some(event)) = tokio::time::timeout_at(deadline, events.next()).await {
+                            changes.push(event.path().to_string());
+                        }
+                        debug!("Remote changes of {}: {:?}", self.folder, changes);
+                    }
+                    None => {
+                        warn!("Events of {} ended, stopping sync session", self.folder);
+                        return;
+                    }
+                },
+                _ = rescan.tick(), if self.push_local_changes => {}
+            }
+        }
+    }
+
+    /// Sync run using the configuration of the session
+    fn sync_request(&self) -> Result<SyncRequestBuilder, PCloudError> {
+        let mut request = self
+            .client
+            .sync_folder(&self.folder, &self.directory)?
+            .conflict_strategy(self.conflict_strategy.clone())
+            .push_local_changes(self.push_local_changes);
+        if let Some(state_file) = &self.state_file {
+            request = request.state_file(state_file);
+        }
+        if let Some(filter) = &self.filter {
+            request = request.filter(filter.clone());
+        }
+        Ok(request)
+    }
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Starts syncing a remote folder and a local directory continuously, driven by diff events. Accepts either a folder id (u64), a folder path (String) or any other pCloud object describing a folder (like Metadata)
+    pub fn sync_session<T: FolderDescriptor, P: AsRef<Path>>(
+        &self,
+        folder_like: T,
+        directory: P,
+    ) -> Result<SyncSessionBuilder, PCloudError> {
+        SyncSessionBuilder::for_folder(self, folder_like, directory)
+    }
+}
@@ -0,0 +1,233 @@
+#![cfg(feature = "testing")]
+use serde_json::{json, Value};
+use wiremock::{
+    matchers::{method, path, query_param},
+    Mock, MockServer, ResponseTemplate,
+};
+
+use crate::{error::PCloudError, pcloud_client::PCloudClient, pcloud_model::PCloudResult};
+
+/// Date used in all canned responses
+pub const CANNED_DATE: &str = "Wed, 25 Jan 2023 12:09:14 +0000";
+
+/// Folder id of the test folder of the preloaded server
+pub const TEST_FOLDER_ID: u64 = 42;
+/// Path of the test folder of the preloaded server
+pub const TEST_FOLDER_PATH: &str = "/test-folder";
+/// File id of the test file of the preloaded server
+pub const TEST_FILE_ID: u64 = 4711;
+/// Path of the test file of the preloaded server
+pub const TEST_FILE_PATH: &str = "/test-folder/test.txt";
+/// File id of files uploaded to the preloaded server
+pub const UPLOADED_FILE_ID: u64 = 4712;
+
+/// Canned metadata of a folder
+pub fn folder_metadata(folder_id: u64, parent_folder_id: u64, name: &str) -> Value {
+    json!({
+        "name": name,
+        "created": CANNED_DATE,
+        "modified": CANNED_DATE,
+        "ismine": true,
+        "thumb": false,
+        "isshared": false,
+        "icon": "folder",
+        "isfolder": true,
+        "id": format!("d{}", folder_id),
+        "folderid": folder_id,
+        "parentfolderid": parent_folder_id,
+    })
+}
+
+/// Canned metadata of a file
+pub fn file_metadata(file_id: u64, parent_folder_id: u64, name: &str, size: u64) -> Value {
+    json!({
+        "name": name,
+        "created": CANNED_DATE,
+        "modified": CANNED_DATE,
+        "ismine": true,
+        "thumb": false,
+        "isshared": false,
+        "icon": "document",
+        "category": 4,
+        "isfolder": false,
+        "id": format!("f{}", file_id),
+        "fileid": file_id,
+        "parentfolderid": parent_folder_id,
+        "size": size,
+        "contenttype": "text/plain",
+        "hash": file_id * 31,
+    })
+}
+
+/// Canned response of the `listfolder` call: The folder metadata with the given contents
+pub fn list_folder_response(mut folder: Value, contents: Vec<Value>) -> Value {
+    folder["contents"] = Value::Array(contents);
+    stat_response(folder)
+}
+
+/// Canned response of all calls returning a single metadata object (`stat`, `createfolder`, `copyfile`, ...)
+pub fn stat_response(metadata: Value) -> Value {
+    json!({
+        "result": 0,
+        "metadata": metadata,
+    })
+}
+
+/// Canned response of the `uploadfile` call
+pub fn upload_response(files: Vec<Value>) -> Value {
+    let file_ids: Vec<Value> = files.iter().map(|f| f["fileid"].clone()).collect();
+    json!({
+        "result": 0,
+        "fileids": file_ids,
+        "metadata": files,
+    })
+}
+
+/// Canned response of the `userinfo` call
+pub fn user_info_response(quota: u64, used_quota: u64) -> Value {
+    json!({
+        "result": 0,
+        "userid": 1,
+        "email": "test@example.com",
+        "emailverified": true,
+        "registered": CANNED_DATE,
+        "language": "en",
+        "premium": false,
+        "quota": quota,
+        "usedquota": used_quota,
+    })
+}
+
+/// Canned error response for the given result code
+pub fn error_response(result: PCloudResult) -> Value {
+    json!({
+        "result": result,
+        "error": result.to_string(),
+    })
+}
+
+/// Wiremock server behaving like the pCloud API for the calls mocked
+pub struct MockPCloudServer {
+    server: MockServer,
+}
+
+impl MockPCloudServer {
+    /// Starts a server without any mocked calls
+    pub async fn start() -> MockPCloudServer {
+        MockPCloudServer {
+            server: MockServer::start().await,
+        }
+    }
+
+    /// Starts a server preloaded with a small file system: The root folder contains the folder `/test-folder` (id 42) which contains the file `test.txt` (id 4711).
+    /// `listfolder`, `stat`, `uploadfile` and `userinfo` calls are answered accordingly.
+    pub async fn start_preloaded() -> MockPCloudServer {
+        let result = MockPCloudServer::start().await;
+
+        let test_folder = folder_metadata(TEST_FOLDER_ID, 0, "test-folder");
+        let test_file = file_metadata(TEST_FILE_ID, TEST_FOLDER_ID, "test.txt", 25);
+
+        let mut root = folder_metadata(0, 0, "/");
+        root.as_object_mut().unwrap().remove("parentfolderid");
+        result
+            .mock_list_folder(
+                0,
+                "/",
+                list_folder_response(root, vec![test_folder.clone()]),
+            )
+            .await;
+        result
+            .mock_list_folder(
+                TEST_FOLDER_ID,
+                TEST_FOLDER_PATH,
+                list_folder_response(test_folder, vec![test_file.clone()]),
+            )
+            .await;
+        result
+            .mock_stat(TEST_FILE_ID, TEST_FILE_PATH, stat_response(test_file))
+            .await;
+        result
+            .mock_upload(upload_response(vec![file_metadata(
+                UPLOADED_FILE_ID,
+                TEST_FOLDER_ID,
+                "uploaded.txt",
+                25,
+            )]))
+            .await;
+        result
+            .mock_method("userinfo", user_info_response(10_000_000_000, 25))
+            .await;
+
+        result
+    }
+
+    /// Base url of the server
+    pub fn uri(&self) -> String {
+        self.server.uri()
+    }
+
+    /// The underlying wiremock server, to register custom mocks
+    pub fn server(&self) -> &MockServer {
+        &self.server
+    }
+
+    /// Creates a PCloudClient connected to this server
+    pub async fn client(&self) -> Result<PCloudClient, PCloudError> {
+        PCloudClient::builder(&self.uri())
+            .with_oauth("test-token")
+            .select_best_api_server(false)
+            .build()
+            .await
+    }
+
+    /// Answers all calls of the given pCloud method with the given body
+    pub async fn mock_method(&self, pcloud_method: &str, body: Value) {
+        Mock::given(path(format!("/{}", pcloud_method)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Answers all calls of the given pCloud method with the given error
+    pub async fn mock_error(&self, pcloud_method: &str, result: PCloudResult) {
+        self.mock_method(pcloud_method, error_response(result))
+            .await;
+    }
+
+    /// Answers `listfolder` calls for the given folder (either requested by id or by path) with the given body
+    pub async fn mock_list_folder(&self, folder_id: u64, folder_path: &str, body: Value) {
+        Mock::given(path("/listfolder"))
+            .and(query_param("folderid", folder_id.to_string()))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body.clone()))
+            .mount(&self.server)
+            .await;
+        Mock::given(path("/listfolder"))
+            .and(query_param("path", folder_path))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Answers `stat` calls for the given file (either requested by id or by path) with the given body
+    pub async fn mock_stat(&self, file_id: u64, file_path: &str, body: Value) {
+        Mock::given(path("/stat"))
+            .and(query_param("fileid", file_id.to_string()))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body.clone()))
+            .mount(&self.server)
+            .await;
+        Mock::given(path("/stat"))
+            .and(query_param("path", file_path))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Answers all `uploadfile` calls with the given body
+    pub async fn mock_upload(&self, body: Value) {
+        Mock::given(method("POST"))
+            .and(path("/uploadfile"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(&self.server)
+            .await;
+    }
+}
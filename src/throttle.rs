@@ -0,0 +1,109 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+
+/// Duration of the slices the bandwidth is allotted in, to avoid bursts
+const SLICE: Duration = Duration::from_millis(100);
+/// Number of slices per second
+const SLICES_PER_SECOND: u64 = 10;
+
+/// Bytes allowed per slice at the given bytes per second
+fn slice_size(bytes_per_second: u64) -> u64 {
+    (bytes_per_second / SLICES_PER_SECOND).max(1)
+}
+
+struct SliceState {
+    /// Start of the current slice
+    slice_start: Instant,
+    /// Bytes passed on within the current slice
+    sent: u64,
+}
+
+/// Bandwidth budget allotted in slices of a tenth of a second, shared by all streams paced by it
+pub(crate) struct SliceBudget {
+    state: Mutex<SliceState>,
+}
+
+impl SliceBudget {
+    pub(crate) fn new() -> SliceBudget {
+        SliceBudget {
+            state: Mutex::new(SliceState {
+                slice_start: Instant::now(),
+                sent: 0,
+            }),
+        }
+    }
+
+    /// Waits until the given number of bytes fit into a slice at the limit returned by `limit` (re-evaluated after each wait, None for full speed)
+    async fn acquire(&self, bytes: u64, limit: &impl Fn() -> Option<u64>) {
+        loop {
+            let budget = match limit() {
+                Some(limit) => slice_size(limit),
+                None => return,
+            };
+            let next_slice = {
+                let mut state = self.state.lock().unwrap();
+                let now = Instant::now();
+                if now >= state.slice_start + SLICE {
+                    state.slice_start = now;
+                    state.sent = 0;
+                }
+                if state.sent == 0 || state.sent + bytes <= budget {
+                    state.sent += bytes;
+                    return;
+                }
+                state.slice_start + SLICE
+            };
+            tokio::time::sleep_until(next_slice.into()).await;
+        }
+    }
+}
+
+/// Passes the stream on within the budget at the limit returned by `limit` (bytes per second, None for full speed), splitting chunks larger than a slice
+pub(crate) fn paced<S, E, L>(
+    stream: S,
+    budget: Arc<SliceBudget>,
+    limit: L,
+) -> impl Stream<Item = Result<Bytes, E>>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+    L: Fn() -> Option<u64>,
+{
+    let state = (Box::pin(stream), Bytes::new(), budget, limit);
+    futures::stream::unfold(
+        state,
+        |(mut stream, mut pending, budget, limit)| async move {
+            while pending.is_empty() {
+                match stream.next().await? {
+                    Ok(chunk) => pending = chunk,
+                    Err(e) => return Some((Err(e), (stream, pending, budget, limit))),
+                }
+            }
+
+            let size = limit()
+                .map(|limit| slice_size(limit) as usize)
+                .unwrap_or(pending.len())
+                .min(pending.len());
+            let slice = pending.split_to(size);
+            budget.acquire(slice.len() as u64, &limit).await;
+            Some((Ok(slice), (stream, pending, budget, limit)))
+        },
+    )
+}
+
+/// Limits the throughput of the given stream to the given number of bytes per second (chunks are split into slices of a tenth of a second)
+pub(crate) fn throttle<S, E>(
+    stream: S,
+    bytes_per_second: u64,
+) -> impl Stream<Item = Result<Bytes, E>>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+{
+    paced(stream, Arc::new(SliceBudget::new()), move || {
+        Some(bytes_per_second)
+    })
+}
@@ -0,0 +1,503 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use bytes::Bytes;
+use log::{debug, warn};
+
+use reqwest::{header::CONTENT_TYPE, RequestBuilder};
+
+use crate::{
+    error::PCloudError,
+    file_ops::{FileDescriptor, PCloudFile, Tree},
+    folder_ops::{FolderDescriptor, PCloudFolder},
+    pcloud_client::PCloudClient,
+    pcloud_model::{
+        DownloadLink, FileOpResult, Metadata, PCloudResult, SavedThumbnail, ThumbnailLink,
+        ThumbnailLinks, WithPCloudResult,
+    },
+};
+
+/// Default time thumbnails are kept in the cache
+pub const DEFAULT_THUMBNAIL_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Default maximum size of all cached thumbnails in bytes
+pub const DEFAULT_THUMBNAIL_CACHE_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Image format of thumbnails
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThumbnailFormat {
+    #[default]
+    Jpeg,
+    Png,
+}
+
+impl ThumbnailFormat {
+    /// Name of the format in requests and file extension
+    fn as_str(&self) -> &'static str {
+        match self {
+            ThumbnailFormat::Jpeg => "jpeg",
+            ThumbnailFormat::Png => "png",
+        }
+    }
+}
+
+/// On-disk cache of thumbnails (see `PCloudClientBuilder::thumbnail_cache()`). Entries expire after the TTL, the oldest ones are evicted once the cache exceeds its maximum size. Failures of the cache are logged and otherwise ignored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThumbnailCache {
+    /// Directory containing the cached thumbnails
+    directory: PathBuf,
+    /// Time thumbnails are kept
+    ttl: Duration,
+    /// Maximum size of all thumbnails in bytes
+    max_size: u64,
+}
+
+impl ThumbnailCache {
+    /// Creates a cache in the given directory (created if missing), keeping thumbnails for 7 days and up to 256 MiB
+    pub fn new<P: AsRef<Path>>(directory: P) -> ThumbnailCache {
+        ThumbnailCache {
+            directory: directory.as_ref().to_path_buf(),
+            ttl: DEFAULT_THUMBNAIL_TTL,
+            max_size: DEFAULT_THUMBNAIL_CACHE_SIZE,
+        }
+    }
+
+    /// Time thumbnails are kept in the cache (defaults to 7 days)
+    pub fn ttl(mut self, value: Duration) -> ThumbnailCache {
+        self.ttl = value;
+        self
+    }
+
+    /// Maximum size of all cached thumbnails in bytes (defaults to 256 MiB)
+    pub fn max_size(mut self, bytes: u64) -> ThumbnailCache {
+        self.max_size = bytes;
+        self
+    }
+
+    /// Removes all cached thumbnails
+    pub async fn clear(&self) -> Result<(), PCloudError> {
+        for (path, _, _) in self.entries().await? {
+            tokio::fs::remove_file(path).await?;
+        }
+        Ok(())
+    }
+
+    /// Reads a thumbnail from the cache, if present and not expired
+    async fn load(&self, name: &str) -> Option<Bytes> {
+        let path = self.directory.join(name);
+        let modified = tokio::fs::metadata(&path).await.ok()?.modified().ok()?;
+        if is_expired(modified, self.ttl) {
+            debug!("Cached thumbnail {} expired", name);
+            let _ = tokio::fs::remove_file(&path).await;
+            return None;
+        }
+        tokio::fs::read(&path).await.ok().map(Bytes::from)
+    }
+
+    /// Writes a thumbnail into the cache and evicts the oldest ones if the cache got too large
+    async fn store(&self, name: &str, content: &[u8]) {
+        if let Err(e) = self.try_store(name, content).await {
+            warn!("Failed to cache thumbnail {}: {}", name, e);
+        }
+    }
+
+    async fn try_store(&self, name: &str, content: &[u8]) -> Result<(), PCloudError> {
+        tokio::fs::create_dir_all(&self.directory).await?;
+        // Concurrent readers never see partially written thumbnails
+        let temp = self.directory.join(format!("{}.tmp", name));
+        tokio::fs::write(&temp, content).await?;
+        tokio::fs::rename(&temp, self.directory.join(name)).await?;
+
+        let mut entries = self.entries().await?;
+        let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        if total > self.max_size {
+            entries.sort_by_key(|(_, _, modified)| *modified);
+            for (path, size, _) in entries {
+                if total <= self.max_size {
+                    break;
+                }
+                debug!("Evicting cached thumbnail {}", path.display());
+                tokio::fs::remove_file(&path).await?;
+                total -= size;
+            }
+        }
+        Ok(())
+    }
+
+    /// All cached thumbnails (path, size, modification time)
+    async fn entries(&self) -> Result<Vec<(PathBuf, u64, SystemTime)>, PCloudError> {
+        let mut entries = Vec::new();
+        let mut dir = match tokio::fs::read_dir(&self.directory).await {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(entries),
+            Err(e) => Err(e)?,
+        };
+        while let Some(entry) = dir.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            let is_temp = entry.path().extension().is_some_and(|e| e == "tmp");
+            if metadata.is_file() && !is_temp {
+                entries.push((entry.path(), metadata.len(), metadata.modified()?));
+            }
+        }
+        Ok(entries)
+    }
+}
+
+/// Checks if an entry written at the given time is older than the TTL
+fn is_expired(modified: SystemTime, ttl: Duration) -> bool {
+    SystemTime::now()
+        .duration_since(modified)
+        .is_ok_and(|age| age > ttl)
+}
+
+/// Requests a thumbnail of an image or video file
+pub struct ThumbnailRequestBuilder {
+    /// Client to actually perform the requests
+    client: PCloudClient,
+    /// File to create the thumbnail of
+    file: PCloudFile,
+    /// Maximum width of the thumbnail
+    width: u32,
+    /// Maximum height of the thumbnail
+    height: u32,
+    /// if set, the thumbnail is cropped to exactly the requested size
+    crop: bool,
+    /// Image format of the thumbnail
+    format: ThumbnailFormat,
+    /// File id and content hash of the file, if known
+    content: Option<(u64, u64)>,
+}
+
+#[allow(dead_code)]
+impl ThumbnailRequestBuilder {
+    pub(crate) fn for_file<T: FileDescriptor>(
+        client: &PCloudClient,
+        file_like: T,
+        width: u32,
+        height: u32,
+    ) -> Result<ThumbnailRequestBuilder, PCloudError> {
+        let file = file_like.to_file()?;
+
+        if file.is_empty() {
+            Err(PCloudResult::NoFileIdOrPathProvided)?
+        }
+
+        Ok(ThumbnailRequestBuilder {
+            client: client.clone(),
+            file,
+            width,
+            height,
+            crop: false,
+            format: ThumbnailFormat::default(),
+            content: None,
+        })
+    }
+
+    /// if set, the thumbnail is cropped to exactly the requested size instead of keeping the aspect ratio
+    pub fn crop(mut self, value: bool) -> ThumbnailRequestBuilder {
+        self.crop = value;
+        self
+    }
+
+    /// Image format of the thumbnail, defaults to JPEG
+    pub fn format(mut self, value: ThumbnailFormat) -> ThumbnailRequestBuilder {
+        self.format = value;
+        self
+    }
+
+    /// Metadata of the file (e.g. from a folder listing). Provides the content hash for the thumbnail cache, saving a request per thumbnail.
+    pub fn metadata(mut self, value: &Metadata) -> ThumbnailRequestBuilder {
+        self.content = value.fileid.zip(value.hash);
+        self
+    }
+
+    /// Creates a request of the given method for the file, size, format and crop of the thumbnail
+    fn request(&self, method: &str) -> RequestBuilder {
+        let mut r = self
+            .client
+            .client()
+            .get(format!("{}/{}", self.client.api_host(), method));
+
+        if let Some(id) = self.file.file_id {
+            r = r.query(&[("fileid", id)]);
+        }
+
+        if let Some(path) = &self.file.path {
+            r = r.query(&[("path", path)]);
+        }
+
+        r = r.query(&[
+            ("size", format!("{}x{}", self.width, self.height)),
+            ("type", self.format.as_str().to_string()),
+        ]);
+
+        if self.crop {
+            r = r.query(&[("crop", "1")]);
+        }
+
+        r
+    }
+
+    /// Requests the link to download the thumbnail from
+    pub async fn link(&self) -> Result<DownloadLink, PCloudError> {
+        debug!(
+            "Requesting thumbnail link of file {} ({}x{})",
+            self.file, self.width, self.height
+        );
+        let r = self.client.add_token(self.request("getthumblink"));
+
+        let result = self.client.send_checked::<DownloadLink>(r).await?;
+        Ok(result)
+    }
+
+    /// Fetches the thumbnail, from the thumbnail cache of the client if enabled
+    pub async fn get(self) -> Result<Bytes, PCloudError> {
+        let cached = match self.client.thumbnail_cache() {
+            Some(cache) => Some((cache.clone(), self.cache_name().await?)),
+            None => None,
+        };
+        if let Some((cache, name)) = &cached {
+            if let Some(thumbnail) = cache.load(name).await {
+                debug!("Using cached thumbnail {}", name);
+                return Ok(thumbnail);
+            }
+        }
+
+        let link = self.link().await?;
+        let thumbnail = self
+            .client
+            .download_link(&link)
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        if let Some((cache, name)) = &cached {
+            cache.store(name, &thumbnail).await;
+        }
+        Ok(thumbnail)
+    }
+
+    /// Fetches the thumbnail directly from the API host with a single request, bypassing the thumbnail cache
+    pub async fn download(&self) -> Result<Bytes, PCloudError> {
+        debug!(
+            "Downloading thumbnail of file {} ({}x{})",
+            self.file, self.width, self.height
+        );
+        let r = self.client.add_token(self.request("getthumb"));
+
+        // The thumbnail is returned as is, failures as JSON result
+        let response = self.client.send(r).await?.error_for_status()?;
+        let is_json = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("application/json"));
+        let content = response.bytes().await?;
+        if is_json {
+            serde_json::from_slice::<FileOpResult>(&content)?.assert_ok()?;
+        }
+        Ok(content)
+    }
+
+    /// Creates the thumbnail and saves it as a new file with the given name in the given folder. Accepts either a folder id (u64), a folder path (String) or any other pCloud object describing a folder (like Metadata)
+    pub async fn save<T: FolderDescriptor>(
+        &self,
+        folder_like: T,
+        name: &str,
+    ) -> Result<SavedThumbnail, PCloudError> {
+        self.save_thumbnail(folder_like.to_folder()?, name, false)
+            .await
+    }
+
+    /// Like `save()`, but fails with `FileOrFolderAlreadyExists` instead of overwriting an existing file
+    pub async fn save_new<T: FolderDescriptor>(
+        &self,
+        folder_like: T,
+        name: &str,
+    ) -> Result<SavedThumbnail, PCloudError> {
+        self.save_thumbnail(folder_like.to_folder()?, name, true)
+            .await
+    }
+
+    async fn save_thumbnail(
+        &self,
+        folder: PCloudFolder,
+        name: &str,
+        no_overwrite: bool,
+    ) -> Result<SavedThumbnail, PCloudError> {
+        let mut r = self.request("savethumb");
+
+        if let Some(id) = folder.folder_id {
+            r = r.query(&[("tofolderid", id.to_string()), ("toname", name.to_string())]);
+        } else if let Some(path) = &folder.path {
+            r = r.query(&[("topath", format!("{}/{}", path.trim_end_matches('/'), name))]);
+        } else {
+            Err(PCloudResult::NoFullPathOrFolderIdProvided)?
+        }
+
+        if no_overwrite {
+            r = r.query(&[("noover", "1")]);
+        }
+
+        debug!(
+            "Saving thumbnail of file {} ({}x{}) into {} as {}",
+            self.file, self.width, self.height, folder, name
+        );
+        r = self.client.add_token(r);
+
+        let result = self.client.send_checked::<SavedThumbnail>(r).await?;
+        Ok(result)
+    }
+
+    /// Name of the thumbnail in the cache, made of the file id, content hash, size and format
+    async fn cache_name(&self) -> Result<String, PCloudError> {
+        let (file_id, hash) = match self.content {
+            Some(content) => content,
+            None => {
+                let metadata = self
+                    .client
+                    .get_file_metadata(self.file.clone())
+                    .await?
+                    .metadata
+                    .ok_or(PCloudResult::FileNotFound)?;
+                metadata
+                    .fileid
+                    .zip(metadata.hash)
+                    .ok_or(PCloudResult::FileNotFound)?
+            }
+        };
+        Ok(format!(
+            "{}-{:x}-{}x{}{}.{}",
+            file_id,
+            hash,
+            self.width,
+            self.height,
+            if self.crop { "-crop" } else { "" },
+            self.format.as_str()
+        ))
+    }
+}
+
+/// Requests the thumbnail links of several files with a single request
+pub struct ThumbnailLinksRequestBuilder {
+    /// Client to actually perform the requests
+    client: PCloudClient,
+    /// Files to create the thumbnails of
+    file_ids: Vec<u64>,
+    /// Maximum width of the thumbnails
+    width: u32,
+    /// Maximum height of the thumbnails
+    height: u32,
+    /// if set, the thumbnails are cropped to exactly the requested size
+    crop: bool,
+    /// Image format of the thumbnails
+    format: ThumbnailFormat,
+}
+
+#[allow(dead_code)]
+impl ThumbnailLinksRequestBuilder {
+    pub(crate) fn for_files(
+        client: &PCloudClient,
+        file_ids: Vec<u64>,
+        width: u32,
+        height: u32,
+    ) -> ThumbnailLinksRequestBuilder {
+        ThumbnailLinksRequestBuilder {
+            client: client.clone(),
+            file_ids,
+            width,
+            height,
+            crop: false,
+            format: ThumbnailFormat::default(),
+        }
+    }
+
+    /// if set, the thumbnails are cropped to exactly the requested size instead of keeping the aspect ratio
+    pub fn crop(mut self, value: bool) -> ThumbnailLinksRequestBuilder {
+        self.crop = value;
+        self
+    }
+
+    /// Image format of the thumbnails, defaults to JPEG
+    pub fn format(mut self, value: ThumbnailFormat) -> ThumbnailLinksRequestBuilder {
+        self.format = value;
+        self
+    }
+
+    /// Requests the links. Files without a thumbnail (e.g. no image or video) are contained with the failure as result.
+    pub async fn get(self) -> Result<Vec<ThumbnailLink>, PCloudError> {
+        if self.file_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut r = self
+            .client
+            .client()
+            .get(format!("{}/getthumbslinks", self.client.api_host()));
+
+        let ids = self
+            .file_ids
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<String>>()
+            .join(",");
+        r = r.query(&[
+            ("fileids", ids),
+            ("size", format!("{}x{}", self.width, self.height)),
+            ("type", self.format.as_str().to_string()),
+        ]);
+
+        if self.crop {
+            r = r.query(&[("crop", "1")]);
+        }
+
+        debug!(
+            "Requesting thumbnail links of {} files ({}x{})",
+            self.file_ids.len(),
+            self.width,
+            self.height
+        );
+        r = self.client.add_token(r);
+
+        let result = self.client.send_checked::<ThumbnailLinks>(r).await?;
+        Ok(result.thumbs)
+    }
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Requests the thumbnail links of the given files with at most the given width and height with a single request
+    pub fn get_thumbnail_links(
+        &self,
+        file_ids: &[u64],
+        width: u32,
+        height: u32,
+    ) -> ThumbnailLinksRequestBuilder {
+        ThumbnailLinksRequestBuilder::for_files(self, file_ids.to_vec(), width, height)
+    }
+
+    /// Requests the thumbnail links of all files of a tree (including the files of its folders) with at most the given width and height
+    pub async fn get_thumbnail_links_for_tree(
+        &self,
+        tree: &Tree,
+        width: u32,
+        height: u32,
+    ) -> Result<ThumbnailLinksRequestBuilder, PCloudError> {
+        let file_ids = tree.resolve_file_ids().await?;
+        Ok(ThumbnailLinksRequestBuilder::for_files(
+            self, file_ids, width, height,
+        ))
+    }
+
+    /// Requests a thumbnail of an image or video file with at most the given width and height (16 to 2048, divisible by 4 or 5). Accepts either a file id (u64), a file path (String) or any other pCloud object describing a file (like Metadata)
+    pub fn get_thumbnail<T: FileDescriptor>(
+        &self,
+        file_like: T,
+        width: u32,
+        height: u32,
+    ) -> Result<ThumbnailRequestBuilder, PCloudError> {
+        ThumbnailRequestBuilder::for_file(self, file_like, width, height)
+    }
+}
@@ -0,0 +1,930 @@
+use std::{
+    collections::{BTreeMap, BinaryHeap, HashMap},
+    fmt::Display,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use chrono::NaiveTime;
+use futures::{
+    future::{AbortHandle, Abortable, Either},
+    stream::FuturesUnordered,
+    Stream, StreamExt,
+};
+use log::{debug, warn};
+use reqwest::Body;
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+use tokio::{io::AsyncWriteExt, sync::mpsc};
+
+use crate::{
+    error::PCloudError,
+    file_ops::{ChecksumMismatch, FileDescriptor, PCloudFile},
+    folder_ops::{FolderDescriptor, PCloudFolder},
+    pcloud_client::PCloudClient,
+    pcloud_model::PCloudResult,
+    progress::{self, ProgressObserver},
+    rate_limit::{limit, BandwidthLimiter},
+    shutdown::ShutdownSignal,
+};
+
+/// Default number of transfers running in parallel
+pub const DEFAULT_TRANSFER_CONCURRENCY: usize = 4;
+
+/// Bandwidth limit applying to a time window of each day
+#[derive(Debug, Clone, PartialEq)]
+struct BandwidthWindow {
+    /// Start of the window (inclusive)
+    start: NaiveTime,
+    /// End of the window (exclusive). If before the start, the window spans midnight.
+    end: NaiveTime,
+    /// Limit in bytes per second, None for full speed
+    bytes_per_second: Option<u64>,
+}
+
+impl BandwidthWindow {
+    fn contains(&self, time: NaiveTime) -> bool {
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            // Spans midnight (or the whole day, if start and end are equal)
+            self.start <= time || time < self.end
+        }
+    }
+}
+
+/// Bandwidth limits by time of day (local time), e.g. full speed at night and 1 MB/s during work hours. The first window containing the current time applies, otherwise the default limit.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BandwidthSchedule {
+    /// Limit in bytes per second outside of all windows, None for full speed
+    default: Option<u64>,
+    /// Time windows with specific limits
+    windows: Vec<BandwidthWindow>,
+}
+
+impl BandwidthSchedule {
+    /// Creates a schedule with the given limit (in bytes per second, None for full speed) applying outside of all windows
+    pub fn new(default_bytes_per_second: Option<u64>) -> BandwidthSchedule {
+        BandwidthSchedule {
+            default: default_bytes_per_second,
+            windows: Vec::new(),
+        }
+    }
+
+    /// Adds a daily window from start (inclusive) to end (exclusive) with the given limit in bytes per second (None for full speed). Windows may span midnight (start after end), equal start and end cover the whole day.
+    pub fn window(
+        mut self,
+        start: NaiveTime,
+        end: NaiveTime,
+        bytes_per_second: Option<u64>,
+    ) -> BandwidthSchedule {
+        self.windows.push(BandwidthWindow {
+            start,
+            end,
+            bytes_per_second,
+        });
+        self
+    }
+
+    /// Limit in bytes per second at the given time of day, None for full speed
+    pub fn limit_at(&self, time: NaiveTime) -> Option<u64> {
+        self.windows
+            .iter()
+            .find(|w| w.contains(time))
+            .map(|w| w.bytes_per_second)
+            .unwrap_or(self.default)
+    }
+}
+
+/// Single transfer of a TransferManager
+#[derive(Debug, Clone)]
+pub enum Transfer {
+    /// Uploads the local file into the folder, keeping its name
+    Upload {
+        source: PathBuf,
+        folder: PCloudFolder,
+    },
+    /// Downloads the file to the local path
+    Download { file: PCloudFile, target: PathBuf },
+}
+
+impl Display for Transfer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Transfer::Upload { source, folder } => {
+                write!(f, "upload of {} into {}", source.display(), folder)
+            }
+            Transfer::Download { file, target } => {
+                write!(f, "download of {} to {}", file, target.display())
+            }
+        }
+    }
+}
+
+/// Priority of a transfer. Transfers of higher priority are started first and may interrupt running transfers of lower priority (see `TransferManager::preempt()`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum TransferPriority {
+    /// Bulk data, transferred when nothing else is waiting
+    Low,
+    #[default]
+    Normal,
+    /// Transferred before all others
+    High,
+}
+
+/// Rules assigning priorities to transfers without an explicit one
+#[derive(Debug, Clone, Default)]
+struct PriorityRules {
+    /// Files up to this size (in bytes) get a high priority
+    small_files: Option<u64>,
+    /// Priorities of local paths (upload sources and download targets) below the given ones, the first match wins
+    paths: Vec<(PathBuf, TransferPriority)>,
+}
+
+impl PriorityRules {
+    async fn classify(&self, client: &PCloudClient, transfer: &Transfer) -> TransferPriority {
+        let local = match transfer {
+            Transfer::Upload { source, .. } => source,
+            Transfer::Download { target, .. } => target,
+        };
+        if let Some((_, priority)) = self.paths.iter().find(|(p, _)| local.starts_with(p)) {
+            return *priority;
+        }
+
+        if let Some(max_size) = self.small_files {
+            let size = match transfer {
+                Transfer::Upload { source, .. } => {
+                    tokio::fs::metadata(source).await.ok().map(|m| m.len())
+                }
+                // The transfer fails later on, if the file is missing
+                Transfer::Download { file, .. } => client
+                    .get_file_metadata(file)
+                    .await
+                    .ok()
+                    .and_then(|stat| stat.metadata)
+                    .and_then(|m| m.size),
+            };
+            if size.map(|s| s <= max_size).unwrap_or(false) {
+                return TransferPriority::High;
+            }
+        }
+        TransferPriority::Normal
+    }
+}
+
+/// Transfer waiting to be started, ordered by priority and then by the order added
+#[derive(Debug)]
+struct Queued {
+    index: usize,
+    transfer: Transfer,
+    priority: TransferPriority,
+    preemptions: usize,
+}
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl Eq for Queued {}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.index.cmp(&self.index))
+    }
+}
+
+/// Transfer currently running
+struct Running {
+    transfer: Transfer,
+    priority: TransferPriority,
+    preemptions: usize,
+    abort: AbortHandle,
+    /// Interrupted for a transfer of higher priority
+    preempted: bool,
+}
+
+/// Transfer added by a TransferQueue, with an explicit priority if given
+type QueuedTransfer = (Transfer, Option<TransferPriority>);
+
+/// Adds transfers to a running TransferManager (see `TransferManager::queue()`)
+#[derive(Clone)]
+pub struct TransferQueue {
+    sender: mpsc::UnboundedSender<QueuedTransfer>,
+}
+
+impl TransferQueue {
+    /// Adds the upload of a local file into the given folder. With None, the priority is assigned by the rules of the TransferManager.
+    pub fn upload<P: AsRef<Path>, T: FolderDescriptor>(
+        &self,
+        source: P,
+        folder_like: T,
+        priority: Option<TransferPriority>,
+    ) -> Result<(), PCloudError> {
+        let folder = folder_like.to_folder()?;
+        if folder.is_empty() {
+            Err(PCloudResult::NoFullPathOrFolderIdProvided)?
+        }
+        let transfer = Transfer::Upload {
+            source: source.as_ref().to_path_buf(),
+            folder,
+        };
+        self.sender.send((transfer, priority))?;
+        Ok(())
+    }
+
+    /// Adds the download of a file to the given local path. With None, the priority is assigned by the rules of the TransferManager.
+    pub fn download<T: FileDescriptor, P: AsRef<Path>>(
+        &self,
+        file_like: T,
+        target: P,
+        priority: Option<TransferPriority>,
+    ) -> Result<(), PCloudError> {
+        let file = file_like.to_file()?;
+        if file.is_empty() {
+            Err(PCloudResult::NoFileIdOrPathProvided)?
+        }
+        let transfer = Transfer::Download {
+            file,
+            target: target.as_ref().to_path_buf(),
+        };
+        self.sender.send((transfer, priority))?;
+        Ok(())
+    }
+}
+
+/// Error of a transfer whose size differs from the one expected
+#[derive(Debug, Clone, PartialEq)]
+pub struct SizeMismatch {
+    /// Size of the source
+    pub expected: u64,
+    /// Size of the transferred file
+    pub actual: u64,
+}
+
+impl Display for SizeMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Size mismatch: expected {} bytes, got {} bytes",
+            self.expected, self.actual
+        )
+    }
+}
+
+impl std::error::Error for SizeMismatch {}
+
+/// Result of the verification of a completed transfer (see `TransferManager::verify()`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerificationStatus {
+    /// Verification disabled or transfer failed before completion
+    NotVerified,
+    /// Size and checksum match
+    Verified,
+    /// Size or checksum still differ after all attempts
+    Failed,
+}
+
+/// Outcome of a single transfer
+#[derive(Debug)]
+pub struct TransferOutcome {
+    /// Transfer executed
+    pub transfer: Transfer,
+    /// Number of bytes transferred or the error of the failed transfer
+    pub result: Result<u64, PCloudError>,
+    /// Verification of the transferred file
+    pub verification: VerificationStatus,
+    /// Number of attempts made
+    pub attempts: usize,
+    /// Priority the transfer ran with
+    pub priority: TransferPriority,
+    /// Number of times the transfer was interrupted (and restarted later) for transfers of higher priority
+    pub preemptions: usize,
+}
+
+/// Failure of a single attempt of a transfer
+enum AttemptError {
+    /// Transfer failed, not retried
+    Transfer(Box<dyn std::error::Error + Send + Sync>),
+    /// Transfer completed, but the result differs from the source
+    Verification(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl<E: Into<Box<dyn std::error::Error + Send + Sync>>> From<E> for AttemptError {
+    fn from(value: E) -> Self {
+        AttemptError::Transfer(value.into())
+    }
+}
+
+/// Checks size and checksums of a transferred file against the ones of the source
+fn verify(
+    expected_size: Option<u64>,
+    actual_size: u64,
+    checksums: [(&str, Option<&String>, Option<String>); 2],
+) -> Result<(), AttemptError> {
+    if let Some(expected) = expected_size {
+        if expected != actual_size {
+            return Err(AttemptError::Verification(Box::new(SizeMismatch {
+                expected,
+                actual: actual_size,
+            })));
+        }
+    }
+
+    let mut verified = false;
+    for (algorithm, expected, actual) in checksums {
+        if let (Some(expected), Some(actual)) = (expected, actual) {
+            if !expected.eq_ignore_ascii_case(&actual) {
+                return Err(AttemptError::Verification(Box::new(ChecksumMismatch {
+                    algorithm: algorithm.to_string(),
+                    expected: expected.clone(),
+                    actual,
+                })));
+            }
+            verified = true;
+        }
+    }
+    if verified {
+        Ok(())
+    } else {
+        Err(AttemptError::Transfer(PCloudError::MissingChecksum.into()))
+    }
+}
+
+/// Result of `TransferManager::run()`, containing the outcome of each transfer in the order added
+#[derive(Debug, Default)]
+pub struct TransferReport {
+    pub outcomes: Vec<TransferOutcome>,
+    /// Transfers not started or interrupted by a shutdown, in the order added. Add them to the next run to resume.
+    pub interrupted: Vec<Transfer>,
+}
+
+impl TransferReport {
+    /// Checks if all transfers succeeded
+    pub fn is_success(&self) -> bool {
+        self.interrupted.is_empty() && self.outcomes.iter().all(|o| o.result.is_ok())
+    }
+
+    /// All failed transfers with their error
+    pub fn failures(&self) -> impl Iterator<Item = &TransferOutcome> {
+        self.outcomes.iter().filter(|o| o.result.is_err())
+    }
+
+    /// Number of bytes transferred by all successful transfers
+    pub fn bytes_transferred(&self) -> u64 {
+        self.outcomes
+            .iter()
+            .filter_map(|o| o.result.as_ref().ok())
+            .sum()
+    }
+}
+
+/// Runs uploads and downloads of local files with bounded concurrency and an optional bandwidth schedule shared by all transfers
+pub struct TransferManager {
+    /// Client to actually perform the requests
+    client: PCloudClient,
+    /// Transfers to run
+    transfers: Vec<Transfer>,
+    /// Explicit priorities of the transfers, by index
+    priorities: HashMap<usize, TransferPriority>,
+    /// Priorities of the transfers without an explicit one
+    rules: PriorityRules,
+    /// Interrupt transfers of lower priority if all slots are taken
+    preempt: bool,
+    /// Transfers added while running
+    queue: (
+        mpsc::UnboundedSender<QueuedTransfer>,
+        mpsc::UnboundedReceiver<QueuedTransfer>,
+    ),
+    /// Maximum number of transfers running in parallel
+    concurrency: usize,
+    /// Bandwidth limits by time of day
+    schedule: Option<BandwidthSchedule>,
+    /// Maximum number of attempts of each transfer, if completed transfers are verified
+    verify_attempts: Option<usize>,
+    /// Receives the progress of the transfers
+    progress: Option<Arc<dyn ProgressObserver>>,
+    /// Stops the transfers gracefully once triggered
+    shutdown: ShutdownSignal,
+}
+
+#[allow(dead_code)]
+impl TransferManager {
+    pub(crate) fn new(client: &PCloudClient) -> TransferManager {
+        TransferManager {
+            client: client.clone(),
+            transfers: Vec::new(),
+            priorities: HashMap::new(),
+            rules: PriorityRules::default(),
+            preempt: true,
+            queue: mpsc::unbounded_channel(),
+            concurrency: DEFAULT_TRANSFER_CONCURRENCY,
+            schedule: None,
+            verify_attempts: None,
+            progress: None,
+            shutdown: client.shutdown_signal().clone(),
+        }
+    }
+
+    /// Maximum number of transfers running in parallel (defaults to 4)
+    pub fn concurrency(mut self, value: usize) -> TransferManager {
+        self.concurrency = value.max(1);
+        self
+    }
+
+    /// Limits the bandwidth of all transfers together to the given number of bytes per second
+    pub fn max_bytes_per_second(self, value: u64) -> TransferManager {
+        self.bandwidth_schedule(BandwidthSchedule::new(Some(value)))
+    }
+
+    /// Limits the bandwidth of all transfers together by time of day. The schedule is evaluated while the transfers progress, so long running transfers speed up or slow down when a window starts or ends.
+    pub fn bandwidth_schedule(mut self, schedule: BandwidthSchedule) -> TransferManager {
+        self.schedule = Some(schedule);
+        self
+    }
+
+    /// Verifies size and checksum (SHA-1, SHA-256 if provided by pCloud) of every completed transfer. Transfers failing the verification are repeated until `max_attempts` (at least 1) attempts were made, the report contains the verification status of each transfer.
+    pub fn verify(mut self, max_attempts: usize) -> TransferManager {
+        self.verify_attempts = Some(max_attempts.max(1));
+        self
+    }
+
+    /// Transfers of files up to the given size (in bytes) get a high priority, so small files aren't stuck behind bulk data. Downloads need an additional request to determine the size.
+    pub fn small_files_first(mut self, max_size: u64) -> TransferManager {
+        self.rules.small_files = Some(max_size);
+        self
+    }
+
+    /// Transfers of local files below the given directory (upload sources and download targets) get the given priority. Takes precedence over `small_files_first()`, the first matching directory wins.
+    pub fn prioritize_path<P: AsRef<Path>>(
+        mut self,
+        path: P,
+        priority: TransferPriority,
+    ) -> TransferManager {
+        self.rules
+            .paths
+            .push((path.as_ref().to_path_buf(), priority));
+        self
+    }
+
+    /// If set (default), a transfer of higher priority waiting for a slot interrupts the running transfer of the lowest priority. The interrupted transfer is restarted from the beginning later on.
+    pub fn preempt(mut self, value: bool) -> TransferManager {
+        self.preempt = value;
+        self
+    }
+
+    /// Reports the progress of the transfers, each transfer being an item named by its description (like `upload of a.txt into /folder`). Only the transfers added before `run()` are announced as items of the operation, retried and interrupted transfers start over.
+    pub fn progress_observer(mut self, value: Arc<dyn ProgressObserver>) -> TransferManager {
+        self.progress = Some(value);
+        self
+    }
+
+    /// Signal to stop the transfers gracefully, defaults to the one of the client (see `PCloudClient::shutdown()`). Once triggered no further transfers are started, running ones get until the deadline to complete and are interrupted afterwards. The report lists all transfers not completed.
+    pub fn shutdown_signal(mut self, value: ShutdownSignal) -> TransferManager {
+        self.shutdown = value;
+        self
+    }
+
+    /// Handle to add transfers while running. `run()` completes once all transfers are done and all handles are dropped.
+    pub fn queue(&self) -> TransferQueue {
+        TransferQueue {
+            sender: self.queue.0.clone(),
+        }
+    }
+
+    /// Adds the upload of a local file into the given folder with an explicit priority
+    pub fn upload_with_priority<P: AsRef<Path>, T: FolderDescriptor>(
+        mut self,
+        source: P,
+        folder_like: T,
+        priority: TransferPriority,
+    ) -> Result<TransferManager, PCloudError> {
+        self.priorities.insert(self.transfers.len(), priority);
+        self.upload(source, folder_like)
+    }
+
+    /// Adds the download of a file to the given local path with an explicit priority
+    pub fn download_with_priority<T: FileDescriptor, P: AsRef<Path>>(
+        mut self,
+        file_like: T,
+        target: P,
+        priority: TransferPriority,
+    ) -> Result<TransferManager, PCloudError> {
+        self.priorities.insert(self.transfers.len(), priority);
+        self.download(file_like, target)
+    }
+
+    /// Adds the upload of a local file into the given folder. Accepts either a folder id (u64), a folder path (String) or any other pCloud object describing a folder (like Metadata)
+    pub fn upload<P: AsRef<Path>, T: FolderDescriptor>(
+        mut self,
+        source: P,
+        folder_like: T,
+    ) -> Result<TransferManager, PCloudError> {
+        let folder = folder_like.to_folder()?;
+        if folder.is_empty() {
+            Err(PCloudResult::NoFullPathOrFolderIdProvided)?
+        }
+        self.transfers.push(Transfer::Upload {
+            source: source.as_ref().to_path_buf(),
+            folder,
+        });
+        Ok(self)
+    }
+
+    /// Adds the download of a file to the given local path. Accepts either a file id (u64), a file path (String) or any other pCloud object describing a file (like Metadata)
+    pub fn download<T: FileDescriptor, P: AsRef<Path>>(
+        mut self,
+        file_like: T,
+        target: P,
+    ) -> Result<TransferManager, PCloudError> {
+        let file = file_like.to_file()?;
+        if file.is_empty() {
+            Err(PCloudResult::NoFileIdOrPathProvided)?
+        }
+        self.transfers.push(Transfer::Download {
+            file,
+            target: target.as_ref().to_path_buf(),
+        });
+        Ok(self)
+    }
+
+    /// Transfers added so far
+    pub fn transfers(&self) -> &[Transfer] {
+        &self.transfers
+    }
+
+    /// Runs all transfers, in the order of their priority. Failed transfers don't abort the others, the report contains the outcome of each transfer in the order added.
+    pub async fn run(self) -> Result<TransferReport, PCloudError> {
+        debug!(
+            "Running {} transfers with concurrency {}",
+            self.transfers.len(),
+            self.concurrency
+        );
+
+        let client = &self.client;
+        let limiter = self
+            .schedule
+            .map(|schedule| Arc::new(BandwidthLimiter::new(schedule)));
+        let limiter = &limiter;
+        let verify_attempts = self.verify_attempts;
+        let progress = &self.progress;
+        let shutdown = &self.shutdown;
+        let _running = shutdown.begin();
+        if let Some(observer) = progress {
+            observer.operation_started(Some(self.transfers.len()), None);
+        }
+        let (sender, mut receiver) = self.queue;
+        // Only the handles of the caller keep the queue open
+        drop(sender);
+
+        let mut pending = BinaryHeap::new();
+        for (index, transfer) in self.transfers.into_iter().enumerate() {
+            let priority = match self.priorities.get(&index) {
+                Some(priority) => *priority,
+                None => self.rules.classify(client, &transfer).await,
+            };
+            pending.push(Queued {
+                index,
+                transfer,
+                priority,
+                preemptions: 0,
+            });
+        }
+        let mut next_index = pending.len();
+        let mut queue_open = true;
+        let mut shutting_down = false;
+        let mut deadline_passed = false;
+
+        let mut running: HashMap<usize, Running> = HashMap::new();
+        let mut tasks = FuturesUnordered::new();
+        let mut outcomes = BTreeMap::new();
+        loop {
+            if !shutting_down && shutdown.is_triggered() {
+                debug!(
+                    "Shutting down, waiting for {} running transfers",
+                    running.len()
+                );
+                shutting_down = true;
+            }
+            while !shutting_down && running.len() < self.concurrency {
+                let queued = match pending.pop() {
+                    Some(queued) => queued,
+                    None => break,
+                };
+                let (abort, registration) = AbortHandle::new_pair();
+                let transfer = queued.transfer.clone();
+                let index = queued.index;
+                tasks.push(async move {
+                    let outcome = Abortable::new(
+                        execute_transfer(client, transfer, limiter, verify_attempts, progress),
+                        registration,
+                    )
+                    .await;
+                    (index, outcome)
+                });
+                running.insert(
+                    index,
+                    Running {
+                        transfer: queued.transfer,
+                        priority: queued.priority,
+                        preemptions: queued.preemptions,
+                        abort,
+                        preempted: false,
+                    },
+                );
+            }
+
+            if self.preempt && !shutting_down {
+                if let Some(waiting) = pending.peek() {
+                    let lowest = running
+                        .iter_mut()
+                        .filter(|(_, r)| !r.preempted && r.priority < waiting.priority)
+                        .min_by_key(|(index, r)| (r.priority, std::cmp::Reverse(**index)));
+                    if let Some((_, running)) = lowest {
+                        debug!("Interrupting {} for {}", running.transfer, waiting.transfer);
+                        running.preempted = true;
+                        running.abort.abort();
+                    }
+                }
+            }
+
+            if tasks.is_empty() && (shutting_down || (pending.is_empty() && !queue_open)) {
+                break;
+            }
+
+            tokio::select! {
+                Some((index, outcome)) = tasks.next(), if !tasks.is_empty() => {
+                    let running = running.remove(&index).expect("Running transfer");
+                    match outcome {
+                        Ok((result, verification, attempts)) => {
+                            outcomes.insert(index, TransferOutcome {
+                                transfer: running.transfer,
+                                result: result.map_err(PCloudError::from),
+                                verification,
+                                attempts,
+                                priority: running.priority,
+                                preemptions: running.preemptions,
+                            });
+                        }
+                        // Preempted (restarted once a slot is free again) or interrupted by the shutdown
+                        Err(_) => pending.push(Queued {
+                            index,
+                            transfer: running.transfer,
+                            priority: running.priority,
+                            preemptions: running.preemptions + usize::from(running.preempted),
+                        }),
+                    }
+                }
+                queued = receiver.recv(), if queue_open => match queued {
+                    Some((transfer, priority)) => {
+                        let priority = match priority {
+                            Some(priority) => priority,
+                            None => self.rules.classify(client, &transfer).await,
+                        };
+                        pending.push(Queued {
+                            index: next_index,
+                            transfer,
+                            priority,
+                            preemptions: 0,
+                        });
+                        next_index += 1;
+                    }
+                    None => queue_open = false,
+                },
+                // Handled at the beginning of the loop
+                _ = shutdown.triggered(), if !shutting_down => {}
+                _ = shutdown.deadline_passed(), if shutting_down && !deadline_passed => {
+                    warn!("Shutdown deadline passed, interrupting {} transfers", running.len());
+                    deadline_passed = true;
+                    for running in running.values() {
+                        running.abort.abort();
+                    }
+                }
+            }
+        }
+
+        let mut interrupted = pending.into_vec();
+        interrupted.sort_by_key(|queued| queued.index);
+        let mut interrupted: Vec<Transfer> = interrupted.into_iter().map(|q| q.transfer).collect();
+        receiver.close();
+        while let Ok((transfer, _)) = receiver.try_recv() {
+            interrupted.push(transfer);
+        }
+
+        if let Some(observer) = progress {
+            observer.operation_finished();
+        }
+        Ok(TransferReport {
+            outcomes: outcomes.into_values().collect(),
+            interrupted,
+        })
+    }
+}
+
+/// Runs a single transfer, repeating it if the verification fails, and returns its result, verification status and the number of attempts made
+async fn execute_transfer(
+    client: &PCloudClient,
+    transfer: Transfer,
+    limiter: &Option<Arc<BandwidthLimiter>>,
+    verify_attempts: Option<usize>,
+    progress: &Option<Arc<dyn ProgressObserver>>,
+) -> (
+    Result<u64, Box<dyn std::error::Error + Send + Sync>>,
+    VerificationStatus,
+    usize,
+) {
+    let max_attempts = verify_attempts.unwrap_or(1);
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let result = run_transfer(
+            client,
+            &transfer,
+            limiter.clone(),
+            verify_attempts.is_some(),
+            progress,
+        )
+        .await;
+        let (result, verification) = match result {
+            Ok(size) if verify_attempts.is_some() => (Ok(size), VerificationStatus::Verified),
+            Ok(size) => (Ok(size), VerificationStatus::NotVerified),
+            Err(AttemptError::Verification(e)) if attempts < max_attempts => {
+                warn!("Verification of {} failed: {}, retrying", transfer, e);
+                continue;
+            }
+            Err(AttemptError::Verification(e)) => (Err(e), VerificationStatus::Failed),
+            Err(AttemptError::Transfer(e)) => (Err(e), VerificationStatus::NotVerified),
+        };
+        if let Err(e) = &result {
+            debug!("Failed {}: {}", transfer, e);
+        }
+        if let Some(observer) = progress {
+            observer.item_finished(
+                &transfer.to_string(),
+                result.as_ref().err().map(|e| e.as_ref()),
+            );
+        }
+        return (result, verification, attempts);
+    }
+}
+
+/// Runs a single transfer (verifying the result, if requested) and returns the number of bytes transferred
+async fn run_transfer(
+    client: &PCloudClient,
+    transfer: &Transfer,
+    limiter: Option<Arc<BandwidthLimiter>>,
+    verification: bool,
+    progress: &Option<Arc<dyn ProgressObserver>>,
+) -> Result<u64, AttemptError> {
+    let item = transfer.to_string();
+    match transfer {
+        Transfer::Upload { source, folder } => {
+            let file_name = source
+                .file_name()
+                .ok_or_else(|| PCloudError::NotAFile(source.clone()))?
+                .to_string_lossy()
+                .to_string();
+            let file = tokio::fs::File::open(source).await?;
+            let size = file.metadata().await?.len();
+            if let Some(observer) = progress {
+                observer.item_started(&item, Some(size));
+            }
+            // SHA-1 of the content actually sent
+            let sha1 = Arc::new(Mutex::new(Sha1::new()));
+            // The bandwidth budget of the client applies in addition to the one of the manager
+            let client_limiter = client.rate_limiter().bandwidth();
+            let body = match (limiter, client_limiter, verification || progress.is_some()) {
+                (None, None, false) => Body::from(file),
+                (limiter, client_limiter, _) => {
+                    let hasher = sha1.clone();
+                    let stream = client
+                        .buffer_pool()
+                        .file_stream(file)
+                        .inspect(move |chunk| {
+                            if let Ok(chunk) = chunk {
+                                hasher.lock().unwrap().update(chunk);
+                            }
+                        });
+                    let stream = match progress {
+                        Some(observer) => {
+                            Either::Left(progress::observe(stream, observer.clone(), item))
+                        }
+                        None => Either::Right(stream),
+                    };
+                    match (limiter, client_limiter) {
+                        (Some(limiter), Some(client_limiter)) => {
+                            Body::wrap_stream(limit(limit(stream, limiter), client_limiter))
+                        }
+                        (Some(limiter), None) | (None, Some(limiter)) => {
+                            Body::wrap_stream(limit(stream, limiter))
+                        }
+                        (None, None) => Body::wrap_stream(stream),
+                    }
+                }
+            };
+
+            debug!("Uploading {} into {}", source.display(), folder);
+            let uploaded = client
+                .upload_file_into_folder(folder)?
+                .with_file(&file_name, body)
+                .upload()
+                .await?;
+
+            if verification {
+                let file_id = uploaded
+                    .into_metadata()?
+                    .first()
+                    .and_then(|m| m.fileid)
+                    .ok_or(PCloudResult::InternalUploadError)?;
+                let checksums = client.checksum_file(file_id)?.get().await?;
+                let remote_size = checksums
+                    .metadata
+                    .as_ref()
+                    .and_then(|m| m.size)
+                    .unwrap_or_default();
+                let local_sha1 = format!("{:x}", sha1.lock().unwrap().clone().finalize());
+                verify(
+                    Some(size),
+                    remote_size,
+                    [
+                        ("SHA-1", Some(&local_sha1), checksums.sha1),
+                        ("SHA-256", None, None),
+                    ],
+                )?;
+                debug!("Verified upload of {}", source.display());
+            }
+            Ok(size)
+        }
+        Transfer::Download { file, target } => {
+            debug!("Downloading {} to {}", file, target.display());
+            let (info, stream) = client.download_stream(file).await?;
+            let stream: std::pin::Pin<Box<dyn Stream<Item = _> + Send>> = match limiter {
+                Some(limiter) => Box::pin(limit(stream, limiter)),
+                None => Box::pin(stream),
+            };
+            let stream = match progress {
+                Some(observer) => {
+                    observer.item_started(&item, info.metadata.as_ref().and_then(|m| m.size));
+                    progress::observe(stream, observer.clone(), item).boxed()
+                }
+                None => stream,
+            };
+
+            let mut size = 0;
+            let mut sha1 = Sha1::new();
+            let mut sha256 = Sha256::new();
+            let mut local = tokio::fs::File::create(target).await?;
+            futures::pin_mut!(stream);
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk?;
+                size += chunk.len() as u64;
+                if verification {
+                    sha1.update(&chunk);
+                    if info.sha256.is_some() {
+                        sha256.update(&chunk);
+                    }
+                }
+                local.write_all(&chunk).await?;
+            }
+            local.flush().await?;
+
+            if verification {
+                verify(
+                    info.metadata.as_ref().and_then(|m| m.size),
+                    size,
+                    [
+                        (
+                            "SHA-1",
+                            info.sha1.as_ref(),
+                            Some(format!("{:x}", sha1.finalize())),
+                        ),
+                        (
+                            "SHA-256",
+                            info.sha256.as_ref(),
+                            Some(format!("{:x}", sha256.finalize())),
+                        ),
+                    ],
+                )?;
+                debug!("Verified download of {}", file);
+            }
+            Ok(size)
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Creates a TransferManager to run uploads and downloads of local files with bounded concurrency and shared bandwidth limits
+    pub fn transfer_manager(&self) -> TransferManager {
+        TransferManager::new(self)
+    }
+}
@@ -0,0 +1,184 @@
+use std::error::Error;
+
+use futures::future::BoxFuture;
+use reqwest::{Request, Response};
+
+/// Abstraction of the HTTP layer. All requests of the PCloudClient (and all its request builders) are finally executed by a HttpTransport.
+/// The default implementation is the reqwest Client. Custom implementations can be set using `PCloudClientBuilder::with_transport()`, e.g. to run without network access.
+pub trait HttpTransport: Send + Sync {
+    /// Executes the given request and returns the response
+    fn execute<'a>(
+        &'a self,
+        request: Request,
+    ) -> BoxFuture<'a, Result<Response, Box<dyn Error + Send + Sync>>>;
+}
+
+/// Default transport: Directly executes the requests with reqwest
+impl HttpTransport for reqwest::Client {
+    fn execute<'a>(
+        &'a self,
+        request: Request,
+    ) -> BoxFuture<'a, Result<Response, Box<dyn Error + Send + Sync>>> {
+        Box::pin(async move {
+            let response = reqwest::Client::execute(self, request).await?;
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(feature = "test-util")]
+pub use mock::{MockTransport, RecordedRequest};
+
+#[cfg(feature = "test-util")]
+mod mock {
+    use std::{
+        collections::{HashMap, VecDeque},
+        error::Error,
+        sync::{Arc, Mutex},
+    };
+
+    use futures::future::BoxFuture;
+    use reqwest::{header::HeaderMap, Method, Request, Response, Url};
+
+    use super::HttpTransport;
+
+    /// Request received by the MockTransport
+    #[derive(Debug, Clone)]
+    pub struct RecordedRequest {
+        /// pCloud method called (last path segment of the url, like `listfolder`)
+        pub method: String,
+        /// HTTP method used
+        pub http_method: Method,
+        /// Full url of the request
+        pub url: Url,
+        /// Request headers
+        pub headers: HeaderMap,
+    }
+
+    impl RecordedRequest {
+        /// Returns the value of the given query parameter
+        pub fn param(&self, name: &str) -> Option<String> {
+            self.url
+                .query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    /// Canned response of the MockTransport
+    struct MockResponse {
+        /// HTTP status code
+        status: u16,
+        /// Response body
+        body: Vec<u8>,
+    }
+
+    #[derive(Default)]
+    struct MockTransportState {
+        /// Registered responses per pCloud method
+        responses: HashMap<String, VecDeque<MockResponse>>,
+        /// All requests received
+        requests: Vec<RecordedRequest>,
+    }
+
+    /// In-memory transport returning canned responses for pCloud methods. Allows to unit-test code calling the PCloudClient without network access.
+    /// All clones share the same responses and recorded requests.
+    #[derive(Clone, Default)]
+    pub struct MockTransport {
+        state: Arc<Mutex<MockTransportState>>,
+    }
+
+    impl MockTransport {
+        /// Creates a new MockTransport without any registered responses
+        pub fn new() -> MockTransport {
+            MockTransport::default()
+        }
+
+        /// Registers a JSON response for the given pCloud method (like `listfolder`). Multiple responses for the same method are returned in the order of registration, the last one is repeated for all further requests.
+        pub fn respond_with(&self, method: &str, body: &str) -> &MockTransport {
+            self.respond_with_status(method, 200, body.as_bytes().to_vec())
+        }
+
+        /// Registers a response with the given HTTP status and raw body for the given pCloud method (or the last path segment of a download url)
+        pub fn respond_with_status<T: Into<Vec<u8>>>(
+            &self,
+            method: &str,
+            status: u16,
+            body: T,
+        ) -> &MockTransport {
+            let mut state = self.state.lock().unwrap();
+            state
+                .responses
+                .entry(method.to_string())
+                .or_default()
+                .push_back(MockResponse {
+                    status,
+                    body: body.into(),
+                });
+            self
+        }
+
+        /// Returns all requests received so far
+        pub fn requests(&self) -> Vec<RecordedRequest> {
+            self.state.lock().unwrap().requests.clone()
+        }
+
+        /// Returns all requests received so far for the given pCloud method
+        pub fn requests_for(&self, method: &str) -> Vec<RecordedRequest> {
+            self.requests()
+                .into_iter()
+                .filter(|r| r.method == method)
+                .collect()
+        }
+
+        /// Records the request and determines the response
+        fn respond(&self, request: &Request) -> Result<Response, Box<dyn Error + Send + Sync>> {
+            let method = request
+                .url()
+                .path_segments()
+                .and_then(|mut segments| segments.next_back())
+                .unwrap_or_default()
+                .to_string();
+
+            let mut state = self.state.lock().unwrap();
+            state.requests.push(RecordedRequest {
+                method: method.clone(),
+                http_method: request.method().clone(),
+                url: request.url().clone(),
+                headers: request.headers().clone(),
+            });
+
+            let queue = state
+                .responses
+                .get_mut(&method)
+                .ok_or_else(|| format!("No mock response registered for method {}", method))?;
+
+            // Keep the last response for all further requests
+            let response = if queue.len() > 1 {
+                queue.pop_front().unwrap()
+            } else {
+                let last = queue.front().unwrap();
+                MockResponse {
+                    status: last.status,
+                    body: last.body.clone(),
+                }
+            };
+
+            let response = http::Response::builder()
+                .status(response.status)
+                .header("content-type", "application/json")
+                .body(response.body)?;
+
+            Ok(Response::from(response))
+        }
+    }
+
+    impl HttpTransport for MockTransport {
+        fn execute<'a>(
+            &'a self,
+            request: Request,
+        ) -> BoxFuture<'a, Result<Response, Box<dyn Error + Send + Sync>>> {
+            Box::pin(async move { self.respond(&request) })
+        }
+    }
+}
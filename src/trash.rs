@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::PCloudError,
+    pcloud_client::PCloudClient,
+    pcloud_model::{FileOrFolderStat, Metadata, PCloudResult},
+};
+
+/// File or folder in the trash with the space it holds
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TrashedItem {
+    /// Name of the file or folder
+    pub name: String,
+    /// Id of the file or folder (like `f123` or `d45`)
+    pub id: String,
+    /// Id of the file, if it is one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fileid: Option<u64>,
+    /// Id of the folder, if it is one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub folderid: Option<u64>,
+    /// Id of the folder the item was deleted from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parentfolderid: Option<u64>,
+    /// Size in bytes of the file or of all files in the folder, reclaimed by `PCloudClient::clear_trash_item()`
+    pub size: u64,
+    /// Number of files (1 for a file)
+    pub files: usize,
+}
+
+impl TrashedItem {
+    /// Sums up the size and number of files of a trashed file or folder
+    fn of(metadata: &Metadata) -> TrashedItem {
+        let (size, files) = usage(metadata);
+        TrashedItem {
+            name: metadata.name.clone(),
+            id: metadata.id.clone(),
+            fileid: metadata.fileid,
+            folderid: metadata.folderid,
+            parentfolderid: metadata.parentfolderid,
+            size,
+            files,
+        }
+    }
+}
+
+/// Space held by the items trashed from a single folder
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TrashFolderUsage {
+    /// Id of the folder the items were deleted from, None if unknown
+    pub folderid: Option<u64>,
+    /// Size in bytes of all files trashed from the folder
+    pub size: u64,
+    /// Number of files trashed from the folder
+    pub files: usize,
+    /// Number of files and folders trashed directly from the folder
+    pub items: usize,
+}
+
+/// Space held by the trash, see `PCloudClient::trash_usage()`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct TrashUsage {
+    /// Size in bytes of all trashed files, reclaimed by `PCloudClient::clear_trash()`
+    pub size: u64,
+    /// Number of trashed files
+    pub files: usize,
+    /// All files and folders in the trash, the largest first
+    pub items: Vec<TrashedItem>,
+    /// Space held per folder the items were deleted from, the largest first
+    pub folders: Vec<TrashFolderUsage>,
+}
+
+impl TrashUsage {
+    /// Creates the report from the recursive listing of the trash
+    pub fn from_listing(trash: &Metadata) -> TrashUsage {
+        let mut items: Vec<TrashedItem> = trash.contents.iter().map(TrashedItem::of).collect();
+        items.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+
+        let mut by_folder: BTreeMap<Option<u64>, TrashFolderUsage> = BTreeMap::new();
+        for item in &items {
+            let folder = by_folder
+                .entry(item.parentfolderid)
+                .or_insert(TrashFolderUsage {
+                    folderid: item.parentfolderid,
+                    size: 0,
+                    files: 0,
+                    items: 0,
+                });
+            folder.size += item.size;
+            folder.files += item.files;
+            folder.items += 1;
+        }
+        let mut folders: Vec<TrashFolderUsage> = by_folder.into_values().collect();
+        folders.sort_by_key(|f| std::cmp::Reverse(f.size));
+
+        TrashUsage {
+            size: items.iter().map(|i| i.size).sum(),
+            files: items.iter().map(|i| i.files).sum(),
+            items,
+            folders,
+        }
+    }
+
+    /// Share of the used quota (see `UserInfo::usedquota`) held by the trash, between 0 and 1
+    pub fn share_of(&self, usedquota: u64) -> f64 {
+        if usedquota == 0 {
+            0.0
+        } else {
+            (self.size as f64 / usedquota as f64).min(1.0)
+        }
+    }
+}
+
+/// Size and number of files of a (recursively listed) file or folder
+fn usage(metadata: &Metadata) -> (u64, usize) {
+    if !metadata.isfolder {
+        return (metadata.size.unwrap_or_default(), 1);
+    }
+    metadata
+        .contents
+        .iter()
+        .map(usage)
+        .fold((0, 0), |(size, files), (s, f)| (size + s, files + f))
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Lists the trash recursively
+    pub async fn list_trash(&self) -> Result<Metadata, PCloudError> {
+        let mut r = self.client().get(format!("{}/trash_list", self.api_host()));
+
+        debug!("Requesting trash");
+        r = r.query(&[("folderid", "0"), ("recursive", "1")]);
+        r = self.add_token(r);
+
+        let result = self.send_checked::<FileOrFolderStat>(r).await?;
+        Ok(result.metadata.ok_or(PCloudResult::DirectoryDoesNotExist)?)
+    }
+
+    /// Reports how much space is held by the trash, per trashed item and per folder the items were deleted from
+    pub async fn trash_usage(&self) -> Result<TrashUsage, PCloudError> {
+        let trash = self.list_trash().await?;
+        Ok(TrashUsage::from_listing(&trash))
+    }
+
+    /// Deletes everything in the trash permanently
+    pub async fn clear_trash(&self) -> Result<(), PCloudError> {
+        debug!("Clearing trash");
+        self.trash_clear(&[("folderid", 0)]).await
+    }
+
+    /// Deletes a single file or folder in the trash permanently
+    pub async fn clear_trash_item(&self, item: &TrashedItem) -> Result<(), PCloudError> {
+        debug!("Clearing {} ({}) from trash", item.name, item.id);
+        match (item.fileid, item.folderid) {
+            (Some(fileid), _) => self.trash_clear(&[("fileid", fileid)]).await,
+            (None, Some(folderid)) => self.trash_clear(&[("folderid", folderid)]).await,
+            (None, None) => Err(PCloudResult::NoFileIdOrPathProvided)?,
+        }
+    }
+
+    async fn trash_clear(&self, query: &[(&str, u64)]) -> Result<(), PCloudError> {
+        let mut r = self
+            .client()
+            .get(format!("{}/trash_clear", self.api_host()));
+        r = r.query(query);
+        r = self.add_token(r);
+
+        self.send_checked::<FileOrFolderStat>(r).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,177 @@
+use std::{collections::HashSet, time::Duration};
+
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use tokio::sync::mpsc::{self, Receiver, Sender};
+
+use crate::{
+    error::PCloudError,
+    pcloud_client::PCloudClient,
+    pcloud_model::{Metadata, PCloudResult, UploadLinkList},
+};
+
+/// Default time between two checks of an upload link for new files
+pub const DEFAULT_INBOX_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// File received through an upload link
+#[derive(Debug)]
+pub struct IncomingFile {
+    /// Id of the upload link
+    pub upload_link_id: u64,
+    /// Path relative to the folder of the upload link, separated by '/'
+    pub path: String,
+    /// Metadata of the file
+    pub metadata: Metadata,
+}
+
+/// Upload statistics of a link, changing with each upload
+type LinkState = (u64, u64, DateTime<Utc>);
+
+/// Polls an upload link and streams the files arriving in its folder, e.g. to trigger processing in "send me files" workflows
+pub struct UploadLinkInboxBuilder {
+    /// Client to actually perform the requests
+    client: PCloudClient,
+    /// Id of the upload link
+    link_id: u64,
+    /// Time between two checks
+    interval: Duration,
+    /// if set, files already present when the stream starts are emitted as well
+    include_existing: bool,
+}
+
+#[allow(dead_code)]
+impl UploadLinkInboxBuilder {
+    pub(crate) fn for_link(client: &PCloudClient, link_id: u64) -> UploadLinkInboxBuilder {
+        UploadLinkInboxBuilder {
+            client: client.clone(),
+            link_id,
+            interval: DEFAULT_INBOX_POLL_INTERVAL,
+            include_existing: false,
+        }
+    }
+
+    /// Time between two checks of the link (defaults to 30 seconds)
+    pub fn interval(mut self, value: Duration) -> UploadLinkInboxBuilder {
+        self.interval = value;
+        self
+    }
+
+    /// if set, files already present in the folder when the stream starts are emitted as well
+    pub fn include_existing(mut self, value: bool) -> UploadLinkInboxBuilder {
+        self.include_existing = value;
+        self
+    }
+
+    /// Streams the incoming files. The folder is only listed when the upload statistics of the link changed. The stream ends when the receiver is dropped or the upload link is deleted, failed checks are repeated with the next one.
+    pub fn stream(self) -> Receiver<IncomingFile> {
+        let (tx, rx) = mpsc::channel::<IncomingFile>(128);
+
+        tokio::spawn(async move {
+            let mut seen = HashSet::new();
+            let mut last = None;
+            while !tx.is_closed() {
+                match self.poll(&tx, &mut seen, &mut last).await {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        debug!("Upload link {} was deleted", self.link_id);
+                        break;
+                    }
+                    Err(e) => warn!("Failed to check upload link {}: {}", self.link_id, e),
+                }
+                tokio::time::sleep(self.interval).await;
+            }
+        });
+
+        rx
+    }
+
+    /// Checks the link once and sends all files not seen before. Returns false if the link doesn't exist anymore.
+    async fn poll(
+        &self,
+        tx: &Sender<IncomingFile>,
+        seen: &mut HashSet<u64>,
+        last: &mut Option<LinkState>,
+    ) -> Result<bool, PCloudError> {
+        let links = self.client.list_upload_links().await?;
+        let link = match links.uploadlinks.into_iter().find(|l| l.id == self.link_id) {
+            Some(link) => link,
+            None => return Ok(false),
+        };
+        let state = (link.files, link.space, link.modified);
+        if last.as_ref() == Some(&state) {
+            return Ok(true);
+        }
+
+        let folder_id = link
+            .metadata
+            .and_then(|m| m.folderid)
+            .ok_or(PCloudResult::DirectoryDoesNotExist)?;
+        let metadata = self
+            .client
+            .list_folder(folder_id)?
+            .recursive(true)
+            .get()
+            .await?
+            .metadata
+            .ok_or(PCloudResult::DirectoryDoesNotExist)?;
+        let mut files = Vec::new();
+        collect_files(metadata.contents, "", &mut files);
+
+        let initial = last.is_none();
+        for (path, metadata) in files {
+            let fileid = match metadata.fileid {
+                Some(fileid) => fileid,
+                None => continue,
+            };
+            if !seen.insert(fileid) || (initial && !self.include_existing) {
+                continue;
+            }
+            debug!("Received {} through upload link {}", path, self.link_id);
+            tx.send(IncomingFile {
+                upload_link_id: self.link_id,
+                path,
+                metadata,
+            })
+            .await?;
+        }
+        *last = Some(state);
+        Ok(true)
+    }
+}
+
+/// Collects all files of the given (recursive) folder contents with their path relative to the folder
+fn collect_files(contents: Vec<Metadata>, prefix: &str, files: &mut Vec<(String, Metadata)>) {
+    for mut metadata in contents {
+        let path = if prefix.is_empty() {
+            metadata.name.clone()
+        } else {
+            format!("{}/{}", prefix, metadata.name)
+        };
+        if metadata.isfolder {
+            collect_files(std::mem::take(&mut metadata.contents), &path, files);
+        } else {
+            files.push((path, metadata));
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Lists the upload links of the user with their upload statistics
+    pub async fn list_upload_links(&self) -> Result<UploadLinkList, PCloudError> {
+        let mut r = self
+            .client()
+            .get(format!("{}/listuploadlinks", self.api_host()));
+
+        debug!("Requesting upload links");
+        r = self.add_token(r);
+
+        let result = self.send_checked::<UploadLinkList>(r).await?;
+        Ok(result)
+    }
+
+    /// Watches an upload link for new files (see `list_upload_links()` for the ids) and streams them as they arrive
+    pub fn watch_upload_link(&self, link_id: u64) -> UploadLinkInboxBuilder {
+        UploadLinkInboxBuilder::for_link(self, link_id)
+    }
+}
@@ -0,0 +1,254 @@
+#![cfg(feature = "vcr")]
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use futures::future::BoxFuture;
+use log::debug;
+use reqwest::{Request, Response};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::PCloudError,
+    redact::{redact_body, redact_url},
+    transport::HttpTransport,
+};
+
+pub use crate::redact::REDACTED;
+
+/// Request part of a recorded interaction
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecordedVcrRequest {
+    /// HTTP method
+    pub method: String,
+    /// Url of the request with all secrets redacted
+    pub url: String,
+}
+
+/// Response part of a recorded interaction
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecordedVcrResponse {
+    /// HTTP status code
+    pub status: u16,
+    /// Response headers
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    /// Textual response body (JSON responses or text downloads), secrets redacted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    /// Binary response body (e.g. file downloads)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub binary_body: Option<Vec<u8>>,
+}
+
+/// Single request / response pair of a cassette
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Interaction {
+    pub request: RecordedVcrRequest,
+    pub response: RecordedVcrResponse,
+}
+
+/// Fixture file containing all recorded interactions
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    /// Loads the cassette from the given JSON file
+    pub fn load(path: &Path) -> Result<Cassette, PCloudError> {
+        let content = std::fs::read(path)?;
+        Ok(serde_json::from_slice(&content)?)
+    }
+
+    /// Writes the cassette as JSON to the given file
+    pub fn save(&self, path: &Path) -> Result<(), PCloudError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Mode of the VcrTransport
+enum VcrMode {
+    /// Requests are forwarded to the inner transport and all interactions are written to the cassette
+    Record(Arc<dyn HttpTransport>),
+    /// Requests are answered from the cassette only
+    Replay,
+}
+
+struct VcrState {
+    cassette: Cassette,
+    /// Interactions already replayed
+    used: Vec<bool>,
+}
+
+/// Transport recording real API responses into a fixture file (cassette) and replaying them later deterministically, e.g. for offline regression tests.
+/// Authentication tokens, usernames and passwords are redacted before anything is written to the cassette.
+pub struct VcrTransport {
+    mode: VcrMode,
+    path: PathBuf,
+    state: Mutex<VcrState>,
+}
+
+impl VcrTransport {
+    /// Records all interactions into the given cassette file using a default reqwest client. An existing cassette is overwritten.
+    pub fn record<P: AsRef<Path>>(path: P) -> VcrTransport {
+        VcrTransport::record_with(path, reqwest::Client::new())
+    }
+
+    /// Records all interactions into the given cassette file, executing the requests with the given transport
+    pub fn record_with<P: AsRef<Path>, T: HttpTransport + 'static>(
+        path: P,
+        inner: T,
+    ) -> VcrTransport {
+        VcrTransport {
+            mode: VcrMode::Record(Arc::new(inner)),
+            path: path.as_ref().to_path_buf(),
+            state: Mutex::new(VcrState {
+                cassette: Cassette::default(),
+                used: Vec::new(),
+            }),
+        }
+    }
+
+    /// Replays the interactions of the given cassette file. Fails for any request not recorded.
+    pub fn replay<P: AsRef<Path>>(path: P) -> Result<VcrTransport, PCloudError> {
+        let cassette = Cassette::load(path.as_ref())?;
+        let used = vec![false; cassette.interactions.len()];
+        Ok(VcrTransport {
+            mode: VcrMode::Replay,
+            path: path.as_ref().to_path_buf(),
+            state: Mutex::new(VcrState { cassette, used }),
+        })
+    }
+
+    /// Replays the given cassette file if present, otherwise records a new one
+    pub fn record_or_replay<P: AsRef<Path>>(path: P) -> Result<VcrTransport, PCloudError> {
+        if path.as_ref().exists() {
+            VcrTransport::replay(path)
+        } else {
+            Ok(VcrTransport::record(path))
+        }
+    }
+
+    /// Path of the cassette file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns a copy of all interactions recorded or loaded so far
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.state.lock().unwrap().cassette.interactions.clone()
+    }
+
+    /// Executes the request with the inner transport and appends the interaction to the cassette
+    async fn record_interaction(
+        &self,
+        inner: &Arc<dyn HttpTransport>,
+        request: Request,
+    ) -> Result<Response, PCloudError> {
+        let recorded_request = RecordedVcrRequest {
+            method: request.method().to_string(),
+            url: redact_url(request.url()),
+        };
+
+        let response = inner.execute(request).await?;
+        let status = response.status().as_u16();
+        let headers: Vec<(String, String)> = response
+            .headers()
+            .iter()
+            .filter(|(name, _)| *name != "set-cookie")
+            .filter_map(|(name, value)| {
+                value
+                    .to_str()
+                    .ok()
+                    .map(|v| (name.to_string(), v.to_string()))
+            })
+            .collect();
+        let bytes = response.bytes().await?.to_vec();
+
+        let (body, binary_body) = match String::from_utf8(bytes.clone()) {
+            Ok(text) => (Some(redact_body(text)), None),
+            Err(_) => (None, Some(bytes.clone())),
+        };
+
+        {
+            let mut state = self.state.lock().unwrap();
+            state.cassette.interactions.push(Interaction {
+                request: recorded_request,
+                response: RecordedVcrResponse {
+                    status,
+                    headers: headers.clone(),
+                    body,
+                    binary_body,
+                },
+            });
+            state.used.push(true);
+            state.cassette.save(&self.path)?;
+        }
+
+        // The caller gets the original (unredacted) response
+        build_response(status, &headers, bytes)
+    }
+
+    /// Answers the request with the first unused interaction matching HTTP method and (redacted) url
+    fn replay_interaction(&self, request: &Request) -> Result<Response, PCloudError> {
+        let method = request.method().to_string();
+        let url = redact_url(request.url());
+
+        let mut state = self.state.lock().unwrap();
+        let index = state
+            .cassette
+            .interactions
+            .iter()
+            .enumerate()
+            .position(|(i, interaction)| {
+                !state.used[i]
+                    && interaction.request.method == method
+                    && interaction.request.url == url
+            })
+            .ok_or_else(|| PCloudError::NotRecorded(format!("{} {}", method, url)))?;
+        state.used[index] = true;
+
+        debug!("Replaying {} {}", method, url);
+        let response = &state.cassette.interactions[index].response;
+        let bytes = match (&response.body, &response.binary_body) {
+            (Some(body), _) => body.as_bytes().to_vec(),
+            (None, Some(binary_body)) => binary_body.clone(),
+            (None, None) => Vec::new(),
+        };
+        build_response(response.status, &response.headers, bytes)
+    }
+}
+
+impl HttpTransport for VcrTransport {
+    fn execute<'a>(
+        &'a self,
+        request: Request,
+    ) -> BoxFuture<'a, Result<Response, Box<dyn Error + Send + Sync>>> {
+        Box::pin(async move {
+            Ok(match &self.mode {
+                VcrMode::Record(inner) => self.record_interaction(inner, request).await?,
+                VcrMode::Replay => self.replay_interaction(&request)?,
+            })
+        })
+    }
+}
+
+/// Creates a reqwest response from the recorded parts
+fn build_response(
+    status: u16,
+    headers: &[(String, String)],
+    body: Vec<u8>,
+) -> Result<Response, PCloudError> {
+    let mut builder = http::Response::builder().status(status);
+    for (name, value) in headers {
+        builder = builder.header(name, value);
+    }
+    Ok(Response::from(builder.body(body)?))
+}
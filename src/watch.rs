@@ -0,0 +1,453 @@
+#![cfg(feature = "notify")]
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Display,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use log::{debug, warn};
+use notify::{
+    event::{ModifyKind, RenameMode},
+    EventKind, RecommendedWatcher, RecursiveMode, Watcher,
+};
+use tokio::sync::mpsc;
+
+use crate::{
+    error::PCloudError,
+    filter::SyncFilter,
+    folder_ops::{FolderDescriptor, PCloudFolder},
+    pcloud_client::PCloudClient,
+    pcloud_model::PCloudResult,
+};
+
+/// Default quiet period after the last local event before a batch is applied
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// Change of the local directory, applied to the remote folder. All paths are relative to the watched directory and separated by '/'.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LocalChange {
+    /// File created or modified locally, uploaded
+    Upload(String),
+    /// File or folder removed (or renamed) locally, deleted remotely
+    Delete(String),
+    /// Folder created (or moved in) locally, created remotely
+    CreateFolder(String),
+}
+
+impl Display for LocalChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LocalChange::Upload(path) => write!(f, "upload {}", path),
+            LocalChange::Delete(path) => write!(f, "delete {}", path),
+            LocalChange::CreateFolder(path) => write!(f, "create folder {}", path),
+        }
+    }
+}
+
+/// Outcome of a single change
+#[derive(Debug)]
+pub struct WatchOperation {
+    /// Change applied
+    pub change: LocalChange,
+    /// Result of applying the change
+    pub result: Result<(), PCloudError>,
+}
+
+/// All changes applied together after a quiet period
+#[derive(Debug, Default)]
+pub struct WatchBatch {
+    /// Outcomes of all changes, in order
+    pub operations: Vec<WatchOperation>,
+}
+
+impl WatchBatch {
+    /// Checks if all changes were applied
+    pub fn is_success(&self) -> bool {
+        self.operations.iter().all(|o| o.result.is_ok())
+    }
+
+    /// Changes and errors of all changes not applied
+    pub fn failures(&self) -> impl Iterator<Item = (&LocalChange, &PCloudError)> {
+        self.operations.iter().filter_map(|o| match &o.result {
+            Ok(_) => None,
+            Err(e) => Some((&o.change, e)),
+        })
+    }
+}
+
+/// What happened to a local path since the last batch
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Touched {
+    /// Created or moved in
+    Appeared,
+    /// Content or metadata changed
+    Changed,
+    /// Removed or moved away
+    Removed,
+}
+
+/// Running watch of a local directory. Dropping it stops watching.
+pub struct DirectoryWatch {
+    /// Keeps the file system watcher alive
+    _watcher: RecommendedWatcher,
+    /// Outcomes of the applied batches
+    batches: mpsc::UnboundedReceiver<WatchBatch>,
+}
+
+impl DirectoryWatch {
+    /// Waits for the next batch of changes applied to the remote folder. None once the watch stopped.
+    pub async fn next_batch(&mut self) -> Option<WatchBatch> {
+        self.batches.recv().await
+    }
+
+    /// Stops watching. Changes not applied yet are discarded.
+    pub fn stop(self) {}
+}
+
+/// Watches a local directory (recursively) and applies its changes to a remote folder: created and modified files are uploaded, removed files and folders are deleted. Events are debounced and applied in batches.
+pub struct WatchDirectoryRequestBuilder {
+    /// Client to actually perform the requests
+    client: PCloudClient,
+    /// Local directory to watch
+    directory: PathBuf,
+    /// Remote folder the directory is mapped to
+    folder: PCloudFolder,
+    /// Quiet period after the last event before a batch is applied
+    debounce: Duration,
+    /// Local files and folders not applied to the remote folder
+    filter: Option<SyncFilter>,
+}
+
+#[allow(dead_code)]
+impl WatchDirectoryRequestBuilder {
+    pub(crate) fn for_directory<P: AsRef<Path>, T: FolderDescriptor>(
+        client: &PCloudClient,
+        directory: P,
+        folder_like: T,
+    ) -> Result<WatchDirectoryRequestBuilder, PCloudError> {
+        let folder = folder_like.to_folder()?;
+
+        if folder.is_empty() {
+            Err(PCloudResult::NoFullPathOrFolderIdProvided)?
+        }
+
+        Ok(WatchDirectoryRequestBuilder {
+            client: client.clone(),
+            directory: directory.as_ref().to_path_buf(),
+            folder,
+            debounce: DEFAULT_DEBOUNCE,
+            filter: None,
+        })
+    }
+
+    /// Quiet period after the last local event before the collected changes are applied (defaults to 2 seconds)
+    pub fn debounce(mut self, value: Duration) -> WatchDirectoryRequestBuilder {
+        self.debounce = value;
+        self
+    }
+
+    /// Local files and folders not applied to the remote folder. Defaults to the rules of the `.pcloudignore` file in the watched directory, if present.
+    pub fn filter(mut self, value: SyncFilter) -> WatchDirectoryRequestBuilder {
+        self.filter = Some(value);
+        self
+    }
+
+    /// Starts watching. Changes are applied in the background until the returned watch is dropped.
+    pub async fn start(self) -> Result<DirectoryWatch, PCloudError> {
+        let directory = tokio::fs::canonicalize(&self.directory).await?;
+        let filter = match self.filter {
+            Some(filter) => filter,
+            None => SyncFilter::for_directory(&directory).await?,
+        };
+        let remote_root = match &self.folder.path {
+            Some(path) => path.clone(),
+            None => self
+                .client
+                .list_folder(&self.folder)?
+                .get()
+                .await?
+                .metadata
+                .and_then(|m| m.path)
+                .ok_or(PCloudResult::DirectoryDoesNotExist)?,
+        };
+
+        let (events_tx, events) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            // Fails only once the watch is stopped
+            let _ = events_tx.send(event);
+        })?;
+        watcher.watch(&directory, RecursiveMode::Recursive)?;
+        debug!(
+            "Watching {} for changes to apply to {}",
+            directory.display(),
+            remote_root
+        );
+
+        let (batches_tx, batches) = mpsc::unbounded_channel();
+        let applier = BatchApplier {
+            client: self.client,
+            directory,
+            remote_root: remote_root.trim_end_matches('/').to_string(),
+            created_folders: HashSet::new(),
+            filter,
+        };
+        tokio::spawn(applier.run(events, batches_tx, self.debounce));
+
+        Ok(DirectoryWatch {
+            _watcher: watcher,
+            batches,
+        })
+    }
+}
+
+/// Collects the local events and applies them as batches
+struct BatchApplier {
+    client: PCloudClient,
+    /// Canonical path of the watched directory
+    directory: PathBuf,
+    /// Path of the remote folder, without trailing '/'
+    remote_root: String,
+    /// Remote folders already created by this watch
+    created_folders: HashSet<String>,
+    /// Local files and folders ignored
+    filter: SyncFilter,
+}
+
+impl BatchApplier {
+    async fn run(
+        mut self,
+        mut events: mpsc::UnboundedReceiver<notify::Result<notify::Event>>,
+        batches: mpsc::UnboundedSender<WatchBatch>,
+        debounce: Duration,
+    ) {
+        let mut pending = BTreeMap::new();
+        loop {
+            let event = if pending.is_empty() {
+                events.recv().await
+            } else {
+                match tokio::time::timeout(debounce, events.recv()).await {
+                    Ok(event) => event,
+                    Err(_) => {
+                        let batch = self.apply(std::mem::take(&mut pending)).await;
+                        if batches.send(batch).is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                }
+            };
+
+            match event {
+                Some(Ok(event)) => self.collect(event, &mut pending),
+                Some(Err(e)) => warn!("Error watching {}: {}", self.directory.display(), e),
+                // Watch stopped
+                None => break,
+            }
+        }
+    }
+
+    /// Records the paths touched by the event, the latest event of a path wins
+    fn collect(&self, event: notify::Event, pending: &mut BTreeMap<String, Touched>) {
+        let touched: Vec<(&PathBuf, Touched)> = match event.kind {
+            EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                event.paths.iter().map(|p| (p, Touched::Appeared)).collect()
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => event
+                .paths
+                .iter()
+                .zip([Touched::Removed, Touched::Appeared])
+                .collect(),
+            EventKind::Modify(ModifyKind::Name(_)) | EventKind::Remove(_) => {
+                event.paths.iter().map(|p| (p, Touched::Removed)).collect()
+            }
+            EventKind::Modify(_) => event.paths.iter().map(|p| (p, Touched::Changed)).collect(),
+            _ => vec![],
+        };
+
+        for (path, kind) in touched {
+            if let Some(relative) = self.relative_path(path) {
+                let kind = match (pending.get(&relative), kind) {
+                    // Still new for the remote side
+                    (Some(Touched::Appeared), Touched::Changed) => Touched::Appeared,
+                    (_, kind) => kind,
+                };
+                pending.insert(relative, kind);
+            }
+        }
+    }
+
+    /// Path relative to the watched directory, separated by '/'. None for the directory itself and paths outside.
+    fn relative_path(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(&self.directory).ok()?;
+        let components: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        if components.is_empty() {
+            None
+        } else {
+            Some(components.join("/"))
+        }
+    }
+
+    /// Translates the touched paths into changes (based on their current local state) and applies them
+    async fn apply(&mut self, pending: BTreeMap<String, Touched>) -> WatchBatch {
+        let mut changes = Vec::new();
+        for (path, touched) in pending {
+            let local = path
+                .split('/')
+                .fold(self.directory.clone(), |p, c| p.join(c));
+            match tokio::fs::metadata(&local).await {
+                Ok(metadata) if metadata.is_file() => {
+                    if !self.filter.is_excluded(&path, Some(metadata.len())) {
+                        changes.push(LocalChange::Upload(path))
+                    }
+                }
+                // Content moved in doesn't cause events of its own
+                Ok(metadata) if metadata.is_dir() && touched == Touched::Appeared => {
+                    if !self.filter.is_folder_excluded(&path) {
+                        collect_directory(&local, &path, &self.filter, &mut changes).await
+                    }
+                }
+                Ok(_) => {}
+                // Excluded files were never uploaded
+                Err(_) if self.filter.is_excluded(&path, None) => {}
+                Err(_) => changes.push(LocalChange::Delete(path)),
+            }
+        }
+        debug!("Applying {} local changes", changes.len());
+
+        let mut batch = WatchBatch::default();
+        for change in changes {
+            let result = self.apply_change(&change).await;
+            if let Err(e) = &result {
+                warn!("Failed to {}: {}", change, e);
+            }
+            batch.operations.push(WatchOperation { change, result });
+        }
+        batch
+    }
+
+    async fn apply_change(&mut self, change: &LocalChange) -> Result<(), PCloudError> {
+        match change {
+            LocalChange::Upload(path) => {
+                let (parent, _) = path.rsplit_once('/').unwrap_or_default();
+                let folder = self.create_folders(parent).await?;
+                let local = path
+                    .split('/')
+                    .fold(self.directory.clone(), |p, c| p.join(c));
+                self.client
+                    .upload_file_into_folder(folder)?
+                    .with_file_from_path(local)
+                    .await?
+                    .keep_local_mtime(true)
+                    .upload()
+                    .await?;
+            }
+            LocalChange::CreateFolder(path) => {
+                self.create_folders(path).await?;
+            }
+            LocalChange::Delete(path) => {
+                // Whether it was a file or a folder is unknown once it is gone
+                let remote = format!("{}/{}", self.remote_root, path);
+                match self.client.delete_file(remote.as_str()).await {
+                    Ok(_) => {}
+                    Err(e) if is_not_found(&e) => {
+                        match self
+                            .client
+                            .delete_folder(remote.as_str())?
+                            .delete_recursive()
+                            .await
+                        {
+                            Ok(_) => {}
+                            // Already deleted, e.g. with its parent folder
+                            Err(e) if is_not_found(&e) => {}
+                            Err(e) => Err(e)?,
+                        }
+                    }
+                    Err(e) => Err(e)?,
+                }
+                self.created_folders
+                    .retain(|f| f != path && !f.starts_with(&format!("{}/", path)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates all missing remote folders of the relative path and returns the remote path
+    async fn create_folders(&mut self, path: &str) -> Result<String, PCloudError> {
+        let mut current = String::new();
+        for name in path.split('/').filter(|n| !n.is_empty()) {
+            let parent = format!("{}{}", self.remote_root, current);
+            current = format!("{}/{}", current, name);
+            if self.created_folders.insert(current.clone()) {
+                let parent = if parent.is_empty() { "/" } else { &parent };
+                self.client.create_folder(parent, name)?.execute().await?;
+            }
+        }
+        let folder = format!("{}{}", self.remote_root, current);
+        Ok(if folder.is_empty() {
+            "/".to_string()
+        } else {
+            folder
+        })
+    }
+}
+
+/// Checks if the error reports a missing file or folder
+fn is_not_found(error: &(dyn std::error::Error + 'static)) -> bool {
+    matches!(
+        PCloudResult::from_error(error),
+        Some(PCloudResult::FileNotFound)
+            | Some(PCloudResult::DirectoryDoesNotExist)
+            | Some(PCloudResult::ComponentOfTheParentDirectoryDoesNotExist)
+    )
+}
+
+/// Collects the folder and all its content (not excluded by the filter) as changes
+async fn collect_directory(
+    local: &Path,
+    path: &str,
+    filter: &SyncFilter,
+    changes: &mut Vec<LocalChange>,
+) {
+    let mut pending = vec![(local.to_path_buf(), path.to_string())];
+    while let Some((local, path)) = pending.pop() {
+        changes.push(LocalChange::CreateFolder(path.clone()));
+        let mut entries = match tokio::fs::read_dir(&local).await {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("Failed to read {}: {}", local.display(), e);
+                continue;
+            }
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let child = format!("{}/{}", path, entry.file_name().to_string_lossy());
+            match entry.file_type().await {
+                Ok(t) if t.is_dir() && !filter.is_folder_excluded(&child) => {
+                    pending.push((entry.path(), child))
+                }
+                Ok(t) if t.is_file() => {
+                    let size = entry.metadata().await.map(|m| m.len()).ok();
+                    if !filter.is_excluded(&child, size) {
+                        changes.push(LocalChange::Upload(child))
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl PCloudClient {
+    /// Watches a local directory and applies its changes (debounced, in batches) to a remote folder. Accepts either a folder id (u64), a folder path (String) or any other pCloud object describing a folder (like Metadata)
+    pub fn watch_directory<P: AsRef<Path>, T: FolderDescriptor>(
+        &self,
+        directory: P,
+        folder_like: T,
+    ) -> Result<WatchDirectoryRequestBuilder, PCloudError> {
+        WatchDirectoryRequestBuilder::for_directory(self, directory, folder_like)
+    }
+}
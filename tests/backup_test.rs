@@ -0,0 +1,67 @@
+use std::path::Path;
+
+use pcloud_async_api::fake_pcloud::FakePCloud;
+
+fn write_file(root: &Path, path: &str, content: &str) -> std::io::Result<()> {
+    let path = root.join(path);
+    std::fs::create_dir_all(path.parent().unwrap())?;
+    std::fs::write(path, content)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_incremental_backup_and_restore(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let pcloud = fake.client().await?;
+
+    let local = std::env::temp_dir().join(format!("pcloud-backup-{}", std::process::id()));
+    let source = local.join("source");
+    write_file(&source, "a.txt", "This is nice test content")?;
+    write_file(&source, "sub/b.txt", "This is other content")?;
+    // Same content as a.txt, stored only once
+    write_file(&source, "sub/c.txt", "This is nice test content")?;
+
+    let store = pcloud.open_backup_store("/backups/laptop").await?;
+    let first = store.backup_directory(&source).await?;
+    assert_eq!(3, first.snapshot.entries.len());
+    assert_eq!(2, first.blobs_uploaded);
+    assert_eq!(
+        vec!["a.txt", "sub/b.txt", "sub/c.txt"],
+        first
+            .snapshot
+            .entries
+            .iter()
+            .map(|e| e.path.as_str())
+            .collect::<Vec<&str>>()
+    );
+
+    // Second snapshot only uploads the changed content
+    write_file(&source, "sub/b.txt", "This is changed content")?;
+    write_file(&source, "d.txt", "This is other content")?;
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    let second = store.backup_directory(&source).await?;
+    assert_eq!(4, second.snapshot.entries.len());
+    assert_eq!(1, second.blobs_uploaded);
+
+    // Reopening the store finds the snapshots
+    let store = pcloud.open_backup_store("/backups/laptop").await?;
+    assert_eq!(
+        vec![first.snapshot.id.clone(), second.snapshot.id.clone()],
+        store.snapshots().await?
+    );
+
+    let restored = local.join("restored");
+    store.restore(&first.snapshot.id, &restored).await?;
+    assert_eq!(
+        "This is other content",
+        std::fs::read_to_string(restored.join("sub/b.txt"))?
+    );
+    assert_eq!(
+        "This is nice test content",
+        std::fs::read_to_string(restored.join("sub/c.txt"))?
+    );
+    assert!(!restored.join("d.txt").exists());
+
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use pcloud_async_api::{blocking, pcloud_client::PCloudClient, transport::MockTransport};
+
+#[test]
+fn test_blocking_client() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let transport = MockTransport::new();
+    transport.respond_with(
+        "userinfo",
+        r#"{ "result": 0, "auth": "session-token", "userid": 1, "quota": 1000, "usedquota": 10, "registered": "Wed, 25 Jan 2023 12:09:14 +0000" }"#,
+    );
+    transport.respond_with(
+        "getfilelink",
+        r#"{ "result": 0, "path": "/dl/test.txt", "hosts": ["c1.pcloud.com"], "expires": "Wed, 25 Jan 2023 12:09:14 +0000" }"#,
+    );
+    transport.respond_with_status("test.txt", 200, "This is nice test content");
+    transport.respond_with("logout", r#"{ "result": 0, "auth_deleted": true }"#);
+
+    let pcloud = blocking::PCloudClient::from_builder(
+        PCloudClient::builder("https://api.pcloud.com")
+            .with_username_and_password("test@example.com", "password")
+            .select_best_api_server(false)
+            .with_transport(transport.clone()),
+    )?;
+
+    let user_info = pcloud.get_user_info()?;
+    assert_eq!(Some(1000), user_info.quota);
+
+    let content = pcloud.download_file("/test-folder/test.txt")?;
+    assert_eq!(b"This is nice test content".to_vec(), content);
+
+    // Session is closed on drop
+    drop(pcloud);
+    assert_eq!(1, transport.requests_for("logout").len());
+
+    Ok(())
+}
@@ -0,0 +1,170 @@
+use pcloud_async_api::{
+    collections::InvalidCollectionPosition,
+    fake_pcloud::FakePCloud,
+    pcloud_model::{CollectionType, PCloudResult},
+};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_collection_list_and_details() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/music/a.mp3", "first song")?;
+    fake.put_file("/music/b.mp3", "second song")?;
+    fake.put_file("/music/c.mp3", "third song")?;
+    let favorites = fake.put_collection(
+        "Favorites",
+        &["/music/c.mp3", "/music/a.mp3", "/music/b.mp3"],
+    )?;
+    fake.put_collection("Empty", &[])?;
+    let pcloud = fake.client().await?;
+
+    let list = pcloud
+        .collection_list()
+        .of_type(CollectionType::Audio)
+        .get()
+        .await?;
+    assert_eq!(2, list.collections.len());
+    let collection = &list.collections[0];
+    assert_eq!("Favorites", collection.name);
+    assert_eq!(3, collection.items);
+    assert_eq!(CollectionType::Audio, collection.collection_type);
+    assert!(collection.contents.is_empty());
+
+    let list = pcloud
+        .collection_list()
+        .show_files(true)
+        .page_size(2)
+        .get()
+        .await?;
+    assert_eq!(2, list.collections[0].contents.len());
+
+    let details = pcloud.collection_details(favorites).get().await?;
+    let names: Vec<_> = details
+        .collection
+        .unwrap()
+        .contents
+        .iter()
+        .map(|item| item.metadata.name.clone())
+        .collect();
+    assert_eq!(vec!["c.mp3", "a.mp3", "b.mp3"], names);
+
+    assert!(pcloud.collection_details(4711).get().await.is_err());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_collection_create_rename_delete(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let a = fake.put_file("/music/a.mp3", "first song")?;
+    fake.put_file("/music/b.mp3", "second song")?;
+    let pcloud = fake.client().await?;
+
+    let created = pcloud
+        .collection_create("Road trip")?
+        .of_type(CollectionType::Audio)
+        .with_file("/music/b.mp3")?
+        .with_file(a)?
+        .execute()
+        .await?
+        .collection
+        .unwrap();
+    assert_eq!("Road trip", created.name);
+    assert_eq!(2, created.items);
+
+    let details = pcloud.collection_details(created.id).get().await?;
+    let names: Vec<_> = details
+        .collection
+        .unwrap()
+        .contents
+        .iter()
+        .map(|item| item.metadata.name.clone())
+        .collect();
+    assert_eq!(vec!["b.mp3", "a.mp3"], names);
+
+    let renamed = pcloud
+        .collection_rename(created.id, "Summer")?
+        .execute()
+        .await?;
+    assert_eq!("Summer", renamed.collection.unwrap().name);
+    assert!(pcloud.collection_rename(created.id, " ").is_err());
+
+    pcloud.collection_delete(created.id).execute().await?;
+    assert!(pcloud.collection_list().get().await?.collections.is_empty());
+    assert!(fake.exists("/music/a.mp3"));
+    assert!(pcloud
+        .collection_delete(created.id)
+        .execute()
+        .await
+        .is_err());
+    Ok(())
+}
+
+/// Names of the files of a collection, in their order
+async fn item_names(
+    pcloud: &pcloud_async_api::pcloud_client::PCloudClient,
+    collection_id: u64,
+) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+    let details = pcloud.collection_details(collection_id).get().await?;
+    Ok(details
+        .collection
+        .unwrap()
+        .contents
+        .into_iter()
+        .map(|item| item.metadata.name)
+        .collect())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_collection_membership() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let a = fake.put_file("/music/a.mp3", "first song")?;
+    fake.put_file("/music/b.mp3", "second song")?;
+    fake.put_file("/music/c.mp3", "third song")?;
+    let pcloud = fake.client().await?;
+    let id = pcloud
+        .collection_create("Mix")?
+        .execute()
+        .await?
+        .collection
+        .unwrap()
+        .id;
+
+    let linked = pcloud
+        .collection_link_files(id)
+        .with_file(a)?
+        .with_file("/music/b.mp3")?
+        .with_file("/music/c.mp3")?
+        .with_file(4711)?
+        .execute()
+        .await?;
+    assert_eq!(4, linked.linkresult.len());
+    assert_eq!(PCloudResult::Ok, linked.linkresult[0].result);
+    assert_eq!(PCloudResult::FileNotFound, linked.linkresult[3].result);
+    assert_eq!(3, linked.collection.unwrap().items);
+
+    pcloud.collection_move(id, 3, 1)?.execute().await?;
+    assert_eq!(
+        vec!["c.mp3", "a.mp3", "b.mp3"],
+        item_names(&pcloud, id).await?
+    );
+    let error = pcloud.collection_move(id, 0, 1).err().unwrap();
+    assert!(error.downcast_ref::<InvalidCollectionPosition>().is_some());
+
+    pcloud
+        .collection_unlink_files(id)
+        .at_position(1)?
+        .with_file("/music/b.mp3")?
+        .execute()
+        .await?;
+    assert_eq!(vec!["a.mp3"], item_names(&pcloud, id).await?);
+
+    pcloud
+        .collection_unlink_files(id)
+        .all(true)
+        .execute()
+        .await?;
+    assert!(item_names(&pcloud, id).await?.is_empty());
+    assert!(fake.exists("/music/a.mp3"));
+    Ok(())
+}
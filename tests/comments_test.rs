@@ -0,0 +1,37 @@
+use pcloud_async_api::{error::PCloudError, fake_pcloud::FakePCloud};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_file_comments() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let fileid = fake.put_file("/review/draft.pdf", "draft content")?;
+    fake.put_file("/review/other.pdf", "other content")?;
+    let pcloud = fake.client().await?;
+
+    let posted = pcloud
+        .post_file_comment("/review/draft.pdf", "Please check page 3, & the summary")
+        .await?
+        .comment
+        .unwrap();
+    assert_eq!(fileid, posted.fileid);
+    assert_eq!("Please check page 3, & the summary", posted.text);
+    assert_eq!(1, posted.author.userid);
+    assert_eq!(Some("fake@pcloud.local".to_string()), posted.author.email);
+    pcloud.post_file_comment(fileid, "Done").await?;
+    pcloud
+        .post_file_comment("/review/other.pdf", "Unrelated")
+        .await?;
+    assert!(matches!(
+        pcloud.post_file_comment(fileid, "  ").await,
+        Err(PCloudError::EmptyComment)
+    ));
+
+    let comments = pcloud.list_file_comments(fileid).await?.comments;
+    let texts: Vec<_> = comments.iter().map(|c| c.text.as_str()).collect();
+    assert_eq!(vec!["Please check page 3, & the summary", "Done"], texts);
+
+    let deleted = pcloud.delete_file_comment(posted.id).await?;
+    assert_eq!(Some(posted.id), deleted.comment.map(|c| c.id));
+    assert_eq!(1, pcloud.list_file_comments(fileid).await?.comments.len());
+    assert!(pcloud.delete_file_comment(posted.id).await.is_err());
+    Ok(())
+}
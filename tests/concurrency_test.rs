@@ -0,0 +1,109 @@
+use std::{
+    error::Error,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use futures::future::BoxFuture;
+use pcloud_async_api::{
+    concurrency::AdaptiveConcurrency, fake_pcloud::FakePCloud, pcloud_model::PCloudResult,
+    transport::HttpTransport,
+};
+use reqwest::{Request, Response};
+
+/// Transport rejecting link requests with a rate limit error while too many are running
+struct OverloadedTransport {
+    fake: FakePCloud,
+    /// Link requests running
+    running: Arc<AtomicUsize>,
+    /// Link requests running at most
+    peak: Arc<AtomicUsize>,
+    /// Number of link requests allowed in parallel
+    capacity: usize,
+}
+
+impl HttpTransport for OverloadedTransport {
+    fn execute<'a>(
+        &'a self,
+        request: Request,
+    ) -> BoxFuture<'a, Result<Response, Box<dyn Error + Send + Sync>>> {
+        Box::pin(async move {
+            if !request.url().path().ends_with("/getfilelink") {
+                return self.fake.execute(request).await;
+            }
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(running, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            let result = if running > self.capacity {
+                Err(PCloudResult::RateLimitExceeded.into())
+            } else {
+                self.fake.execute(request).await
+            };
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            result
+        })
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_adaptive_concurrency_backs_off() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let mut file_ids = Vec::new();
+    for i in 0..40 {
+        file_ids.push(fake.put_file(&format!("/files/{}.txt", i), "content")?);
+    }
+    let peak = Arc::new(AtomicUsize::new(0));
+    let transport = OverloadedTransport {
+        fake: fake.clone(),
+        running: Arc::new(AtomicUsize::new(0)),
+        peak: peak.clone(),
+        capacity: 4,
+    };
+    let pcloud = fake
+        .client_builder()
+        .with_transport(transport)
+        .build()
+        .await?;
+
+    let controller = AdaptiveConcurrency::new(16).latency_threshold(None);
+    let links = pcloud
+        .get_download_links(file_ids)
+        .adaptive_concurrency(controller.clone())
+        .get()
+        .await;
+
+    // The limit grows until the server rejects requests and is cut down afterwards
+    let failed = links.values().filter(|l| l.is_err()).count();
+    assert!(failed > 0);
+    assert!(failed < 10);
+    assert!(peak.load(Ordering::SeqCst) <= 8);
+    assert!(controller.current_limit() < 8);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_adaptive_concurrency_grows() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let pcloud = fake.client().await?;
+    fake.create_folder("/uploads")?;
+
+    let controller = AdaptiveConcurrency::new(4).latency_threshold(None);
+    let sources = (0..20).map(|i| (format!("{}.txt", i), format!("content {}", i)));
+    let report = pcloud
+        .upload_files("/uploads", sources)?
+        .adaptive_concurrency(controller.clone())
+        .upload()
+        .await?;
+
+    assert_eq!(20, report.outcomes.len());
+    assert!(report.outcomes.iter().all(|o| o.result.is_ok()));
+    assert_eq!(4, controller.current_limit());
+    assert_eq!(
+        Some(b"content 7".to_vec()),
+        fake.read_file("/uploads/7.txt")
+    );
+    Ok(())
+}
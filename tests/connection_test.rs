@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use pcloud_async_api::{
+    error::PCloudError,
+    pcloud_client::PCloudClient,
+    testing::{user_info_response, MockPCloudServer},
+};
+use wiremock::{
+    matchers::{header, path},
+    Mock, ResponseTemplate,
+};
+
+#[tokio::test]
+async fn test_user_agent() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let server = MockPCloudServer::start().await;
+    Mock::given(path("/userinfo"))
+        .and(header("user-agent", "my-app/1.0"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(user_info_response(1000, 10)))
+        .mount(server.server())
+        .await;
+
+    let pcloud = PCloudClient::builder(&server.uri())
+        .with_oauth("test-token")
+        .select_best_api_server(false)
+        .user_agent("my-app/1.0")
+        .connect_timeout(Duration::from_secs(5))
+        .pool_max_idle_per_host(2)
+        .proxy(reqwest::Proxy::custom(|_| None::<reqwest::Url>))
+        .build()
+        .await?;
+    assert_eq!(Some(1000), pcloud.get_user_info().await?.quota);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_timeout() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let server = MockPCloudServer::start().await;
+    Mock::given(path("/userinfo"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(user_info_response(1000, 10))
+                .set_delay(Duration::from_secs(2)),
+        )
+        .mount(server.server())
+        .await;
+
+    let pcloud = PCloudClient::builder(&server.uri())
+        .with_oauth("test-token")
+        .select_best_api_server(false)
+        .timeout(Duration::from_millis(200))
+        .build()
+        .await?;
+    let error = pcloud.get_user_info().await.unwrap_err();
+    assert!(matches!(&error, PCloudError::Http(e) if e.is_timeout()));
+    assert!(error.is_network());
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use std::error::Error;
+
+use futures::future::BoxFuture;
+use pcloud_async_api::{
+    cross_account::transfer_between, fake_pcloud::FakePCloud, file_ops::ChecksumMismatch,
+    transport::HttpTransport,
+};
+use reqwest::{Request, Response};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_transfer_folder_between_accounts() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let source_fake = FakePCloud::start().await?;
+    source_fake.put_file("/photos/a.jpg", "first photo")?;
+    source_fake.put_file("/photos/2023/b.jpg", "second photo")?;
+    source_fake.create_folder("/photos/empty")?;
+    let target_fake = FakePCloud::start().await?;
+    target_fake.create_folder("/migrated")?;
+    let source = source_fake.client().await?;
+    let target = target_fake.client().await?;
+
+    let report = transfer_between(&source, "/photos", &target, "/migrated")?
+        .concurrency(2)
+        .execute()
+        .await?;
+
+    assert!(report.is_success());
+    assert_eq!(3, report.folders);
+    assert_eq!(2, report.transferred.len());
+    assert_eq!("photos/2023/b.jpg", report.transferred[0].path);
+    assert_eq!(23, report.bytes_transferred());
+    assert_eq!(
+        Some(b"first photo".to_vec()),
+        target_fake.read_file("/migrated/photos/a.jpg")
+    );
+    assert_eq!(
+        Some(b"second photo".to_vec()),
+        target_fake.read_file("/migrated/photos/2023/b.jpg")
+    );
+    assert!(target_fake.exists("/migrated/photos/empty"));
+    // The source stays untouched
+    assert!(source_fake.exists("/photos/a.jpg"));
+
+    // Single files are copied directly into the target folder
+    let report = transfer_between(&source, "/photos/a.jpg", &target, "/")?
+        .execute()
+        .await?;
+    assert_eq!(1, report.transferred.len());
+    assert_eq!(0, report.folders);
+    assert_eq!(
+        Some(b"first photo".to_vec()),
+        target_fake.read_file("/a.jpg")
+    );
+    Ok(())
+}
+
+/// Transport reporting wrong checksums of all files
+struct CorruptingTransport {
+    fake: FakePCloud,
+}
+
+impl HttpTransport for CorruptingTransport {
+    fn execute<'a>(
+        &'a self,
+        request: Request,
+    ) -> BoxFuture<'a, Result<Response, Box<dyn Error + Send + Sync>>> {
+        Box::pin(async move {
+            if !request.url().path().ends_with("/checksumfile") {
+                return self.fake.execute(request).await;
+            }
+            let response = self.fake.execute(request).await?;
+            let mut body: serde_json::Value = response.json().await?;
+            body["sha1"] = serde_json::json!("0000000000000000000000000000000000000000");
+            Ok(hyper::Response::new(body.to_string()).into())
+        })
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_transfer_between_accounts_verifies_checksums(
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let source_fake = FakePCloud::start().await?;
+    source_fake.put_file("/docs/report.pdf", "report")?;
+    let target_fake = FakePCloud::start().await?;
+    let source = source_fake.client().await?;
+    let target = target_fake
+        .client_builder()
+        .with_transport(CorruptingTransport {
+            fake: target_fake.clone(),
+        })
+        .build()
+        .await?;
+
+    let report = transfer_between(&source, "/docs", &target, "/")?
+        .execute()
+        .await?;
+    assert!(!report.is_success());
+    assert_eq!("docs/report.pdf", report.failed[0].path);
+    assert!(report.failed[0]
+        .error
+        .downcast_ref::<ChecksumMismatch>()
+        .is_some());
+
+    let report = transfer_between(&source, "/docs", &target, "/")?
+        .verify(false)
+        .execute()
+        .await?;
+    assert!(report.is_success());
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use std::io::Write;
+
+use pcloud_async_api::{decompress::Compression, fake_pcloud::FakePCloud};
+
+const CONTENT: &str = "This is nice test content, compressed and decompressed again.";
+
+fn gzip(content: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(content).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[test]
+fn test_detect_compression() {
+    assert_eq!(
+        Some(Compression::Gzip),
+        Compression::detect("backup.tar.GZ", None)
+    );
+    assert_eq!(
+        Some(Compression::Zstd),
+        Compression::detect("backup.tar.zst", None)
+    );
+    assert_eq!(
+        Some(Compression::Gzip),
+        Compression::detect("backup", Some("application/gzip"))
+    );
+    assert_eq!(None, Compression::detect("test.txt", Some("text/plain")));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_download_decompressed() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/test-folder/test.txt.gz", gzip(CONTENT.as_bytes()))?;
+    fake.put_file(
+        "/test-folder/test.txt.zst",
+        zstd::encode_all(CONTENT.as_bytes(), 3)?,
+    )?;
+    fake.put_file("/test-folder/test.bin", gzip(CONTENT.as_bytes()))?;
+    fake.put_file("/test-folder/test.txt", CONTENT)?;
+    let pcloud = fake.client().await?;
+
+    for (path, compression) in [
+        ("/test-folder/test.txt.gz", Some(Compression::Gzip)),
+        ("/test-folder/test.txt.zst", Some(Compression::Zstd)),
+        ("/test-folder/test.txt", None),
+    ] {
+        let mut output = Vec::new();
+        let result = pcloud
+            .download_decompressed(path)?
+            .write_to(&mut output)
+            .await?;
+        assert_eq!(compression, result.compression);
+        assert_eq!(CONTENT.len() as u64, result.decompressed_bytes);
+        assert_eq!(CONTENT.as_bytes(), &output[..]);
+    }
+
+    // Compression not detectable from the name, but forced
+    let mut output = Vec::new();
+    pcloud
+        .download_decompressed("/test-folder/test.bin")?
+        .compression(Compression::Gzip)
+        .write_to(&mut output)
+        .await?;
+    assert_eq!(CONTENT.as_bytes(), &output[..]);
+
+    Ok(())
+}
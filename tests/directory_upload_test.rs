@@ -0,0 +1,61 @@
+use pcloud_async_api::fake_pcloud::FakePCloud;
+
+#[tokio::test]
+async fn test_upload_directory() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.create_folder("/backup")?;
+    let pcloud = fake.client().await?;
+
+    let local = std::env::temp_dir().join(format!("pcloud-upload-dir-{}", std::process::id()));
+    std::fs::create_dir_all(local.join("docs/2023"))?;
+    std::fs::create_dir_all(local.join("empty"))?;
+    std::fs::write(local.join("a.txt"), "first")?;
+    std::fs::write(local.join("docs/b.txt"), "second")?;
+    std::fs::write(local.join("docs/2023/c.txt"), "third")?;
+
+    let report = pcloud
+        .upload_directory(&local, "/backup")?
+        .concurrency(2)
+        .execute()
+        .await?;
+    assert!(report.is_success());
+    assert_eq!(3, report.folders);
+    let paths: Vec<&str> = report.uploaded.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(vec!["a.txt", "docs/2023/c.txt", "docs/b.txt"], paths);
+    assert_eq!(16, report.bytes_uploaded());
+    assert_eq!(Some(b"first".to_vec()), fake.read_file("/backup/a.txt"));
+    assert_eq!(
+        Some(b"second".to_vec()),
+        fake.read_file("/backup/docs/b.txt")
+    );
+    assert_eq!(
+        Some(b"third".to_vec()),
+        fake.read_file("/backup/docs/2023/c.txt")
+    );
+    assert!(fake.exists("/backup/empty"));
+
+    // Uploading again reuses the existing folders and replaces the files
+    std::fs::write(local.join("docs/b.txt"), "changed")?;
+    let report = pcloud
+        .upload_directory(&local, "/backup")?
+        .execute()
+        .await?;
+    assert!(report.is_success());
+    assert_eq!(3, report.uploaded.len());
+    assert_eq!(
+        Some(b"changed".to_vec()),
+        fake.read_file("/backup/docs/b.txt")
+    );
+
+    // Failed files are reported
+    let report = pcloud
+        .upload_directory(local.join("docs/2023"), "/missing")?
+        .execute()
+        .await?;
+    assert!(!report.is_success());
+    assert_eq!("c.txt", report.failed[0].path);
+    assert!(report.uploaded.is_empty());
+
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+use pcloud_async_api::{
+    dns::{CachingResolver, IpPreference},
+    pcloud_client::PCloudClient,
+};
+
+#[tokio::test]
+async fn test_caching_resolver() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let resolver = CachingResolver::new(Some(Duration::from_secs(60)), IpPreference::Ipv4Only);
+    assert!(resolver.cached_addrs("localhost").is_none());
+
+    let addrs = resolver.lookup("localhost").await?;
+    assert!(!addrs.is_empty());
+    assert!(addrs.iter().all(|a| a.is_ipv4()));
+    assert_eq!(Some(addrs), resolver.cached_addrs("localhost"));
+
+    resolver.clear();
+    assert!(resolver.cached_addrs("localhost").is_none());
+
+    // Without TTL nothing is cached
+    let resolver = CachingResolver::new(None, IpPreference::PreferIpv4);
+    let addrs = resolver.lookup("localhost").await?;
+    assert!(addrs[0].is_ipv4());
+    assert!(resolver.cached_addrs("localhost").is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_client_with_dns_options() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let pcloud = PCloudClient::builder("http://localhost:1")
+        .with_oauth("test-token")
+        .select_best_api_server(false)
+        .dns_cache_ttl(Duration::from_secs(300))
+        .ip_preference(IpPreference::PreferIpv4)
+        .build()
+        .await?;
+    assert!(pcloud.ping().await.is_err());
+    Ok(())
+}
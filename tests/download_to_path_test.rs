@@ -0,0 +1,120 @@
+use std::{
+    error::Error,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+
+use futures::future::BoxFuture;
+use pcloud_async_api::{fake_pcloud::FakePCloud, transport::HttpTransport};
+use reqwest::{Request, Response};
+
+/// Transport counting the checksum requests and reporting a wrong SHA-1 checksum of all files
+struct CorruptingTransport {
+    fake: FakePCloud,
+    checksum_calls: Arc<AtomicUsize>,
+}
+
+impl HttpTransport for CorruptingTransport {
+    fn execute<'a>(
+        &'a self,
+        request: Request,
+    ) -> BoxFuture<'a, Result<Response, Box<dyn Error + Send + Sync>>> {
+        Box::pin(async move {
+            if !request.url().path().ends_with("/checksumfile") {
+                return self.fake.execute(request).await;
+            }
+            self.checksum_calls.fetch_add(1, Ordering::SeqCst);
+            let response = self.fake.execute(request).await?;
+            let mut body: serde_json::Value = response.json().await?;
+            body["sha1"] = serde_json::json!("0000000000000000000000000000000000000000");
+            Ok(hyper::Response::new(body.to_string()).into())
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_download_file_to_path() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let content: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+    fake.put_file("/docs/big.bin", content.clone())?;
+    let pcloud = fake.client().await?;
+
+    let local = std::env::temp_dir().join(format!("pcloud-download-path-{}", std::process::id()));
+    std::fs::create_dir_all(&local)?;
+
+    // Keeps the modification time of the remote file
+    let before = SystemTime::now() - Duration::from_secs(2);
+    let info = pcloud
+        .download_file_to_path("/docs/big.bin", local.join("big.bin"), true)
+        .await?;
+    assert_eq!(content, std::fs::read(local.join("big.bin"))?);
+    assert!(!local.join("big.bin.part").exists());
+    let modified = info.metadata.as_ref().unwrap().modified;
+    assert_eq!(
+        SystemTime::from(modified),
+        std::fs::metadata(local.join("big.bin"))?.modified()?
+    );
+
+    // Overwrites existing files, leaving the modification time as it is
+    std::fs::write(local.join("big.bin"), "outdated")?;
+    let metadata = pcloud.get_file_metadata("/docs/big.bin").await?;
+    pcloud
+        .download_file_to_path(&metadata, local.join("big.bin"), false)
+        .await?;
+    assert_eq!(content, std::fs::read(local.join("big.bin"))?);
+    assert!(std::fs::metadata(local.join("big.bin"))?.modified()? > before);
+
+    // Missing files fail without leaving anything behind
+    assert!(pcloud
+        .download_file_to_path("/docs/missing.bin", local.join("missing.bin"), true)
+        .await
+        .is_err());
+    assert!(!local.join("missing.bin").exists());
+
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_download_file_to_path_unverified() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/docs/a.txt", "This is nice test content")?;
+    let checksum_calls = Arc::new(AtomicUsize::new(0));
+    let pcloud = fake
+        .client_builder()
+        .with_transport(CorruptingTransport {
+            fake: fake.clone(),
+            checksum_calls: checksum_calls.clone(),
+        })
+        .build()
+        .await?;
+
+    let local =
+        std::env::temp_dir().join(format!("pcloud-download-unverified-{}", std::process::id()));
+    std::fs::create_dir_all(&local)?;
+
+    // The checksums are neither requested nor compared
+    for keep_mtime in [false, true] {
+        pcloud
+            .download_file_to_path("/docs/a.txt", local.join("a.txt"), keep_mtime)
+            .await?;
+        assert_eq!(
+            "This is nice test content",
+            std::fs::read_to_string(local.join("a.txt"))?
+        );
+    }
+    assert_eq!(0, checksum_calls.load(Ordering::SeqCst));
+
+    // Only download_and_verify() enforces them
+    assert!(pcloud
+        .download_and_verify("/docs/a.txt", local.join("b.txt"))
+        .await
+        .is_err());
+    assert_eq!(1, checksum_calls.load(Ordering::SeqCst));
+
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use pcloud_async_api::{error::PCloudError, fake_pcloud::FakePCloud, pcloud_model::PCloudResult};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_api_error() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let pcloud = fake.client().await?;
+
+    let error = pcloud.get_file_metadata("/missing.txt").await.unwrap_err();
+    assert!(matches!(
+        error,
+        PCloudError::ApiError(PCloudResult::FileNotFound)
+    ));
+    assert_eq!(Some(&PCloudResult::FileNotFound), error.api_result());
+    assert!(!error.is_network());
+    // Still found by helpers walking the source chain
+    assert_eq!(
+        Some(&PCloudResult::FileNotFound),
+        PCloudResult::from_error(&error)
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_invalid_descriptor() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.create_folder("/docs")?;
+    let pcloud = fake.client().await?;
+
+    // A folder doesn't describe a file, so nothing is requested
+    let folder = pcloud.list_folder("/docs")?.get().await?;
+    let error = pcloud.get_file_metadata(&folder).await.unwrap_err();
+    assert!(matches!(
+        error,
+        PCloudError::InvalidDescriptor(PCloudResult::InvalidFileOrFolderName)
+    ));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_io_error() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/docs/a.txt", "content")?;
+    let pcloud = fake.client().await?;
+
+    // A file can't be the parent folder of the download
+    let local = std::env::temp_dir().join(format!("pcloud-error-{}", std::process::id()));
+    std::fs::write(&local, "local")?;
+    let error = pcloud
+        .download_and_verify("/docs/a.txt", local.join("a.txt"))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, PCloudError::Io(_)));
+    assert!(error.api_result().is_none());
+
+    std::fs::remove_file(&local)?;
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use std::{
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server,
+};
+use pcloud_async_api::{event_bridge::EventBridge, fake_pcloud::FakePCloud};
+use tokio::sync::mpsc;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_bridge_events_to_channel() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let pcloud = fake.client().await?;
+    fake.put_file("/docs/a.txt", "content")?;
+
+    let (tx, mut rx) = mpsc::channel(16);
+    let bridge = EventBridge::channel(tx).spawn(pcloud.get_events().stream());
+
+    let mut events = Vec::new();
+    for _ in 0..2 {
+        let json = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await?
+            .unwrap();
+        events.push(serde_json::from_str::<serde_json::Value>(&json)?);
+    }
+    assert_eq!("createfolder", events[0]["event"]);
+    assert_eq!("createfile", events[1]["event"]);
+    assert_eq!("a.txt", events[1]["metadata"]["name"]);
+
+    // The bridge ends once the receiver is gone
+    drop(rx);
+    fake.put_file("/docs/b.txt", "content")?;
+    let last = tokio::time::timeout(Duration::from_secs(5), bridge).await???;
+    assert_eq!(Some(2), last);
+    Ok(())
+}
+
+/// Starts a webhook endpoint failing the first request with a server error and collecting all bodies
+async fn start_endpoint(
+    received: Arc<Mutex<Vec<String>>>,
+) -> Result<SocketAddr, Box<dyn std::error::Error + Send + Sync>> {
+    let make_service = make_service_fn(move |_| {
+        let received = received.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                let received = received.clone();
+                async move {
+                    assert_eq!(
+                        Some("secret"),
+                        request
+                            .headers()
+                            .get("X-Token")
+                            .map(|v| v.to_str().unwrap())
+                    );
+                    let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+                    let mut received = received.lock().unwrap();
+                    received.push(String::from_utf8_lossy(&body).to_string());
+                    let status = if received.len() == 1 { 503 } else { 200 };
+                    Ok::<_, Infallible>(
+                        Response::builder()
+                            .status(status)
+                            .body(Body::empty())
+                            .unwrap(),
+                    )
+                }
+            }))
+        }
+    });
+    let server = Server::try_bind(&SocketAddr::from(([127, 0, 0, 1], 0)))?.serve(make_service);
+    let addr = server.local_addr();
+    tokio::spawn(server);
+    Ok(addr)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_bridge_events_to_webhook() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let pcloud = fake.client().await?;
+    fake.create_folder("/docs")?;
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let addr = start_endpoint(received.clone()).await?;
+
+    let (tx, rx) = mpsc::channel(16);
+    let bridge = EventBridge::http(&format!("http://{}/hook", addr))
+        .http_client(reqwest::Client::builder().no_proxy().build()?)
+        .header("X-Token", "secret")
+        .retry_delay(Duration::from_millis(10))
+        .spawn(rx);
+    let diff = pcloud.get_events().get().await?;
+    for entry in diff.entries {
+        tx.send(entry).await?;
+    }
+    drop(tx);
+
+    let last = tokio::time::timeout(Duration::from_secs(5), bridge).await???;
+    assert_eq!(Some(1), last);
+    let received = received.lock().unwrap();
+    // The first attempt failed and was repeated
+    assert_eq!(2, received.len());
+    assert_eq!(received[0], received[1]);
+    assert!(received[1].contains("\"createfolder\""));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_bridge_gives_up() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let pcloud = fake.client().await?;
+    fake.create_folder("/docs")?;
+
+    let (tx, rx) = mpsc::channel(16);
+    let bridge = EventBridge::http("http://127.0.0.1:1/hook")
+        .http_client(reqwest::Client::builder().no_proxy().build()?)
+        .max_retries(1)
+        .retry_delay(Duration::from_millis(10))
+        .spawn(rx);
+    for entry in pcloud.get_events().get().await?.entries {
+        tx.send(entry).await?;
+    }
+
+    let err = tokio::time::timeout(Duration::from_secs(5), bridge)
+        .await??
+        .unwrap_err();
+    assert_eq!(None, err.last_delivered);
+    assert_eq!(1, err.diffid);
+    Ok(())
+}
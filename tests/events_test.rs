@@ -0,0 +1,336 @@
+use std::{
+    error::Error,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use futures::{future::BoxFuture, StreamExt};
+use pcloud_async_api::{
+    events::{aggregate_stream, ChangeAggregator, ChangeKind, ResyncReason, ResyncRequired},
+    fake_pcloud::FakePCloud,
+    folder_watch::FolderEvent,
+    transport::HttpTransport,
+};
+use reqwest::{Request, Response};
+
+/// Transport failing the first diff call with a connection error and never answering the second one
+struct FlakyTransport {
+    fake: FakePCloud,
+    diff_calls: Arc<AtomicUsize>,
+    /// If set, all diff calls fail
+    always_fail: bool,
+}
+
+impl HttpTransport for FlakyTransport {
+    fn execute<'a>(
+        &'a self,
+        request: Request,
+    ) -> BoxFuture<'a, Result<Response, Box<dyn Error + Send + Sync>>> {
+        Box::pin(async move {
+            if request.url().path().ends_with("/diff") {
+                match self.diff_calls.fetch_add(1, Ordering::SeqCst) {
+                    _ if self.always_fail => Err("Connection reset by peer")?,
+                    0 => Err("Connection reset by peer")?,
+                    1 => futures::future::pending::<()>().await,
+                    _ => {}
+                }
+            }
+            self.fake.execute(request).await
+        })
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_coalesce_changes() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let pcloud = fake.client().await?;
+    fake.put_file("/docs/c.txt", "This is moved content")?;
+
+    let mut aggregator = ChangeAggregator::new();
+    let diff = pcloud.get_events().get().await?;
+    for entry in diff.entries {
+        aggregator.push(entry);
+    }
+    let changes = aggregator.take();
+    assert_eq!(2, changes.created().count());
+    assert!(aggregator.is_empty());
+
+    // Created and modified, created and deleted, moved
+    fake.put_file("/docs/a.txt", "This is")?;
+    fake.put_file("/docs/a.txt", "This is nice test content")?;
+    fake.put_file("/docs/b.txt", "This is temporary content")?;
+    pcloud.delete_file("/docs/b.txt").await?;
+    pcloud.move_file("/docs/c.txt", "/")?.execute().await?;
+
+    let diff = pcloud
+        .get_events()
+        .after_diff_id(changes.diffid.unwrap())
+        .get()
+        .await?;
+    assert_eq!(5, diff.entries.len());
+    for entry in diff.entries {
+        aggregator.push(entry);
+    }
+    let changes = aggregator.take();
+    assert_eq!(2, changes.changes.len());
+    assert_eq!(ChangeKind::Created, changes.changes[0].kind);
+    assert_eq!("a.txt", changes.changes[0].metadata.name);
+    assert_eq!(Some(25), changes.changes[0].metadata.size);
+    assert!(matches!(
+        &changes.changes[1].kind,
+        ChangeKind::Moved { from_name, modified: false, .. } if from_name == "c.txt"
+    ));
+    assert_eq!(Some(0), changes.changes[1].metadata.parentfolderid);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_aggregate_stream() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let pcloud = fake.client().await?;
+    fake.put_file("/a.txt", "This is")?;
+    fake.put_file("/a.txt", "This is nice test content")?;
+    pcloud.delete_file("/a.txt").await?;
+    fake.put_file("/b.txt", "This is other content")?;
+
+    let (tx, rx) = tokio::sync::mpsc::channel(16);
+    let mut changes = aggregate_stream(rx, Duration::from_millis(100));
+    for entry in pcloud.get_events().get().await?.entries {
+        tx.send(entry).await?;
+    }
+
+    let change_set = changes.recv().await.unwrap();
+    assert_eq!(
+        vec!["b.txt"],
+        change_set
+            .created()
+            .map(|m| m.name.as_str())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(Some(4), change_set.diffid);
+
+    // The stream ends with its source
+    drop(tx);
+    assert!(changes.recv().await.is_none());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_replay_with_resync() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let pcloud = fake.client().await?;
+    fake.put_file("/docs/a.txt", "This is nice test content")?;
+
+    let baseline = pcloud.resync_baseline("/docs").await?;
+    assert_eq!(1, baseline.metadata.contents.len());
+    fake.put_file("/docs/b.txt", "This is other content")?;
+    fake.put_file("/docs/c.txt", "This is more content")?;
+
+    // Replayed in batches
+    let diff = pcloud
+        .get_events()
+        .after_diff_id(baseline.diffid)
+        .limit(1)
+        .replay()
+        .await?;
+    assert_eq!(2, diff.entries.len());
+    assert_eq!(baseline.diffid + 2, diff.diffid);
+    let diff = pcloud
+        .get_events()
+        .after_diff_id(diff.diffid)
+        .replay()
+        .await?;
+    assert!(diff.entries.is_empty());
+
+    // Expired history
+    let stored = diff.diffid;
+    fake.put_file("/docs/d.txt", "This is new content")?;
+    fake.expire_events();
+    fake.put_file("/docs/e.txt", "This is newer content")?;
+    let error = pcloud
+        .get_events()
+        .after_diff_id(stored)
+        .replay()
+        .await
+        .unwrap_err();
+    let resync = error.downcast_ref::<ResyncRequired>().unwrap();
+    assert_eq!(
+        ResyncReason::Gap {
+            expected: stored + 1,
+            received: stored + 2
+        },
+        resync.reason
+    );
+
+    // Reset
+    let baseline = pcloud.resync_baseline("/docs").await?;
+    assert_eq!(5, baseline.metadata.contents.len());
+    fake.reset_events();
+    let error = pcloud
+        .get_events()
+        .after_diff_id(baseline.diffid)
+        .replay()
+        .await
+        .unwrap_err();
+    assert_eq!(
+        Some(&ResyncReason::Reset),
+        error.downcast_ref::<ResyncRequired>().map(|r| &r.reason)
+    );
+
+    // Newer than the account
+    let error = pcloud
+        .get_events()
+        .after_diff_id(1_000)
+        .replay()
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<ResyncRequired>().map(|r| &r.reason),
+        Some(ResyncReason::Behind { stored: 1_000, .. })
+    ));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_stream_reconnects() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/docs/a.txt", "This is nice test content")?;
+    let diff_calls = Arc::new(AtomicUsize::new(0));
+    let transport = FlakyTransport {
+        fake: fake.clone(),
+        diff_calls: diff_calls.clone(),
+        always_fail: false,
+    };
+    let pcloud = fake
+        .client_builder()
+        .with_transport(transport)
+        .tcp_keepalive(Duration::from_secs(30))
+        .build()
+        .await?;
+
+    // The stalled call is abandoned after the block timeout (and some grace), the failed one repeated
+    let mut events = pcloud
+        .get_events()
+        .after_diff_id(0)
+        .block_timeout(Duration::from_millis(200))
+        .reconnect_delay(Duration::from_millis(10))
+        .stream();
+    let entry = tokio::time::timeout(Duration::from_secs(5), events.recv()).await?;
+    assert!(entry.is_some());
+    assert!(diff_calls.load(Ordering::SeqCst) >= 3);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_stream_gives_up() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let diff_calls = Arc::new(AtomicUsize::new(0));
+    let transport = FlakyTransport {
+        fake: fake.clone(),
+        diff_calls: diff_calls.clone(),
+        always_fail: true,
+    };
+    let pcloud = fake
+        .client_builder()
+        .with_transport(transport)
+        .build()
+        .await?;
+
+    let mut events = pcloud
+        .get_events()
+        .after_diff_id(0)
+        .max_reconnects(2)
+        .reconnect_delay(Duration::from_millis(10))
+        .stream();
+    let closed = tokio::time::timeout(Duration::from_secs(5), events.recv()).await?;
+    assert!(closed.is_none());
+    assert_eq!(3, diff_calls.load(Ordering::SeqCst));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_diff_stream() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/docs/a.txt", "first")?;
+    let pcloud = fake.client().await?;
+
+    let mut events = pcloud.diff_stream();
+    let first = tokio::time::timeout(Duration::from_secs(5), events.next())
+        .await?
+        .unwrap();
+    // Events created while polling are yielded by the next call
+    fake.put_file("/docs/b.txt", "second")?;
+    let mut names = vec![first.metadata.unwrap().name];
+    while !names.contains(&"b.txt".to_string()) {
+        let entry = tokio::time::timeout(Duration::from_secs(5), events.next())
+            .await?
+            .unwrap();
+        assert!(entry.diffid > first.diffid);
+        if let Some(metadata) = entry.metadata {
+            names.push(metadata.name);
+        }
+    }
+    assert!(names.contains(&"a.txt".to_string()));
+
+    // Continues after a stored diff id
+    let latest = pcloud.get_events().only_last(0).get().await?.diffid;
+    fake.put_file("/docs/c.txt", "third")?;
+    let entries: Vec<_> = pcloud
+        .get_events()
+        .after_diff_id(latest)
+        .into_stream()
+        .take(1)
+        .collect()
+        .await;
+    assert_eq!("c.txt", entries[0].metadata.as_ref().unwrap().name);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_watch_folder() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let pcloud = fake.client().await?;
+    fake.create_folder("/docs/sub")?;
+    fake.put_file("/docs/a.txt", "This is")?;
+    fake.put_file("/other/b.txt", "This is elsewhere")?;
+
+    let events = pcloud.watch_folder("/docs").await?;
+    fake.put_file("/docs/a.txt", "This is nice test content")?;
+    fake.put_file("/other/c.txt", "This is ignored")?;
+    pcloud
+        .move_file("/docs/a.txt", "/docs/sub/")?
+        .execute()
+        .await?;
+    pcloud
+        .move_file("/other/b.txt", "/docs/")?
+        .execute()
+        .await?;
+    fake.put_file("/docs/sub/d.txt", "This is new")?;
+    pcloud.delete_file("/docs/sub/a.txt").await?;
+
+    let events: Vec<FolderEvent> =
+        tokio::time::timeout(Duration::from_secs(5), events.take(5).collect::<Vec<_>>()).await?;
+    let summary: Vec<String> = events
+        .iter()
+        .map(|e| match e {
+            FolderEvent::Created { path, .. } => format!("created {}", path),
+            FolderEvent::Modified { path, .. } => format!("modified {}", path),
+            FolderEvent::Deleted { path, .. } => format!("deleted {}", path),
+            FolderEvent::Moved { from, path, .. } => format!("moved {} {}", from, path),
+        })
+        .collect();
+    assert_eq!(
+        vec![
+            "modified a.txt",
+            "moved a.txt sub/a.txt",
+            "created b.txt",
+            "created sub/d.txt",
+            "deleted sub/a.txt",
+        ],
+        summary
+    );
+    Ok(())
+}
@@ -0,0 +1,1031 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use chrono::{DateTime, TimeZone, Utc};
+use futures::{StreamExt, TryStreamExt};
+use pcloud_async_api::{
+    batch_upload::{UploadFailure, UploadSource},
+    fake_pcloud::FakePCloud,
+    pcloud_client::{CorrelatedError, PCloudClient},
+    pcloud_model::{PCloudResult, UploadedFile},
+};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_file_lifecycle_with_fake_pcloud(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let pcloud = fake.client().await?;
+
+    let folder = pcloud.create_folder("/", "test-folder")?.execute().await?;
+    let folder_id = folder.metadata.unwrap().folderid.unwrap();
+
+    let upload = pcloud
+        .upload_file_into_folder(folder_id)?
+        .with_file("test.txt", "This is nice test content")
+        .upload()
+        .await?;
+    assert_eq!(1, upload.fileids().len());
+    assert_eq!(
+        Some(b"This is nice test content".to_vec()),
+        fake.read_file("/test-folder/test.txt")
+    );
+
+    let listing = pcloud.list_folder("/test-folder")?.get().await?;
+    let contents = listing.metadata.unwrap().contents;
+    assert_eq!(1, contents.len());
+    assert_eq!("test.txt", contents[0].name);
+    assert_eq!(Some(25), contents[0].size);
+
+    let content = pcloud
+        .download_file("/test-folder/test.txt")
+        .await?
+        .text()
+        .await?;
+    assert_eq!("This is nice test content", content);
+
+    pcloud
+        .move_file(upload.fileids()[0], "/test-folder/")?
+        .with_new_name("renamed.txt")
+        .execute()
+        .await?;
+    assert!(!fake.exists("/test-folder/test.txt"));
+    assert!(fake.exists("/test-folder/renamed.txt"));
+
+    let err = pcloud
+        .get_file_metadata("/test-folder/test.txt")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        Some(&PCloudResult::FileNotFound),
+        err.downcast_ref::<PCloudResult>()
+    );
+
+    pcloud.delete_folder(folder_id)?.delete_recursive().await?;
+    assert!(!fake.exists("/test-folder"));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_correlation_id_of_api_error() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    let fake = FakePCloud::start().await?;
+    let pcloud = fake
+        .client_builder()
+        .with_correlation_id("X-Request-Id", || "req-42".to_string())
+        .build()
+        .await?;
+
+    let err = pcloud.get_file_metadata("/missing.txt").await.unwrap_err();
+    assert_eq!(Some("req-42"), err.correlation_id());
+    assert_eq!(Some(&PCloudResult::FileNotFound), err.api_result());
+    let correlated = err.downcast_ref::<CorrelatedError>().unwrap();
+    assert_eq!("req-42", correlated.correlation_id);
+    assert_eq!(
+        Some(&PCloudResult::FileNotFound),
+        err.downcast_ref::<PCloudResult>()
+    );
+
+    let err = pcloud
+        .call::<serde_json::Value>("stat", &[("path", "/missing.txt")])
+        .await
+        .unwrap_err();
+    assert_eq!(Some("req-42"), err.correlation_id());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_public_link_with_fake_pcloud() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/shared/file.txt", "Shared content")?;
+    let pcloud = fake.client().await?;
+
+    let link = pcloud
+        .get_public_link_for_file("/shared/file.txt")?
+        .get()
+        .await?;
+    let download_link = pcloud.get_public_download_link_for_file(&link).await?;
+    let content = pcloud.download_link(&download_link).await?.text().await?;
+    assert_eq!("Shared content", content);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_audio_link_with_fake_pcloud() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    let fake = FakePCloud::start().await?;
+    let id = fake.put_file("/music/song.flac", "Lossless audio")?;
+    let pcloud = fake.client().await?;
+
+    let link = pcloud.get_audio_link_for_file(id)?.get().await?;
+    assert_eq!(Some(format!("/audio/{}/192", id)), link.path);
+    let link = pcloud
+        .get_audio_link_for_file("/music/song.flac")?
+        .bitrate(128)
+        .get()
+        .await?;
+    let content = pcloud.download_link(&link).await?.text().await?;
+    assert!(content.starts_with("audio 128 "));
+
+    let missing = pcloud
+        .get_audio_link_for_file("/music/missing.mp3")?
+        .get()
+        .await;
+    assert_eq!(
+        Some(&PCloudResult::FileNotFound),
+        missing.err().as_ref().and_then(|e| e.api_result())
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_upload_file_from_path() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.create_folder("/test-folder")?;
+    let pcloud = fake.client().await?;
+
+    let local_folder = std::env::temp_dir().join(format!("pcloud-upload-{}", std::process::id()));
+    std::fs::create_dir_all(&local_folder)?;
+    let local_file = local_folder.join("local.txt");
+    std::fs::write(&local_file, "This is nice local content")?;
+    let local_mtime = DateTime::<Utc>::from(std::fs::metadata(&local_file)?.modified()?);
+
+    let upload = pcloud
+        .upload_file_into_folder("/test-folder")?
+        .with_file_from_path(&local_file)
+        .await?
+        .keep_local_mtime(true)
+        .upload()
+        .await?
+        .into_metadata()?;
+    std::fs::remove_dir_all(&local_folder)?;
+
+    assert_eq!("local.txt", upload[0].name);
+    assert_eq!(Some(26), upload[0].size);
+    assert_eq!(local_mtime.timestamp(), upload[0].modified.timestamp());
+    assert_eq!(
+        Some(b"This is nice local content".to_vec()),
+        fake.read_file("/test-folder/local.txt")
+    );
+
+    let missing = pcloud
+        .upload_file_into_folder("/test-folder")?
+        .with_file_from_path(local_folder.join("missing.txt"))
+        .await;
+    assert!(missing.is_err());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_upload_with_small_transfer_chunks(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.create_folder("/test-folder")?;
+    let pcloud = fake.client_builder().transfer_chunk_size(5).build().await?;
+
+    let local_folder = std::env::temp_dir().join(format!("pcloud-chunks-{}", std::process::id()));
+    std::fs::create_dir_all(&local_folder)?;
+    let content = "This is nice local content, read in many small chunks";
+    for i in 0..3 {
+        std::fs::write(local_folder.join(format!("local-{}.txt", i)), content)?;
+    }
+
+    // Uploads running in parallel share the pooled buffers
+    let uploads = (0..3).map(|i| {
+        let pcloud = pcloud.clone();
+        let local_file = local_folder.join(format!("local-{}.txt", i));
+        async move {
+            pcloud
+                .upload_file_into_folder("/test-folder")?
+                .with_file_from_path(local_file)
+                .await?
+                .max_bytes_per_second(1_000_000)
+                .skip_identical(true)
+                .upload()
+                .await
+        }
+    });
+    for upload in futures::future::join_all(uploads).await {
+        assert_eq!(Some(content.len() as u64), upload?.into_metadata()?[0].size);
+    }
+    std::fs::remove_dir_all(&local_folder)?;
+
+    for i in 0..3 {
+        assert_eq!(
+            Some(content.as_bytes().to_vec()),
+            fake.read_file(&format!("/test-folder/local-{}.txt", i))
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_download_reuses_pooled_buffers(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/test-folder/a.txt", "0123456789abcdefghij")?;
+    fake.put_file("/test-folder/b.txt", "klmnopqrstuvwxyz")?;
+    let pcloud = fake.client_builder().transfer_chunk_size(8).build().await?;
+
+    // Each chunk reuses the allocation of the previous one, once that is dropped
+    let (_, stream) = pcloud.download_stream("/test-folder/a.txt").await?;
+    futures::pin_mut!(stream);
+    let first = stream.next().await.unwrap()?;
+    assert_eq!("01234567", first);
+    let address = first.as_ptr();
+    drop(first);
+    let mut chunks = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        assert_eq!(address, chunk.as_ptr());
+        chunks.push(String::from_utf8(chunk.to_vec())?);
+    }
+    assert_eq!(vec!["89abcdef", "ghij"], chunks);
+
+    // Chunks still held keep their content, the next one gets a new allocation
+    let (_, stream) = pcloud.download_stream("/test-folder/b.txt").await?;
+    let chunks: Vec<_> = stream.try_collect().await?;
+    assert_eq!(vec!["klmnopqr", "stuvwxyz"], chunks);
+    assert_eq!(address, chunks[0].as_ptr());
+    assert_ne!(address, chunks[1].as_ptr());
+
+    Ok(())
+}
+
+/// Reader counting the sources currently open
+struct TrackedReader {
+    content: std::io::Cursor<Vec<u8>>,
+    open: Arc<AtomicUsize>,
+}
+
+impl tokio::io::AsyncRead for TrackedReader {
+    fn poll_read(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::pin::Pin::new(&mut self.content).poll_read(cx, buf)
+    }
+}
+
+impl Drop for TrackedReader {
+    fn drop(&mut self) {
+        self.open.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_upload_parts_opened_lazily() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.create_folder("/test-folder")?;
+    let pcloud = fake.client().await?;
+
+    let opened = Arc::new(AtomicUsize::new(0));
+    let open = Arc::new(AtomicUsize::new(0));
+    let max_open = Arc::new(AtomicUsize::new(0));
+    let mut request = pcloud.upload_file_into_folder("/test-folder")?;
+    for i in 0..50 {
+        let (opened, open, max_open) = (opened.clone(), open.clone(), max_open.clone());
+        let content = format!("This is lazy content {}", i).into_bytes();
+        request = request.with_file_from_source(
+            &format!("lazy-{}.txt", i),
+            Some(content.len() as u64),
+            move || {
+                opened.fetch_add(1, Ordering::SeqCst);
+                let now_open = open.fetch_add(1, Ordering::SeqCst) + 1;
+                max_open.fetch_max(now_open, Ordering::SeqCst);
+                let reader = TrackedReader {
+                    content: std::io::Cursor::new(content.clone()),
+                    open: open.clone(),
+                };
+                async move { Ok(reader) }
+            },
+        );
+    }
+    assert_eq!(0, opened.load(Ordering::SeqCst));
+
+    let upload = request.upload().await?;
+    assert_eq!(50, upload.outcomes.len());
+    assert!(upload.is_success());
+    assert_eq!(50, opened.load(Ordering::SeqCst));
+    // Parts are streamed one after the other
+    assert_eq!(1, max_open.load(Ordering::SeqCst));
+    assert_eq!(0, open.load(Ordering::SeqCst));
+    assert_eq!(
+        Some(b"This is lazy content 42".to_vec()),
+        fake.read_file("/test-folder/lazy-42.txt")
+    );
+
+    // Directories are rejected right away
+    let directory = pcloud
+        .upload_file_into_folder("/test-folder")?
+        .with_file_from_path(std::env::temp_dir())
+        .await;
+    assert!(directory.is_err());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_upload_file_from_bytes() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.create_folder("/test-folder")?;
+    let pcloud = fake.client().await?;
+
+    let content = bytes::Bytes::from_static(b"This is nice shared content");
+    let upload = pcloud
+        .upload_file_into_folder("/test-folder")?
+        .with_file_from_bytes("shared.txt", content.clone())
+        .with_file_from_bytes("throttled.txt", content.slice(8..))
+        .max_bytes_per_second(1_000_000)
+        .upload()
+        .await?
+        .into_metadata()?;
+
+    assert_eq!(Some(27), upload[0].size);
+    assert_eq!(Some(19), upload[1].size);
+    assert_eq!(
+        Some(content.to_vec()),
+        fake.read_file("/test-folder/shared.txt")
+    );
+    assert_eq!(
+        Some(b"nice shared content".to_vec()),
+        fake.read_file("/test-folder/throttled.txt")
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "mmap")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_upload_file_mmapped() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.create_folder("/test-folder")?;
+    let pcloud = fake.client().await?;
+
+    let local_folder = std::env::temp_dir().join(format!("pcloud-mmap-{}", std::process::id()));
+    std::fs::create_dir_all(&local_folder)?;
+    let local_file = local_folder.join("mapped.txt");
+    std::fs::write(&local_file, "This is nice mapped content")?;
+    let empty_file = local_folder.join("empty.txt");
+    std::fs::write(&empty_file, "")?;
+
+    let upload = pcloud
+        .upload_file_into_folder("/test-folder")?
+        .with_file_mmapped(&local_file)
+        .await?
+        .with_file_mmapped(&empty_file)
+        .await?
+        .upload()
+        .await?
+        .into_metadata()?;
+    std::fs::remove_dir_all(&local_folder)?;
+
+    assert_eq!("mapped.txt", upload[0].name);
+    assert_eq!(Some(0), upload[1].size);
+    assert_eq!(
+        Some(b"This is nice mapped content".to_vec()),
+        fake.read_file("/test-folder/mapped.txt")
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_upload_file_names_and_content_type(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.create_folder("/test-folder")?;
+    let pcloud = fake.client().await?;
+
+    for invalid in ["", "..", "sub/test.txt", "sub\\test.txt", "line\nbreak.txt"] {
+        let error = pcloud
+            .upload_file_into_folder("/test-folder")?
+            .with_file("valid.txt", "This is nice test content")
+            .with_file(invalid, "This is nice test content")
+            .upload()
+            .await
+            .unwrap_err();
+        assert_eq!(
+            Some(&PCloudResult::InvalidFileOrFolderName),
+            error.downcast_ref::<PCloudResult>()
+        );
+    }
+    // Nothing is uploaded if a single name is invalid
+    assert!(!fake.exists("/test-folder/valid.txt"));
+
+    let upload = pcloud
+        .upload_file_into_folder("/test-folder")?
+        .with_file_and_content_type("image.png", vec![0x89, 0x50, 0x4e, 0x47], "image/png")
+        .with_file("Grüße 100%.txt", "This is nice test content")
+        .percent_encode_file_names(true)
+        .upload()
+        .await?;
+    assert_eq!(2, upload.fileids().len());
+    assert!(fake.exists("/test-folder/image.png"));
+    assert!(fake.exists("/test-folder/Gr%C3%BC%C3%9Fe 100%25.txt"));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_upload_speed_limit() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.create_folder("/test-folder")?;
+    let pcloud = fake.client().await?;
+
+    let content: Vec<u8> = (0..4000).map(|i| (i % 256) as u8).collect();
+    let start = std::time::Instant::now();
+    pcloud
+        .upload_file_into_folder("/test-folder")?
+        .with_file("throttled.bin", content.clone())
+        .max_bytes_per_second(8000)
+        .upload()
+        .await?;
+    // 4000 bytes at 8000 bytes/s in slices of 800 bytes: The last slice is due after 400 ms
+    assert!(start.elapsed() >= std::time::Duration::from_millis(350));
+    assert_eq!(Some(content), fake.read_file("/test-folder/throttled.bin"));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_upload_deduplication() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/backup/monday/unchanged.txt", "This is unchanged content")?;
+    fake.put_file("/backup/monday/changed.txt", "This is old content")?;
+    fake.put_file("/backup/tuesday/present.txt", "This is present content")?;
+    let pcloud = fake.client().await?;
+
+    let upload = pcloud
+        .upload_file_into_folder("/backup/tuesday")?
+        .with_file("unchanged.txt", "This is unchanged content")
+        .with_file("changed.txt", "This is new content")
+        .with_file("present.txt", "This is present content")
+        .skip_identical(true)
+        .copy_identical_from("/backup/monday")?
+        .upload()
+        .await?;
+
+    assert_eq!(3, upload.fileids().len());
+    let names: Vec<&str> = upload.uploaded().map(|(name, _)| name).collect();
+    assert_eq!(vec!["unchanged.txt", "changed.txt", "present.txt"], names);
+
+    let report = pcloud
+        .upload_file_into_folder("/backup/tuesday")?
+        .with_file("present.txt", "This is present content")
+        .with_file("new.txt", "This is new content")
+        .with_file("rename.txt", "This is renamed content")
+        .with_file("rename.txt", "This is renamed content too")
+        .rename_if_exists(true)
+        .skip_identical(true)
+        .upload()
+        .await?;
+    assert!(report.is_success());
+    let uploaded: Vec<(&str, &str)> = report
+        .uploaded()
+        .map(|(requested, metadata)| (requested, metadata.name.as_str()))
+        .collect();
+    assert_eq!(
+        vec![
+            ("present.txt", "present.txt"),
+            ("new.txt", "new.txt"),
+            ("rename.txt", "rename.txt"),
+            ("rename.txt", "rename (1).txt")
+        ],
+        uploaded
+    );
+    assert_eq!(
+        Some(b"This is unchanged content".to_vec()),
+        fake.read_file("/backup/tuesday/unchanged.txt")
+    );
+    assert_eq!(
+        Some(b"This is new content".to_vec()),
+        fake.read_file("/backup/tuesday/changed.txt")
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_upload_files() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.create_folder("/test-folder")?;
+    let pcloud = fake.client().await?;
+
+    let local_folder = std::env::temp_dir().join(format!("pcloud-batch-{}", std::process::id()));
+    std::fs::create_dir_all(&local_folder)?;
+    let local_file = local_folder.join("local.txt");
+    std::fs::write(&local_file, "This is nice local content")?;
+
+    let sources = vec![
+        UploadSource::from(local_file.clone()),
+        UploadSource::from(("first.txt", "This is the first content")),
+        UploadSource::from(("invalid/name.txt", "This is invalid")),
+        UploadSource::from(("second.txt", "This is the second content")),
+    ];
+
+    let report = pcloud
+        .upload_files("/test-folder", sources.clone())?
+        .concurrency(2)
+        .upload()
+        .await?;
+    assert!(!report.is_success());
+    assert_eq!(3, report.fileids().len());
+    // Outcomes in the order of the sources
+    let names: Vec<&str> = report
+        .outcomes
+        .iter()
+        .map(|o| o.file_name.as_str())
+        .collect();
+    assert_eq!(
+        vec!["local.txt", "first.txt", "invalid/name.txt", "second.txt"],
+        names
+    );
+    assert_eq!(
+        "second.txt",
+        report.outcomes[3].result.as_ref().unwrap().name
+    );
+    let failures: Vec<&str> = report.failures().map(|(name, _)| name).collect();
+    assert_eq!(vec!["invalid/name.txt"], failures);
+    assert_eq!(
+        Some(b"This is nice local content".to_vec()),
+        fake.read_file("/test-folder/local.txt")
+    );
+    assert!(fake.exists("/test-folder/first.txt"));
+    assert!(fake.exists("/test-folder/second.txt"));
+
+    let error = pcloud
+        .upload_files("/test-folder", sources)?
+        .concurrency(1)
+        .fail_fast(true)
+        .upload()
+        .await
+        .unwrap_err();
+    std::fs::remove_dir_all(&local_folder)?;
+    let failure = error.downcast_ref::<UploadFailure>().unwrap();
+    assert_eq!("invalid/name.txt", failure.file_name);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_set_file_times() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let fileid = fake.put_file("/test-folder/test.txt", "This is nice test content")?;
+    let pcloud = fake.client().await?;
+
+    let mtime = Utc.with_ymd_and_hms(2020, 5, 17, 8, 30, 0).unwrap();
+    let ctime = Utc.with_ymd_and_hms(2019, 1, 2, 3, 4, 5).unwrap();
+    let result = pcloud
+        .set_file_times("/test-folder/test.txt", &mtime, Some(&ctime))
+        .await?;
+    let metadata = result.metadata.unwrap();
+    assert_eq!("test.txt", metadata.name);
+    assert_eq!(Some(fileid), metadata.fileid);
+    assert_eq!(mtime, metadata.modified);
+    assert_eq!(ctime, metadata.created);
+
+    let listing = pcloud.list_folder("/test-folder")?.get().await?;
+    let contents = listing.metadata.unwrap().contents;
+    assert_eq!(1, contents.len());
+    assert_eq!(Some(fileid), contents[0].fileid);
+    assert_eq!(mtime, contents[0].modified);
+    assert_eq!(
+        Some(b"This is nice test content".to_vec()),
+        fake.read_file("/test-folder/test.txt")
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_read_range() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/test-folder/test.txt", "This is nice test content")?;
+    let pcloud = fake.client().await?;
+
+    let range = pcloud.read_range("/test-folder/test.txt", 8, 4).await?;
+    assert_eq!(&b"nice"[..], &range[..]);
+
+    // Ranges exceeding the end of the file are truncated
+    let range = pcloud.read_range("/test-folder/test.txt", 18, 100).await?;
+    assert_eq!(&b"content"[..], &range[..]);
+    let range = pcloud.read_range("/test-folder/test.txt", 100, 10).await?;
+    assert!(range.is_empty());
+
+    // Lengths beyond the largest offset read to the end of the file
+    let range = pcloud
+        .read_range("/test-folder/test.txt", 18, u64::MAX)
+        .await?;
+    assert_eq!(&b"content"[..], &range[..]);
+    let range = pcloud
+        .read_range("/test-folder/test.txt", u64::MAX, u64::MAX)
+        .await?;
+    assert!(range.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_download_public_folder_link() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/photos/a.txt", "This is nice test content")?;
+    fake.put_file("/photos/2023/b.txt", "This is other content")?;
+    fake.put_file("/private/c.txt", "This is secret content")?;
+    let pcloud = fake.client().await?;
+    let link = pcloud
+        .call_json("getfolderpublink", &[("path", "/photos")])
+        .await?;
+    let code = link["code"].as_str().unwrap().to_string();
+
+    // Without any authentication
+    let anonymous = PCloudClient::builder("https://api.pcloud.com")
+        .select_best_api_server(false)
+        .with_transport(fake.clone())
+        .build()
+        .await?;
+
+    let local = std::env::temp_dir().join(format!("pcloud-publink-{}", std::process::id()));
+    let progress = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let reported = progress.clone();
+    let report = anonymous
+        .download_public_link(&code, &local)
+        .concurrency(2)
+        .on_progress(move |p| reported.lock().unwrap().push(p.clone()))
+        .execute()
+        .await?;
+
+    assert_eq!(vec!["2023/b.txt", "a.txt"], report.files);
+    assert_eq!(46, report.bytes_downloaded);
+    assert_eq!(
+        "This is other content",
+        std::fs::read_to_string(local.join("2023").join("b.txt"))?
+    );
+    assert!(!local.join("c.txt").exists());
+
+    let progress = progress.lock().unwrap().clone();
+    assert_eq!(2, progress.len());
+    let last = progress.iter().max_by_key(|p| p.files_completed).unwrap();
+    assert_eq!((2, 2), (last.files_completed, last.files_total));
+    assert_eq!(46, last.bytes_total);
+
+    assert!(anonymous
+        .download_public_link("invalid", &local)
+        .execute()
+        .await
+        .is_err());
+
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_get_download_links() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    for i in 0..5 {
+        fake.put_file(&format!("/gallery/{}.jpg", i), format!("image {}", i))?;
+    }
+    let pcloud = fake.client().await?;
+
+    let listing = pcloud
+        .list_folder("/gallery")?
+        .get()
+        .await?
+        .metadata
+        .unwrap();
+    let file_ids: Vec<u64> = listing.contents.iter().filter_map(|m| m.fileid).collect();
+    let links = pcloud
+        .get_download_links(file_ids.iter().copied().chain([4711]))
+        .concurrency(2)
+        .get()
+        .await;
+
+    assert_eq!(6, links.len());
+    for file_id in &file_ids {
+        let link = links[file_id].as_ref().unwrap();
+        assert!(link
+            .into_url()
+            .unwrap()
+            .ends_with(&format!("/dl/{}", file_id)));
+    }
+    let error = links[&4711].as_ref().err().unwrap();
+    assert_eq!(
+        Some(&PCloudResult::FileNotFound),
+        error.downcast_ref::<PCloudResult>()
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_delete_folder_contents() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/inbox/a.txt", "first")?;
+    fake.put_file("/inbox/2023/b.txt", "second")?;
+    fake.put_file("/inbox/2023/old/c.txt", "third")?;
+    fake.create_folder("/inbox/empty")?;
+    fake.put_file("/other/d.txt", "untouched")?;
+    let pcloud = fake.client().await?;
+
+    let deleted = pcloud.delete_folder_contents("/inbox").await?;
+    assert_eq!(Some(3), deleted.deletedfiles);
+    assert_eq!(Some(3), deleted.deletedfolders);
+
+    let listing = pcloud.list_folder("/inbox")?.get().await?.metadata.unwrap();
+    assert!(listing.contents.is_empty());
+    assert!(fake.exists("/other/d.txt"));
+
+    // An empty folder stays as is
+    let deleted = pcloud.delete_folder_contents("/inbox").await?;
+    assert_eq!(Some(0), deleted.deletedfiles);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_copy_folder_with_progress() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/photos/a.jpg", "12345")?;
+    fake.put_file("/photos/2023/b.jpg", "1234567890")?;
+    fake.put_file("/backup/photos/a.jpg", "12345")?;
+    let pcloud = fake.client().await?;
+
+    let reported = Arc::new(Mutex::new(Vec::new()));
+    let progress = reported.clone();
+    pcloud
+        .copy_folder("/photos", "/backup/")?
+        .on_progress(move |p| progress.lock().unwrap().push(p.clone()))
+        .poll_interval(Duration::from_millis(10))
+        .execute()
+        .await?;
+
+    assert!(fake.exists("/backup/photos/2023/b.jpg"));
+    let reported = reported.lock().unwrap();
+    // Files already in the destination don't count as copied
+    assert_eq!(0, reported.first().unwrap().files_copied);
+    let last = reported.last().unwrap();
+    assert_eq!(2, last.files_total);
+    assert_eq!(15, last.bytes_total);
+    assert_eq!(2, last.files_copied);
+    assert_eq!(15, last.bytes_copied);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_copy_folder_content_only() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/photos/a.jpg", "12345")?;
+    fake.create_folder("/backup")?;
+    let pcloud = fake.client().await?;
+
+    pcloud
+        .copy_folder("/photos", "/backup/")?
+        .copy_content_only(true)
+        .execute()
+        .await?;
+    assert!(fake.exists("/backup/a.jpg"));
+    assert!(!fake.exists("/backup/photos/a.jpg"));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_folder_listing() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/docs/a.txt", "a")?;
+    fake.put_file("/docs/b.txt", "b")?;
+    fake.put_file("/docs/drafts/c.txt", "c")?;
+    let pcloud = fake.client().await?;
+
+    let listing = pcloud.list_folder("/docs")?.listing().await?;
+    assert_eq!("docs", listing.folder().name);
+    assert!(listing.folder().contents.is_empty());
+    assert_eq!(2, listing.file_count());
+    assert_eq!(1, listing.folder_count());
+    assert!(listing.files().iter().all(|f| !f.isfolder));
+    assert_eq!("drafts", listing.folders()[0].name);
+    // Contents of subfolders only come with recursive listings
+    assert!(listing.folders()[0].contents.is_empty());
+
+    let listing = pcloud
+        .list_folder("/docs")?
+        .recursive(true)
+        .listing()
+        .await?;
+    assert_eq!(1, listing.folders()[0].contents.len());
+
+    let empty = pcloud
+        .list_folder("/docs/drafts")?
+        .nofiles(true)
+        .listing()
+        .await?;
+    assert!(empty.is_empty());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_lazy_folder_listing() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/docs/a.txt", "a")?;
+    fake.put_file("/docs/drafts/c.txt", "c")?;
+    fake.create_folder("/empty")?;
+    let pcloud = fake.client().await?;
+
+    let lazy = pcloud
+        .list_folder("/docs")?
+        .recursive(true)
+        .get_lazy()
+        .await?
+        .metadata
+        .unwrap();
+    assert_eq!("docs", lazy.metadata.name);
+    assert!(lazy.metadata.contents.is_empty());
+    assert!(lazy.has_contents());
+    let contents = lazy.contents()?;
+    assert_eq!(2, contents.len());
+    let drafts = contents.iter().find(|m| m.isfolder).unwrap();
+    assert_eq!(1, drafts.contents.len());
+
+    let full = lazy.into_metadata()?;
+    let expected = pcloud
+        .list_folder("/docs")?
+        .recursive(true)
+        .get()
+        .await?
+        .metadata
+        .unwrap();
+    assert_eq!(expected.folderid, full.folderid);
+    assert_eq!(expected.contents.len(), full.contents.len());
+
+    let empty = pcloud
+        .list_folder("/empty")?
+        .get_lazy()
+        .await?
+        .metadata
+        .unwrap();
+    assert!(!empty.has_contents());
+    assert!(empty.contents()?.is_empty());
+
+    let metadata = pcloud.list_folder("/docs")?.folder_metadata().await?;
+    assert!(metadata.isfolder);
+    assert_eq!(Some("/docs".to_string()), metadata.path);
+    assert!(pcloud
+        .list_folder("/missing")?
+        .folder_metadata()
+        .await
+        .is_err());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_revision_list_accessors() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let id = fake.put_file("/notes.txt", "first")?;
+    // pCloud dates have a resolution of seconds
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    fake.put_file("/notes.txt", "second")?;
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    fake.put_file("/notes.txt", "third")?;
+    let pcloud = fake.client().await?;
+
+    let mut revisions = pcloud.list_file_revisions(id).await?;
+    let oldest = revisions.oldest().unwrap();
+    let latest = revisions.latest().unwrap();
+    assert!(oldest.created < latest.created);
+    assert_eq!(5, oldest.size);
+    assert_eq!(6, latest.size);
+    assert_eq!(
+        oldest.revisionid,
+        revisions.find_by_hash(oldest.hash).unwrap().revisionid
+    );
+    assert!(revisions.find_by_hash(0).is_none());
+
+    let content = pcloud.download_revision(id, oldest).await?.text().await?;
+    assert_eq!("first", content);
+
+    revisions.sort_by_date();
+    assert_eq!(5, revisions.revisions[0].size);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_uploaded_file_pairs() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let pcloud = fake.client().await?;
+
+    let report = pcloud
+        .upload_file_into_folder("/")?
+        .with_file("a.txt", "first")
+        .with_file("b.txt", "second")
+        .upload()
+        .await?;
+    // Raw result as returned by pCloud
+    let mut upload = UploadedFile {
+        result: PCloudResult::Ok,
+        fileids: report.fileids(),
+        metadata: report.into_metadata()?,
+    };
+    let files: Vec<_> = upload
+        .files()?
+        .map(|(id, m)| (id, m.name.clone()))
+        .collect();
+    assert_eq!(2, files.len());
+    assert_eq!("a.txt", files[0].1);
+    let stat = pcloud.get_file_metadata("/a.txt").await?;
+    assert_eq!(Some(files[0].0), stat.metadata.and_then(|m| m.fileid));
+
+    let (id, metadata) = upload.find_by_name("b.txt").unwrap();
+    assert_eq!(Some(6), metadata.size);
+    assert_eq!(files[1].0, id);
+    assert!(upload.find_by_name("c.txt").is_none());
+
+    // Ids and metadata not lining up are rejected
+    upload.fileids.swap(0, 1);
+    assert!(upload.files().is_err());
+    upload.fileids.pop();
+    assert!(upload.files().is_err());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_upload_report_pairs() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let pcloud = fake.client().await?;
+
+    let upload = pcloud
+        .upload_file_into_folder("/")?
+        .with_file("a.txt", "first")
+        .with_file("b.txt", "second")
+        .with_file("b.txt", "second again")
+        .rename_if_exists(true)
+        .upload()
+        .await?;
+    let fileids = upload.fileids();
+    assert_eq!(3, fileids.len());
+    let names: Vec<_> = upload.files().map(|(_, m)| m.name.as_str()).collect();
+    assert_eq!(vec!["a.txt", "b.txt", "b (1).txt"], names);
+    let stat = pcloud.get_file_metadata("/a.txt").await?;
+    assert_eq!(Some(fileids[0]), stat.metadata.and_then(|m| m.fileid));
+
+    // Found by the requested name as well as by the name after renaming
+    let metadata = upload.find_by_name("b.txt").unwrap();
+    assert_eq!(Some(6), metadata.size);
+    assert_eq!(Some(fileids[1]), metadata.fileid);
+    let renamed = upload.find_by_name("b (1).txt").unwrap();
+    assert_eq!(Some(fileids[2]), renamed.fileid);
+    assert!(upload.find_by_name("c.txt").is_none());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_parent_navigation() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/photos/2023/beach.jpg", "jpg")?;
+    let pcloud = fake.client().await?;
+
+    let file = pcloud
+        .get_file_metadata("/photos/2023/beach.jpg")
+        .await?
+        .metadata
+        .unwrap();
+    let year = pcloud.parent_of(&file).await?.unwrap();
+    assert_eq!("2023", year.name);
+    let photos = year.parent(&pcloud).await?.unwrap();
+    assert_eq!("photos", photos.name);
+    assert_eq!(1, photos.contents.len());
+
+    let root = photos.parent(&pcloud).await?.unwrap();
+    assert_eq!(Some(0), root.folderid);
+    assert!(root.parent(&pcloud).await?.is_none());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_resolve_path() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let file_id = fake.put_file("/photos/2023/beach.jpg", "jpg")?;
+    let folder_id = fake.create_folder("/photos/2023")?;
+    let pcloud = fake.client().await?;
+
+    let path = pcloud.resolve_path(&format!("f{}", file_id)).await?;
+    assert_eq!("/photos/2023/beach.jpg", path);
+    assert_eq!("/", pcloud.resolve_path("d0").await?);
+    assert!(pcloud.resolve_path("x1").await.is_err());
+
+    // Folders resolved once are cached
+    let resolver = pcloud.path_resolver();
+    assert_eq!("/photos/2023", resolver.resolve_folder(folder_id).await?);
+    pcloud.delete_folder("/photos")?.delete_recursive().await?;
+    assert_eq!("/photos/2023", resolver.resolve_folder(folder_id).await?);
+    resolver.clear();
+    assert!(resolver.resolve_folder(folder_id).await.is_err());
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use std::io::SeekFrom;
+
+use pcloud_async_api::{fake_pcloud::FakePCloud, low_level_file::PCloudFileFlag};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_copy_into_handle() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.create_folder("/docs")?;
+    let pcloud = fake.client().await?;
+
+    let content: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+    let mut handle = pcloud
+        .open_file()
+        .by_file_path("/docs/data.bin")
+        .with_flag(PCloudFileFlag::CREATE)
+        .open()
+        .await?
+        .into_handle()
+        .buffer_size(30_000);
+    let copied = tokio::io::copy(&mut content.as_slice(), &mut handle).await?;
+    assert_eq!(100_000, copied);
+    handle.flush().await?;
+    assert_eq!(content, fake.read_file("/docs/data.bin").unwrap());
+
+    // Read back from the start and the end
+    assert_eq!(0, handle.rewind().await?);
+    let mut read = Vec::new();
+    handle.read_to_end(&mut read).await?;
+    assert_eq!(content, read);
+    assert_eq!(99_990, handle.seek(SeekFrom::End(-10)).await?);
+    let mut tail = Vec::new();
+    handle.read_to_end(&mut tail).await?;
+    assert_eq!(&content[99_990..], tail.as_slice());
+    assert!(handle.seek(SeekFrom::Current(-200_000)).await.is_err());
+
+    handle.close().await?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_edit_with_handle() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/docs/notes.txt", "first\nsecond\nthird\n")?;
+    let pcloud = fake.client().await?;
+
+    let mut handle = pcloud
+        .open_file()
+        .by_file_path("/docs/notes.txt")
+        .open()
+        .await?
+        .into_handle();
+
+    // Overwrite in the middle, the written data is read back before being flushed explicitly
+    handle.seek(SeekFrom::Start(6)).await?;
+    handle.write_all(b"SECOND").await?;
+    assert_eq!(12, handle.position());
+    handle.rewind().await?;
+    let mut lines = BufReader::new(&mut handle).lines();
+    let mut read = Vec::new();
+    while let Some(line) = lines.next_line().await? {
+        read.push(line);
+    }
+    assert_eq!(vec!["first", "SECOND", "third"], read);
+
+    // Appended at the end
+    handle.seek(SeekFrom::End(0)).await?;
+    handle.write_all(b"fourth\n").await?;
+    handle.shutdown().await?;
+    handle.close().await?;
+    assert_eq!(
+        b"first\nSECOND\nthird\nfourth\n".to_vec(),
+        fake.read_file("/docs/notes.txt").unwrap()
+    );
+    Ok(())
+}
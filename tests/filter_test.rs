@@ -0,0 +1,98 @@
+use pcloud_async_api::{fake_pcloud::FakePCloud, filter::SyncFilter, sync::SyncAction};
+
+#[test]
+fn test_filter_rules() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let filter = SyncFilter::parse(
+        "# Build output\n\
+         /target/\n\
+         node_modules/\n\
+         *.tmp\n\
+         !keep.tmp\n\
+         docs/**/draft-?.md\n\
+         [Tt]humbs.db\n\
+         @max-size 1K\n\
+         @exclude-extensions bak\n",
+    )?;
+
+    assert!(filter.is_excluded("target/debug/app", None));
+    assert!(!filter.is_excluded("src/target/app", None));
+    assert!(filter.is_excluded("web/node_modules/lib/index.js", None));
+    assert!(filter.is_folder_excluded("web/node_modules"));
+    assert!(filter.is_excluded("a/b/file.tmp", None));
+    assert!(!filter.is_excluded("a/b/keep.tmp", None));
+    assert!(filter.is_excluded("docs/draft-1.md", None));
+    assert!(filter.is_excluded("docs/2023/05/draft-2.md", None));
+    assert!(!filter.is_excluded("docs/draft-10.md", None));
+    assert!(filter.is_excluded("photos/thumbs.db", None));
+    assert!(filter.is_excluded("notes.BAK", None));
+    assert!(filter.is_excluded("video.mp4", Some(2048)));
+    assert!(!filter.is_excluded("video.mp4", Some(1024)));
+    assert!(!filter.is_excluded("video.mp4", None));
+
+    let filter = SyncFilter::new().extensions(["jpg", ".PNG"]).min_size(10);
+    assert!(!filter.is_excluded("a/photo.JPG", Some(10)));
+    assert!(!filter.is_excluded("a/photo.png", None));
+    assert!(filter.is_excluded("a/photo.png", Some(9)));
+    assert!(filter.is_excluded("a/notes.txt", None));
+    assert!(filter.is_excluded("a/README", None));
+
+    let error = SyncFilter::parse("*.tmp\n@max-size lots\n").unwrap_err();
+    assert_eq!(2, error.line);
+    assert!(SyncFilter::new().is_empty());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_filtered_sync_and_download() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/docs/a.txt", "This is nice test content")?;
+    fake.put_file("/docs/cache/b.txt", "This is cached content")?;
+    fake.put_file("/docs/c.tmp", "This is temporary content")?;
+    let pcloud = fake.client().await?;
+
+    let local = std::env::temp_dir().join(format!("pcloud-filter-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&local);
+    std::fs::create_dir_all(local.join("cache"))?;
+    std::fs::write(local.join(".pcloudignore"), "cache/\n*.tmp\n")?;
+    std::fs::write(local.join("cache").join("d.txt"), "This is local cache")?;
+    std::fs::write(local.join("e.tmp"), "This is local temporary content")?;
+
+    let report = pcloud.sync_folder("/docs", &local)?.execute().await?;
+    let actions: Vec<(SyncAction, &str)> = report
+        .operations
+        .iter()
+        .map(|o| (o.action, o.path.as_str()))
+        .collect();
+    assert_eq!(
+        vec![
+            (SyncAction::Upload, ".pcloudignore"),
+            (SyncAction::Download, "a.txt"),
+        ],
+        actions
+    );
+    assert_eq!(3, report.excluded);
+    assert!(!fake.exists("/docs/cache/d.txt"));
+    assert!(!fake.exists("/docs/e.tmp"));
+    assert!(!local.join("c.tmp").exists());
+
+    // Excluded files are never deleted
+    std::fs::remove_file(local.join("e.tmp"))?;
+    let report = pcloud.sync_folder("/docs", &local)?.execute().await?;
+    assert!(!report.has_changes());
+    assert!(fake.exists("/docs/c.tmp"));
+
+    // An explicit filter replaces the ignore file
+    let target = local.join("download");
+    let report = pcloud
+        .download_directory("/docs", &target)?
+        .filter(SyncFilter::new().exclude("*.txt"))
+        .execute()
+        .await?;
+    assert_eq!(2, report.downloaded);
+    assert_eq!(2, report.excluded);
+    assert!(target.join("c.tmp").exists());
+    assert!(!target.join("a.txt").exists());
+
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use pcloud_async_api::{
+    fake_pcloud::FakePCloud,
+    guard::{GuardRails, GuardReason, GuardViolation},
+};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_guard_rails_limits() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/big/a.bin", vec![0u8; 600])?;
+    fake.put_file("/big/sub/b.bin", vec![0u8; 600])?;
+    fake.put_file("/many/1.txt", "1")?;
+    fake.put_file("/many/2.txt", "2")?;
+    fake.put_file("/many/3.txt", "3")?;
+    fake.put_file("/many/4.txt", "4")?;
+    fake.put_file("/small/c.txt", "c")?;
+    let pcloud = fake
+        .client_builder()
+        .guard_rails(
+            GuardRails::new()
+                .max_folder_size(1000)
+                .max_folder_entries(3),
+        )
+        .build()
+        .await?;
+
+    let err = pcloud
+        .delete_folder(0)?
+        .delete_recursive()
+        .await
+        .unwrap_err();
+    let violation = err.downcast_ref::<GuardViolation>().unwrap();
+    assert_eq!(GuardReason::RootFolder, violation.reason);
+    assert_eq!("/", violation.path);
+
+    let err = pcloud.delete_folder_contents("/").await.unwrap_err();
+    assert!(err.downcast_ref::<GuardViolation>().is_some());
+
+    let err = pcloud
+        .delete_folder("/big")?
+        .delete_recursive()
+        .await
+        .unwrap_err();
+    let violation = err.downcast_ref::<GuardViolation>().unwrap();
+    assert_eq!("delete", violation.operation);
+    assert_eq!(
+        GuardReason::FolderTooLarge {
+            bytes: 1200,
+            limit: 1000
+        },
+        violation.reason
+    );
+
+    let err = pcloud
+        .move_folder("/many", "/small/")?
+        .execute()
+        .await
+        .unwrap_err();
+    let violation = err.downcast_ref::<GuardViolation>().unwrap();
+    assert_eq!("move", violation.operation);
+    assert_eq!(
+        GuardReason::TooManyEntries {
+            entries: 4,
+            limit: 3
+        },
+        violation.reason
+    );
+    assert!(fake.exists("/many/1.txt"));
+
+    pcloud.delete_folder("/small")?.delete_recursive().await?;
+    assert!(!fake.exists("/small"));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_guard_rails_allowed_base() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let inside = fake.put_file("/work/tmp/a.txt", "a")?;
+    fake.put_file("/work/b.txt", "b")?;
+    let outside = fake.put_file("/photos/c.jpg", "c")?;
+    let photos = fake.create_folder("/photos/2023")?;
+    let pcloud = fake
+        .client_builder()
+        .guard_rails(GuardRails::new().allowed_base("/work"))
+        .build()
+        .await?;
+
+    // Files and folders given by id are resolved to their path
+    let err = pcloud.delete_file(outside).await.unwrap_err();
+    let violation = err.downcast_ref::<GuardViolation>().unwrap();
+    assert_eq!("/photos/c.jpg", violation.path);
+    assert_eq!(
+        GuardReason::OutsideAllowedBase {
+            base: "/work".to_string()
+        },
+        violation.reason
+    );
+    let err = pcloud
+        .delete_folder(photos)?
+        .delete_recursive()
+        .await
+        .unwrap_err();
+    assert_eq!(
+        "/photos/2023",
+        err.downcast_ref::<GuardViolation>().unwrap().path
+    );
+    assert!(fake.exists("/photos/c.jpg"));
+
+    // The base itself is protected, moves only stay within it
+    let err = pcloud
+        .delete_folder("/work")?
+        .delete_recursive()
+        .await
+        .unwrap_err();
+    assert!(err.downcast_ref::<GuardViolation>().is_some());
+    let err = pcloud
+        .move_file("/work/b.txt", "/photos/")?
+        .execute()
+        .await
+        .unwrap_err();
+    assert!(err.downcast_ref::<GuardViolation>().is_some());
+
+    pcloud
+        .move_file("/work/b.txt", "/work/tmp/")?
+        .execute()
+        .await?;
+    pcloud.delete_file(inside).await?;
+    assert!(fake.exists("/work/tmp/b.txt"));
+    assert!(!fake.exists("/work/tmp/a.txt"));
+    Ok(())
+}
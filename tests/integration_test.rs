@@ -78,8 +78,11 @@ async fn test_event_stream() -> Result<(), Box<dyn std::error::Error + Send + Sy
         .upload()
         .await?;
 
-    assert_eq!(PCloudResult::Ok, upload_result.result);
-    assert_eq!("test.txt", upload_result.metadata.get(0).unwrap().name);
+    assert!(upload_result.is_success());
+    assert_eq!(
+        "test.txt",
+        upload_result.outcomes[0].result.as_ref().unwrap().name
+    );
 
     // Delete test folder
     let deletefolder_result = pcloud
@@ -141,8 +144,11 @@ async fn test_file_revisions() -> Result<(), Box<dyn std::error::Error + Send +
         .upload()
         .await?;
 
-    assert_eq!(PCloudResult::Ok, upload_result.result);
-    assert_eq!("test.txt", upload_result.metadata.get(0).unwrap().name);
+    assert!(upload_result.is_success());
+    assert_eq!(
+        "test.txt",
+        upload_result.outcomes[0].result.as_ref().unwrap().name
+    );
 
     // Overwrite file content
     let upload_result1 = pcloud
@@ -154,8 +160,11 @@ async fn test_file_revisions() -> Result<(), Box<dyn std::error::Error + Send +
         .upload()
         .await?;
 
-    assert_eq!(PCloudResult::Ok, upload_result1.result);
-    assert_eq!("test.txt", upload_result1.metadata.get(0).unwrap().name);
+    assert!(upload_result1.is_success());
+    assert_eq!(
+        "test.txt",
+        upload_result1.outcomes[0].result.as_ref().unwrap().name
+    );
 
     sleep(Duration::from_millis(200)).await;
 
@@ -227,20 +236,30 @@ async fn test_file_operations() -> Result<(), Box<dyn std::error::Error + Send +
         .upload()
         .await?;
 
-    assert_eq!(PCloudResult::Ok, upload_result.result);
-    assert_eq!(2, upload_result.fileids.len());
-    assert_eq!(2, upload_result.metadata.len());
-    assert_eq!("test.txt", upload_result.metadata.get(0).unwrap().name);
+    assert!(upload_result.is_success());
+    assert_eq!(2, upload_result.fileids().len());
+    assert_eq!(2, upload_result.outcomes.len());
+    assert_eq!(
+        "test.txt",
+        upload_result.outcomes[0].result.as_ref().unwrap().name
+    );
     assert_eq!(
         "second test.txt",
-        upload_result.metadata.get(1).unwrap().name
+        upload_result.outcomes[1].result.as_ref().unwrap().name
     );
-    assert_eq!(date, upload_result.metadata.get(0).unwrap().modified);
-    assert_eq!(date, upload_result.metadata.get(1).unwrap().modified);
-    info!("Created test files: {:?}", upload_result.fileids);
+    assert_eq!(
+        date,
+        upload_result.outcomes[0].result.as_ref().unwrap().modified
+    );
+    assert_eq!(
+        date,
+        upload_result.outcomes[1].result.as_ref().unwrap().modified
+    );
+    info!("Created test files: {:?}", upload_result.fileids());
 
-    let file_id = upload_result.fileids.get(0).unwrap();
-    let file_id2 = upload_result.fileids.get(1).unwrap();
+    let fileids = upload_result.fileids();
+    let file_id = fileids.first().unwrap();
+    let file_id2 = fileids.get(1).unwrap();
 
     // Download file
     let download_result = pcloud.download_file(file_id).await?.text().await?;
@@ -377,17 +396,21 @@ async fn test_zip_download() -> Result<(), Box<dyn std::error::Error + Send + Sy
         .upload()
         .await?;
 
-    assert_eq!(PCloudResult::Ok, upload_result.result);
-    assert_eq!(2, upload_result.fileids.len());
-    assert_eq!(2, upload_result.metadata.len());
-    assert_eq!("test.txt", upload_result.metadata.get(0).unwrap().name);
+    assert!(upload_result.is_success());
+    assert_eq!(2, upload_result.fileids().len());
+    assert_eq!(2, upload_result.outcomes.len());
+    assert_eq!(
+        "test.txt",
+        upload_result.outcomes[0].result.as_ref().unwrap().name
+    );
     assert_eq!(
         "second test.txt",
-        upload_result.metadata.get(1).unwrap().name
+        upload_result.outcomes[1].result.as_ref().unwrap().name
     );
 
-    let file_id = upload_result.fileids.get(0).unwrap();
-    let file_id2 = upload_result.fileids.get(1).unwrap();
+    let fileids = upload_result.fileids();
+    let file_id = fileids.first().unwrap();
+    let file_id2 = fileids.get(1).unwrap();
 
     // Download file
     let download_result = pcloud.download_file(file_id).await?.text().await?;
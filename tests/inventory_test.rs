@@ -0,0 +1,51 @@
+use pcloud_async_api::{
+    fake_pcloud::FakePCloud,
+    inventory::{InventoryEntry, InventoryFormat},
+};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_export_json_lines() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let fileid = fake.put_file("/photos/a.txt", "This is nice test content")?;
+    fake.put_file("/photos/2023/b.txt", "This is other content")?;
+    let pcloud = fake.client().await?;
+
+    let mut output = Vec::new();
+    let count = pcloud
+        .export_inventory("/photos")?
+        .write_to(&mut output)
+        .await?;
+    assert_eq!(2, count);
+
+    let entries = String::from_utf8(output)?
+        .lines()
+        .map(serde_json::from_str::<InventoryEntry>)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!("2023/b.txt", entries[0].path);
+    assert_eq!("a.txt", entries[1].path);
+    assert_eq!(fileid, entries[1].fileid);
+    assert_eq!(Some(25), entries[1].size);
+    assert!(entries[1].hash.is_some());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_export_csv() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/docs/report, final.txt", "This is nice test content")?;
+    let pcloud = fake.client().await?;
+
+    let mut output = Vec::new();
+    pcloud
+        .export_inventory("/docs")?
+        .format(InventoryFormat::Csv)
+        .write_to(&mut output)
+        .await?;
+
+    let output = String::from_utf8(output)?;
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(2, lines.len());
+    assert_eq!(InventoryEntry::CSV_HEADER, lines[0]);
+    assert!(lines[1].starts_with("\"report, final.txt\",25,"));
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use std::io::SeekFrom;
+
+use pcloud_async_api::{
+    error::PCloudError, fake_pcloud::FakePCloud, low_level_file::PCloudFileFlag,
+    pcloud_model::PCloudResult,
+};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_create_write_read() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.create_folder("/docs")?;
+    let pcloud = fake.client().await?;
+
+    let file = pcloud
+        .open_file()
+        .by_file_path("/docs/new.txt")
+        .with_flag(PCloudFileFlag::CREATE)
+        .with_flag(PCloudFileFlag::WRITE)
+        .open()
+        .await?;
+    assert_eq!(Some(5), file.write("Hello").await?.bytes);
+    assert_eq!(Some(6), file.write(" world").await?.bytes);
+
+    let size = file.size().await?;
+    assert_eq!(Some(11), size.size);
+    assert_eq!(Some(11), size.offset);
+
+    // Positional operations don't move the offset
+    file.write_at(0, "J").await?;
+    assert_eq!("Jello", file.read_at(0, 5).await?);
+    assert_eq!(Some(11), file.size().await?.offset);
+
+    assert_eq!(6, file.seek(SeekFrom::Start(6)).await?);
+    assert_eq!("wor", file.read(3).await?);
+    assert_eq!("ld", file.read(100).await?);
+    assert_eq!(8, file.seek(SeekFrom::End(-3)).await?);
+    assert_eq!(5, file.seek(SeekFrom::Current(-3)).await?);
+
+    file.truncate(5).await?;
+    assert_eq!(Some(5), file.size().await?.size);
+    let file_id = file.file_id();
+    file.close().await?;
+
+    assert_eq!(b"Jello".to_vec(), fake.read_file("/docs/new.txt").unwrap());
+    assert_eq!(
+        Some(file_id),
+        pcloud
+            .get_file_metadata("/docs/new.txt")
+            .await?
+            .metadata
+            .unwrap()
+            .fileid
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_open_flags() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/docs/a.txt", "existing")?;
+    let pcloud = fake.client().await?;
+
+    // Not created without the flag
+    let error = pcloud
+        .open_file()
+        .by_file_path("/docs/missing.txt")
+        .open()
+        .await
+        .err()
+        .unwrap();
+    assert!(matches!(
+        error,
+        PCloudError::ApiError(PCloudResult::FileNotFound)
+    ));
+
+    // Exclusive creation fails for existing files
+    let error = pcloud
+        .open_file()
+        .by_file_in_folder("/docs", "a.txt")
+        .await?
+        .with_flag(PCloudFileFlag::CREATE)
+        .with_flag(PCloudFileFlag::EXCL)
+        .open()
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(
+        Some(&PCloudResult::FileOrFolderAlreadyExists),
+        error.api_result()
+    );
+
+    // Appending writes to the end regardless of the offset
+    let file = pcloud
+        .open_file()
+        .by_file_id("/docs/a.txt")
+        .await?
+        .with_flag(PCloudFileFlag::APPEND)
+        .open()
+        .await?;
+    file.write(" and more").await?;
+    file.close().await?;
+    assert_eq!(
+        b"existing and more".to_vec(),
+        fake.read_file("/docs/a.txt").unwrap()
+    );
+
+    // Truncated on open
+    let file = pcloud
+        .open_file()
+        .by_file_path("/docs/a.txt")
+        .with_flag(PCloudFileFlag::TRUNCATE)
+        .open()
+        .await?;
+    assert_eq!(Some(0), file.size().await?.size);
+    let fd = file.fd();
+    file.close().await?;
+
+    // Descriptors aren't reused
+    let reopened = pcloud
+        .open_file()
+        .by_file_path("/docs/a.txt")
+        .open()
+        .await?;
+    assert_ne!(fd, reopened.fd());
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use pcloud_async_api::{fake_pcloud::FakePCloud, manifest::DEFAULT_MANIFEST_NAME};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_manifest_is_deterministic() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let pcloud = fake.client().await?;
+    fake.put_file("/data/b.txt", "This is b")?;
+    fake.put_file("/data/sub/a.txt", "This is a")?;
+    fake.put_file("/data/a.txt", "This is a")?;
+
+    let first = pcloud.integrity_manifest("/data")?.create().await?;
+    let second = pcloud
+        .integrity_manifest("/data")?
+        .concurrency(1)
+        .create()
+        .await?;
+    assert_eq!(first, second);
+    assert!(first.is_consistent());
+    let paths: Vec<&str> = first.entries.iter().map(|e| e.path.as_str()).collect();
+    assert_eq!(vec!["a.txt", "b.txt", "sub/a.txt"], paths);
+    assert_eq!(first.entries[0].sha1, first.entries[2].sha1);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_verify_stored_manifest() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let pcloud = fake.client().await?;
+    fake.put_file("/data/a.txt", "This is a")?;
+    fake.put_file("/data/b.txt", "This is b")?;
+    fake.put_file("/data/sub/c.txt", "This is c")?;
+
+    let manifest = pcloud.integrity_manifest("/data")?.store().await?;
+    assert_eq!(3, manifest.entries.len());
+    assert!(fake.exists(&format!("/data/{}", DEFAULT_MANIFEST_NAME)));
+    assert!(pcloud.integrity_manifest("/data")?.verify().await?.is_ok());
+
+    // Silent corruption, a lost file and an unexpected one
+    fake.put_file("/data/a.txt", "This is A")?;
+    pcloud.delete_file("/data/sub/c.txt").await?;
+    fake.put_file("/data/d.txt", "This is d")?;
+    let verification = pcloud.integrity_manifest("/data")?.verify().await?;
+    assert!(!verification.is_ok());
+    assert!(verification.manifest_consistent);
+    assert_eq!(vec!["a.txt".to_string()], verification.mismatched);
+    assert_eq!(vec!["sub/c.txt".to_string()], verification.missing);
+    assert_eq!(vec!["d.txt".to_string()], verification.added);
+
+    // A manifest modified without updating its digest
+    let mut tampered = manifest.clone();
+    tampered.entries[0].sha1 = "0".repeat(40);
+    let verification = pcloud
+        .integrity_manifest("/data")?
+        .verify_against(&tampered)
+        .await?;
+    assert!(!verification.manifest_consistent);
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use pcloud_async_api::pcloud_model::{MediaInfo, Metadata};
+
+fn metadata(media_fields: &str) -> Result<Metadata, serde_json::Error> {
+    serde_json::from_str(&format!(
+        r#"{{"parentfolderid": 0, "isfolder": false, "ismine": true, "isshared": false, "name": "file", "id": "f1", "fileid": 1,
+        "created": "Sat, 24 Jul 2021 07:46:31 +0000", "modified": "Sat, 24 Jul 2021 07:46:31 +0000", "icon": "file", "thumb": false{}}}"#,
+        media_fields
+    ))
+}
+
+#[test]
+fn test_video_info() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let video = metadata(
+        r#", "width": 1920, "height": 1080, "duration": "63.48", "fps": "29.97", "videocodec": "h264", "audiocodec": "aac", "videobitrate": 4500, "audiosamplerate": 48000, "rotate": 90"#,
+    )?;
+    let info = match video.media_info() {
+        Some(MediaInfo::Video(info)) => info,
+        other => panic!("Expected video info, got {:?}", other),
+    };
+    assert_eq!(Some(Duration::from_millis(63480)), info.duration);
+    assert_eq!(Some(29.97), info.fps);
+    assert_eq!(Some("h264"), info.video_codec.as_deref());
+    assert_eq!(Some(48000), info.audio_sample_rate);
+    assert_eq!(None, info.audio_bitrate);
+    assert_eq!(Some((1920, 1080)), video.media_info().unwrap().dimensions());
+
+    // Written back as the flat fields sent by pCloud
+    let json = serde_json::to_value(&video)?;
+    assert_eq!("63.48", json["duration"]);
+    assert_eq!("h264", json["videocodec"]);
+    assert_eq!(1920, json["width"]);
+    let parsed: Metadata = serde_json::from_value(json)?;
+    assert_eq!(video.media, parsed.media);
+    Ok(())
+}
+
+#[test]
+fn test_audio_and_image_info() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let audio = metadata(r#", "artist": "Artist", "title": "Song", "trackno": "3/12""#)?;
+    let info = match audio.media_info() {
+        Some(MediaInfo::Audio(info)) => info,
+        other => panic!("Expected audio info, got {:?}", other),
+    };
+    assert_eq!(Some("Artist"), info.artist.as_deref());
+    assert_eq!(None, info.album);
+    assert_eq!(Some(3), info.track_number());
+    assert_eq!(None, audio.media_info().unwrap().dimensions());
+
+    let image = metadata(r#", "width": 640, "height": 480"#)?;
+    assert_eq!(Some((640, 480)), image.media_info().unwrap().dimensions());
+    assert!(matches!(image.media_info(), Some(MediaInfo::Image(_))));
+
+    let plain = metadata("")?;
+    assert_eq!(None, plain.media_info());
+    let json = serde_json::to_value(&plain)?;
+    assert!(json.get("width").is_none());
+    Ok(())
+}
+
+#[test]
+fn test_media_kind_by_category() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    // Videos with dimensions only aren't mistaken for images
+    let video = metadata(r#", "category": 2, "width": 1280, "height": 720"#)?;
+    let info = match video.media_info() {
+        Some(MediaInfo::Video(info)) => info,
+        other => panic!("Expected video info, got {:?}", other),
+    };
+    assert_eq!(Some(1280), info.width);
+    assert_eq!(None, info.duration);
+
+    // Tags of videos are kept
+    let clip = metadata(
+        r#", "contenttype": "video/mp4", "duration": "12.5", "artist": "Artist", "title": "Clip""#,
+    )?;
+    let info = match clip.media_info() {
+        Some(MediaInfo::Video(info)) => info,
+        other => panic!("Expected video info, got {:?}", other),
+    };
+    let tags = info.tags.as_ref().unwrap();
+    assert_eq!(Some("Artist"), tags.artist.as_deref());
+    let json = serde_json::to_value(&clip)?;
+    assert_eq!("Clip", json["title"]);
+    let parsed: Metadata = serde_json::from_value(json)?;
+    assert_eq!(clip.media, parsed.media);
+
+    // Other categories carry no media information
+    let document = metadata(r#", "category": 4, "width": 100, "height": 100"#)?;
+    assert_eq!(None, document.media_info());
+    Ok(())
+}
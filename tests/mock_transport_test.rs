@@ -0,0 +1,980 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use bytes::Bytes;
+use futures::{SinkExt, TryStreamExt};
+use pcloud_async_api::{
+    chunked_upload::{FileUploadStateStore, ResumableUpload, UploadState, UploadStateStore},
+    error::PCloudError,
+    file_ops::{ChecksumMismatch, InsufficientQuota},
+    interceptor::Interceptor,
+    pcloud_client::{CorrelatedError, DownloadHostPolicy, PCloudClient, Region},
+    pcloud_model::{DownloadLink, PCloudResult},
+    raw_capture::DeserializationError,
+    transfer::VerificationStatus,
+    transport::MockTransport,
+};
+use reqwest::{header::HeaderValue, Request, Response};
+
+const LIST_FOLDER: &str = r#"{
+    "result": 0,
+    "metadata": {
+        "path": "/test-folder", "name": "test-folder", "created": "Wed, 25 Jan 2023 12:09:14 +0000",
+        "ismine": true, "thumb": false, "modified": "Wed, 25 Jan 2023 12:09:14 +0000", "id": "d42",
+        "isshared": false, "icon": "folder", "isfolder": true, "parentfolderid": 0, "folderid": 42,
+        "contents": [
+            {
+                "name": "test.txt", "created": "Wed, 25 Jan 2023 12:09:14 +0000", "thumb": false,
+                "modified": "Wed, 25 Jan 2023 12:09:14 +0000", "isfolder": false, "fileid": 4711,
+                "isshared": false, "icon": "document", "id": "f4711", "ismine": true,
+                "parentfolderid": 42, "size": 25, "contenttype": "text/plain", "hash": 123456
+            }
+        ]
+    }
+}"#;
+
+const STAT: &str = r#"{
+    "result": 0,
+    "metadata": {
+        "name": "output.txt", "created": "Wed, 25 Jan 2023 12:09:14 +0000", "thumb": false,
+        "modified": "Wed, 25 Jan 2023 12:09:14 +0000", "isfolder": false, "fileid": 4711,
+        "isshared": false, "icon": "document", "id": "f4711", "ismine": true,
+        "parentfolderid": 42, "size": 19, "contenttype": "text/plain", "hash": 123456
+    }
+}"#;
+
+const UPLOAD: &str = r#"{
+    "result": 0,
+    "fileids": [4711],
+    "metadata": [{
+        "name": "small.bin", "created": "Wed, 25 Jan 2023 12:09:14 +0000", "thumb": false,
+        "modified": "Wed, 25 Jan 2023 12:09:14 +0000", "isfolder": false, "fileid": 4711,
+        "isshared": false, "icon": "document", "id": "f4711", "ismine": true,
+        "parentfolderid": 42, "size": 50, "contenttype": "application/octet-stream", "hash": 123456
+    }]
+}"#;
+
+async fn get_client(transport: &MockTransport) -> Result<PCloudClient, PCloudError> {
+    PCloudClient::builder("https://api.pcloud.com")
+        .with_oauth("test-token")
+        .select_best_api_server(false)
+        .with_transport(transport.clone())
+        .build()
+        .await
+}
+
+#[tokio::test]
+async fn test_list_folder_with_mock_transport(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let transport = MockTransport::new();
+    transport.respond_with("listfolder", LIST_FOLDER);
+
+    let pcloud = get_client(&transport).await?;
+
+    let folder = pcloud
+        .list_folder("/test-folder")?
+        .recursive(true)
+        .get()
+        .await?;
+    let metadata = folder.metadata.unwrap();
+    assert_eq!("test-folder", metadata.name);
+    assert_eq!(1, metadata.contents.len());
+    assert_eq!(Some(4711), metadata.contents[0].fileid);
+
+    let requests = transport.requests_for("listfolder");
+    assert_eq!(1, requests.len());
+    assert_eq!(Some("/test-folder".to_string()), requests[0].param("path"));
+    assert_eq!(Some("1".to_string()), requests[0].param("recursive"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_api_error_with_mock_transport() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    let transport = MockTransport::new();
+    transport.respond_with("stat", r#"{ "result": 2009, "error": "File not found." }"#);
+
+    let pcloud = get_client(&transport).await?;
+
+    let err = pcloud.get_file_metadata(4711).await.unwrap_err();
+    assert_eq!(
+        Some(&PCloudResult::FileNotFound),
+        err.downcast_ref::<PCloudResult>()
+    );
+
+    let result = PCloudResult::from_error(&err).unwrap();
+    assert_eq!(2009, result.code());
+    assert_eq!(2, result.error_class());
+    assert!(result.is_not_found());
+    assert!(!result.is_retryable());
+    assert!(!result.is_auth_error());
+
+    assert!(PCloudResult::TooManyLogins.is_retryable());
+    assert!(PCloudResult::InternalUploadError.is_retryable());
+    assert!(PCloudResult::InvalidAccessToken.is_auth_error());
+    assert_eq!(0, PCloudResult::Ok.error_class());
+
+    // Codes not known to this crate don't break deserialization
+    transport.respond_with(
+        "listfolder",
+        r#"{ "result": 2999, "error": "Something new." }"#,
+    );
+    let err = pcloud.list_folder(42)?.get().await.unwrap_err();
+    let result = err.api_result().unwrap();
+    assert_eq!(&PCloudResult::Unknown(2999), result);
+    assert_eq!(2, result.error_class());
+    assert_eq!(
+        PCloudResult::LinkDeletedByOwner,
+        PCloudResult::from_code(7002)
+    );
+    assert_eq!(
+        "7002",
+        serde_json::to_string(&PCloudResult::LinkDeletedByOwner)?
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_api_server_selection_with_mock_transport(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let transport = MockTransport::new();
+    transport.respond_with(
+        "getapiserver",
+        r#"{ "result": 0, "binapi": ["bineapi-ams1.pcloud.com"], "api": ["eapi-ams1.pcloud.com"] }"#,
+    );
+    transport.respond_with(
+        "userinfo",
+        r#"{ "result": 0, "userid": 1, "email": "test@example.com", "quota": 1000, "usedquota": 10, "registered": "Wed, 25 Jan 2023 12:09:14 +0000" }"#,
+    );
+
+    let pcloud = PCloudClient::builder("https://eapi.pcloud.com")
+        .with_oauth("test-token")
+        .with_transport(transport.clone())
+        .build()
+        .await?;
+
+    let user_info = pcloud.get_user_info().await?;
+    assert_eq!(Some(1000), user_info.quota);
+
+    let requests = transport.requests_for("userinfo");
+    assert_eq!(1, requests.len());
+    assert_eq!(Some("eapi-ams1.pcloud.com"), requests[0].url.host_str());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_region_detection_with_mock_transport(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let transport = MockTransport::new();
+    transport.respond_with(
+        "userinfo",
+        r#"{ "result": 2094, "error": "Invalid 'access_token' provided." }"#,
+    );
+    transport.respond_with(
+        "userinfo",
+        r#"{ "result": 0, "userid": 1, "email": "test@example.com", "quota": 1000, "usedquota": 10, "registered": "Wed, 25 Jan 2023 12:09:14 +0000" }"#,
+    );
+
+    let pcloud = PCloudClient::builder(Region::Us.api_host())
+        .with_oauth("test-token")
+        .detect_region(true)
+        .select_best_api_server(false)
+        .with_transport(transport.clone())
+        .build()
+        .await?;
+    assert_eq!(Some(Region::Eu), pcloud.region());
+
+    pcloud.get_user_info().await?;
+    let hosts: Vec<_> = transport
+        .requests_for("userinfo")
+        .iter()
+        .map(|r| r.url.host_str().unwrap_or_default().to_string())
+        .collect();
+    assert_eq!(
+        vec!["api.pcloud.com", "eapi.pcloud.com", "eapi.pcloud.com"],
+        hosts
+    );
+
+    assert_eq!(
+        Some(Region::Eu),
+        Region::from_hostname("eapi-ams1.pcloud.com")
+    );
+    assert_eq!(
+        Some(Region::Us),
+        Region::from_hostname("https://api.pcloud.com/")
+    );
+    assert_eq!(None, Region::from_hostname("http://localhost:8080"));
+    assert_eq!(Some(Region::Eu), Region::from_location_id(2));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_preconnect_with_mock_transport(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let transport = MockTransport::new();
+    transport.respond_with(
+        "currentserver",
+        r#"{ "result": 0, "hostname": "api7.pcloud.com", "ip": "127.0.0.1" }"#,
+    );
+    transport.respond_with_status("", 200, "");
+
+    PCloudClient::builder("https://api.pcloud.com")
+        .with_oauth("test-token")
+        .select_best_api_server(false)
+        .with_transport(transport.clone())
+        .preconnect(true)
+        .preconnect_download_host("c123.pcloud.com")
+        .build()
+        .await?;
+    assert_eq!(1, transport.requests_for("currentserver").len());
+    let warm_up = transport.requests_for("");
+    assert_eq!(1, warm_up.len());
+    assert_eq!(Some("c123.pcloud.com"), warm_up[0].url.host_str());
+
+    // Failing warm-ups don't fail the client
+    let transport = MockTransport::new();
+    PCloudClient::builder("https://api.pcloud.com")
+        .with_oauth("test-token")
+        .select_best_api_server(false)
+        .with_transport(transport.clone())
+        .preconnect(true)
+        .build()
+        .await?;
+    assert_eq!(1, transport.requests().len());
+
+    Ok(())
+}
+
+/// Adds a custom header to all requests and counts the responses
+struct HeaderInterceptor {
+    responses: Arc<AtomicUsize>,
+}
+
+impl Interceptor for HeaderInterceptor {
+    fn on_request(&self, request: &mut Request) {
+        request
+            .headers_mut()
+            .insert("x-custom-header", HeaderValue::from_static("test"));
+    }
+
+    fn on_response(&self, _response: &Response) {
+        self.responses.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[tokio::test]
+async fn test_interceptor_with_mock_transport(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let transport = MockTransport::new();
+    transport.respond_with("listfolder", LIST_FOLDER);
+    let responses = Arc::new(AtomicUsize::new(0));
+
+    let pcloud = PCloudClient::builder("https://api.pcloud.com")
+        .with_oauth("test-token")
+        .select_best_api_server(false)
+        .with_transport(transport.clone())
+        .with_interceptor(HeaderInterceptor {
+            responses: responses.clone(),
+        })
+        .build()
+        .await?;
+
+    pcloud.list_folder("/test-folder")?.get().await?;
+
+    let requests = transport.requests_for("listfolder");
+    assert_eq!(
+        Some(&HeaderValue::from_static("test")),
+        requests[0].headers.get("x-custom-header")
+    );
+    assert_eq!(1, responses.load(Ordering::SeqCst));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_correlation_id_with_mock_transport(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let transport = MockTransport::new();
+    transport.respond_with("listfolder", LIST_FOLDER);
+    let counter = Arc::new(AtomicUsize::new(0));
+    let generator_counter = counter.clone();
+
+    let pcloud = PCloudClient::builder("https://api.pcloud.com")
+        .with_oauth("test-token")
+        .select_best_api_server(false)
+        .with_transport(transport.clone())
+        .with_correlation_id("X-Request-Id", move || {
+            format!("req-{}", generator_counter.fetch_add(1, Ordering::SeqCst))
+        })
+        .build()
+        .await?;
+
+    pcloud.list_folder("/test-folder")?.get().await?;
+    let requests = transport.requests_for("listfolder");
+    assert_eq!(
+        Some(&HeaderValue::from_static("req-0")),
+        requests[0].headers.get("x-request-id")
+    );
+
+    // No response registered for stat -> transport error carries the correlation id
+    let err = pcloud.get_file_metadata(4711).await.unwrap_err();
+    let err = err.downcast_ref::<CorrelatedError>().unwrap();
+    assert_eq!("req-1", err.correlation_id);
+
+    // Responses that can't be deserialized carry it as well
+    transport.respond_with("checksumfile", r#"{ "result": 0, "sha1": 42 }"#);
+    let err = pcloud.checksum_file(4711)?.get().await.unwrap_err();
+    assert_eq!(Some("req-2"), err.correlation_id());
+    assert!(err
+        .downcast_ref::<reqwest::Error>()
+        .is_some_and(|e| e.is_decode()));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_raw_response_capture_with_mock_transport(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let transport = MockTransport::new();
+    transport.respond_with("listfolder", LIST_FOLDER);
+    transport.respond_with(
+        "stat",
+        r#"{ "result": 0, "metadata": { "unexpected": true } }"#,
+    );
+
+    let pcloud = PCloudClient::builder("https://api.pcloud.com")
+        .with_oauth("test-token")
+        .select_best_api_server(false)
+        .with_transport(transport.clone())
+        .capture_raw_responses(1)
+        .build()
+        .await?;
+
+    pcloud.list_folder("/test-folder")?.get().await?;
+    let raw = pcloud.last_raw_responses();
+    assert_eq!(1, raw.len());
+    assert!(raw[0].body.contains("test-folder"));
+
+    let err = pcloud.get_file_metadata(4711).await.unwrap_err();
+    let err = err.downcast_ref::<DeserializationError>().unwrap();
+    assert!(err.response.body.contains("unexpected"));
+
+    // Only the last response is retained
+    let raw = pcloud.last_raw_responses();
+    assert_eq!(1, raw.len());
+    assert!(raw[0].url.ends_with("/stat?fileid=4711"));
+
+    // Clones are pointer-sized and share the captured responses
+    let clone = pcloud.clone();
+    assert_eq!(std::mem::size_of::<usize>(), std::mem::size_of_val(&clone));
+    assert_eq!(raw[0].url, clone.last_raw_responses()[0].url);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_ping_and_validate_token_with_mock_transport(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let transport = MockTransport::new();
+    transport.respond_with(
+        "currentserver",
+        r#"{ "result": 0, "ip": "127.0.0.1", "hostname": "api7.pcloud.com" }"#,
+    );
+    transport.respond_with(
+        "userinfo",
+        r#"{ "result": 0, "userid": 1, "email": "test@example.com", "registered": "Wed, 25 Jan 2023 12:09:14 +0000" }"#,
+    );
+    transport.respond_with(
+        "listtokens",
+        r#"{ "result": 0, "tokens": [
+            { "tokenid": 7, "device": "other", "current": false, "expires": "Fri, 01 Jan 2027 00:00:00 +0000" },
+            { "tokenid": 8, "device": "test", "current": true, "expires": "Sat, 01 Jan 2028 00:00:00 +0000" }
+        ] }"#,
+    );
+    transport.respond_with(
+        "userinfo",
+        r#"{ "result": 2094, "error": "Invalid 'access_token' provided." }"#,
+    );
+
+    let pcloud = get_client(&transport).await?;
+
+    pcloud.ping().await?;
+
+    let validation = pcloud.validate_token().await?;
+    assert!(validation.valid);
+    assert_eq!(Some(1), validation.userid);
+    assert_eq!(
+        Some("2028-01-01T00:00:00+00:00".to_string()),
+        validation.expires.map(|e| e.to_rfc3339())
+    );
+
+    let validation = pcloud.validate_token().await?;
+    assert!(!validation.valid);
+    assert_eq!(PCloudResult::InvalidAccessToken, validation.result);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_upload_sink_with_mock_transport(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let transport = MockTransport::new();
+    transport.respond_with("upload_create", r#"{ "result": 0, "uploadid": 17 }"#);
+    transport.respond_with("upload_write", r#"{ "result": 0 }"#);
+    transport.respond_with("upload_save", STAT);
+
+    let pcloud = get_client(&transport).await?;
+
+    let mut sink = pcloud.create_upload().await?.into_sink().chunk_size(4);
+    let mut lines = futures::stream::iter(
+        vec!["first\n", "second\n", "third\n"]
+            .into_iter()
+            .map(|line| Ok(Bytes::from(line))),
+    );
+    sink.send_all(&mut lines).await?;
+    let saved = sink.save("/test-folder", "output.txt").await?;
+    assert_eq!(Some(4711), saved.metadata.unwrap().fileid);
+
+    // 19 bytes in chunks of 4 bytes
+    let writes = transport.requests_for("upload_write");
+    assert_eq!(5, writes.len());
+    assert_eq!(Some("16".to_string()), writes[4].param("uploadoffset"));
+    assert_eq!(
+        Some("output.txt".to_string()),
+        transport.requests_for("upload_save")[0].param("name")
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_download_stream_with_mock_transport(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let transport = MockTransport::new();
+    transport.respond_with(
+        "checksumfile",
+        &STAT.replace("\"result\": 0,", "\"result\": 0, \"sha1\": \"abc\","),
+    );
+    transport.respond_with(
+        "getfilelink",
+        r#"{ "result": 0, "path": "/dl/output.txt", "hosts": ["c1.pcloud.com"], "expires": "Wed, 25 Jan 2023 12:09:14 +0000" }"#,
+    );
+    transport.respond_with_status("output.txt", 200, "first\nsecond\nthird\n");
+    transport.respond_with_status("output.txt", 404, "");
+
+    let pcloud = get_client(&transport).await?;
+
+    let (info, stream) = pcloud.download_stream(4711).await?;
+    assert_eq!(Some(19), info.content_length);
+    assert_eq!(Some("abc".to_string()), info.sha1);
+
+    let chunks: Vec<Bytes> = stream.try_collect().await?;
+    assert_eq!(Bytes::from("first\nsecond\nthird\n"), chunks.concat());
+
+    // Unsuccessful downloads fail
+    assert!(pcloud.download_stream(4711).await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_call_unwrapped_method() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let transport = MockTransport::new();
+    transport.respond_with(
+        "getzippedfilelink",
+        r#"{ "result": 0, "path": "/dl/archive.zip", "hosts": ["c1.pcloud.com"], "expires": "Wed, 25 Jan 2023 12:09:14 +0000" }"#,
+    );
+    transport.respond_with(
+        "sharefolder",
+        r#"{ "result": 2003, "error": "Access denied. You do not have permissions to preform this operation." }"#,
+    );
+    transport.respond_with(
+        "unknownerror",
+        r#"{ "result": 9999, "error": "Something new" }"#,
+    );
+
+    let pcloud = get_client(&transport).await?;
+
+    let link: DownloadLink = pcloud
+        .call("getzippedfilelink", &[("folderid", "42")])
+        .await?;
+    assert_eq!("/dl/archive.zip", link.path.unwrap());
+
+    let request = &transport.requests_for("getzippedfilelink")[0];
+    assert_eq!(Some("42".to_string()), request.param("folderid"));
+    assert_eq!(
+        "Bearer test-token",
+        request.headers.get("authorization").unwrap()
+    );
+
+    let error = pcloud
+        .call_json("sharefolder", &[("folderid", "42")])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        Some(&PCloudResult::AccessDenied),
+        error.downcast_ref::<PCloudResult>()
+    );
+
+    let error = pcloud.call_json("unknownerror", &[]).await.unwrap_err();
+    assert_eq!(
+        Some(&PCloudResult::Unknown(9999)),
+        error.downcast_ref::<PCloudResult>()
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_upload_quota_check() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let transport = MockTransport::new();
+    transport.respond_with(
+        "userinfo",
+        r#"{ "result": 0, "userid": 1, "quota": 1000, "usedquota": 900, "registered": "Wed, 25 Jan 2023 12:09:14 +0000" }"#,
+    );
+    transport.respond_with("uploadfile", UPLOAD);
+
+    let pcloud = get_client(&transport).await?;
+
+    let error = pcloud
+        .upload_file_into_folder(42)?
+        .with_file("large.bin", vec![0u8; 150])
+        .with_file("small.bin", vec![0u8; 50])
+        .check_quota(true)
+        .upload()
+        .await
+        .unwrap_err();
+    assert_eq!(
+        Some(&InsufficientQuota {
+            needed: 200,
+            available: 100
+        }),
+        error.downcast_ref::<InsufficientQuota>()
+    );
+    assert!(matches!(error, PCloudError::InsufficientQuota(_)));
+    assert!(transport.requests_for("uploadfile").is_empty());
+
+    let upload = pcloud
+        .upload_file_into_folder(42)?
+        .with_file("small.bin", vec![0u8; 50])
+        .check_quota(true)
+        .upload()
+        .await?;
+    assert_eq!(vec![4711], upload.fileids());
+    assert_eq!(1, transport.requests_for("uploadfile").len());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_resume_chunked_upload() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let transport = MockTransport::new();
+    transport.respond_with("upload_create", r#"{ "result": 0, "uploadid": 17 }"#);
+    transport.respond_with("upload_write", r#"{ "result": 0 }"#);
+    transport.respond_with("upload_info", r#"{ "result": 0, "size": 6 }"#);
+    transport.respond_with("upload_save", STAT);
+
+    let pcloud = get_client(&transport).await?;
+    let state_dir =
+        std::env::temp_dir().join(format!("pcloud-upload-state-{}", std::process::id()));
+    let store = Arc::new(FileUploadStateStore::new(&state_dir));
+
+    // First run is interrupted after the first chunk
+    let mut upload = pcloud
+        .resume_upload("/local/output.txt", store.clone())
+        .await?;
+    upload.write("first\n").await?;
+    drop(upload);
+    assert_eq!(
+        Some(UploadState {
+            upload_id: 17,
+            offset: 6
+        }),
+        store.load("/local/output.txt")?
+    );
+
+    // Second run continues at the offset known to pCloud
+    let mut upload = pcloud
+        .resume_upload("/local/output.txt", store.clone())
+        .await?;
+    assert_eq!(17, upload.upload_id());
+    assert_eq!(6, upload.offset());
+    upload.write("second\nthird\n").await?;
+    upload.save("/test-folder", "output.txt").await?;
+
+    assert_eq!(1, transport.requests_for("upload_create").len());
+    assert_eq!(
+        Some("6".to_string()),
+        transport.requests_for("upload_write")[1].param("uploadoffset")
+    );
+    assert_eq!(None, store.load("/local/output.txt")?);
+    std::fs::remove_dir_all(&state_dir)?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_write_chunks_at_offsets() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let transport = MockTransport::new();
+    transport.respond_with("upload_create", r#"{ "result": 0, "uploadid": 17 }"#);
+    transport.respond_with("upload_write", r#"{ "result": 0 }"#);
+    transport.respond_with("upload_save", STAT);
+
+    let pcloud = get_client(&transport).await?;
+    let state_dir =
+        std::env::temp_dir().join(format!("pcloud-upload-offsets-{}", std::process::id()));
+    let store = Arc::new(FileUploadStateStore::new(&state_dir));
+
+    let mut upload: ResumableUpload = pcloud
+        .resume_upload("/local/chunks.bin", store.clone())
+        .await?;
+    upload.write_at(6, "second").await?;
+    assert_eq!(12, upload.offset());
+    // Repeating an earlier chunk keeps the offset
+    upload.write_at(0, "first\n").await?;
+    assert_eq!(12, upload.offset());
+    assert_eq!(
+        Some(UploadState {
+            upload_id: 17,
+            offset: 12
+        }),
+        store.load("/local/chunks.bin")?
+    );
+    upload.save("/test-folder", "chunks.bin").await?;
+
+    let writes = transport.requests_for("upload_write");
+    assert_eq!(Some("6".to_string()), writes[0].param("uploadoffset"));
+    assert_eq!(Some("0".to_string()), writes[1].param("uploadoffset"));
+    assert_eq!(
+        Some("/test-folder".to_string()),
+        transport.requests_for("upload_save")[0].param("path")
+    );
+    assert_eq!(None, store.load("/local/chunks.bin")?);
+    std::fs::remove_dir_all(&state_dir)?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_resume_chunked_upload_after_failure(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let transport = MockTransport::new();
+    transport.respond_with("upload_create", r#"{ "result": 0, "uploadid": 18 }"#);
+    transport.respond_with_status("upload_info", 503, "Service Unavailable");
+    transport.respond_with("upload_info", r#"{ "result": 0, "size": 6 }"#);
+    transport.respond_with(
+        "upload_info",
+        r#"{ "result": 1900, "error": "Upload not found." }"#,
+    );
+
+    let pcloud = get_client(&transport).await?;
+    let state_dir =
+        std::env::temp_dir().join(format!("pcloud-upload-retry-{}", std::process::id()));
+    let store = Arc::new(FileUploadStateStore::new(&state_dir));
+    let stored = UploadState {
+        upload_id: 17,
+        offset: 6,
+    };
+    store.save("/local/output.txt", &stored)?;
+
+    // Transient failure is returned, the stored upload is kept
+    assert!(pcloud
+        .resume_upload("/local/output.txt", store.clone())
+        .await
+        .is_err());
+    assert_eq!(Some(stored.clone()), store.load("/local/output.txt")?);
+    assert!(transport.requests_for("upload_create").is_empty());
+
+    // Once pCloud is reachable again, the upload continues
+    let upload = pcloud
+        .resume_upload("/local/output.txt", store.clone())
+        .await?;
+    assert_eq!(17, upload.upload_id());
+    assert_eq!(6, upload.offset());
+
+    // An upload unknown to pCloud starts over
+    let upload = pcloud
+        .resume_upload("/local/output.txt", store.clone())
+        .await?;
+    assert_eq!(18, upload.upload_id());
+    assert_eq!(0, upload.offset());
+    assert_eq!(
+        Some(UploadState {
+            upload_id: 18,
+            offset: 0
+        }),
+        store.load("/local/output.txt")?
+    );
+    std::fs::remove_dir_all(&state_dir)?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_download_and_verify() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let transport = MockTransport::new();
+    transport.respond_with(
+        "checksumfile",
+        r#"{ "result": 0, "sha1": "bc690bd72e79ecfce3f732b281f994fa830010a3", "sha256": "68c96189a0c7b07fa68e9c81abf40934f6ef7754cb790df116599b1282c278d3" }"#,
+    );
+    transport.respond_with(
+        "checksumfile",
+        r#"{ "result": 0, "sha1": "bc690bd72e79ecfce3f732b281f994fa830010a3", "sha256": "0000000000000000000000000000000000000000000000000000000000000000" }"#,
+    );
+    transport.respond_with(
+        "getfilelink",
+        r#"{ "result": 0, "path": "/dl/test.txt", "hosts": ["c1.pcloud.com"], "expires": "Wed, 25 Jan 2023 12:09:14 +0000" }"#,
+    );
+    transport.respond_with_status("test.txt", 200, "This is nice test content");
+
+    let pcloud = get_client(&transport).await?;
+    let local = std::env::temp_dir().join(format!("pcloud-verify-{}", std::process::id()));
+    std::fs::create_dir_all(&local)?;
+
+    let info = pcloud
+        .download_and_verify(4711, local.join("test.txt"))
+        .await?;
+    assert_eq!(Some(25), info.content_length);
+    assert_eq!(
+        "This is nice test content",
+        std::fs::read_to_string(local.join("test.txt"))?
+    );
+
+    let error = pcloud
+        .download_and_verify(4711, local.join("corrupt.txt"))
+        .await
+        .unwrap_err();
+    let mismatch = error.downcast_ref::<ChecksumMismatch>().unwrap();
+    assert_eq!("SHA-256", mismatch.algorithm);
+    assert_eq!(
+        "68c96189a0c7b07fa68e9c81abf40934f6ef7754cb790df116599b1282c278d3",
+        mismatch.actual
+    );
+    assert!(!local.join("corrupt.txt").exists());
+    assert!(!local.join("corrupt.txt.part").exists());
+
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_verified_downloads_pin_checksummed_file(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let transport = MockTransport::new();
+    transport.respond_with(
+        "checksumfile",
+        r#"{
+            "result": 0, "sha1": "bc690bd72e79ecfce3f732b281f994fa830010a3",
+            "metadata": {
+                "name": "test.txt", "created": "Wed, 25 Jan 2023 12:09:14 +0000", "thumb": false,
+                "modified": "Wed, 25 Jan 2023 12:09:14 +0000", "isfolder": false, "fileid": 4711,
+                "isshared": false, "icon": "document", "id": "f4711", "ismine": true,
+                "parentfolderid": 42, "size": 25, "contenttype": "text/plain", "hash": 123456
+            }
+        }"#,
+    );
+    transport.respond_with(
+        "getfilelink",
+        r#"{ "result": 0, "path": "/dl/test.txt", "hosts": ["c1.pcloud.com"], "expires": "Wed, 25 Jan 2023 12:09:14 +0000" }"#,
+    );
+    transport.respond_with_status("test.txt", 200, "This is nice test content");
+
+    let pcloud = get_client(&transport).await?;
+    let local = std::env::temp_dir().join(format!("pcloud-pinned-{}", std::process::id()));
+    std::fs::create_dir_all(&local)?;
+
+    // All verified downloads fetch the link of the file the checksums were taken from
+    pcloud
+        .download_and_verify("/test-folder/test.txt", local.join("a.txt"))
+        .await?;
+    pcloud
+        .segmented_download("/test-folder/test.txt", local.join("b.txt"))?
+        .verify_checksum(true)
+        .execute()
+        .await?;
+    pcloud
+        .get_download_link_for_file("/test-folder/test.txt")?
+        .verify_checksum(true)
+        .save_to(local.join("c.txt"))
+        .await?;
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        assert_eq!(
+            "This is nice test content",
+            std::fs::read_to_string(local.join(name))?
+        );
+    }
+    let links = transport.requests_for("getfilelink");
+    assert_eq!(3, links.len());
+    for link in &links {
+        assert_eq!(Some("4711".to_string()), link.param("fileid"));
+        assert_eq!(None, link.param("path"));
+    }
+
+    // Downloads without verification don't need the checksums
+    pcloud
+        .get_download_link_for_file("/test-folder/test.txt")?
+        .save_to(local.join("d.txt"))
+        .await?;
+    assert_eq!(3, transport.requests_for("checksumfile").len());
+    assert_eq!(
+        Some("/test-folder/test.txt".to_string()),
+        transport.requests_for("getfilelink")[3].param("path")
+    );
+
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_verify_checksums() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let transport = MockTransport::new();
+    // US API servers return MD5 instead of SHA-256
+    transport.respond_with(
+        "checksumfile",
+        r#"{ "result": 0, "sha1": "bc690bd72e79ecfce3f732b281f994fa830010a3", "md5": "bdc4177fd56d3feee29976921ee14d86" }"#,
+    );
+    transport.respond_with(
+        "checksumfile",
+        r#"{ "result": 0, "sha1": "bc690bd72e79ecfce3f732b281f994fa830010a3", "md5": "00000000000000000000000000000000" }"#,
+    );
+
+    let pcloud = get_client(&transport).await?;
+    let checksums = pcloud.checksum_file(4711)?.get().await?;
+    checksums.verify_bytes(b"This is nice test content")?;
+    let error = checksums.verify_bytes(b"Other content").unwrap_err();
+    assert_eq!(
+        "SHA-1",
+        error.downcast_ref::<ChecksumMismatch>().unwrap().algorithm
+    );
+
+    let local = std::env::temp_dir().join(format!("pcloud-checksums-{}", std::process::id()));
+    std::fs::create_dir_all(&local)?;
+    std::fs::write(local.join("test.txt"), "This is nice test content")?;
+    checksums.verify_file(local.join("test.txt")).await?;
+
+    let corrupt = pcloud.checksum_file(4711)?.get().await?;
+    let error = corrupt
+        .verify_file(local.join("test.txt"))
+        .await
+        .unwrap_err();
+    let mismatch = error.downcast_ref::<ChecksumMismatch>().unwrap();
+    assert_eq!("MD5", mismatch.algorithm);
+    assert_eq!("bdc4177fd56d3feee29976921ee14d86", mismatch.actual);
+
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_download_host_policy() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let transport = MockTransport::new();
+    transport.respond_with(
+        "getfilelink",
+        r#"{ "result": 0, "path": "/dl/test.txt", "hosts": ["c1.pcloud.com", "lux1.pcloud.com", "lux2.pcloud.com"], "expires": "Wed, 25 Jan 2023 12:09:14 +0000" }"#,
+    );
+    transport.respond_with_status("test.txt", 503, "");
+    transport.respond_with_status("test.txt", 200, "This is nice test content");
+
+    let pcloud = PCloudClient::builder("https://api.pcloud.com")
+        .with_oauth("test-token")
+        .select_best_api_server(false)
+        .with_transport(transport.clone())
+        .download_hosts(DownloadHostPolicy::pin(["lux"]))
+        .build()
+        .await?;
+
+    // Pinned hosts are tried in order, falling back to the next one on server errors
+    let content = pcloud.download_file(4711).await?.text().await?;
+    assert_eq!("This is nice test content", content);
+    let hosts: Vec<String> = transport
+        .requests_for("test.txt")
+        .iter()
+        .map(|r| r.url.host_str().unwrap_or_default().to_string())
+        .collect();
+    assert_eq!(vec!["lux1.pcloud.com", "lux2.pcloud.com"], hosts);
+
+    let policy = DownloadHostPolicy::prefer(["lux"]);
+    let hosts = vec!["c1.pcloud.com".to_string(), "lux1.pcloud.com".to_string()];
+    assert_eq!(
+        vec!["lux1.pcloud.com", "c1.pcloud.com"],
+        policy.order(&hosts)
+    );
+    assert_eq!(
+        vec!["lux1.pcloud.com"],
+        DownloadHostPolicy::pin(["lux"]).order(&hosts)
+    );
+    assert!(DownloadHostPolicy::pin(["ams"]).order(&hosts).is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_read_range_without_range_support(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let transport = MockTransport::new();
+    transport.respond_with(
+        "getfilelink",
+        r#"{ "result": 0, "path": "/dl/test.txt", "hosts": ["c1.pcloud.com"], "expires": "Wed, 25 Jan 2023 12:09:14 +0000" }"#,
+    );
+    // Full content instead of partial content
+    transport.respond_with_status("test.txt", 200, "This is nice test content");
+
+    let pcloud = get_client(&transport).await?;
+    let range = pcloud.read_range(4711, 8, 4).await?;
+    assert_eq!(Bytes::from("nice"), range);
+    assert_eq!(
+        Some("bytes=8-11"),
+        transport.requests_for("test.txt")[0]
+            .headers
+            .get("range")
+            .and_then(|v| v.to_str().ok())
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_transfer_verification_retries() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    let transport = MockTransport::new();
+    transport.respond_with(
+        "checksumfile",
+        &STAT.replace(
+            "\"result\": 0,",
+            "\"result\": 0, \"sha1\": \"0000000000000000000000000000000000000000\",",
+        ),
+    );
+    transport.respond_with(
+        "getfilelink",
+        r#"{ "result": 0, "path": "/dl/output.txt", "hosts": ["c1.pcloud.com"], "expires": "Wed, 25 Jan 2023 12:09:14 +0000" }"#,
+    );
+    transport.respond_with_status("output.txt", 200, "first\nsecond\nthird\n");
+
+    let pcloud = get_client(&transport).await?;
+    let local = std::env::temp_dir().join(format!("pcloud-retry-{}.txt", std::process::id()));
+    let report = pcloud
+        .transfer_manager()
+        .verify(3)
+        .download(4711, &local)?
+        .run()
+        .await?;
+
+    let outcome = &report.outcomes[0];
+    assert_eq!(VerificationStatus::Failed, outcome.verification);
+    assert_eq!(3, outcome.attempts);
+    let error = outcome.result.as_ref().unwrap_err();
+    assert_eq!(
+        "SHA-1",
+        error.downcast_ref::<ChecksumMismatch>().unwrap().algorithm
+    );
+    assert_eq!(3, transport.requests_for("output.txt").len());
+
+    std::fs::remove_file(&local)?;
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use pcloud_async_api::{
+    fake_pcloud::FakePCloud,
+    pcloud_model::Share,
+    permissions::{PermissionDenied, Permissions},
+};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_copy_into_read_only_share() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/own/report.pdf", "report")?;
+    fake.put_shared_folder("/team", 42, Permissions::READ)?;
+    let pcloud = fake.client().await?;
+
+    let err = pcloud
+        .copy_file("/own/report.pdf", "/team/")?
+        .check_permissions(true)
+        .execute()
+        .await
+        .unwrap_err();
+    let denied = err.downcast_ref::<PermissionDenied>().unwrap();
+    assert_eq!("copy", denied.operation);
+    assert_eq!(Permissions::CREATE, denied.permission);
+    assert_eq!("team", denied.name);
+    assert_eq!(Some(42), denied.owner);
+    assert!(!fake.exists("/team/report.pdf"));
+
+    // Folders owned by the user are not restricted
+    pcloud
+        .copy_folder("/team", "/own/")?
+        .check_permissions(true)
+        .execute()
+        .await?;
+    assert!(fake.exists("/own/team"));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_delete_and_move_in_share() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_shared_folder("/team", 42, Permissions::READ | Permissions::CREATE)?;
+    fake.put_file("/team/drafts/a.txt", "draft")?;
+    fake.create_folder("/own")?;
+    let pcloud = fake.client().await?;
+
+    let err = pcloud
+        .delete_folder("/team/drafts")?
+        .check_permissions(true)
+        .delete_recursive()
+        .await
+        .unwrap_err();
+    let denied = err.downcast_ref::<PermissionDenied>().unwrap();
+    assert_eq!(Permissions::DELETE, denied.permission);
+    assert_eq!("drafts", denied.name);
+    assert!(fake.exists("/team/drafts/a.txt"));
+
+    let err = pcloud
+        .move_file("/team/drafts/a.txt", "/own/")?
+        .check_permissions(true)
+        .execute()
+        .await
+        .unwrap_err();
+    assert!(err.downcast_ref::<PermissionDenied>().is_some());
+    assert!(fake.exists("/team/drafts/a.txt"));
+
+    // Moving into the share only needs the create permission
+    fake.put_file("/own/b.txt", "mine")?;
+    pcloud
+        .move_file("/own/b.txt", "/team/drafts/")?
+        .check_permissions(true)
+        .execute()
+        .await?;
+    assert!(fake.exists("/team/drafts/b.txt"));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_permission_flags() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_shared_folder("/team", 42, Permissions::READ | Permissions::CREATE)?;
+    fake.put_file("/team/a.txt", "a")?;
+    fake.create_folder("/own")?;
+    let pcloud = fake.client().await?;
+
+    let listing = pcloud.list_folder("/team")?.listing().await?;
+    let permissions = listing.folder().permissions.unwrap();
+    assert!(permissions.can_read());
+    assert!(permissions.can_create());
+    assert!(!permissions.can_delete());
+    assert!(!permissions.can_write());
+    assert!(permissions.contains(Permissions::CREATE));
+    let file = &listing.files()[0];
+    assert_eq!(Some(Permissions::READ), file.permissions);
+    // Files are written back without the create flag, as sent by pCloud
+    let json = serde_json::to_value(file)?;
+    assert_eq!(true, json["canread"]);
+    assert!(json.get("cancreate").is_none());
+
+    // Owned folders have no flags, but allow everything
+    let own = pcloud.list_folder("/own")?.listing().await?;
+    assert_eq!(None, own.folder().permissions);
+    assert!(own.folder().effective_permissions().can_write());
+
+    let share: Share = serde_json::from_str(
+        r#"{"folderid": 1, "created": null, "expires": null, "canread": true, "canmodify": true, "candelete": true, "cancreate": true}"#,
+    )?;
+    assert_eq!(Some(Permissions::all()), share.permissions);
+    let json = serde_json::to_value(&share)?;
+    assert_eq!(true, json["cancreate"]);
+    let removed: Share =
+        serde_json::from_str(r#"{"folderid": 1, "created": null, "expires": null}"#)?;
+    assert_eq!(None, removed.permissions);
+    Ok(())
+}
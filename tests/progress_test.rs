@@ -0,0 +1,204 @@
+use std::sync::{Arc, Mutex};
+
+use pcloud_async_api::{fake_pcloud::FakePCloud, progress::ProgressObserver};
+
+/// Records all events, with the last progress of each item
+#[derive(Default)]
+struct Recorder {
+    events: Mutex<Vec<String>>,
+}
+
+impl Recorder {
+    fn events(&self) -> Vec<String> {
+        self.events.lock().unwrap().clone()
+    }
+
+    fn push(&self, event: String) {
+        self.events.lock().unwrap().push(event);
+    }
+}
+
+impl ProgressObserver for Recorder {
+    fn operation_started(&self, items: Option<usize>, total_bytes: Option<u64>) {
+        self.push(format!("operation {:?} {:?}", items, total_bytes));
+    }
+
+    fn item_started(&self, item: &str, size: Option<u64>) {
+        self.push(format!("start {} {:?}", item, size));
+    }
+
+    fn item_progress(&self, item: &str, transferred: u64) {
+        let mut events = self.events.lock().unwrap();
+        let prefix = format!("progress {} ", item);
+        events.retain(|e| !e.starts_with(&prefix));
+        events.push(format!("{}{}", prefix, transferred));
+    }
+
+    fn item_finished(&self, item: &str, error: Option<&(dyn std::error::Error + Send + Sync)>) {
+        self.push(format!("finish {} {}", item, error.is_none()));
+    }
+
+    fn operation_finished(&self) {
+        self.push("finished".to_string());
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_upload_progress() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.create_folder("/uploads")?;
+    let pcloud = fake.client().await?;
+
+    let recorder = Arc::new(Recorder::default());
+    pcloud
+        .upload_file_into_folder("/uploads")?
+        .with_file_from_bytes("a.txt", vec![1u8; 3000].into())
+        .progress_observer(recorder.clone())
+        .upload()
+        .await?;
+
+    assert_eq!(
+        vec![
+            "operation Some(1) Some(3000)",
+            "start a.txt Some(3000)",
+            "progress a.txt 3000",
+            "finish a.txt true",
+            "finished",
+        ],
+        recorder.events()
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_upload_files_progress() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.create_folder("/uploads")?;
+    let pcloud = fake.client().await?;
+
+    let recorder = Arc::new(Recorder::default());
+    pcloud
+        .upload_files("/uploads", vec![("a.txt", "aaa"), ("b.txt", "bbbbb")])?
+        .concurrency(1)
+        .progress_observer(recorder.clone())
+        .upload()
+        .await?;
+
+    // The uploads of the single files only report their items
+    assert_eq!(
+        vec![
+            "operation Some(2) None",
+            "start a.txt Some(3)",
+            "progress a.txt 3",
+            "finish a.txt true",
+            "start b.txt Some(5)",
+            "progress b.txt 5",
+            "finish b.txt true",
+            "finished",
+        ],
+        recorder.events()
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_transfer_progress() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.create_folder("/transfers")?;
+    fake.put_file("/transfers/remote.bin", vec![7u8; 20_000])?;
+    let pcloud = fake.client().await?;
+
+    let local = std::env::temp_dir().join(format!("pcloud-progress-{}", std::process::id()));
+    std::fs::create_dir_all(&local)?;
+
+    let recorder = Arc::new(Recorder::default());
+    let report = pcloud
+        .transfer_manager()
+        .progress_observer(recorder.clone())
+        .download("/transfers/remote.bin", local.join("remote.bin"))?
+        .download("/transfers/missing.bin", local.join("missing.bin"))?
+        .concurrency(1)
+        .run()
+        .await?;
+    assert!(!report.is_success());
+
+    let download = format!(
+        "download of /transfers/remote.bin to {}",
+        local.join("remote.bin").display()
+    );
+    let missing = format!(
+        "download of /transfers/missing.bin to {}",
+        local.join("missing.bin").display()
+    );
+    assert_eq!(
+        vec![
+            "operation Some(2) None".to_string(),
+            format!("start {} Some(20000)", download),
+            format!("progress {} 20000", download),
+            format!("finish {} true", download),
+            format!("finish {} false", missing),
+            "finished".to_string(),
+        ],
+        recorder.events()
+    );
+
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_sync_and_directory_download_progress(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/docs/a.txt", "remote")?;
+    let pcloud = fake.client().await?;
+
+    let local = std::env::temp_dir().join(format!("pcloud-progress-sync-{}", std::process::id()));
+    std::fs::create_dir_all(local.join("notes"))?;
+    std::fs::write(local.join("notes/b.txt"), "local!!")?;
+
+    let recorder = Arc::new(Recorder::default());
+    pcloud
+        .sync_folder("/docs", &local)?
+        .progress_observer(recorder.clone())
+        .execute()
+        .await?;
+    assert_eq!(
+        vec![
+            "operation None None",
+            "start a.txt Some(6)",
+            "progress a.txt 6",
+            "finish a.txt true",
+            "start notes/b.txt Some(7)",
+            "progress notes/b.txt 7",
+            "finish notes/b.txt true",
+            "finished",
+        ],
+        recorder.events()
+    );
+
+    let target = local.join("download");
+    let recorder = Arc::new(Recorder::default());
+    pcloud
+        .download_directory("/docs", &target)?
+        .concurrency(1)
+        .progress_observer(recorder.clone())
+        .execute()
+        .await?;
+    assert_eq!(
+        vec![
+            "operation Some(2) Some(13)",
+            "start notes/b.txt Some(7)",
+            "progress notes/b.txt 7",
+            "finish notes/b.txt true",
+            "start a.txt Some(6)",
+            "progress a.txt 6",
+            "finish a.txt true",
+            "finished",
+        ],
+        recorder.events()
+    );
+
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
@@ -0,0 +1,36 @@
+use pcloud_async_api::{pcloud_client::PCloudClient, transport::MockTransport};
+use reqwest::header::HeaderValue;
+
+#[tokio::test]
+async fn test_proxy_download_with_range() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let transport = MockTransport::new();
+    transport.respond_with(
+        "getfilelink",
+        r#"{ "result": 0, "path": "/dl/test.txt", "hosts": ["c1.pcloud.com"], "expires": "Wed, 25 Jan 2023 12:09:14 +0000" }"#,
+    );
+    transport.respond_with_status("test.txt", 206, "nice");
+
+    let pcloud = PCloudClient::builder("https://api.pcloud.com")
+        .with_oauth("test-token")
+        .select_best_api_server(false)
+        .with_transport(transport.clone())
+        .build()
+        .await?;
+
+    let response = pcloud
+        .proxy_download(4711, Some(&HeaderValue::from_static("bytes=8-11")))
+        .await?;
+    assert_eq!(206, response.status().as_u16());
+    assert!(response.headers().contains_key("content-type"));
+
+    let body = hyper::body::to_bytes(response.into_body()).await?;
+    assert_eq!("nice", body);
+
+    let requests = transport.requests_for("test.txt");
+    assert_eq!(
+        Some(&HeaderValue::from_static("bytes=8-11")),
+        requests[0].headers.get("range")
+    );
+
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use std::time::{Duration, Instant};
+
+use pcloud_async_api::{fake_pcloud::FakePCloud, rate_limit::RateLimiter};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_request_rate_shared_by_clones() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/a.txt", "a")?;
+    let limiter = RateLimiter::new().max_requests_per_second(20);
+    let pcloud = fake
+        .client_builder()
+        .rate_limiter(limiter.clone())
+        .build()
+        .await?;
+    // A separately built client sharing the same limiter
+    let other = fake.client_builder().rate_limiter(limiter).build().await?;
+
+    let start = Instant::now();
+    let requests = (0..10).map(|i| {
+        let client = if i % 2 == 0 {
+            pcloud.clone()
+        } else {
+            other.clone()
+        };
+        async move { client.get_file_metadata("/a.txt").await }
+    });
+    for result in futures::future::join_all(requests).await {
+        assert!(result?.metadata.is_some());
+    }
+    // 10 requests at 20 per second, the first one starts immediately
+    assert!(start.elapsed() >= Duration::from_millis(400));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_bandwidth_shared_by_clones() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/data.bin", vec![7u8; 20_000])?;
+    let pcloud = fake
+        .client_builder()
+        .rate_limiter(RateLimiter::new().max_bytes_per_second(50_000))
+        .build()
+        .await?;
+
+    let start = Instant::now();
+    let upload = pcloud
+        .upload_file_into_folder("/")?
+        .with_file("upload.bin", vec![3u8; 20_000])
+        .upload();
+    let download = pcloud.download_file("/data.bin");
+    let (upload, download) = futures::future::join(upload, download).await;
+    assert_eq!(Some(20_000), upload?.into_metadata()?[0].size);
+    let response = download?;
+    assert!(response.status().is_success());
+    assert_eq!(vec![7u8; 20_000], response.bytes().await?.to_vec());
+    // 40 KB at 50 KB/s in total
+    assert!(start.elapsed() >= Duration::from_millis(600));
+    assert_eq!(Some(vec![3u8; 20_000]), fake.read_file("/upload.bin"));
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use pcloud_async_api::fake_pcloud::FakePCloud;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_restore_folder_to() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/docs/a.txt", "first version")?;
+    fake.put_file("/docs/sub/b.txt", "unchanged")?;
+    // pCloud reports times in seconds
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let timestamp = Utc::now();
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    fake.put_file("/docs/a.txt", "encrypted garbage")?;
+    fake.put_file("/docs/c.txt", "created later")?;
+    let pcloud = fake.client().await?;
+
+    let report = pcloud
+        .restore_folder_to("/docs", timestamp)?
+        .target("/restored")?
+        .execute()
+        .await?;
+
+    let mut restored: Vec<_> = report.restored.iter().map(|f| f.path.as_str()).collect();
+    restored.sort();
+    assert_eq!(vec!["a.txt", "sub/b.txt"], restored);
+    assert_eq!(vec!["c.txt".to_string()], report.skipped);
+    assert!(report.unavailable.is_empty());
+    let a = report.restored.iter().find(|f| f.path == "a.txt").unwrap();
+    assert!(a.revisionid.is_some());
+
+    assert_eq!(
+        Some(b"first version".to_vec()),
+        fake.read_file("/restored/a.txt")
+    );
+    assert_eq!(
+        Some(b"unchanged".to_vec()),
+        fake.read_file("/restored/sub/b.txt")
+    );
+    assert!(!fake.exists("/restored/c.txt"));
+    // The folder itself is untouched
+    assert_eq!(
+        Some(b"encrypted garbage".to_vec()),
+        fake.read_file("/docs/a.txt")
+    );
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_restore_folder_default_target() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/docs/a.txt", "content")?;
+    let pcloud = fake.client().await?;
+
+    let timestamp = Utc::now() + chrono::Duration::seconds(5);
+    let report = pcloud
+        .restore_folder_to("/docs", timestamp)?
+        .execute()
+        .await?;
+    assert_eq!(1, report.restored.len());
+    assert_eq!(None, report.restored[0].revisionid);
+
+    let target = format!("/docs (restored {})", timestamp.format("%Y-%m-%d %H.%M.%S"));
+    assert_eq!(
+        Some(b"content".to_vec()),
+        fake.read_file(&format!("{}/a.txt", target))
+    );
+    Ok(())
+}
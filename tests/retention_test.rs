@@ -0,0 +1,94 @@
+use chrono::{Duration, Utc};
+use pcloud_async_api::{fake_pcloud::FakePCloud, retention::RetentionReason};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_retention_by_age() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/backups/old.tar", "old backup")?;
+    fake.put_file("/backups/keep/old.tar", "excluded backup")?;
+    fake.put_file("/backups/new.tar", "new backup")?;
+    let pcloud = fake.client().await?;
+    let old = Utc::now() - Duration::days(40);
+    pcloud
+        .set_file_times("/backups/old.tar", &old, None)
+        .await?;
+    pcloud
+        .set_file_times("/backups/keep/old.tar", &old, None)
+        .await?;
+
+    let report = pcloud
+        .apply_retention("/backups")?
+        .older_than_days(30)
+        .exclude("/keep/")
+        .dry_run(true)
+        .execute()
+        .await?;
+    assert!(report.dry_run);
+    assert_eq!(1, report.expired.len());
+    assert_eq!("old.tar", report.expired[0].path);
+    assert_eq!(RetentionReason::Age, report.expired[0].reason);
+    assert_eq!(10, report.expired_bytes());
+    assert_eq!(1, report.kept);
+    assert!(fake.exists("/backups/old.tar"));
+
+    let report = pcloud
+        .apply_retention("/backups")?
+        .older_than_days(30)
+        .exclude("/keep/")
+        .execute()
+        .await?;
+    assert_eq!(1, report.expired.len());
+    assert!(!fake.exists("/backups/old.tar"));
+    assert!(fake.exists("/backups/keep/old.tar"));
+    assert!(fake.exists("/backups/new.tar"));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_retention_by_count_with_archive(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let pcloud = fake.client().await?;
+    for day in 1..=4 {
+        let path = format!("/backups/daily/{}.tar", day);
+        fake.put_file(&path, format!("backup {}", day))?;
+        pcloud
+            .set_file_times(
+                path.as_str(),
+                &(Utc::now() - Duration::days(10 - day)),
+                None,
+            )
+            .await?;
+    }
+    fake.put_file("/backups/weekly/1.tar", "weekly backup")?;
+
+    let report = pcloud
+        .apply_retention("/backups")?
+        .keep_per_folder(2)
+        .archive_to("/backups/archive")?
+        .execute()
+        .await?;
+    let mut expired: Vec<_> = report.expired.iter().map(|f| f.path.as_str()).collect();
+    expired.sort();
+    assert_eq!(vec!["daily/1.tar", "daily/2.tar"], expired);
+    assert!(report
+        .expired
+        .iter()
+        .all(|f| f.reason == RetentionReason::Count));
+    assert_eq!(3, report.kept);
+
+    assert!(fake.exists("/backups/archive/daily/1.tar"));
+    assert!(fake.exists("/backups/archive/daily/2.tar"));
+    assert!(!fake.exists("/backups/daily/1.tar"));
+    assert!(fake.exists("/backups/daily/4.tar"));
+
+    // The archive itself is never cleaned up
+    let report = pcloud
+        .apply_retention("/backups")?
+        .keep_per_folder(2)
+        .archive_to("/backups/archive")?
+        .execute()
+        .await?;
+    assert!(report.expired.is_empty());
+    Ok(())
+}
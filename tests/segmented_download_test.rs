@@ -0,0 +1,128 @@
+use std::error::Error;
+
+use futures::future::BoxFuture;
+use pcloud_async_api::{
+    fake_pcloud::FakePCloud, file_ops::ChecksumMismatch, transport::HttpTransport,
+};
+use reqwest::{Request, Response};
+
+#[tokio::test]
+async fn test_segmented_download() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let content: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+    fake.put_file("/docs/big.bin", content.clone())?;
+    fake.put_file("/docs/empty.bin", "")?;
+    let pcloud = fake.client().await?;
+
+    let local = std::env::temp_dir().join(format!("pcloud-segmented-{}", std::process::id()));
+    std::fs::create_dir_all(&local)?;
+
+    // Several segments, the last one shorter
+    let info = pcloud
+        .segmented_download("/docs/big.bin", local.join("big.bin"))?
+        .segment_size(64 * 1024)
+        .parallelism(3)
+        .execute()
+        .await?;
+    assert_eq!(Some(300_000), info.content_length);
+    assert_eq!(content, std::fs::read(local.join("big.bin"))?);
+    assert!(!local.join("big.bin.part").exists());
+
+    // A single segment covering the whole file
+    pcloud
+        .segmented_download("/docs/big.bin", local.join("single.bin"))?
+        .execute()
+        .await?;
+    assert_eq!(content, std::fs::read(local.join("single.bin"))?);
+
+    // Empty files don't need any segment
+    pcloud
+        .segmented_download("/docs/empty.bin", local.join("empty.bin"))?
+        .execute()
+        .await?;
+    assert!(std::fs::read(local.join("empty.bin"))?.is_empty());
+
+    // Missing files fail without leaving anything behind
+    assert!(pcloud
+        .segmented_download("/docs/missing.bin", local.join("missing.bin"))?
+        .execute()
+        .await
+        .is_err());
+    assert!(!local.join("missing.bin").exists());
+    assert!(!local.join("missing.bin.part").exists());
+
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
+
+/// Transport reporting a wrong SHA-1 checksum of all files
+struct CorruptingTransport {
+    fake: FakePCloud,
+}
+
+impl HttpTransport for CorruptingTransport {
+    fn execute<'a>(
+        &'a self,
+        request: Request,
+    ) -> BoxFuture<'a, Result<Response, Box<dyn Error + Send + Sync>>> {
+        Box::pin(async move {
+            if !request.url().path().ends_with("/checksumfile") {
+                return self.fake.execute(request).await;
+            }
+            let response = self.fake.execute(request).await?;
+            let mut body: serde_json::Value = response.json().await?;
+            body["sha1"] = serde_json::json!("0000000000000000000000000000000000000000");
+            Ok(hyper::Response::new(body.to_string()).into())
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_segmented_download_verifies_checksum() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let content: Vec<u8> = (0..200_000u32).map(|i| (i % 241) as u8).collect();
+    fake.put_file("/docs/big.bin", content.clone())?;
+    let pcloud = fake.client().await?;
+
+    let local =
+        std::env::temp_dir().join(format!("pcloud-segmented-verify-{}", std::process::id()));
+    std::fs::create_dir_all(&local)?;
+
+    pcloud
+        .segmented_download("/docs/big.bin", local.join("big.bin"))?
+        .segment_size(50_000)
+        .verify_checksum(true)
+        .execute()
+        .await?;
+    assert_eq!(content, std::fs::read(local.join("big.bin"))?);
+
+    // Corrupt transfers are rejected and not kept
+    let corrupting = fake
+        .client_builder()
+        .with_transport(CorruptingTransport { fake: fake.clone() })
+        .build()
+        .await?;
+    let error = corrupting
+        .segmented_download("/docs/big.bin", local.join("corrupt.bin"))?
+        .segment_size(50_000)
+        .verify_checksum(true)
+        .execute()
+        .await
+        .unwrap_err();
+    assert_eq!(
+        "SHA-1",
+        error.downcast_ref::<ChecksumMismatch>().unwrap().algorithm
+    );
+    assert!(!local.join("corrupt.bin").exists());
+    assert!(!local.join("corrupt.bin.part").exists());
+
+    // Without verification the checksums aren't compared
+    corrupting
+        .segmented_download("/docs/big.bin", local.join("corrupt.bin"))?
+        .execute()
+        .await?;
+    assert_eq!(content, std::fs::read(local.join("corrupt.bin"))?);
+
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
@@ -0,0 +1,145 @@
+use std::{error::Error, sync::Arc, time::Duration};
+
+use futures::future::BoxFuture;
+use pcloud_async_api::{
+    fake_pcloud::FakePCloud,
+    shutdown::{ShutDown, ShutdownSignal},
+    transport::HttpTransport,
+};
+use reqwest::{Request, Response};
+use tokio::sync::{Notify, Semaphore};
+
+/// Transport holding back file downloads until the gate is opened
+struct GatedTransport {
+    fake: FakePCloud,
+    /// Notified when a download arrives at the gate
+    arrived: Arc<Notify>,
+    /// Each permit lets a single download pass
+    gate: Arc<Semaphore>,
+}
+
+impl HttpTransport for GatedTransport {
+    fn execute<'a>(
+        &'a self,
+        request: Request,
+    ) -> BoxFuture<'a, Result<Response, Box<dyn Error + Send + Sync>>> {
+        Box::pin(async move {
+            if request.url().path().starts_with("/dl/") {
+                self.arrived.notify_one();
+                self.gate.acquire().await?.forget();
+            }
+            self.fake.execute(request).await
+        })
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_client_shutdown() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/docs/a.txt", "content")?;
+    let pcloud = fake
+        .client_builder()
+        .with_username_and_password("user", "password")
+        .build()
+        .await?;
+
+    assert!(pcloud.get_file_metadata("/docs/a.txt").await.is_ok());
+    assert!(pcloud.shutdown(Duration::from_secs(1)).await?);
+    assert!(pcloud.shutdown_signal().is_triggered());
+
+    let error = pcloud.get_file_metadata("/docs/a.txt").await.unwrap_err();
+    assert!(error.downcast_ref::<ShutDown>().is_some());
+    // Shutting down again doesn't log out twice
+    assert!(pcloud.shutdown(Duration::from_secs(1)).await?);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_transfer_manager_shutdown() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/transfers/a.bin", vec![1u8; 1000])?;
+    fake.put_file("/transfers/b.bin", vec![2u8; 1000])?;
+    let pcloud = fake.client().await?;
+
+    let local = std::env::temp_dir().join(format!("pcloud-shutdown-{}", std::process::id()));
+    std::fs::create_dir_all(&local)?;
+
+    // Triggered before the run, nothing is started
+    let signal = ShutdownSignal::new();
+    signal.trigger(Duration::ZERO);
+    let report = pcloud
+        .transfer_manager()
+        .shutdown_signal(signal)
+        .download("/transfers/a.bin", local.join("a.bin"))?
+        .download("/transfers/b.bin", local.join("b.bin"))?
+        .run()
+        .await?;
+    assert!(report.outcomes.is_empty());
+    assert_eq!(2, report.interrupted.len());
+    assert!(!report.is_success());
+    assert!(!local.join("a.bin").exists());
+
+    // Triggered while running, transfers queued afterwards aren't started and the run completes although the queue is still open
+    let arrived = Arc::new(Notify::new());
+    let gate = Arc::new(Semaphore::new(0));
+    let pcloud = fake
+        .client_builder()
+        .with_transport(GatedTransport {
+            fake: fake.clone(),
+            arrived: arrived.clone(),
+            gate: gate.clone(),
+        })
+        .build()
+        .await?;
+    let signal = ShutdownSignal::new();
+    let manager = pcloud
+        .transfer_manager()
+        .shutdown_signal(signal.clone())
+        .download("/transfers/a.bin", local.join("a.bin"))?;
+    let queue = manager.queue();
+    let run = tokio::spawn(manager.run());
+    // The first transfer is in flight until the gate is opened
+    tokio::time::timeout(Duration::from_secs(5), arrived.notified()).await?;
+    signal.trigger(Duration::from_secs(5));
+    queue.download("/transfers/b.bin", local.join("b.bin"), None)?;
+    gate.add_permits(1);
+    let report = tokio::time::timeout(Duration::from_secs(5), run).await???;
+    assert_eq!(1, report.outcomes.len());
+    assert!(report.outcomes[0].result.is_ok());
+    assert_eq!(1, report.interrupted.len());
+    assert!(local.join("a.bin").exists());
+    assert!(!local.join("b.bin").exists());
+
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_sync_shutdown() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/docs/a.txt", "remote")?;
+    let pcloud = fake.client().await?;
+
+    let local = std::env::temp_dir().join(format!("pcloud-shutdown-sync-{}", std::process::id()));
+    std::fs::create_dir_all(&local)?;
+
+    let signal = ShutdownSignal::new();
+    signal.trigger(Duration::ZERO);
+    let report = pcloud
+        .sync_folder("/docs", &local)?
+        .shutdown_signal(signal)
+        .execute()
+        .await?;
+    assert!(report.interrupted);
+    assert!(!report.has_changes());
+    assert!(!local.join("a.txt").exists());
+
+    // The next run continues
+    let report = pcloud.sync_folder("/docs", &local)?.execute().await?;
+    assert!(!report.interrupted);
+    assert!(report.remote_listed);
+    assert_eq!("remote", std::fs::read_to_string(local.join("a.txt"))?);
+
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
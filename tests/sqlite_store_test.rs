@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use pcloud_async_api::{
+    fake_pcloud::FakePCloud,
+    sqlite_store::SqliteSyncStateStore,
+    sync::{SyncAction, SyncStateStore},
+};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_sync_with_sqlite_store() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/docs/a.txt", "This is nice test content")?;
+    let pcloud = fake.client().await?;
+
+    let local = std::env::temp_dir().join(format!("pcloud-sqlite-sync-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&local);
+    std::fs::create_dir_all(&local)?;
+    std::fs::write(local.join("b.txt"), "This is local content")?;
+
+    // The database is located in the synced directory, but never synced itself
+    let database = local.join("sync.db");
+    let store = Arc::new(SqliteSyncStateStore::open(&database)?);
+    let report = pcloud
+        .sync_folder("/docs", &local)?
+        .state_store(store.clone())
+        .execute()
+        .await?;
+    assert_eq!(2, report.operations.len());
+    assert!(!fake.exists("/docs/sync.db"));
+    assert!(!local.join(".pcloud-sync.json").exists());
+    drop(store);
+
+    // Reopened, the state survives
+    let store = Arc::new(SqliteSyncStateStore::open(&database)?);
+    let mut entries = store.entries()?;
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        vec!["a.txt", "b.txt"],
+        entries.iter().map(|(p, _)| p.as_str()).collect::<Vec<_>>()
+    );
+    assert_eq!(21, entries[1].1.local_size);
+    assert!(store.load_checkpoint()?.diffid.is_some());
+
+    std::fs::remove_file(local.join("a.txt"))?;
+    let report = pcloud
+        .sync_folder("/docs", &local)?
+        .state_store(store.clone())
+        .execute()
+        .await?;
+    assert_eq!(1, report.operations.len());
+    assert!(!fake.exists("/docs/a.txt"));
+    assert_eq!(1, store.entries()?.len());
+
+    let actions: Vec<(SyncAction, String)> = store
+        .operations()?
+        .into_iter()
+        .map(|o| (o.action, o.path))
+        .collect();
+    assert_eq!(
+        vec![
+            (SyncAction::Download, "a.txt".to_string()),
+            (SyncAction::Upload, "b.txt".to_string()),
+            (SyncAction::DeleteRemote, "a.txt".to_string()),
+        ],
+        actions
+    );
+
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use pcloud_async_api::{
+    fake_pcloud::FakePCloud,
+    sync::{SyncAction, SyncReport},
+    sync_session::SyncSession,
+};
+
+/// Waits for the first successful run applying the given action to the given path
+async fn wait_for(
+    session: &mut SyncSession,
+    action: SyncAction,
+    path: &str,
+) -> Result<SyncReport, Box<dyn std::error::Error + Send + Sync>> {
+    loop {
+        let report = tokio::time::timeout(Duration::from_secs(10), session.next_run())
+            .await?
+            .ok_or("Session stopped")??;
+        if report
+            .operations
+            .iter()
+            .any(|o| o.action == action && o.path == path)
+        {
+            return Ok(report);
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_sync_session() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/docs/a.txt", "remote a")?;
+    let pcloud = fake.client().await?;
+
+    let local = std::env::temp_dir().join(format!("pcloud-session-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&local);
+    std::fs::create_dir_all(&local)?;
+    std::fs::write(local.join("b.txt"), "local b")?;
+
+    let mut session = pcloud
+        .sync_session("/docs", &local)?
+        .window(Duration::from_millis(100))
+        .rescan_interval(Duration::from_millis(300))
+        .start()
+        .await?;
+
+    // Initial run in both directions
+    let report = wait_for(&mut session, SyncAction::Download, "a.txt").await?;
+    assert!(report
+        .operations
+        .iter()
+        .any(|o| o.action == SyncAction::Upload && o.path == "b.txt"));
+    assert_eq!("remote a", std::fs::read_to_string(local.join("a.txt"))?);
+    assert_eq!(Some(b"local b".to_vec()), fake.read_file("/docs/b.txt"));
+
+    // Remote changes arrive as diff events
+    fake.put_file("/docs/sub/c.txt", "remote c")?;
+    wait_for(&mut session, SyncAction::Download, "sub/c.txt").await?;
+    assert_eq!(
+        "remote c",
+        std::fs::read_to_string(local.join("sub").join("c.txt"))?
+    );
+
+    // Local changes are picked up by the rescan
+    std::fs::write(local.join("d.txt"), "local d")?;
+    wait_for(&mut session, SyncAction::Upload, "d.txt").await?;
+    assert_eq!(Some(b"local d".to_vec()), fake.read_file("/docs/d.txt"));
+
+    session.stop().await;
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_sync_session_without_pushing() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/docs/a.txt", "remote a")?;
+    fake.put_file("/docs/b.txt", "remote b")?;
+    let pcloud = fake.client().await?;
+
+    let local = std::env::temp_dir().join(format!("pcloud-session-pull-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&local);
+    std::fs::create_dir_all(&local)?;
+    std::fs::write(local.join("b.txt"), "local b, conflicting")?;
+    std::fs::write(local.join("c.txt"), "local c")?;
+
+    let mut session = pcloud
+        .sync_session("/docs", &local)?
+        .window(Duration::from_millis(100))
+        .push_local_changes(false)
+        .start()
+        .await?;
+
+    // Conflicts are resolved in favor of the remote version, local files are left alone
+    let report = wait_for(&mut session, SyncAction::Download, "a.txt").await?;
+    assert_eq!(1, report.local_changes_pending);
+    assert!(report
+        .operations
+        .iter()
+        .all(|o| o.action == SyncAction::Download));
+    assert_eq!("remote b", std::fs::read_to_string(local.join("b.txt"))?);
+    assert_eq!("local c", std::fs::read_to_string(local.join("c.txt"))?);
+    assert!(!fake.exists("/docs/c.txt"));
+
+    // Changes outside the folder don't start a run
+    fake.put_file("/other/e.txt", "remote e")?;
+    assert!(
+        tokio::time::timeout(Duration::from_millis(500), session.next_run())
+            .await
+            .is_err()
+    );
+    fake.put_file("/docs/f.txt", "remote f")?;
+    wait_for(&mut session, SyncAction::Download, "f.txt").await?;
+
+    drop(session);
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
@@ -0,0 +1,511 @@
+use pcloud_async_api::{
+    directory_download::{
+        DownloadEntry, DownloadState, OverwritePolicy, DEFAULT_DOWNLOAD_STATE_FILE,
+    },
+    fake_pcloud::FakePCloud,
+    pcloud_model::Metadata,
+    sync::{ConflictResolution, ConflictStrategy, SyncAction, SyncOperation},
+    transfer::TransferPriority,
+};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_incremental_mirror() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/photos/a.txt", "This is nice test content")?;
+    fake.put_file("/photos/2023/b.txt", "This is other content")?;
+    fake.put_file("/photos/2023/c.txt", "This is more content")?;
+    let pcloud = fake.client().await?;
+
+    let local = std::env::temp_dir().join(format!("pcloud-mirror-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&local);
+    std::fs::create_dir_all(&local)?;
+    std::fs::write(local.join("local-only.txt"), "Never mirrored")?;
+
+    let report = pcloud.mirror_folder("/photos", &local)?.execute().await?;
+    assert_eq!(vec!["2023/b.txt", "2023/c.txt", "a.txt"], report.added);
+    assert_eq!(0, report.unchanged);
+    assert_eq!(
+        "This is other content",
+        std::fs::read_to_string(local.join("2023").join("b.txt"))?
+    );
+
+    // Nothing changed
+    let report = pcloud.mirror_folder("/photos", &local)?.execute().await?;
+    assert!(!report.has_changes());
+    assert_eq!(3, report.unchanged);
+
+    // Changed, new and removed files
+    fake.put_file("/photos/a.txt", "This is changed content")?;
+    fake.put_file("/photos/d.txt", "This is new content")?;
+    pcloud.delete_file("/photos/2023/c.txt").await?;
+    std::fs::remove_file(local.join("2023").join("b.txt"))?;
+
+    let report = pcloud
+        .mirror_folder("/photos", &local)?
+        .delete_removed(true)
+        .execute()
+        .await?;
+    assert_eq!(vec!["d.txt"], report.added);
+    assert_eq!(vec!["2023/b.txt", "a.txt"], report.updated);
+    assert_eq!(vec!["2023/c.txt"], report.deleted);
+    assert_eq!(0, report.unchanged);
+    assert_eq!(
+        "This is changed content",
+        std::fs::read_to_string(local.join("a.txt"))?
+    );
+    assert!(!local.join("2023").join("c.txt").exists());
+    // Files never mirrored are kept
+    assert!(local.join("local-only.txt").exists());
+
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_resume_directory_download() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let a = fake.put_file("/photos/a.txt", "This is nice test content")?;
+    let b = fake.put_file("/photos/2023/b.txt", "This is other content")?;
+    let pcloud = fake.client().await?;
+
+    let local = std::env::temp_dir().join(format!("pcloud-download-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&local);
+
+    let report = pcloud
+        .download_directory("/photos", &local)?
+        .execute()
+        .await?;
+    assert_eq!(2, report.downloaded);
+    assert!(!report.resumed);
+    assert_eq!(
+        "This is other content",
+        std::fs::read_to_string(local.join("2023").join("b.txt"))?
+    );
+    // Completed downloads leave no state behind
+    assert!(!local.join(DEFAULT_DOWNLOAD_STATE_FILE).exists());
+
+    // Interrupted run: a.txt completed, b.txt missing. The listing of the state is used, c.txt is not known.
+    fake.put_file("/photos/c.txt", "This is more content")?;
+    std::fs::remove_file(local.join("2023").join("b.txt"))?;
+    let listing = pcloud.list_folder("/photos")?.recursive(true).get().await?;
+    let hash_of = |id: u64| {
+        fn find(contents: &[Metadata], id: u64) -> Option<u64> {
+            contents.iter().find_map(|m| {
+                if m.fileid == Some(id) {
+                    m.hash
+                } else {
+                    find(&m.contents, id)
+                }
+            })
+        }
+        find(&listing.metadata.as_ref().unwrap().contents, id)
+    };
+    let state = DownloadState {
+        folder: "/photos".to_string(),
+        files: vec![
+            DownloadEntry {
+                path: "a.txt".to_string(),
+                fileid: a,
+                hash: hash_of(a),
+                size: Some(25),
+            },
+            DownloadEntry {
+                path: "2023/b.txt".to_string(),
+                fileid: b,
+                hash: hash_of(b),
+                size: Some(21),
+            },
+        ],
+        completed: [(a, hash_of(a))].into_iter().collect(),
+    };
+    state.save(&local.join(DEFAULT_DOWNLOAD_STATE_FILE)).await?;
+
+    let report = pcloud
+        .download_directory("/photos", &local)?
+        .execute()
+        .await?;
+    assert!(report.resumed);
+    assert_eq!(1, report.skipped);
+    assert_eq!(1, report.downloaded);
+    assert!(local.join("2023").join("b.txt").exists());
+    assert!(!local.join("c.txt").exists());
+
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_directory_download_overwrite_policy(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/photos/a.txt", "remote a")?;
+    fake.put_file("/photos/2023/b.txt", "remote b")?;
+    fake.put_file("/photos/2023/c.txt", "remote c")?;
+    fake.put_file("/photos/2024/d.txt", "remote d")?;
+    let pcloud = fake.client().await?;
+
+    let local =
+        std::env::temp_dir().join(format!("pcloud-download-overwrite-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&local);
+    std::fs::create_dir_all(local.join("2023"))?;
+    std::fs::write(local.join("a.txt"), "local a!")?;
+    std::fs::write(local.join("2023").join("b.txt"), "local b")?;
+
+    // Existing files are kept, the others downloaded in parallel
+    let report = pcloud
+        .download_directory("/photos", &local)?
+        .overwrite(OverwritePolicy::Never)
+        .concurrency(3)
+        .execute()
+        .await?;
+    assert_eq!(2, report.kept);
+    assert_eq!(2, report.downloaded);
+    assert_eq!("local a!", std::fs::read_to_string(local.join("a.txt"))?);
+    assert_eq!(
+        "local b",
+        std::fs::read_to_string(local.join("2023").join("b.txt"))?
+    );
+    assert_eq!(
+        "remote c",
+        std::fs::read_to_string(local.join("2023").join("c.txt"))?
+    );
+    assert_eq!(
+        "remote d",
+        std::fs::read_to_string(local.join("2024").join("d.txt"))?
+    );
+
+    // Only files with a different size are replaced
+    let report = pcloud
+        .download_directory("/photos", &local)?
+        .overwrite(OverwritePolicy::IfSizeDiffers)
+        .execute()
+        .await?;
+    assert_eq!(3, report.kept);
+    assert_eq!(1, report.downloaded);
+    assert_eq!("local a!", std::fs::read_to_string(local.join("a.txt"))?);
+    assert_eq!(
+        "remote b",
+        std::fs::read_to_string(local.join("2023").join("b.txt"))?
+    );
+
+    // All files are replaced by default
+    let report = pcloud
+        .download_directory("/photos", &local)?
+        .execute()
+        .await?;
+    assert_eq!(0, report.kept);
+    assert_eq!(4, report.downloaded);
+    assert_eq!("remote a", std::fs::read_to_string(local.join("a.txt"))?);
+
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_directory_download_failures() -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+{
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/photos/a.txt", "remote a")?;
+    fake.put_file("/photos/2023/b.txt", "remote b")?;
+    fake.put_file("/photos/2023/c.txt", "remote c")?;
+    let pcloud = fake.client().await?;
+
+    let local =
+        std::env::temp_dir().join(format!("pcloud-download-failures-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&local);
+    // A directory in place of a.txt can't be replaced by the download
+    std::fs::create_dir_all(local.join("a.txt"))?;
+    std::fs::write(local.join("a.txt").join("keep"), "blocking")?;
+
+    // The other files are still downloaded, no partial files are left behind
+    let report = pcloud
+        .download_directory("/photos", &local)?
+        .execute()
+        .await?;
+    assert!(!report.is_success());
+    assert_eq!(2, report.downloaded);
+    assert_eq!(
+        vec!["a.txt"],
+        report
+            .failed
+            .iter()
+            .map(|f| f.path.as_str())
+            .collect::<Vec<_>>()
+    );
+    assert!(!local.join("a.txt.part").exists());
+    assert_eq!(
+        "remote c",
+        std::fs::read_to_string(local.join("2023").join("c.txt"))?
+    );
+    assert!(local.join(DEFAULT_DOWNLOAD_STATE_FILE).exists());
+
+    // The state is kept, so another run only retries the failed file
+    std::fs::remove_dir_all(local.join("a.txt"))?;
+    let report = pcloud
+        .download_directory("/photos", &local)?
+        .execute()
+        .await?;
+    assert!(report.is_success());
+    assert!(report.resumed);
+    assert_eq!(1, report.downloaded);
+    assert_eq!(2, report.skipped);
+    assert_eq!("remote a", std::fs::read_to_string(local.join("a.txt"))?);
+    assert!(!local.join(DEFAULT_DOWNLOAD_STATE_FILE).exists());
+
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_two_way_sync() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/docs/a.txt", "This is nice test content")?;
+    fake.put_file("/docs/sub/b.txt", "This is other content")?;
+    let pcloud = fake.client().await?;
+
+    let local = std::env::temp_dir().join(format!("pcloud-sync-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&local);
+    std::fs::create_dir_all(local.join("new"))?;
+    std::fs::write(local.join("new").join("c.txt"), "This is local content")?;
+
+    let report = pcloud.sync_folder("/docs", &local)?.execute().await?;
+    let actions: Vec<(SyncAction, &str)> = report
+        .operations
+        .iter()
+        .map(|o| (o.action, o.path.as_str()))
+        .collect();
+    assert_eq!(
+        vec![
+            (SyncAction::Download, "a.txt"),
+            (SyncAction::Upload, "new/c.txt"),
+            (SyncAction::Download, "sub/b.txt"),
+        ],
+        actions
+    );
+    assert_eq!(
+        Some(b"This is local content".to_vec()),
+        fake.read_file("/docs/new/c.txt")
+    );
+    assert_eq!(
+        "This is other content",
+        std::fs::read_to_string(local.join("sub").join("b.txt"))?
+    );
+
+    // The events of the own upload make the next run list the folder once more
+    let report = pcloud.sync_folder("/docs", &local)?.execute().await?;
+    assert!(!report.has_changes());
+    assert!(report.remote_listed);
+    let report = pcloud.sync_folder("/docs", &local)?.execute().await?;
+    assert!(!report.has_changes());
+    assert!(!report.remote_listed);
+    assert_eq!(3, report.unchanged);
+
+    // Changes on both sides
+    fake.put_file("/docs/a.txt", "This is changed remote content")?;
+    std::fs::write(
+        local.join("sub").join("b.txt"),
+        "This is changed local content",
+    )?;
+    std::fs::remove_file(local.join("new").join("c.txt"))?;
+
+    let report = pcloud.sync_folder("/docs", &local)?.execute().await?;
+    let actions: Vec<(SyncAction, &str)> = report
+        .operations
+        .iter()
+        .map(|o| (o.action, o.path.as_str()))
+        .collect();
+    assert_eq!(
+        vec![
+            (SyncAction::Download, "a.txt"),
+            (SyncAction::DeleteRemote, "new/c.txt"),
+            (SyncAction::Upload, "sub/b.txt"),
+        ],
+        actions
+    );
+    assert_eq!(
+        "This is changed remote content",
+        std::fs::read_to_string(local.join("a.txt"))?
+    );
+    assert_eq!(
+        Some(b"This is changed local content".to_vec()),
+        fake.read_file("/docs/sub/b.txt")
+    );
+    assert!(!fake.exists("/docs/new/c.txt"));
+
+    // Deleted remotely
+    pcloud.delete_file("/docs/a.txt").await?;
+    let report = pcloud.sync_folder("/docs", &local)?.execute().await?;
+    assert_eq!(SyncAction::DeleteLocal, report.operations[0].action);
+    assert!(!local.join("a.txt").exists());
+
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_two_way_sync_conflicts() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.put_file("/docs/a.txt", "This is nice test content")?;
+    fake.put_file("/docs/b.txt", "This is other content")?;
+    let pcloud = fake.client().await?;
+
+    let local = std::env::temp_dir().join(format!("pcloud-conflict-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&local);
+    std::fs::create_dir_all(&local)?;
+    // Identical content on both sides is no conflict
+    std::fs::write(local.join("a.txt"), "This is nice test content")?;
+    let log = std::env::temp_dir().join(format!("pcloud-conflict-{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&log);
+
+    let report = pcloud.sync_folder("/docs", &local)?.execute().await?;
+    assert_eq!(0, report.conflicts);
+    assert_eq!(1, report.operations.len());
+
+    // Keep both
+    fake.put_file("/docs/a.txt", "This is changed remote content")?;
+    std::fs::write(local.join("a.txt"), "This is changed local content")?;
+    let report = pcloud
+        .sync_folder("/docs", &local)?
+        .conflict_strategy(ConflictStrategy::KeepBoth)
+        .operation_log(&log)
+        .execute()
+        .await?;
+    assert_eq!(1, report.conflicts);
+    let copy = report.operations[0].path.clone();
+    assert!(copy.starts_with("a (conflict "));
+    assert_eq!(
+        "This is changed local content",
+        std::fs::read_to_string(local.join(&copy))?
+    );
+    assert_eq!(
+        Some(b"This is changed local content".to_vec()),
+        fake.read_file(&format!("/docs/{}", copy))
+    );
+    assert_eq!(
+        "This is changed remote content",
+        std::fs::read_to_string(local.join("a.txt"))?
+    );
+
+    // Prompt
+    fake.put_file("/docs/b.txt", "This is changed remote content")?;
+    std::fs::write(local.join("b.txt"), "This is changed local content")?;
+    let report = pcloud
+        .sync_folder("/docs", &local)?
+        .on_conflict(|conflict| {
+            assert_eq!("b.txt", conflict.path);
+            ConflictResolution::KeepLocal
+        })
+        .operation_log(&log)
+        .execute()
+        .await?;
+    assert_eq!(1, report.conflicts);
+    assert_eq!(
+        Some(b"This is changed local content".to_vec()),
+        fake.read_file("/docs/b.txt")
+    );
+
+    // All operations are logged
+    let logged = std::fs::read_to_string(&log)?
+        .lines()
+        .map(serde_json::from_str::<SyncOperation>)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(3, logged.len());
+    assert_eq!(Some(ConflictResolution::KeepBoth), logged[0].conflict);
+    assert_eq!(Some(ConflictResolution::KeepLocal), logged[2].conflict);
+
+    std::fs::remove_dir_all(&local)?;
+    std::fs::remove_file(&log)?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_two_way_sync_moves() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let a = fake.put_file("/docs/a.txt", "This is nice test content")?;
+    fake.put_file("/docs/b.txt", "This is other content")?;
+    let pcloud = fake.client().await?;
+
+    let local = std::env::temp_dir().join(format!("pcloud-sync-moves-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&local);
+    pcloud.sync_folder("/docs", &local)?.execute().await?;
+
+    // Moved locally, the remote file keeps its id
+    std::fs::create_dir_all(local.join("archive"))?;
+    std::fs::rename(local.join("a.txt"), local.join("archive").join("a.txt"))?;
+    let report = pcloud.sync_folder("/docs", &local)?.execute().await?;
+    assert_eq!(1, report.operations.len());
+    let operation = &report.operations[0];
+    assert_eq!(SyncAction::MoveRemote, operation.action);
+    assert_eq!("archive/a.txt", operation.path);
+    assert_eq!(Some("a.txt"), operation.from.as_deref());
+    assert!(!fake.exists("/docs/a.txt"));
+    assert_eq!(
+        a,
+        pcloud
+            .get_file_metadata("/docs/archive/a.txt")
+            .await?
+            .metadata
+            .and_then(|m| m.fileid)
+            .unwrap()
+    );
+
+    // Renamed remotely
+    pcloud
+        .move_file("/docs/b.txt", "/docs/")?
+        .with_new_name("c.txt")
+        .execute()
+        .await?;
+    let report = pcloud.sync_folder("/docs", &local)?.execute().await?;
+    let actions: Vec<(SyncAction, &str, Option<&str>)> = report
+        .operations
+        .iter()
+        .map(|o| (o.action, o.path.as_str(), o.from.as_deref()))
+        .collect();
+    assert_eq!(
+        vec![(SyncAction::MoveLocal, "c.txt", Some("b.txt"))],
+        actions
+    );
+    assert!(!local.join("b.txt").exists());
+    assert_eq!(
+        "This is other content",
+        std::fs::read_to_string(local.join("c.txt"))?
+    );
+
+    let report = pcloud.sync_folder("/docs", &local)?.execute().await?;
+    assert!(!report.has_changes());
+
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_two_way_sync_priorities() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.create_folder("/docs")?;
+    let pcloud = fake.client().await?;
+
+    let local = std::env::temp_dir().join(format!("pcloud-sync-priorities-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&local);
+    std::fs::create_dir_all(local.join("urgent"))?;
+    std::fs::create_dir_all(local.join("videos"))?;
+    std::fs::write(local.join("a.bin"), vec![1u8; 2_000])?;
+    std::fs::write(local.join("b.txt"), "This is small content")?;
+    std::fs::write(local.join("urgent").join("c.bin"), vec![2u8; 2_000])?;
+    std::fs::write(local.join("videos").join("d.txt"), "This is a small video")?;
+
+    let report = pcloud
+        .sync_folder("/docs", &local)?
+        .small_files_first(100)
+        .prioritize_folder("urgent", TransferPriority::High)
+        .prioritize_folder("/videos/", TransferPriority::Low)
+        .execute()
+        .await?;
+    let paths: Vec<&str> = report.operations.iter().map(|o| o.path.as_str()).collect();
+    assert_eq!(
+        vec!["b.txt", "urgent/c.bin", "a.bin", "videos/d.txt"],
+        paths
+    );
+
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
@@ -0,0 +1,212 @@
+use std::{
+    error::Error,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use futures::future::BoxFuture;
+use pcloud_async_api::{
+    fake_pcloud::FakePCloud,
+    pcloud_model::PCloudResult,
+    thumbnails::{ThumbnailCache, ThumbnailFormat},
+    transport::HttpTransport,
+};
+use reqwest::{Request, Response};
+
+/// Transport counting the thumbnail link requests
+struct CountingTransport {
+    fake: FakePCloud,
+    thumb_calls: Arc<AtomicUsize>,
+}
+
+impl HttpTransport for CountingTransport {
+    fn execute<'a>(
+        &'a self,
+        request: Request,
+    ) -> BoxFuture<'a, Result<Response, Box<dyn Error + Send + Sync>>> {
+        if request.url().path().ends_with("/getthumblink") {
+            self.thumb_calls.fetch_add(1, Ordering::SeqCst);
+        }
+        self.fake.execute(request)
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_get_thumbnail() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let pcloud = fake.client().await?;
+    fake.put_file("/photos/a.jpg", "This is a photo")?;
+
+    let thumbnail = pcloud
+        .get_thumbnail("/photos/a.jpg", 120, 120)?
+        .crop(true)
+        .format(ThumbnailFormat::Png)
+        .get()
+        .await?;
+    assert!(thumbnail.starts_with(b"thumb 120x120 "));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_thumbnail_cache() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let id = fake.put_file("/photos/a.jpg", "This is a photo")?;
+    let directory = std::env::temp_dir().join(format!("pcloud-thumbs-{}", std::process::id()));
+    let cache = ThumbnailCache::new(&directory);
+    let thumb_calls = Arc::new(AtomicUsize::new(0));
+    let client = || {
+        fake.client_builder()
+            .with_transport(CountingTransport {
+                fake: fake.clone(),
+                thumb_calls: thumb_calls.clone(),
+            })
+            .thumbnail_cache(cache.clone())
+            .build()
+    };
+
+    let first = client().await?.get_thumbnail(id, 64, 64)?.get().await?;
+    // A later session finds the thumbnail in the cache
+    let second = client().await?.get_thumbnail(id, 64, 64)?.get().await?;
+    assert_eq!(first, second);
+    assert_eq!(1, thumb_calls.load(Ordering::SeqCst));
+
+    // Other sizes and changed content get new thumbnails
+    let pcloud = client().await?;
+    pcloud.get_thumbnail(id, 128, 128)?.get().await?;
+    assert_eq!(2, thumb_calls.load(Ordering::SeqCst));
+    fake.put_file("/photos/a.jpg", "This is another photo")?;
+    let metadata = pcloud.get_file_metadata(id).await?.metadata.unwrap();
+    let changed = pcloud
+        .get_thumbnail(id, 64, 64)?
+        .metadata(&metadata)
+        .get()
+        .await?;
+    assert_ne!(first, changed);
+    assert_eq!(3, thumb_calls.load(Ordering::SeqCst));
+
+    cache.clear().await?;
+    pcloud.get_thumbnail(id, 64, 64)?.get().await?;
+    assert_eq!(4, thumb_calls.load(Ordering::SeqCst));
+    std::fs::remove_dir_all(&directory)?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_thumbnail_cache_evicts_oldest() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let a = fake.put_file("/photos/a.jpg", "This is a photo")?;
+    let b = fake.put_file("/photos/b.jpg", "This is another photo")?;
+    let directory =
+        std::env::temp_dir().join(format!("pcloud-thumbs-evict-{}", std::process::id()));
+    // Room for a single thumbnail only
+    let pcloud = fake
+        .client_builder()
+        .thumbnail_cache(ThumbnailCache::new(&directory).max_size(60))
+        .build()
+        .await?;
+
+    pcloud.get_thumbnail(a, 64, 64)?.get().await?;
+    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    pcloud.get_thumbnail(b, 64, 64)?.get().await?;
+    let names: Vec<String> = std::fs::read_dir(&directory)?
+        .map(|e| e.map(|e| e.file_name().to_string_lossy().to_string()))
+        .collect::<Result<_, _>>()?;
+    assert_eq!(1, names.len());
+    assert!(names[0].starts_with(&format!("{}-", b)));
+    std::fs::remove_dir_all(&directory)?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_download_thumbnail() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let pcloud = fake.client().await?;
+    let id = fake.put_file("/photos/a.jpg", "This is a photo")?;
+
+    let direct = pcloud.get_thumbnail(id, 64, 64)?.download().await?;
+    let linked = pcloud.get_thumbnail(id, 64, 64)?.get().await?;
+    assert_eq!(linked, direct);
+
+    let missing = pcloud
+        .get_thumbnail("/photos/b.jpg", 64, 64)?
+        .download()
+        .await;
+    assert!(missing.is_err());
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_thumbnail_links_of_tree() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let pcloud = fake.client().await?;
+    let a = fake.put_file("/photos/a.jpg", "This is a photo")?;
+    let b = fake.put_file("/photos/2023/b.jpg", "This is another photo")?;
+    let c = fake.put_file("/photos/2023/c.jpg", "This is an excluded photo")?;
+    let d = fake.put_file("/other/d.jpg", "This is a single photo")?;
+
+    let tree = pcloud
+        .create_tree()
+        .with_folder("/photos")
+        .await?
+        .with_file(d)
+        .await?
+        .without_file(c)
+        .await?;
+    let links = pcloud
+        .get_thumbnail_links_for_tree(&tree, 32, 32)
+        .await?
+        .crop(true)
+        .get()
+        .await?;
+    let mut ids: Vec<u64> = links.iter().map(|l| l.fileid).collect();
+    ids.sort();
+    assert_eq!(vec![a, b, d], ids);
+
+    for link in links {
+        assert_eq!(Some("32x32".to_string()), link.size);
+        let thumbnail = pcloud
+            .download_link(&link.into_download_link())
+            .await?
+            .bytes()
+            .await?;
+        assert!(thumbnail.starts_with(b"thumb 32x32 "));
+    }
+
+    // Failures are reported per file
+    let links = pcloud
+        .get_thumbnail_links(&[a, 999_999], 32, 32)
+        .get()
+        .await?;
+    assert_eq!(PCloudResult::Ok, links[0].result);
+    assert_eq!(PCloudResult::FileNotFound, links[1].result);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_save_thumbnail() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let pcloud = fake.client().await?;
+    fake.put_file("/photos/a.jpg", "This is a photo")?;
+    fake.put_file("/previews/existing.jpg", "Keep me")?;
+
+    let thumbnail = pcloud.get_thumbnail("/photos/a.jpg", 120, 80)?;
+    let saved = thumbnail.save("/previews", "a-small.jpg").await?;
+    assert_eq!(Some(120), saved.width);
+    assert_eq!(Some(80), saved.height);
+    assert_eq!(
+        Some("a-small.jpg".to_string()),
+        saved.metadata.map(|m| m.name)
+    );
+    let content = fake.read_file("/previews/a-small.jpg").unwrap();
+    assert_eq!(thumbnail.download().await?.to_vec(), content);
+
+    let existing = thumbnail.save_new("/previews", "existing.jpg").await;
+    assert!(existing.is_err());
+    assert_eq!(
+        Some(b"Keep me".to_vec()),
+        fake.read_file("/previews/existing.jpg")
+    );
+    Ok(())
+}
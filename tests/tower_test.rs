@@ -0,0 +1,51 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use pcloud_async_api::{
+    pcloud_client::PCloudClient, service::PCloudRequest, transport::MockTransport,
+};
+use tower::{limit::ConcurrencyLimitLayer, timeout::TimeoutLayer, util::MapRequestLayer};
+
+const STAT: &str = r#"{
+    "result": 0,
+    "metadata": {
+        "name": "test.txt", "created": "Wed, 25 Jan 2023 12:09:14 +0000", "thumb": false,
+        "modified": "Wed, 25 Jan 2023 12:09:14 +0000", "isfolder": false, "fileid": 4711,
+        "isshared": false, "icon": "document", "id": "f4711", "ismine": true,
+        "parentfolderid": 42, "size": 25, "contenttype": "text/plain", "hash": 123456
+    }
+}"#;
+
+#[tokio::test]
+async fn test_tower_layers() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let transport = MockTransport::new();
+    transport.respond_with("stat", STAT);
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+
+    let pcloud = PCloudClient::builder("https://api.pcloud.com")
+        .with_oauth("test-token")
+        .select_best_api_server(false)
+        .with_transport(transport.clone())
+        .with_layer(MapRequestLayer::new(move |request: PCloudRequest| {
+            assert_eq!("stat", request.method());
+            counter.fetch_add(1, Ordering::SeqCst);
+            request
+        }))
+        .with_layer(ConcurrencyLimitLayer::new(2))
+        .with_layer(TimeoutLayer::new(Duration::from_secs(5)))
+        .build()
+        .await?;
+
+    let stat = pcloud.get_file_metadata(4711).await?;
+    assert_eq!(Some(4711), stat.metadata.unwrap().fileid);
+    assert_eq!(1, calls.load(Ordering::SeqCst));
+    assert_eq!(1, transport.requests_for("stat").len());
+
+    Ok(())
+}
@@ -0,0 +1,153 @@
+use std::time::{Duration, Instant};
+
+use chrono::NaiveTime;
+use pcloud_async_api::{
+    fake_pcloud::FakePCloud,
+    transfer::{BandwidthSchedule, TransferPriority, VerificationStatus},
+};
+
+fn time(hour: u32, minute: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+}
+
+#[test]
+fn test_bandwidth_schedule() {
+    let schedule = BandwidthSchedule::new(Some(5_000_000))
+        .window(time(9, 0), time(18, 0), Some(1_000_000))
+        .window(time(22, 0), time(6, 0), None);
+
+    assert_eq!(Some(1_000_000), schedule.limit_at(time(9, 0)));
+    assert_eq!(Some(1_000_000), schedule.limit_at(time(17, 59)));
+    assert_eq!(Some(5_000_000), schedule.limit_at(time(18, 0)));
+    assert_eq!(None, schedule.limit_at(time(23, 30)));
+    assert_eq!(None, schedule.limit_at(time(2, 0)));
+    assert_eq!(Some(5_000_000), schedule.limit_at(time(6, 0)));
+
+    // Equal start and end cover the whole day
+    let schedule = BandwidthSchedule::new(None).window(time(0, 0), time(0, 0), Some(42));
+    assert_eq!(Some(42), schedule.limit_at(time(13, 37)));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_transfers_share_bandwidth() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.create_folder("/test-folder")?;
+    fake.put_file("/test-folder/remote.bin", vec![7u8; 20_000])?;
+    let pcloud = fake.client().await?;
+
+    let local = std::env::temp_dir().join(format!("pcloud-transfer-{}", std::process::id()));
+    std::fs::create_dir_all(&local)?;
+    std::fs::write(local.join("local.bin"), vec![3u8; 20_000])?;
+
+    // 40 KB in total at 50 KB/s, shared by both transfers
+    let schedule = BandwidthSchedule::new(None).window(time(0, 0), time(0, 0), Some(50_000));
+    let start = Instant::now();
+    let report = pcloud
+        .transfer_manager()
+        .bandwidth_schedule(schedule)
+        .upload(local.join("local.bin"), "/test-folder")?
+        .download("/test-folder/remote.bin", local.join("remote.bin"))?
+        .download("/test-folder/missing.bin", local.join("missing.bin"))?
+        .run()
+        .await?;
+    assert!(start.elapsed() >= Duration::from_millis(600));
+
+    assert!(!report.is_success());
+    assert_eq!(1, report.failures().count());
+    assert_eq!(40_000, report.bytes_transferred());
+    assert_eq!(
+        Some(vec![3u8; 20_000]),
+        fake.read_file("/test-folder/local.bin")
+    );
+    assert_eq!(vec![7u8; 20_000], std::fs::read(local.join("remote.bin"))?);
+
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_verified_transfers() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.create_folder("/test-folder")?;
+    fake.put_file("/test-folder/remote.txt", "This is nice test content")?;
+    let pcloud = fake.client().await?;
+
+    let local = std::env::temp_dir().join(format!("pcloud-verify-{}", std::process::id()));
+    std::fs::create_dir_all(&local)?;
+    std::fs::write(local.join("local.txt"), "This is other content")?;
+
+    let report = pcloud
+        .transfer_manager()
+        .verify(3)
+        .upload(local.join("local.txt"), "/test-folder")?
+        .download("/test-folder/remote.txt", local.join("remote.txt"))?
+        .run()
+        .await?;
+    assert!(report.is_success());
+    for outcome in &report.outcomes {
+        assert_eq!(VerificationStatus::Verified, outcome.verification);
+        assert_eq!(1, outcome.attempts);
+    }
+
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_transfer_priorities() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.create_folder("/test-folder")?;
+    fake.put_file("/test-folder/bulk.bin", vec![7u8; 30_000])?;
+    let pcloud = fake.client().await?;
+
+    let local = std::env::temp_dir().join(format!("pcloud-priorities-{}", std::process::id()));
+    std::fs::create_dir_all(local.join("urgent"))?;
+    std::fs::write(local.join("small.txt"), "This is nice test content")?;
+    std::fs::write(local.join("large.bin"), vec![3u8; 5_000])?;
+    std::fs::write(
+        local.join("urgent").join("late.txt"),
+        "This is urgent content",
+    )?;
+
+    // A single slot, the bulk download is interrupted by the urgent upload queued while running
+    let schedule = BandwidthSchedule::new(None).window(time(0, 0), time(0, 0), Some(50_000));
+    let manager = pcloud
+        .transfer_manager()
+        .concurrency(1)
+        .bandwidth_schedule(schedule)
+        .small_files_first(1_000)
+        .prioritize_path(local.join("urgent"), TransferPriority::High)
+        .download_with_priority(
+            "/test-folder/bulk.bin",
+            local.join("bulk.bin"),
+            TransferPriority::Low,
+        )?
+        .upload(local.join("small.txt"), "/test-folder")?
+        .upload(local.join("large.bin"), "/test-folder")?;
+    let queue = manager.queue();
+    let run = tokio::spawn(manager.run());
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    queue.upload(local.join("urgent").join("late.txt"), "/test-folder", None)?;
+    drop(queue);
+    let report = run.await??;
+
+    assert!(report.is_success());
+    let priorities: Vec<TransferPriority> = report.outcomes.iter().map(|o| o.priority).collect();
+    assert_eq!(
+        vec![
+            TransferPriority::Low,
+            TransferPriority::High,
+            TransferPriority::Normal,
+            TransferPriority::High
+        ],
+        priorities
+    );
+    assert_eq!(1, report.outcomes[0].preemptions);
+    assert_eq!(0, report.outcomes[3].preemptions);
+    assert_eq!(vec![7u8; 30_000], std::fs::read(local.join("bulk.bin"))?);
+    assert!(fake.exists("/test-folder/late.txt"));
+
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
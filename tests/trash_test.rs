@@ -0,0 +1,47 @@
+use pcloud_async_api::{fake_pcloud::FakePCloud, trash::TrashUsage};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_trash_usage() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let docs = fake.create_folder("/docs")?;
+    fake.put_file("/docs/a.txt", "1234567890")?;
+    fake.put_file("/photos/2023/b.jpg", "12345")?;
+    fake.put_file("/photos/2023/c.jpg", "123")?;
+    fake.put_file("/photos/keep.jpg", "kept")?;
+    let pcloud = fake.client().await?;
+
+    assert_eq!(0, pcloud.trash_usage().await?.size);
+
+    pcloud.delete_file("/docs/a.txt").await?;
+    pcloud
+        .delete_folder("/photos/2023")?
+        .delete_recursive()
+        .await?;
+
+    let usage = pcloud.trash_usage().await?;
+    assert_eq!(18, usage.size);
+    assert_eq!(3, usage.files);
+    assert_eq!(2, usage.items.len());
+    assert_eq!("a.txt", usage.items[0].name);
+    assert_eq!(10, usage.items[0].size);
+    assert_eq!(Some(docs), usage.items[0].parentfolderid);
+    assert_eq!("2023", usage.items[1].name);
+    assert_eq!(8, usage.items[1].size);
+    assert_eq!(2, usage.items[1].files);
+    assert_eq!(2, usage.folders.len());
+    assert_eq!(Some(docs), usage.folders[0].folderid);
+
+    let used = pcloud.get_user_info().await?.usedquota.unwrap();
+    assert_eq!(22, used);
+    assert!((usage.share_of(used) - 18.0 / 22.0).abs() < 1e-9);
+
+    // Clearing an item reclaims exactly its size
+    pcloud.clear_trash_item(&usage.items[1]).await?;
+    let usage = pcloud.trash_usage().await?;
+    assert_eq!(10, usage.size);
+    assert_eq!(1, usage.items.len());
+
+    pcloud.clear_trash().await?;
+    assert_eq!(TrashUsage::default(), pcloud.trash_usage().await?);
+    Ok(())
+}
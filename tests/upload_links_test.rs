@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use pcloud_async_api::fake_pcloud::FakePCloud;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_list_upload_links() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let pcloud = fake.client().await?;
+    let id = fake.put_upload_link("/inbox", "Send me your photos")?;
+    fake.put_file("/inbox/a.jpg", "This is a photo")?;
+
+    let links = pcloud.list_upload_links().await?;
+    assert_eq!(1, links.uploadlinks.len());
+    let link = &links.uploadlinks[0];
+    assert_eq!(id, link.id);
+    assert_eq!("Send me your photos", link.comment);
+    assert_eq!(1, link.files);
+    assert_eq!(15, link.space);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_watch_upload_link() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    let pcloud = fake.client().await?;
+    let id = fake.put_upload_link("/inbox", "Send me files")?;
+    fake.put_file("/inbox/old.txt", "This was here before")?;
+
+    let mut inbox = pcloud
+        .watch_upload_link(id)
+        .interval(Duration::from_millis(20))
+        .stream();
+    // Give the stream time to take its baseline
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    fake.put_file("/inbox/new.txt", "This is new")?;
+    fake.put_file("/elsewhere/other.txt", "This is ignored")?;
+
+    let file = tokio::time::timeout(Duration::from_secs(5), inbox.recv())
+        .await?
+        .unwrap();
+    assert_eq!(id, file.upload_link_id);
+    assert_eq!("new.txt", file.path);
+    assert_eq!(Some(11), file.metadata.size);
+    assert!(
+        tokio::time::timeout(Duration::from_millis(200), inbox.recv())
+            .await
+            .is_err()
+    );
+
+    // Existing files are only emitted on request
+    let mut inbox = pcloud
+        .watch_upload_link(id)
+        .interval(Duration::from_millis(20))
+        .include_existing(true)
+        .stream();
+    let mut paths = Vec::new();
+    for _ in 0..2 {
+        let file = tokio::time::timeout(Duration::from_secs(5), inbox.recv())
+            .await?
+            .unwrap();
+        paths.push(file.path);
+    }
+    paths.sort();
+    assert_eq!(vec!["new.txt", "old.txt"], paths);
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use pcloud_async_api::{
+    error::PCloudError,
+    pcloud_client::PCloudClient,
+    transport::{HttpTransport, MockTransport},
+    vcr::VcrTransport,
+};
+
+const LIST_FOLDER: &str = r#"{
+    "result": 0,
+    "metadata": {
+        "path": "/test-folder", "name": "test-folder", "created": "Wed, 25 Jan 2023 12:09:14 +0000",
+        "ismine": true, "thumb": false, "modified": "Wed, 25 Jan 2023 12:09:14 +0000", "id": "d42",
+        "isshared": false, "icon": "folder", "isfolder": true, "parentfolderid": 0, "folderid": 42,
+        "contents": []
+    }
+}"#;
+
+async fn get_client<T: HttpTransport + 'static>(transport: T) -> Result<PCloudClient, PCloudError> {
+    PCloudClient::builder("https://api.pcloud.com")
+        .with_username_and_password("test@example.com", "secret-password")
+        .select_best_api_server(false)
+        .with_transport(transport)
+        .build()
+        .await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_record_and_replay() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let cassette = std::env::temp_dir().join(format!("vcr-test-{}.json", uuid::Uuid::new_v4()));
+
+    let mock = MockTransport::new();
+    mock.respond_with(
+        "userinfo",
+        r#"{ "result": 0, "auth": "secret-session-token", "userid": 1, "registered": "Wed, 25 Jan 2023 12:09:14 +0000" }"#,
+    );
+    mock.respond_with("listfolder", LIST_FOLDER);
+    mock.respond_with("logout", r#"{ "result": 0, "auth_deleted": true }"#);
+
+    // Record
+    {
+        let pcloud = get_client(VcrTransport::record_with(&cassette, mock.clone())).await?;
+        let folder = pcloud.list_folder("/test-folder")?.get().await?;
+        assert_eq!(Some(42), folder.metadata.unwrap().folderid);
+    }
+
+    let content = std::fs::read_to_string(&cassette)?;
+    assert!(!content.contains("secret-session-token"));
+    assert!(!content.contains("secret-password"));
+    assert!(!content.contains("test%40example.com"));
+
+    // Replay without the mock
+    let requests_recorded = mock.requests().len();
+    {
+        let pcloud = get_client(VcrTransport::replay(&cassette)?).await?;
+        let folder = pcloud.list_folder("/test-folder")?.get().await?;
+        assert_eq!(Some(42), folder.metadata.unwrap().folderid);
+
+        // Requests not recorded fail
+        assert!(pcloud.list_folder("/other-folder")?.get().await.is_err());
+    }
+    assert_eq!(requests_recorded, mock.requests().len());
+
+    std::fs::remove_file(&cassette)?;
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use pcloud_async_api::{
+    fake_pcloud::FakePCloud,
+    watch::{LocalChange, WatchBatch},
+};
+
+/// Waits for the next batch with changes, fails after a few seconds
+async fn next_batch(watch: &mut pcloud_async_api::watch::DirectoryWatch) -> WatchBatch {
+    tokio::time::timeout(Duration::from_secs(10), watch.next_batch())
+        .await
+        .expect("No batch received")
+        .expect("Watch stopped")
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_watch_directory() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let fake = FakePCloud::start().await?;
+    fake.create_folder("/backup")?;
+    let pcloud = fake.client().await?;
+
+    let local = std::env::temp_dir().join(format!("pcloud-watch-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&local);
+    std::fs::create_dir_all(&local)?;
+
+    let mut watch = pcloud
+        .watch_directory(&local, "/backup")?
+        .debounce(Duration::from_millis(300))
+        .start()
+        .await?;
+
+    // Several events of the same file are applied once
+    std::fs::create_dir_all(local.join("sub"))?;
+    std::fs::write(local.join("sub").join("a.txt"), "This is")?;
+    std::fs::write(local.join("sub").join("a.txt"), "This is nice test content")?;
+    std::fs::write(local.join("b.txt"), "This is other content")?;
+
+    let batch = next_batch(&mut watch).await;
+    assert!(batch.is_success());
+    assert!(batch
+        .operations
+        .iter()
+        .any(|o| o.change == LocalChange::Upload("sub/a.txt".to_string())));
+    assert_eq!(
+        Some(b"This is nice test content".to_vec()),
+        fake.read_file("/backup/sub/a.txt")
+    );
+    assert_eq!(
+        Some(b"This is other content".to_vec()),
+        fake.read_file("/backup/b.txt")
+    );
+
+    // Deleted files and folders
+    std::fs::remove_file(local.join("b.txt"))?;
+    std::fs::remove_dir_all(local.join("sub"))?;
+
+    let batch = next_batch(&mut watch).await;
+    assert!(batch.is_success());
+    assert!(batch
+        .operations
+        .iter()
+        .all(|o| matches!(o.change, LocalChange::Delete(_))));
+    assert!(!fake.exists("/backup/b.txt"));
+    assert!(!fake.exists("/backup/sub"));
+    assert!(fake.exists("/backup"));
+
+    watch.stop();
+    std::fs::remove_dir_all(&local)?;
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use pcloud_async_api::{
+    pcloud_model::PCloudResult,
+    testing::{MockPCloudServer, TEST_FILE_ID, TEST_FOLDER_ID, UPLOADED_FILE_ID},
+};
+
+#[tokio::test]
+async fn test_preloaded_wiremock_server() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let server = MockPCloudServer::start_preloaded().await;
+    let pcloud = server.client().await?;
+
+    let folder = pcloud.list_folder("/test-folder")?.get().await?;
+    let metadata = folder.metadata.unwrap();
+    assert_eq!(Some(TEST_FOLDER_ID), metadata.folderid);
+    assert_eq!(1, metadata.contents.len());
+    assert_eq!("test.txt", metadata.contents[0].name);
+
+    let file = pcloud.get_file_metadata(TEST_FILE_ID).await?;
+    assert_eq!(Some(25), file.metadata.unwrap().size);
+
+    let upload = pcloud
+        .upload_file_into_folder(TEST_FOLDER_ID)?
+        .with_file("uploaded.txt", "This is nice test content")
+        .upload()
+        .await?;
+    assert_eq!(vec![UPLOADED_FILE_ID], upload.fileids());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_wiremock_error_response() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let server = MockPCloudServer::start().await;
+    server.mock_error("stat", PCloudResult::FileNotFound).await;
+    let pcloud = server.client().await?;
+
+    let err = pcloud.get_file_metadata(TEST_FILE_ID).await.unwrap_err();
+    assert_eq!(
+        Some(&PCloudResult::FileNotFound),
+        err.downcast_ref::<PCloudResult>()
+    );
+
+    Ok(())
+}